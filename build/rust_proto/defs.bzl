@@ -67,7 +67,9 @@ def rust_proto3_library(
     Args:
         name: Name of the rust_prost_library target
         srcs: List of .proto source files (edition 2024)
-        deps: List of proto_library dependencies (use transformed versions if they use editions)
+        deps: List of proto_library dependencies. For protos that use editions, use the
+            `<prefix>_proto3` target the macro makes for them, e.g.
+            "//gamedef:gamedef_proto3".
         visibility: Visibility for the rust_prost_library target
         **kwargs: Additional arguments passed to rust_prost_library
 
@@ -104,11 +106,17 @@ def rust_proto3_library(
     # Use the target name directly (without underscore prefix) so the generated
     # Rust crate gets a clean name matching the rust_prost_library target
     proto3_name = "%s_proto3" % name.replace("_rust_proto", "").replace("_proto", "")
+    # Strip the transform's output directory so that the files keep their
+    # import paths, and protos that import them resolve as they do for other
+    # languages.
     proto_library(
         name = proto3_name,
         srcs = [":%s" % transform_name],
         deps = deps,
+        strip_import_prefix = transform_name,
+        import_prefix = native.package_name(),
         tags = ["manual"],
+        visibility = visibility,
     )
 
     # Generate Rust code from transformed proto_library
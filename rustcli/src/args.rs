@@ -0,0 +1,167 @@
+//! Command-line arguments: positional arguments, `--name value` (or
+//! `--name=value`) options, and `--name` switches. A bare `--` ends the
+//! options, so later arguments are positional even if they start with `-`.
+//! Options and switches not given take their defaults from the config file
+//! (see [`crate::config`]).
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::{config, CliError};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Args {
+    positional: Vec<String>,
+    values: Vec<(&'static str, String)>,
+    switches: Vec<&'static str>,
+    /// Options set by the config file rather than the command line.
+    defaults: Vec<(&'static str, &'static str)>,
+}
+
+impl Args {
+    /// Parses `argv`, in which the names in `options` take a value and those
+    /// in `switches` don't. Any other name is an error.
+    pub fn parse(
+        argv: &[String],
+        options: &[&'static str],
+        switches: &[&'static str],
+    ) -> Result<Self, CliError> {
+        let mut args = Args::default();
+        let mut rest = argv.iter();
+        while let Some(arg) = rest.next() {
+            if arg == "--" {
+                args.positional.extend(rest.cloned());
+                break;
+            }
+            let Some(flag) = arg.strip_prefix("--") else {
+                args.positional.push(arg.clone());
+                continue;
+            };
+            let (name, inline) = match flag.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (flag, None),
+            };
+            if let Some(&name) = options.iter().find(|&&o| o == name) {
+                let value = match inline {
+                    Some(value) => value.to_string(),
+                    None => rest
+                        .next()
+                        .cloned()
+                        .ok_or_else(|| CliError::Usage(format!("--{} needs a value", name)))?,
+                };
+                args.values.push((name, value));
+            } else if let Some(&name) = switches.iter().find(|&&s| s == name) {
+                if inline.is_some() {
+                    return Err(CliError::Usage(format!("--{} doesn't take a value", name)));
+                }
+                args.switches.push(name);
+            } else {
+                return Err(CliError::Usage(format!("unknown option --{}", name)));
+            }
+        }
+        for &name in options {
+            if let (None, Some(value)) = (args.value(name), config::default(name)) {
+                args.defaults.push((name, value));
+            }
+        }
+        for &name in switches {
+            if config::default(name) == Some("true") && !args.switch(name) {
+                args.switches.push(name);
+            }
+        }
+        Ok(args)
+    }
+
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+
+    /// The last value given for `name`, or else its default, if any.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .rev()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v.as_str())
+            .or_else(|| self.configured(name))
+    }
+
+    fn configured(&self, name: &str) -> Option<&'static str> {
+        self.defaults
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| *v)
+    }
+
+    /// Every value given for `name`, in order, for options that can be
+    /// given more than once. Defaults aren't included.
+    pub fn values(&self, name: &str) -> impl Iterator<Item = &str> + '_ {
+        let name = name.to_string();
+        self.values
+            .iter()
+            .filter(move |(n, _)| *n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The last value given for `name`, parsed.
+    pub fn parsed<T>(&self, name: &str) -> Result<Option<T>, CliError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.value(name)
+            .map(|v| {
+                v.parse().map_err(|e| {
+                    let set = match self.values.iter().any(|(n, _)| *n == name) {
+                        true => "",
+                        false => " (from the config file)",
+                    };
+                    CliError::Usage(format!("--{} {:?}{}: {}", name, v, set, e))
+                })
+            })
+            .transpose()
+    }
+
+    pub fn switch(&self, name: &str) -> bool {
+        self.switches.contains(&name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let args = Args::parse(
+            &argv("a.yaml --players 6 --seed=42 --json b.json --players 9 -- --c"),
+            &["players", "seed"],
+            &["json"],
+        )
+        .unwrap();
+        assert_eq!(args.positional(), argv("a.yaml b.json --c"));
+        assert_eq!(args.value("players"), Some("9"));
+        assert_eq!(args.values("players").collect::<Vec<_>>(), ["6", "9"]);
+        assert_eq!(args.value("seed"), Some("42"));
+        assert_eq!(args.parsed::<u64>("seed").unwrap(), Some(42));
+        assert_eq!(args.parsed::<u64>("hands").unwrap(), None);
+        assert!(args.switch("json") && !args.switch("color"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let usage = |s: &str| match Args::parse(&argv(s), &["seed"], &["json"]) {
+            Err(CliError::Usage(message)) => message,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(usage("--seed"), "--seed needs a value");
+        assert_eq!(usage("--json=yes"), "--json doesn't take a value");
+        assert_eq!(usage("--hands 3"), "unknown option --hands");
+        let args = Args::parse(&argv("--seed x"), &["seed"], &[]).unwrap();
+        assert!(args.parsed::<u64>("seed").is_err());
+    }
+}
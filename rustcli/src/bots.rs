@@ -0,0 +1,74 @@
+//! Bots for the seats, chosen by name on the command line.
+
+use rustlib::cards::SplitMix64;
+use rustlib::strategy::{Passive, PotOdds, Random, Strategy};
+
+use crate::CliError;
+
+/// The names `--bots` takes.
+pub const NAMES: &str = "passive, random or pot-odds";
+
+/// The bot named `name`, drawing any random numbers from `seed`.
+pub fn by_name(name: &str, seed: u64) -> Option<Box<dyn Strategy>> {
+    match name {
+        "passive" => Some(Box::new(Passive)),
+        "random" => Some(Box::new(Random::new(SplitMix64::new(seed)))),
+        "pot-odds" => Some(Box::new(PotOdds {
+            seed,
+            ..Default::default()
+        })),
+        _ => None,
+    }
+}
+
+/// A bot per seat, with the names they were chosen by.
+pub struct Bots {
+    pub names: Vec<String>,
+    pub strategies: Vec<Box<dyn Strategy>>,
+}
+
+/// A bot for each of `seats` seats from `--bots`, which names one bot for
+/// every seat or a comma-separated bot per seat. Each bot is seeded from
+/// `seed` and its seat.
+pub fn from_arg(arg: &str, seats: usize, seed: u64) -> Result<Bots, CliError> {
+    let names: Vec<&str> = arg.split(',').map(str::trim).collect();
+    let names: Vec<&str> = match names.len() {
+        1 => vec![names[0]; seats],
+        n if n == seats => names,
+        n => {
+            return Err(CliError::Usage(format!(
+                "--bots names {} bots for {} seats",
+                n, seats
+            )))
+        }
+    };
+    let strategies = names
+        .iter()
+        .enumerate()
+        .map(|(seat, &name)| {
+            by_name(name, seed.wrapping_add(seat as u64)).ok_or_else(|| {
+                CliError::Usage(format!("unknown bot {:?}; expected {}", name, NAMES))
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Bots {
+        names: names.into_iter().map(String::from).collect(),
+        strategies,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_arg() {
+        let bots = from_arg("random", 3, 0).unwrap();
+        assert_eq!(bots.names, vec!["random".to_string(); 3]);
+        assert_eq!(bots.strategies.len(), 3);
+        let bots = from_arg("passive, pot-odds", 2, 0).unwrap();
+        assert_eq!(bots.names, ["passive", "pot-odds"]);
+        assert!(from_arg("passive,random", 3, 0).is_err());
+        assert!(from_arg("shark", 2, 0).is_err());
+    }
+}
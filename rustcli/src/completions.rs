@@ -0,0 +1,331 @@
+//! `snapfold completions`: shell scripts that complete snapfold's commands
+//! and options.
+//!
+//! The commands and their options are read from the usage texts, so the
+//! scripts can't fall behind what `snapfold help` says.
+
+use std::process::ExitCode;
+
+use rustlib::presets;
+
+use crate::args::Args;
+use crate::CliError;
+
+pub const USAGE: &str = "\
+usage: snapfold completions SHELL
+
+Prints a script for SHELL (bash, zsh or fish) that completes snapfold's
+commands, their options, and the values of options that take one of a few
+words. To load it for every new shell:
+
+  bash   snapfold completions bash > ~/.local/share/bash-completion/completions/snapfold
+  zsh    snapfold completions zsh > ~/.zfunc/_snapfold   (with ~/.zfunc in fpath)
+  fish   snapfold completions fish > ~/.config/fish/completions/snapfold.fish";
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(argv, &[], &[])?;
+    let script = match args.positional() {
+        [shell] if shell == "bash" => bash(),
+        [shell] if shell == "zsh" => zsh(),
+        [shell] if shell == "fish" => fish(),
+        [shell] => {
+            return Err(CliError::Usage(format!(
+                "no completions for {:?}; expected bash, zsh or fish",
+                shell
+            )))
+        }
+        _ => return Err(CliError::Usage("completions needs a shell".to_string())),
+    };
+    print!("{}", script);
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Each command and what it does, from the list in `snapfold help`.
+pub fn commands() -> Vec<(&'static str, &'static str)> {
+    let list = crate::USAGE
+        .split_once("Commands:\n")
+        .map_or("", |(_, rest)| rest.split("\n\n").next().unwrap_or(""));
+    list.lines()
+        .filter_map(|line| {
+            let line = line.trim_start();
+            let name = line.split_whitespace().next()?;
+            let about = line.split_once("  ").map_or("", |(_, about)| about.trim());
+            Some((name, about))
+        })
+        .collect()
+}
+
+/// An option in a usage text, and the name its value is shown with if it
+/// takes one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Flag {
+    pub name: &'static str,
+    pub value: Option<&'static str>,
+    pub about: String,
+}
+
+/// The options listed in `usage`, the text `snapfold help COMMAND` prints.
+pub fn flags(usage: &'static str) -> Vec<Flag> {
+    let Some((_, options)) = usage.split_once("Options:\n") else {
+        return Vec::new();
+    };
+    let mut flags: Vec<Flag> = Vec::new();
+    for line in options.lines() {
+        if let Some(rest) = line.strip_prefix("  --") {
+            let mut words = rest.splitn(2, ' ');
+            let name = words.next().unwrap_or(rest);
+            let rest = words.next().unwrap_or("");
+            let value = rest
+                .split(' ')
+                .next()
+                .filter(|w| !w.is_empty() && w.chars().all(|c| c.is_ascii_uppercase()));
+            let about = match value {
+                Some(v) => &rest[v.len()..],
+                None => rest,
+            };
+            flags.push(Flag {
+                name,
+                value,
+                about: about.trim().to_string(),
+            });
+        } else if let Some(flag) = flags.last_mut().filter(|_| line.starts_with("    ")) {
+            if !flag.about.is_empty() {
+                flag.about.push(' ');
+            }
+            flag.about.push_str(line.trim());
+        }
+    }
+    flags
+}
+
+/// What can follow an option.
+enum Values {
+    Words(Vec<&'static str>),
+    Files,
+    Dirs,
+    Anything,
+}
+
+fn values(flag: &Flag) -> Values {
+    match (flag.name, flag.value) {
+        ("output", _) => Values::Words(vec!["text", "json"]),
+        ("format" | "to", _) => Values::Words(vec!["json", "yaml", "dsl", "binpb", "game"]),
+        ("template", _) => Values::Words(presets::NAMES.to_vec()),
+        ("strategy", _) => Values::Words(vec!["passive", "random", "pot-odds"]),
+        (_, Some("FILE" | "PATH")) => Values::Files,
+        (_, Some("DIR")) => Values::Dirs,
+        _ => Values::Anything,
+    }
+}
+
+/// Every command but `help`, with its options.
+fn each_command() -> Vec<(&'static str, &'static str, Vec<Flag>)> {
+    commands()
+        .into_iter()
+        .filter_map(|(name, about)| Some((name, about, flags(crate::usage(name)?))))
+        .collect()
+}
+
+fn names() -> String {
+    let names: Vec<&str> = commands().into_iter().map(|(name, _)| name).collect();
+    names.join(" ")
+}
+
+fn bash() -> String {
+    let mut out = String::from(
+        "# bash completion for snapfold\n\
+         _snapfold() {\n    \
+             local cur=${COMP_WORDS[COMP_CWORD]} prev=${COMP_WORDS[COMP_CWORD-1]}\n    \
+             if (( COMP_CWORD == 1 )); then\n        ",
+    );
+    out.push_str(&format!(
+        "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n        return\n    fi\n",
+        names()
+    ));
+    out.push_str(&format!(
+        "    if [[ ${{COMP_WORDS[1]}} == help ]]; then\n        \
+             COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n        return\n    fi\n",
+        names()
+    ));
+    out.push_str("    local flags\n    case ${COMP_WORDS[1]} in\n");
+    for (name, _, flags) in each_command() {
+        let mut cases = String::new();
+        for flag in &flags {
+            let reply = match values(flag) {
+                Values::Words(words) => {
+                    format!(
+                        "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+                        words.join(" ")
+                    )
+                }
+                Values::Files => "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string(),
+                Values::Dirs => "COMPREPLY=($(compgen -d -- \"$cur\"))".to_string(),
+                Values::Anything if flag.value.is_some() => "COMPREPLY=()".to_string(),
+                Values::Anything => continue,
+            };
+            cases.push_str(&format!(
+                "                --{}) {}; return ;;\n",
+                flag.name, reply
+            ));
+        }
+        let names: Vec<String> = flags.iter().map(|f| format!("--{}", f.name)).collect();
+        out.push_str(&format!("        {})\n", name));
+        if !cases.is_empty() {
+            out.push_str(&format!(
+                "            case $prev in\n{}            esac\n",
+                cases
+            ));
+        }
+        out.push_str(&format!("            flags=\"{}\" ;;\n", names.join(" ")));
+    }
+    out.push_str(
+        "    esac\n    \
+         if [[ $cur == -* ]]; then\n        \
+             COMPREPLY=($(compgen -W \"$flags --help\" -- \"$cur\"))\n    \
+         else\n        \
+             COMPREPLY=($(compgen -f -- \"$cur\"))\n    \
+         fi\n\
+         }\n\
+         complete -o filenames -F _snapfold snapfold\n",
+    );
+    out
+}
+
+/// `text` quoted for zsh's `_arguments` and `_describe`, inside single
+/// quotes.
+fn zsh_quote(text: &str) -> String {
+    text.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+fn zsh() -> String {
+    let mut out =
+        String::from("#compdef snapfold\n\n_snapfold() {\n    local -a commands\n    commands=(\n");
+    for (name, about) in commands() {
+        out.push_str(&format!("        '{}:{}'\n", name, zsh_quote(about)));
+    }
+    out.push_str(
+        "    )\n    \
+         if (( CURRENT == 2 )); then\n        \
+             _describe 'command' commands\n        \
+             return\n    \
+         fi\n    \
+         case $words[2] in\n        \
+             help) _describe 'command' commands ;;\n",
+    );
+    for (name, _, flags) in each_command() {
+        out.push_str(&format!("        {})\n            _arguments \\\n", name));
+        for flag in &flags {
+            let about = zsh_quote(&flag.about);
+            let spec = match (flag.value, values(flag)) {
+                (None, _) => format!("--{}[{}]", flag.name, about),
+                (Some(v), Values::Words(words)) => {
+                    format!("--{}[{}]:{}:({})", flag.name, about, v, words.join(" "))
+                }
+                (Some(v), Values::Files) => format!("--{}[{}]:{}:_files", flag.name, about, v),
+                (Some(v), Values::Dirs) => {
+                    format!("--{}[{}]:{}:_files -/", flag.name, about, v)
+                }
+                (Some(v), Values::Anything) => format!("--{}[{}]:{}: ", flag.name, about, v),
+            };
+            out.push_str(&format!("                '{}' \\\n", spec));
+        }
+        out.push_str("                '*:file:_files' ;;\n");
+    }
+    out.push_str("    esac\n}\n\n_snapfold \"$@\"\n");
+    out
+}
+
+/// `text` inside fish's single quotes.
+fn fish_quote(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+fn fish() -> String {
+    let mut out = String::from("# fish completion for snapfold\ncomplete -c snapfold -f\n");
+    for (name, about) in commands() {
+        out.push_str(&format!(
+            "complete -c snapfold -n __fish_use_subcommand -a {} -d '{}'\n",
+            name,
+            fish_quote(about)
+        ));
+    }
+    out.push_str(&format!(
+        "complete -c snapfold -n '__fish_seen_subcommand_from help' -a '{}'\n",
+        names()
+    ));
+    for (name, _, flags) in each_command() {
+        let when = format!("-n '__fish_seen_subcommand_from {}'", name);
+        out.push_str(&format!("complete -c snapfold {} -F\n", when));
+        for flag in &flags {
+            let takes = match (flag.value, values(flag)) {
+                (None, _) => String::new(),
+                (Some(_), Values::Words(words)) => format!(" -x -a '{}'", words.join(" ")),
+                (Some(_), Values::Files) => " -r -F".to_string(),
+                (Some(_), Values::Dirs) => " -x -a '(__fish_complete_directories)'".to_string(),
+                (Some(_), Values::Anything) => " -x".to_string(),
+            };
+            out.push_str(&format!(
+                "complete -c snapfold {} -l {}{} -d '{}'\n",
+                when,
+                flag.name,
+                takes,
+                fish_quote(&flag.about)
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags() {
+        let commands = commands();
+        assert_eq!(
+            commands[0],
+            (
+                "validate",
+                "check game definitions and lint them for mistakes"
+            )
+        );
+        // Every command but help has a usage text to read options from.
+        for (name, _) in &commands {
+            assert_eq!(crate::usage(name).is_some(), *name != "help", "{}", name);
+        }
+
+        let flags = flags(crate::fetch::USAGE);
+        assert_eq!(
+            flags[0],
+            Flag {
+                name: "verify-fingerprint",
+                value: Some("HASH"),
+                about: "fail unless the game's fingerprint, the SHA-256 of its rules, is \
+                        HASH (in hex); nothing is saved if it isn't"
+                    .to_string(),
+            }
+        );
+        assert!(flags
+            .iter()
+            .any(|f| f.name == "no-cache" && f.value.is_none()));
+    }
+
+    #[test]
+    fn test_scripts() {
+        let bash = bash();
+        assert!(bash.contains("                --template) COMPREPLY=($(compgen -W \"holdem "));
+        assert!(bash.ends_with("complete -o filenames -F _snapfold snapfold\n"));
+        let zsh = zsh();
+        assert!(zsh.contains(
+            "'--output[text, or json to print the changes as JSON]:FORMAT:(text json)' \\\n"
+        ));
+        let fish = fish();
+        assert!(fish.contains(
+            "complete -c snapfold -n '__fish_seen_subcommand_from diff' -l exit-code -d 'exit with status 1"
+        ));
+    }
+}
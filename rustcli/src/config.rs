@@ -0,0 +1,310 @@
+//! `config.toml`: defaults for command-line options.
+//!
+//! Each setting is named for an option and gives the value to use when the
+//! option isn't on the command line. Settings at the top of the file apply
+//! to every command that takes the option, and those under a `[COMMAND]`
+//! table to that command only, ahead of the top ones:
+//!
+//! ```toml
+//! output = "json"
+//! registry = "https://games.example.com/defs"
+//!
+//! [simulate]
+//! seed = 7
+//! hands = 10000
+//! ```
+//!
+//! A switch is set with `true`. Only the TOML that settings need is read:
+//! tables, comments, and strings, numbers and booleans as values.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::diag::{Diagnostic, Level};
+use crate::{completions, CliError};
+
+/// One `key = value` line, with the table it's in, if any.
+#[derive(Clone, Debug, PartialEq)]
+struct Setting {
+    table: Option<String>,
+    key: String,
+    /// The value as it would be written on the command line.
+    value: String,
+    line: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    settings: Vec<Setting>,
+}
+
+static DEFAULTS: OnceLock<Vec<(String, String)>> = OnceLock::new();
+
+impl Config {
+    /// Where the file is read from: `$SNAPFOLD_CONFIG`, or
+    /// `snapfold/config.toml` in `$XDG_CONFIG_HOME` or `~/.config`.
+    pub fn path() -> Option<PathBuf> {
+        let var = |name| std::env::var_os(name).filter(|v| !v.is_empty());
+        if let Some(path) = var("SNAPFOLD_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        let base = match var("XDG_CONFIG_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(var("HOME")?).join(".config"),
+        };
+        Some(base.join("snapfold").join("config.toml"))
+    }
+
+    /// Reads the file at `path`, which may not exist, and warns about any
+    /// setting that no command would use.
+    pub fn load(path: &Path) -> Result<Self, CliError> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(error) => {
+                return Err(CliError::Io {
+                    path: path.to_path_buf(),
+                    error,
+                })
+            }
+        };
+        let config = Config::parse(&text).map_err(|(line, column, message)| CliError::Load {
+            path: path.to_path_buf(),
+            source: Some(text.clone()),
+            at: Some((line, column)),
+            message,
+        })?;
+        for (setting, problem) in config.unused() {
+            Diagnostic::new(Level::Warning, problem)
+                .in_file(path)
+                .at(setting.line, 1, &text)
+                .emit();
+        }
+        Ok(config)
+    }
+
+    /// Parses the text of a config file. An error gives the 1-based line
+    /// and column of the problem.
+    pub fn parse(text: &str) -> Result<Self, (usize, usize, String)> {
+        let mut config = Config::default();
+        let mut table = None;
+        for (i, raw) in text.lines().enumerate() {
+            let number = i + 1;
+            let line = raw.trim();
+            let column = raw.len() - raw.trim_start().len() + 1;
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(rest) = line.strip_prefix('[') {
+                let Some((name, after)) = rest.split_once(']') else {
+                    return Err((number, column, "a table needs a closing ]".to_string()));
+                };
+                if !comment(after) {
+                    return Err((number, column, "expected a line break after ]".to_string()));
+                }
+                let name = name.trim();
+                if !bare_key(name) {
+                    return Err((number, column + 1, format!("{:?} can't be a table", name)));
+                }
+                table = Some(name.to_string());
+                continue;
+            }
+            let Some(eq) = line.find('=') else {
+                return Err((number, column, "expected key = value".to_string()));
+            };
+            let key = line[..eq].trim();
+            if !bare_key(key) {
+                return Err((number, column, format!("{:?} can't be a key", key)));
+            }
+            let after = &line[eq + 1..];
+            let at = column + eq + 1 + after.len() - after.trim_start().len();
+            let value = value(after.trim()).map_err(|message| (number, at, message))?;
+            if config
+                .settings
+                .iter()
+                .any(|s| s.table == table && s.key == key)
+            {
+                return Err((number, column, format!("{} is set twice", key)));
+            }
+            config.settings.push(Setting {
+                table: table.clone(),
+                key: key.to_string(),
+                value,
+                line: number,
+            });
+        }
+        Ok(config)
+    }
+
+    /// The defaults for `command`: its own table's settings, then the ones
+    /// at the top that it doesn't override.
+    pub fn defaults(&self, command: &str) -> Vec<(String, String)> {
+        let mut out: Vec<(String, String)> = self
+            .settings
+            .iter()
+            .filter(|s| s.table.as_deref() == Some(command))
+            .map(|s| (s.key.clone(), s.value.clone()))
+            .collect();
+        for s in self.settings.iter().filter(|s| s.table.is_none()) {
+            if !out.iter().any(|(key, _)| *key == s.key) {
+                out.push((s.key.clone(), s.value.clone()));
+            }
+        }
+        out
+    }
+
+    /// Settings that would never be used, and why.
+    fn unused(&self) -> Vec<(&Setting, String)> {
+        let commands = completions::commands();
+        let takes = |command: &str, key: &str| {
+            crate::usage(command).is_some_and(|usage| {
+                completions::flags(usage)
+                    .iter()
+                    .any(|flag| flag.name == key)
+            })
+        };
+        self.settings
+            .iter()
+            .filter_map(|s| match &s.table {
+                Some(table) if crate::usage(table).is_none() => {
+                    Some((s, format!("[{}] isn't a snapfold command", table)))
+                }
+                Some(table) => (!takes(table, &s.key))
+                    .then(|| (s, format!("{} doesn't take --{}", table, s.key))),
+                None => (!commands.iter().any(|(c, _)| takes(c, &s.key)))
+                    .then(|| (s, format!("no command takes --{}", s.key))),
+            })
+            .collect()
+    }
+
+    /// Makes `command`'s defaults the ones [`default`] gives.
+    pub fn apply(&self, command: &str) {
+        let _ = DEFAULTS.set(self.defaults(command));
+    }
+}
+
+/// The configured default for option `name` of the command being run.
+pub fn default(name: &str) -> Option<&'static str> {
+    DEFAULTS
+        .get()?
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+fn bare_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Whether `text` is only space and maybe a comment.
+fn comment(text: &str) -> bool {
+    let text = text.trim_start();
+    text.is_empty() || text.starts_with('#')
+}
+
+/// A value as the command line would give it: a string's contents, or a
+/// number or boolean as written.
+fn value(text: &str) -> Result<String, String> {
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut out = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    return match comment(chars.as_str()) {
+                        true => Ok(out),
+                        false => Err("expected a line break after the string".to_string()),
+                    }
+                }
+                '\\' => out.push(match chars.next() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    other => {
+                        return Err(format!(
+                            "unknown escape \\{}",
+                            other.map_or(String::new(), String::from)
+                        ))
+                    }
+                }),
+                c => out.push(c),
+            }
+        }
+        return Err("the string isn't closed".to_string());
+    }
+    if let Some(rest) = text.strip_prefix('\'') {
+        return match rest.split_once('\'') {
+            Some((literal, after)) if comment(after) => Ok(literal.to_string()),
+            Some(_) => Err("expected a line break after the string".to_string()),
+            None => Err("the string isn't closed".to_string()),
+        };
+    }
+    let word = text.split('#').next().unwrap_or("").trim();
+    let number = word.replace('_', "");
+    if word == "true" || word == "false" || number.parse::<f64>().is_ok() {
+        return Ok(number);
+    }
+    Err(format!(
+        "{:?} isn't a string, number or boolean; strings need quotes",
+        word
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse(
+            "# defaults\n\
+             output = \"json\"\n\
+             seed = 1_000 # every command\n\
+             \n\
+             [simulate]\n\
+             seed = 7\n\
+             bots = 'pot-odds'\n\
+             [validate]\n\
+             deny-warnings = true\n",
+        )
+        .unwrap();
+        let pairs = |v: Vec<(String, String)>| {
+            v.into_iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            pairs(config.defaults("simulate")),
+            ["seed=7", "bots=pot-odds", "output=json"]
+        );
+        assert_eq!(pairs(config.defaults("deal")), ["output=json", "seed=1000"]);
+        assert!(config.unused().is_empty());
+
+        let config = Config::parse("players = 4\n[deal]\nhands = 3\n[nope]\nx = 1\n").unwrap();
+        let problems: Vec<String> = config.unused().into_iter().map(|(_, p)| p).collect();
+        assert_eq!(
+            problems,
+            [
+                "deal doesn't take --hands",
+                "[nope] isn't a snapfold command"
+            ]
+        );
+
+        let error = |text: &str| Config::parse(text).unwrap_err();
+        assert_eq!(
+            error("a = 1\n  b = hello\n"),
+            (
+                2,
+                7,
+                "\"hello\" isn't a string, number or boolean; strings need quotes".to_string()
+            )
+        );
+        assert_eq!(error("[x\n").2, "a table needs a closing ]");
+        assert_eq!(error("a = \"x\n").2, "the string isn't closed");
+        assert_eq!(error("a = 1\na = 2\n").0, 2);
+    }
+}
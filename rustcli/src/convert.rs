@@ -0,0 +1,172 @@
+//! `snapfold convert`: rewrites a game definition in another format.
+
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use prost::Message;
+use rustlib::fingerprint::hex;
+use rustlib::gamedef::{self, json::Value};
+use rustlib::GameStructure;
+
+use crate::args::Args;
+use crate::diag::{Diagnostic, Level};
+use crate::load::{self, Format};
+use crate::output::{self, Output};
+use crate::CliError;
+
+pub const USAGE: &str = "\
+usage: snapfold convert FILE [--to FORMAT] [--out PATH] [--output FORMAT]
+                            [--format FORMAT]
+
+Reads a game definition and writes it in another format: json, yaml, dsl,
+binpb (a bare binary proto) or game (a framed binary proto with a
+checksum). Between the two binary formats the proto is copied byte for
+byte, so fields this build doesn't know about are kept; the text formats
+can't hold them, and a warning says when any are dropped.
+
+Options:
+  --to FORMAT      the format to write, if --out doesn't have its
+                   extension
+  --out PATH       write to PATH instead of stdout
+  --output FORMAT  text, or json to print what was converted as JSON,
+                   with the converted game in it unless --out is given
+  --format FORMAT  read FILE as json, yaml, dsl, binpb or game";
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(argv, &["to", "out", "output", "format"], &[])?;
+    let report = Output::from_args(&args)?;
+    let [path] = args.positional() else {
+        return Err(CliError::Usage("convert needs one game file".to_string()));
+    };
+    let path = Path::new(path);
+    let output = args.value("out").map(Path::new);
+    let to = match Format::from_arg(args.value("to"))? {
+        Some(to) => to,
+        None => output.and_then(Format::of).ok_or_else(|| {
+            CliError::Usage(format!(
+                "pass --to with the format to write ({})",
+                Format::NAMES
+            ))
+        })?,
+    };
+    let from = Format::from_arg(args.value("format"))?.or_else(|| Format::of(path));
+    let message = match from {
+        Some(format @ (Format::Proto | Format::File)) => Some(load::read_message(path, format)?),
+        _ => None,
+    };
+    let game = match &message {
+        Some(message) => GameStructure::decode(message.as_slice()).map_err(|e| CliError::Load {
+            path: path.to_path_buf(),
+            source: None,
+            at: None,
+            message: e.to_string(),
+        })?,
+        None => load::load(path, from)?,
+    };
+
+    let (bytes, dropped) = convert(&game, message.as_deref(), to);
+    if dropped {
+        Diagnostic::new(
+            Level::Warning,
+            format!(
+                "fields this build doesn't know about can't be written as {}, and were dropped",
+                format!("{:?}", to).to_lowercase()
+            ),
+        )
+        .in_file(path)
+        .emit();
+    }
+    match output {
+        Some(out) => std::fs::write(out, &bytes).map_err(|error| CliError::Io {
+            path: out.to_path_buf(),
+            error,
+        })?,
+        None if report.is_json() => {}
+        None => {
+            let mut stdout = std::io::stdout();
+            if matches!(to, Format::Proto | Format::File) && stdout.is_terminal() {
+                return Err(CliError::Usage(
+                    "won't write a binary format to the terminal; pass --out".to_string(),
+                ));
+            }
+            stdout
+                .write_all(&bytes)
+                .and_then(|()| stdout.flush())
+                .map_err(|error| CliError::Io {
+                    path: "<stdout>".into(),
+                    error,
+                })?
+        }
+    }
+    if report.is_json() {
+        println!("{}", json(to, &bytes, dropped, output).pretty());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// What was converted, as JSON: the format written, whether fields were
+/// dropped, and either the path written to or the converted game, as text
+/// or for the binary formats as hex.
+fn json(to: Format, bytes: &[u8], dropped: bool, written: Option<&Path>) -> Value {
+    let mut fields = vec![
+        ("format", output::string(format!("{:?}", to).to_lowercase())),
+        ("droppedFields", Value::Bool(dropped)),
+    ];
+    match (written, to) {
+        (Some(path), _) => fields.push(("path", output::string(path.display().to_string()))),
+        (None, Format::Proto | Format::File) => {
+            fields.push(("contentHex", output::string(hex(bytes))))
+        }
+        (None, _) => fields.push((
+            "content",
+            output::string(String::from_utf8_lossy(bytes).into_owned()),
+        )),
+    }
+    output::document("convert", fields)
+}
+
+/// `game` written as `to`, and whether fields in `message`, the binary
+/// proto it was read from if it was, were lost on the way.
+fn convert(game: &GameStructure, message: Option<&[u8]>, to: Format) -> (Vec<u8>, bool) {
+    let proto = || message.map_or_else(|| game.encode_to_vec(), <[u8]>::to_vec);
+    // Decoding skips fields it doesn't know, so they're gone if encoding
+    // the game again doesn't give back the bytes it was read from.
+    let dropped = || message.is_some_and(|m| m != game.encode_to_vec());
+    match to {
+        Format::Proto => (proto(), false),
+        Format::File => (gamedef::file::frame_message(&proto()), false),
+        Format::Json => (gamedef::to_json(game).into_bytes(), dropped()),
+        Format::Yaml => (gamedef::to_yaml(game).into_bytes(), dropped()),
+        Format::Dsl => (gamedef::to_dsl(game).into_bytes(), dropped()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustlib::presets;
+
+    #[test]
+    fn test_convert() {
+        let game = presets::five_card_draw();
+        for to in [Format::Json, Format::Yaml, Format::Dsl] {
+            let (bytes, dropped) = convert(&game, None, to);
+            assert!(!dropped);
+            let text = String::from_utf8(bytes).unwrap();
+            let back = match to {
+                Format::Json => gamedef::from_json(&text),
+                Format::Yaml => gamedef::from_yaml(&text),
+                _ => gamedef::from_dsl(&text),
+            };
+            assert_eq!(back.unwrap(), game, "{:?}", to);
+        }
+        let (bytes, _) = convert(&game, None, Format::Dsl);
+        let value = json(Format::Dsl, &bytes, false, None).to_string();
+        assert!(value.starts_with(
+            r#"{"schema":"snapfold.convert.v1","format":"dsl","droppedFields":false,"content":""#
+        ));
+        let value = json(Format::Proto, &[1, 171], false, None).to_string();
+        assert!(value.ends_with(r#""contentHex":"01ab"}"#));
+    }
+}
@@ -0,0 +1,213 @@
+//! `snapfold deal`: deals a hand and shows every card.
+
+use std::fmt::Write;
+use std::path::Path;
+use std::process::ExitCode;
+
+use rustlib::engine::{EngineConfig, GameEngine};
+use rustlib::entropy::{EntropySource, OsEntropy};
+use rustlib::gamedef::json::Value;
+use rustlib::history::HandFile;
+use rustlib::shuffle::Shuffler;
+use rustlib::strategy::{Passive, Strategy};
+use rustlib::view::GameView;
+
+use crate::args::Args;
+use crate::history;
+use crate::load::{self, Format};
+use crate::output::{self, Output};
+use crate::table::Table;
+use crate::{diag, CliError};
+
+pub const USAGE: &str = "\
+usage: snapfold deal FILE [--players N] [--seed N] [--output FORMAT]
+                          [--record PATH] [--format FORMAT]
+
+Deals a hand of the game and prints every player's cards and the boards.
+Every player checks or calls and stands pat, so every card the game deals
+is dealt. The seed is printed, so the same hand can be dealt again.
+
+Options:
+  --players N      seats at the table (default 6)
+  --seed N         seed for the shuffle (default a random one)
+  --output FORMAT  text, or json to print the hand as JSON, with cards as
+                   the deck labels them
+  --record PATH    save the hand to PATH for `snapfold replay`
+  --format FORMAT  read FILE as json, yaml, dsl, binpb or game";
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(
+        argv,
+        &["players", "seed", "record", "output", "format"],
+        &[],
+    )?;
+    let output = Output::from_args(&args)?;
+    let [path] = args.positional() else {
+        return Err(CliError::Usage("deal needs one game file".to_string()));
+    };
+    let players = args.parsed("players")?.unwrap_or(6);
+    if players < 2 {
+        return Err(CliError::Usage("--players must be at least 2".to_string()));
+    }
+    let seed = match args.parsed("seed")? {
+        Some(seed) => seed,
+        None => OsEntropy::new()
+            .map_err(|error| CliError::Io {
+                path: "/dev/urandom".into(),
+                error,
+            })?
+            .next_u64(),
+    };
+    let game = load::load_playable(Path::new(path), Format::from_arg(args.value("format"))?)?;
+    let engine = deal(game, players, seed).map_err(|message| CliError::Load {
+        path: path.into(),
+        source: None,
+        at: None,
+        message,
+    })?;
+    if let Some(record) = args.value("record") {
+        let file = HandFile::new(&engine, &names(&engine)).map_err(|e| CliError::Load {
+            path: path.into(),
+            source: None,
+            at: None,
+            message: e.to_string(),
+        })?;
+        history::write(&file, Path::new(record))?;
+    }
+    match output.is_json() {
+        true => println!("{}", json(&engine, seed).pretty()),
+        false => print!(
+            "{}",
+            render(&engine, seed, diag::use_color(&std::io::stdout()))
+        ),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// A hand of `game` between `players` passive players, played out.
+fn deal(game: rustlib::GameStructure, players: usize, seed: u64) -> Result<GameEngine, String> {
+    let config = EngineConfig {
+        shuffler: Shuffler::seeded(seed),
+        ..EngineConfig::new(players)
+    };
+    let mut engine = GameEngine::with_config(game, config).map_err(|e| e.to_string())?;
+    while let Some(seat) = engine.to_act() {
+        let action = Passive.act(&GameView::new(&engine, seat));
+        engine
+            .apply_action(seat, action)
+            .map_err(|e| format!("seat {} can't act: {}", seat + 1, e))?;
+    }
+    Ok(engine)
+}
+
+fn names(engine: &GameEngine) -> Vec<String> {
+    (1..=engine.seats().len())
+        .map(|seat| format!("Seat {}", seat))
+        .collect()
+}
+
+/// The hand as printed: a line per seat, then a line per board.
+fn render(engine: &GameEngine, seed: u64, color: bool) -> String {
+    let names = names(engine);
+    let mut table = Table::new(engine, &names, None);
+    table.color = color;
+    let game = engine.game();
+    let mut out = format!(
+        "{}, {} players, seed {}\n",
+        if game.name.is_empty() {
+            &game.id
+        } else {
+            &game.name
+        },
+        names.len(),
+        seed
+    );
+    let width = names.iter().map(String::len).max().unwrap_or(0);
+    for (name, seat) in names.iter().zip(engine.seats()) {
+        let cards: Vec<_> = seat.hole.iter().map(|c| c.card).collect();
+        let _ = writeln!(out, "{:<width$}  {}", name, table.cards(&cards));
+    }
+    let boards = engine.boards();
+    for (i, board) in boards.iter().enumerate() {
+        let label = match boards.len() {
+            1 => "Board".to_string(),
+            _ => format!("Board {}", i + 1),
+        };
+        let _ = writeln!(out, "{:<width$}  {}", label, table.cards(board));
+    }
+    out
+}
+
+/// The hand as JSON: the seed, each seat's cards with whether they are
+/// face up, and the boards.
+fn json(engine: &GameEngine, seed: u64) -> Value {
+    let names = names(engine);
+    let table = Table::new(engine, &names, None);
+    let card = |c| Value::String(table.shoe.label(c));
+    let players = engine
+        .seats()
+        .iter()
+        .map(|seat| {
+            let cards = seat
+                .hole
+                .iter()
+                .map(|c| {
+                    Value::Object(vec![
+                        ("card".to_string(), card(c.card)),
+                        ("faceUp".to_string(), Value::Bool(c.face_up)),
+                    ])
+                })
+                .collect();
+            Value::Array(cards)
+        })
+        .collect();
+    let boards = engine
+        .boards()
+        .iter()
+        .map(|b| Value::Array(b.iter().map(|&c| card(c)).collect()))
+        .collect();
+    output::document(
+        "deal",
+        vec![
+            ("game", Value::String(engine.game().id.clone())),
+            ("seed", output::seed_json(seed)),
+            ("players", Value::Array(players)),
+            ("boards", Value::Array(boards)),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustlib::presets;
+
+    #[test]
+    fn test_deal() {
+        let engine = deal(presets::holdem(), 3, 7).unwrap();
+        let text = render(&engine, 7, false);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "Texas Hold'em, 3 players, seed 7");
+        assert!(lines[1].starts_with("Seat 1  ["));
+        assert_eq!(lines[4].matches('[').count(), 5);
+        assert_eq!(
+            render(&deal(presets::holdem(), 3, 7).unwrap(), 7, false),
+            text
+        );
+
+        let value = json(&engine, 7);
+        let Value::Object(fields) = &value else {
+            panic!("{}", value)
+        };
+        assert_eq!(
+            fields[2],
+            ("seed".to_string(), Value::String("7".to_string()))
+        );
+        match &fields[3].1 {
+            Value::Array(players) => assert_eq!(players.len(), 3),
+            other => panic!("{}", other),
+        }
+        assert!(value.to_string().contains("\"faceUp\":false"));
+    }
+}
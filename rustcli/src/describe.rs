@@ -0,0 +1,663 @@
+//! `snapfold describe`: the rules of a game in words.
+
+use std::fmt::Write;
+use std::path::Path;
+use std::process::ExitCode;
+
+use rustlib::burn_policy::Mode;
+use rustlib::cards::Shoe;
+use rustlib::dealing_errors::ExposedCard;
+use rustlib::deck::JokerRule;
+use rustlib::gamedef::json::Value;
+use rustlib::phase::betting_round::bring_in::Starter;
+use rustlib::phase::betting_round::BettingOrder;
+use rustlib::phase::condition::Measure;
+use rustlib::phase::exchange::DrawSource;
+use rustlib::phase::pass_cards::Direction;
+use rustlib::phase::{self, PhaseType};
+use rustlib::scoring::{self, Range};
+use rustlib::{
+    game_structure, presets, resolve, GameStructure, HandRanking, HandType, Phase, Scoring,
+    StandardDeck,
+};
+
+use crate::args::Args;
+use crate::load::{self, Format};
+use crate::output::{self, Output};
+use crate::CliError;
+
+pub const USAGE: &str = "\
+usage: snapfold describe FILE [--output FORMAT] [--format FORMAT]
+
+Prints the rules of the game as a Markdown document: the deck, each phase
+of a hand in order, how the betting works, and how hands are scored. Games
+that extend a standard game are described in full.
+
+Options:
+  --output FORMAT  text, or json to print each section's lines as JSON
+  --format FORMAT  read FILE as json, yaml, dsl, binpb or game";
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(argv, &["output", "format"], &[])?;
+    let output = Output::from_args(&args)?;
+    let [path] = args.positional() else {
+        return Err(CliError::Usage("describe needs one game file".to_string()));
+    };
+    let path = Path::new(path);
+    let game = load::load(path, Format::from_arg(args.value("format"))?)?;
+    let game = resolve::resolve(&game, presets::by_name).map_err(|e| CliError::Load {
+        path: path.to_path_buf(),
+        source: None,
+        at: None,
+        message: e.to_string(),
+    })?;
+    match output.is_json() {
+        true => println!("{}", json(&game).pretty()),
+        false => print!("{}", describe(&game)),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The rules of `game` as Markdown.
+fn describe(game: &GameStructure) -> String {
+    let mut out = String::new();
+    let title = match game.name.is_empty() {
+        true => game.id.clone(),
+        false => format!("{} ({})", game.name, game.id),
+    };
+    let _ = writeln!(out, "# {}\n", title);
+    if !game.description.is_empty() {
+        let _ = writeln!(out, "{}\n", game.description);
+    }
+
+    let _ = writeln!(out, "## Deck\n");
+    for line in deck(game) {
+        let _ = writeln!(out, "- {}", line);
+    }
+
+    let _ = writeln!(out, "\n## Play\n");
+    write_steps(&mut out, &play(game), 0);
+
+    let _ = writeln!(out, "\n## Betting\n");
+    for line in betting(game) {
+        let _ = writeln!(out, "- {}", line);
+    }
+
+    let _ = writeln!(out, "\n## Scoring\n");
+    for line in scorings(game) {
+        let _ = writeln!(out, "- {}", line);
+    }
+    out
+}
+
+fn deck(game: &GameStructure) -> Vec<String> {
+    let shoe = Shoe::for_game(game);
+    let decks = game.shoe.as_ref().map_or(1, |s| s.decks.max(1));
+    let cards = shoe.remaining() / decks as usize;
+    let standard = match &game.deck {
+        Some(game_structure::Deck::StandardDeck(d)) => StandardDeck::try_from(*d).ok(),
+        Some(game_structure::Deck::CustomDeck(_)) => None,
+        None => Some(StandardDeck::DeckPoker),
+    };
+    let mut lines = vec![match standard {
+        Some(StandardDeck::DeckShortDeck) => {
+            format!("A {}-card short deck: the sixes through the aces.", cards)
+        }
+        Some(_) => format!("A standard {}-card deck.", cards),
+        None => format!(
+            "A {}-card deck: ranks {}, suits {}.",
+            cards,
+            shoe.rank_labels().join(" "),
+            shoe.suit_labels().join(" ")
+        ),
+    }];
+    if let Some(game_structure::Deck::CustomDeck(d)) = &game.deck {
+        if d.jokers > 0 {
+            let rule = match shoe.joker_rule() {
+                JokerRule::Bug => "the bug: an ace, or any card that completes a straight or flush",
+                _ => "wild",
+            };
+            lines.push(format!("{} jokers, which play as {}.", d.jokers, rule));
+        }
+        let wilds: Vec<String> = d
+            .wilds
+            .iter()
+            .map(|w| match w.suits.is_empty() {
+                true => format!("every {}", w.rank),
+                false => format!("the {} of {}", w.rank, w.suits.join(" and ")),
+            })
+            .collect();
+        if !wilds.is_empty() {
+            lines.push(format!("Wild cards: {}.", wilds.join(", ")));
+        }
+    }
+    if let Some(s) = &game.shoe {
+        if s.decks > 1 {
+            let penetration = match s.penetration {
+                0 => String::new(),
+                p => format!(", reshuffled once {}% of it has been dealt", p),
+            };
+            lines.push(format!(
+                "{} decks are shuffled together into a shoe{}.",
+                s.decks, penetration
+            ));
+        }
+    }
+    if let Some(b) = &game.burns {
+        match b.mode() {
+            Mode::EveryStreet => lines.push(format!(
+                "{} burned before every deal but the first.",
+                cards_text(b.cards.max(1))
+            )),
+            Mode::Never => lines.push("No cards are burned.".to_string()),
+            _ => {}
+        }
+    }
+    lines
+}
+
+/// A step of play in words, and for a repeat, the steps repeated.
+struct Step {
+    text: String,
+    steps: Vec<Step>,
+}
+
+/// The steps of a hand of `game`, ending with the showdown.
+fn play(game: &GameStructure) -> Vec<Step> {
+    let burns = game.burns.clone().unwrap_or_default();
+    let mut steps = phases(game, &game.phases, burns.mode());
+    steps.push(Step {
+        text: "Showdown.".to_string(),
+        steps: Vec::new(),
+    });
+    steps
+}
+
+fn phases(game: &GameStructure, phases: &[Phase], burns: Mode) -> Vec<Step> {
+    phases
+        .iter()
+        .map(|p| {
+            let mut text = match &p.phase_type {
+                Some(PhaseType::Repeat(r)) => {
+                    let until = match &r.until {
+                        Some(c) => format!(", stopping early once {}", condition(c)),
+                        None => String::new(),
+                    };
+                    return Step {
+                        text: format!("Repeat {} times{}", r.times, until),
+                        steps: self::phases(game, &r.phases, burns),
+                    };
+                }
+                Some(t) => phase_text(game, t, burns),
+                None => "(an empty phase)".to_string(),
+            };
+            if let Some(c) = &p.condition {
+                let otherwise = match c.end_hand {
+                    true => "; otherwise the hand goes straight to showdown",
+                    false => "",
+                };
+                let _ = write!(text, " Only when {}{}.", condition(c), otherwise);
+            }
+            Step {
+                text,
+                steps: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+/// Appends `steps` as a numbered list, nested `depth` levels deep.
+fn write_steps(out: &mut String, steps: &[Step], depth: usize) {
+    let indent = "   ".repeat(depth);
+    for (i, step) in steps.iter().enumerate() {
+        match step.steps.is_empty() {
+            true => {
+                let _ = writeln!(out, "{}{}. {}", indent, i + 1, step.text);
+            }
+            false => {
+                let _ = writeln!(out, "{}{}. {}:", indent, i + 1, step.text);
+                write_steps(out, &step.steps, depth + 1);
+            }
+        }
+    }
+}
+
+/// The rules of `game` as JSON, in the same words as [`describe`]: a list
+/// of lines for each section, and play as a list of steps, each with the
+/// steps it repeats if any.
+fn json(game: &GameStructure) -> Value {
+    fn list(steps: &[Step]) -> Value {
+        Value::Array(
+            steps
+                .iter()
+                .map(|s| {
+                    let mut fields = vec![("text".to_string(), output::string(s.text.as_str()))];
+                    if !s.steps.is_empty() {
+                        fields.push(("steps".to_string(), list(&s.steps)));
+                    }
+                    Value::Object(fields)
+                })
+                .collect(),
+        )
+    }
+    let lines = |lines: Vec<String>| Value::Array(lines.into_iter().map(Value::String).collect());
+    output::document(
+        "describe",
+        vec![
+            ("id", output::string(game.id.as_str())),
+            ("name", output::string(game.name.as_str())),
+            ("description", output::string(game.description.as_str())),
+            ("deck", lines(deck(game))),
+            ("play", list(&play(game))),
+            ("betting", lines(betting(game))),
+            ("scoring", lines(scorings(game))),
+        ],
+    )
+}
+
+fn phase_text(game: &GameStructure, phase: &PhaseType, burns: Mode) -> String {
+    let burn = |n: i32| match (n, burns) {
+        (n, Mode::PerDeal | Mode::Unknown) if n > 0 => format!(", after {} burned", cards_text(n)),
+        _ => String::new(),
+    };
+    match phase {
+        PhaseType::PlayerDeal(d) if !d.face_up_cards.is_empty() => {
+            let up = d.face_up_cards.iter().filter(|&&u| u).count();
+            let down = d.face_up_cards.len() - up;
+            format!(
+                "Each player is dealt {} down and {} up{}.",
+                down,
+                up,
+                burn(d.burn)
+            )
+        }
+        PhaseType::PlayerDeal(d) => format!(
+            "Each player is dealt {} face {}{}.",
+            cards_text(d.cards),
+            if d.face_up { "up" } else { "down" },
+            burn(d.burn)
+        ),
+        PhaseType::CommunityDeal(d) => {
+            let board = match game.community_board_count > 1 {
+                true => format!("board {}", d.board_idx + 1),
+                false => "the board".to_string(),
+            };
+            let name = match d.name.is_empty() {
+                true => String::new(),
+                false => format!("{}: ", d.name),
+            };
+            format!(
+                "{}{} dealt to {}{}{}.",
+                name,
+                cards_text(d.cards),
+                board,
+                if d.face_down { " face down" } else { "" },
+                burn(d.burn)
+            )
+        }
+        PhaseType::BettingRound(r) => {
+            let name = match r.name.is_empty() {
+                true => "A betting round".to_string(),
+                false => format!("Betting round ({})", r.name),
+            };
+            let start = match r.order() {
+                BettingOrder::LeftOfDealer => "the player left of the button",
+                BettingOrder::BestFaceup => "the best hand showing",
+                _ => "the player after the blinds",
+            };
+            let mut text = format!(
+                "{}, starting with {}. The smallest bet is {}.",
+                name,
+                start,
+                multiple(r.min_bet, "big blind")
+            );
+            if let Some(b) = &r.bring_in {
+                let card = match b.starter() {
+                    Starter::Highest => "highest",
+                    _ => "lowest",
+                };
+                let complete = match b.allow_complete {
+                    true => ", or may complete to a full bet",
+                    false => "",
+                };
+                let _ = write!(
+                    text,
+                    " The {} card showing brings it in for {}{}.",
+                    card,
+                    multiple(b.amount, "small blind"),
+                    complete
+                );
+            }
+            text
+        }
+        PhaseType::Exchange(e) => {
+            let mut text = format!(
+                "Draw: each player may discard up to {} and draw replacements.",
+                cards_text(e.max_exchange)
+            );
+            if e.lock_faceup {
+                text.push_str(" Face-up cards can't be discarded.");
+            }
+            if e.lock_facedown {
+                text.push_str(" Face-down cards can't be discarded.");
+            }
+            if e.draw_source() == DrawSource::ReshuffleDiscards {
+                text.push_str(
+                    " If the deck runs out, the earlier discards are shuffled to replace it.",
+                );
+            }
+            if e.public_discards {
+                text.push_str(" Discards are shown face up.");
+            }
+            text
+        }
+        PhaseType::Ante(a) => match a.payer() {
+            phase::ante::Payer::BigBlind => {
+                "The big blind posts the ante for the table.".to_string()
+            }
+            _ => "Every player posts the ante.".to_string(),
+        },
+        PhaseType::PassCards(p) => {
+            let direction = match p.direction() {
+                Direction::Right => "to the right",
+                Direction::Across => "across the table",
+                _ => "to the left",
+            };
+            let when = match p.sequential {
+                true => ", one player at a time",
+                false => ", all at once",
+            };
+            format!(
+                "Each player passes {} {}{}.",
+                cards_text(p.cards),
+                direction,
+                when
+            )
+        }
+        PhaseType::Reveal(r) if r.community => {
+            let board = match game.community_board_count > 1 {
+                true => format!("board {}", r.board_idx + 1),
+                false => "the board".to_string(),
+            };
+            format!("{} of {} turned face up.", cards_text(r.cards), board)
+        }
+        PhaseType::Reveal(r) => format!(
+            "Each player turns up {} of their face-down cards{}.",
+            r.cards,
+            if r.choose { ", of their choice" } else { "" }
+        ),
+        PhaseType::Declare(d) => format!(
+            "Each player declares for the high half of the pot, the low half, or both, {}.",
+            match d.in_turn {
+                true => "aloud in turn starting left of the button",
+                false => "in secret and all at once",
+            }
+        ),
+        PhaseType::Buy(b) => format!(
+            "Each player may buy a card {} for {}{}.",
+            if b.face_up { "face up" } else { "face down" },
+            multiple(b.price, "small blind"),
+            if b.replace {
+                ", discarding a card of their choice for it"
+            } else {
+                ""
+            }
+        ),
+        PhaseType::Repeat(_) => unreachable!("repeats are listed with their phases"),
+    }
+}
+
+fn betting(game: &GameStructure) -> Vec<String> {
+    let rounds = count_phases(&game.phases, &|t| matches!(t, PhaseType::BettingRound(_)));
+    let antes = count_phases(&game.phases, &|t| matches!(t, PhaseType::Ante(_)));
+    let mut lines = vec![format!("{} betting rounds.", rounds)];
+    if antes == 0 {
+        lines
+            .push("Any ante the table sets is posted by every player before the deal.".to_string());
+    }
+    lines.push(
+        "The blinds, the stakes and the limit (no-limit, pot-limit or fixed-limit) are \
+         set by the table."
+            .to_string(),
+    );
+    if game.all_in_runouts > 1 {
+        lines.push(format!(
+            "When everyone is all in, the rest of the board is run out {} times and each \
+             pot split between the runs.",
+            game.all_in_runouts
+        ));
+    }
+    if let Some(r) = &game.rake {
+        let cap = match r.cap {
+            0 => String::new(),
+            cap => format!(", up to {}", cap),
+        };
+        let no_flop = match r.no_flop_no_drop {
+            true => ", but not from hands that end before the first board card",
+            false => "",
+        };
+        lines.push(format!(
+            "The house rakes {}% of each pot{}{}.",
+            r.basis_points as f64 / 100.0,
+            cap,
+            no_flop
+        ));
+    }
+    let errors = game.dealing_errors.clone().unwrap_or_default();
+    lines.push(
+        match errors.exposed_card() {
+            ExposedCard::PlayOn => "A card exposed in the deal stays in the hand, face up.",
+            ExposedCard::Redeal => {
+                "A card exposed in the deal is a misdeal, unless someone has acted, when it \
+                 stays face up."
+            }
+            _ => "A card exposed in the deal is burned and replaced.",
+        }
+        .to_string(),
+    );
+    if errors.max_exposed > 0 {
+        lines.push(format!(
+            "{} exposed cards in one hand is a misdeal.",
+            errors.max_exposed
+        ));
+    }
+    lines
+}
+
+/// How many phases, counting those inside repeats once per pass, match
+/// `kind`.
+fn count_phases(phases: &[Phase], kind: &dyn Fn(&PhaseType) -> bool) -> usize {
+    phases
+        .iter()
+        .map(|p| match &p.phase_type {
+            Some(PhaseType::Repeat(r)) => r.times.max(0) as usize * count_phases(&r.phases, kind),
+            Some(t) => kind(t) as usize,
+            None => 0,
+        })
+        .sum()
+}
+
+fn scorings(game: &GameStructure) -> Vec<String> {
+    let mut lines = Vec::new();
+    let default = [Scoring::default()];
+    let scorings = match game.scorings.is_empty() {
+        true => &default[..],
+        false => &game.scorings,
+    };
+    for s in scorings {
+        let name = match (s.name.is_empty(), scorings.len()) {
+            (false, _) => format!("**{}**: ", s.name),
+            (true, 1) => String::new(),
+            (true, _) if s.lo => "**Low**: ".to_string(),
+            (true, _) => "**High**: ".to_string(),
+        };
+        lines.push(format!("{}{}", name, scoring(s)));
+    }
+    if scorings.len() > 1 {
+        lines.push("Each pot is split between the winners of each scoring.".to_string());
+        if scorings.iter().any(|s| s.lo && !s.lo_qualifier.is_empty()) {
+            lines.push(
+                "If no hand qualifies for a scoring, the others share its part of the pot."
+                    .to_string(),
+            );
+        }
+    }
+    lines
+}
+
+fn scoring(s: &Scoring) -> String {
+    let ranking = match &s.ranking {
+        Some(scoring::Ranking::PointsExpression(expr)) => {
+            let best = if s.lo { "lowest" } else { "highest" };
+            return format!("The {} total of `{}` wins.", best, expr);
+        }
+        Some(scoring::Ranking::CustomHandOrder(order)) => {
+            let types: Vec<&str> = order
+                .hand_types
+                .iter()
+                .map(|&t| hand_type(HandType::try_from(t).unwrap_or_default()))
+                .collect();
+            format!("hands rank, best first: {}", types.join(", "))
+        }
+        Some(scoring::Ranking::StandardRanking(r)) => {
+            match HandRanking::try_from(*r).unwrap_or_default() {
+                HandRanking::RankingAceFive => "ace-to-five ranking".to_string(),
+                HandRanking::RankingDeuceSeven => "deuce-to-seven ranking".to_string(),
+                HandRanking::RankingBadugi => "badugi ranking".to_string(),
+                HandRanking::RankingShortDeck | HandRanking::RankingShortDeckHighStraight => {
+                    "short-deck ranking: flushes beat full houses and trips beat straights"
+                        .to_string()
+                }
+                _ => "standard poker ranking".to_string(),
+            }
+        }
+        None => "standard poker ranking".to_string(),
+    };
+    let mut text = match s.lo {
+        true => format!("The lowest hand wins, by {}.", ranking),
+        false => format!("The best hand wins, by {}.", ranking),
+    };
+    if s.lo && !s.lo_qualifier.is_empty() {
+        let _ = write!(
+            text,
+            " Only unpaired hands with no card above {} qualify.",
+            s.lo_qualifier
+        );
+    }
+    let mut uses = Vec::new();
+    if let Some(r) = &s.hand_cards {
+        if let Some(n) = range(r) {
+            uses.push(format!("{} from their hand", n));
+        }
+    }
+    for (i, r) in s.community_cards.iter().enumerate() {
+        let board = match s.community_cards.len() {
+            1 => "the board".to_string(),
+            _ => format!("board {}", i + 1),
+        };
+        if let Some(n) = range(r) {
+            uses.push(format!("{} from {}", n, board));
+        }
+    }
+    if !uses.is_empty() {
+        let _ = write!(text, " Players use {}.", uses.join(" and "));
+    }
+    if s.wraparound_straight {
+        text.push_str(" Straights can wrap around, as in K-A-2-3-4.");
+    }
+    if s.natural_wilds {
+        text.push_str(" Wild cards play as themselves; jokers are still wild.");
+    }
+    text
+}
+
+/// How many cards a scoring range allows, or `None` if any number will do.
+fn range(r: &Range) -> Option<String> {
+    match (r.min, r.max) {
+        (-1, -1) => None,
+        (min, max) if min == max => Some(format!("exactly {}", cards_text(max))),
+        (min, max) if min <= 0 => Some(format!("at most {}", cards_text(max))),
+        (min, max) => Some(format!("{} to {} cards", min, max)),
+    }
+}
+
+fn condition(c: &phase::Condition) -> String {
+    let measure = match c.measure() {
+        Measure::ActivePlayers => "the number of players with chips left to bet",
+        Measure::Pot => "the pot",
+        _ => "the number of players still in the hand",
+    };
+    let compare = if c.negate { "at most" } else { "more than" };
+    format!("{} is {} {}", measure, compare, c.above)
+}
+
+fn cards_text(n: i32) -> String {
+    match n {
+        1 => "1 card".to_string(),
+        n => format!("{} cards", n),
+    }
+}
+
+/// `n` of `unit`, where 0 counts as 1.
+fn multiple(n: i32, unit: &str) -> String {
+    match n.max(1) {
+        1 => format!("1 {}", unit),
+        n => format!("{} {}s", n, unit),
+    }
+}
+
+pub fn hand_type(t: HandType) -> &'static str {
+    match t {
+        HandType::NoPair => "high card",
+        HandType::Pair => "pair",
+        HandType::TwoPair => "two pair",
+        HandType::ThreeOfAKind => "three of a kind",
+        HandType::Straight => "straight",
+        HandType::Flush => "flush",
+        HandType::FullHouse => "full house",
+        HandType::FourOfAKind => "four of a kind",
+        HandType::StraightFlush => "straight flush",
+        HandType::FiveOfAKind => "five of a kind",
+        HandType::FlushHouse => "flush house",
+        HandType::FlushFive => "flush five",
+        HandType::Unknown => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        let text = describe(&presets::omaha_hi_lo());
+        assert!(text
+            .starts_with("# Omaha Hi-Lo (omaha-hilo)\n\n## Deck\n\n- A standard 52-card deck.\n"));
+        assert!(text.contains("\n1. Each player is dealt 4 cards face down.\n"));
+        assert!(text.contains(
+            "\n2. Betting round (Pre-flop), starting with the player after the blinds. \
+             The smallest bet is 1 big blind.\n"
+        ));
+        assert!(text.contains("\n3. 3 cards dealt to the board, after 1 card burned.\n"));
+        assert!(text.contains("\n9. Showdown.\n"));
+        assert!(text.contains("\n- 4 betting rounds.\n"));
+        assert!(text.contains(
+            "\n- **Lo**: The lowest hand wins, by ace-to-five ranking. Only unpaired hands \
+             with no card above 8 qualify. Players use exactly 2 cards from their hand and \
+             exactly 3 cards from the board.\n"
+        ));
+
+        let text = describe(&presets::seven_card_stud());
+        assert!(text.contains("Each player is dealt 2 down and 1 up."));
+        assert!(text.contains(
+            "The lowest card showing brings it in for 1 small blind, or may complete to a \
+             full bet."
+        ));
+        assert!(!text.contains("Any ante the table sets"));
+
+        let value = json(&presets::omaha_hi_lo()).to_string();
+        assert!(value.starts_with(r#"{"schema":"snapfold.describe.v1","id":"omaha-hilo""#));
+        assert!(value.contains(r#""play":[{"text":"Each player is dealt 4 cards face down."}"#));
+        assert!(value.contains(r#"{"text":"Showdown."}],"betting":["4 betting rounds.""#));
+    }
+}
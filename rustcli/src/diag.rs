@@ -0,0 +1,187 @@
+//! Diagnostics for stderr, in color when stderr is a terminal and
+//! `NO_COLOR` isn't set.
+
+use std::fmt::Write;
+use std::io::IsTerminal;
+use std::path::Path;
+
+use crate::CliError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Level {
+    fn label(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Info => "info",
+        }
+    }
+
+    /// The ANSI style the label is drawn in.
+    fn style(self) -> &'static str {
+        match self {
+            Level::Error => "1;31",
+            Level::Warning => "1;33",
+            Level::Info => "1;36",
+        }
+    }
+}
+
+/// A message, optionally about a file and a place in its text.
+#[derive(Clone, Debug)]
+pub struct Diagnostic<'a> {
+    pub level: Level,
+    pub message: String,
+    pub path: Option<&'a Path>,
+    /// A 1-based line and column, and the text they count through.
+    pub at: Option<(usize, usize, &'a str)>,
+}
+
+impl<'a> Diagnostic<'a> {
+    pub fn new(level: Level, message: impl Into<String>) -> Self {
+        Diagnostic {
+            level,
+            message: message.into(),
+            path: None,
+            at: None,
+        }
+    }
+
+    pub fn in_file(mut self, path: &'a Path) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    pub fn at(mut self, line: usize, column: usize, source: &'a str) -> Self {
+        self.at = Some((line, column, source));
+        self
+    }
+
+    /// Prints the diagnostic to stderr.
+    pub fn emit(&self) {
+        eprint!("{}", self.render(use_color(&std::io::stderr())));
+    }
+
+    /// The diagnostic as printed, with ANSI styles if `color`:
+    ///
+    /// ```text
+    /// error: unknown field "bets"
+    ///   --> game.yaml:4:3
+    ///    |
+    ///  4 |   bets: 2
+    ///    |   ^
+    /// ```
+    pub fn render(&self, color: bool) -> String {
+        let paint = |style: &str, text: &str| match color {
+            true => format!("\x1b[{}m{}\x1b[0m", style, text),
+            false => text.to_string(),
+        };
+        let mut out = format!(
+            "{}{}\n",
+            paint(self.level.style(), &format!("{}:", self.level.label())),
+            paint("1", &format!(" {}", self.message)),
+        );
+        let line_text = self
+            .at
+            .and_then(|(line, _, source)| source.lines().nth(line.checked_sub(1)?));
+        let gutter = self.at.map_or(0, |(line, _, _)| line.to_string().len()) + 1;
+        if let Some(path) = self.path {
+            let place = match self.at {
+                Some((line, column, _)) => format!("{}:{}:{}", path.display(), line, column),
+                None => path.display().to_string(),
+            };
+            let arrow = paint("1;34", &format!("{:>w$}-->", "", w = gutter));
+            let _ = writeln!(out, "{} {}", arrow, place);
+        }
+        if let (Some((line, column, _)), Some(text)) = (self.at, line_text) {
+            let bar = paint("1;34", &format!("{:>w$} |", "", w = gutter));
+            // Tabs stay tabs so the caret lines up under them.
+            let pad: String = text
+                .chars()
+                .take(column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let number = paint("1;34", &format!("{:>w$} |", line, w = gutter));
+            let _ = writeln!(out, "{}", bar);
+            let _ = writeln!(out, "{} {}", number, text);
+            let _ = writeln!(out, "{} {}{}", bar, pad, paint(self.level.style(), "^"));
+        }
+        out
+    }
+}
+
+/// Whether output to `stream` should be colored.
+pub fn use_color(stream: &impl IsTerminal) -> bool {
+    std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && stream.is_terminal()
+}
+
+/// Prints why a command failed.
+pub fn report(error: &CliError) {
+    match error {
+        CliError::Usage(message) => {
+            Diagnostic::new(Level::Error, message.as_str()).emit();
+            eprintln!("Run `snapfold help` for usage.");
+        }
+        CliError::Io { path, error } => Diagnostic::new(Level::Error, error.to_string())
+            .in_file(path)
+            .emit(),
+        CliError::Load {
+            path,
+            source,
+            at,
+            message,
+        } => {
+            let mut d = Diagnostic::new(Level::Error, message.as_str()).in_file(path);
+            if let (Some((line, column)), Some(source)) = (at, source) {
+                d = d.at(*line, *column, source);
+            }
+            d.emit()
+        }
+        CliError::Invalid { path, errors } => {
+            for e in errors {
+                Diagnostic::new(Level::Error, e.to_string())
+                    .in_file(path)
+                    .emit();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let source = "id: holdem\n  bets: 2\n";
+        let d = Diagnostic::new(Level::Error, "unknown field \"bets\"")
+            .in_file(Path::new("game.yaml"))
+            .at(2, 3, source);
+        assert_eq!(
+            d.render(false),
+            "error: unknown field \"bets\"\n  \
+             --> game.yaml:2:3\n   |\n 2 |   bets: 2\n   |   ^\n"
+        );
+        let colored = d.render(true);
+        assert!(colored.starts_with("\x1b[1;31merror:\x1b[0m"));
+        assert!(colored.contains("\x1b[1;31m^\x1b[0m"));
+
+        let d = Diagnostic::new(Level::Warning, "phase 1: betting round has no name")
+            .in_file(Path::new("game.json"));
+        assert_eq!(
+            d.render(false),
+            "warning: phase 1: betting round has no name\n --> game.json\n"
+        );
+        // A position past the end of the text points at the file alone.
+        let d = Diagnostic::new(Level::Error, "x")
+            .in_file(Path::new("a"))
+            .at(9, 1, source);
+        assert_eq!(d.render(false), "error: x\n  --> a:9:1\n");
+    }
+}
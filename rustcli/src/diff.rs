@@ -0,0 +1,152 @@
+//! `snapfold diff`: what changed between two game definitions.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use rustlib::gamedef::diff::{Change, ChangeKind};
+use rustlib::gamedef::json::Value;
+use rustlib::gamedef::{self, GameDiff};
+use rustlib::{presets, resolve, GameStructure};
+
+use crate::args::Args;
+use crate::diag;
+use crate::load::{self, Format};
+use crate::output::{self, Output};
+use crate::CliError;
+
+pub const USAGE: &str = "\
+usage: snapfold diff OLD NEW [--exit-code] [--resolve] [--output FORMAT]
+                             [--format FORMAT]
+
+Compares two game definitions field by field and prints each change on a
+line: + for a field set in NEW, - for one cleared, ~ for a new value.
+Fields are named by their path, such as phases[2].bettingRound.minBet,
+and the two files may be in different formats.
+
+Options:
+  --exit-code      exit with status 1 if the games differ, as for a check
+                   that rule changes are reviewed
+  --resolve        compare the games in full, with the standard games they
+                   extend filled in
+  --output FORMAT  text, or json to print the changes as JSON
+  --format FORMAT  read both files as json, yaml, dsl, binpb or game";
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(argv, &["output", "format"], &["exit-code", "resolve"])?;
+    let output = Output::from_args(&args)?;
+    let [old, new] = args.positional() else {
+        return Err(CliError::Usage("diff needs two game files".to_string()));
+    };
+    let format = Format::from_arg(args.value("format"))?;
+    let read = |path: &str| -> Result<GameStructure, CliError> {
+        let path = Path::new(path);
+        let game = load::load(path, format)?;
+        match args.switch("resolve") {
+            true => resolve::resolve(&game, presets::by_name).map_err(|e| CliError::Load {
+                path: path.to_path_buf(),
+                source: None,
+                at: None,
+                message: e.to_string(),
+            }),
+            false => Ok(game),
+        }
+    };
+    let changes = gamedef::diff(&read(old)?, &read(new)?);
+    match output.is_json() {
+        true => println!("{}", json(&changes).pretty()),
+        false => print!("{}", render(&changes, diag::use_color(&std::io::stdout()))),
+    }
+    Ok(match args.switch("exit-code") && !changes.is_empty() {
+        true => ExitCode::from(1),
+        false => ExitCode::SUCCESS,
+    })
+}
+
+/// The changes one to a line, as `GameDiff` prints them, with additions
+/// in green, removals in red and changed values in yellow if `color`.
+fn render(changes: &GameDiff, color: bool) -> String {
+    if !color {
+        return changes.to_string();
+    }
+    let mut out = String::new();
+    for change in &changes.changes {
+        let (style, line) = match &change.kind {
+            ChangeKind::Added(v) => ("32", format!("+ {}: {}", change.path, v)),
+            ChangeKind::Removed(v) => ("31", format!("- {}: {}", change.path, v)),
+            ChangeKind::Changed { from, to } => {
+                ("33", format!("~ {}: {} -> {}", change.path, from, to))
+            }
+        };
+        out.push_str(&format!("\x1b[{}m{}\x1b[0m\n", style, line));
+    }
+    out
+}
+
+fn json(changes: &GameDiff) -> Value {
+    let items = changes.changes.iter().map(change_json).collect();
+    output::document(
+        "diff",
+        vec![
+            ("same", Value::Bool(changes.is_empty())),
+            ("changes", Value::Array(items)),
+        ],
+    )
+}
+
+fn change_json(change: &Change) -> Value {
+    let mut fields = vec![("path".to_string(), output::string(change.path.as_str()))];
+    match &change.kind {
+        ChangeKind::Added(v) => {
+            fields.push(("kind".to_string(), output::string("added")));
+            fields.push(("value".to_string(), v.clone()));
+        }
+        ChangeKind::Removed(v) => {
+            fields.push(("kind".to_string(), output::string("removed")));
+            fields.push(("value".to_string(), v.clone()));
+        }
+        ChangeKind::Changed { from, to } => {
+            fields.push(("kind".to_string(), output::string("changed")));
+            fields.push(("from".to_string(), from.clone()));
+            fields.push(("to".to_string(), to.clone()));
+        }
+    }
+    Value::Object(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustlib::phase::PhaseType;
+
+    #[test]
+    fn test_diff() {
+        let old = presets::holdem();
+        let mut new = old.clone();
+        new.name = "Hold'em, bigger bets".to_string();
+        if let Some(PhaseType::BettingRound(round)) = &mut new.phases[1].phase_type {
+            round.min_bet = 4;
+        }
+        let changes = gamedef::diff(&old, &new);
+        let plain = render(&changes, false);
+        assert_eq!(plain, changes.to_string());
+        let colored = render(&changes, true);
+        assert_eq!(colored.lines().count(), plain.lines().count());
+        assert!(colored.starts_with("\x1b[33m~ name: "), "{}", colored);
+
+        let value = json(&changes).to_string();
+        assert!(
+            value.starts_with(r#"{"schema":"snapfold.diff.v1","same":false,"changes":[{"path":"name","kind":"changed","from":"#),
+            "{}",
+            value
+        );
+        assert!(
+            value.contains(r#""kind":"changed","from":1,"to":4}"#),
+            "{}",
+            value
+        );
+        assert_eq!(
+            json(&gamedef::diff(&old, &old)).to_string(),
+            r#"{"schema":"snapfold.diff.v1","same":true,"changes":[]}"#
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! `snapfold equity`: how often each hand or range wins.
+
+use std::fmt::Write;
+use std::path::Path;
+use std::process::ExitCode;
+
+use rustlib::cards::{parse_cards, Card};
+use rustlib::equity::{self, Equity};
+use rustlib::gamedef::json::Value;
+use rustlib::range::Range;
+use rustlib::{presets, GameStructure};
+
+use crate::args::Args;
+use crate::load::{self, Format};
+use crate::output::{self, Output};
+use crate::CliError;
+
+pub const USAGE: &str = "\
+usage: snapfold equity HAND HAND... [--board CARDS]... [--trials N] [--seed N]
+                       [--threads N] [--game FILE] [--output FORMAT]
+                       [--format FORMAT]
+
+Prints each hand's share of the pot, with a 95% confidence interval, over
+random finishes of the hand. A HAND is cards, like \"AhKh\", or a range of
+two-card hands, like \"AKs\", \"QQ+, AJs+\" or \"10%\"; \"\" stands for any
+hand. If every finish can be played out in no more than N trials, each is
+played once and the results are exact.
+
+Options:
+  --board CARDS    cards on the board so far; give it again for each board
+                   of a game with more than one
+  --trials N       finishes to play (default 100000; 1e6 is a million)
+  --seed N         seed for the finishes (default 0)
+  --threads N      threads to play them on (default one per core)
+  --game FILE      play FILE (default Texas Hold'em)
+  --output FORMAT  text, or json to print the equities as JSON
+  --format FORMAT  read FILE as json, yaml, dsl, binpb or game";
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(
+        argv,
+        &[
+            "board", "trials", "seed", "threads", "game", "output", "format",
+        ],
+        &[],
+    )?;
+    let output = Output::from_args(&args)?;
+    let labels = args.positional();
+    if labels.len() < 2 {
+        return Err(CliError::Usage(
+            "equity needs at least two hands".to_string(),
+        ));
+    }
+    let hands = labels
+        .iter()
+        .map(|h| hand(h))
+        .collect::<Result<Vec<_>, _>>()?;
+    let boards = args
+        .values("board")
+        .map(|b| parse_cards(b).map_err(|e| CliError::Usage(format!("--board {:?}: {}", b, e))))
+        .collect::<Result<Vec<_>, _>>()?;
+    let trials = match args.parsed::<f64>("trials")? {
+        None => 100_000,
+        Some(n) if n >= 1.0 && n.fract() == 0.0 && n <= usize::MAX as f64 => n as usize,
+        Some(_) => {
+            return Err(CliError::Usage(
+                "--trials must be a whole number of at least 1".to_string(),
+            ))
+        }
+    };
+    let game = match args.value("game") {
+        Some(path) => {
+            load::load_playable(Path::new(path), Format::from_arg(args.value("format"))?)?
+        }
+        None => presets::holdem(),
+    };
+    let run = Run {
+        trials,
+        seed: args.parsed("seed")?.unwrap_or(0),
+        threads: args.parsed("threads")?.unwrap_or(0),
+    };
+    let equities = equities(&game, &hands, &boards, &run).map_err(CliError::Usage)?;
+    match output.is_json() {
+        true => println!("{}", json(&game, labels, &equities, &run).pretty()),
+        false => print!("{}", render(&game, labels, &equities, &run)),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// A player's hole cards: known, or drawn from a range.
+#[derive(Clone, Debug, PartialEq)]
+enum Hand {
+    Cards(Vec<Card>),
+    Range(Range),
+}
+
+fn hand(text: &str) -> Result<Hand, CliError> {
+    if let Ok(cards) = parse_cards(text) {
+        return Ok(Hand::Cards(cards));
+    }
+    text.parse()
+        .map(Hand::Range)
+        .map_err(|e| CliError::Usage(format!("{:?} isn't cards or a range: {}", text, e)))
+}
+
+/// How many finishes to play, and how.
+struct Run {
+    trials: usize,
+    seed: u64,
+    threads: usize,
+}
+
+/// Each hand's equity. Known hands are played out with
+/// [`equity::simulate_parallel`], and ranges with [`equity::sample_ranges`],
+/// in which any known hands have to be two cards (or none, for any hand).
+fn equities(
+    game: &GameStructure,
+    hands: &[Hand],
+    boards: &[Vec<Card>],
+    run: &Run,
+) -> Result<Vec<Equity>, String> {
+    let known: Option<Vec<Vec<Card>>> = hands
+        .iter()
+        .map(|h| match h {
+            Hand::Cards(cards) => Some(cards.clone()),
+            Hand::Range(_) => None,
+        })
+        .collect();
+    let result = match known {
+        Some(known) => {
+            equity::simulate_parallel(game, &known, boards, run.trials, run.seed, run.threads)
+        }
+        None => {
+            let ranges = hands
+                .iter()
+                .map(|h| match h {
+                    Hand::Range(range) => Ok(range.clone()),
+                    Hand::Cards(cards) if cards.is_empty() => Ok(Range::all()),
+                    Hand::Cards(cards) if cards.len() == 2 => format!("{}{}", cards[0], cards[1])
+                        .parse()
+                        .map_err(|e| format!("{}", e)),
+                    Hand::Cards(_) => {
+                        Err("hands given with ranges must be two cards, or none".to_string())
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            equity::sample_ranges(game, &ranges, boards, run.trials, run.seed)
+        }
+    };
+    result.map_err(|e| e.to_string())
+}
+
+fn exact(equities: &[Equity]) -> bool {
+    equities.iter().all(|e| e.margin == 0.0)
+}
+
+/// A table of the hands' equities, then a line saying how they were found.
+fn render(game: &GameStructure, labels: &[String], equities: &[Equity], run: &Run) -> String {
+    let header = ["Hand", "Equity", "95% interval", "Win", "Tie"];
+    let rows: Vec<[String; 5]> = labels
+        .iter()
+        .zip(equities)
+        .map(|(label, e)| {
+            let (low, high) = e.interval();
+            [
+                match label.is_empty() {
+                    true => "any".to_string(),
+                    false => label.clone(),
+                },
+                format!("{:.2}%", e.equity),
+                match e.margin {
+                    0.0 => "exact".to_string(),
+                    _ => format!("{:.2}-{:.2}%", low, high),
+                },
+                format!("{:.2}%", e.win),
+                format!("{:.2}%", e.tie),
+            ]
+        })
+        .collect();
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| {
+            rows.iter()
+                .map(|r| r[i].chars().count())
+                .chain([header[i].len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let mut out = String::new();
+    let mut line = |cells: &[&str]| {
+        let mut text = format!("{:<width$}", cells[0], width = widths[0]);
+        for (cell, width) in cells[1..].iter().zip(&widths[1..]) {
+            let _ = write!(text, "  {:>width$}", cell, width = width);
+        }
+        out.push_str(text.trim_end());
+        out.push('\n');
+    };
+    line(&header);
+    for row in &rows {
+        line(&row.each_ref().map(String::as_str));
+    }
+    let name = match game.name.is_empty() {
+        true => &game.id,
+        false => &game.name,
+    };
+    let _ = match exact(equities) {
+        true => writeln!(out, "{}, every finish played out", name),
+        false => writeln!(out, "{}, {} trials, seed {}", name, run.trials, run.seed),
+    };
+    out
+}
+
+/// The equities as JSON: for each hand, its share of the pot and how often
+/// it wins, ties and loses, in percent, with the bounds of the interval.
+fn json(game: &GameStructure, labels: &[String], equities: &[Equity], run: &Run) -> Value {
+    let number = |n: f64| Value::Number(n);
+    let hands = labels
+        .iter()
+        .zip(equities)
+        .map(|(label, e)| {
+            let (low, high) = e.interval();
+            Value::Object(vec![
+                ("hand".to_string(), Value::String(label.clone())),
+                ("equity".to_string(), number(e.equity)),
+                ("low".to_string(), number(low)),
+                ("high".to_string(), number(high)),
+                ("win".to_string(), number(e.win)),
+                ("tie".to_string(), number(e.tie)),
+                ("lose".to_string(), number(e.lose)),
+            ])
+        })
+        .collect();
+    output::document(
+        "equity",
+        vec![
+            ("game", Value::String(game.id.clone())),
+            ("exact", Value::Bool(exact(equities))),
+            ("trials", number(run.trials as f64)),
+            ("seed", output::seed_json(run.seed)),
+            ("hands", Value::Array(hands)),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(s: &[&str]) -> Vec<String> {
+        s.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_equity() {
+        let run = Run {
+            trials: 2000,
+            seed: 1,
+            threads: 2,
+        };
+        let game = presets::holdem();
+        let board = vec![parse_cards("Qh 7h 2c 3s").unwrap()];
+
+        let hands = [hand("AhKh").unwrap(), hand("QcQs").unwrap()];
+        let equities = super::equities(&game, &hands, &board, &run).unwrap();
+        let text = render(&game, &labels(&["AhKh", "QcQs"]), &equities, &run);
+        assert!(text.starts_with("Hand  Equity  95% interval"), "{}", text);
+        assert!(
+            text.contains("\nAhKh  15.91%         exact  15.91%  0.00%\n"),
+            "{}",
+            text
+        );
+        assert!(
+            text.ends_with("\nTexas Hold'em, every finish played out\n"),
+            "{}",
+            text
+        );
+
+        let hands = [hand("AKs").unwrap(), hand("QQ").unwrap()];
+        assert!(matches!(hands[0], Hand::Range(_)));
+        let equities = super::equities(&game, &hands, &board, &run).unwrap();
+        let text = render(&game, &labels(&["AKs", "QQ"]), &equities, &run);
+        assert!(
+            text.ends_with("\nTexas Hold'em, 2000 trials, seed 1\n"),
+            "{}",
+            text
+        );
+        let value = json(&game, &labels(&["AKs", "QQ"]), &equities, &run);
+        assert!(value.to_string().contains("\"exact\":false"));
+
+        let mixed = [hand("AhKhQh").unwrap(), hand("QQ").unwrap()];
+        assert!(super::equities(&game, &mixed, &board, &run).is_err());
+        assert!(hand("AKx").is_err());
+    }
+}
@@ -0,0 +1,212 @@
+//! `snapfold eval`: the best hand a set of cards makes.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use rustlib::cards::{parse_cards, Card};
+use rustlib::gamedef::json::Value;
+use rustlib::handeval::HandRank;
+use rustlib::showdown::Scorer;
+use rustlib::{presets, GameStructure};
+
+use crate::args::Args;
+use crate::load::{self, Format};
+use crate::output::{self, Output};
+use crate::{describe, CliError};
+
+pub const USAGE: &str = "\
+usage: snapfold eval CARDS [BOARD...] [--omaha] [--game FILE] [--output FORMAT]
+                     [--format FORMAT]
+
+Prints the best hand that CARDS make with the boards, if any: the cards
+that play, the kind of hand, and the hand in words. Cards are written
+like \"Ah Kh\" or \"AhKh\". Without --omaha or --game the hand is the best
+five of all the cards, by the standard high hand ranking.
+
+Options:
+  --omaha          play exactly two of CARDS and three from a board
+  --game FILE      score the hand as FILE does, once for each of its
+                   scorings (high and low, say)
+  --output FORMAT  text, or json to print the hands as JSON
+  --format FORMAT  read FILE as json, yaml, dsl, binpb or game";
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(argv, &["game", "output", "format"], &["omaha"])?;
+    let Some((hole, boards)) = args.positional().split_first() else {
+        return Err(CliError::Usage("eval needs the cards to rank".to_string()));
+    };
+    let hole = cards(hole)?;
+    let boards = boards
+        .iter()
+        .map(|b| cards(b))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut all: Vec<Card> = hole
+        .iter()
+        .chain(boards.iter().flatten())
+        .copied()
+        .collect();
+    all.sort();
+    if let Some(w) = all.windows(2).find(|w| w[0] == w[1]) {
+        return Err(CliError::Usage(format!("{} is in the cards twice", w[0])));
+    }
+    let game = match (args.value("game"), args.switch("omaha")) {
+        (Some(_), true) => {
+            return Err(CliError::Usage(
+                "pass one of --omaha and --game".to_string(),
+            ))
+        }
+        (Some(path), false) => {
+            load::load_playable(Path::new(path), Format::from_arg(args.value("format"))?)?
+        }
+        (None, true) => presets::omaha(),
+        (None, false) => GameStructure::default(),
+    };
+    let output = Output::from_args(&args)?;
+    let hands = eval(&game, &hole, &boards);
+    match output.is_json() {
+        true => println!("{}", json(&hands).pretty()),
+        false => print!("{}", render(&hands)),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn cards(text: &str) -> Result<Vec<Card>, CliError> {
+    parse_cards(text).map_err(|e| CliError::Usage(format!("can't read {:?}: {}", text, e)))
+}
+
+/// A hand as one of the game's scorings ranks it.
+struct Hand {
+    /// What the scoring is called, if the game has more than one.
+    scoring: Option<String>,
+    best: Option<(HandRank, Vec<Card>)>,
+}
+
+/// The best hand `hole` and `boards` make by each of `game`'s scorings.
+fn eval(game: &GameStructure, hole: &[Card], boards: &[Vec<Card>]) -> Vec<Hand> {
+    let scorers = Scorer::for_game(game);
+    let named = scorers.len() > 1;
+    scorers
+        .iter()
+        .map(|scorer| {
+            let s = scorer.scoring();
+            Hand {
+                scoring: match (s.name.is_empty(), named) {
+                    (false, _) => Some(s.name.clone()),
+                    (true, false) => None,
+                    (true, true) if s.lo => Some("Low".to_string()),
+                    (true, true) => Some("High".to_string()),
+                },
+                best: scorer.best_cards(hole, boards).map(|(rank, mut cards)| {
+                    sort(rank, &mut cards);
+                    (rank, cards)
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Puts the cards of a hand in the order it's read: pairs and sets first,
+/// then highest first, or for a low hand lowest first, with aces low.
+fn sort(rank: HandRank, cards: &mut [Card]) {
+    let ranks: Vec<u8> = cards.iter().map(|c| c.rank).collect();
+    let count = |r| ranks.iter().filter(|&&x| x == r).count();
+    match rank.is_low() {
+        true => cards.sort_by_key(|c| (c.rank + 1) % 13),
+        false => cards.sort_by_key(|c| std::cmp::Reverse((count(c.rank), c.rank))),
+    }
+}
+
+/// The kind of hand `rank` is, such as "flush".
+fn class(rank: HandRank) -> &'static str {
+    match rank {
+        _ if rank.points().is_some() => "points",
+        _ if rank.is_low() && rank.hand_type() == rustlib::HandType::NoPair => "low",
+        _ => describe::hand_type(rank.hand_type()),
+    }
+}
+
+fn card_list(cards: &[Card]) -> String {
+    cards
+        .iter()
+        .map(Card::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A line per hand, such as "Ah Kh Qh Jh Th  straight flush: Royal Flush".
+fn render(hands: &[Hand]) -> String {
+    let mut out = String::new();
+    for hand in hands {
+        if let Some(name) = &hand.scoring {
+            out.push_str(&format!("{}: ", name));
+        }
+        match &hand.best {
+            Some((rank, cards)) => out.push_str(&format!(
+                "{}  {}: {}\n",
+                card_list(cards),
+                class(*rank),
+                rank.describe()
+            )),
+            None => out.push_str("no hand\n"),
+        }
+    }
+    out
+}
+
+/// The hands as JSON: for each scoring, the cards that play, the kind of
+/// hand and its description, or null if the cards make no hand.
+fn json(hands: &[Hand]) -> Value {
+    let hands = hands
+        .iter()
+        .map(|hand| {
+            let mut fields = Vec::new();
+            if let Some(name) = &hand.scoring {
+                fields.push(("scoring".to_string(), Value::String(name.clone())));
+            }
+            let best = match &hand.best {
+                Some((rank, cards)) => Value::Object(vec![
+                    (
+                        "cards".to_string(),
+                        Value::Array(cards.iter().map(|c| Value::String(c.to_string())).collect()),
+                    ),
+                    ("class".to_string(), Value::String(class(*rank).to_string())),
+                    ("description".to_string(), Value::String(rank.describe())),
+                    ("short".to_string(), Value::String(rank.describe_short())),
+                ]),
+                None => Value::Null,
+            };
+            fields.push(("hand".to_string(), best));
+            Value::Object(fields)
+        })
+        .collect();
+    output::document("eval", vec![("hands", Value::Array(hands))])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval() {
+        let hole = parse_cards("AhKh Qh Jh Th 2c 3d").unwrap();
+        let hands = eval(&GameStructure::default(), &hole, &[]);
+        assert_eq!(
+            render(&hands),
+            "Ah Kh Qh Jh Th  straight flush: Royal Flush\n"
+        );
+
+        let hole = parse_cards("Ah Kh Qh Jh").unwrap();
+        let board = vec![parse_cards("2h 7c 8d 9s 3c").unwrap()];
+        let text = render(&eval(&presets::omaha(), &hole, &board));
+        assert_eq!(text, "Ah Kh 9s 8d 7c  high card: High Card, Ace\n");
+
+        let hole = parse_cards("Ah 2d Kh Jh").unwrap();
+        let hands = eval(&presets::omaha_hi_lo(), &hole, &board);
+        let text = render(&hands);
+        assert!(text.contains("\nLo: Ah 2d 3c 7c 8d  low: "), "{}", text);
+        let value = json(&hands).to_string();
+        assert!(value.contains("\"scoring\":\"High\""), "{}", value);
+        assert!(value.contains("\"class\":\"low\""), "{}", value);
+        assert!(text.starts_with("High: 2d 2h Ah 9s 8d  pair: "), "{}", text);
+    }
+}
@@ -0,0 +1,155 @@
+//! `snapfold export`: recorded hands as PokerStars hand histories, for
+//! hand trackers to import.
+
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::UNIX_EPOCH;
+
+use rustlib::fingerprint::sha256;
+use rustlib::gamedef::json::Value;
+use rustlib::history::HandFile;
+use rustlib::pokerstars::{self, Header};
+
+use crate::args::Args;
+use crate::history;
+use crate::output::{self, Output};
+use crate::CliError;
+
+pub const USAGE: &str = "\
+usage: snapfold export FILE... [--seat N] [--table NAME] [--output FORMAT]
+
+Writes hands saved by `snapfold play --record` or `snapfold deal
+--record`, or kept by a server with --history, as PokerStars hand
+histories, the text that trackers such as PokerTracker and Hold'em
+Manager import. Only finished no-limit Hold'em, Omaha and Omaha Hi/Lo
+hands can be written. Each hand is dated when it started if a server
+dealt it, and otherwise when its file was saved, and numbered from a
+hash of the hand, so exporting it again gives it the same number.
+
+Options:
+  --seat N         show seat N's hole cards, as the hero's (default only
+                   the hands shown down)
+  --table NAME     the table's name in the histories (default snapfold)
+  --output FORMAT  text, or json to print each file's history as JSON";
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(argv, &["seat", "table", "output"], &[])?;
+    let output = Output::from_args(&args)?;
+    if args.positional().is_empty() {
+        return Err(CliError::Usage("export needs a hand file".to_string()));
+    }
+    let hero = match args.parsed::<usize>("seat")? {
+        Some(0) => return Err(CliError::Usage("seats count from 1".to_string())),
+        seat => seat.map(|s| s - 1),
+    };
+    let table = args.value("table").unwrap_or("snapfold");
+    let mut hands = Vec::new();
+    for path in args.positional() {
+        let path = Path::new(path);
+        for file in history::read(path)? {
+            let started = match file.time {
+                Some(time) => time.started,
+                None => std::fs::metadata(path)
+                    .and_then(|m| m.modified())
+                    .map_err(|error| CliError::Io {
+                        path: path.to_path_buf(),
+                        error,
+                    })?
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs()),
+            };
+            let history =
+                export(&file, table, started, hero).map_err(|message| CliError::Load {
+                    path: path.to_path_buf(),
+                    source: None,
+                    at: None,
+                    message,
+                })?;
+            hands.push((path, history));
+        }
+    }
+    match output.is_json() {
+        true => {
+            let hands = hands
+                .iter()
+                .map(|(path, history)| {
+                    Value::Object(vec![
+                        (
+                            "file".to_string(),
+                            output::string(path.display().to_string()),
+                        ),
+                        ("history".to_string(), output::string(history.as_str())),
+                    ])
+                })
+                .collect();
+            println!(
+                "{}",
+                output::document("export", vec![("hands", Value::Array(hands))]).pretty()
+            );
+        }
+        // Trackers tell hands apart by the blank lines between them.
+        false => {
+            for (_, history) in &hands {
+                println!("{}", history);
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `file`'s hand as a history, started at `started`, with `hero`'s cards.
+fn export(
+    file: &HandFile,
+    table: &str,
+    started: u64,
+    hero: Option<usize>,
+) -> Result<String, String> {
+    let engine = file
+        .hand
+        .restore(file.game.clone())
+        .map_err(|e| format!("can't play the hand back: {}", e))?;
+    let hash = sha256(file.hand.as_bytes());
+    let hand = u64::from_be_bytes(hash[..8].try_into().expect("8 bytes")) % 1_000_000_000_000;
+    let header = Header {
+        hand,
+        table,
+        started,
+        names: &file.names,
+        hero,
+    };
+    pokerstars::hand_history(&engine, &header).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustlib::engine::{Action, EngineConfig, GameEngine};
+    use rustlib::presets;
+    use rustlib::shuffle::Shuffler;
+
+    #[test]
+    fn test_export() {
+        let config = EngineConfig {
+            shuffler: Shuffler::seeded(3),
+            ..EngineConfig::new(2)
+        };
+        let mut engine = GameEngine::with_config(presets::holdem(), config).unwrap();
+        let names = vec!["Ann".to_string(), "Bo".to_string()];
+        let seat = engine.to_act().unwrap();
+        assert!(export(&HandFile::new(&engine, &names).unwrap(), "t", 0, None).is_err());
+
+        engine.apply_action(seat, Action::Raise(6)).unwrap();
+        engine.apply_action(1 - seat, Action::Fold).unwrap();
+        let file = HandFile::new(&engine, &names).unwrap();
+        let text = export(&file, "home game", 86_400, Some(1 - seat)).unwrap();
+        assert!(text.starts_with("PokerStars Hand #"), "{}", text);
+        assert!(text.contains(": Hold'em No Limit (1/2) - 1970/01/02 00:00:00 UTC\n"));
+        assert!(text.contains("\nTable 'home game' 2-max Seat #"));
+        assert!(text.contains(&format!("\nDealt to {} [", names[1 - seat])));
+        assert!(text.contains(&format!("\n{}: raises 4 to 6\n", names[seat])));
+        assert!(text.contains(&format!("\nUncalled bet (4) returned to {}\n", names[seat])));
+        assert!(text.contains(&format!("\n{} collected 4 from pot\n", names[seat])));
+        // The same hand gets the same number.
+        assert_eq!(export(&file, "home game", 86_400, Some(1 - seat)), Ok(text));
+    }
+}
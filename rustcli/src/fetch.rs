@@ -0,0 +1,356 @@
+//! `snapfold fetch`: downloads a game definition, checks it and saves it.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use reqwest::blocking::Client;
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use reqwest::StatusCode;
+use rustlib::fingerprint::{fingerprint, hex, sha256};
+use rustlib::gamedef::json::Value;
+use rustlib::validation;
+
+use crate::args::Args;
+use crate::load::{self, Format};
+use crate::output::{self, Output};
+use crate::CliError;
+
+pub const USAGE: &str = "\
+usage: snapfold fetch URL|NAME [--verify-fingerprint HASH] [--out PATH]
+                               [--registry URL] [--cache DIR] [--no-cache]
+                               [--format FORMAT] [--output FORMAT]
+
+Downloads the game definition at URL, or the one called NAME (such as
+omaha.json) in the registry, checks that it loads and can be played, and
+saves it as it was downloaded. Downloads are cached, and fetching the same
+URL again asks the server for the file only if it has changed since (by
+its ETag).
+
+Options:
+  --verify-fingerprint HASH
+                   fail unless the game's fingerprint, the SHA-256 of its
+                   rules, is HASH (in hex); nothing is saved if it isn't
+  --out PATH       where to save the game (default the last part of URL,
+                   in the current directory)
+  --registry URL   the URL that names are found under, usually set in the
+                   config file
+  --cache DIR      keep downloads in DIR (default $SNAPFOLD_CACHE, or
+                   snapfold/games in the user's cache directory)
+  --no-cache       always download the whole file, and don't cache it
+  --format FORMAT  read the game as json, yaml, dsl, binpb or game, if
+                   URL doesn't end in its extension
+  --output FORMAT  text, or json to print what was saved as JSON";
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(
+        argv,
+        &[
+            "verify-fingerprint",
+            "out",
+            "registry",
+            "cache",
+            "format",
+            "output",
+        ],
+        &["no-cache"],
+    )?;
+    let output = Output::from_args(&args)?;
+    let [url] = args.positional() else {
+        return Err(CliError::Usage("fetch needs the URL of a game".to_string()));
+    };
+    let url = &match args.value("registry") {
+        Some(registry) if !url.contains("://") => {
+            format!("{}/{}", registry.trim_end_matches('/'), url)
+        }
+        _ => url.clone(),
+    };
+    let Some(rest) = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+    else {
+        return Err(CliError::Usage(format!(
+            "{:?} isn't an http or https URL, or a name in a --registry",
+            url
+        )));
+    };
+    // The last part of the URL's path, without any query or fragment.
+    let name = rest
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.split_once('/'))
+        .and_then(|(_, path)| path.rsplit('/').next())
+        .filter(|name| !name.is_empty());
+    let format = match Format::from_arg(args.value("format"))? {
+        Some(format) => format,
+        None => name.and_then(|n| Format::of(Path::new(n))).ok_or_else(|| {
+            CliError::Usage(format!(
+                "can't tell the format of {} from its extension; pass --format ({})",
+                url,
+                Format::NAMES
+            ))
+        })?,
+    };
+    let out = match (args.value("out"), name) {
+        (Some(out), _) => PathBuf::from(out),
+        (None, Some(name)) => PathBuf::from(name),
+        (None, None) => {
+            return Err(CliError::Usage(format!(
+                "{} doesn't end in a file name; pass --out",
+                url
+            )))
+        }
+    };
+    let cache = match args.switch("no-cache") {
+        true => None,
+        false => Some(Cache::new(match args.value("cache") {
+            Some(dir) => PathBuf::from(dir),
+            None => Cache::default_dir().ok_or_else(|| {
+                CliError::Usage("no cache directory; pass --cache or --no-cache".to_string())
+            })?,
+        })),
+    };
+
+    let failed = |message: String| CliError::Load {
+        path: PathBuf::from(url),
+        source: None,
+        at: None,
+        message,
+    };
+    let download = download(url, cache.as_ref(), |etag| get(url, etag)).map_err(failed)?;
+    let source = Path::new(url.as_str());
+    let game = load::parse(source, download.bytes.clone(), format)?;
+    validation::validate(&game).map_err(|errors| CliError::Invalid {
+        path: source.to_path_buf(),
+        errors,
+    })?;
+    let print = hex(&fingerprint(&game));
+    if let Some(want) = args.value("verify-fingerprint") {
+        if !want.trim().eq_ignore_ascii_case(&print) {
+            return Err(failed(format!(
+                "the game's fingerprint is {}, not {}",
+                print, want
+            )));
+        }
+    }
+    if let (Some(cache), false) = (&cache, download.cached) {
+        cache.store(url, &download)?;
+    }
+    std::fs::write(&out, &download.bytes).map_err(|error| CliError::Io {
+        path: out.clone(),
+        error,
+    })?;
+    match output.is_json() {
+        true => println!("{}", json(url, &out, &print, &download).pretty()),
+        false => println!(
+            "saved {} to {}{}\nfingerprint {}",
+            game.id,
+            out.display(),
+            match download.cached {
+                true => ", unchanged since it was cached",
+                false => "",
+            },
+            print
+        ),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// What the server sent back.
+#[derive(Debug)]
+enum Fetched {
+    /// The file hasn't changed since the ETag asked about.
+    NotModified,
+    Body {
+        bytes: Vec<u8>,
+        etag: Option<String>,
+    },
+}
+
+/// Asks for `url`, only if it no longer has `etag` when that's given.
+fn get(url: &str, etag: Option<&str>) -> Result<Fetched, String> {
+    let client = Client::builder()
+        .user_agent("snapfold")
+        .build()
+        .map_err(|e| e.to_string())?;
+    let mut request = client.get(url);
+    if let Some(etag) = etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    let response = request
+        .send()
+        .map_err(|e| format!("can't download it: {}", e))?;
+    match response.status() {
+        StatusCode::NOT_MODIFIED => Ok(Fetched::NotModified),
+        status if status.is_success() => {
+            let etag = response
+                .headers()
+                .get(ETAG)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let bytes = response
+                .bytes()
+                .map_err(|e| format!("can't download it: {}", e))?;
+            Ok(Fetched::Body {
+                bytes: bytes.to_vec(),
+                etag,
+            })
+        }
+        status => Err(format!("the server answered {}", status)),
+    }
+}
+
+/// A downloaded file, and whether it came from the cache.
+#[derive(Debug)]
+struct Download {
+    bytes: Vec<u8>,
+    etag: Option<String>,
+    cached: bool,
+}
+
+/// Gets `url` with `get`, which is passed the ETag of the cached copy if
+/// there is one, and falls back to that copy if the server says it's
+/// still current.
+fn download(
+    url: &str,
+    cache: Option<&Cache>,
+    get: impl FnOnce(Option<&str>) -> Result<Fetched, String>,
+) -> Result<Download, String> {
+    let cached = cache.and_then(|c| c.load(url));
+    let etag = cached.as_ref().and_then(|c| c.etag.as_deref());
+    match get(etag)? {
+        Fetched::Body { bytes, etag } => Ok(Download {
+            bytes,
+            etag,
+            cached: false,
+        }),
+        Fetched::NotModified => cached.ok_or_else(|| {
+            "the server said the file hasn't changed, but it isn't cached".to_string()
+        }),
+    }
+}
+
+/// Downloaded files, each kept under the hash of its URL with the ETag it
+/// was sent with beside it.
+struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    fn new(dir: PathBuf) -> Self {
+        Cache { dir }
+    }
+
+    /// `$SNAPFOLD_CACHE`, or `snapfold/games` in `$XDG_CACHE_HOME` or
+    /// `~/.cache`.
+    fn default_dir() -> Option<PathBuf> {
+        let var = |name| std::env::var_os(name).filter(|v| !v.is_empty());
+        if let Some(dir) = var("SNAPFOLD_CACHE") {
+            return Some(PathBuf::from(dir));
+        }
+        let base = match var("XDG_CACHE_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(var("HOME")?).join(".cache"),
+        };
+        Some(base.join("snapfold").join("games"))
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(hex(&sha256(url.as_bytes())))
+    }
+
+    /// The cached copy of `url`, if any. A copy that can't be read is as
+    /// good as none, since it can be downloaded again.
+    fn load(&self, url: &str) -> Option<Download> {
+        let path = self.path(url);
+        let bytes = std::fs::read(&path).ok()?;
+        let etag = std::fs::read_to_string(path.with_extension("etag")).ok();
+        Some(Download {
+            bytes,
+            etag,
+            cached: true,
+        })
+    }
+
+    fn store(&self, url: &str, download: &Download) -> Result<(), CliError> {
+        let path = self.path(url);
+        let io = |path: &Path| {
+            let path = path.to_path_buf();
+            move |error| CliError::Io { path, error }
+        };
+        std::fs::create_dir_all(&self.dir).map_err(io(&self.dir))?;
+        std::fs::write(&path, &download.bytes).map_err(io(&path))?;
+        let etag = path.with_extension("etag");
+        match &download.etag {
+            Some(tag) => std::fs::write(&etag, tag).map_err(io(&etag)),
+            None => match std::fs::remove_file(&etag) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(io(&etag)(e)),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+fn json(url: &str, path: &Path, print: &str, download: &Download) -> Value {
+    output::document(
+        "fetch",
+        vec![
+            ("url", output::string(url)),
+            ("path", output::string(path.display().to_string())),
+            ("fingerprint", output::string(print)),
+            ("cached", Value::Bool(download.cached)),
+            (
+                "etag",
+                download.etag.as_deref().map_or(Value::Null, output::string),
+            ),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_download() {
+        let dir = std::env::temp_dir().join(format!("snapfold-fetch-{}", std::process::id()));
+        let cache = Cache::new(dir.clone());
+        let url = "https://example.com/games/holdem.json";
+        let body = |etag: Option<&str>| Fetched::Body {
+            bytes: b"{}".to_vec(),
+            etag: etag.map(str::to_string),
+        };
+
+        let first = download(url, Some(&cache), |etag| {
+            assert_eq!(etag, None);
+            Ok(body(Some("\"v1\"")))
+        })
+        .unwrap();
+        assert!(!first.cached);
+        cache.store(url, &first).unwrap();
+
+        // Again, the cached ETag is sent, and the cached copy used when
+        // the server says it's current.
+        let again = download(url, Some(&cache), |etag| {
+            assert_eq!(etag, Some("\"v1\""));
+            Ok(Fetched::NotModified)
+        })
+        .unwrap();
+        assert!(again.cached);
+        assert_eq!(again.bytes, b"{}");
+
+        // Without an ETag, the file is cached but downloaded every time.
+        let unversioned = Download {
+            bytes: b"{}".to_vec(),
+            etag: None,
+            cached: false,
+        };
+        cache.store(url, &unversioned).unwrap();
+        download(url, Some(&cache), |etag| {
+            assert_eq!(etag, None);
+            Ok(body(None))
+        })
+        .unwrap();
+        assert!(download(url, None, |_| Ok(Fetched::NotModified)).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,72 @@
+//! Hand files on disk, as `snapfold play --record` and `snapfold deal
+//! --record` save them and `snapfold replay` and `snapfold export` read
+//! them. The format is [`rustlib::history`]'s, shared with the server's
+//! history, so a hand the server kept plays back here too.
+
+use std::path::Path;
+
+use rustlib::history::HandFile;
+
+use crate::CliError;
+
+/// The hands in the file at `path`: the one a `--record` option saved, or
+/// those a server's history holds, one per line.
+pub fn read(path: &Path) -> Result<Vec<HandFile>, CliError> {
+    let text = std::fs::read_to_string(path).map_err(|error| CliError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    let error = |at, message| CliError::Load {
+        path: path.to_path_buf(),
+        source: None,
+        at,
+        message,
+    };
+    let lines: Vec<(usize, &str)> = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .collect();
+    match HandFile::parse(&text) {
+        Ok(file) => Ok(vec![file]),
+        Err(message) if lines.len() < 2 => Err(error(None, message)),
+        Err(_) => lines
+            .into_iter()
+            .map(|(n, line)| HandFile::parse(line).map_err(|m| error(Some((n + 1, 1)), m)))
+            .collect(),
+    }
+}
+
+pub fn write(file: &HandFile, path: &Path) -> Result<(), CliError> {
+    std::fs::write(path, file.to_json().pretty() + "\n").map_err(|error| CliError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustlib::engine::GameEngine;
+    use rustlib::presets;
+
+    #[test]
+    fn test_read() {
+        let path = std::env::temp_dir().join(format!("snapfold-hands-{}.json", std::process::id()));
+        let engine = GameEngine::new(presets::holdem(), 2).unwrap();
+        let file = HandFile::new(&engine, &["a".to_string(), "b".to_string()]).unwrap();
+        write(&file, &path).unwrap();
+        assert_eq!(read(&path).unwrap(), std::slice::from_ref(&file));
+
+        // A server's history has a hand on each line.
+        let line = file.to_json().to_string();
+        std::fs::write(&path, format!("{}\n\n{}\n", line, line)).unwrap();
+        assert_eq!(read(&path).unwrap(), [file.clone(), file]);
+        std::fs::write(&path, format!("{}\n[]\n", line)).unwrap();
+        match read(&path) {
+            Err(CliError::Load { at, .. }) => assert_eq!(at, Some((2, 1))),
+            other => panic!("{:?}", other.map(|hands| hands.len())),
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,253 @@
+//! Just enough HTTP/1.1 for `snapfold serve` and `snapfold registry`: one
+//! request per connection, with a body only if it has a `Content-Length`.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::diag::{Diagnostic, Level};
+
+/// The most a request's line, headers or body may take up.
+const LIMIT: usize = 1 << 20;
+
+/// How long a connection may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The most connections answered at once. Past that, the oldest is waited
+/// for before another is accepted.
+const MAX_CONNECTIONS: usize = 64;
+
+/// What a server answers requests with.
+pub trait Handler: Send + Sync + 'static {
+    /// The response to `request`, from `peer` if known.
+    fn respond(&self, request: &Request, peer: Option<SocketAddr>) -> Response;
+
+    /// Whether to stop accepting connections.
+    fn stopping(&self) -> bool {
+        false
+    }
+}
+
+/// Answers each connection to `listener` on a thread of its own until
+/// `handler` says to stop, then waits for the ones still open.
+pub fn serve<H: Handler>(handler: Arc<H>, listener: TcpListener) {
+    let addr = listener.local_addr().ok();
+    let mut workers: VecDeque<JoinHandle<()>> = VecDeque::new();
+    for stream in listener.incoming() {
+        if handler.stopping() {
+            break;
+        }
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                Diagnostic::new(Level::Warning, format!("can't accept a connection: {}", e)).emit();
+                continue;
+            }
+        };
+        workers.retain(|w| !w.is_finished());
+        if workers.len() >= MAX_CONNECTIONS {
+            if let Some(oldest) = workers.pop_front() {
+                let _ = oldest.join();
+            }
+        }
+        let handler = Arc::clone(&handler);
+        workers.push_back(thread::spawn(move || connection(&*handler, stream, addr)));
+    }
+    for worker in workers {
+        let _ = worker.join();
+    }
+}
+
+/// Answers the request on `stream`. `addr` is where the server listens.
+fn connection(handler: &impl Handler, stream: TcpStream, addr: Option<SocketAddr>) {
+    let peer = stream.peer_addr().ok();
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let mut reader = BufReader::new(&stream);
+    let response = match Request::read(&mut reader) {
+        Ok(Some(request)) => handler.respond(&request, peer),
+        Ok(None) => return,
+        Err(e) => Response::text(400, format!("{}\n", e)),
+    };
+    let _ = response.write(&mut &stream);
+    let _ = stream.shutdown(Shutdown::Both);
+    if let (true, Some(addr)) = (handler.stopping(), addr) {
+        // Wake the accept loop, so it sees it's time to stop.
+        let _ = TcpStream::connect(addr);
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    /// The path, without the query string.
+    pub path: String,
+    pub query: String,
+    /// Header names are lowercased.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Reads a request from `reader`, or `None` if the connection closed
+    /// before sending one.
+    pub fn read(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+        let mut line = String::new();
+        if reader.take(LIMIT as u64).read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(target), Some(_version)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid(format!("bad request line {:?}", line.trim_end())));
+        };
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let mut request = Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: query.to_string(),
+            ..Request::default()
+        };
+        let mut read = line.len();
+        loop {
+            line.clear();
+            read += reader.take((LIMIT - read) as u64).read_line(&mut line)?;
+            let header = line.trim_end_matches(['\r', '\n']);
+            if header.is_empty() {
+                break;
+            }
+            if read >= LIMIT {
+                return Err(invalid("headers too long".to_string()));
+            }
+            let (name, value) = header
+                .split_once(':')
+                .ok_or_else(|| invalid(format!("bad header {:?}", header)))?;
+            request
+                .headers
+                .push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+        if let Some(length) = request.header("content-length") {
+            let length: usize = length
+                .parse()
+                .map_err(|_| invalid(format!("bad Content-Length {:?}", length)))?;
+            if length > LIMIT {
+                return Err(invalid("body too long".to_string()));
+            }
+            request.body = vec![0; length];
+            reader.read_exact(&mut request.body)?;
+        }
+        Ok(Some(request))
+    }
+
+    /// The value of the header `name`, given in lowercase.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    /// Headers besides the content type, length and connection.
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn json(status: u16, body: String) -> Self {
+        Response {
+            status,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body: body.into_bytes(),
+        }
+    }
+
+    pub fn text(status: u16, body: impl Into<String>) -> Self {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            headers: Vec::new(),
+            body: body.into().into_bytes(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    /// Writes the response and says the connection will close.
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(
+            out,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n",
+            self.status,
+            reason(self.status),
+            self.content_type,
+            self.body.len()
+        )?;
+        for (name, value) in &self.headers {
+            write!(out, "{}: {}\r\n", name, value)?;
+        }
+        write!(out, "Connection: close\r\n\r\n")?;
+        out.write_all(&self.body)?;
+        out.flush()
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_and_response() {
+        let raw = "POST /games/holdem?x=1 HTTP/1.1\r\nHost: localhost\r\n\
+                   Content-Length: 4\r\n\r\nbodyleft over";
+        let request = Request::read(&mut raw.as_bytes()).unwrap().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/games/holdem");
+        assert_eq!(request.query, "x=1");
+        assert_eq!(request.header("host"), Some("localhost"));
+        assert_eq!(request.body, b"body");
+        assert_eq!(Request::read(&mut "".as_bytes()).unwrap(), None);
+        assert!(Request::read(&mut "nonsense\r\n\r\n".as_bytes()).is_err());
+
+        let mut out = Vec::new();
+        Response::text(404, "no").write(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "HTTP/1.1 404 Not Found\r\nContent-Type: text/plain; charset=utf-8\r\n\
+             Content-Length: 2\r\nConnection: close\r\n\r\nno"
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Reading game definitions from files, in any of the formats the library
+//! reads.
+
+use std::path::Path;
+
+use prost::Message;
+use rustlib::gamedef;
+use rustlib::{validation, GameStructure};
+
+use crate::CliError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Canonical protobuf JSON.
+    Json,
+    Yaml,
+    /// The text DSL of [`gamedef::dsl`].
+    Dsl,
+    /// A bare binary proto.
+    Proto,
+    /// A framed binary proto with a checksum, as [`gamedef::GameFile`] writes.
+    File,
+}
+
+impl Format {
+    /// The names `--format` takes.
+    pub const NAMES: &'static str = "json, yaml, dsl, binpb or game";
+
+    /// The format named `name`, which is also the file extension it's
+    /// recognized by.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "dsl" => Some(Format::Dsl),
+            "binpb" | "pb" => Some(Format::Proto),
+            "game" => Some(Format::File),
+            _ => None,
+        }
+    }
+
+    /// The format of `path`, going by its extension.
+    pub fn of(path: &Path) -> Option<Self> {
+        Self::from_name(path.extension()?.to_str()?)
+    }
+
+    /// `--format` from the command line, if given.
+    pub fn from_arg(arg: Option<&str>) -> Result<Option<Self>, CliError> {
+        arg.map(|name| {
+            Self::from_name(name).ok_or_else(|| {
+                CliError::Usage(format!(
+                    "unknown format {:?}; expected {}",
+                    name,
+                    Self::NAMES
+                ))
+            })
+        })
+        .transpose()
+    }
+}
+
+/// Reads the game at `path`, in `format` or else the format its extension
+/// names.
+pub fn load(path: &Path, format: Option<Format>) -> Result<GameStructure, CliError> {
+    let format = format.or_else(|| Format::of(path)).ok_or_else(|| {
+        CliError::Usage(format!(
+            "can't tell the format of {} from its extension; pass --format ({})",
+            path.display(),
+            Format::NAMES
+        ))
+    })?;
+    let bytes = match format {
+        Format::Proto | Format::File => return decode(path, &read_message(path, format)?),
+        _ => std::fs::read(path).map_err(|error| CliError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?,
+    };
+    parse(path, bytes, format)
+}
+
+/// Parses `bytes` as a game in `format`. `path` says where they came from,
+/// for errors.
+pub fn parse(path: &Path, bytes: Vec<u8>, format: Format) -> Result<GameStructure, CliError> {
+    let text = match format {
+        Format::Proto => return decode(path, &bytes),
+        Format::File => {
+            let message =
+                gamedef::file::unframe_message(&bytes).map_err(|e| failed(path, e.to_string()))?;
+            return decode(path, message);
+        }
+        _ => String::from_utf8(bytes).map_err(|_| failed(path, "isn't UTF-8 text".to_string()))?,
+    };
+    let parsed = match format {
+        Format::Json => gamedef::from_json(&text),
+        Format::Yaml => gamedef::from_yaml(&text),
+        _ => gamedef::from_dsl(&text),
+    };
+    parsed.map_err(|e| {
+        let (at, message) = match e {
+            gamedef::Error::Syntax(e) => (Some((e.line, e.column)), e.message),
+            gamedef::Error::FieldAt {
+                line,
+                column,
+                error,
+            } => (Some((line, column)), error.to_string()),
+            gamedef::Error::Field(error) => (None, error.to_string()),
+        };
+        CliError::Load {
+            path: path.to_path_buf(),
+            source: Some(text),
+            at,
+            message,
+        }
+    })
+}
+
+fn decode(path: &Path, message: &[u8]) -> Result<GameStructure, CliError> {
+    GameStructure::decode(message).map_err(|e| failed(path, e.to_string()))
+}
+
+/// The encoded `GameStructure` in `path`, a binary [`Format::Proto`] or
+/// [`Format::File`], byte for byte.
+pub fn read_message(path: &Path, format: Format) -> Result<Vec<u8>, CliError> {
+    let mut bytes = std::fs::read(path).map_err(|error| CliError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    if format == Format::File {
+        bytes = gamedef::file::unframe_message(&bytes)
+            .map_err(|e| failed(path, e.to_string()))?
+            .to_vec();
+    }
+    Ok(bytes)
+}
+
+fn failed(path: &Path, message: String) -> CliError {
+    CliError::Load {
+        path: path.to_path_buf(),
+        source: None,
+        at: None,
+        message,
+    }
+}
+
+/// As [`load`], failing if the game doesn't pass validation.
+pub fn load_playable(path: &Path, format: Option<Format>) -> Result<GameStructure, CliError> {
+    let game = load(path, format)?;
+    validation::validate(&game).map_err(|errors| CliError::Invalid {
+        path: path.to_path_buf(),
+        errors,
+    })?;
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `contents` to a fresh file named `name` in the temp
+    /// directory.
+    pub(crate) fn temp_file(name: &str, contents: &[u8]) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("snapfold-cli-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(Format::of(Path::new("a/holdem.YML")), Some(Format::Yaml));
+        assert_eq!(Format::of(Path::new("holdem.game")), Some(Format::File));
+        assert_eq!(Format::of(Path::new("holdem")), None);
+        assert!(Format::from_arg(Some("toml")).is_err());
+        assert_eq!(
+            Format::from_arg(Some("binpb")).unwrap(),
+            Some(Format::Proto)
+        );
+    }
+
+    #[test]
+    fn test_load() {
+        let holdem = rustlib::presets::holdem();
+        let json = temp_file("holdem.json", gamedef::to_json(&holdem).as_bytes());
+        assert_eq!(load(&json, None).unwrap(), holdem);
+        let dsl = temp_file("holdem.txt", gamedef::to_dsl(&holdem).as_bytes());
+        assert!(matches!(load(&dsl, None), Err(CliError::Usage(_))));
+        assert_eq!(load(&dsl, Some(Format::Dsl)).unwrap(), holdem);
+
+        let bad = temp_file("bad.yaml", b"id: holdem\nphases:\n  - bogus: 1\n");
+        match load(&bad, None) {
+            Err(CliError::Load { at, source, .. }) => {
+                assert_eq!(at.map(|(line, _)| line), Some(3));
+                assert!(source.is_some());
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(matches!(
+            load(Path::new("/nonexistent/game.json"), None),
+            Err(CliError::Io { .. })
+        ));
+    }
+}
@@ -1,23 +1,182 @@
-use rustlib;
+//! `snapfold`: a command-line tool for game definitions.
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("Hello from the Rust CLI!");
-    println!("Built with Bazel");
-    println!("{}", rustlib::get_greeting("Rust CLI"));
+mod args;
+mod bots;
+mod completions;
+mod config;
+mod convert;
+mod deal;
+mod describe;
+mod diag;
+mod diff;
+mod equity;
+mod eval;
+mod export;
+mod fetch;
+mod history;
+mod http;
+mod load;
+mod new;
+mod output;
+mod play;
+mod registry;
+mod replay;
+mod serve;
+mod simulate;
+mod table;
+mod tournament;
+mod validate;
 
-    // Simple example showing reqwest is available
-    let url = "https://httpbin.org/get";
-    println!("\nMaking a GET request to {}...", url);
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitCode;
 
-    let response = reqwest::get(url).await?;
-    let status = response.status();
+use rustlib::validation::ValidationError;
 
-    println!("Response status: {}", status);
+const USAGE: &str = "\
+usage: snapfold COMMAND [ARGS...]
 
-    if status.is_success() {
-        println!("Request successful!");
+Commands:
+  validate FILE...   check game definitions and lint them for mistakes
+  simulate FILE      play many hands between bots and sum up the results
+  tournament FILE    play a tournament between bots down to a winner
+  play FILE          play the game at the terminal against bots
+  convert FILE       rewrite a game definition in another format
+  describe FILE      print the rules of a game
+  diff OLD NEW       show what changed between two game definitions
+  deal FILE          deal a hand and show every card
+  replay FILE        play back a recorded hand
+  export FILE...     write recorded hands as PokerStars hand histories
+  new ID             start a game definition from a standard game
+  eval CARDS         print the best hand some cards make
+  equity HAND...     work out how often each hand or range wins
+  fetch URL          download a game definition and check it
+  serve              host a directory of game definitions over HTTP
+  registry           run a server that game definitions can be uploaded to
+  completions SHELL  print a script that completes commands in SHELL
+  help [COMMAND]     show this message, or a command's usage
+
+Every command takes --output json to print JSON for other programs to
+read, in a shape named by its schema field; diagnostics stay on stderr.
+
+Defaults for any option can be set in ~/.config/snapfold/config.toml (or
+the file $SNAPFOLD_CONFIG names): `seed = 7` at the top of the file for
+every command that takes --seed, or under a [simulate] table for one.
+
+Game definitions are read as JSON, YAML, the text DSL, a binary proto or
+a framed game file, going by the extension (.json, .yaml, .dsl, .binpb,
+.game) unless --format says otherwise.";
+
+/// Why a command couldn't run. Each is reported on stderr.
+#[derive(Debug)]
+pub enum CliError {
+    /// The command line is wrong.
+    Usage(String),
+    /// A file couldn't be read or written.
+    Io { path: PathBuf, error: io::Error },
+    /// A file was read but isn't a game definition.
+    Load {
+        path: PathBuf,
+        /// The text of the file, if it is text.
+        source: Option<String>,
+        /// The 1-based line and column of the problem, if known.
+        at: Option<(usize, usize)>,
+        message: String,
+    },
+    /// A game definition loaded but can't be played.
+    Invalid {
+        path: PathBuf,
+        errors: Vec<ValidationError>,
+    },
+}
+
+/// The usage of each command.
+fn usage(command: &str) -> Option<&'static str> {
+    match command {
+        "validate" => Some(validate::USAGE),
+        "simulate" => Some(simulate::USAGE),
+        "tournament" => Some(tournament::USAGE),
+        "play" => Some(play::USAGE),
+        "convert" => Some(convert::USAGE),
+        "describe" => Some(describe::USAGE),
+        "diff" => Some(diff::USAGE),
+        "deal" => Some(deal::USAGE),
+        "replay" => Some(replay::USAGE),
+        "export" => Some(export::USAGE),
+        "new" => Some(new::USAGE),
+        "eval" => Some(eval::USAGE),
+        "equity" => Some(equity::USAGE),
+        "fetch" => Some(fetch::USAGE),
+        "serve" => Some(serve::USAGE),
+        "registry" => Some(registry::USAGE),
+        "completions" => Some(completions::USAGE),
+        _ => None,
     }
+}
 
+/// Loads the config file's defaults for `command`.
+fn apply_config(command: &str) -> Result<(), CliError> {
+    if usage(command).is_none() {
+        return Ok(());
+    }
+    if let Some(path) = config::Config::path() {
+        config::Config::load(&path)?.apply(command);
+    }
     Ok(())
 }
+
+fn main() -> ExitCode {
+    let argv: Vec<String> = std::env::args().skip(1).collect();
+    let Some((command, rest)) = argv.split_first() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    };
+    if rest.iter().any(|a| a == "--help" || a == "-h") {
+        if let Some(text) = usage(command) {
+            println!("{}", text);
+            return ExitCode::SUCCESS;
+        }
+    }
+    if let Err(e) = apply_config(command) {
+        diag::report(&e);
+        return ExitCode::from(2);
+    }
+    let result = match command.as_str() {
+        "validate" => validate::run(rest),
+        "simulate" => simulate::run(rest),
+        "tournament" => tournament::run(rest),
+        "play" => play::run(rest),
+        "convert" => convert::run(rest),
+        "describe" => describe::run(rest),
+        "diff" => diff::run(rest),
+        "deal" => deal::run(rest),
+        "replay" => replay::run(rest),
+        "export" => export::run(rest),
+        "new" => new::run(rest),
+        "eval" => eval::run(rest),
+        "equity" => equity::run(rest),
+        "fetch" => fetch::run(rest),
+        "serve" => serve::run(rest),
+        "registry" => registry::run(rest),
+        "completions" => completions::run(rest),
+        "help" | "--help" | "-h" => match rest.first().map(|c| (c, usage(c))) {
+            None => {
+                println!("{}", USAGE);
+                Ok(ExitCode::SUCCESS)
+            }
+            Some((_, Some(text))) => {
+                println!("{}", text);
+                Ok(ExitCode::SUCCESS)
+            }
+            Some((c, None)) => Err(CliError::Usage(format!("unknown command {:?}", c))),
+        },
+        _ => Err(CliError::Usage(format!("unknown command {:?}", command))),
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            diag::report(&e);
+            ExitCode::from(2)
+        }
+    }
+}
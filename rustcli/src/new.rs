@@ -0,0 +1,311 @@
+//! `snapfold new`: writes a starting game definition from a standard game.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use rustlib::gamedef::json::Value;
+use rustlib::{gamedef, presets, GameStructure};
+
+use crate::args::Args;
+use crate::load::Format;
+use crate::output::{self, Output};
+use crate::CliError;
+
+pub const USAGE: &str = "\
+usage: snapfold new ID [--template GAME] [--to FORMAT] [--out PATH]
+                       [--force] [--output FORMAT]
+
+Writes a game definition called ID to start a new game from, a copy of a
+standard game with a comment on each field saying what it does. Edit it,
+then check it with `snapfold validate` and read it back with `snapfold
+describe`.
+
+Options:
+  --template GAME  the game to start from: holdem (the default), omaha,
+                   omaha-hilo, stud, stud-hilo, razz, draw, triple-draw,
+                   badugi or short-deck
+  --to FORMAT      yaml (the default), dsl or json; JSON can't hold
+                   comments, so that file has none
+  --out PATH       where to write the game (default ID.FORMAT)
+  --force          overwrite PATH if it already exists
+  --output FORMAT  text, or json to print what was written as JSON";
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(argv, &["template", "to", "out", "output"], &["force"])?;
+    let output = Output::from_args(&args)?;
+    let [id] = args.positional() else {
+        return Err(CliError::Usage("new needs the id of the game".to_string()));
+    };
+    if id.is_empty() || !id.chars().all(|c| c.is_alphanumeric() || "-_.".contains(c)) {
+        return Err(CliError::Usage(format!(
+            "{:?} can't be a game id: use letters, digits, '-', '_' and '.'",
+            id
+        )));
+    }
+    let template = args.value("template").unwrap_or("holdem");
+    let Some(game) = presets::by_name(template) else {
+        return Err(CliError::Usage(format!(
+            "no standard game called {:?}; expected one of {}",
+            template,
+            presets::NAMES.join(", ")
+        )));
+    };
+    let to = Format::from_arg(args.value("to"))?.unwrap_or(Format::Yaml);
+    let extension = match to {
+        Format::Yaml => "yaml",
+        Format::Dsl => "dsl",
+        Format::Json => "json",
+        Format::Proto | Format::File => {
+            return Err(CliError::Usage(
+                "a new game is written as yaml, dsl or json, to be edited".to_string(),
+            ))
+        }
+    };
+    let path = match args.value("out") {
+        Some(out) => PathBuf::from(out),
+        None => PathBuf::from(format!("{}.{}", id, extension)),
+    };
+    if path.exists() && !args.switch("force") {
+        return Err(CliError::Usage(format!(
+            "{} already exists; pass --force to overwrite it",
+            path.display()
+        )));
+    }
+    let text = scaffold(&start(game, id), to);
+    std::fs::write(&path, text).map_err(|error| CliError::Io {
+        path: path.clone(),
+        error,
+    })?;
+    match output.is_json() {
+        true => println!("{}", json(id, template, to, &path).pretty()),
+        false => println!("wrote {}, a copy of {}", path.display(), template),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `template` renamed to `id`, with nothing else changed.
+fn start(mut template: GameStructure, id: &str) -> GameStructure {
+    let based_on = template.name;
+    template.id = id.to_string();
+    template.name = id.to_string();
+    if template.description.is_empty() {
+        template.description = format!("A game based on {}.", based_on);
+    }
+    template
+}
+
+/// `game` written as `to`, with comments on the first use of each field.
+fn scaffold(game: &GameStructure, to: Format) -> String {
+    match to {
+        Format::Yaml => annotate(&gamedef::to_yaml(game), yaml_key, YAML_COMMENTS),
+        Format::Dsl => annotate(&gamedef::to_dsl(game), dsl_statement, DSL_COMMENTS),
+        _ => gamedef::to_json(game),
+    }
+}
+
+/// Puts a `#` comment above the first line of `text` that each entry of
+/// `comments` applies to, as found by `key`, at that line's indent.
+fn annotate(text: &str, key: fn(&str) -> Option<&str>, comments: &[(&str, &str)]) -> String {
+    let mut out = String::from(HEADER);
+    let mut seen = Vec::new();
+    for line in text.lines() {
+        let body = line.trim_start();
+        let comment = key(body)
+            .filter(|k| !seen.contains(k))
+            .and_then(|k| comments.iter().find(|(name, _)| *name == k));
+        if let Some((name, comment)) = comment {
+            seen.push(*name);
+            let indent = &line[..line.len() - body.len()];
+            for part in comment.lines() {
+                out.push_str(&format!("{}# {}\n", indent, part));
+            }
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// The key a YAML line sets, such as `minBet` for `  - minBet: 1`.
+fn yaml_key(line: &str) -> Option<&str> {
+    let line = line.strip_prefix("- ").unwrap_or(line);
+    line.split_once(':').map(|(key, _)| key)
+}
+
+/// The keyword a DSL statement starts with, such as `bet`.
+fn dsl_statement(line: &str) -> Option<&str> {
+    line.split_whitespace().next()
+}
+
+const HEADER: &str = "\
+# A new game, copied from a standard one. Change what you like, then run
+# `snapfold validate` on this file to check that the game can be played.
+";
+
+const YAML_COMMENTS: &[(&str, &str)] = &[
+    ("id", "A short name for the game, used to refer to it."),
+    ("name", "The name players see."),
+    ("description", "A sentence or two about the game."),
+    (
+        "standardDeck",
+        "DECK_POKER for 52 cards, or DECK_SHORT_DECK for the sixes up.",
+    ),
+    (
+        "phases",
+        "What happens in a hand, in order. Each phase deals, bets, draws, or\n\
+         does one other thing.",
+    ),
+    ("ante", "Every player puts in a forced bet before the deal."),
+    (
+        "payer",
+        "Who pays the ante: PAYER_EVERY_PLAYER or PAYER_BIG_BLIND.",
+    ),
+    ("playerDeal", "Deal cards to each player."),
+    ("cards", "How many cards."),
+    ("faceUp", "Deal them face up, for everyone to see."),
+    (
+        "faceUpCards",
+        "Face up (true) or down (false) for each card, to mix the two.",
+    ),
+    ("communityDeal", "Deal shared cards to the board."),
+    ("burn", "Cards dealt away face down before the deal."),
+    ("bettingRound", "A round of betting."),
+    ("minBet", "The smallest bet or raise, in chips."),
+    (
+        "order",
+        "Who acts first: BETTING_ORDER_FOLLOW_BLINDS (left of the big blind),\n\
+         BETTING_ORDER_LEFT_OF_DEALER, or BETTING_ORDER_BEST_FACEUP.",
+    ),
+    (
+        "bringIn",
+        "The lowest (or highest) upcard must open with a small forced bet.",
+    ),
+    (
+        "starter",
+        "STARTER_LOWEST or STARTER_HIGHEST upcard brings it in.",
+    ),
+    ("amount", "The bring-in, in chips."),
+    (
+        "allowComplete",
+        "The bring-in player may open for the full minimum bet instead.",
+    ),
+    ("exchange", "A draw: players swap cards for new ones."),
+    ("maxExchange", "The most cards a player may swap."),
+    (
+        "drawSource",
+        "Where replacements come from once the deck runs out.",
+    ),
+    (
+        "communityBoardCount",
+        "How many boards are dealt, for games with more than one.",
+    ),
+    (
+        "scorings",
+        "How hands are ranked at showdown. With two scorings, such as a high\n\
+         and a low, the pot is split between them.",
+    ),
+    (
+        "standardRanking",
+        "RANKING_STANDARD for poker hands, or a lowball ranking.",
+    ),
+    ("lo", "The lowest hand wins rather than the highest."),
+    (
+        "loQualifier",
+        "The worst hand that can win the low, such as 8 for eight or better.",
+    ),
+    (
+        "topCardCanBeLow",
+        "Aces count low, as in ace-to-five lowball.",
+    ),
+    (
+        "handCards",
+        "How many of a player's own cards the hand must use.",
+    ),
+    (
+        "communityCards",
+        "How many cards from each board the hand must use.",
+    ),
+    ("min", "At least this many."),
+    ("max", "At most this many."),
+    (
+        "version",
+        "The version of the format this file is written in. Leave it be.",
+    ),
+];
+
+const DSL_COMMENTS: &[(&str, &str)] = &[
+    ("id", "A short name for the game, used to refer to it."),
+    ("name", "The name players see."),
+    ("description", "A sentence or two about the game."),
+    (
+        "deck",
+        "deck standard for 52 cards, deck short for the sixes up, or list\n\
+         the ranks and suits.",
+    ),
+    (
+        "ante",
+        "Every player, or with big-blind just the big blind, antes.",
+    ),
+    (
+        "deal",
+        "Deal N cards to each player, down or up; `deal 2 down 1 up` mixes\n\
+         the two.",
+    ),
+    (
+        "bet",
+        "A round of betting: its name, the smallest bet, and who acts first\n\
+         (follow-blinds, left-of-dealer or best-faceup). A stud round may\n\
+         add bring-in lowest | highest [amount N] [complete].",
+    ),
+    (
+        "board",
+        "Deal N shared cards to the board, burning B cards first with burn B.",
+    ),
+    (
+        "draw",
+        "A draw: each player may swap up to N cards for new ones.",
+    ),
+    (
+        "score",
+        "How hands are ranked at showdown: standard, or a lowball ranking\n\
+         with low. hand N and community N say how many cards a hand must\n\
+         use from each place. Two score lines split the pot.",
+    ),
+];
+
+fn json(id: &str, template: &str, to: Format, path: &Path) -> Value {
+    output::document(
+        "new",
+        vec![
+            ("id", output::string(id)),
+            ("template", output::string(template)),
+            ("format", output::string(format!("{:?}", to).to_lowercase())),
+            ("path", output::string(path.display().to_string())),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaffold() {
+        for template in presets::all() {
+            let game = start(template.clone(), "my-game");
+            assert_eq!(game.id, "my-game");
+            assert_eq!(game.phases, template.phases);
+
+            let text = scaffold(&game, Format::Yaml);
+            assert!(text.contains("\n# The name players see.\nname: my-game\n"));
+            assert_eq!(gamedef::from_yaml(&text).unwrap(), game, "{}", text);
+            let text = scaffold(&game, Format::Dsl);
+            assert_eq!(gamedef::from_dsl(&text).unwrap(), game, "{}", text);
+        }
+
+        let text = scaffold(&start(presets::omaha(), "o"), Format::Yaml);
+        // Fields are explained once, where they first appear.
+        assert_eq!(text.matches("# The smallest bet").count(), 1);
+        assert!(text.contains("  - playerDeal:\n      # How many cards.\n      cards: 4\n"));
+    }
+}
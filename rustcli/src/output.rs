@@ -0,0 +1,85 @@
+//! `--output`: whether a command prints for people or for other programs.
+//!
+//! With `--output json` a command prints JSON to stdout: one object, or
+//! for commands that keep going (`play`, `serve`) one object per line.
+//! Each object's `schema` field names its shape, `snapfold.COMMAND.v1`. A
+//! version only ever gains fields; any other change to a shape bumps it.
+//! Diagnostics still go to stderr, as text.
+
+use rustlib::gamedef::json::Value;
+
+use crate::args::Args;
+use crate::CliError;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Output {
+    #[default]
+    Text,
+    Json,
+}
+
+impl Output {
+    /// The `--output` given in `args`, or text.
+    pub fn from_args(args: &Args) -> Result<Self, CliError> {
+        match args.value("output") {
+            None | Some("text") => Ok(Output::Text),
+            Some("json") => Ok(Output::Json),
+            Some(other) => Err(CliError::Usage(format!(
+                "--output {:?}: expected text or json",
+                other
+            ))),
+        }
+    }
+
+    pub fn is_json(self) -> bool {
+        self == Output::Json
+    }
+}
+
+/// An object of `command`'s output: its schema, then `fields`.
+pub fn document(command: &str, fields: Vec<(&str, Value)>) -> Value {
+    let schema = (
+        "schema".to_string(),
+        Value::String(format!("snapfold.{}.v1", command)),
+    );
+    Value::Object(
+        std::iter::once(schema)
+            .chain(
+                fields
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), value)),
+            )
+            .collect(),
+    )
+}
+
+/// `text` as a JSON string.
+pub fn string(text: impl Into<String>) -> Value {
+    Value::String(text.into())
+}
+
+/// A random seed. As a string, since seeds don't fit in a double.
+pub fn seed_json(seed: u64) -> Value {
+    Value::String(seed.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output() {
+        let args = |s: &str| {
+            let argv: Vec<String> = s.split_whitespace().map(String::from).collect();
+            Args::parse(&argv, &["output"], &[]).unwrap()
+        };
+        assert_eq!(Output::from_args(&args("")).unwrap(), Output::Text);
+        assert!(Output::from_args(&args("--output json")).unwrap().is_json());
+        assert!(Output::from_args(&args("--output yaml")).is_err());
+        assert_eq!(
+            document("eval", vec![("hands", Value::Array(Vec::new()))]).to_string(),
+            r#"{"schema":"snapfold.eval.v1","hands":[]}"#
+        );
+        assert_eq!(seed_json(u64::MAX).to_string(), r#""18446744073709551615""#);
+    }
+}
@@ -0,0 +1,544 @@
+//! `snapfold play`: plays a game at the terminal against bots.
+
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use rustlib::cards::parse_cards;
+use rustlib::engine::{Action, Chips, EngineConfig, GameEngine, HandStatus, LegalAction};
+use rustlib::gamedef::json::Value;
+use rustlib::history::HandFile;
+use rustlib::phase::declare::Side;
+use rustlib::shuffle::Shuffler;
+use rustlib::strategy::Strategy;
+use rustlib::view::GameView;
+use rustlib::GameStructure;
+
+use crate::args::Args;
+use crate::history;
+use crate::load::{self, Format};
+use crate::output::{self, Output};
+use crate::table::{self, Table};
+use crate::{bots, diag, CliError};
+
+pub const USAGE: &str = "\
+usage: snapfold play FILE [--players N] [--bots N] [--strategy BOT] [--stack N]
+                     [--seed N] [--record DIR] [--output FORMAT]
+                     [--format FORMAT]
+
+Deals hands of the game at the terminal, with you in the first seats and
+bots in the rest. Stacks carry over from hand to hand until you stop or
+only one seat has chips left.
+
+At your turn, type an action:
+  f, fold          k, x, check       c, call          a, all-in
+  b N, bet N       r N, raise N      N                bet or raise to N
+  d CARDS, discard CARDS (an empty discard stands pat)
+  pass CARDS       reveal CARDS      declare high|low|both
+  buy [CARD]       decline           table            redraw the table
+Cards are written like Ah Td 9c.
+
+Options:
+  --players N      seats at the table (default 4)
+  --bots N         how many seats are bots (default all but one); any
+                   other seats are played at this terminal in turn
+  --strategy BOT   passive, random or pot-odds (default pot-odds)
+  --stack N        starting stack for every seat (default 200, with 1/2
+                   blinds)
+  --seed N         seed for the shuffles and the bots, to deal the same
+                   hands again (default a secure shuffle)
+  --record DIR     save each hand to DIR as hand-N.json, for
+                   `snapfold replay`
+  --output FORMAT  text, or json for a line of JSON per message, so
+                   another program can play
+  --format FORMAT  read FILE as json, yaml, dsl, binpb or game";
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(
+        argv,
+        &[
+            "players", "bots", "strategy", "stack", "seed", "record", "output", "format",
+        ],
+        &[],
+    )?;
+    let [path] = args.positional() else {
+        return Err(CliError::Usage("play needs one game file".to_string()));
+    };
+    let players: usize = args.parsed("players")?.unwrap_or(4);
+    if players < 2 {
+        return Err(CliError::Usage("--players must be at least 2".to_string()));
+    }
+    let bots: usize = args.parsed("bots")?.unwrap_or(players - 1);
+    if bots >= players {
+        return Err(CliError::Usage(format!(
+            "--bots {} leaves no seat to play at a table of {}",
+            bots, players
+        )));
+    }
+    let stack = args.parsed("stack")?.unwrap_or(200);
+    let seed: Option<u64> = args.parsed("seed")?;
+    let strategy = args.value("strategy").unwrap_or("pot-odds");
+    let bots = bots::from_arg(strategy, bots, seed.unwrap_or(0))?;
+    let game = load::load_playable(Path::new(path), Format::from_arg(args.value("format"))?)?;
+
+    let session = Session {
+        game,
+        humans: players - bots.strategies.len(),
+        bots: bots.strategies,
+        stack,
+        seed,
+        color: diag::use_color(&io::stdout()),
+        record: args.value("record").map(PathBuf::from),
+        output: Output::from_args(&args)?,
+    };
+    if let Some(dir) = &session.record {
+        std::fs::create_dir_all(dir).map_err(|error| CliError::Io {
+            path: dir.clone(),
+            error,
+        })?;
+    }
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut out = io::stdout();
+    match session.play(&mut input, &mut out) {
+        Ok(()) => Ok(ExitCode::SUCCESS),
+        Err(PlayError::Io(error)) => Err(CliError::Io {
+            path: "<terminal>".into(),
+            error,
+        }),
+        Err(PlayError::Record(error)) => Err(error),
+        Err(PlayError::Engine(message)) => Err(CliError::Load {
+            path: path.into(),
+            source: None,
+            at: None,
+            message,
+        }),
+    }
+}
+
+/// A game at the table: the humans in the first seats and a bot in each
+/// seat after them.
+struct Session {
+    game: GameStructure,
+    humans: usize,
+    bots: Vec<Box<dyn Strategy>>,
+    stack: Chips,
+    seed: Option<u64>,
+    color: bool,
+    /// The directory to save each hand in.
+    record: Option<PathBuf>,
+    output: Output,
+}
+
+#[derive(Debug)]
+enum PlayError {
+    Io(io::Error),
+    /// The engine refused to deal or rejected a bot's action.
+    Engine(String),
+    /// A hand couldn't be saved.
+    Record(CliError),
+}
+
+impl From<io::Error> for PlayError {
+    fn from(error: io::Error) -> Self {
+        PlayError::Io(error)
+    }
+}
+
+impl Session {
+    fn seats(&self) -> usize {
+        self.humans + self.bots.len()
+    }
+
+    fn names(&self) -> Vec<String> {
+        (0..self.seats())
+            .map(|seat| match seat < self.humans {
+                true => format!("Player {}", seat + 1),
+                false => format!("Bot {}", seat + 1),
+            })
+            .collect()
+    }
+
+    /// Deals hands until the input ends, the humans stop or go broke, or
+    /// only one seat has chips.
+    fn play(&self, input: &mut dyn BufRead, out: &mut dyn Write) -> Result<(), PlayError> {
+        let names = self.names();
+        let mut stacks = vec![self.stack; self.seats()];
+        let mut button = 0;
+        for hand in 0.. {
+            let shuffler = match self.seed {
+                Some(seed) => Shuffler::seeded(seed.wrapping_add(hand)),
+                None => Shuffler::secure(),
+            };
+            let config = EngineConfig {
+                stacks: stacks.clone(),
+                button,
+                shuffler,
+                ..EngineConfig::new(self.seats())
+            };
+            let mut engine = GameEngine::with_config(self.game.clone(), config)
+                .map_err(|e| PlayError::Engine(format!("can't deal hand {}: {}", hand + 1, e)))?;
+            self.say(
+                out,
+                &format!("\n=== Hand {} ===", hand + 1),
+                "hand",
+                vec![("number", Value::Number((hand + 1) as f64))],
+            )?;
+            if !self.play_hand(&mut engine, &names, input, out)? {
+                return Ok(());
+            }
+            if engine.status() == HandStatus::Misdeal {
+                self.say(out, "Dealing the hand again.", "misdeal", Vec::new())?;
+                continue;
+            }
+            if let Some(dir) = &self.record {
+                let file =
+                    HandFile::new(&engine, &names).map_err(|e| PlayError::Engine(e.to_string()))?;
+                history::write(&file, &dir.join(format!("hand-{}.json", hand + 1)))
+                    .map_err(PlayError::Record)?;
+            }
+
+            stacks = engine.seats().iter().map(|s| s.stack).collect();
+            let standings: Vec<String> = names
+                .iter()
+                .zip(&stacks)
+                .map(|(name, stack)| format!("{} {}", name, stack))
+                .collect();
+            let stacks_json = names
+                .iter()
+                .zip(&stacks)
+                .map(|(name, &stack)| {
+                    Value::Object(vec![
+                        ("player".to_string(), output::string(name.as_str())),
+                        ("stack".to_string(), Value::Number(stack as f64)),
+                    ])
+                })
+                .collect();
+            self.say(
+                out,
+                &format!("Stacks: {}", standings.join(", ")),
+                "stacks",
+                vec![("stacks", Value::Array(stacks_json))],
+            )?;
+            if stacks.iter().filter(|&&s| s > 0).count() < 2 {
+                self.say(out, "The game is over.", "end", Vec::new())?;
+                return Ok(());
+            }
+            if stacks[..self.humans].iter().all(|&s| s == 0) {
+                let text = "No one at this terminal has chips left.";
+                self.say(out, text, "end", Vec::new())?;
+                return Ok(());
+            }
+            button = (1..=stacks.len())
+                .map(|i| (button + i) % stacks.len())
+                .find(|&seat| stacks[seat] > 0)
+                .unwrap_or(button);
+            self.ask(out, "Deal another hand? [Y/n] ", "another", Vec::new())?;
+            let Some(line) = read_line(input)? else {
+                return Ok(());
+            };
+            if matches!(
+                line.trim().to_ascii_lowercase().as_str(),
+                "n" | "no" | "q" | "quit"
+            ) {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Plays `engine`'s hand to the end, telling `out` what happens.
+    /// Returns `false` if the input ended first.
+    fn play_hand(
+        &self,
+        engine: &mut GameEngine,
+        names: &[String],
+        input: &mut dyn BufRead,
+        out: &mut dyn Write,
+    ) -> Result<bool, PlayError> {
+        // With one human, their cards are shown as they're dealt. Players
+        // sharing a terminal only see their cards at their own prompt, so
+        // out of turn the table is watched from a seat past the last one,
+        // which shows no one's.
+        let watching = match self.humans {
+            1 => Some(0),
+            _ => Some(self.seats()),
+        };
+        let mut table = Table::new(engine, names, watching);
+        table.color = self.color;
+        let mut seen = 0;
+        loop {
+            for event in &engine.events()[seen..] {
+                if let Some(text) = table.event(engine, event) {
+                    self.say(out, &text, "event", Vec::new())?;
+                }
+            }
+            seen = engine.events().len();
+            let Some(seat) = engine.to_act() else {
+                return Ok(true);
+            };
+            if seat >= self.humans {
+                let action = self.bots[seat - self.humans].act(&GameView::new(engine, seat));
+                engine.apply_action(seat, action.clone()).map_err(|e| {
+                    PlayError::Engine(format!("{} can't {:?}: {}", names[seat], action, e))
+                })?;
+                continue;
+            }
+
+            table.viewer = Some(seat);
+            self.show(out, &table, engine, "\n")?;
+            loop {
+                let legal = engine.legal_actions(seat);
+                let options = table::options(&legal);
+                let choices = options.split(" | ").map(output::string).collect();
+                self.ask(
+                    out,
+                    &format!("{} to act ({})> ", names[seat], options),
+                    "turn",
+                    vec![
+                        ("player", output::string(names[seat].as_str())),
+                        ("options", Value::Array(choices)),
+                    ],
+                )?;
+                let Some(line) = read_line(input)? else {
+                    return Ok(false);
+                };
+                let line = line.trim();
+                if line == "table" {
+                    self.show(out, &table, engine, "")?;
+                    continue;
+                }
+                let result = parse_action(line, &legal).and_then(|action| {
+                    engine.apply_action(seat, action).map_err(|e| e.to_string())
+                });
+                match result {
+                    Ok(_) => break,
+                    Err(message) => self.say(out, &message, "error", Vec::new())?,
+                }
+            }
+            table.viewer = watching;
+        }
+    }
+
+    /// Writes `text` as a line, or with `--output json` a line of JSON of
+    /// type `kind` with `fields`, or if there are none, the text.
+    fn say(
+        &self,
+        out: &mut dyn Write,
+        text: &str,
+        kind: &str,
+        fields: Vec<(&str, Value)>,
+    ) -> io::Result<()> {
+        match self.output.is_json() {
+            true => writeln!(out, "{}", self.line(text, kind, fields)),
+            false => writeln!(out, "{}", text),
+        }
+    }
+
+    /// As [`Session::say`], for a prompt waiting on a line of input.
+    fn ask(
+        &self,
+        out: &mut dyn Write,
+        text: &str,
+        kind: &str,
+        fields: Vec<(&str, Value)>,
+    ) -> io::Result<()> {
+        match self.output.is_json() {
+            true => writeln!(out, "{}", self.line(text, kind, fields))?,
+            false => write!(out, "{}", text)?,
+        }
+        out.flush()
+    }
+
+    /// Draws the table after `before`, or writes it as a line of JSON.
+    fn show(
+        &self,
+        out: &mut dyn Write,
+        table: &Table,
+        engine: &GameEngine,
+        before: &str,
+    ) -> io::Result<()> {
+        match self.output.is_json() {
+            true => writeln!(out, "{}", self.line("", "table", table.json(engine))),
+            false => write!(out, "{}{}", before, table.draw(engine)),
+        }
+    }
+
+    fn line<'a>(&self, text: &str, kind: &'a str, fields: Vec<(&'a str, Value)>) -> Value {
+        let mut all = vec![("type", output::string(kind))];
+        match fields.is_empty() {
+            true => all.push(("text", output::string(text.trim()))),
+            false => all.extend(fields),
+        }
+        output::document("play", all)
+    }
+}
+
+/// A line of `input` without its line ending, or `None` at the end.
+fn read_line(input: &mut dyn BufRead) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    match input.read_line(&mut line)? {
+        0 => Ok(None),
+        _ => Ok(Some(line.trim_end_matches(['\r', '\n']).to_string())),
+    }
+}
+
+/// The action typed at a prompt when `legal` are the actions open. Bets
+/// and raises are totals for the round, and a bare number is whichever
+/// of the two is open.
+fn parse_action(text: &str, legal: &[LegalAction]) -> Result<Action, String> {
+    let text = text.trim();
+    let (word, rest) = match text.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (text, ""),
+    };
+    let word = word.to_ascii_lowercase();
+    let can = |matches: fn(&LegalAction) -> bool| legal.iter().any(matches);
+    let chips = |rest: &str| {
+        rest.parse::<Chips>()
+            .map_err(|_| format!("{:?} isn't a number of chips", rest))
+    };
+    let cards = |rest: &str| match rest {
+        "" => Ok(Vec::new()),
+        rest => parse_cards(rest).map_err(|e| e.to_string()),
+    };
+    // A bet when no one has bet yet, and otherwise a raise.
+    let wager = |total: Chips| match can(|a| matches!(a, LegalAction::Raise { .. })) {
+        true => Action::Raise(total),
+        false => Action::Bet(total),
+    };
+    match word.as_str() {
+        "f" | "fold" => Ok(Action::Fold),
+        "k" | "x" | "check" | "decline" => Ok(Action::Check),
+        // `c` checks if there's nothing to call.
+        "c" | "call" => match can(|a| matches!(a, LegalAction::Call(_))) {
+            true => Ok(Action::Call),
+            false => Ok(Action::Check),
+        },
+        "a" | "all-in" | "allin" | "shove" => Ok(Action::AllIn),
+        "b" | "bet" | "r" | "raise" => chips(rest).map(wager),
+        "d" | "discard" | "draw" => cards(rest).map(Action::Discard),
+        "pat" => Ok(Action::Discard(Vec::new())),
+        "pass" | "p" => cards(rest).map(Action::Pass),
+        "reveal" | "show" => cards(rest).map(Action::Reveal),
+        "buy" => cards(rest).map(Action::Buy),
+        "declare" => match rest.to_ascii_lowercase().as_str() {
+            "high" | "h" | "hi" => Ok(Action::Declare(Side::High)),
+            "low" | "l" | "lo" => Ok(Action::Declare(Side::Low)),
+            "both" | "b" | "swing" => Ok(Action::Declare(Side::Both)),
+            rest => Err(format!("declare high, low or both, not {:?}", rest)),
+        },
+        "" => Err("type an action, or `table` to see the table again".to_string()),
+        _ if rest.is_empty() && word.parse::<Chips>().is_ok() => chips(&word).map(wager),
+        _ => Err(format!(
+            "unknown action {:?}; see `snapfold play --help`",
+            text
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustlib::presets;
+    use rustlib::strategy::Passive;
+
+    #[test]
+    fn test_parse_action() {
+        let open = [
+            LegalAction::Fold,
+            LegalAction::Check,
+            LegalAction::Bet { min: 2, max: 200 },
+        ];
+        let facing = [
+            LegalAction::Fold,
+            LegalAction::Call(2),
+            LegalAction::Raise { min: 4, max: 200 },
+        ];
+        assert_eq!(parse_action("c", &open), Ok(Action::Check));
+        assert_eq!(parse_action("c", &facing), Ok(Action::Call));
+        assert_eq!(parse_action("F", &facing), Ok(Action::Fold));
+        assert_eq!(parse_action("20", &open), Ok(Action::Bet(20)));
+        assert_eq!(parse_action("bet 20", &facing), Ok(Action::Raise(20)));
+        assert_eq!(parse_action("r  8", &facing), Ok(Action::Raise(8)));
+        assert_eq!(
+            parse_action("d Ah 9c", &[]),
+            Ok(Action::Discard(parse_cards("Ah 9c").unwrap()))
+        );
+        assert_eq!(
+            parse_action("discard", &[]),
+            Ok(Action::Discard(Vec::new()))
+        );
+        assert_eq!(
+            parse_action("declare both", &[]),
+            Ok(Action::Declare(Side::Both))
+        );
+        assert_eq!(parse_action("decline", &[]), Ok(Action::Check));
+        assert!(parse_action("bet lots", &open).is_err());
+        assert!(parse_action("d Zz", &[]).is_err());
+        assert!(parse_action("dance", &open).is_err());
+    }
+
+    #[test]
+    fn test_session() {
+        let session = Session {
+            game: presets::holdem(),
+            humans: 1,
+            bots: vec![Box::new(Passive)],
+            stack: 200,
+            seed: Some(7),
+            color: false,
+            record: Some(
+                std::env::temp_dir().join(format!("snapfold-play-{}", std::process::id())),
+            ),
+            output: Output::Text,
+        };
+        std::fs::create_dir_all(session.record.as_ref().unwrap()).unwrap();
+        // A typo, then a fold; a second hand folded too, then stop.
+        let mut input: &[u8] = b"fodl\nf\n\nf\nn\n";
+        let mut out = Vec::new();
+        session.play(&mut input, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("=== Hand 1 ===") && out.contains("=== Hand 2 ==="));
+        assert!(!out.contains("=== Hand 3 ==="));
+        assert!(out.contains("unknown action \"fodl\""));
+        assert_eq!(out.matches("Player 1 folds").count(), 2);
+        assert!(out.contains("Player 1 to act (fold | call 1 | raise 4-200 | all-in 200)> "));
+        assert!(out.contains("Stacks: Player 1 199, Bot 2 201\n"));
+        // The bot's cards are never shown, since it didn't have to show
+        // them.
+        assert!(out.contains("Bot 2 is dealt [##] [##]"));
+        assert!(!out.contains("[K♠] [K♥]"));
+
+        let dir = session.record.as_ref().unwrap();
+        let first = history::read(&dir.join("hand-1.json")).unwrap().remove(0);
+        assert_eq!(first.names, session.names());
+        assert!(dir.join("hand-2.json").exists() && !dir.join("hand-3.json").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+
+        let session = Session {
+            record: None,
+            output: Output::Json,
+            bots: vec![Box::new(Passive)],
+            ..session
+        };
+        let mut out = Vec::new();
+        session.play(&mut &b"f\nn\n"[..], &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines[0],
+            r#"{"schema":"snapfold.play.v1","type":"hand","number":1}"#
+        );
+        assert!(lines
+            .iter()
+            .all(|l| l.starts_with(r#"{"schema":"snapfold.play.v1""#)));
+        assert!(
+            out.contains(r#""type":"event","text":"Player 1 folds"}"#),
+            "{}",
+            out
+        );
+        assert!(out.contains(r#""type":"turn","player":"Player 1","options":["fold","call 1""#));
+        assert!(out.contains(r#""type":"stacks","stacks":[{"player":"Player 1","stack":199}"#));
+    }
+}
@@ -0,0 +1,520 @@
+//! `snapfold registry`: an HTTP server where clients and game servers
+//! share game definitions.
+//!
+//! The store is a directory. `objects/` holds each game ever uploaded as
+//! a file of its canonical JSON, named by that JSON's SHA-256, which is the
+//! game's fingerprint; `ids/` holds a file per id naming the fingerprint of
+//! the game it stands for now. Objects are never changed or removed, so a
+//! fingerprint always fetches the same rules.
+
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+use rustlib::fingerprint::{hex, sha256};
+use rustlib::gamedef::codec;
+use rustlib::gamedef::json::{self, Value};
+use rustlib::validation;
+
+use crate::args::Args;
+use crate::diag::{Diagnostic, Level};
+use crate::http::{self, Handler, Request, Response};
+use crate::load::{self, Format};
+use crate::output::{self, Output};
+use crate::CliError;
+
+pub const USAGE: &str = "\
+usage: snapfold registry --store DIR [--port N] [--host ADDRESS] [--output FORMAT]
+
+Runs a registry of game definitions over HTTP, kept in DIR. Uploads are
+checked to load and be playable, and stored under their fingerprint, the
+SHA-256 of their rules: the same rules are stored once however they were
+written, and the game a fingerprint names never changes.
+
+  GET    /games               the id, name and fingerprint of each game
+  POST   /games               add a game under its id
+  GET    /games/ID            a game as canonical JSON, with its fingerprint
+                              as the ETag
+  PUT    /games/ID            add or replace the game under ID
+  DELETE /games/ID            remove ID; its rules stay under their
+                              fingerprint
+  GET    /fingerprints/HASH   the game whose fingerprint is HASH
+
+Uploads are read as JSON, or as YAML or a binary proto if their
+Content-Type is application/yaml or application/x-protobuf, or in the
+format ?format=NAME names. Games can be fetched by name with
+`snapfold fetch holdem.json --registry http://HOST:PORT/games`.
+
+Options:
+  --store DIR       the directory to keep games in, made if it doesn't exist
+  --port N          the port to listen on (default 8080)
+  --host ADDRESS    the address to listen on (default 127.0.0.1)
+  --output FORMAT   text, or json to print a line of JSON when the server
+                    starts listening";
+
+/// The longest id a game may be stored under.
+const MAX_ID: usize = 128;
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(argv, &["store", "port", "host", "output"], &[])?;
+    let output = Output::from_args(&args)?;
+    if !args.positional().is_empty() {
+        return Err(CliError::Usage(
+            "registry takes --store DIR, not files".to_string(),
+        ));
+    }
+    let dir = args
+        .value("store")
+        .ok_or_else(|| CliError::Usage("registry needs --store DIR".to_string()))?;
+    let registry = Registry::open(Path::new(dir)).map_err(|error| CliError::Io {
+        path: dir.into(),
+        error,
+    })?;
+    let games = registry
+        .entries()
+        .map_err(|error| CliError::Io {
+            path: dir.into(),
+            error,
+        })?
+        .len();
+    let host = args.value("host").unwrap_or("127.0.0.1");
+    let port: u16 = args.parsed("port")?.unwrap_or(8080);
+    let io = |error| CliError::Io {
+        path: format!("{}:{}", host, port).into(),
+        error,
+    };
+    let listener = TcpListener::bind((host, port)).map_err(io)?;
+    let addr = listener.local_addr().map_err(io)?;
+    match output.is_json() {
+        true => {
+            let doc = output::document(
+                "registry",
+                vec![
+                    ("type", output::string("listening")),
+                    ("address", output::string(addr.to_string())),
+                    ("games", Value::Number(games as f64)),
+                ],
+            );
+            println!("{}", doc);
+        }
+        false => Diagnostic::new(
+            Level::Info,
+            format!("serving {} games from {} on http://{}", games, dir, addr),
+        )
+        .emit(),
+    }
+    http::serve(Arc::new(registry), listener);
+    Ok(ExitCode::SUCCESS)
+}
+
+/// A game the registry has under an id.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Entry {
+    id: String,
+    name: String,
+    fingerprint: String,
+}
+
+impl Entry {
+    fn json(&self) -> Value {
+        Value::Object(vec![
+            ("id".to_string(), Value::String(self.id.clone())),
+            ("name".to_string(), Value::String(self.name.clone())),
+            (
+                "fingerprint".to_string(),
+                Value::String(self.fingerprint.clone()),
+            ),
+        ])
+    }
+}
+
+struct Registry {
+    dir: PathBuf,
+    /// Held while the store changes, so that checking an id and writing it
+    /// happen together.
+    writing: Mutex<()>,
+}
+
+impl Registry {
+    fn open(dir: &Path) -> io::Result<Self> {
+        std::fs::create_dir_all(dir.join("objects"))?;
+        std::fs::create_dir_all(dir.join("ids"))?;
+        Ok(Registry {
+            dir: dir.to_path_buf(),
+            writing: Mutex::new(()),
+        })
+    }
+
+    fn object(&self, fingerprint: &str) -> PathBuf {
+        self.dir
+            .join("objects")
+            .join(format!("{}.json", fingerprint))
+    }
+
+    /// The fingerprint of the game under `id`, if there is one.
+    fn lookup(&self, id: &str) -> io::Result<Option<String>> {
+        match std::fs::read_to_string(self.dir.join("ids").join(id)) {
+            Ok(fingerprint) => Ok(Some(fingerprint.trim().to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The game under `id`, if there is one.
+    fn entry(&self, id: &str) -> io::Result<Option<Entry>> {
+        let Some(fingerprint) = self.lookup(id)? else {
+            return Ok(None);
+        };
+        let text = std::fs::read_to_string(self.object(&fingerprint))?;
+        let name = match json::parse(&text) {
+            Ok(Value::Object(fields)) => fields
+                .into_iter()
+                .find(|(key, _)| key == "name")
+                .and_then(|(_, v)| match v {
+                    Value::String(name) => Some(name),
+                    _ => None,
+                }),
+            _ => None,
+        };
+        Ok(Some(Entry {
+            id: id.to_string(),
+            name: name.unwrap_or_default(),
+            fingerprint,
+        }))
+    }
+
+    /// Every game under an id, by id.
+    fn entries(&self) -> io::Result<Vec<Entry>> {
+        let mut ids = Vec::new();
+        for file in std::fs::read_dir(self.dir.join("ids"))? {
+            let name = file?.file_name();
+            if let Some(id) = name.to_str().filter(|id| check_id(id).is_ok()) {
+                ids.push(id.to_string());
+            }
+        }
+        ids.sort();
+        let mut entries = Vec::new();
+        for id in ids {
+            entries.extend(self.entry(&id)?);
+        }
+        Ok(entries)
+    }
+
+    fn handle(&self, request: &Request) -> Response {
+        self.route(request)
+            .unwrap_or_else(|e| Response::text(500, format!("{}\n", e)))
+    }
+
+    fn route(&self, request: &Request) -> io::Result<Response> {
+        let path = request.path.trim_end_matches('/');
+        let method = request.method.as_str();
+        let not_allowed = || Response::text(405, format!("{} isn't allowed here\n", method));
+        if path == "/games" {
+            return match method {
+                "GET" => {
+                    let entries = self.entries()?.iter().map(Entry::json).collect();
+                    Ok(Response::json(200, Value::Array(entries).to_string()))
+                }
+                "POST" => self.upload(request, None),
+                _ => Ok(not_allowed()),
+            };
+        }
+        if let Some(id) = path.strip_prefix("/games/") {
+            return match method {
+                "GET" => {
+                    // `fetch` names games by file, as in holdem.json.
+                    let found = match self.lookup(id)? {
+                        Some(fingerprint) => Some(fingerprint),
+                        None => match id.strip_suffix(".json") {
+                            Some(id) => self.lookup(id)?,
+                            None => None,
+                        },
+                    };
+                    match found {
+                        Some(fingerprint) => self.fetch(&fingerprint, request),
+                        None => Ok(Response::text(404, format!("no game {:?}\n", id))),
+                    }
+                }
+                "PUT" => self.upload(request, Some(id)),
+                "DELETE" => self.delete(id),
+                _ => Ok(not_allowed()),
+            };
+        }
+        if let Some(fingerprint) = path.strip_prefix("/fingerprints/") {
+            return match method {
+                "GET" if is_fingerprint(fingerprint) => self.fetch(fingerprint, request),
+                "GET" => Ok(Response::text(
+                    404,
+                    format!("{:?} isn't a fingerprint\n", fingerprint),
+                )),
+                _ => Ok(not_allowed()),
+            };
+        }
+        Ok(Response::text(
+            404,
+            format!("nothing at {}\n", request.path),
+        ))
+    }
+
+    /// The game with `fingerprint`, or a 304 if the request's
+    /// If-None-Match says the client has it already.
+    fn fetch(&self, fingerprint: &str, request: &Request) -> io::Result<Response> {
+        let etag = format!("\"{}\"", fingerprint);
+        if request.header("if-none-match") == Some(etag.as_str()) {
+            return Ok(Response::text(304, "").with_header("ETag", etag));
+        }
+        match std::fs::read_to_string(self.object(fingerprint)) {
+            Ok(text) => Ok(Response::json(200, text).with_header("ETag", etag)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Response::text(
+                404,
+                format!("no game has the fingerprint {}\n", fingerprint),
+            )),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Stores the game in the body of `request` under its id. A PUT names
+    /// the id, and may replace the game under it; a POST may not.
+    fn upload(&self, request: &Request, put: Option<&str>) -> io::Result<Response> {
+        let format = match upload_format(request) {
+            Ok(format) => format,
+            Err(message) => return Ok(Response::text(400, message)),
+        };
+        let mut game = match load::parse(Path::new("upload"), request.body.clone(), format) {
+            Ok(game) => game,
+            Err(e) => return Ok(Response::text(400, problem(&e))),
+        };
+        if let Some(id) = put {
+            if game.id.is_empty() {
+                game.id = id.to_string();
+            } else if game.id != id {
+                let message = format!("the game's id is {:?}, not {:?}\n", game.id, id);
+                return Ok(Response::text(400, message));
+            }
+        }
+        if let Err(message) = check_id(&game.id) {
+            return Ok(Response::text(400, message));
+        }
+        if let Err(errors) = validation::validate(&game) {
+            let errors: String = errors.iter().map(|e| format!("{}\n", e)).collect();
+            return Ok(Response::text(400, errors));
+        }
+        // The fingerprint is the hash of exactly this text.
+        let text = codec::encode(&game).to_string();
+        let fingerprint = hex(&sha256(text.as_bytes()));
+
+        let _writing = self.writing.lock().expect("registry lock");
+        let before = self.lookup(&game.id)?;
+        if put.is_none() && before.as_ref().is_some_and(|b| *b != fingerprint) {
+            let message = format!(
+                "there is already a game {:?}; PUT /games/{} to replace it\n",
+                game.id, game.id
+            );
+            return Ok(Response::text(409, message));
+        }
+        let object = self.object(&fingerprint);
+        if !object.exists() {
+            write_file(&object, text.as_bytes())?;
+        }
+        write_file(&self.dir.join("ids").join(&game.id), fingerprint.as_bytes())?;
+        let entry = Entry {
+            id: game.id.clone(),
+            name: game.name.clone(),
+            fingerprint,
+        };
+        let status = if before.is_none() { 201 } else { 200 };
+        Ok(Response::json(status, entry.json().to_string())
+            .with_header("ETag", format!("\"{}\"", entry.fingerprint)))
+    }
+
+    fn delete(&self, id: &str) -> io::Result<Response> {
+        let _writing = self.writing.lock().expect("registry lock");
+        if check_id(id).is_err() || self.lookup(id)?.is_none() {
+            return Ok(Response::text(404, format!("no game {:?}\n", id)));
+        }
+        std::fs::remove_file(self.dir.join("ids").join(id))?;
+        Ok(Response::text(204, ""))
+    }
+}
+
+/// The format an upload is in, from `?format=` or its Content-Type.
+fn upload_format(request: &Request) -> Result<Format, String> {
+    if let Some(name) = request
+        .query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("format="))
+    {
+        return Format::from_name(name)
+            .ok_or_else(|| format!("unknown format {:?}; expected {}\n", name, Format::NAMES));
+    }
+    let content_type = request.header("content-type").unwrap_or("");
+    let mime = content_type.split(';').next().unwrap_or("").trim();
+    Ok(match mime.to_ascii_lowercase().as_str() {
+        "application/yaml" | "application/x-yaml" | "text/yaml" => Format::Yaml,
+        "application/x-protobuf" | "application/protobuf" => Format::Proto,
+        // Including form data, which is what `curl -d` says it sends.
+        _ => Format::Json,
+    })
+}
+
+/// Checks that `id` can name a game in the registry, and a file.
+fn check_id(id: &str) -> Result<(), String> {
+    if id.is_empty() {
+        return Err("the game has no id to store it under\n".to_string());
+    }
+    let allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.');
+    if id.len() > MAX_ID || id.starts_with('.') || !id.chars().all(allowed) {
+        return Err(format!(
+            "{:?} can't be an id: ids are letters, digits, '-', '_' and '.', \
+             not starting with '.'\n",
+            id
+        ));
+    }
+    Ok(())
+}
+
+fn is_fingerprint(text: &str) -> bool {
+    text.len() == 64
+        && text
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
+
+/// Why an upload couldn't be read, for the body of a 400.
+fn problem(error: &CliError) -> String {
+    match error {
+        CliError::Load {
+            at: Some((line, column)),
+            message,
+            ..
+        } => format!("{}:{}: {}\n", line, column, message),
+        CliError::Load { message, .. } | CliError::Usage(message) => format!("{}\n", message),
+        CliError::Io { error, .. } => format!("{}\n", error),
+        CliError::Invalid { errors, .. } => errors.iter().map(|e| format!("{}\n", e)).collect(),
+    }
+}
+
+/// Writes `path` by renaming a finished file into place, so that readers
+/// never see half of it. Only called with the registry's lock held.
+fn write_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy())
+        .unwrap_or_default();
+    // Ids can't start with '.', so this is never taken for one.
+    let partial = path.with_file_name(format!(".{}.partial", name));
+    std::fs::write(&partial, contents)?;
+    std::fs::rename(&partial, path)
+}
+
+impl Handler for Registry {
+    fn respond(&self, request: &Request, _peer: Option<SocketAddr>) -> Response {
+        self.handle(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustlib::fingerprint::fingerprint;
+    use rustlib::gamedef;
+    use rustlib::presets;
+
+    fn request(method: &str, path: &str, body: &str) -> Request {
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: query.to_string(),
+            headers: Vec::new(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn body(response: &Response) -> &str {
+        std::str::from_utf8(&response.body).unwrap()
+    }
+
+    #[test]
+    fn test_registry() {
+        let dir = std::env::temp_dir().join(format!("snapfold-registry-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let registry = Registry::open(&dir).unwrap();
+        let holdem = presets::holdem();
+        let print = hex(&fingerprint(&holdem));
+
+        let created = registry.handle(&request("POST", "/games", &gamedef::to_json(&holdem)));
+        assert_eq!(created.status, 201, "{}", body(&created));
+        assert!(body(&created).contains(&print));
+        // The same rules again change nothing, written in any format.
+        let yaml = request("POST", "/games?format=yaml", &gamedef::to_yaml(&holdem));
+        assert_eq!(registry.handle(&yaml).status, 200);
+
+        let mut changed = holdem.clone();
+        changed.name = "Hold'em, again".to_string();
+        let taken = registry.handle(&request("POST", "/games", &gamedef::to_json(&changed)));
+        assert_eq!(taken.status, 409);
+        let replaced = registry.handle(&request(
+            "PUT",
+            "/games/holdem",
+            &gamedef::to_json(&changed),
+        ));
+        assert_eq!(replaced.status, 200);
+        let wrong = registry.handle(&request("PUT", "/games/razz", &gamedef::to_json(&changed)));
+        assert_eq!(wrong.status, 400);
+
+        let list = registry.handle(&request("GET", "/games", ""));
+        assert_eq!(
+            body(&list),
+            format!(
+                r#"[{{"id":"holdem","name":"Hold'em, again","fingerprint":"{}"}}]"#,
+                hex(&fingerprint(&changed))
+            )
+        );
+        let fetched = registry.handle(&request("GET", "/games/holdem.json", ""));
+        assert_eq!(gamedef::from_json(body(&fetched)).unwrap(), changed);
+        let mut cached = request("GET", "/games/holdem", "");
+        cached.headers.push((
+            "if-none-match".to_string(),
+            format!("\"{}\"", hex(&fingerprint(&changed))),
+        ));
+        assert_eq!(registry.handle(&cached).status, 304);
+
+        // A fingerprint still names the rules it was made from.
+        let original = registry.handle(&request("GET", &format!("/fingerprints/{}", print), ""));
+        assert_eq!(hex(&sha256(&original.body)), print);
+        assert_eq!(gamedef::from_json(body(&original)).unwrap(), holdem);
+
+        assert_eq!(
+            registry
+                .handle(&request("DELETE", "/games/holdem", ""))
+                .status,
+            204
+        );
+        assert_eq!(
+            registry.handle(&request("GET", "/games/holdem", "")).status,
+            404
+        );
+        assert_eq!(
+            registry
+                .handle(&request("GET", &format!("/fingerprints/{}", print), ""))
+                .status,
+            200
+        );
+
+        let mut unplayable = holdem.clone();
+        unplayable.phases.clear();
+        let refused = registry.handle(&request("POST", "/games", &gamedef::to_json(&unplayable)));
+        assert_eq!(refused.status, 400);
+        let broken = registry.handle(&request("POST", "/games", "{\"id\": "));
+        assert!(body(&broken).starts_with("1:8: "), "{}", body(&broken));
+        let mut unnamed = holdem.clone();
+        unnamed.id = "../holdem".to_string();
+        let refused = registry.handle(&request("POST", "/games", &gamedef::to_json(&unnamed)));
+        assert_eq!(refused.status, 400);
+        assert_eq!(registry.handle(&request("PATCH", "/games", "")).status, 405);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,254 @@
+//! `snapfold replay`: plays a recorded hand back in the terminal.
+
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use rustlib::cards::Shoe;
+use rustlib::gamedef::json::Value;
+use rustlib::history::HandFile;
+
+use crate::args::Args;
+use crate::history;
+use crate::output::{self, Output};
+use crate::table::Table;
+use crate::{diag, CliError};
+
+pub const USAGE: &str = "\
+usage: snapfold replay FILE [--step] [--seat N] [--output FORMAT]
+
+Plays back a hand saved by `snapfold play --record` or `snapfold deal
+--record`, or one a server kept with --history, printing what happened
+at the table in order, then the table as the hand ended.
+
+Options:
+  --hand N         play the Nth hand in a server's history file (default
+                   the file's only hand)
+  --step           draw the table after each action and wait for Enter (q
+                   to stop)
+  --seat N         watch from seat N, seeing only its face-down cards
+                   (default every card)
+  --output FORMAT  text, or json to print the events and the table at the
+                   end as JSON; not with --step";
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(argv, &["hand", "seat", "output"], &["step"])?;
+    let output = Output::from_args(&args)?;
+    if output.is_json() && args.switch("step") {
+        return Err(CliError::Usage(
+            "--step waits at the terminal; it can't go with --output json".to_string(),
+        ));
+    }
+    let [path] = args.positional() else {
+        return Err(CliError::Usage("replay needs one hand file".to_string()));
+    };
+    let path = Path::new(path);
+    let mut hands = history::read(path)?;
+    let file = match (args.parsed::<usize>("hand")?, hands.len()) {
+        (Some(n), count) if (1..=count).contains(&n) => hands.swap_remove(n - 1),
+        (Some(n), count) => {
+            return Err(CliError::Usage(format!(
+                "--hand {} isn't one of the file's {} hands",
+                n, count
+            )))
+        }
+        (None, 1) => hands.remove(0),
+        (None, count) => {
+            return Err(CliError::Usage(format!(
+                "the file holds {} hands; pick one with --hand",
+                count
+            )))
+        }
+    };
+    let viewer = match args.parsed::<usize>("seat")? {
+        Some(seat) if (1..=file.names.len()).contains(&seat) => Some(seat - 1),
+        Some(seat) => {
+            return Err(CliError::Usage(format!(
+                "--seat {} isn't one of the hand's {} seats",
+                seat,
+                file.names.len()
+            )))
+        }
+        None => None,
+    };
+    let stdin = io::stdin();
+    let mut out = io::stdout();
+    if output.is_json() {
+        return match json(&file, viewer) {
+            Ok(doc) => {
+                println!("{}", doc.pretty());
+                Ok(ExitCode::SUCCESS)
+            }
+            Err(message) => Err(CliError::Load {
+                path: path.to_path_buf(),
+                source: None,
+                at: None,
+                message,
+            }),
+        };
+    }
+    let color = diag::use_color(&out);
+    let result = replay(
+        &file,
+        viewer,
+        args.switch("step"),
+        color,
+        &mut stdin.lock(),
+        &mut out,
+    );
+    match result {
+        Ok(Ok(())) => Ok(ExitCode::SUCCESS),
+        Ok(Err(message)) => Err(CliError::Load {
+            path: path.to_path_buf(),
+            source: None,
+            at: None,
+            message,
+        }),
+        Err(error) => Err(CliError::Io {
+            path: "<terminal>".into(),
+            error,
+        }),
+    }
+}
+
+/// Plays `file`'s hand back to `out`, pausing for `input` after each step
+/// if `step`. The inner error says why the hand can't be played back.
+fn replay(
+    file: &HandFile,
+    viewer: Option<usize>,
+    step: bool,
+    color: bool,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> io::Result<Result<(), String>> {
+    let table = Table {
+        shoe: Shoe::for_game(&file.game),
+        names: &file.names,
+        viewer,
+        color,
+    };
+    let mut seen = 0;
+    let mut stopped = false;
+    let mut failed = None;
+    let mut each = |engine: &rustlib::engine::GameEngine| {
+        if stopped || failed.is_some() {
+            return;
+        }
+        let result = (|| -> io::Result<()> {
+            for event in &engine.events()[seen..] {
+                if let Some(text) = table.event(engine, event) {
+                    writeln!(out, "{}", text)?;
+                }
+            }
+            seen = engine.events().len();
+            if step && engine.to_act().is_some() {
+                write!(out, "\n{}[Enter] next, q to stop> ", table.draw(engine))?;
+                out.flush()?;
+                let mut line = String::new();
+                if input.read_line(&mut line)? == 0 || line.trim() == "q" {
+                    stopped = true;
+                }
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            failed = Some(e);
+        }
+    };
+    let engine = match file.hand.replay(file.game.clone(), &mut each) {
+        Ok(engine) => engine,
+        Err(e) => return Ok(Err(format!("can't play the hand back: {}", e))),
+    };
+    if let Some(error) = failed {
+        return Err(error);
+    }
+    if !stopped {
+        write!(out, "\n{}", table.draw(&engine))?;
+    }
+    Ok(Ok(()))
+}
+
+/// The hand as JSON: the players, what happened in words, and the table
+/// as the hand ended.
+fn json(file: &HandFile, viewer: Option<usize>) -> Result<Value, String> {
+    let table = Table {
+        shoe: Shoe::for_game(&file.game),
+        names: &file.names,
+        viewer,
+        color: false,
+    };
+    let engine = file
+        .hand
+        .restore(file.game.clone())
+        .map_err(|e| format!("can't play the hand back: {}", e))?;
+    let events = engine
+        .events()
+        .iter()
+        .filter_map(|e| table.event(&engine, e))
+        .map(Value::String)
+        .collect();
+    let names = file.names.iter().map(|n| output::string(n.as_str()));
+    let mut fields = vec![
+        ("game", output::string(file.game.id.as_str())),
+        ("players", Value::Array(names.collect())),
+        ("events", Value::Array(events)),
+    ];
+    fields.extend(table.json(&engine));
+    Ok(output::document("replay", fields))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustlib::engine::{Action, EngineConfig, GameEngine};
+    use rustlib::presets;
+    use rustlib::shuffle::Shuffler;
+
+    #[test]
+    fn test_replay() {
+        let config = EngineConfig {
+            shuffler: Shuffler::seeded(3),
+            ..EngineConfig::new(2)
+        };
+        let mut engine = GameEngine::with_config(presets::holdem(), config).unwrap();
+        let seat = engine.to_act().unwrap();
+        engine.apply_action(seat, Action::Raise(6)).unwrap();
+        engine.apply_action(1 - seat, Action::Fold).unwrap();
+        let names = vec!["Ann".to_string(), "Bo".to_string()];
+        let file = HandFile::new(&engine, &names).unwrap();
+
+        let mut out = Vec::new();
+        replay(&file, None, false, false, &mut "".as_bytes(), &mut out)
+            .unwrap()
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains(" raises to 6\n"), "{}", text);
+        assert!(text.contains(" folds\n"), "{}", text);
+        assert!(!text.contains("[##]"), "{}", text);
+
+        // Stepping stops at each turn, and q stops the replay there.
+        let mut out = Vec::new();
+        replay(
+            &file,
+            Some(0),
+            true,
+            false,
+            &mut "\nq\n".as_bytes(),
+            &mut out,
+        )
+        .unwrap()
+        .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.matches("q to stop> ").count(), 2, "{}", text);
+        assert!(text.contains("[##]"), "{}", text);
+        assert!(!text.contains(" folds"), "{}", text);
+
+        let value = json(&file, Some(1)).unwrap().to_string();
+        assert!(value.starts_with(
+            r#"{"schema":"snapfold.replay.v1","game":"holdem","players":["Ann","Bo"],"events":["#
+        ));
+        assert!(value.contains(r#""Bo folds","4 uncalled returned to Ann","Ann wins 4"]"#));
+        // Watching from Bo's seat, Ann's folded-to cards stay hidden.
+        assert!(value.contains(r#"{"player":"Ann","stack":202,"cards":[null,null]"#));
+    }
+}
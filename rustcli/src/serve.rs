@@ -0,0 +1,281 @@
+//! `snapfold serve`: hosts a directory of game definitions over HTTP.
+
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use rustlib::gamedef::{self, json::Value};
+use rustlib::GameStructure;
+
+use crate::args::Args;
+use crate::diag::{self, Diagnostic, Level};
+use crate::http::{self, Handler, Request, Response};
+use crate::load::{self, Format};
+use crate::output::{self, Output};
+use crate::CliError;
+
+pub const USAGE: &str = "\
+usage: snapfold serve --games DIR [--port N] [--host ADDRESS] [--output FORMAT]
+
+Serves every game definition in DIR over HTTP. Files that don't load or
+aren't playable are reported and left out.
+
+  GET  /status      whether the server is up, for how long, and how busy
+  GET  /games       the id and name of each game
+  GET  /games/ID    a game definition as JSON
+  POST /shutdown    stop, once the requests already taken are answered;
+                    only accepted from the machine the server runs on
+
+Options:
+  --games DIR       the directory of game definitions
+  --port N          the port to listen on (default 8080)
+  --host ADDRESS    the address to listen on (default 127.0.0.1)
+  --output FORMAT   text, or json to print a line of JSON when the server
+                    starts listening and another when it stops";
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(argv, &["games", "port", "host", "output"], &[])?;
+    let output = Output::from_args(&args)?;
+    if !args.positional().is_empty() {
+        return Err(CliError::Usage(
+            "serve takes --games DIR, not files".to_string(),
+        ));
+    }
+    let dir = args
+        .value("games")
+        .ok_or_else(|| CliError::Usage("serve needs --games DIR".to_string()))?;
+    let games = load_dir(Path::new(dir))?;
+    let host = args.value("host").unwrap_or("127.0.0.1");
+    let port: u16 = args.parsed("port")?.unwrap_or(8080);
+    let listener = TcpListener::bind((host, port)).map_err(|error| CliError::Io {
+        path: format!("{}:{}", host, port).into(),
+        error,
+    })?;
+    let server = Server::new(games, &listener).map_err(|error| CliError::Io {
+        path: format!("{}:{}", host, port).into(),
+        error,
+    })?;
+    match output.is_json() {
+        true => {
+            let ids = server
+                .games
+                .iter()
+                .map(|(id, _)| output::string(id.as_str()));
+            let doc = output::document(
+                "serve",
+                vec![
+                    ("type", output::string("listening")),
+                    ("address", output::string(server.addr.to_string())),
+                    ("games", Value::Array(ids.collect())),
+                ],
+            );
+            println!("{}", doc);
+        }
+        false => Diagnostic::new(
+            Level::Info,
+            format!(
+                "serving {} games on http://{}",
+                server.games.len(),
+                server.addr
+            ),
+        )
+        .emit(),
+    }
+    http::serve(Arc::clone(&server), listener);
+    if output.is_json() {
+        let requests = server.requests.load(Ordering::Relaxed) as f64;
+        let doc = output::document(
+            "serve",
+            vec![
+                ("type", output::string("stopped")),
+                ("requests", Value::Number(requests)),
+            ],
+        );
+        println!("{}", doc);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Every playable game in `dir`, by file name. A file that doesn't load is
+/// reported and skipped, as is a second game with an id already taken.
+fn load_dir(dir: &Path) -> Result<Vec<(String, GameStructure)>, CliError> {
+    let io = |error| CliError::Io {
+        path: dir.to_path_buf(),
+        error,
+    };
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .map_err(io)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()
+        .map_err(io)?;
+    paths.retain(|p| p.is_file() && Format::of(p).is_some());
+    paths.sort();
+    let mut games: Vec<(String, GameStructure)> = Vec::new();
+    for path in &paths {
+        let game = match load::load_playable(path, None) {
+            Ok(game) => game,
+            Err(e) => {
+                diag::report(&e);
+                continue;
+            }
+        };
+        let id = match game.id.is_empty() {
+            true => path
+                .file_stem()
+                .map_or(String::new(), |s| s.to_string_lossy().into_owned()),
+            false => game.id.clone(),
+        };
+        if games.iter().any(|(other, _)| *other == id) {
+            Diagnostic::new(
+                Level::Warning,
+                format!("another file already has the id {:?}; skipped", id),
+            )
+            .in_file(path)
+            .emit();
+            continue;
+        }
+        games.push((id, game));
+    }
+    Ok(games)
+}
+
+struct Server {
+    games: Vec<(String, GameStructure)>,
+    /// Where the server listens, so shutting down can wake it.
+    addr: SocketAddr,
+    started: Instant,
+    requests: AtomicU64,
+    stopping: AtomicBool,
+}
+
+impl Server {
+    fn new(games: Vec<(String, GameStructure)>, listener: &TcpListener) -> io::Result<Arc<Self>> {
+        Ok(Arc::new(Server {
+            games,
+            addr: listener.local_addr()?,
+            started: Instant::now(),
+            requests: AtomicU64::new(0),
+            stopping: AtomicBool::new(false),
+        }))
+    }
+
+    /// The response to `request`, from `peer` if known.
+    fn handle(&self, request: &Request, peer: Option<SocketAddr>) -> Response {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let path = request.path.trim_end_matches('/');
+        let method = request.method.as_str();
+        match (method, path) {
+            ("GET", "/status") => Response::json(200, self.status().to_string()),
+            ("GET", "/games") => {
+                let games = self
+                    .games
+                    .iter()
+                    .map(|(id, game)| {
+                        Value::Object(vec![
+                            ("id".to_string(), Value::String(id.clone())),
+                            ("name".to_string(), Value::String(game.name.clone())),
+                        ])
+                    })
+                    .collect();
+                Response::json(200, Value::Array(games).to_string())
+            }
+            ("GET", _) if path.starts_with("/games/") => {
+                let id = &path["/games/".len()..];
+                match self.games.iter().find(|(other, _)| other == id) {
+                    Some((_, game)) => Response::json(200, gamedef::to_json(game)),
+                    None => Response::text(404, format!("no game {:?}\n", id)),
+                }
+            }
+            ("POST", "/shutdown") => {
+                if !peer.is_some_and(|p| p.ip().is_loopback()) {
+                    return Response::text(403, "shut down from the server's own machine\n");
+                }
+                self.stopping.store(true, Ordering::SeqCst);
+                Response::json(202, self.status().to_string())
+            }
+            (_, "/status" | "/games" | "/shutdown") => {
+                Response::text(405, format!("{} isn't allowed here\n", method))
+            }
+            (_, _) if path.starts_with("/games/") => {
+                Response::text(405, format!("{} isn't allowed here\n", method))
+            }
+            _ => Response::text(404, format!("nothing at {}\n", request.path)),
+        }
+    }
+
+    fn status(&self) -> Value {
+        let status = match self.stopping.load(Ordering::SeqCst) {
+            true => "stopping",
+            false => "ok",
+        };
+        Value::Object(vec![
+            ("status".to_string(), Value::String(status.to_string())),
+            (
+                "uptimeSeconds".to_string(),
+                Value::Number(self.started.elapsed().as_secs() as f64),
+            ),
+            ("games".to_string(), Value::Number(self.games.len() as f64)),
+            (
+                "requests".to_string(),
+                Value::Number(self.requests.load(Ordering::Relaxed) as f64),
+            ),
+        ])
+    }
+}
+
+impl Handler for Server {
+    fn respond(&self, request: &Request, peer: Option<SocketAddr>) -> Response {
+        self.handle(request, peer)
+    }
+
+    fn stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustlib::presets;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::thread;
+
+    fn get(addr: SocketAddr, method: &str, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "{} {} HTTP/1.1\r\nHost: test\r\n\r\n", method, path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_serve() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let games = vec![("holdem".to_string(), presets::holdem())];
+        let server = Server::new(games, &listener).unwrap();
+        let addr = server.addr;
+        let serving = thread::spawn(move || http::serve(server, listener));
+
+        let status = get(addr, "GET", "/status");
+        assert!(status.starts_with("HTTP/1.1 200 OK\r\n"), "{}", status);
+        assert!(status.ends_with(r#""games":1,"requests":1}"#), "{}", status);
+        let list = get(addr, "GET", "/games/");
+        assert!(list.ends_with(r#"[{"id":"holdem","name":"Texas Hold'em"}]"#));
+        let game = get(addr, "GET", "/games/holdem");
+        let body = game.split("\r\n\r\n").nth(1).unwrap();
+        assert_eq!(gamedef::from_json(body).unwrap(), presets::holdem());
+        assert!(get(addr, "GET", "/games/razz").starts_with("HTTP/1.1 404"));
+        assert!(get(addr, "PUT", "/games").starts_with("HTTP/1.1 405"));
+
+        let stop = get(addr, "POST", "/shutdown");
+        assert!(stop.starts_with("HTTP/1.1 202"), "{}", stop);
+        assert!(stop.contains(r#""status":"stopping""#));
+        serving.join().unwrap();
+        assert!(TcpStream::connect(addr).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+//! `snapfold simulate`: plays many hands of a game between bots and prints
+//! what happened.
+
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use rustlib::gamedef::json::Value;
+use rustlib::simulate::{self, HandResult, SimulationReport};
+use rustlib::GameStructure;
+
+use crate::args::Args;
+use crate::load::{self, Format};
+use crate::output::{self, Output};
+use crate::{bots, CliError};
+
+pub const USAGE: &str = "\
+usage: snapfold simulate FILE [--players N] [--hands N] [--seed N] [--bots BOTS]
+                         [--results PATH] [--output FORMAT] [--format FORMAT]
+
+Plays hands of the game between bots and prints totals: pot sizes, how
+often hands reach a showdown, each seat's results, and how long each phase
+lasts. The same seed always plays the same hands.
+
+Options:
+  --players N      seats at the table (default 6)
+  --hands N        hands to play (default 1000)
+  --seed N         seed for the shuffles and the bots (default 0)
+  --bots BOTS      passive, random or pot-odds for every seat, or one per
+                   seat separated by commas (default random)
+  --results PATH   also write each hand's result to PATH, one JSON object
+                   per line
+  --output FORMAT  text, or json to print the report as JSON
+  --format FORMAT  read FILE as json, yaml, dsl, binpb or game";
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(
+        argv,
+        &[
+            "players", "hands", "seed", "bots", "results", "output", "format",
+        ],
+        &[],
+    )?;
+    let output = Output::from_args(&args)?;
+    let [path] = args.positional() else {
+        return Err(CliError::Usage("simulate needs one game file".to_string()));
+    };
+    let players = args.parsed("players")?.unwrap_or(6);
+    if players < 2 {
+        return Err(CliError::Usage("--players must be at least 2".to_string()));
+    }
+    let hands = args.parsed("hands")?.unwrap_or(1000);
+    let seed = args.parsed("seed")?.unwrap_or(0);
+    let bots = bots::from_arg(args.value("bots").unwrap_or("random"), players, seed)?;
+    let game = load::load_playable(Path::new(path), Format::from_arg(args.value("format"))?)?;
+
+    let mut results = match args.value("results") {
+        Some(out) => {
+            let file = File::create(out).map_err(|error| CliError::Io {
+                path: out.into(),
+                error,
+            })?;
+            Some((out, BufWriter::new(file)))
+        }
+        None => None,
+    };
+    let mut write_error = None;
+    let report = simulate::simulate_games_with(&game, &bots.strategies, hands, seed, |result| {
+        if let Some((_, out)) = &mut results {
+            if write_error.is_none() {
+                write_error = writeln!(out, "{}", hand_json(result)).err();
+            }
+        }
+    });
+    if let Some((out, mut writer)) = results {
+        if let Some(error) = write_error.or_else(|| writer.flush().err()) {
+            return Err(CliError::Io {
+                path: out.into(),
+                error,
+            });
+        }
+    }
+    match report {
+        Ok(report) => {
+            match output.is_json() {
+                true => println!("{}", json(&game, &report, &bots.names, seed).pretty()),
+                false => print!("{}", render(&game, &report, &bots.names, seed)),
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(e) => Err(CliError::Load {
+            path: path.into(),
+            source: None,
+            at: None,
+            message: format!("the simulation stopped: {}", e),
+        }),
+    }
+}
+
+/// A hand's result as one line of JSON.
+fn hand_json(result: &HandResult) -> Value {
+    let number = |n: f64| Value::Number(n);
+    Value::Object(vec![
+        ("hand".to_string(), number(result.hand as f64)),
+        ("button".to_string(), number(result.button as f64)),
+        ("pot".to_string(), number(result.pot as f64)),
+        ("showdown".to_string(), Value::Bool(result.showdown)),
+        (
+            "net".to_string(),
+            Value::Array(result.net.iter().map(|&n| number(n as f64)).collect()),
+        ),
+    ])
+}
+
+/// The report as JSON, with the same figures as [`render`].
+fn json(game: &GameStructure, report: &SimulationReport, bots: &[String], seed: u64) -> Value {
+    let number = |n: f64| Value::Number(n);
+    let seats = report
+        .seats
+        .iter()
+        .zip(bots)
+        .map(|(stats, bot)| {
+            Value::Object(vec![
+                ("bot".to_string(), output::string(bot.as_str())),
+                ("handsWon".to_string(), number(stats.hands_won as f64)),
+                ("winRate".to_string(), number(stats.win_rate)),
+                ("net".to_string(), number(stats.net as f64)),
+            ])
+        })
+        .collect();
+    let phases = report
+        .phases
+        .iter()
+        .map(|phase| {
+            Value::Object(vec![
+                ("label".to_string(), output::string(phase.label.as_str())),
+                ("played".to_string(), number(phase.played as f64)),
+                ("actionsPerHand".to_string(), number(phase.mean_actions())),
+            ])
+        })
+        .collect();
+    output::document(
+        "simulate",
+        vec![
+            ("game", output::string(game.id.as_str())),
+            ("hands", number(report.hands as f64)),
+            ("seed", output::seed_json(seed)),
+            (
+                "pots",
+                Value::Object(vec![
+                    ("mean".to_string(), number(report.pots.mean)),
+                    ("min".to_string(), number(report.pots.min as f64)),
+                    ("max".to_string(), number(report.pots.max as f64)),
+                ]),
+            ),
+            ("showdownRate", number(report.showdown_rate())),
+            ("seats", Value::Array(seats)),
+            ("phases", Value::Array(phases)),
+        ],
+    )
+}
+
+/// The report as printed.
+fn render(game: &GameStructure, report: &SimulationReport, bots: &[String], seed: u64) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{} ({}): {} hands, {} players, seed {}",
+        game.name,
+        game.id,
+        report.hands,
+        bots.len(),
+        seed
+    );
+    let _ = writeln!(
+        out,
+        "pots: mean {:.2}, min {}, max {}",
+        report.pots.mean, report.pots.min, report.pots.max
+    );
+    let _ = writeln!(out, "showdowns: {:.1}%", report.showdown_rate());
+    let _ = writeln!(out);
+    let width = bots.iter().map(String::len).max().unwrap_or(0).max(3);
+    let _ = writeln!(
+        out,
+        "seat  {:<width$}  {:>9}  {:>7}  {:>10}",
+        "bot", "hands won", "win %", "net"
+    );
+    for (seat, (stats, bot)) in report.seats.iter().zip(bots).enumerate() {
+        let _ = writeln!(
+            out,
+            "{:>4}  {:<width$}  {:>9}  {:>6.1}%  {:>+10}",
+            seat, bot, stats.hands_won, stats.win_rate, stats.net
+        );
+    }
+    let _ = writeln!(out);
+    let width = report
+        .phases
+        .iter()
+        .map(|p| p.label.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(5);
+    let _ = writeln!(
+        out,
+        "{:<width$}  {:>7}  {:>12}",
+        "phase", "played", "actions/hand"
+    );
+    for phase in &report.phases {
+        let _ = writeln!(
+            out,
+            "{:<width$}  {:>7}  {:>12.2}",
+            phase.label,
+            phase.played,
+            phase.mean_actions()
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustlib::presets;
+    use rustlib::strategy::{Passive, Strategy};
+
+    #[test]
+    fn test_render() {
+        let game = presets::holdem();
+        let strategies: Vec<Box<dyn Strategy>> = vec![Box::new(Passive), Box::new(Passive)];
+        let report = simulate::simulate_games(&game, &strategies, 10).unwrap();
+        let text = render(&game, &report, &["passive".into(), "passive".into()], 0);
+        assert!(text.starts_with("Texas Hold'em (holdem): 10 hands, 2 players, seed 0\n"));
+        assert!(text.contains("pots: mean 4.00, min 4, max 4\nshowdowns: 100.0%\n"));
+        assert!(text.contains("\nPre-flop "));
+        assert_eq!(text.lines().filter(|l| l.contains("passive")).count(), 2);
+
+        let value = json(&game, &report, &["passive".into(), "passive".into()], 0).to_string();
+        assert!(value.starts_with(r#"{"schema":"snapfold.simulate.v1","game":"holdem""#));
+        assert!(value.contains(r#""pots":{"mean":4,"min":4,"max":4},"showdownRate":100"#));
+    }
+
+    #[test]
+    fn test_hand_json() {
+        let result = HandResult {
+            hand: 3,
+            button: 1,
+            pot: 40,
+            showdown: true,
+            net: vec![20, -20],
+        };
+        assert_eq!(
+            hand_json(&result).to_string(),
+            r#"{"hand":3,"button":1,"pot":40,"showdown":true,"net":[20,-20]}"#
+        );
+    }
+}
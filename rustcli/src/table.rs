@@ -0,0 +1,398 @@
+//! Drawing a hand in the terminal: cards with their suits, the boards, the
+//! pot and each seat, and what happened at the table in words.
+
+use std::fmt::Write;
+
+use rustlib::cards::{Card, Shoe};
+use rustlib::engine::{Action, Chips, Event, GameEngine, LegalAction};
+use rustlib::gamedef::json::Value;
+use rustlib::phase::declare::Side;
+use rustlib::phase::PhaseType;
+
+/// How to draw a hand: the deck's labels for the cards, what to call each
+/// seat, and whose face-down cards to show.
+pub struct Table<'a> {
+    pub shoe: Shoe,
+    pub names: &'a [String],
+    /// The seat watching, whose face-down cards are shown, or `None` to
+    /// show every card.
+    pub viewer: Option<usize>,
+    pub color: bool,
+}
+
+impl<'a> Table<'a> {
+    pub fn new(engine: &GameEngine, names: &'a [String], viewer: Option<usize>) -> Self {
+        Table {
+            shoe: Shoe::for_game(engine.game()),
+            names,
+            viewer,
+            color: false,
+        }
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        match self.color {
+            true => format!("\x1b[{}m{}\x1b[0m", style, text),
+            false => text.to_string(),
+        }
+    }
+
+    /// `card` in brackets, in red if it is a heart or diamond.
+    pub fn card(&self, card: Card) -> String {
+        let label = self.shoe.label(card);
+        let red = label.ends_with('♥') || label.ends_with('♦');
+        let text = format!("[{}]", label);
+        match red {
+            true => self.paint("31", &text),
+            false => self.paint("1", &text),
+        }
+    }
+
+    pub fn cards(&self, cards: &[Card]) -> String {
+        let cards: Vec<String> = cards.iter().map(|&c| self.card(c)).collect();
+        cards.join(" ")
+    }
+
+    fn hidden(&self) -> String {
+        self.paint("2", "[##]")
+    }
+
+    fn seat(&self, seat: usize) -> &str {
+        self.names.get(seat).map_or("?", String::as_str)
+    }
+
+    fn sees(&self, seat: usize) -> bool {
+        self.viewer.is_none_or(|v| v == seat)
+    }
+
+    /// The boards, the pot, and a line per seat.
+    pub fn draw(&self, engine: &GameEngine) -> String {
+        let mut out = String::new();
+        let boards = engine.boards();
+        for (i, board) in boards.iter().enumerate() {
+            let label = match boards.len() {
+                1 => "board".to_string(),
+                _ => format!("board {}", i + 1),
+            };
+            let cards = match board.is_empty() {
+                true => "-".to_string(),
+                false => self.cards(board),
+            };
+            let _ = writeln!(out, "{}: {}", label, cards);
+        }
+        let _ = writeln!(out, "pot: {}", engine.pot());
+        let width = self
+            .names
+            .iter()
+            .map(|n| n.chars().count())
+            .max()
+            .unwrap_or(0);
+        let to_act = engine.to_act();
+        for (i, seat) in engine.seats().iter().enumerate() {
+            let cards: Vec<String> = seat
+                .hole
+                .iter()
+                .map(|c| match c.face_up || self.sees(i) {
+                    true => self.card(c.card),
+                    false => self.hidden(),
+                })
+                .collect();
+            let mut notes = Vec::new();
+            if i == engine.button() {
+                notes.push("button");
+            }
+            if seat.folded {
+                notes.push("folded");
+            }
+            if to_act == Some(i) {
+                notes.push("to act");
+            }
+            let mut line = format!(
+                "{:<width$} {:>7}  {}",
+                self.seat(i),
+                seat.stack,
+                cards.join(" ")
+            );
+            if !notes.is_empty() {
+                let _ = write!(line, "  ({})", notes.join(", "));
+            }
+            let _ = writeln!(out, "{}", line.trim_end());
+        }
+        out
+    }
+
+    /// What [`Table::draw`] shows, as JSON: the boards, the pot, and each
+    /// seat's name, stack and cards, with `null` for a card not shown.
+    pub fn json(&self, engine: &GameEngine) -> Vec<(&'static str, Value)> {
+        let label = |card| Value::String(self.shoe.label(card));
+        let boards = engine
+            .boards()
+            .iter()
+            .map(|b| Value::Array(b.iter().map(|&c| label(c)).collect()))
+            .collect();
+        let seats = engine
+            .seats()
+            .iter()
+            .enumerate()
+            .map(|(i, seat)| {
+                let cards = seat
+                    .hole
+                    .iter()
+                    .map(|c| match c.face_up || self.sees(i) {
+                        true => label(c.card),
+                        false => Value::Null,
+                    })
+                    .collect();
+                Value::Object(vec![
+                    (
+                        "player".to_string(),
+                        Value::String(self.seat(i).to_string()),
+                    ),
+                    ("stack".to_string(), Value::Number(seat.stack as f64)),
+                    ("cards".to_string(), Value::Array(cards)),
+                    ("button".to_string(), Value::Bool(i == engine.button())),
+                    ("folded".to_string(), Value::Bool(seat.folded)),
+                    ("toAct".to_string(), Value::Bool(engine.to_act() == Some(i))),
+                ])
+            })
+            .collect();
+        vec![
+            ("boards", Value::Array(boards)),
+            ("pot", Value::Number(engine.pot() as f64)),
+            ("seats", Value::Array(seats)),
+        ]
+    }
+
+    /// `action` as a verb phrase, such as "raises to 20".
+    pub fn action(&self, action: &Action) -> String {
+        match action {
+            Action::Fold => "folds".to_string(),
+            Action::Check => "checks".to_string(),
+            Action::Call => "calls".to_string(),
+            Action::Bet(total) => format!("bets {}", total),
+            Action::Raise(total) => format!("raises to {}", total),
+            Action::AllIn => "goes all in".to_string(),
+            Action::Discard(cards) if cards.is_empty() => "stands pat".to_string(),
+            Action::Discard(cards) => format!("discards {}", cards.len()),
+            Action::Pass(cards) => format!("passes {}", cards.len()),
+            Action::Reveal(cards) => format!("turns up {}", self.cards(cards)),
+            Action::Declare(side) => format!("declares {}", side_name(*side)),
+            Action::Buy(_) => "buys a card".to_string(),
+        }
+    }
+
+    /// What `event` means at the table, or `None` for bookkeeping no one
+    /// at the table would see.
+    pub fn event(&self, engine: &GameEngine, event: &Event) -> Option<String> {
+        let chips = |seat: usize, what: &str, amount: Chips| {
+            format!("{} posts {} of {}", self.seat(seat), what, amount)
+        };
+        let board = |board: usize| match engine.boards().len() {
+            1 => "the board".to_string(),
+            _ => format!("board {}", board + 1),
+        };
+        Some(match event {
+            Event::PhaseStarted { index } => match &engine.phases()[*index].phase_type {
+                Some(PhaseType::BettingRound(round)) if !round.name.is_empty() => {
+                    self.paint("1;34", &format!("-- {} --", round.name))
+                }
+                _ => return None,
+            },
+            Event::HoleCardsDealt { seat, cards } => {
+                let cards: Vec<String> = cards
+                    .iter()
+                    .map(|c| match c.face_up || self.sees(*seat) {
+                        true => self.card(c.card),
+                        false => self.hidden(),
+                    })
+                    .collect();
+                format!("{} is dealt {}", self.seat(*seat), cards.join(" "))
+            }
+            Event::BoardDealt { board: b, cards } => {
+                format!("{} to {}", self.cards(cards), board(*b))
+            }
+            Event::HiddenBoardDealt { board: b, count } => {
+                format!("{} cards face down to {}", count, board(*b))
+            }
+            Event::CardsRevealed { seat, cards } => {
+                format!("{} turns up {}", self.seat(*seat), self.cards(cards))
+            }
+            Event::BoardRevealed { board: b, cards } => {
+                format!("{} turned up on {}", self.cards(cards), board(*b))
+            }
+            Event::AntePosted { seat, amount } => chips(*seat, "an ante", *amount),
+            Event::BlindPosted { seat, amount } => chips(*seat, "a blind", *amount),
+            Event::DeadBlindPosted { seat, amount } => chips(*seat, "a dead blind", *amount),
+            Event::StraddlePosted { seat, amount } => chips(*seat, "a straddle", *amount),
+            Event::BombPotPosted { seat, amount } => chips(*seat, "a bomb pot", *amount),
+            Event::KillBlindPosted { seat, amount } => chips(*seat, "a kill blind", *amount),
+            Event::UncalledBetReturned { seat, amount } => {
+                format!("{} uncalled returned to {}", amount, self.seat(*seat))
+            }
+            Event::RakeTaken { amount } => format!("the house rakes {}", amount),
+            Event::Acted { seat, action } => {
+                format!("{} {}", self.seat(*seat), self.action(action))
+            }
+            Event::CardsExchanged { seat, count } => match count {
+                0 => format!("{} stands pat", self.seat(*seat)),
+                n => format!("{} draws {}", self.seat(*seat), n),
+            },
+            Event::CardsPassed { from, to, count } => {
+                format!(
+                    "{} passes {} to {}",
+                    self.seat(*from),
+                    count,
+                    self.seat(*to)
+                )
+            }
+            Event::DiscardsShown { seat, cards } => {
+                format!("{} discarded {}", self.seat(*seat), self.cards(cards))
+            }
+            Event::DiscardsReshuffled { count } => {
+                format!("{} discards shuffled into a new deck", count)
+            }
+            Event::RunoutStarted { run } => format!("run {}", run + 1),
+            Event::HandShown { seat, rank, .. } => {
+                let cards: Vec<Card> = engine.seats()[*seat].hole.iter().map(|c| c.card).collect();
+                format!(
+                    "{} shows {}: {}",
+                    self.seat(*seat),
+                    self.cards(&cards),
+                    rank.describe()
+                )
+            }
+            Event::PotAwarded { seat, amount } => {
+                self.paint("1;32", &format!("{} wins {}", self.seat(*seat), amount))
+            }
+            Event::CardExposed { seat, card, .. } => {
+                format!("{}'s {} is exposed", self.seat(*seat), self.card(*card))
+            }
+            Event::Declared { seat, side } => {
+                format!("{} declared {}", self.seat(*seat), side_name(*side))
+            }
+            Event::CardBought {
+                seat,
+                amount,
+                shown,
+                ..
+            } => match shown {
+                Some(card) => format!(
+                    "{} buys {} for {}",
+                    self.seat(*seat),
+                    self.card(*card),
+                    amount
+                ),
+                None => format!("{} buys a card for {}", self.seat(*seat), amount),
+            },
+            Event::BuyDeclined { seat } => format!("{} doesn't buy", self.seat(*seat)),
+            Event::HandDeclaredDead { seat } => format!("{}'s hand is dead", self.seat(*seat)),
+            Event::Misdeal => "misdeal: every chip is returned".to_string(),
+            Event::Shuffled { .. }
+            | Event::ShuffleCommitted { .. }
+            | Event::ShuffleRevealed { .. }
+            | Event::PhaseSkipped { .. }
+            | Event::HandComplete => return None,
+        })
+    }
+}
+
+/// The actions open, for a prompt: "fold | call 2 | raise 4-200".
+pub fn options(legal: &[LegalAction]) -> String {
+    let options: Vec<String> = legal
+        .iter()
+        .map(|a| match a {
+            LegalAction::Fold => "fold".to_string(),
+            LegalAction::Check => "check".to_string(),
+            LegalAction::Call(chips) => format!("call {}", chips),
+            LegalAction::Bet { min, max } if min == max => format!("bet {}", min),
+            LegalAction::Bet { min, max } => format!("bet {}-{}", min, max),
+            LegalAction::Raise { min, max } if min == max => format!("raise {}", min),
+            LegalAction::Raise { min, max } => format!("raise {}-{}", min, max),
+            LegalAction::AllIn(total) => format!("all-in {}", total),
+            LegalAction::Discard { max } => format!("discard up to {} cards", max),
+            LegalAction::Pass { count } => format!("pass {} cards", count),
+            LegalAction::Reveal { count } => format!("reveal {} cards", count),
+            LegalAction::Declare => "declare high, low or both".to_string(),
+            LegalAction::Buy { price, .. } => format!("buy for {} (or check)", price),
+        })
+        .collect();
+    options.join(" | ")
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::High => "high",
+        Side::Low => "low",
+        Side::Both => "both ways",
+        Side::Unknown => "nothing",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustlib::engine::EngineConfig;
+    use rustlib::presets;
+    use rustlib::shuffle::Shuffler;
+
+    #[test]
+    fn test_draw() {
+        let config = EngineConfig {
+            shuffler: Shuffler::seeded(1),
+            ..EngineConfig::new(2)
+        };
+        let engine = GameEngine::with_config(presets::holdem(), config).unwrap();
+        let names = ["you".to_string(), "bot".to_string()];
+        let table = Table::new(&engine, &names, Some(0));
+        let text = table.draw(&engine);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[..2], ["board: -", "pot: 3"]);
+        // The viewer's cards show and the other seat's don't.
+        assert!(!lines[2].contains("[##]") && lines[2].contains("(button, to act)"));
+        assert!(lines[3].ends_with("[##] [##]"));
+        let everyone = Table::new(&engine, &names, None).draw(&engine);
+        assert!(!everyone.contains("[##]"));
+        let json = Value::Object(
+            table
+                .json(&engine)
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        );
+        assert!(json.to_string().ends_with(
+            r#""stack":198,"cards":[null,null],"button":false,"folded":false,"toAct":false}]}"#
+        ));
+
+        let said: Vec<String> = engine
+            .events()
+            .iter()
+            .filter_map(|e| table.event(&engine, e))
+            .collect();
+        assert!(said.contains(&"bot is dealt [##] [##]".to_string()));
+        assert!(said.contains(&"bot posts a blind of 2".to_string()));
+        assert!(said.contains(&"-- Pre-flop --".to_string()));
+    }
+
+    #[test]
+    fn test_cards_and_options() {
+        let mut table = Table {
+            shoe: Shoe::standard(),
+            names: &[],
+            viewer: None,
+            color: false,
+        };
+        let cards = rustlib::cards::parse_cards("Ah 10s").unwrap();
+        assert_eq!(table.cards(&cards), "[A♥] [10♠]");
+        table.color = true;
+        assert_eq!(table.card(cards[0]), "\x1b[31m[A♥]\x1b[0m");
+        assert_eq!(
+            options(&[
+                LegalAction::Fold,
+                LegalAction::Call(2),
+                LegalAction::Raise { min: 4, max: 200 },
+                LegalAction::AllIn(200),
+            ]),
+            "fold | call 2 | raise 4-200 | all-in 200"
+        );
+    }
+}
@@ -0,0 +1,438 @@
+//! `snapfold tournament`: plays a whole tournament between bots and prints
+//! how it finished.
+
+use std::fmt::Write as _;
+use std::path::Path;
+use std::process::ExitCode;
+
+use rustlib::engine::Chips;
+use rustlib::gamedef::json::{self, Value};
+use rustlib::gamedef::yaml;
+use rustlib::tournament::{self, Level, Structure, TournamentReport};
+use rustlib::GameStructure;
+
+use crate::args::Args;
+use crate::load::{self, Format};
+use crate::output::{self, Output};
+use crate::{bots, CliError};
+
+pub const USAGE: &str = "\
+usage: snapfold tournament FILE [--structure PATH] [--players N] [--bots BOTS]
+                           [--seed N] [--output FORMAT] [--format FORMAT]
+
+Plays a tournament of the game between bots, at tables of up to nine: the
+blinds go up level by level, players are knocked out when they lose their
+chips, and tables are broken and balanced as the field shrinks, down to a
+winner. Prints each player's finishing place and prize, and the prizes
+each kind of bot took. The same seed always plays the same tournament.
+
+The structure is a YAML or JSON file such as:
+
+  stack: 1500           # chips each player starts with
+  handsPerLevel: 10     # hands each table plays at a level
+  tableSize: 9
+  buyIn: 100            # the prize pool is the buy-ins added up
+  levels:
+    - blinds: [10, 20]
+    - blinds: [25, 50]
+      ante: 5
+  payouts: [50, 30, 20] # shares of the prize pool, first place first
+
+Every field may be left out. Without --structure, levels start at 10/20
+and go up to 1000/2000, and the top tenth of the field is paid.
+
+Options:
+  --structure PATH  the blinds, stacks and payouts, as above
+  --players N       entrants (default 18)
+  --bots BOTS       passive, random or pot-odds for every player, or one per
+                    player separated by commas (default random)
+  --seed N          seed for the seating, the shuffles and the bots
+                    (default 0)
+  --output FORMAT   text, or json to print the results as JSON
+  --format FORMAT   read FILE as json, yaml, dsl, binpb or game";
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(
+        argv,
+        &["structure", "players", "bots", "seed", "output", "format"],
+        &[],
+    )?;
+    let output = Output::from_args(&args)?;
+    let [path] = args.positional() else {
+        return Err(CliError::Usage(
+            "tournament needs one game file".to_string(),
+        ));
+    };
+    let players = args.parsed("players")?.unwrap_or(18);
+    if players < 2 {
+        return Err(CliError::Usage("--players must be at least 2".to_string()));
+    }
+    let seed = args.parsed("seed")?.unwrap_or(0);
+    let bots = bots::from_arg(args.value("bots").unwrap_or("random"), players, seed)?;
+    let game = load::load_playable(Path::new(path), Format::from_arg(args.value("format"))?)?;
+    let (structure, buy_in) = match args.value("structure") {
+        Some(file) => read_structure(Path::new(file), players)?,
+        None => parse_structure(&Value::Object(Vec::new()), players).map_err(CliError::Usage)?,
+    };
+
+    let report =
+        tournament::run_tournament(&game, &structure, &bots.strategies, seed).map_err(|e| {
+            CliError::Load {
+                path: path.into(),
+                source: None,
+                at: None,
+                message: format!("the tournament stopped: {}", e),
+            }
+        })?;
+    let pool = (buy_in * players as Chips) as f64;
+    match output.is_json() {
+        true => println!(
+            "{}",
+            json(&game, &structure, &report, &bots.names, pool, seed).pretty()
+        ),
+        false => print!(
+            "{}",
+            render(&game, &structure, &report, &bots.names, pool, seed)
+        ),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The structure in the YAML or JSON file at `path`, and the buy-in.
+fn read_structure(path: &Path, players: usize) -> Result<(Structure, Chips), CliError> {
+    let text = std::fs::read_to_string(path).map_err(|error| CliError::Io {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    let failed = |at, message| CliError::Load {
+        path: path.to_path_buf(),
+        source: Some(text.clone()),
+        at,
+        message,
+    };
+    let value = match Format::of(path) {
+        Some(Format::Json) => json::parse(&text),
+        _ => yaml::parse(&text).map(|doc| doc.value),
+    }
+    .map_err(|e| failed(Some((e.line, e.column)), e.message))?;
+    parse_structure(&value, players).map_err(|message| failed(None, message))
+}
+
+/// The blinds the default levels start from, doubled for each level
+/// past the first full cycle.
+const DEFAULT_BLINDS: [(Chips, Chips, Chips); 11] = [
+    (10, 20, 0),
+    (15, 30, 0),
+    (25, 50, 0),
+    (50, 100, 0),
+    (75, 150, 0),
+    (100, 200, 25),
+    (150, 300, 25),
+    (200, 400, 50),
+    (300, 600, 75),
+    (500, 1000, 100),
+    (1000, 2000, 200),
+];
+
+/// The structure a parsed file describes, with defaults for what it leaves
+/// out, and the buy-in.
+fn parse_structure(value: &Value, players: usize) -> Result<(Structure, Chips), String> {
+    let Value::Object(fields) = value else {
+        return Err("the structure should be a mapping of fields".to_string());
+    };
+    let get = |name: &str| fields.iter().find(|(k, _)| k == name).map(|(_, v)| v);
+    let count = |v: &Value, name: &str| match v {
+        Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as Chips),
+        _ => Err(format!("{} should be a whole number", name)),
+    };
+    let field = |name: &str, default: Chips| get(name).map_or(Ok(default), |v| count(v, name));
+    for (key, _) in fields {
+        if ![
+            "stack",
+            "handsPerLevel",
+            "tableSize",
+            "buyIn",
+            "levels",
+            "payouts",
+        ]
+        .contains(&key.as_str())
+        {
+            return Err(format!("unknown field {:?}", key));
+        }
+    }
+    let levels = match get("levels") {
+        None => DEFAULT_BLINDS
+            .iter()
+            .map(|&(small, big, ante)| Level {
+                blinds: vec![small, big],
+                ante,
+            })
+            .collect(),
+        Some(Value::Array(levels)) if !levels.is_empty() => levels
+            .iter()
+            .enumerate()
+            .map(|(i, level)| {
+                let name = format!("levels[{}]", i);
+                let Value::Object(level) = level else {
+                    return Err(format!("{} should have blinds and maybe an ante", name));
+                };
+                let mut out = Level::default();
+                for (key, v) in level {
+                    match (key.as_str(), v) {
+                        ("blinds", Value::Array(blinds)) => {
+                            out.blinds = blinds
+                                .iter()
+                                .map(|b| count(b, &format!("{}.blinds", name)))
+                                .collect::<Result<_, _>>()?
+                        }
+                        ("ante", v) => out.ante = count(v, &format!("{}.ante", name))?,
+                        (key, _) => return Err(format!("{}: unexpected {:?}", name, key)),
+                    }
+                }
+                Ok(out)
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err("levels should be a list of levels".to_string()),
+    };
+    let payouts = match get("payouts") {
+        None => tournament::default_payouts(players),
+        Some(Value::Array(shares)) => shares
+            .iter()
+            .map(|share| match share {
+                Value::Number(n) if *n >= 0.0 => Ok(*n),
+                _ => Err("payouts should be a list of shares".to_string()),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err("payouts should be a list of shares".to_string()),
+    };
+    let structure = Structure {
+        stack: field("stack", 1500)?,
+        levels,
+        rounds_per_level: field("handsPerLevel", 10)? as usize,
+        table_size: field("tableSize", 9)? as usize,
+        payouts,
+    };
+    if structure.table_size < 2 {
+        return Err("tableSize should be at least 2".to_string());
+    }
+    Ok((structure, field("buyIn", 100)?))
+}
+
+fn blinds_text(level: &Level) -> String {
+    let blinds: Vec<String> = level.blinds.iter().map(Chips::to_string).collect();
+    match level.ante {
+        0 => format!("blinds {}", blinds.join("/")),
+        ante => format!("blinds {}, ante {}", blinds.join("/"), ante),
+    }
+}
+
+/// Totals for the players of each kind of bot: how many there were, how
+/// many were paid, their mean finishing place and their prizes.
+fn by_bot<'a>(
+    report: &TournamentReport,
+    bots: &'a [String],
+    pool: f64,
+) -> Vec<(&'a str, usize, usize, f64, f64)> {
+    let mut totals: Vec<(&str, usize, usize, f64, f64)> = Vec::new();
+    for finish in &report.finishes {
+        let bot = bots[finish.player].as_str();
+        let i = match totals.iter().position(|t| t.0 == bot) {
+            Some(i) => i,
+            None => {
+                totals.push((bot, 0, 0, 0.0, 0.0));
+                totals.len() - 1
+            }
+        };
+        let t = &mut totals[i];
+        t.1 += 1;
+        t.2 += usize::from(finish.prize > 0.0);
+        t.3 += finish.place as f64;
+        t.4 += finish.prize * pool;
+    }
+    for t in &mut totals {
+        t.3 /= t.1 as f64;
+    }
+    totals
+}
+
+/// The results as JSON, with the same figures as [`render`].
+fn json(
+    game: &GameStructure,
+    structure: &Structure,
+    report: &TournamentReport,
+    bots: &[String],
+    pool: f64,
+    seed: u64,
+) -> Value {
+    let number = |n: f64| Value::Number(n);
+    let level = &structure.levels[report.level];
+    let finishes = report
+        .finishes
+        .iter()
+        .map(|f| {
+            Value::Object(vec![
+                ("place".to_string(), number(f.place as f64)),
+                (
+                    "player".to_string(),
+                    output::string(format!("Player {}", f.player + 1)),
+                ),
+                ("bot".to_string(), output::string(bots[f.player].as_str())),
+                (
+                    "round".to_string(),
+                    f.round.map_or(Value::Null, |r| number((r + 1) as f64)),
+                ),
+                ("prize".to_string(), number(f.prize * pool)),
+            ])
+        })
+        .collect();
+    let totals = by_bot(report, bots, pool)
+        .into_iter()
+        .map(|(bot, players, paid, place, prizes)| {
+            Value::Object(vec![
+                ("bot".to_string(), output::string(bot)),
+                ("players".to_string(), number(players as f64)),
+                ("paid".to_string(), number(paid as f64)),
+                ("meanPlace".to_string(), number(place)),
+                ("prizes".to_string(), number(prizes)),
+            ])
+        })
+        .collect();
+    output::document(
+        "tournament",
+        vec![
+            ("game", output::string(game.id.as_str())),
+            ("players", number(bots.len() as f64)),
+            ("seed", output::seed_json(seed)),
+            ("rounds", number(report.rounds as f64)),
+            ("hands", number(report.hands as f64)),
+            (
+                "level",
+                Value::Object(vec![
+                    ("number".to_string(), number((report.level + 1) as f64)),
+                    (
+                        "blinds".to_string(),
+                        Value::Array(level.blinds.iter().map(|&b| number(b as f64)).collect()),
+                    ),
+                    ("ante".to_string(), number(level.ante as f64)),
+                ]),
+            ),
+            ("prizePool", number(pool)),
+            ("finishes", Value::Array(finishes)),
+            ("bots", Value::Array(totals)),
+        ],
+    )
+}
+
+/// The results as printed.
+fn render(
+    game: &GameStructure,
+    structure: &Structure,
+    report: &TournamentReport,
+    bots: &[String],
+    pool: f64,
+    seed: u64,
+) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{} ({}): {} players, seed {}",
+        game.name,
+        game.id,
+        bots.len(),
+        seed
+    );
+    let _ = writeln!(
+        out,
+        "{} hands over {} rounds, ending at level {} ({})",
+        report.hands,
+        report.rounds,
+        report.level + 1,
+        blinds_text(&structure.levels[report.level])
+    );
+    let _ = writeln!(out, "prize pool: {:.2}", pool);
+    let _ = writeln!(out);
+    let width = bots.iter().map(String::len).max().unwrap_or(0).max(3);
+    let _ = writeln!(
+        out,
+        "place  {:<10}  {:<width$}  {:>6}  {:>10}",
+        "player", "bot", "out in", "prize"
+    );
+    for f in &report.finishes {
+        let round = f.round.map_or("-".to_string(), |r| format!("{}", r + 1));
+        let _ = writeln!(
+            out,
+            "{:>5}  {:<10}  {:<width$}  {:>6}  {:>10.2}",
+            f.place,
+            format!("Player {}", f.player + 1),
+            bots[f.player],
+            round,
+            f.prize * pool
+        );
+    }
+    let _ = writeln!(out);
+    let _ = writeln!(
+        out,
+        "{:<width$}  {:>7}  {:>4}  {:>10}  {:>10}",
+        "bot", "players", "paid", "mean place", "prizes"
+    );
+    for (bot, players, paid, place, prizes) in by_bot(report, bots, pool) {
+        let _ = writeln!(
+            out,
+            "{:<width$}  {:>7}  {:>4}  {:>10.1}  {:>10.2}",
+            bot, players, paid, place, prizes
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustlib::presets;
+
+    #[test]
+    fn test_structure() {
+        let doc = yaml::parse(
+            "stack: 200\n\
+             handsPerLevel: 4\n\
+             tableSize: 6\n\
+             levels:\n  - blinds: [1, 2]\n  - blinds: [5, 10]\n    ante: 1\n\
+             payouts: [70, 30]\n",
+        )
+        .unwrap();
+        let (structure, buy_in) = parse_structure(&doc.value, 12).unwrap();
+        assert_eq!(buy_in, 100);
+        assert_eq!(structure.stack, 200);
+        assert_eq!(
+            structure.levels[1],
+            Level {
+                blinds: vec![5, 10],
+                ante: 1
+            }
+        );
+        assert_eq!(structure.payouts, [70.0, 30.0]);
+
+        let (defaults, _) = parse_structure(&Value::Object(Vec::new()), 45).unwrap();
+        assert_eq!(defaults.levels.len(), DEFAULT_BLINDS.len());
+        assert_eq!(defaults.payouts.len(), 4);
+        let bad = json::parse(r#"{"levels": [{"blinds": [1, -2]}]}"#).unwrap();
+        assert_eq!(
+            parse_structure(&bad, 2).unwrap_err(),
+            "levels[0].blinds should be a whole number"
+        );
+        let bad = json::parse(r#"{"stacks": 10}"#).unwrap();
+        assert!(parse_structure(&bad, 2).is_err());
+
+        let game = presets::holdem();
+        let bots = bots::from_arg("random,pot-odds", 2, 1).unwrap();
+        let report = tournament::run_tournament(&game, &structure, &bots.strategies, 1).unwrap();
+        let text = render(&game, &structure, &report, &bots.names, 200.0, 1);
+        assert!(text.contains("\nprize pool: 200.00\n"), "{}", text);
+        assert!(text.contains("pot-odds       -      140.00\n"), "{}", text);
+        let value = json(&game, &structure, &report, &bots.names, 200.0, 1).to_string();
+        assert!(value.starts_with(
+            r#"{"schema":"snapfold.tournament.v1","game":"holdem","players":2,"seed":"1","#
+        ));
+        assert!(value.contains(r#""round":null,"prize":140}"#), "{}", value);
+    }
+}
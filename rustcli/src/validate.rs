@@ -0,0 +1,190 @@
+//! `snapfold validate`: loads game definitions, checks that they can be
+//! played, and lints them.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use rustlib::gamedef::json::Value;
+use rustlib::lint::{self, Severity, Span};
+use rustlib::{validation, GameStructure};
+
+use crate::args::Args;
+use crate::diag::{self, Diagnostic, Level};
+use crate::load::{self, Format};
+use crate::output::{self, Output};
+use crate::CliError;
+
+pub const USAGE: &str = "\
+usage: snapfold validate FILE... [--format FORMAT] [--deny-warnings]
+                                 [--output FORMAT]
+
+Loads each game definition, checks that it can be played, and lints it for
+likely mistakes. Exits with status 1 if any file doesn't load or isn't
+valid, or with --deny-warnings, has lint warnings.
+
+Options:
+  --format FORMAT   read every file as json, yaml, dsl, binpb or game
+  --deny-warnings   fail on lint warnings as well as errors
+  --output FORMAT   text, or json to print each file's findings as JSON";
+
+pub fn run(argv: &[String]) -> Result<ExitCode, CliError> {
+    let args = Args::parse(argv, &["format", "output"], &["deny-warnings"])?;
+    let output = Output::from_args(&args)?;
+    let format = Format::from_arg(args.value("format"))?;
+    if args.positional().is_empty() {
+        return Err(CliError::Usage(
+            "validate needs at least one file".to_string(),
+        ));
+    }
+    let mut failed = false;
+    let mut files = Vec::new();
+    for path in args.positional() {
+        let path = Path::new(path);
+        let game = match load::load(path, format) {
+            Ok(game) => game,
+            Err(e @ CliError::Usage(_)) => return Err(e),
+            Err(e) => {
+                diag::report(&e);
+                failed = true;
+                files.push(Value::Object(vec![
+                    (
+                        "path".to_string(),
+                        output::string(path.display().to_string()),
+                    ),
+                    ("ok".to_string(), Value::Bool(false)),
+                    ("loadError".to_string(), output::string(load_error(&e))),
+                ]));
+                continue;
+            }
+        };
+        let findings = check(&game);
+        for (level, message) in &findings {
+            Diagnostic::new(*level, message.as_str())
+                .in_file(path)
+                .emit();
+        }
+        let count = |level| findings.iter().filter(|(l, _)| *l == level).count();
+        let (errors, warnings) = (count(Level::Error), count(Level::Warning));
+        let ok = !(errors > 0 || (warnings > 0 && args.switch("deny-warnings")));
+        files.push(file_json(path, ok, &findings));
+        if !ok {
+            failed = true;
+            eprintln!(
+                "{}: {} errors, {} warnings",
+                path.display(),
+                errors,
+                warnings
+            );
+        } else if !output.is_json() {
+            println!("{}: ok, {} warnings", path.display(), warnings);
+        }
+    }
+    if output.is_json() {
+        let doc = output::document(
+            "validate",
+            vec![("ok", Value::Bool(!failed)), ("files", Value::Array(files))],
+        );
+        println!("{}", doc.pretty());
+    }
+    Ok(if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Why a file couldn't be loaded, without the file's name.
+fn load_error(error: &CliError) -> String {
+    match error {
+        CliError::Usage(message) | CliError::Load { message, .. } => message.clone(),
+        CliError::Io { error, .. } => error.to_string(),
+        CliError::Invalid { errors, .. } => errors
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>()
+            .join("; "),
+    }
+}
+
+/// A file's findings as JSON: whether it passed, and each finding's level
+/// and message.
+fn file_json(path: &Path, ok: bool, findings: &[(Level, String)]) -> Value {
+    let findings = findings
+        .iter()
+        .map(|(level, message)| {
+            let level = match level {
+                Level::Error => "error",
+                Level::Warning => "warning",
+                Level::Info => "info",
+            };
+            Value::Object(vec![
+                ("level".to_string(), output::string(level)),
+                ("message".to_string(), output::string(message.as_str())),
+            ])
+        })
+        .collect();
+    Value::Object(vec![
+        (
+            "path".to_string(),
+            output::string(path.display().to_string()),
+        ),
+        ("ok".to_string(), Value::Bool(ok)),
+        ("findings".to_string(), Value::Array(findings)),
+    ])
+}
+
+/// Every problem with `game`: the validation errors, then the lints.
+fn check(game: &GameStructure) -> Vec<(Level, String)> {
+    let errors = validation::validate(game).err().unwrap_or_default();
+    let errors = errors.iter().map(|e| (Level::Error, e.to_string()));
+    let lints = lint::lint(game).into_iter().map(|l| {
+        let level = match l.severity {
+            Severity::Warning => Level::Warning,
+            Severity::Info => Level::Info,
+        };
+        let message = match l.span {
+            Span::Game => l.kind.to_string(),
+            Span::Phase(i) => format!("phase {}: {}", i, l.kind),
+            Span::Scoring(i) => format!("scoring {}: {}", i, l.kind),
+        };
+        (level, message)
+    });
+    errors.chain(lints).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustlib::presets;
+
+    #[test]
+    fn test_check() {
+        assert!(check(&presets::holdem())
+            .iter()
+            .all(|(level, _)| *level != Level::Error));
+
+        let mut game = presets::holdem();
+        game.phases.swap(0, 1);
+        if let Some(rustlib::phase::PhaseType::BettingRound(round)) = &mut game.phases[3].phase_type
+        {
+            round.name.clear();
+        }
+        let findings = check(&game);
+        assert_eq!(
+            findings[0],
+            (
+                Level::Error,
+                "phase 0: betting before any cards are dealt".to_string()
+            )
+        );
+        assert!(findings.contains(&(
+            Level::Info,
+            "phase 3: betting round has no name".to_string()
+        )));
+        let value = file_json(Path::new("a.yaml"), false, &findings[..1]).to_string();
+        assert_eq!(
+            value,
+            r#"{"path":"a.yaml","ok":false,"findings":[{"level":"error","message":"phase 0: betting before any cards are dealt"}]}"#
+        );
+    }
+}
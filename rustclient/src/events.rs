@@ -0,0 +1,81 @@
+//! The stream of a table's events.
+
+use service_proto3::snapfold::server::{table_event, TableEvent};
+use tonic::Streaming;
+
+use crate::Error;
+
+/// What happens at a table, in order, as the server sends it.
+#[derive(Debug)]
+pub struct Events {
+    stream: Streaming<TableEvent>,
+    sequence: Sequence,
+}
+
+impl Events {
+    pub(crate) fn new(stream: Streaming<TableEvent>) -> Self {
+        Events {
+            stream,
+            sequence: Sequence::default(),
+        }
+    }
+
+    /// The next event, or `None` once the server ends the stream. Fails if
+    /// the server skipped an event, since the table can't be followed then.
+    pub async fn next(&mut self) -> Result<Option<TableEvent>, Error> {
+        let Some(event) = self.stream.message().await? else {
+            return Ok(None);
+        };
+        match event.kind {
+            Some(table_event::Kind::Snapshot(_)) => self.sequence.last = Some(event.sequence),
+            _ => self.sequence.check(event.sequence)?,
+        }
+        Ok(Some(event))
+    }
+
+    /// The sequence of the last event received, to resume from if the
+    /// stream breaks.
+    pub fn last(&self) -> Option<u64> {
+        self.sequence.last
+    }
+}
+
+/// Checks that events come numbered one after another. The first may have
+/// any number, since a stream starts with the hand already in progress. A
+/// snapshot carries the number of the last event it covers.
+#[derive(Clone, Copy, Debug, Default)]
+struct Sequence {
+    last: Option<u64>,
+}
+
+impl Sequence {
+    fn check(&mut self, got: u64) -> Result<(), Error> {
+        if let Some(last) = self.last {
+            if got != last + 1 {
+                return Err(Error::Missed {
+                    expected: last + 1,
+                    got,
+                });
+            }
+        }
+        self.last = Some(got);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence() {
+        let mut sequence = Sequence::default();
+        for n in 7..10 {
+            sequence.check(n).unwrap();
+        }
+        match sequence.check(11) {
+            Err(Error::Missed { expected, got }) => assert_eq!((expected, got), (10, 11)),
+            other => panic!("{:?}", other),
+        }
+    }
+}
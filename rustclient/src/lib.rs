@@ -0,0 +1,435 @@
+//! `snapfold_client`: plays at tables on a snapfold game server, over the
+//! GameService API in rustserver/service.proto, so that programs don't have
+//! to speak the protocol themselves.
+//!
+//! ```ignore
+//! let mut client = Client::connect("http://127.0.0.1:50051").await?;
+//! let table = client.create_table(presets::holdem(), &Settings::default()).await?;
+//! let mut seat = client.join(&table, "alice").await?;
+//! let mut events = seat.events().await?;
+//! while let Some(event) = events.next().await? {
+//!     if let Some(table_event::Kind::Turn(turn)) = &event.kind {
+//!         if turn.seat as usize == seat.seat() {
+//!             seat.act(Action::Call).await?;
+//!         }
+//!     }
+//! }
+//! ```
+
+mod events;
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use rustlib::engine::{Action, Chips};
+use rustlib::{GameStructure, PlayerAction};
+use service_proto3::snapfold::server::game_service_client::GameServiceClient;
+use service_proto3::snapfold::server::{
+    CreateTableRequest, JoinTableRequest, LeaveTableRequest, ListHandsRequest, ListHandsResponse,
+    ListTablesRequest, MuteRequest, PingRequest, SendChatRequest, StreamEventsRequest,
+    SubmitActionRequest, TableSummary,
+};
+use tonic::metadata::AsciiMetadataValue;
+
+/// The canned messages a player can send in chat, with or without text.
+pub use service_proto3::snapfold::server::chat::Emote;
+/// What the server does for a player who has lost their connection and
+/// runs out of time on their turn.
+pub use service_proto3::snapfold::server::create_table_request::Timeout;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity};
+
+pub use events::Events;
+/// The GameService messages, such as the `TableEvent`s that [`Events`]
+/// yields.
+pub use service_proto3::snapfold::server as proto;
+
+/// Why a call to the server failed.
+#[derive(Debug)]
+pub enum Error {
+    /// The server couldn't be reached.
+    Connect(tonic::transport::Error),
+    /// The token has characters that can't be sent in a header.
+    Token,
+    /// The server turned the request down, or the connection broke.
+    Server(tonic::Status),
+    /// Events were skipped: the one numbered `got` came when `expected`
+    /// was due. Watch the table again to catch up.
+    Missed { expected: u64, got: u64 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Connect(e) => write!(f, "can't reach the server: {}", e),
+            Error::Token => f.write_str("the token can't be sent in a header"),
+            Error::Server(status) => write!(f, "the server says: {}", status.message()),
+            Error::Missed { expected, got } => {
+                write!(
+                    f,
+                    "expected event {} from the server, got {}",
+                    expected, got
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<tonic::Status> for Error {
+    fn from(status: tonic::Status) -> Self {
+        Error::Server(status)
+    }
+}
+
+/// How a new table is set up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Settings {
+    /// From 2 to 10.
+    pub seats: usize,
+    /// Chips each player sits down with.
+    pub stack: Chips,
+    /// Blinds, smallest first.
+    pub blinds: Vec<Chips>,
+    pub ante: Chips,
+    /// Seated players with chips it takes to deal a hand.
+    pub start: usize,
+    /// How long a player who has lost their connection has to act.
+    pub timeout: Duration,
+    pub on_timeout: Timeout,
+}
+
+impl Default for Settings {
+    /// Six seats of 200 chips, with blinds of 1 and 2, dealing once two
+    /// players sit down, and checking or folding for a player who has been
+    /// gone 30 seconds.
+    fn default() -> Self {
+        Settings {
+            seats: 6,
+            stack: 200,
+            blinds: vec![1, 2],
+            ante: 0,
+            start: 2,
+            timeout: Duration::from_secs(30),
+            on_timeout: Timeout::CheckOrFold,
+        }
+    }
+}
+
+fn create_request(game: GameStructure, settings: &Settings) -> CreateTableRequest {
+    CreateTableRequest {
+        game: Some(game),
+        seats: settings.seats as i32,
+        stack: settings.stack as i64,
+        blinds: settings.blinds.iter().map(|&b| b as i64).collect(),
+        ante: settings.ante as i64,
+        start: settings.start as i32,
+        timeout_seconds: settings.timeout.as_secs() as i32,
+        on_timeout: settings.on_timeout.into(),
+    }
+}
+
+/// How a client checks a server over TLS, and proves who it is to one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Tls {
+    /// The certificates, in PEM, of the authorities the server's
+    /// certificate must be signed by, or empty to trust the system's.
+    pub ca: Vec<u8>,
+    /// The client's certificate chain and private key, in PEM, for
+    /// servers that require one.
+    pub identity: Option<(Vec<u8>, Vec<u8>)>,
+    /// The name the server's certificate must be for, if not the host in
+    /// its URL.
+    pub domain: Option<String>,
+}
+
+/// A connection to a game server.
+#[derive(Clone, Debug)]
+pub struct Client {
+    inner: GameServiceClient<Channel>,
+    /// Sent with each request once signed in.
+    authorization: Option<AsciiMetadataValue>,
+}
+
+impl Client {
+    /// Connects to the server at `addr`, a URL such as
+    /// `http://127.0.0.1:50051`.
+    pub async fn connect(addr: impl Into<String>) -> Result<Self, Error> {
+        let inner = GameServiceClient::connect(addr.into())
+            .await
+            .map_err(Error::Connect)?;
+        Ok(Client {
+            inner,
+            authorization: None,
+        })
+    }
+
+    /// Connects to the server at `addr` over TLS, a URL such as
+    /// `https://cards.example.com:50051`, checking it and identifying the
+    /// client as `tls` says.
+    pub async fn connect_tls(addr: impl Into<String>, tls: &Tls) -> Result<Self, Error> {
+        let mut config = match tls.ca.as_slice() {
+            [] => ClientTlsConfig::new().with_native_roots(),
+            ca => ClientTlsConfig::new().ca_certificate(Certificate::from_pem(ca)),
+        };
+        if let Some((cert, key)) = &tls.identity {
+            config = config.identity(Identity::from_pem(cert, key));
+        }
+        if let Some(domain) = &tls.domain {
+            config = config.domain_name(domain.clone());
+        }
+        let channel = Endpoint::from_shared(addr.into())
+            .and_then(|endpoint| endpoint.tls_config(config))
+            .map_err(Error::Connect)?
+            .connect()
+            .await
+            .map_err(Error::Connect)?;
+        Ok(Client {
+            inner: GameServiceClient::new(channel),
+            authorization: None,
+        })
+    }
+
+    /// Signs every request in with `token`, for servers that require
+    /// players to sign in. Seats taken are then only good for this
+    /// token's player.
+    pub fn with_token(mut self, token: &str) -> Result<Self, Error> {
+        let value =
+            AsciiMetadataValue::try_from(format!("Bearer {}", token)).map_err(|_| Error::Token)?;
+        self.authorization = Some(value);
+        Ok(self)
+    }
+
+    fn request<T>(&self, message: T) -> tonic::Request<T> {
+        let mut request = tonic::Request::new(message);
+        if let Some(value) = &self.authorization {
+            request
+                .metadata_mut()
+                .insert("authorization", value.clone());
+        }
+        request
+    }
+
+    /// Opens a table for `game`, and returns its id.
+    pub async fn create_table(
+        &mut self,
+        game: GameStructure,
+        settings: &Settings,
+    ) -> Result<String, Error> {
+        let request = self.request(create_request(game, settings));
+        let response = self.inner.create_table(request).await?;
+        Ok(response.into_inner().table_id)
+    }
+
+    /// The open tables, in the order they were opened: those for the game
+    /// with id `game_id`, or all of them if it is empty.
+    pub async fn list_tables(&mut self, game_id: &str) -> Result<Vec<TableSummary>, Error> {
+        let request = self.request(ListTablesRequest {
+            game_id: game_id.to_string(),
+            open: false,
+        });
+        let response = self.inner.list_tables(request).await?;
+        Ok(response.into_inner().tables)
+    }
+
+    /// A page of the hands played to the end that `request` asks for,
+    /// newest first. Those this client's player was dealt into show their
+    /// own cards; others show what a spectator saw.
+    pub async fn list_hands(
+        &mut self,
+        request: ListHandsRequest,
+    ) -> Result<ListHandsResponse, Error> {
+        let request = self.request(request);
+        Ok(self.inner.list_hands(request).await?.into_inner())
+    }
+
+    /// Takes the first open seat at table `table_id` as `name`.
+    pub async fn join(&mut self, table_id: &str, name: &str) -> Result<Seat, Error> {
+        let request = self.request(JoinTableRequest {
+            table_id: table_id.to_string(),
+            name: name.to_string(),
+            ..Default::default()
+        });
+        let response = self.inner.join_table(request).await?.into_inner();
+        Ok(Seat {
+            client: self.clone(),
+            table_id: table_id.to_string(),
+            player_id: response.player_id,
+            seat: response.seat as usize,
+            round_trip: Duration::ZERO,
+        })
+    }
+
+    /// Watches table `table_id` without a seat, seeing only the cards that
+    /// are face up.
+    pub async fn watch(&mut self, table_id: &str) -> Result<Events, Error> {
+        self.stream_events(table_id, "", 0).await
+    }
+
+    /// Watches table `table_id` as [`Client::watch`] does, but with each
+    /// event held back by `delay`, up to an hour, starting with the next
+    /// hand.
+    pub async fn watch_delayed(
+        &mut self,
+        table_id: &str,
+        delay: Duration,
+    ) -> Result<Events, Error> {
+        let request = self.request(StreamEventsRequest {
+            table_id: table_id.to_string(),
+            delay_seconds: delay.as_secs() as i32,
+            ..Default::default()
+        });
+        let stream = self.inner.stream_events(request).await?.into_inner();
+        Ok(Events::new(stream))
+    }
+
+    async fn stream_events(
+        &mut self,
+        table_id: &str,
+        player_id: &str,
+        after: u64,
+    ) -> Result<Events, Error> {
+        let request = self.request(StreamEventsRequest {
+            table_id: table_id.to_string(),
+            player_id: player_id.to_string(),
+            after,
+            ..Default::default()
+        });
+        let stream = self.inner.stream_events(request).await?.into_inner();
+        Ok(Events::new(stream))
+    }
+}
+
+/// A seat taken at a table, for playing it.
+#[derive(Clone, Debug)]
+pub struct Seat {
+    client: Client,
+    table_id: String,
+    player_id: String,
+    seat: usize,
+    /// How long the last ping took, or zero before the first.
+    round_trip: Duration,
+}
+
+impl Seat {
+    pub fn seat(&self) -> usize {
+        self.seat
+    }
+
+    pub fn table_id(&self) -> &str {
+        &self.table_id
+    }
+
+    /// The id that lets whoever has it act for the seat, or on a server
+    /// that signs players in, lets the player who took it.
+    pub fn player_id(&self) -> &str {
+        &self.player_id
+    }
+
+    /// What happens at the table, starting with the hand in progress, with
+    /// the seat's own cards face up.
+    pub async fn events(&mut self) -> Result<Events, Error> {
+        self.resume(0).await
+    }
+
+    /// Picks up the table's events again after a broken stream whose last
+    /// event was `after`, as given by [`Events::last`], with a snapshot of
+    /// the table once caught up. The server holds the seat meanwhile.
+    pub async fn resume(&mut self, after: u64) -> Result<Events, Error> {
+        let (table_id, player_id) = (self.table_id.clone(), self.player_id.clone());
+        self.client
+            .stream_events(&table_id, &player_id, after)
+            .await
+    }
+
+    /// Plays `action` for the seat, if it is the seat's turn and the
+    /// action is legal.
+    pub async fn act(&mut self, action: Action) -> Result<(), Error> {
+        let request = self.client.request(SubmitActionRequest {
+            table_id: self.table_id.clone(),
+            player_id: self.player_id.clone(),
+            action: Some(PlayerAction::from(&action)),
+        });
+        self.client.inner.submit_action(request).await?;
+        Ok(())
+    }
+
+    /// Tells the server the seat's player is still there, with how long
+    /// the last ping took, and returns how long this one took. Pinging
+    /// every few seconds lets the server give the seat as much longer to
+    /// act as its actions take to get there.
+    pub async fn ping(&mut self) -> Result<Duration, Error> {
+        let request = self.client.request(PingRequest {
+            table_id: self.table_id.clone(),
+            player_id: self.player_id.clone(),
+            round_trip_ms: u32::try_from(self.round_trip.as_millis()).unwrap_or(u32::MAX),
+        });
+        let started = Instant::now();
+        self.client.inner.ping(request).await?;
+        self.round_trip = started.elapsed();
+        Ok(self.round_trip)
+    }
+
+    /// Says `text` to everyone at the table, with `emote` if it isn't
+    /// unspecified, once the server's chat filter lets it through.
+    pub async fn chat(&mut self, text: &str, emote: Emote) -> Result<(), Error> {
+        let request = self.client.request(SendChatRequest {
+            table_id: self.table_id.clone(),
+            player_id: self.player_id.clone(),
+            text: text.to_string(),
+            emote: emote.into(),
+        });
+        self.client.inner.send_chat(request).await?;
+        Ok(())
+    }
+
+    /// Stops the seat's events showing what the player called `name`
+    /// says, or starts again if `muted` is false.
+    pub async fn mute(&mut self, name: &str, muted: bool) -> Result<(), Error> {
+        let request = self.client.request(MuteRequest {
+            table_id: self.table_id.clone(),
+            player_id: self.player_id.clone(),
+            name: name.to_string(),
+            muted,
+        });
+        self.client.inner.mute(request).await?;
+        Ok(())
+    }
+
+    /// Gives up the seat, folding first if the hand being played isn't
+    /// over.
+    pub async fn leave(mut self) -> Result<(), Error> {
+        let request = self.client.request(LeaveTableRequest {
+            table_id: self.table_id,
+            player_id: self.player_id,
+        });
+        self.client.inner.leave_table(request).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_request() {
+        let settings = Settings {
+            ante: 1,
+            ..Settings::default()
+        };
+        let request = create_request(rustlib::presets::holdem(), &settings);
+        assert_eq!(request.game, Some(rustlib::presets::holdem()));
+        assert_eq!(
+            (
+                request.seats,
+                request.stack,
+                request.blinds,
+                request.ante,
+                request.start
+            ),
+            (6, 200, vec![1, 2], 1, 2)
+        );
+        assert_eq!(request.timeout_seconds, 30);
+    }
+}
@@ -0,0 +1,148 @@
+//! An append-only record of every use of randomness in a hand, for
+//! investigating disputes.
+//!
+//! Each record carries the SHA-256 of the one before it together with its
+//! own contents, so a record can't be changed, dropped or slipped in
+//! without breaking every hash after it. The operator signs the last hash
+//! with a secret key (HMAC-SHA256), and anyone holding the key can check
+//! the whole log against the signature.
+
+use crate::cards::{Card, Shoe};
+use crate::fingerprint::{hmac, sha256};
+
+/// Where a card taken off the deck went.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Destination {
+    Seat(usize),
+    Board(usize),
+    Burn,
+}
+
+/// One use of randomness.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditEntry {
+    /// The deck was shuffled. `permutation` identifies the order it came
+    /// out in: the SHA-256 of its cards, bottom first. `round` numbers the
+    /// shuffles of one hand as the [`crate::shuffle::Shuffler`] does.
+    Shuffle { round: u64, permutation: [u8; 32] },
+    /// The next card came off the deck.
+    Draw { card: Card, to: Destination },
+}
+
+impl AuditEntry {
+    pub(crate) fn shuffle(round: u64, shoe: &Shoe) -> Self {
+        let order: Vec<u8> = shoe.cards().iter().flat_map(|c| [c.rank, c.suit]).collect();
+        AuditEntry::Shuffle {
+            round,
+            permutation: sha256(&order),
+        }
+    }
+
+    /// The bytes that go into the record's hash.
+    fn encode(&self) -> Vec<u8> {
+        match *self {
+            AuditEntry::Shuffle { round, permutation } => {
+                let mut bytes = vec![0];
+                bytes.extend_from_slice(&round.to_le_bytes());
+                bytes.extend_from_slice(&permutation);
+                bytes
+            }
+            AuditEntry::Draw { card, to } => {
+                let (kind, index) = match to {
+                    Destination::Seat(seat) => (0, seat),
+                    Destination::Board(board) => (1, board),
+                    Destination::Burn => (2, 0),
+                };
+                let mut bytes = vec![1, card.rank, card.suit, kind];
+                bytes.extend_from_slice(&(index as u64).to_le_bytes());
+                bytes
+            }
+        }
+    }
+}
+
+/// An entry with the hash chaining it to the ones before.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AuditRecord {
+    pub entry: AuditEntry,
+    pub hash: [u8; 32],
+}
+
+/// The hand's records, oldest first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuditLog {
+    records: Vec<AuditRecord>,
+}
+
+impl AuditLog {
+    pub(crate) fn append(&mut self, entry: AuditEntry) {
+        let mut data = self.head().to_vec();
+        data.extend(entry.encode());
+        let hash = sha256(&data);
+        self.records.push(AuditRecord { entry, hash });
+    }
+
+    pub fn records(&self) -> &[AuditRecord] {
+        &self.records
+    }
+
+    /// The last record's hash, which commits to the whole log. All zeros
+    /// while the log is empty.
+    pub fn head(&self) -> [u8; 32] {
+        self.records.last().map_or([0; 32], |r| r.hash)
+    }
+
+    /// Signs the log as it stands with the operator's `key`.
+    pub fn sign(&self, key: &[u8]) -> [u8; 32] {
+        hmac(key, &self.head())
+    }
+
+    /// Whether `records` chain together and end in the log `key` signed.
+    pub fn verify(records: &[AuditRecord], key: &[u8], signature: &[u8; 32]) -> bool {
+        let mut log = AuditLog::default();
+        for record in records {
+            log.append(record.entry);
+            if log.head() != record.hash {
+                return false;
+            }
+        }
+        log.sign(key) == *signature
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let mut log = AuditLog::default();
+        log.append(AuditEntry::shuffle(0, &Shoe::standard()));
+        for (i, to) in [
+            Destination::Burn,
+            Destination::Seat(1),
+            Destination::Board(0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let card = Card::new(i as u8, 0);
+            log.append(AuditEntry::Draw { card, to });
+        }
+        let key = b"operator secret";
+        let signature = log.sign(key);
+        assert!(AuditLog::verify(log.records(), key, &signature));
+        assert!(!AuditLog::verify(log.records(), b"wrong key", &signature));
+
+        let mut records = log.records().to_vec();
+        records[2].entry = AuditEntry::Draw {
+            card: Card::new(12, 3),
+            to: Destination::Seat(1),
+        };
+        assert!(!AuditLog::verify(&records, key, &signature));
+        records.remove(2);
+        assert!(!AuditLog::verify(&records, key, &signature));
+        records.truncate(2);
+        assert!(!AuditLog::verify(&records, key, &signature));
+    }
+}
@@ -0,0 +1,367 @@
+//! Ranking many seven-card hands at once.
+//!
+//! Equity calculations spend nearly all their time ranking seven natural
+//! cards from a single deck, over and over. [`evaluate_seven`] ranks
+//! [`LANES`] such hands per step with the same branch-free arithmetic on
+//! every hand's rank bitmasks, which the compiler turns into vector
+//! instructions. On x86-64 processors with AVX2 it uses a copy compiled for
+//! that instruction set, chosen when called; elsewhere the portable copy
+//! runs. Either way the ranks are exactly those of [`Evaluator::evaluate`].
+
+use std::ops::{BitAnd, BitOr, Not, Shl};
+
+use crate::cards::Card;
+use crate::handeval::{Evaluator, HandRank};
+use crate::HandType;
+
+/// Hands ranked per step.
+pub const LANES: usize = 8;
+
+/// The ranks of `hands` under `evaluator`, in order. Each hand must be
+/// seven different cards of the evaluator's deck, with no jokers; others
+/// should go through [`Evaluator::evaluate`].
+pub fn evaluate_seven(evaluator: &Evaluator, hands: &[[Card; 7]]) -> Vec<HandRank> {
+    let kernel = Kernel::new(evaluator);
+    let mut ranks = vec![0; hands.len()];
+    #[cfg(target_arch = "x86_64")]
+    if std::is_x86_feature_detected!("avx2") {
+        // SAFETY: the processor supports AVX2.
+        unsafe { kernel.run_avx2(hands, &mut ranks) };
+        return ranks.into_iter().map(HandRank::from_value).collect();
+    }
+    kernel.run(hands, &mut ranks);
+    ranks.into_iter().map(HandRank::from_value).collect()
+}
+
+/// An evaluator's rules, unpacked for the lanes.
+struct Kernel {
+    low_rank: u32,
+    high_rank: u32,
+    ace_low_straight: bool,
+
+    /// Each hand type's packed strength and type, or `None` if the
+    /// evaluator doesn't rank it.
+    categories: [Option<u32>; 16],
+}
+
+impl Kernel {
+    fn new(evaluator: &Evaluator) -> Self {
+        let mut categories = [None; 16];
+        for (i, &t) in evaluator.order.iter().enumerate() {
+            let strength = (evaluator.order.len() - i) as u32;
+            categories[t as usize & 0xF].get_or_insert(strength << 24 | (t as u32) << 20);
+        }
+        Kernel {
+            low_rank: evaluator.low_rank as u32,
+            high_rank: evaluator.high_rank as u32,
+            ace_low_straight: evaluator.ace_low_straight
+                && evaluator.high_rank >= evaluator.low_rank + 4,
+            categories,
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn run_avx2(&self, hands: &[[Card; 7]], ranks: &mut [u32]) {
+        self.run(hands, ranks)
+    }
+
+    #[inline(always)]
+    fn run(&self, hands: &[[Card; 7]], ranks: &mut [u32]) {
+        for (hands, ranks) in hands.chunks(LANES).zip(ranks.chunks_mut(LANES)) {
+            // Each hand's ranks in each suit, one lane per hand.
+            let mut suits = [Lanes::splat(0); 4];
+            for (lane, hand) in hands.iter().enumerate() {
+                for c in hand {
+                    suits[c.suit as usize & 3].0[lane] |= 1 << (c.rank & 0xF);
+                }
+            }
+            let out = self.rank(suits);
+            ranks.copy_from_slice(&out.0[..ranks.len()]);
+        }
+    }
+
+    /// The packed ranks of the hands with ranks `a`, `b`, `c` and `d` in
+    /// the four suits. Every category each hand makes is scored, and the
+    /// strongest wins, as the strength is in the top bits.
+    #[inline(always)]
+    fn rank(&self, [a, b, c, d]: [Lanes; 4]) -> Lanes {
+        let all = a | b | c | d;
+        let pairs = (a & b) | (a & c) | (a & d) | (b & c) | (b & d) | (c & d);
+        let trips = (a & b & (c | d)) | (c & d & (a | b));
+        let quads = a & b & c & d;
+        // Seven cards can't make a flush in more than one suit.
+        let flush = [a, b, c, d].iter().fold(Lanes::splat(0), |f, &s| {
+            f | s & s.test(|s| s.count_ones() >= 5)
+        });
+
+        let (straight, straight_high) = self.straight(all);
+        let (straight_flush, flush_high) = self.straight(flush);
+        let top_pair = pairs.map(high);
+        let second_pair = (pairs & !bit(top_pair)).map(high);
+        let top_trips = trips.map(high);
+        let top_quads = quads.map(high);
+        let made = |mask: Lanes| mask.test(|m| m != 0);
+        let kickers = |used: Lanes, skip, n| {
+            all.zip(used, |all, used| {
+                ranks_of(keep_top(all & !used, n), n, skip)
+            })
+        };
+
+        let candidates = [
+            (HandType::StraightFlush, straight_flush, flush_high << 16),
+            (
+                HandType::FourOfAKind,
+                made(quads),
+                top_quads << 16 | kickers(bit(top_quads), 1, 1),
+            ),
+            (
+                HandType::FullHouse,
+                made(trips) & made(pairs & !bit(top_trips)),
+                top_trips << 16 | (pairs & !bit(top_trips)).map(high) << 12,
+            ),
+            (
+                HandType::Flush,
+                made(flush),
+                flush.map(|f| ranks_of(keep_top(f, 5), 5, 0)),
+            ),
+            (HandType::Straight, straight, straight_high << 16),
+            (
+                HandType::ThreeOfAKind,
+                made(trips),
+                top_trips << 16 | kickers(bit(top_trips), 1, 2),
+            ),
+            (
+                HandType::TwoPair,
+                pairs.test(|p| p.count_ones() >= 2),
+                top_pair << 16
+                    | second_pair << 12
+                    | kickers(bit(top_pair) | bit(second_pair), 2, 1),
+            ),
+            (
+                HandType::Pair,
+                made(pairs),
+                top_pair << 16 | kickers(bit(top_pair), 1, 3),
+            ),
+        ];
+        // A hand that makes nothing listed ranks as its high cards.
+        let no_pair =
+            self.categories[HandType::NoPair as usize].unwrap_or((HandType::NoPair as u32) << 20);
+        let no_pair = Lanes::splat(no_pair) | kickers(Lanes::splat(0), 0, 5);
+        candidates.iter().fold(no_pair, |best, &(t, made, ranks)| {
+            match self.categories[t as usize] {
+                Some(category) => best.zip(made & (Lanes::splat(category) | ranks), u32::max),
+                None => best,
+            }
+        })
+    }
+
+    /// Whether the ranks `mask` hold a five-card straight, as a lane mask,
+    /// and if so its top rank, with a wheel topped by the fourth-lowest
+    /// rank.
+    #[inline(always)]
+    fn straight(&self, mask: Lanes) -> (Lanes, Lanes) {
+        // Bit r + 1 stands for rank r, leaving room below the lowest rank
+        // for the top rank playing low.
+        let mut w = mask << 1;
+        if self.ace_low_straight {
+            let (low, high) = (self.low_rank, self.high_rank);
+            w = w | mask.map(|m| (m >> high & 1) << low);
+        }
+        let runs = w & w << 1 & w << 2 & w << 3 & w << 4;
+        (
+            runs.test(|r| r != 0),
+            runs.map(|r| high(r).saturating_sub(1)),
+        )
+    }
+}
+
+/// A value for each hand in a step. Each operation applies to every lane
+/// in turn, which the compiler makes a vector instruction or a few.
+#[derive(Clone, Copy)]
+struct Lanes([u32; LANES]);
+
+impl Lanes {
+    #[inline(always)]
+    fn splat(value: u32) -> Self {
+        Lanes([value; LANES])
+    }
+
+    #[inline(always)]
+    fn map(self, f: impl Fn(u32) -> u32) -> Self {
+        Lanes(std::array::from_fn(|i| f(self.0[i])))
+    }
+
+    #[inline(always)]
+    fn zip(self, other: Self, f: impl Fn(u32, u32) -> u32) -> Self {
+        Lanes(std::array::from_fn(|i| f(self.0[i], other.0[i])))
+    }
+
+    /// All ones in the lanes where `f` holds, and zero elsewhere.
+    #[inline(always)]
+    fn test(self, f: impl Fn(u32) -> bool) -> Self {
+        self.map(|v| select(f(v), !0, 0))
+    }
+}
+
+impl BitAnd for Lanes {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitand(self, other: Self) -> Self {
+        self.zip(other, |a, b| a & b)
+    }
+}
+
+impl BitOr for Lanes {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitor(self, other: Self) -> Self {
+        self.zip(other, |a, b| a | b)
+    }
+}
+
+impl Not for Lanes {
+    type Output = Self;
+
+    #[inline(always)]
+    fn not(self) -> Self {
+        self.map(|v| !v)
+    }
+}
+
+impl Shl<u32> for Lanes {
+    type Output = Self;
+
+    #[inline(always)]
+    fn shl(self, n: u32) -> Self {
+        self.map(|v| v << n)
+    }
+}
+
+/// The bit for each lane's rank.
+#[inline(always)]
+fn bit(ranks: Lanes) -> Lanes {
+    ranks.map(|r| 1 << r)
+}
+
+#[inline(always)]
+fn select(condition: bool, yes: u32, no: u32) -> u32 {
+    let mask = (condition as u32).wrapping_neg();
+    yes & mask | no & !mask
+}
+
+/// The highest set bit of `mask`, or 0 if there is none.
+#[inline(always)]
+fn high(mask: u32) -> u32 {
+    31 - (mask | 1).leading_zeros()
+}
+
+/// `mask` with only its `n` highest bits left set.
+#[inline(always)]
+fn keep_top(mut mask: u32, n: u32) -> u32 {
+    for _ in 0..6 {
+        mask = select(mask.count_ones() > n, mask & mask.wrapping_sub(1), mask);
+    }
+    mask
+}
+
+/// The set bits of `mask`, at most `n` of them, as 4-bit ranks highest
+/// first, packed after `skip` ranks already placed.
+#[inline(always)]
+fn ranks_of(mut mask: u32, n: u32, skip: u32) -> u32 {
+    let mut packed = 0;
+    for i in skip..skip + n {
+        let r = high(mask);
+        packed |= select(mask != 0, r << (16 - 4 * i), 0);
+        mask &= !(1 << r);
+    }
+    packed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::{Shoe, SplitMix64};
+    use crate::handeval::SHORT_DECK_ORDER;
+
+    fn random_hands(deck: &[Card], count: usize, seed: u64) -> Vec<[Card; 7]> {
+        let mut rng = SplitMix64(seed);
+        (0..count)
+            .map(|_| {
+                let mut cards = deck.to_vec();
+                crate::shuffle::fisher_yates(&mut cards, &mut rng);
+                std::array::from_fn(|i| cards[i])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_evaluate() {
+        let standard = Shoe::standard().cards().to_vec();
+        let short: Vec<Card> = standard.iter().copied().filter(|c| c.rank >= 4).collect();
+        let reordered = Evaluator::STANDARD.with_order(&SHORT_DECK_ORDER[..5]);
+        for (evaluator, deck) in [
+            (Evaluator::STANDARD, &standard),
+            (Evaluator::SHORT_DECK, &short),
+            (reordered, &standard),
+        ] {
+            // An odd count leaves the last step part full.
+            let hands = random_hands(deck, 20_003, 7);
+            let expected: Vec<HandRank> = hands.iter().map(|h| evaluator.evaluate(h)).collect();
+            assert_eq!(evaluate_seven(&evaluator, &hands), expected);
+            let mut ranks = vec![0; hands.len()];
+            Kernel::new(&evaluator).run(&hands, &mut ranks);
+            assert_eq!(
+                ranks,
+                expected.iter().map(|r| r.value()).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_every_category() {
+        let hands = [
+            "As Ks Qs Js Ts 2c 3d",
+            "Ah 2h 3h 4h 5h Kc Kd",
+            "9c 9d 9h 9s 2c 2d Ah",
+            "Kc Kd Kh 4s 4c 4d 2c",
+            "Ah 9h 7h 4h 2h 2c 2d",
+            "Ah 2c 3d 4s 5h Kc Kd",
+            "9c 9d 9h 3s 2c Ad Kh",
+            "Ac Ad Kh Ks 3c 3d Qs",
+            "9c 9d 4h 3s 2c Ad Kh",
+            "Kc 9d 4h 3s 2c Td Jh",
+        ];
+        let hands: Vec<[Card; 7]> = hands
+            .iter()
+            .map(|s| crate::cards::parse_cards(s).unwrap().try_into().unwrap())
+            .collect();
+        let expected: Vec<HandRank> = hands.iter().map(|h| crate::handeval::evaluate(h)).collect();
+        assert_eq!(evaluate_seven(&Evaluator::STANDARD, &hands), expected);
+    }
+
+    /// A benchmark rather than a test, as timings mean little unoptimized:
+    /// `bazel test -c opt //rustlib:rustlib_test --test_arg=--ignored`.
+    #[test]
+    #[ignore]
+    fn test_faster_than_evaluate() {
+        let hands = random_hands(Shoe::standard().cards(), 200_000, 11);
+        let evaluator = Evaluator::STANDARD;
+        let time = |f: &dyn Fn() -> Vec<HandRank>| {
+            (0..3)
+                .map(|_| {
+                    let start = std::time::Instant::now();
+                    std::hint::black_box(f());
+                    start.elapsed()
+                })
+                .min()
+                .unwrap()
+        };
+        let one_by_one = time(&|| hands.iter().map(|h| evaluator.evaluate(h)).collect());
+        let batched = time(&|| evaluate_seven(&evaluator, &hands));
+        eprintln!("one by one {:?}, batched {:?}", one_by_one, batched);
+        assert!(batched < one_by_one);
+    }
+}
@@ -0,0 +1,705 @@
+//! Betting-round bookkeeping: who is to act, how much they owe, and which
+//! bets and raises are legal.
+
+use std::fmt;
+
+use crate::engine::{Action, Chips, LegalAction};
+
+/// One seat's position in a betting round.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bettor {
+    /// Chips behind, not yet committed to the pot.
+    pub stack: Chips,
+
+    /// Chips committed during this round, including any blinds.
+    pub bet: Chips,
+
+    pub folded: bool,
+}
+
+impl Bettor {
+    pub fn new(stack: Chips, bet: Chips) -> Self {
+        Bettor {
+            stack,
+            bet,
+            folded: false,
+        }
+    }
+
+    fn can_act(&self) -> bool {
+        !self.folded && self.stack > 0
+    }
+}
+
+/// Why an action was rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BettingError {
+    /// The round is over; nobody is left to act.
+    RoundComplete,
+    OutOfTurn {
+        seat: usize,
+        to_act: usize,
+    },
+    CannotCheck {
+        to_call: Chips,
+    },
+    NothingToCall,
+    /// A bet was attempted when the round has already been opened.
+    AlreadyOpened {
+        current_bet: Chips,
+    },
+    /// A raise was attempted before anyone has bet.
+    NotOpened,
+    BetTooSmall {
+        amount: Chips,
+        min: Chips,
+    },
+    RaiseTooSmall {
+        amount: Chips,
+        min: Chips,
+    },
+    ExceedsStack {
+        amount: Chips,
+        max: Chips,
+    },
+    /// The player already acted and has only faced an incomplete (all-in)
+    /// raise since, so they may only call or fold.
+    RaiseNotReopened,
+    /// The action doesn't belong in a betting round.
+    NotABettingAction(Action),
+    /// The bring-in player must open for the bring-in, or complete to
+    /// `complete` where allowed.
+    BringInRequired {
+        amount: Chips,
+        complete: Option<Chips>,
+    },
+}
+
+impl fmt::Display for BettingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BettingError::RoundComplete => write!(f, "the betting round is complete"),
+            BettingError::OutOfTurn { seat, to_act } => {
+                write!(
+                    f,
+                    "seat {} acted out of turn; seat {} is to act",
+                    seat, to_act
+                )
+            }
+            BettingError::CannotCheck { to_call } => {
+                write!(f, "cannot check facing a bet; {} to call", to_call)
+            }
+            BettingError::NothingToCall => write!(f, "there is no bet to call"),
+            BettingError::AlreadyOpened { current_bet } => write!(
+                f,
+                "betting is already open at {}; raise instead",
+                current_bet
+            ),
+            BettingError::NotOpened => write!(f, "there is no bet to raise; bet instead"),
+            BettingError::BetTooSmall { amount, min } => {
+                write!(f, "bet of {} is below the minimum of {}", amount, min)
+            }
+            BettingError::RaiseTooSmall { amount, min } => {
+                write!(f, "raise to {} is below the minimum of {}", amount, min)
+            }
+            BettingError::ExceedsStack { amount, max } => {
+                write!(f, "{} exceeds the {} available", amount, max)
+            }
+            BettingError::RaiseNotReopened => write!(
+                f,
+                "betting was not reopened by a full raise; only call or fold is allowed"
+            ),
+            BettingError::NotABettingAction(a) => {
+                write!(f, "{:?} is not a betting action", a)
+            }
+            BettingError::BringInRequired { amount, complete } => match complete {
+                Some(c) => write!(f, "must bring in for {} or complete to {}", amount, c),
+                None => write!(f, "must bring in for {}", amount),
+            },
+        }
+    }
+}
+
+impl std::error::Error for BettingError {}
+
+/// State of a single betting round.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BettingState {
+    bettors: Vec<Bettor>,
+    to_act: Option<usize>,
+    current_bet: Chips,
+    min_bet: Chips,
+
+    /// Size of the last full bet or raise; the next raise must be at least
+    /// this much more than `current_bet`.
+    min_raise: Chips,
+
+    /// Seats that still owe a decision before the round can close.
+    needs_action: Vec<bool>,
+
+    /// Seats that have acted since the last full bet or raise. They may not
+    /// re-raise if only an incomplete raise has happened since.
+    acted_since_full_raise: Vec<bool>,
+
+    /// A forced opening bet that the first player to act must make, and
+    /// whether they may complete to `min_bet` instead.
+    bring_in: Option<(Chips, bool)>,
+
+    /// Seats that have acted at all this round.
+    acted: Vec<bool>,
+
+    /// A seat (such as a button straddler) that is passed over until
+    /// everyone else has acted once.
+    deferred: Option<usize>,
+}
+
+impl BettingState {
+    /// Creates a round where `min_bet` is the smallest opening bet and raise
+    /// increment. Any chips already in `bettors[i].bet` (blinds) count
+    /// towards the current bet.
+    pub fn new(bettors: Vec<Bettor>, min_bet: Chips) -> Self {
+        let current_bet = bettors.iter().map(|b| b.bet).max().unwrap_or(0);
+        let needs_action = bettors.iter().map(Bettor::can_act).collect();
+        let n = bettors.len();
+        BettingState {
+            bettors,
+            to_act: None,
+            current_bet,
+            min_bet,
+            min_raise: min_bet,
+            needs_action,
+            acted_since_full_raise: vec![false; n],
+            bring_in: None,
+            acted: vec![false; n],
+            deferred: None,
+        }
+    }
+
+    /// Makes `seat` act after everyone else on the first pass around the
+    /// table, as for a button straddle. Call before [`BettingState::begin`].
+    pub fn defer(&mut self, seat: usize) {
+        self.deferred = Some(seat);
+    }
+
+    /// Requires the first player to act to open for `amount`, as in stud.
+    /// If `allow_complete` is set they may instead bet the full `min_bet`.
+    /// Call before [`BettingState::begin`].
+    pub fn set_bring_in(&mut self, amount: Chips, allow_complete: bool) {
+        if self.current_bet == 0 && amount > 0 {
+            self.bring_in = Some((amount, allow_complete));
+        }
+    }
+
+    /// Starts the action with `first`, or the next seat after it that can
+    /// act. Returns false (and the round is complete) if no decisions are
+    /// needed, e.g. because everyone but one player is all-in.
+    pub fn begin(&mut self, first: usize) -> bool {
+        let n = self.bettors.len();
+        let actors = self.bettors.iter().filter(|b| b.can_act()).count();
+        let single_matched = actors == 1
+            && self
+                .bettors
+                .iter()
+                .any(|b| b.can_act() && b.bet >= self.current_bet);
+        if n == 0 || actors == 0 || single_matched || self.live_count() <= 1 {
+            self.to_act = None;
+            return false;
+        }
+        self.to_act = self.next_actor((0..n).map(|i| (first + i) % n));
+        self.to_act.is_some()
+    }
+
+    pub fn bettors(&self) -> &[Bettor] {
+        &self.bettors
+    }
+
+    pub fn to_act(&self) -> Option<usize> {
+        self.to_act
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.to_act.is_none()
+    }
+
+    /// The largest total bet this round.
+    pub fn current_bet(&self) -> Chips {
+        self.current_bet
+    }
+
+    pub fn min_bet(&self) -> Chips {
+        self.min_bet
+    }
+
+    /// The minimum raise increment over the current bet.
+    pub fn min_raise(&self) -> Chips {
+        self.min_raise
+    }
+
+    /// Chips `seat` must add to match the current bet, capped at their stack.
+    pub fn to_call(&self, seat: usize) -> Chips {
+        let b = &self.bettors[seat];
+        (self.current_bet - b.bet.min(self.current_bet)).min(b.stack)
+    }
+
+    /// The most `seat` can bet or raise to: everything they have.
+    pub fn max_total(&self, seat: usize) -> Chips {
+        let b = &self.bettors[seat];
+        b.bet + b.stack
+    }
+
+    /// The smallest legal total for a bet or raise by `seat`, or `None` if
+    /// they cannot bet or raise at all. Short stacks may always go all-in.
+    pub fn min_total(&self, seat: usize) -> Option<Chips> {
+        let b = &self.bettors[seat];
+        let max = self.max_total(seat);
+        if !b.can_act() || max <= self.current_bet {
+            return None;
+        }
+        if self.current_bet == 0 {
+            return Some(self.min_bet.min(max));
+        }
+        if self.acted_since_full_raise[seat] {
+            return None;
+        }
+        Some((self.current_bet + self.min_raise).min(max))
+    }
+
+    /// The actions open to `seat`, with the bounds on any bet or raise.
+    /// Empty unless `seat` is the player to act.
+    pub fn legal_actions(&self, seat: usize) -> Vec<LegalAction> {
+        if self.to_act != Some(seat) {
+            return Vec::new();
+        }
+        let max = self.max_total(seat);
+        let mut actions = Vec::new();
+        if let Some((amount, complete)) = self.bring_in_options(seat) {
+            actions.push(LegalAction::Bet {
+                min: amount,
+                max: amount,
+            });
+            if let Some(min) = complete {
+                actions.push(LegalAction::Bet { min, max });
+            }
+        } else {
+            actions.push(LegalAction::Fold);
+            let to_call = self.to_call(seat);
+            if to_call == 0 {
+                actions.push(LegalAction::Check);
+            } else {
+                actions.push(LegalAction::Call(to_call));
+            }
+            if let Some(min) = self.min_total(seat) {
+                if self.current_bet == 0 {
+                    actions.push(LegalAction::Bet { min, max });
+                } else {
+                    actions.push(LegalAction::Raise { min, max });
+                }
+            }
+        }
+        let stack = self.bettors[seat].stack;
+        let all_in = actions.iter().any(|a| match *a {
+            LegalAction::Call(c) => c == stack,
+            LegalAction::Bet { max: m, .. } | LegalAction::Raise { max: m, .. } => m == max,
+            _ => false,
+        });
+        if all_in {
+            actions.push(LegalAction::AllIn(max));
+        }
+        actions
+    }
+
+    /// Validates and applies an action, returning the chips the player moved
+    /// from their stack into the pot.
+    pub fn apply(&mut self, seat: usize, action: &Action) -> Result<Chips, BettingError> {
+        let to_act = self.to_act.ok_or(BettingError::RoundComplete)?;
+        if seat != to_act {
+            return Err(BettingError::OutOfTurn { seat, to_act });
+        }
+        let to_call = self.to_call(seat);
+        let max = self.max_total(seat);
+        let all_in;
+        let action = match action {
+            Action::AllIn => {
+                all_in = if max <= self.current_bet {
+                    Action::Call
+                } else if self.current_bet == 0 {
+                    Action::Bet(max)
+                } else {
+                    Action::Raise(max)
+                };
+                &all_in
+            }
+            other => other,
+        };
+
+        let bring_in = self.bring_in_options(seat);
+        if let Some((amount, complete)) = bring_in {
+            let valid = matches!(*action, Action::Bet(to)
+                if to == amount || complete.is_some_and(|c| to >= c));
+            if !valid {
+                return Err(BettingError::BringInRequired { amount, complete });
+            }
+        }
+
+        let (paid, full_raise) = match *action {
+            Action::Fold => {
+                self.bettors[seat].folded = true;
+                (0, false)
+            }
+            Action::Check => {
+                if to_call > 0 {
+                    return Err(BettingError::CannotCheck { to_call });
+                }
+                (0, false)
+            }
+            Action::Call => {
+                if to_call == 0 {
+                    return Err(BettingError::NothingToCall);
+                }
+                (to_call, false)
+            }
+            Action::Bet(to) => {
+                if self.current_bet > 0 {
+                    return Err(BettingError::AlreadyOpened {
+                        current_bet: self.current_bet,
+                    });
+                }
+                self.check_total(to, max)?;
+                let min = bring_in.map_or(self.min_bet, |(amount, _)| amount).min(max);
+                if to < min {
+                    return Err(BettingError::BetTooSmall { amount: to, min });
+                }
+                (to - self.bettors[seat].bet, self.raise_to(to))
+            }
+            Action::Raise(to) => {
+                if self.current_bet == 0 {
+                    return Err(BettingError::NotOpened);
+                }
+                self.check_total(to, max)?;
+                let Some(min) = self.min_total(seat) else {
+                    return Err(BettingError::RaiseNotReopened);
+                };
+                if to < min {
+                    return Err(BettingError::RaiseTooSmall { amount: to, min });
+                }
+                (to - self.bettors[seat].bet, self.raise_to(to))
+            }
+            ref other => return Err(BettingError::NotABettingAction(other.clone())),
+        };
+
+        let b = &mut self.bettors[seat];
+        b.stack -= paid;
+        b.bet += paid;
+        let bet = b.bet;
+
+        if bet > self.current_bet {
+            self.current_bet = bet;
+            // Any increase in the bet, full or not, requires a response.
+            for (i, other) in self.bettors.iter().enumerate() {
+                if i != seat && other.can_act() {
+                    self.needs_action[i] = true;
+                }
+            }
+            if full_raise {
+                self.acted_since_full_raise
+                    .iter_mut()
+                    .for_each(|a| *a = false);
+            }
+        }
+        if bring_in.is_some() {
+            self.bring_in = None;
+            if !full_raise {
+                // Completing the bring-in to `min_bet` counts as a full raise.
+                self.min_raise = self.min_bet - bet;
+            }
+        }
+        self.needs_action[seat] = false;
+        self.acted_since_full_raise[seat] = true;
+        self.acted[seat] = true;
+        self.advance(seat);
+        Ok(paid)
+    }
+
+    /// Folds `seat` whether or not it is their turn, as when their hand is
+    /// declared dead. Action moves on if it was theirs.
+    pub fn fold_out(&mut self, seat: usize) {
+        self.bettors[seat].folded = true;
+        self.needs_action[seat] = false;
+        if self.deferred == Some(seat) {
+            self.deferred = None;
+        }
+        if self.to_act == Some(seat) || self.live_count() <= 1 {
+            self.bring_in = None;
+            self.advance(seat);
+        }
+    }
+
+    fn check_total(&self, to: Chips, max: Chips) -> Result<(), BettingError> {
+        if to > max {
+            return Err(BettingError::ExceedsStack { amount: to, max });
+        }
+        Ok(())
+    }
+
+    /// Records a bet or raise to `to`, returning whether it was a full raise
+    /// that reopens the betting.
+    fn raise_to(&mut self, to: Chips) -> bool {
+        let increment = to - self.current_bet;
+        let full = if self.current_bet == 0 {
+            increment >= self.min_bet
+        } else {
+            increment >= self.min_raise
+        };
+        if full {
+            self.min_raise = increment.max(self.min_bet);
+        }
+        full
+    }
+
+    /// The bring-in amount and, if allowed, the completion amount, when
+    /// `seat` is the one owing the bring-in.
+    fn bring_in_options(&self, seat: usize) -> Option<(Chips, Option<Chips>)> {
+        let (amount, allow_complete) = self.bring_in?;
+        let max = self.max_total(seat);
+        let complete = (allow_complete && self.min_bet > amount && max > amount)
+            .then(|| self.min_bet.min(max));
+        Some((amount.min(max), complete))
+    }
+
+    fn live_count(&self) -> usize {
+        self.bettors.iter().filter(|b| !b.folded).count()
+    }
+
+    fn advance(&mut self, from: usize) {
+        let n = self.bettors.len();
+        self.to_act = if self.live_count() <= 1 {
+            None
+        } else {
+            self.next_actor((1..=n).map(|i| (from + i) % n))
+        };
+    }
+
+    /// The first of `seats` that owes a decision, passing over the deferred
+    /// seat while anyone else has yet to act.
+    fn next_actor(&self, seats: impl Iterator<Item = usize>) -> Option<usize> {
+        let pending = |s: usize| self.needs_action[s] && self.bettors[s].can_act();
+        let waiting =
+            |d: usize| (0..self.bettors.len()).any(|s| s != d && pending(s) && !self.acted[s]);
+        let mut deferred = None;
+        for s in seats {
+            if !pending(s) {
+                continue;
+            }
+            if Some(s) == self.deferred && waiting(s) {
+                deferred = Some(s);
+                continue;
+            }
+            return Some(s);
+        }
+        deferred
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three players with 1/2 blinds posted by seats 1 and 2.
+    fn blinds_round() -> BettingState {
+        let mut round = BettingState::new(
+            vec![Bettor::new(100, 0), Bettor::new(99, 1), Bettor::new(98, 2)],
+            2,
+        );
+        assert!(round.begin(0));
+        round
+    }
+
+    #[test]
+    fn test_blinds_round_closes_after_big_blind_checks() {
+        let mut round = blinds_round();
+        assert_eq!(round.apply(0, &Action::Call), Ok(2));
+        assert_eq!(round.apply(1, &Action::Call), Ok(1));
+        assert_eq!(round.to_act(), Some(2));
+        assert_eq!(round.legal_actions(2)[1], LegalAction::Check);
+        assert_eq!(round.apply(2, &Action::Check), Ok(0));
+        assert!(round.is_complete());
+    }
+
+    #[test]
+    fn test_rejects_illegal_actions() {
+        let mut round = blinds_round();
+        assert_eq!(
+            round.apply(1, &Action::Call),
+            Err(BettingError::OutOfTurn { seat: 1, to_act: 0 })
+        );
+        assert_eq!(
+            round.apply(0, &Action::Check),
+            Err(BettingError::CannotCheck { to_call: 2 })
+        );
+        assert_eq!(
+            round.apply(0, &Action::Bet(10)),
+            Err(BettingError::AlreadyOpened { current_bet: 2 })
+        );
+        assert_eq!(
+            round.apply(0, &Action::Raise(3)),
+            Err(BettingError::RaiseTooSmall { amount: 3, min: 4 })
+        );
+        assert_eq!(
+            round.apply(0, &Action::Raise(500)),
+            Err(BettingError::ExceedsStack {
+                amount: 500,
+                max: 100
+            })
+        );
+        assert_eq!(round.apply(0, &Action::Raise(10)), Ok(10));
+        assert_eq!(
+            round.legal_actions(1),
+            vec![
+                LegalAction::Fold,
+                LegalAction::Call(9),
+                LegalAction::Raise { min: 18, max: 100 },
+                LegalAction::AllIn(100),
+            ]
+        );
+        // The next raise must be at least another 8.
+        assert_eq!(
+            round.apply(1, &Action::Raise(15)),
+            Err(BettingError::RaiseTooSmall {
+                amount: 15,
+                min: 18
+            })
+        );
+    }
+
+    #[test]
+    fn test_unopened_round() {
+        let mut round = BettingState::new(vec![Bettor::new(50, 0), Bettor::new(50, 0)], 2);
+        assert!(round.begin(1));
+        assert_eq!(
+            round.apply(1, &Action::Raise(4)),
+            Err(BettingError::NotOpened)
+        );
+        assert_eq!(
+            round.apply(1, &Action::Bet(1)),
+            Err(BettingError::BetTooSmall { amount: 1, min: 2 })
+        );
+        assert_eq!(round.apply(1, &Action::Check), Ok(0));
+        assert_eq!(
+            round.apply(0, &Action::Call),
+            Err(BettingError::NothingToCall)
+        );
+        assert_eq!(round.apply(0, &Action::Check), Ok(0));
+        assert!(round.is_complete());
+    }
+
+    #[test]
+    fn test_bring_in() {
+        let mut round = BettingState::new(
+            vec![
+                Bettor::new(100, 0),
+                Bettor::new(100, 0),
+                Bettor::new(100, 0),
+            ],
+            4,
+        );
+        round.set_bring_in(1, true);
+        assert!(round.begin(1));
+        assert_eq!(
+            round.legal_actions(1),
+            vec![
+                LegalAction::Bet { min: 1, max: 1 },
+                LegalAction::Bet { min: 4, max: 100 },
+                LegalAction::AllIn(100),
+            ]
+        );
+        assert_eq!(
+            round.apply(1, &Action::Fold),
+            Err(BettingError::BringInRequired {
+                amount: 1,
+                complete: Some(4)
+            })
+        );
+        assert_eq!(round.apply(1, &Action::Bet(1)), Ok(1));
+        // Completing to the full bet is a legal raise, after which raises
+        // go up by the full bet again.
+        assert_eq!(round.min_total(2), Some(4));
+        assert_eq!(round.apply(2, &Action::Raise(4)), Ok(4));
+        assert_eq!(round.min_total(0), Some(8));
+        round.apply(0, &Action::Call).unwrap();
+        round.apply(1, &Action::Call).unwrap();
+        assert!(round.is_complete());
+    }
+
+    #[test]
+    fn test_deferred_seat_acts_last() {
+        // Seat 0 is a button straddler; seats 1 and 2 posted the blinds.
+        let mut round = BettingState::new(
+            vec![
+                Bettor::new(96, 4),
+                Bettor::new(99, 1),
+                Bettor::new(98, 2),
+                Bettor::new(100, 0),
+            ],
+            4,
+        );
+        round.defer(0);
+        assert!(round.begin(3));
+        round.apply(3, &Action::Call).unwrap();
+        assert_eq!(round.to_act(), Some(1));
+        round.apply(1, &Action::Call).unwrap();
+        round.apply(2, &Action::Call).unwrap();
+        assert_eq!(round.to_act(), Some(0));
+        assert_eq!(round.min_total(0), Some(8));
+        round.apply(0, &Action::Check).unwrap();
+        assert!(round.is_complete());
+    }
+
+    #[test]
+    fn test_incomplete_all_in_raise_does_not_reopen() {
+        let mut round = BettingState::new(
+            vec![Bettor::new(100, 0), Bettor::new(15, 0), Bettor::new(100, 0)],
+            2,
+        );
+        assert!(round.begin(0));
+        round.apply(0, &Action::Bet(10)).unwrap();
+        // All-in for 15 is less than a full raise to 20.
+        round.apply(1, &Action::Raise(15)).unwrap();
+        // Seat 2 hasn't acted yet and may still raise.
+        assert_eq!(round.min_total(2), Some(25));
+        round.apply(2, &Action::Call).unwrap();
+        // Seat 0 already acted and may only call or fold.
+        assert_eq!(
+            round.legal_actions(0),
+            vec![LegalAction::Fold, LegalAction::Call(5)]
+        );
+        assert_eq!(
+            round.apply(0, &Action::Raise(40)),
+            Err(BettingError::RaiseNotReopened)
+        );
+        round.apply(0, &Action::Call).unwrap();
+        assert!(round.is_complete());
+    }
+
+    #[test]
+    fn test_all_in_resolves_to_call_bet_or_raise() {
+        let mut round = BettingState::new(
+            vec![Bettor::new(30, 0), Bettor::new(100, 0), Bettor::new(20, 0)],
+            2,
+        );
+        assert!(round.begin(0));
+        assert_eq!(round.apply(0, &Action::AllIn), Ok(30));
+        assert_eq!(round.current_bet(), 30);
+        assert_eq!(round.apply(1, &Action::AllIn), Ok(100));
+        // Seat 2 can only call off the rest of their stack.
+        assert_eq!(
+            round.legal_actions(2),
+            vec![
+                LegalAction::Fold,
+                LegalAction::Call(20),
+                LegalAction::AllIn(20)
+            ]
+        );
+        assert_eq!(round.apply(2, &Action::AllIn), Ok(20));
+        assert!(round.is_complete());
+    }
+}
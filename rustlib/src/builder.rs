@@ -0,0 +1,334 @@
+//! Fluent construction of [`GameStructure`]s, as an alternative to writing
+//! out the prost structs by hand.
+
+use crate::gamedef::migrations::CURRENT_VERSION;
+use crate::phase::betting_round::BettingOrder;
+use crate::validation::{validate, ValidationError};
+use crate::{
+    game_structure, phase, scoring, Deck, GameStructure, HandRanking, Phase, Scoring, StandardDeck,
+};
+
+/// Builds a [`GameStructure`] one phase at a time, in the order the phases
+/// are played.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GameBuilder {
+    game: GameStructure,
+}
+
+impl GameBuilder {
+    /// An empty game with the given id, which is also its name until
+    /// [`GameBuilder::name`] is called.
+    pub fn new(id: &str) -> Self {
+        GameBuilder {
+            game: GameStructure {
+                id: id.to_string(),
+                name: id.to_string(),
+                version: CURRENT_VERSION,
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.game.name = name.to_string();
+        self
+    }
+
+    /// The standard 52-card deck. This is also the default.
+    pub fn standard_deck(self) -> Self {
+        self.deck(StandardDeck::DeckPoker)
+    }
+
+    /// The 36-card deck with the deuces through fives removed.
+    pub fn short_deck(self) -> Self {
+        self.deck(StandardDeck::DeckShortDeck)
+    }
+
+    /// Burns `cards` before every deal but the first, whatever the deals
+    /// themselves say.
+    pub fn burn_every_street(mut self, cards: i32) -> Self {
+        self.game.burns = Some(crate::BurnPolicy {
+            mode: crate::burn_policy::Mode::EveryStreet.into(),
+            cards,
+        });
+        self
+    }
+
+    pub fn custom_deck(mut self, deck: Deck) -> Self {
+        self.game.deck = Some(game_structure::Deck::CustomDeck(deck));
+        self
+    }
+
+    /// Deals `cards` face down to every player.
+    pub fn deal_hole(self, cards: i32) -> Self {
+        self.player_deal(cards, false)
+    }
+
+    /// Deals `cards` face up to every player.
+    pub fn deal_up(self, cards: i32) -> Self {
+        self.player_deal(cards, true)
+    }
+
+    /// Deals `down` cards face down and then `up` face up to every player in
+    /// one phase, as on third street in stud.
+    pub fn deal_down_up(self, down: usize, up: usize) -> Self {
+        let mut face_up_cards = vec![false; down];
+        face_up_cards.resize(down + up, true);
+        self.phase(phase::PhaseType::PlayerDeal(phase::PlayerDeal {
+            face_up_cards,
+            ..Default::default()
+        }))
+    }
+
+    /// Deals `cards` to the first community board.
+    pub fn community(self, cards: i32) -> Self {
+        self.community_on(0, cards)
+    }
+
+    /// Deals `cards` to community board `board`, adding boards as needed.
+    pub fn community_on(self, board: i32, cards: i32) -> Self {
+        self.street(board, 0, cards)
+    }
+
+    /// Burns `burn` cards, then deals `cards` to community board `board`.
+    pub fn street(mut self, board: i32, burn: i32, cards: i32) -> Self {
+        self.game.community_board_count = self.game.community_board_count.max(board + 1);
+        self.phase(phase::PhaseType::CommunityDeal(phase::CommunityDeal {
+            name: String::new(),
+            cards,
+            board_idx: board,
+            burn,
+            face_down: false,
+        }))
+    }
+
+    /// A betting round with the minimum bet of one big blind. The first
+    /// round follows the blinds; later ones start left of the dealer.
+    pub fn betting(self, name: &str) -> Self {
+        let first = !self
+            .game
+            .phases
+            .iter()
+            .any(|p| matches!(p.phase_type, Some(phase::PhaseType::BettingRound(_))));
+        let order = if first {
+            BettingOrder::FollowBlinds
+        } else {
+            BettingOrder::LeftOfDealer
+        };
+        self.betting_round(phase::BettingRound {
+            name: name.to_string(),
+            min_bet: 1,
+            order: order.into(),
+            ..Default::default()
+        })
+    }
+
+    /// A betting round with every setting spelled out, e.g. for a stud
+    /// bring-in.
+    pub fn betting_round(self, round: phase::BettingRound) -> Self {
+        self.phase(phase::PhaseType::BettingRound(round))
+    }
+
+    /// A draw in which each player may exchange up to `max` cards.
+    pub fn exchange(self, max: i32) -> Self {
+        self.phase(phase::PhaseType::Exchange(phase::Exchange {
+            max_exchange: max,
+            ..Default::default()
+        }))
+    }
+
+    /// Every player passes `cards` cards at once in `direction`.
+    pub fn pass(self, cards: i32, direction: phase::pass_cards::Direction) -> Self {
+        self.phase(phase::PhaseType::PassCards(phase::PassCards {
+            cards,
+            direction: direction.into(),
+            ..Default::default()
+        }))
+    }
+
+    /// Every player turns `cards` of their face-down cards face up, picking
+    /// which ones if `choose` is set.
+    pub fn reveal(self, cards: i32, choose: bool) -> Self {
+        self.phase(phase::PhaseType::Reveal(phase::Reveal {
+            cards,
+            choose,
+            ..Default::default()
+        }))
+    }
+
+    /// Every player declares for the high half of the pot, the low half or
+    /// both: in secret and all at once, or aloud in turn if `in_turn` is set.
+    pub fn declare(self, in_turn: bool) -> Self {
+        self.phase(phase::PhaseType::Declare(phase::Declare { in_turn }))
+    }
+
+    /// Each player may buy one more card for `price` times the smallest
+    /// blind.
+    pub fn buy(self, price: i32, face_up: bool) -> Self {
+        self.phase(phase::PhaseType::Buy(phase::Buy {
+            price,
+            face_up,
+            ..Default::default()
+        }))
+    }
+
+    /// Plays `phases` `times` times over, e.g. the up-card streets of stud.
+    pub fn repeat(self, times: i32, phases: Vec<Phase>) -> Self {
+        self.phase(phase::PhaseType::Repeat(phase::Repeat {
+            times,
+            phases,
+            ..Default::default()
+        }))
+    }
+
+    pub fn ante(self, payer: phase::ante::Payer) -> Self {
+        self.phase(phase::PhaseType::Ante(phase::Ante {
+            payer: payer.into(),
+        }))
+    }
+
+    /// Appends any phase.
+    pub fn phase(mut self, phase_type: phase::PhaseType) -> Self {
+        self.game.phases.push(Phase {
+            phase_type: Some(phase_type),
+            ..Default::default()
+        });
+        self
+    }
+
+    /// Plays the last phase added only when `condition` holds.
+    pub fn only_if(mut self, condition: phase::Condition) -> Self {
+        if let Some(p) = self.game.phases.last_mut() {
+            p.condition = Some(condition);
+        }
+        self
+    }
+
+    /// Scores the best standard high hand.
+    pub fn high(self) -> Self {
+        self.scoring(Scoring {
+            ranking: Some(scoring::Ranking::StandardRanking(
+                HandRanking::RankingStandard.into(),
+            )),
+            ..Default::default()
+        })
+    }
+
+    /// Scores the best ace-to-five low, qualifying only with no card above
+    /// `qualifier` (e.g. "8"), or with any hand if `qualifier` is empty.
+    pub fn ace_five_low(self, qualifier: &str) -> Self {
+        self.scoring(Scoring {
+            name: "Lo".to_string(),
+            ranking: Some(scoring::Ranking::StandardRanking(
+                HandRanking::RankingAceFive.into(),
+            )),
+            lo: true,
+            lo_qualifier: qualifier.to_string(),
+            top_card_can_be_low: true,
+            ..Default::default()
+        })
+    }
+
+    /// Scores hands by the points `expression` gives them (see
+    /// [`crate::points`]), highest total first.
+    pub fn points(self, name: &str, expression: &str) -> Self {
+        self.scoring(Scoring {
+            name: name.to_string(),
+            ranking: Some(scoring::Ranking::PointsExpression(expression.to_string())),
+            ..Default::default()
+        })
+    }
+
+    /// Adds a scoring rule. Games with more than one split each pot between
+    /// them.
+    pub fn scoring(mut self, scoring: Scoring) -> Self {
+        self.game.scorings.push(scoring);
+        self
+    }
+
+    /// The finished game, checked with [`validate`]. Games without a deck get
+    /// the standard deck, and games without a scoring rule are scored high.
+    pub fn build(mut self) -> Result<GameStructure, Vec<ValidationError>> {
+        if self.game.deck.is_none() {
+            self = self.standard_deck();
+        }
+        if self.game.scorings.is_empty() {
+            self = self.high();
+        }
+        validate(&self.game)?;
+        Ok(self.game)
+    }
+
+    fn player_deal(self, cards: i32, face_up: bool) -> Self {
+        self.phase(phase::PhaseType::PlayerDeal(phase::PlayerDeal {
+            cards,
+            face_up,
+            ..Default::default()
+        }))
+    }
+
+    fn deck(mut self, deck: StandardDeck) -> Self {
+        self.game.deck = Some(game_structure::Deck::StandardDeck(deck.into()));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{GameEngine, HandStatus};
+
+    #[test]
+    fn test_build_omaha_hilo() {
+        let game = GameBuilder::new("omaha-hilo")
+            .deal_hole(4)
+            .betting("Pre-flop")
+            .community(3)
+            .betting("Flop")
+            .high()
+            .ace_five_low("8")
+            .build()
+            .unwrap();
+        assert_eq!(game.name, "omaha-hilo");
+        assert_eq!(game.community_board_count, 1);
+        assert_eq!(
+            game.deck,
+            Some(game_structure::Deck::StandardDeck(
+                StandardDeck::DeckPoker.into()
+            ))
+        );
+        assert_eq!(game.scorings.len(), 2);
+        let orders: Vec<BettingOrder> = game
+            .phases
+            .iter()
+            .filter_map(|p| match &p.phase_type {
+                Some(phase::PhaseType::BettingRound(r)) => Some(r.order()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            orders,
+            vec![BettingOrder::FollowBlinds, BettingOrder::LeftOfDealer]
+        );
+
+        let engine = GameEngine::new(game, 3).unwrap();
+        assert_eq!(engine.status(), HandStatus::InProgress);
+        assert!(engine.seats().iter().all(|s| s.hole.len() == 4));
+    }
+
+    #[test]
+    fn test_build_errors() {
+        assert_eq!(
+            GameBuilder::new("empty").build(),
+            Err(vec![ValidationError::NoPhases])
+        );
+        assert_eq!(
+            GameBuilder::new("nothing")
+                .deal_hole(2)
+                .betting("Bet")
+                .community(0)
+                .build(),
+            Err(vec![ValidationError::EmptyDeal { phase: 2 }])
+        );
+    }
+}
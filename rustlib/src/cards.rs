@@ -0,0 +1,513 @@
+//! Card and deck primitives shared by the engine and the hand evaluators.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::deck::JokerRule;
+use crate::shuffle::{fisher_yates, Shuffler};
+use crate::{game_structure, Deck, GameStructure, StandardDeck};
+
+/// Rank labels of the standard 52-card deck, lowest first.
+pub const STANDARD_RANKS: [&str; 13] = [
+    "2", "3", "4", "5", "6", "7", "8", "9", "10", "J", "Q", "K", "A",
+];
+
+/// Suit labels of the standard deck, in the order given by the proto default.
+pub const STANDARD_SUITS: [&str; 4] = ["♠", "♥", "♦", "♣"];
+
+/// Single-character rank and suit names used for compact notation ("Ah", "Tc").
+pub(crate) const RANK_CHARS: &[u8; 13] = b"23456789TJQKA";
+const SUIT_CHARS: &[u8; 4] = b"shdc";
+
+/// A single playing card.
+///
+/// `rank` and `suit` are ordinals: for the standard deck, rank 0 is the deuce
+/// and rank 12 the ace, and suits follow [`STANDARD_SUITS`]. Custom decks use
+/// the position of the rank/suit in the deck definition instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Card {
+    pub rank: u8,
+    pub suit: u8,
+}
+
+impl Card {
+    /// A joker. Its rank and suit are outside any deck's, so it never
+    /// matches a natural card.
+    pub const JOKER: Card = Card::new(15, 15);
+
+    pub const fn new(rank: u8, suit: u8) -> Self {
+        Card { rank, suit }
+    }
+}
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rank = RANK_CHARS.get(self.rank as usize).map(|&c| c as char);
+        let suit = SUIT_CHARS.get(self.suit as usize).map(|&c| c as char);
+        match (rank, suit) {
+            _ if *self == Card::JOKER => f.write_str("Jk"),
+            (Some(r), Some(s)) => write!(f, "{}{}", r, s),
+            _ => write!(f, "[{}:{}]", self.rank, self.suit),
+        }
+    }
+}
+
+/// Error returned when parsing card notation fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseCardError(pub String);
+
+impl fmt::Display for ParseCardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid card {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseCardError {}
+
+impl FromStr for Card {
+    type Err = ParseCardError;
+
+    /// Parses standard-deck notation such as `"Ah"`, `"Tc"`, `"10d"` or `"Q♠"`,
+    /// or `"Jk"` for a joker.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseCardError(s.to_string());
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("jk") {
+            return Ok(Card::JOKER);
+        }
+        let suit_char = s.chars().last().ok_or_else(err)?;
+        let rank_str = &s[..s.len() - suit_char.len_utf8()];
+
+        let suit = match suit_char {
+            's' | 'S' | '♠' => 0,
+            'h' | 'H' | '♥' => 1,
+            'd' | 'D' | '♦' => 2,
+            'c' | 'C' | '♣' => 3,
+            _ => return Err(err()),
+        };
+        let rank = match rank_str.to_ascii_uppercase().as_str() {
+            "10" | "T" => 8,
+            r if r.len() == 1 => RANK_CHARS
+                .iter()
+                .position(|&c| c == r.as_bytes()[0])
+                .ok_or_else(err)?,
+            _ => return Err(err()),
+        };
+        Ok(Card::new(rank as u8, suit))
+    }
+}
+
+impl From<Card> for crate::Card {
+    fn from(c: Card) -> Self {
+        crate::Card {
+            rank: c.rank.into(),
+            suit: c.suit.into(),
+        }
+    }
+}
+
+impl TryFrom<&crate::Card> for Card {
+    type Error = ParseCardError;
+
+    fn try_from(c: &crate::Card) -> Result<Self, Self::Error> {
+        match (u8::try_from(c.rank), u8::try_from(c.suit)) {
+            (Ok(rank), Ok(suit)) => Ok(Card::new(rank, suit)),
+            _ => Err(ParseCardError(format!("[{}:{}]", c.rank, c.suit))),
+        }
+    }
+}
+
+/// Parses a whitespace- or comma-separated list of cards. Cards may also be
+/// run together, e.g. `"AhKh"`.
+pub fn parse_cards(s: &str) -> Result<Vec<Card>, ParseCardError> {
+    let mut cards = Vec::new();
+    for token in s.split(|c: char| c.is_whitespace() || c == ',') {
+        let mut rest = token;
+        while !rest.is_empty() {
+            // A card is a rank ("10" or one character) followed by a suit.
+            let rank_len = if rest.starts_with("10") { 2 } else { 1 };
+            let suit_len = rest[rank_len.min(rest.len())..]
+                .chars()
+                .next()
+                .map_or(0, char::len_utf8);
+            let len = (rank_len + suit_len).min(rest.len());
+            cards.push(rest[..len].parse()?);
+            rest = &rest[len..];
+        }
+    }
+    Ok(cards)
+}
+
+/// The undealt cards for a hand, along with the labels of the deck they came
+/// from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shoe {
+    ranks: Vec<String>,
+    suits: Vec<String>,
+    cards: Vec<Card>,
+    wild: Vec<Card>,
+    points: Vec<(Card, i32)>,
+    joker_rule: JokerRule,
+
+    /// For shoes kept from hand to hand, how many cards are left when the
+    /// cut card comes out.
+    cut: Option<usize>,
+}
+
+/// The rank and suit labels of a custom deck, with the standard ones standing
+/// in for any that are unset.
+pub(crate) fn deck_labels(deck: &Deck) -> (Vec<&str>, Vec<&str>) {
+    let ranks = if deck.ranks.is_empty() {
+        STANDARD_RANKS.to_vec()
+    } else {
+        deck.ranks.iter().map(String::as_str).collect()
+    };
+    let suits = if deck.suits.is_empty() {
+        STANDARD_SUITS.to_vec()
+    } else {
+        deck.suits.iter().map(String::as_str).collect()
+    };
+    (ranks, suits)
+}
+
+impl Shoe {
+    /// The standard 52-card deck, unshuffled.
+    pub fn standard() -> Self {
+        Self::from_labels(&STANDARD_RANKS, &STANDARD_SUITS, 0)
+    }
+
+    /// The 36-card 6+ deck. Ranks keep their standard ordinals, so the lowest
+    /// card is rank 4 (the six).
+    pub fn short_deck() -> Self {
+        Self::from_labels(&STANDARD_RANKS, &STANDARD_SUITS, 4)
+    }
+
+    /// Builds an unshuffled shoe for the deck configured in `game`, with as
+    /// many copies of it as the game's [`crate::ShoeConfig`] asks for. Games
+    /// that don't specify a deck use the standard 52 cards.
+    pub fn for_game(game: &GameStructure) -> Self {
+        let mut shoe = Self::single_deck(game);
+        let config = game.shoe.clone().unwrap_or_default();
+        let deck = shoe.cards.clone();
+        for _ in 1..config.decks {
+            shoe.cards.extend_from_slice(&deck);
+        }
+        if config.penetration > 0 {
+            let kept = 100 - config.penetration.min(100) as usize;
+            shoe.cut = Some(shoe.cards.len() * kept / 100);
+        }
+        shoe
+    }
+
+    fn single_deck(game: &GameStructure) -> Self {
+        match &game.deck {
+            Some(game_structure::Deck::StandardDeck(d)) => {
+                match StandardDeck::try_from(*d).unwrap_or_default() {
+                    StandardDeck::DeckShortDeck => Self::short_deck(),
+                    _ => Self::standard(),
+                }
+            }
+            Some(game_structure::Deck::CustomDeck(deck)) => Self::custom(deck),
+            None => Self::standard(),
+        }
+    }
+
+    /// A custom deck: every rank in every suit, or the listed cards. Cards
+    /// with labels the deck doesn't have are left out; validation reports
+    /// them.
+    fn custom(deck: &Deck) -> Self {
+        let (ranks, suits) = deck_labels(deck);
+        let mut shoe = Self::from_labels(&ranks, &suits, 0);
+        let find = |rank: &str, suit: &str| {
+            let rank = ranks.iter().position(|r| *r == rank)?;
+            let suit = suits.iter().position(|s| *s == suit)?;
+            Some(Card::new(rank as u8, suit as u8))
+        };
+        if !deck.cards.is_empty() {
+            shoe.cards.clear();
+            for spec in &deck.cards {
+                let Some(card) = find(&spec.rank, &spec.suit) else {
+                    continue;
+                };
+                for _ in 0..spec.copies.max(1) {
+                    shoe.cards.push(card);
+                }
+                if spec.wild {
+                    shoe.wild.push(card);
+                }
+                if spec.points != 0 {
+                    shoe.points.push((card, spec.points));
+                }
+            }
+        }
+        for wild in &deck.wilds {
+            for suit in suits
+                .iter()
+                .filter(|s| wild.suits.is_empty() || wild.suits.iter().any(|w| w == *s))
+            {
+                shoe.wild.extend(find(&wild.rank, suit));
+            }
+        }
+        shoe.wild.sort();
+        shoe.wild.dedup();
+        for _ in 0..deck.jokers {
+            shoe.cards.push(Card::JOKER);
+        }
+        shoe.joker_rule = match deck.joker_rule() {
+            JokerRule::Bug => JokerRule::Bug,
+            _ => JokerRule::Wild,
+        };
+        shoe
+    }
+
+    fn from_labels(ranks: &[&str], suits: &[&str], first_rank: usize) -> Self {
+        let mut cards = Vec::with_capacity((ranks.len() - first_rank) * suits.len());
+        for suit in 0..suits.len() {
+            for rank in first_rank..ranks.len() {
+                cards.push(Card::new(rank as u8, suit as u8));
+            }
+        }
+        Shoe {
+            ranks: ranks.iter().map(|s| s.to_string()).collect(),
+            suits: suits.iter().map(|s| s.to_string()).collect(),
+            cards,
+            wild: Vec::new(),
+            points: Vec::new(),
+            joker_rule: JokerRule::Wild,
+            cut: None,
+        }
+    }
+
+    /// Shuffles the remaining cards. The same seed always produces the same
+    /// order.
+    pub fn shuffle(&mut self, seed: u64) {
+        fisher_yates(&mut self.cards, &mut SplitMix64(seed));
+    }
+
+    /// Shuffles the remaining cards with `shuffler`, for shuffle `round` of
+    /// the hand.
+    pub fn shuffle_with(&mut self, shuffler: &Shuffler, round: u64) {
+        shuffler.shuffle(&mut self.cards, round);
+    }
+
+    /// Takes the next card off the top, if any remain.
+    pub fn deal(&mut self) -> Option<Card> {
+        self.cards.pop()
+    }
+
+    /// Removes specific cards (e.g. known hole cards) from the shoe. Returns
+    /// false if any of them were not present.
+    pub fn remove(&mut self, cards: &[Card]) -> bool {
+        let mut all = true;
+        for c in cards {
+            match self.cards.iter().position(|x| x == c) {
+                Some(i) => {
+                    self.cards.remove(i);
+                }
+                None => all = false,
+            }
+        }
+        all
+    }
+
+    /// Puts `cards` back and shuffles the shoe, as when the discards are
+    /// reshuffled during a draw.
+    pub fn restock(&mut self, cards: &[Card], shuffler: &Shuffler, round: u64) {
+        self.cards.extend_from_slice(cards);
+        self.shuffle_with(shuffler, round);
+    }
+
+    /// Whether the deck definition makes `card` wild. Jokers are wild unless
+    /// they play as the bug.
+    pub fn is_wild(&self, card: Card) -> bool {
+        if card == Card::JOKER {
+            return self.joker_rule != JokerRule::Bug;
+        }
+        self.wild.contains(&card)
+    }
+
+    /// The natural cards the deck definition makes wild, in card order.
+    pub fn wild_cards(&self) -> &[Card] {
+        &self.wild
+    }
+
+    /// What the deck's jokers can stand for: [`JokerRule::Wild`] or
+    /// [`JokerRule::Bug`].
+    pub fn joker_rule(&self) -> JokerRule {
+        self.joker_rule
+    }
+
+    /// The point value the deck definition gives `card`, or 0.
+    pub fn points(&self, card: Card) -> i32 {
+        self.points
+            .iter()
+            .find(|(c, _)| *c == card)
+            .map_or(0, |&(_, p)| p)
+    }
+
+    /// The undealt cards, bottom first: the next card dealt is the last.
+    pub(crate) fn cards(&self) -> &[Card] {
+        &self.cards
+    }
+
+    /// Replaces the undealt cards with `cards`, bottom first.
+    pub(crate) fn set_cards(&mut self, cards: Vec<Card>) {
+        self.cards = cards;
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.cards.len()
+    }
+
+    /// Whether the shoe has to be reshuffled before the next hand: always,
+    /// unless the game keeps its shoe between hands and the cut card hasn't
+    /// come out yet.
+    pub fn needs_shuffle(&self) -> bool {
+        self.cut.is_none_or(|cut| self.cards.len() <= cut)
+    }
+
+    /// Rank labels of the deck definition, lowest first.
+    pub fn rank_labels(&self) -> &[String] {
+        &self.ranks
+    }
+
+    /// The lowest and highest ranks among the shoe's natural cards.
+    pub fn rank_range(&self) -> (u8, u8) {
+        let ranks = self
+            .cards
+            .iter()
+            .filter(|&&c| c != Card::JOKER)
+            .map(|c| c.rank);
+        (ranks.clone().min().unwrap_or(0), ranks.max().unwrap_or(0))
+    }
+
+    /// Suit labels of the deck definition, in the order it lists them.
+    pub fn suit_labels(&self) -> &[String] {
+        &self.suits
+    }
+
+    /// Number of distinct ranks in the deck definition.
+    pub fn rank_count(&self) -> usize {
+        self.ranks.len()
+    }
+
+    /// Human-readable label for a card using this deck's rank and suit names.
+    pub fn label(&self, card: Card) -> String {
+        if card == Card::JOKER {
+            return "Joker".to_string();
+        }
+        let rank = self
+            .ranks
+            .get(card.rank as usize)
+            .map_or("?", String::as_str);
+        let suit = self
+            .suits
+            .get(card.suit as usize)
+            .map_or("?", String::as_str);
+        format!("{}{}", rank, suit)
+    }
+}
+
+/// Small, fast PRNG used for reproducible shuffles. Also handy for seeding
+/// bots in simulations; it is predictable, so never deal real games with
+/// it.
+pub struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64(seed)
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cards() {
+        assert_eq!("Ah".parse::<Card>(), Ok(Card::new(12, 1)));
+        assert_eq!("10d".parse::<Card>(), Ok(Card::new(8, 2)));
+        assert_eq!(
+            parse_cards("AhKh 2c, T♠").unwrap(),
+            vec![
+                Card::new(12, 1),
+                Card::new(11, 1),
+                Card::new(0, 3),
+                Card::new(8, 0)
+            ]
+        );
+        assert!("Xh".parse::<Card>().is_err());
+        assert_eq!(Card::new(8, 0).to_string(), "Ts");
+        assert_eq!(
+            parse_cards("JkAh").unwrap(),
+            vec![Card::JOKER, Card::new(12, 1)]
+        );
+        assert_eq!(Card::JOKER.to_string(), "Jk");
+    }
+
+    #[test]
+    fn test_shoe_shuffle_is_deterministic() {
+        let mut a = Shoe::standard();
+        let mut b = Shoe::standard();
+        a.shuffle(42);
+        b.shuffle(42);
+        assert_eq!(a, b);
+        assert_eq!(a.remaining(), 52);
+        assert_eq!(Shoe::short_deck().remaining(), 36);
+        assert_eq!(Shoe::short_deck().rank_range(), (4, 12));
+    }
+
+    #[test]
+    fn test_custom_deck() {
+        let spec = |rank: &str, suit: &str| crate::deck::CardSpec {
+            rank: rank.to_string(),
+            suit: suit.to_string(),
+            copies: 2,
+            ..Default::default()
+        };
+        // A Pinochle deck: two of each card from the nine up.
+        let mut deck = Deck::default();
+        for rank in ["9", "10", "J", "Q", "K", "A"] {
+            for suit in STANDARD_SUITS {
+                deck.cards.push(spec(rank, suit));
+            }
+        }
+        deck.cards[0].points = 10;
+        deck.cards[1].wild = true;
+        deck.cards.push(spec("1", "♠"));
+        deck.wilds.push(crate::deck::Wild {
+            rank: "A".to_string(),
+            suits: vec!["♥".to_string()],
+        });
+        let game = GameStructure {
+            deck: Some(game_structure::Deck::CustomDeck(deck)),
+            ..Default::default()
+        };
+        let shoe = Shoe::for_game(&game);
+        assert_eq!(shoe.remaining(), 48);
+        assert_eq!(shoe.points(Card::new(7, 0)), 10);
+        assert_eq!(shoe.points(Card::new(7, 1)), 0);
+        assert!(shoe.is_wild(Card::new(7, 1)));
+        assert!(shoe.is_wild(Card::new(12, 1)));
+        assert!(!shoe.is_wild(Card::new(12, 0)));
+
+        let mut deck = Deck {
+            jokers: 2,
+            joker_rule: JokerRule::Bug.into(),
+            ..Default::default()
+        };
+        let bug = Shoe::custom(&deck);
+        assert_eq!(bug.remaining(), 54);
+        assert!(!bug.is_wild(Card::JOKER));
+        deck.joker_rule = JokerRule::Unknown.into();
+        assert!(Shoe::custom(&deck).is_wild(Card::JOKER));
+    }
+}
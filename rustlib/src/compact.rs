@@ -0,0 +1,589 @@
+//! A compact encoding of a hand in progress, for holding many hands in
+//! memory at once in simulations and solvers.
+//!
+//! The engine is deterministic: a hand's state follows from how it was set
+//! up, the order each shuffle left the deck in, and what was done at the
+//! table. [`CompactState`] packs just those into one small buffer, with
+//! cards as single bytes and chip counts as fixed 32-bit integers, and
+//! [`CompactState::restore`] plays them back into an engine in the same
+//! state as the original.
+
+use std::fmt;
+
+use crate::audit::AuditEntry;
+use crate::blinds::kill::Size;
+use crate::cards::{Card, Shoe};
+use crate::engine::{Action, Chips, EngineConfig, EngineError, GameEngine, Input};
+use crate::kill::KillPot;
+use crate::phase::declare::Side;
+use crate::positions::{HandPositions, MissedBlinds};
+use crate::shuffle::{Commitment, Shuffler};
+use crate::GameStructure;
+
+/// Bumped whenever the layout changes.
+const VERSION: u8 = 1;
+
+/// Stands for `None` in a byte that otherwise holds a seat.
+const NO_SEAT: u8 = u8::MAX;
+
+/// Why a hand can't be packed or unpacked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompactError {
+    /// A chip count, seat, card or count is too big for its field.
+    OutOfRange,
+    /// The bytes end early, or hold something no hand packs to.
+    Malformed,
+    /// Playing the hand back failed, as when it is restored against a
+    /// different game than it was packed from.
+    Engine(EngineError),
+}
+
+impl fmt::Display for CompactError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompactError::OutOfRange => write!(f, "the hand has a value too big to pack"),
+            CompactError::Malformed => write!(f, "the packed hand is malformed"),
+            CompactError::Engine(e) => write!(f, "the packed hand doesn't play back: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CompactError {}
+
+impl From<EngineError> for CompactError {
+    fn from(e: EngineError) -> Self {
+        CompactError::Engine(e)
+    }
+}
+
+/// A hand of any game, packed. Unpacking needs the game it was packed
+/// from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CompactState {
+    bytes: Box<[u8]>,
+}
+
+impl CompactState {
+    /// Packs the hand in `engine`.
+    pub fn capture(engine: &GameEngine) -> Result<Self, CompactError> {
+        let config = engine.config();
+        let mut w = Writer::default();
+        w.u8(VERSION);
+        w.count(config.stacks.len())?;
+        for &stack in &config.stacks {
+            w.chips(stack)?;
+        }
+        w.count(config.blinds.len())?;
+        for &blind in &config.blinds {
+            w.chips(blind)?;
+        }
+        w.chips(config.ante)?;
+        w.seat(Some(config.button))?;
+        w.count(config.straddles)?;
+        w.chips(config.bomb_pot)?;
+        w.u32(config.runouts);
+        w.u8(u8::from(config.button_straddle) | u8::from(config.bomb_pot_double_board) << 1);
+        match &config.positions {
+            Some(p) => {
+                w.u8(1);
+                w.seat(Some(p.button))?;
+                w.u8(u8::from(p.dead_button));
+                w.seat(p.small_blind)?;
+                w.seat(Some(p.big_blind))?;
+                w.count(p.owed.len())?;
+                for &(seat, missed) in &p.owed {
+                    w.seat(Some(seat))?;
+                    w.u8(u8::from(missed.small) | u8::from(missed.big) << 1);
+                }
+            }
+            None => w.u8(0),
+        }
+        match config.kill {
+            Some(kill) => {
+                w.u8(1);
+                w.seat(Some(kill.seat))?;
+                w.u8(kill.size as i32 as u8);
+            }
+            None => w.u8(0),
+        }
+
+        // The order each shuffle left the deck in: the cards drawn after
+        // it, last first, under whatever is still undealt. A hand dealt
+        // from a kept shoe starts with draws before any shuffle.
+        let mut orders: Vec<(Option<u64>, Vec<Card>)> = vec![(None, Vec::new())];
+        for record in engine.audit_log().records() {
+            match record.entry {
+                AuditEntry::Shuffle { round, .. } => orders.push((Some(round), Vec::new())),
+                AuditEntry::Draw { card, .. } => orders.last_mut().unwrap().1.push(card),
+            }
+        }
+        for order in &mut orders {
+            order.1.reverse();
+        }
+        let undealt = engine.shoe().cards();
+        orders
+            .last_mut()
+            .unwrap()
+            .1
+            .splice(0..0, undealt.iter().copied());
+        let kept = orders.len() == 1 || !orders[0].1.is_empty();
+        match kept {
+            true => {
+                w.u8(1);
+                w.cards(&orders[0].1)?;
+            }
+            false => w.u8(0),
+        }
+        match &config.shuffler {
+            Shuffler::Seeded(seed) => {
+                w.u8(0);
+                w.u64(*seed);
+            }
+            Shuffler::Committed(c) => {
+                w.u8(1);
+                w.u64(c.seed);
+                w.bytes.extend_from_slice(&c.salt);
+            }
+            _ => {
+                w.u8(2);
+                w.count(orders.len() - 1)?;
+                for (round, order) in &orders[1..] {
+                    w.u64(round.unwrap_or_default());
+                    w.cards(order)?;
+                }
+            }
+        }
+
+        let ops = engine.inputs();
+        w.u32(u32::try_from(ops.len()).map_err(|_| CompactError::OutOfRange)?);
+        for op in ops {
+            match op {
+                Input::Act(seat, action) => {
+                    w.u8(0);
+                    w.seat(Some(*seat))?;
+                    w.action(action)?;
+                }
+                Input::Expose(seat, card) => {
+                    w.u8(1);
+                    w.seat(Some(*seat))?;
+                    w.card(*card)?;
+                }
+                Input::DeadHand(seat) => {
+                    w.u8(2);
+                    w.seat(Some(*seat))?;
+                }
+                Input::Misdeal => w.u8(3),
+            }
+        }
+        Ok(CompactState {
+            bytes: w.bytes.into_boxed_slice(),
+        })
+    }
+
+    /// Plays the hand back into an engine for `game`, which must be the
+    /// game it was packed from.
+    pub fn restore(&self, game: GameStructure) -> Result<GameEngine, CompactError> {
+        self.replay(game, |_| {})
+    }
+
+    /// As [`CompactState::restore`], calling `each` with the engine once
+    /// it has dealt and again after each action or other input played
+    /// back, to step through the hand.
+    pub fn replay(
+        &self,
+        game: GameStructure,
+        mut each: impl FnMut(&GameEngine),
+    ) -> Result<GameEngine, CompactError> {
+        let mut r = Reader {
+            bytes: &self.bytes,
+            at: 0,
+            seats: 0,
+        };
+        if r.u8()? != VERSION {
+            return Err(CompactError::Malformed);
+        }
+        let mut config = EngineConfig::new(0);
+        config.stacks = (0..r.u8()?).map(|_| r.chips()).collect::<Result<_, _>>()?;
+        r.seats = config.stacks.len();
+        config.blinds = (0..r.u8()?).map(|_| r.chips()).collect::<Result<_, _>>()?;
+        config.ante = r.chips()?;
+        config.button = r.seat()?.ok_or(CompactError::Malformed)?;
+        config.straddles = r.u8()? as usize;
+        config.bomb_pot = r.chips()?;
+        config.runouts = r.u32()?;
+        let flags = r.u8()?;
+        config.button_straddle = flags & 1 != 0;
+        config.bomb_pot_double_board = flags & 2 != 0;
+        if r.u8()? == 1 {
+            let button = r.seat()?.ok_or(CompactError::Malformed)?;
+            let dead_button = r.u8()? != 0;
+            let small_blind = r.seat()?;
+            let big_blind = r.seat()?.ok_or(CompactError::Malformed)?;
+            let owed = (0..r.u8()?)
+                .map(|_| {
+                    let seat = r.seat()?.ok_or(CompactError::Malformed)?;
+                    let missed = r.u8()?;
+                    let missed = MissedBlinds {
+                        small: missed & 1 != 0,
+                        big: missed & 2 != 0,
+                    };
+                    Ok((seat, missed))
+                })
+                .collect::<Result<_, CompactError>>()?;
+            config.positions = Some(HandPositions {
+                button,
+                dead_button,
+                small_blind,
+                big_blind,
+                owed,
+            });
+        }
+        if r.u8()? == 1 {
+            let seat = r.seat()?.ok_or(CompactError::Malformed)?;
+            let size = Size::try_from(r.u8()? as i32).map_err(|_| CompactError::Malformed)?;
+            config.kill = Some(KillPot { seat, size });
+        }
+        if r.u8()? == 1 {
+            let mut shoe = Shoe::for_game(&game);
+            shoe.set_cards(r.cards()?);
+            config.shoe = Some(shoe);
+        }
+        config.shuffler = match r.u8()? {
+            0 => Shuffler::Seeded(r.u64()?),
+            1 => {
+                let seed = r.u64()?;
+                let salt = r.take(16)?.try_into().unwrap();
+                Shuffler::Committed(Commitment { seed, salt })
+            }
+            2 => Shuffler::Recorded(
+                (0..r.u8()?)
+                    .map(|_| Ok((r.u64()?, r.cards()?)))
+                    .collect::<Result<_, CompactError>>()?,
+            ),
+            _ => return Err(CompactError::Malformed),
+        };
+
+        let mut engine = GameEngine::with_config(game, config)?;
+        each(&engine);
+        for _ in 0..r.u32()? {
+            match r.u8()? {
+                0 => {
+                    let seat = r.seat()?.ok_or(CompactError::Malformed)?;
+                    engine.apply_action(seat, r.action()?)?;
+                }
+                1 => {
+                    let seat = r.seat()?.ok_or(CompactError::Malformed)?;
+                    engine.expose_card(seat, r.card()?)?;
+                }
+                2 => engine.declare_dead_hand(r.seat()?.ok_or(CompactError::Malformed)?)?,
+                3 => engine.declare_misdeal()?,
+                _ => return Err(CompactError::Malformed),
+            }
+            each(&engine);
+        }
+        if r.at != r.bytes.len() {
+            return Err(CompactError::Malformed);
+        }
+        Ok(engine)
+    }
+
+    /// The packed bytes, to store or send.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// A state from bytes that [`Self::as_bytes`] gave. They're checked
+    /// when restored.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        CompactState {
+            bytes: bytes.into(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, v: u8) {
+        self.bytes.push(v);
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.bytes.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.bytes.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn chips(&mut self, chips: Chips) -> Result<(), CompactError> {
+        self.u32(u32::try_from(chips).map_err(|_| CompactError::OutOfRange)?);
+        Ok(())
+    }
+
+    fn count(&mut self, n: usize) -> Result<(), CompactError> {
+        self.u8(u8::try_from(n).map_err(|_| CompactError::OutOfRange)?);
+        Ok(())
+    }
+
+    fn seat(&mut self, seat: Option<usize>) -> Result<(), CompactError> {
+        match seat {
+            Some(seat) if seat < NO_SEAT as usize => self.u8(seat as u8),
+            Some(_) => return Err(CompactError::OutOfRange),
+            None => self.u8(NO_SEAT),
+        }
+        Ok(())
+    }
+
+    /// A card as its rank and suit in four bits each.
+    fn card(&mut self, card: Card) -> Result<(), CompactError> {
+        if card.rank > 0xF || card.suit > 0xF {
+            return Err(CompactError::OutOfRange);
+        }
+        self.u8(card.rank << 4 | card.suit);
+        Ok(())
+    }
+
+    fn cards(&mut self, cards: &[Card]) -> Result<(), CompactError> {
+        let n = u16::try_from(cards.len()).map_err(|_| CompactError::OutOfRange)?;
+        self.bytes.extend_from_slice(&n.to_le_bytes());
+        cards.iter().try_for_each(|&c| self.card(c))
+    }
+
+    fn action(&mut self, action: &Action) -> Result<(), CompactError> {
+        match action {
+            Action::Fold => self.u8(0),
+            Action::Check => self.u8(1),
+            Action::Call => self.u8(2),
+            Action::Bet(to) => {
+                self.u8(3);
+                self.chips(*to)?;
+            }
+            Action::Raise(to) => {
+                self.u8(4);
+                self.chips(*to)?;
+            }
+            Action::AllIn => self.u8(5),
+            Action::Discard(cards) => {
+                self.u8(6);
+                self.cards(cards)?;
+            }
+            Action::Pass(cards) => {
+                self.u8(7);
+                self.cards(cards)?;
+            }
+            Action::Reveal(cards) => {
+                self.u8(8);
+                self.cards(cards)?;
+            }
+            Action::Declare(side) => {
+                self.u8(9);
+                self.u8(*side as i32 as u8);
+            }
+            Action::Buy(cards) => {
+                self.u8(10);
+                self.cards(cards)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+    /// How many seats the table has, once the stacks are read.
+    seats: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], CompactError> {
+        let bytes = self
+            .bytes
+            .get(self.at..self.at + n)
+            .ok_or(CompactError::Malformed)?;
+        self.at += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, CompactError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, CompactError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, CompactError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn chips(&mut self) -> Result<Chips, CompactError> {
+        Ok(self.u32()? as Chips)
+    }
+
+    fn seat(&mut self) -> Result<Option<usize>, CompactError> {
+        match self.u8()? {
+            NO_SEAT => Ok(None),
+            seat if (seat as usize) < self.seats => Ok(Some(seat as usize)),
+            _ => Err(CompactError::Malformed),
+        }
+    }
+
+    fn card(&mut self) -> Result<Card, CompactError> {
+        let b = self.u8()?;
+        Ok(Card::new(b >> 4, b & 0xF))
+    }
+
+    fn cards(&mut self) -> Result<Vec<Card>, CompactError> {
+        let n = u16::from_le_bytes(self.take(2)?.try_into().unwrap());
+        (0..n).map(|_| self.card()).collect()
+    }
+
+    fn action(&mut self) -> Result<Action, CompactError> {
+        Ok(match self.u8()? {
+            0 => Action::Fold,
+            1 => Action::Check,
+            2 => Action::Call,
+            3 => Action::Bet(self.chips()?),
+            4 => Action::Raise(self.chips()?),
+            5 => Action::AllIn,
+            6 => Action::Discard(self.cards()?),
+            7 => Action::Pass(self.cards()?),
+            8 => Action::Reveal(self.cards()?),
+            9 => Action::Declare(
+                Side::try_from(self.u8()? as i32).map_err(|_| CompactError::Malformed)?,
+            ),
+            10 => Action::Buy(self.cards()?),
+            _ => return Err(CompactError::Malformed),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets;
+    use crate::strategy::{Random, Strategy};
+    use crate::view::GameView;
+
+    fn assert_same(a: &GameEngine, b: &GameEngine) {
+        assert_eq!(a.seats(), b.seats());
+        assert_eq!(a.boards(), b.boards());
+        assert_eq!(a.events(), b.events());
+        assert_eq!(a.audit_log(), b.audit_log());
+        assert_eq!(a.pot_manager(), b.pot_manager());
+        assert_eq!(a.status(), b.status());
+        assert_eq!(a.to_act(), b.to_act());
+        assert_eq!(a.shoe().cards(), b.shoe().cards());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for (game, shuffler) in [
+            (presets::holdem(), Shuffler::seeded(4)),
+            (presets::five_card_draw(), Shuffler::secure()),
+            (presets::seven_card_stud_hi_lo(), Shuffler::committed()),
+        ] {
+            let config = EngineConfig {
+                shuffler,
+                ..EngineConfig::new(4)
+            };
+            let mut engine = GameEngine::with_config(game.clone(), config).unwrap();
+            let bot = Random::new(crate::cards::SplitMix64(9));
+            let mut actions = 0;
+            let mut steps = Vec::new();
+            while let Some(seat) = engine.to_act() {
+                steps.push(engine.events().len());
+                let packed = CompactState::capture(&engine).unwrap();
+                let restored = packed.restore(game.clone()).unwrap();
+                assert_same(&engine, &restored);
+                assert_eq!(CompactState::capture(&restored).unwrap(), packed);
+                let action = bot.act(&GameView::new(&engine, seat));
+                engine.apply_action(seat, action).unwrap();
+                actions += 1;
+            }
+            let packed = CompactState::capture(&engine).unwrap();
+            // A secure shuffle's order is packed whole, a byte a card.
+            assert!(packed.len() < 160 + 8 * actions, "{} bytes", packed.len());
+            let restored = CompactState::from_bytes(packed.as_bytes())
+                .restore(game.clone())
+                .unwrap();
+            assert_same(&engine, &restored);
+
+            steps.push(engine.events().len());
+            let mut replayed = Vec::new();
+            let last = packed
+                .replay(game.clone(), |e| replayed.push(e.events().len()))
+                .unwrap();
+            assert_eq!(replayed, steps);
+            assert_same(&engine, &last);
+        }
+    }
+
+    #[test]
+    fn test_bad_bytes() {
+        let engine = GameEngine::new(presets::holdem(), 2).unwrap();
+        let packed = CompactState::capture(&engine).unwrap();
+        let bytes = packed.as_bytes();
+        let truncated = CompactState::from_bytes(&bytes[..bytes.len() - 1]);
+        assert_eq!(
+            truncated.restore(presets::holdem()).err(),
+            Some(CompactError::Malformed)
+        );
+        let huge = EngineConfig {
+            stacks: vec![1 << 40, 200],
+            ..EngineConfig::new(2)
+        };
+        let engine = GameEngine::with_config(presets::holdem(), huge).unwrap();
+        assert_eq!(
+            CompactState::capture(&engine),
+            Err(CompactError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn test_bad_seats() {
+        let mut config = EngineConfig::new(3);
+        config.positions = Some(HandPositions {
+            button: 0,
+            dead_button: false,
+            small_blind: Some(1),
+            big_blind: 2,
+            owed: vec![(
+                0,
+                MissedBlinds {
+                    small: false,
+                    big: true,
+                },
+            )],
+        });
+        let engine = GameEngine::with_config(presets::holdem(), config).unwrap();
+        let packed = CompactState::capture(&engine).unwrap();
+        assert!(packed.restore(presets::holdem()).is_ok());
+        // The button, small blind, big blind and owing seat, past the three
+        // stacks, two blinds and the other settings.
+        for (at, seat) in [(39, 0), (41, 1), (42, 2), (44, 0)] {
+            let mut bytes = packed.as_bytes().to_vec();
+            assert_eq!(bytes[at], seat);
+            bytes[at] = 3;
+            assert_eq!(
+                CompactState::from_bytes(&bytes)
+                    .restore(presets::holdem())
+                    .err(),
+                Some(CompactError::Malformed)
+            );
+        }
+    }
+}
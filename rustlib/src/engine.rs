@@ -0,0 +1,3707 @@
+//! Runtime state machine that plays out a single hand of a [`GameStructure`].
+//!
+//! The engine walks the game's phase list in order, with any `Repeat`
+//! phases written out in full (see [`crate::repeat`]). Dealer phases
+//! (dealing to players or boards) run automatically; phases that need player
+//! input (betting rounds, exchanges, passes, declarations, buys) pause the
+//! engine until the player to act calls [`GameEngine::apply_action`].
+
+use std::cmp::Reverse;
+use std::fmt;
+
+use crate::audit::{AuditEntry, AuditLog, Destination};
+use crate::betting::{BettingError, BettingState, Bettor};
+use crate::cards::{Card, Shoe};
+use crate::dealing_errors::ExposedCard;
+use crate::handeval::{self, HandRank};
+use crate::kill::KillPot;
+use crate::phase::betting_round::bring_in::Starter;
+use crate::phase::betting_round::BettingOrder;
+use crate::phase::condition::Measure;
+use crate::phase::declare::Side;
+use crate::phase::exchange::DrawSource;
+use crate::phase::pass_cards::Direction;
+use crate::positions::HandPositions;
+use crate::pot::{Pot, PotManager};
+use crate::repeat::{self, Expanded, Stop};
+use crate::showdown::{self, ShowdownResult};
+use crate::shuffle::{Commitment, Shuffler};
+use crate::validation;
+use crate::{phase, GameStructure, Phase};
+
+/// Chip amounts are whole, non-negative units.
+pub type Chips = u64;
+
+/// Table setup for a hand.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EngineConfig {
+    /// Starting stack for each seat. The number of entries is the number of
+    /// players at the table; seats with an empty stack sit the hand out.
+    pub stacks: Vec<Chips>,
+
+    /// Blinds posted at the start of the first betting round, smallest first.
+    pub blinds: Vec<Chips>,
+
+    /// Ante collected by the game's `Ante` phases, or from every player
+    /// before the first phase if the game has none.
+    pub ante: Chips,
+
+    /// Seat holding the dealer button.
+    pub button: usize,
+
+    /// Explicit button and blind seats, usually from
+    /// [`crate::positions::TablePositions::next_hand`]. When set, its button overrides
+    /// `button`. Otherwise the blinds are posted by the seats left of the
+    /// button.
+    pub positions: Option<HandPositions>,
+
+    /// How the deck is shuffled. A seeded shuffler deals the same cards
+    /// every time, and its seed is recorded in the hand's events.
+    pub shuffler: Shuffler,
+
+    /// The shoe left over from the previous hand, from
+    /// [`GameEngine::remaining_shoe`]. The hand is dealt from it unless it
+    /// is due to be reshuffled, in which case a fresh shoe is shuffled with
+    /// `shuffler`.
+    pub shoe: Option<Shoe>,
+
+    /// Straddles posted before the first betting round, each double the one
+    /// before: the first by the seat after the last blind, any further ones
+    /// (double straddles) by the seats after it. Action starts after the
+    /// last straddler, who acts last.
+    pub straddles: usize,
+
+    /// Whether the button posts a straddle. Action starts after the blinds
+    /// as usual, but passes over the button until the blinds have acted.
+    /// Only used when `straddles` is zero.
+    pub button_straddle: bool,
+
+    /// If nonzero, this hand is a bomb pot: no blinds, antes or straddles are
+    /// posted, and instead every player puts in this amount in place of the
+    /// first betting round.
+    pub bomb_pot: Chips,
+
+    /// Deal a bomb pot on two boards, splitting each pot between them.
+    pub bomb_pot_double_board: bool,
+
+    /// If set, this hand is a kill pot: bet sizes are raised and the killer
+    /// posts a kill blind of the raised big blind, usually from
+    /// [`crate::kill::KillTracker::record`].
+    pub kill: Option<KillPot>,
+
+    /// How many times to run out the board once the players are all-in.
+    /// Zero uses the game's `all_in_runouts`.
+    pub runouts: u32,
+}
+
+impl EngineConfig {
+    /// `players` seats with 100 big blinds each, playing 1/2 blinds.
+    pub fn new(players: usize) -> Self {
+        EngineConfig {
+            stacks: vec![200; players],
+            blinds: vec![1, 2],
+            ante: 0,
+            button: 0,
+            positions: None,
+            shuffler: Shuffler::default(),
+            shoe: None,
+            straddles: 0,
+            button_straddle: false,
+            bomb_pot: 0,
+            bomb_pot_double_board: false,
+            kill: None,
+            runouts: 0,
+        }
+    }
+}
+
+/// Something a player can do when it is their turn.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Fold,
+    Check,
+    Call,
+
+    /// Open the betting. The amount is the player's total for the round.
+    Bet(Chips),
+
+    /// Raise to the given total for the round.
+    Raise(Chips),
+
+    /// Put in every remaining chip: a call, bet or raise, whichever the
+    /// player's stack amounts to.
+    AllIn,
+
+    /// Discard the given cards during an exchange phase and draw replacements.
+    /// An empty list stands pat.
+    Discard(Vec<Card>),
+
+    /// Pass the given cards to another player during a passing phase.
+    Pass(Vec<Card>),
+
+    /// Turn the given face-down cards face up during a reveal phase that
+    /// lets players choose.
+    Reveal(Vec<Card>),
+
+    /// Declare for the high half of the pot, the low half, or both.
+    Declare(Side),
+
+    /// Pay for a card during a buy phase, discarding the given card if the
+    /// bought card replaces one. Declining to buy is a check.
+    Buy(Vec<Card>),
+}
+
+impl Action {
+    /// The action as the rest of the table sees it, without the cards
+    /// the player threw away or passed. Revealed cards are for everyone.
+    pub fn redacted(&self) -> Action {
+        match self {
+            Action::Discard(_) => Action::Discard(Vec::new()),
+            Action::Pass(_) => Action::Pass(Vec::new()),
+            Action::Buy(_) => Action::Buy(Vec::new()),
+            action => action.clone(),
+        }
+    }
+}
+
+impl From<&Action> for crate::PlayerAction {
+    fn from(action: &Action) -> Self {
+        use crate::player_action::Kind;
+        let side = match action {
+            Action::Declare(side) => *side,
+            _ => Side::Unknown,
+        };
+        let (kind, amount, cards) = match action {
+            Action::Fold => (Kind::Fold, 0, Vec::new()),
+            Action::Check => (Kind::Check, 0, Vec::new()),
+            Action::Call => (Kind::Call, 0, Vec::new()),
+            Action::Bet(to) => (Kind::Bet, *to, Vec::new()),
+            Action::Raise(to) => (Kind::Raise, *to, Vec::new()),
+            Action::AllIn => (Kind::AllIn, 0, Vec::new()),
+            Action::Discard(cards) => (Kind::Discard, 0, cards.clone()),
+            Action::Pass(cards) => (Kind::Pass, 0, cards.clone()),
+            Action::Reveal(cards) => (Kind::Reveal, 0, cards.clone()),
+            Action::Declare(_) => (Kind::Declare, 0, Vec::new()),
+            Action::Buy(cards) => (Kind::Buy, 0, cards.clone()),
+        };
+        crate::PlayerAction {
+            kind: kind.into(),
+            amount: amount as i64,
+            cards: cards.into_iter().map(Into::into).collect(),
+            side: side.into(),
+        }
+    }
+}
+
+impl TryFrom<&crate::PlayerAction> for Action {
+    type Error = EngineError;
+
+    fn try_from(msg: &crate::PlayerAction) -> Result<Self, Self::Error> {
+        use crate::player_action::Kind;
+        let malformed = |why: String| EngineError::MalformedAction(why);
+        let amount = Chips::try_from(msg.amount)
+            .map_err(|_| malformed(format!("negative amount {}", msg.amount)))?;
+        Ok(match Kind::try_from(msg.kind) {
+            Ok(Kind::Fold) => Action::Fold,
+            Ok(Kind::Check) => Action::Check,
+            Ok(Kind::Call) => Action::Call,
+            Ok(Kind::Bet) => Action::Bet(amount),
+            Ok(Kind::Raise) => Action::Raise(amount),
+            Ok(Kind::AllIn) => Action::AllIn,
+            Ok(kind @ (Kind::Discard | Kind::Pass | Kind::Reveal | Kind::Buy)) => {
+                let cards = msg
+                    .cards
+                    .iter()
+                    .map(Card::try_from)
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| malformed(e.to_string()))?;
+                match kind {
+                    Kind::Pass => Action::Pass(cards),
+                    Kind::Reveal => Action::Reveal(cards),
+                    Kind::Buy => Action::Buy(cards),
+                    _ => Action::Discard(cards),
+                }
+            }
+            Ok(Kind::Declare) => match msg.side() {
+                Side::Unknown => return Err(malformed(format!("unknown side {}", msg.side))),
+                side => Action::Declare(side),
+            },
+            Ok(Kind::Unknown) | Err(_) => {
+                return Err(malformed(format!("unknown action kind {}", msg.kind)))
+            }
+        })
+    }
+}
+
+/// One kind of action open to the player to act, with its bounds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LegalAction {
+    Fold,
+    Check,
+
+    /// Call, adding this many chips.
+    Call(Chips),
+
+    /// Bet any total from `min` to `max` inclusive.
+    Bet {
+        min: Chips,
+        max: Chips,
+    },
+
+    /// Raise to any total from `min` to `max` inclusive.
+    Raise {
+        min: Chips,
+        max: Chips,
+    },
+
+    /// Go all-in for this total.
+    AllIn(Chips),
+
+    /// Exchange up to `max` cards.
+    Discard {
+        max: usize,
+    },
+
+    /// Pass exactly `count` cards.
+    Pass {
+        count: usize,
+    },
+
+    /// Turn exactly `count` face-down cards face up.
+    Reveal {
+        count: usize,
+    },
+
+    /// Declare high, low or both.
+    Declare,
+
+    /// Buy a card for `price`, discarding one card if it `replace`s one.
+    Buy {
+        price: Chips,
+        replace: bool,
+    },
+}
+
+impl LegalAction {
+    /// Whether `action` falls within this option.
+    pub fn permits(&self, action: &Action) -> bool {
+        match (self, action) {
+            (LegalAction::Fold, Action::Fold)
+            | (LegalAction::Check, Action::Check)
+            | (LegalAction::Call(_), Action::Call)
+            | (LegalAction::AllIn(_), Action::AllIn) => true,
+            (LegalAction::Declare, Action::Declare(side)) => *side != Side::Unknown,
+            (LegalAction::Bet { min, max }, Action::Bet(to))
+            | (LegalAction::Raise { min, max }, Action::Raise(to)) => (min..=max).contains(&to),
+            (LegalAction::Discard { max }, Action::Discard(cards)) => cards.len() <= *max,
+            (LegalAction::Buy { replace, .. }, Action::Buy(cards)) => {
+                cards.len() == usize::from(*replace)
+            }
+            (LegalAction::Pass { count }, Action::Pass(cards))
+            | (LegalAction::Reveal { count }, Action::Reveal(cards)) => cards.len() == *count,
+            _ => false,
+        }
+    }
+}
+
+/// A card held by a player, along with whether it is exposed to the table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DealtCard {
+    pub card: Card,
+    pub face_up: bool,
+}
+
+/// Whether each card of `deal` comes face up, in dealing order.
+pub fn dealt_face_up(deal: &phase::PlayerDeal) -> Vec<bool> {
+    if deal.face_up_cards.is_empty() {
+        vec![deal.face_up; deal.cards.max(0) as usize]
+    } else {
+        deal.face_up_cards.clone()
+    }
+}
+
+/// Sets every deal's burn count as `burns` says.
+pub(crate) fn apply_burns(phases: &mut [Phase], burns: &crate::BurnPolicy) {
+    use crate::burn_policy::Mode;
+    let each = match burns.mode() {
+        Mode::Unknown | Mode::PerDeal => return,
+        Mode::EveryStreet => burns.cards.max(1),
+        Mode::Never => 0,
+    };
+    let mut first = true;
+    for p in phases {
+        let burn = match &mut p.phase_type {
+            Some(phase::PhaseType::PlayerDeal(deal)) => &mut deal.burn,
+            Some(phase::PhaseType::CommunityDeal(deal)) => &mut deal.burn,
+            _ => continue,
+        };
+        *burn = if first { 0 } else { each };
+        first = false;
+    }
+}
+
+/// Per-player state for the current hand.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Seat {
+    pub stack: Chips,
+    pub hole: Vec<DealtCard>,
+    pub folded: bool,
+}
+
+impl Seat {
+    /// Still contesting the pot.
+    pub fn is_live(&self) -> bool {
+        !self.folded
+    }
+
+    /// Still contesting the pot and able to make decisions.
+    pub fn can_act(&self) -> bool {
+        !self.folded && self.stack > 0
+    }
+
+    pub fn is_all_in(&self) -> bool {
+        !self.folded && self.stack == 0
+    }
+}
+
+/// Record of what happened during the hand, in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A fresh deck was shuffled with a seeded [`Shuffler`], which deals the
+    /// same cards again given the same seed.
+    Shuffled {
+        seed: u64,
+    },
+    /// A fresh deck was shuffled with a committed [`Shuffler`]: the hash of
+    /// its secret seed and salt, published before any card is dealt.
+    ShuffleCommitted {
+        hash: [u8; 32],
+    },
+    /// The committed shuffler's seed and salt, revealed once no more hands
+    /// will be dealt from the shoe, so the deal can be checked with
+    /// [`crate::shuffle::verify_deal`].
+    ShuffleRevealed {
+        seed: u64,
+        salt: [u8; 16],
+    },
+    PhaseStarted {
+        index: usize,
+    },
+    /// The phase's condition didn't hold, so it wasn't played.
+    PhaseSkipped {
+        index: usize,
+    },
+    HoleCardsDealt {
+        seat: usize,
+        cards: Vec<DealtCard>,
+    },
+    BoardDealt {
+        board: usize,
+        cards: Vec<Card>,
+    },
+    /// Community cards dealt face down, to be turned up by a later reveal.
+    HiddenBoardDealt {
+        board: usize,
+        count: usize,
+    },
+    /// Face-down cards in a seat's hand turned face up for the whole table.
+    CardsRevealed {
+        seat: usize,
+        cards: Vec<Card>,
+    },
+    /// Face-down community cards turned face up.
+    BoardRevealed {
+        board: usize,
+        cards: Vec<Card>,
+    },
+    AntePosted {
+        seat: usize,
+        amount: Chips,
+    },
+    BlindPosted {
+        seat: usize,
+        amount: Chips,
+    },
+    DeadBlindPosted {
+        seat: usize,
+        amount: Chips,
+    },
+    StraddlePosted {
+        seat: usize,
+        amount: Chips,
+    },
+    BombPotPosted {
+        seat: usize,
+        amount: Chips,
+    },
+    KillBlindPosted {
+        seat: usize,
+        amount: Chips,
+    },
+    UncalledBetReturned {
+        seat: usize,
+        amount: Chips,
+    },
+    /// Rake withheld by the house before the pots were awarded.
+    RakeTaken {
+        amount: Chips,
+    },
+    Acted {
+        seat: usize,
+        action: Action,
+    },
+    CardsExchanged {
+        seat: usize,
+        count: usize,
+    },
+    /// Cards moved from one hand to another in a passing phase.
+    CardsPassed {
+        from: usize,
+        to: usize,
+        count: usize,
+    },
+    /// The cards a seat discarded, in draws with public discards.
+    DiscardsShown {
+        seat: usize,
+        cards: Vec<Card>,
+    },
+    /// The deck ran out during a draw and the earlier discards were shuffled
+    /// to replace it.
+    DiscardsReshuffled {
+        count: usize,
+    },
+    /// The board is being run out again. Runs are numbered from 0 and only
+    /// reported when there is more than one.
+    RunoutStarted {
+        run: usize,
+    },
+    HandShown {
+        seat: usize,
+        scoring: usize,
+        rank: HandRank,
+    },
+    PotAwarded {
+        seat: usize,
+        amount: Chips,
+    },
+    /// A face-down card was exposed during the deal. If the game's policy
+    /// replaced it, `replacement` is the card the player got instead.
+    CardExposed {
+        seat: usize,
+        card: Card,
+        replacement: Option<Card>,
+    },
+    /// The half of the pot a seat is playing for in a declare game.
+    Declared {
+        seat: usize,
+        side: Side,
+    },
+    /// The seat paid `amount` for a card in a buy phase. `replaced` is
+    /// whether the card took the place of a discard, and `shown` is the card
+    /// if it was dealt face up.
+    CardBought {
+        seat: usize,
+        amount: Chips,
+        replaced: bool,
+        shown: Option<Card>,
+    },
+    BuyDeclined {
+        seat: usize,
+    },
+    /// The seat's hand was ruled dead and no longer contests the pot.
+    HandDeclaredDead {
+        seat: usize,
+    },
+    HandComplete,
+    /// The hand was abandoned and every chip returned to its owner.
+    Misdeal,
+}
+
+/// Who a hand's events are shown to, which decides the cards they see.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Audience {
+    /// The player in the seat, who sees their own cards.
+    Seat(usize),
+    /// Anyone without a seat, who sees only what the table does: cards
+    /// that are face up, and hands shown down.
+    Spectator,
+}
+
+impl Audience {
+    /// Whether the audience sees the cards only `seat` holds or handles.
+    pub fn sees(self, seat: usize) -> bool {
+        self == Audience::Seat(seat)
+    }
+
+    /// Whether the audience sees `card`, dealt to `seat`.
+    pub fn sees_card(self, seat: usize, card: &DealtCard) -> bool {
+        card.face_up || self.sees(seat)
+    }
+}
+
+impl Event {
+    /// The event as `audience` may see it: without other seats' face-down
+    /// cards, the cards they threw away, passed or discarded for a bought
+    /// one, or the card they got for one that was exposed.
+    pub fn redacted(&self, audience: Audience) -> Event {
+        match self {
+            Event::HoleCardsDealt { seat, cards } => Event::HoleCardsDealt {
+                seat: *seat,
+                cards: cards
+                    .iter()
+                    .filter(|c| audience.sees_card(*seat, c))
+                    .copied()
+                    .collect(),
+            },
+            Event::Acted { seat, action } if !audience.sees(*seat) => Event::Acted {
+                seat: *seat,
+                action: action.redacted(),
+            },
+            Event::CardExposed {
+                seat,
+                card,
+                replacement: Some(_),
+            } if !audience.sees(*seat) => Event::CardExposed {
+                seat: *seat,
+                card: *card,
+                replacement: None,
+            },
+            event => event.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HandStatus {
+    /// Waiting on a player action.
+    InProgress,
+
+    /// The pot has been awarded.
+    Complete,
+
+    /// The hand was a misdeal and should be dealt again.
+    Misdeal,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EngineError {
+    NotEnoughPlayers,
+    /// More players than the deck can deal a whole hand to.
+    TooManyPlayers {
+        players: usize,
+        max: usize,
+    },
+    DeckExhausted,
+    HandOver,
+    NotYourTurn {
+        seat: usize,
+        to_act: usize,
+    },
+    IllegalAction(Action),
+    Betting(BettingError),
+    InvalidDiscard(String),
+    InvalidPass(String),
+    InvalidReveal(String),
+    InvalidBuy(String),
+    /// A [`crate::PlayerAction`] message that doesn't describe an action.
+    MalformedAction(String),
+    /// A [`crate::GameEvent`] message that doesn't describe an event.
+    MalformedEvent(String),
+    /// A misdeal was declared after a player had acted.
+    MisdealTooLate,
+    /// The seat has already folded or was never dealt in.
+    NotInHand(usize),
+    /// The card isn't one of the seat's face-down cards from the deal in
+    /// progress.
+    NotExposable(Card),
+    /// The config puts the button, a blind or the kill on a seat the table
+    /// doesn't have, or gives a seat two of them.
+    InvalidSeat(String),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::NotEnoughPlayers => {
+                write!(f, "at least two players with chips are required")
+            }
+            EngineError::TooManyPlayers { players, max } => write!(
+                f,
+                "the deck can't deal to {} players, only {}",
+                players, max
+            ),
+            EngineError::DeckExhausted => write!(f, "the deck ran out of cards"),
+            EngineError::HandOver => write!(f, "no action is pending"),
+            EngineError::NotYourTurn { seat, to_act } => {
+                write!(f, "seat {} acted, but it is seat {}'s turn", seat, to_act)
+            }
+            EngineError::IllegalAction(a) => write!(f, "{:?} is not legal right now", a),
+            EngineError::Betting(e) => e.fmt(f),
+            EngineError::InvalidDiscard(why) => write!(f, "invalid discard: {}", why),
+            EngineError::InvalidPass(why) => write!(f, "invalid pass: {}", why),
+            EngineError::InvalidReveal(why) => write!(f, "invalid reveal: {}", why),
+            EngineError::InvalidBuy(why) => write!(f, "invalid buy: {}", why),
+            EngineError::MalformedAction(why) => write!(f, "malformed action: {}", why),
+            EngineError::MalformedEvent(why) => write!(f, "malformed event: {}", why),
+            EngineError::MisdealTooLate => {
+                write!(f, "a misdeal can't be declared once a player has acted")
+            }
+            EngineError::NotInHand(seat) => write!(f, "seat {} is not in the hand", seat),
+            EngineError::NotExposable(card) => {
+                write!(f, "{} is not a face-down card from this deal", card)
+            }
+            EngineError::InvalidSeat(why) => write!(f, "invalid seat: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+impl From<BettingError> for EngineError {
+    fn from(e: BettingError) -> Self {
+        EngineError::Betting(e)
+    }
+}
+
+/// Checks that the seats `config` names are all at the table, and that
+/// the blinds are on different seats and no seat owes blinds twice.
+fn check_seats(config: &EngineConfig) -> Result<(), EngineError> {
+    let seats = config.stacks.len();
+    let at_table = |what: &str, seat: usize| match seat < seats {
+        true => Ok(()),
+        false => Err(EngineError::InvalidSeat(format!(
+            "the {} is on seat {}, but the table has {}",
+            what, seat, seats
+        ))),
+    };
+    at_table("button", config.button)?;
+    if let Some(positions) = &config.positions {
+        at_table("big blind", positions.big_blind)?;
+        if let Some(seat) = positions.small_blind {
+            at_table("small blind", seat)?;
+            if seat == positions.big_blind {
+                return Err(EngineError::InvalidSeat(format!(
+                    "seat {} has both blinds",
+                    seat
+                )));
+            }
+        }
+        for (i, &(seat, _)) in positions.owed.iter().enumerate() {
+            at_table("seat owing blinds", seat)?;
+            if positions.owed[..i].iter().any(|&(owing, _)| owing == seat) {
+                return Err(EngineError::InvalidSeat(format!(
+                    "seat {} owes blinds twice",
+                    seat
+                )));
+            }
+        }
+    }
+    if let Some(kill) = &config.kill {
+        at_table("kill", kill.seat)?;
+    }
+    Ok(())
+}
+
+/// Something done to the hand from outside, in the order it was done.
+/// With the config, these are all it takes to play the hand again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Input {
+    Act(usize, Action),
+    Expose(usize, Card),
+    DeadHand(usize),
+    Misdeal,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Step {
+    Betting(BettingState),
+    Exchange {
+        to_act: usize,
+        remaining: Vec<usize>,
+    },
+    /// A passing phase. `chosen` holds the cards picked so far in a
+    /// simultaneous pass, which change hands once everyone has chosen.
+    Passing {
+        to_act: usize,
+        remaining: Vec<usize>,
+        chosen: Vec<(usize, Vec<Card>)>,
+    },
+    /// A reveal phase in which players pick their cards. Like a simultaneous
+    /// pass, nothing is turned up until everyone has chosen.
+    Revealing {
+        to_act: usize,
+        remaining: Vec<usize>,
+        chosen: Vec<(usize, Vec<Card>)>,
+    },
+    /// A declare phase. `chosen` holds the secret declarations made so far,
+    /// which are shown once everyone has declared.
+    Declaring {
+        to_act: usize,
+        remaining: Vec<usize>,
+        chosen: Vec<(usize, Side)>,
+    },
+    /// A buy phase, offered to the seats that can afford the card.
+    Buying {
+        to_act: usize,
+        remaining: Vec<usize>,
+    },
+    Complete,
+    Misdeal,
+}
+
+/// One dealing of the remaining board and the showdown for its share of the
+/// pots.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Runout {
+    pub boards: Vec<Vec<Card>>,
+    pub result: ShowdownResult,
+}
+
+/// Plays one hand of a game on a table of N players.
+#[derive(Clone, Debug)]
+pub struct GameEngine {
+    game: GameStructure,
+    /// The game's phases with repeats written out, as they are played.
+    phases: Vec<Phase>,
+    stops: Vec<Stop>,
+    config: EngineConfig,
+    shoe: Shoe,
+    seats: Vec<Seat>,
+    pots: PotManager,
+    boards: Vec<Vec<Card>>,
+    /// Community cards dealt face down and not yet revealed, per board.
+    hidden: Vec<Vec<Card>>,
+    /// Each seat's declaration, once declared.
+    declarations: Vec<Option<Side>>,
+    phase_idx: usize,
+    blinds_posted: bool,
+    rounds_started: usize,
+    rake: Chips,
+    step: Step,
+    runouts: Vec<Runout>,
+    burned: Vec<Card>,
+    discards: Vec<Card>,
+    events: Vec<Event>,
+    inputs: Vec<Input>,
+    audit: AuditLog,
+}
+
+impl GameEngine {
+    /// Starts a hand with `players` seats using [`EngineConfig::new`].
+    pub fn new(game: GameStructure, players: usize) -> Result<Self, EngineError> {
+        Self::with_config(game, EngineConfig::new(players))
+    }
+
+    pub fn with_config(game: GameStructure, mut config: EngineConfig) -> Result<Self, EngineError> {
+        if let Some(positions) = &config.positions {
+            config.button = positions.button;
+        }
+        let players = config.stacks.iter().filter(|&&s| s > 0).count();
+        if players < 2 {
+            return Err(EngineError::NotEnoughPlayers);
+        }
+        let max = validation::max_seats(&game);
+        if players > max {
+            return Err(EngineError::TooManyPlayers { players, max });
+        }
+        check_seats(&config)?;
+        let mut audit = AuditLog::default();
+        let mut shuffled = false;
+        // A shoe too short for the whole hand is reshuffled early, rather
+        // than run out partway through.
+        let needed = validation::cards_dealt(&game, players);
+        let shoe = match config.shoe.take() {
+            Some(shoe) if !shoe.needs_shuffle() && shoe.remaining() >= needed => shoe,
+            _ => {
+                let mut shoe = Shoe::for_game(&game);
+                shoe.shuffle_with(&config.shuffler, 0);
+                audit.append(AuditEntry::shuffle(0, &shoe));
+                shuffled = true;
+                shoe
+            }
+        };
+
+        let seats: Vec<Seat> = config
+            .stacks
+            .iter()
+            .map(|&stack| Seat {
+                stack,
+                folded: stack == 0,
+                ..Default::default()
+            })
+            .collect();
+        let mut board_count = game.community_board_count.max(0) as usize;
+        if config.bomb_pot > 0 && config.bomb_pot_double_board && board_count == 1 {
+            board_count = 2;
+        }
+        let boards = vec![Vec::new(); board_count];
+
+        let Expanded { mut phases, stops } = repeat::expand(&game.phases);
+        if let Some(burns) = &game.burns {
+            apply_burns(&mut phases, burns);
+        }
+        let mut engine = GameEngine {
+            game,
+            phases,
+            stops,
+            config,
+            shoe,
+            pots: PotManager::new(seats.len()),
+            declarations: vec![None; seats.len()],
+            seats,
+            hidden: vec![Vec::new(); boards.len()],
+            boards,
+            phase_idx: 0,
+            blinds_posted: false,
+            rounds_started: 0,
+            rake: 0,
+            step: Step::Complete,
+            runouts: Vec::new(),
+            burned: Vec::new(),
+            discards: Vec::new(),
+            events: Vec::new(),
+            inputs: Vec::new(),
+            audit,
+        };
+        if shuffled {
+            if let Some(seed) = engine.config.shuffler.seed() {
+                engine.events.push(Event::Shuffled { seed });
+            }
+            if let Some(commitment) = engine.config.shuffler.commitment() {
+                let hash = commitment.hash();
+                engine.events.push(Event::ShuffleCommitted { hash });
+            }
+        }
+        let has_ante_phase = engine
+            .phases
+            .iter()
+            .any(|p| matches!(p.phase_type, Some(phase::PhaseType::Ante(_))));
+        if !has_ante_phase {
+            engine.collect_antes(&phase::Ante::default());
+        }
+        engine.advance()?;
+        Ok(engine)
+    }
+
+    pub fn game(&self) -> &GameStructure {
+        &self.game
+    }
+
+    /// The phases in the order they are played, with repeats written out.
+    /// Phase indices in events, and `current_phase`, refer to this list.
+    pub fn phases(&self) -> &[Phase] {
+        &self.phases
+    }
+
+    /// The undealt cards, to deal the next hand from, if the game keeps its
+    /// shoe between hands and the cut card hasn't come out.
+    pub fn remaining_shoe(&self) -> Option<&Shoe> {
+        Some(&self.shoe).filter(|shoe| !shoe.needs_shuffle())
+    }
+
+    /// The undealt cards, whether or not they're due to be reshuffled.
+    pub(crate) fn shoe(&self) -> &Shoe {
+        &self.shoe
+    }
+
+    pub(crate) fn inputs(&self) -> &[Input] {
+        &self.inputs
+    }
+
+    pub fn config(&self) -> &EngineConfig {
+        &self.config
+    }
+
+    pub fn seats(&self) -> &[Seat] {
+        &self.seats
+    }
+
+    /// The face-up community cards on each board.
+    pub fn boards(&self) -> &[Vec<Card>] {
+        &self.boards
+    }
+
+    /// How many community cards lie face down on each board.
+    pub fn hidden_boards(&self) -> Vec<usize> {
+        self.hidden.iter().map(Vec::len).collect()
+    }
+
+    /// What each seat declared in a declare hi-lo game, by seat.
+    pub fn declarations(&self) -> &[Option<Side>] {
+        &self.declarations
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Every shuffle and every card taken off the deck this hand, in a
+    /// tamper-evident log for the operator to sign.
+    pub fn audit_log(&self) -> &AuditLog {
+        &self.audit
+    }
+
+    pub fn button(&self) -> usize {
+        self.config.button
+    }
+
+    /// Total chips committed by all players this hand.
+    pub fn pot(&self) -> Chips {
+        self.pots.total()
+    }
+
+    /// Per-seat contributions for the hand.
+    pub fn pot_manager(&self) -> &PotManager {
+        &self.pots
+    }
+
+    /// Chips withheld as rake once the hand is over.
+    pub fn rake(&self) -> Chips {
+        self.rake
+    }
+
+    /// The main pot and any side pots, with the seats eligible for each.
+    pub fn pots(&self) -> Vec<Pot> {
+        self.pots.pots()
+    }
+
+    /// How the pots were split, if the hand went to showdown. If the board
+    /// was run more than once, this is the first runout.
+    pub fn showdown(&self) -> Option<&ShowdownResult> {
+        self.runouts.first().map(|r| &r.result)
+    }
+
+    /// Every runout of the board that went to showdown, in order.
+    pub fn runouts(&self) -> &[Runout] {
+        &self.runouts
+    }
+
+    /// Cards taken out of play: burns before each street, and exposed cards
+    /// that were replaced.
+    pub fn burned(&self) -> &[Card] {
+        &self.burned
+    }
+
+    /// Cards thrown away in draws and not yet reshuffled into the deck.
+    pub fn discards(&self) -> &[Card] {
+        &self.discards
+    }
+
+    pub fn status(&self) -> HandStatus {
+        match self.step {
+            Step::Betting(_)
+            | Step::Exchange { .. }
+            | Step::Passing { .. }
+            | Step::Revealing { .. }
+            | Step::Declaring { .. }
+            | Step::Buying { .. } => HandStatus::InProgress,
+            Step::Complete => HandStatus::Complete,
+            Step::Misdeal => HandStatus::Misdeal,
+        }
+    }
+
+    /// The phase currently waiting on players, if any.
+    pub fn current_phase(&self) -> Option<&Phase> {
+        match self.step {
+            Step::Betting(_)
+            | Step::Exchange { .. }
+            | Step::Passing { .. }
+            | Step::Revealing { .. }
+            | Step::Declaring { .. }
+            | Step::Buying { .. } => self.phases.get(self.phase_idx),
+            Step::Complete | Step::Misdeal => None,
+        }
+    }
+
+    /// The seat whose action is pending.
+    pub fn to_act(&self) -> Option<usize> {
+        match &self.step {
+            Step::Betting(r) => r.to_act(),
+            Step::Exchange { to_act, .. }
+            | Step::Passing { to_act, .. }
+            | Step::Revealing { to_act, .. }
+            | Step::Declaring { to_act, .. }
+            | Step::Buying { to_act, .. } => Some(*to_act),
+            Step::Complete | Step::Misdeal => None,
+        }
+    }
+
+    /// The actions open to `seat`, with the bounds on any bet, raise or
+    /// exchange. Empty unless it is `seat`'s turn.
+    pub fn legal_actions(&self, seat: usize) -> Vec<LegalAction> {
+        match (
+            &self.step,
+            self.current_phase().and_then(|p| p.phase_type.as_ref()),
+        ) {
+            (Step::Betting(r), _) => r.legal_actions(seat),
+            (Step::Exchange { to_act, .. }, Some(phase::PhaseType::Exchange(rules)))
+                if *to_act == seat =>
+            {
+                vec![LegalAction::Discard {
+                    max: (rules.max_exchange.max(0) as usize).min(self.drawable(rules)),
+                }]
+            }
+            (Step::Passing { to_act, .. }, Some(phase::PhaseType::PassCards(rules)))
+                if *to_act == seat =>
+            {
+                vec![LegalAction::Pass {
+                    count: rules.cards.max(0) as usize,
+                }]
+            }
+            (Step::Revealing { to_act, .. }, Some(phase::PhaseType::Reveal(rules)))
+                if *to_act == seat =>
+            {
+                vec![LegalAction::Reveal {
+                    count: self.reveal_count(seat, rules),
+                }]
+            }
+            (Step::Declaring { to_act, .. }, _) if *to_act == seat => vec![LegalAction::Declare],
+            (Step::Buying { to_act, .. }, Some(phase::PhaseType::Buy(rules)))
+                if *to_act == seat =>
+            {
+                vec![
+                    LegalAction::Check,
+                    LegalAction::Buy {
+                        price: self.buy_price(rules),
+                        replace: rules.replace,
+                    },
+                ]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Applies an action for `seat`, then runs the hand forward to the next
+    /// decision point.
+    pub fn apply_action(&mut self, seat: usize, action: Action) -> Result<(), EngineError> {
+        self.act(seat, action.clone())?;
+        self.inputs.push(Input::Act(seat, action));
+        Ok(())
+    }
+
+    fn act(&mut self, seat: usize, action: Action) -> Result<(), EngineError> {
+        let to_act = self.to_act().ok_or(EngineError::HandOver)?;
+        if seat != to_act {
+            return Err(EngineError::NotYourTurn { seat, to_act });
+        }
+        match std::mem::replace(&mut self.step, Step::Complete) {
+            Step::Betting(mut round) => {
+                let paid = match round.apply(seat, &action) {
+                    Ok(paid) => paid,
+                    Err(e) => {
+                        self.step = Step::Betting(round);
+                        return Err(e.into());
+                    }
+                };
+                self.pay(seat, paid);
+                if round.bettors()[seat].folded {
+                    self.seats[seat].folded = true;
+                    self.pots.fold(seat);
+                }
+                self.events.push(Event::Acted { seat, action });
+                if round.is_complete() {
+                    self.finish_phase()
+                } else {
+                    self.step = Step::Betting(round);
+                    Ok(())
+                }
+            }
+            Step::Exchange {
+                to_act,
+                mut remaining,
+            } => {
+                let cards = match &action {
+                    Action::Discard(cards) => cards,
+                    _ => {
+                        self.step = Step::Exchange { to_act, remaining };
+                        return Err(EngineError::IllegalAction(action));
+                    }
+                };
+                if let Err(e) = self.exchange(seat, cards) {
+                    self.step = Step::Exchange { to_act, remaining };
+                    return Err(e);
+                }
+                if remaining.is_empty() {
+                    self.finish_phase()
+                } else {
+                    let next = remaining.remove(0);
+                    self.step = Step::Exchange {
+                        to_act: next,
+                        remaining,
+                    };
+                    Ok(())
+                }
+            }
+            Step::Passing {
+                to_act,
+                mut remaining,
+                mut chosen,
+            } => {
+                let result = match &action {
+                    Action::Pass(cards) => self.pass(seat, cards, &mut chosen),
+                    _ => Err(EngineError::IllegalAction(action)),
+                };
+                if let Err(e) = result {
+                    self.step = Step::Passing {
+                        to_act,
+                        remaining,
+                        chosen,
+                    };
+                    return Err(e);
+                }
+                if remaining.is_empty() {
+                    self.finish_passing(chosen)
+                } else {
+                    let next = remaining.remove(0);
+                    self.step = Step::Passing {
+                        to_act: next,
+                        remaining,
+                        chosen,
+                    };
+                    Ok(())
+                }
+            }
+            Step::Revealing {
+                to_act,
+                mut remaining,
+                mut chosen,
+            } => {
+                let result = match &action {
+                    Action::Reveal(cards) => self.choose_reveal(seat, cards, &mut chosen),
+                    _ => Err(EngineError::IllegalAction(action)),
+                };
+                if let Err(e) = result {
+                    self.step = Step::Revealing {
+                        to_act,
+                        remaining,
+                        chosen,
+                    };
+                    return Err(e);
+                }
+                if remaining.is_empty() {
+                    self.finish_revealing(chosen)
+                } else {
+                    let next = remaining.remove(0);
+                    self.step = Step::Revealing {
+                        to_act: next,
+                        remaining,
+                        chosen,
+                    };
+                    Ok(())
+                }
+            }
+            Step::Declaring {
+                to_act,
+                mut remaining,
+                mut chosen,
+            } => {
+                match action {
+                    Action::Declare(side) if side != Side::Unknown => {
+                        if self.declares_in_turn() {
+                            self.declare(seat, side);
+                        } else {
+                            chosen.push((seat, side));
+                        }
+                    }
+                    _ => {
+                        self.step = Step::Declaring {
+                            to_act,
+                            remaining,
+                            chosen,
+                        };
+                        return Err(EngineError::IllegalAction(action));
+                    }
+                }
+                if remaining.is_empty() {
+                    self.finish_declaring(chosen)
+                } else {
+                    let next = remaining.remove(0);
+                    self.step = Step::Declaring {
+                        to_act: next,
+                        remaining,
+                        chosen,
+                    };
+                    Ok(())
+                }
+            }
+            Step::Buying {
+                to_act,
+                mut remaining,
+            } => {
+                let result = match &action {
+                    Action::Check => {
+                        self.events.push(Event::BuyDeclined { seat });
+                        Ok(())
+                    }
+                    Action::Buy(cards) => self.buy(seat, cards),
+                    _ => Err(EngineError::IllegalAction(action)),
+                };
+                if let Err(e) = result {
+                    self.step = Step::Buying { to_act, remaining };
+                    return Err(e);
+                }
+                if remaining.is_empty() {
+                    self.finish_phase()
+                } else {
+                    let next = remaining.remove(0);
+                    self.step = Step::Buying {
+                        to_act: next,
+                        remaining,
+                    };
+                    Ok(())
+                }
+            }
+            step => {
+                self.step = step;
+                Err(EngineError::HandOver)
+            }
+        }
+    }
+
+    /// Whether a misdeal can still be declared: the hand is under way and
+    /// nobody has acted yet.
+    pub fn can_misdeal(&self) -> bool {
+        self.status() == HandStatus::InProgress
+            && !self.events.iter().any(|e| {
+                matches!(
+                    e,
+                    Event::Acted { .. }
+                        | Event::CardsExchanged { .. }
+                        | Event::CardsPassed { .. }
+                        | Event::CardBought { .. }
+                )
+            })
+    }
+
+    /// Abandons the hand, returning every chip that went in, including antes
+    /// and blinds. Use [`GameEngine::redeal`] to deal it again.
+    pub fn declare_misdeal(&mut self) -> Result<(), EngineError> {
+        self.misdeal()?;
+        self.inputs.push(Input::Misdeal);
+        Ok(())
+    }
+
+    fn misdeal(&mut self) -> Result<(), EngineError> {
+        if self.status() != HandStatus::InProgress {
+            return Err(EngineError::HandOver);
+        }
+        if !self.can_misdeal() {
+            return Err(EngineError::MisdealTooLate);
+        }
+        for seat in 0..self.seats.len() {
+            self.seats[seat].stack += self.pots.contributed(seat);
+        }
+        self.pots = PotManager::new(self.seats.len());
+        self.reveal_shuffle();
+        self.events.push(Event::Misdeal);
+        self.step = Step::Misdeal;
+        Ok(())
+    }
+
+    /// A fresh deal of this hand, with the same button, blinds and stacks and
+    /// the next shuffle.
+    pub fn redeal(&self) -> Result<GameEngine, EngineError> {
+        let config = EngineConfig {
+            shuffler: self.config.shuffler.next(),
+            ..self.config.clone()
+        };
+        Self::with_config(self.game.clone(), config)
+    }
+
+    /// Reports that `card`, dealt face down to `seat` in the deal that just
+    /// ran, was exposed. What happens next follows the game's
+    /// [`crate::DealingErrors`]: the card is burned and replaced, kept face
+    /// up, or the hand is a misdeal.
+    pub fn expose_card(&mut self, seat: usize, card: Card) -> Result<(), EngineError> {
+        self.expose(seat, card)?;
+        self.inputs.push(Input::Expose(seat, card));
+        Ok(())
+    }
+
+    fn expose(&mut self, seat: usize, card: Card) -> Result<(), EngineError> {
+        if self.status() != HandStatus::InProgress {
+            return Err(EngineError::HandOver);
+        }
+        if !self.seats.get(seat).is_some_and(Seat::is_live) {
+            return Err(EngineError::NotInHand(seat));
+        }
+        let last_action = self.events.iter().rposition(|e| {
+            matches!(
+                e,
+                Event::Acted { .. } | Event::CardsExchanged { .. } | Event::CardsPassed { .. }
+            )
+        });
+        let dealt = self.events.iter().rposition(|e| match e {
+            Event::HoleCardsDealt { seat: s, cards } => {
+                *s == seat && cards.iter().any(|c| c.card == card)
+            }
+            _ => false,
+        });
+        let in_deal = dealt.is_some_and(|d| last_action.is_none_or(|a| a < d));
+        let Some(idx) = self.seats[seat]
+            .hole
+            .iter()
+            .position(|c| c.card == card && !c.face_up)
+            .filter(|_| in_deal)
+        else {
+            return Err(EngineError::NotExposable(card));
+        };
+
+        let rules = self.game.dealing_errors.clone().unwrap_or_default();
+        let exposed = 1 + self
+            .events
+            .iter()
+            .filter(|e| matches!(e, Event::CardExposed { .. }))
+            .count();
+        let misdeal = rules.exposed_card() == ExposedCard::Redeal
+            || (rules.max_exposed > 0 && exposed >= rules.max_exposed as usize);
+        if misdeal && self.can_misdeal() {
+            self.events.push(Event::CardExposed {
+                seat,
+                card,
+                replacement: None,
+            });
+            return self.misdeal();
+        }
+
+        let replacement = match rules.exposed_card() {
+            ExposedCard::Unknown | ExposedCard::Burn => {
+                let next = self.draw(Destination::Seat(seat))?;
+                self.seats[seat].hole[idx].card = next;
+                self.burned.push(card);
+                Some(next)
+            }
+            ExposedCard::PlayOn | ExposedCard::Redeal => {
+                self.seats[seat].hole[idx].face_up = true;
+                None
+            }
+        };
+        self.events.push(Event::CardExposed {
+            seat,
+            card,
+            replacement,
+        });
+        Ok(())
+    }
+
+    /// Rules `seat`'s hand dead, e.g. for holding the wrong number of cards.
+    /// It is folded at once, even if it isn't their turn, and its chips stay
+    /// in the pot.
+    pub fn declare_dead_hand(&mut self, seat: usize) -> Result<(), EngineError> {
+        self.kill_hand(seat)?;
+        self.inputs.push(Input::DeadHand(seat));
+        Ok(())
+    }
+
+    fn kill_hand(&mut self, seat: usize) -> Result<(), EngineError> {
+        if self.status() != HandStatus::InProgress {
+            return Err(EngineError::HandOver);
+        }
+        if !self.seats.get(seat).is_some_and(Seat::is_live) {
+            return Err(EngineError::NotInHand(seat));
+        }
+        self.seats[seat].folded = true;
+        self.pots.fold(seat);
+        self.events.push(Event::HandDeclaredDead { seat });
+        match std::mem::replace(&mut self.step, Step::Complete) {
+            Step::Betting(mut round) => {
+                round.fold_out(seat);
+                if round.is_complete() {
+                    return self.finish_phase();
+                }
+                self.step = Step::Betting(round);
+            }
+            Step::Exchange {
+                mut to_act,
+                mut remaining,
+            } => {
+                remaining.retain(|&s| s != seat);
+                if self.live_count() <= 1 || (to_act == seat && remaining.is_empty()) {
+                    return self.finish_phase();
+                }
+                if to_act == seat {
+                    to_act = remaining.remove(0);
+                }
+                self.step = Step::Exchange { to_act, remaining };
+            }
+            Step::Buying {
+                mut to_act,
+                mut remaining,
+            } => {
+                remaining.retain(|&s| s != seat);
+                if self.live_count() <= 1 || (to_act == seat && remaining.is_empty()) {
+                    return self.finish_phase();
+                }
+                if to_act == seat {
+                    to_act = remaining.remove(0);
+                }
+                self.step = Step::Buying { to_act, remaining };
+            }
+            Step::Passing {
+                mut to_act,
+                mut remaining,
+                mut chosen,
+            } => {
+                remaining.retain(|&s| s != seat);
+                chosen.retain(|(s, _)| *s != seat);
+                if self.live_count() <= 1 || (to_act == seat && remaining.is_empty()) {
+                    return self.finish_passing(chosen);
+                }
+                if to_act == seat {
+                    to_act = remaining.remove(0);
+                }
+                self.step = Step::Passing {
+                    to_act,
+                    remaining,
+                    chosen,
+                };
+            }
+            Step::Revealing {
+                mut to_act,
+                mut remaining,
+                mut chosen,
+            } => {
+                remaining.retain(|&s| s != seat);
+                chosen.retain(|(s, _)| *s != seat);
+                if self.live_count() <= 1 || (to_act == seat && remaining.is_empty()) {
+                    return self.finish_revealing(chosen);
+                }
+                if to_act == seat {
+                    to_act = remaining.remove(0);
+                }
+                self.step = Step::Revealing {
+                    to_act,
+                    remaining,
+                    chosen,
+                };
+            }
+            Step::Declaring {
+                mut to_act,
+                mut remaining,
+                mut chosen,
+            } => {
+                remaining.retain(|&s| s != seat);
+                chosen.retain(|(s, _)| *s != seat);
+                if self.live_count() <= 1 || (to_act == seat && remaining.is_empty()) {
+                    return self.finish_declaring(chosen);
+                }
+                if to_act == seat {
+                    to_act = remaining.remove(0);
+                }
+                self.step = Step::Declaring {
+                    to_act,
+                    remaining,
+                    chosen,
+                };
+            }
+            step => self.step = step,
+        }
+        Ok(())
+    }
+
+    fn live_count(&self) -> usize {
+        self.seats.iter().filter(|s| s.is_live()).count()
+    }
+
+    /// Seats in dealing order: starting left of the button and going around.
+    fn seats_from_button(&self) -> impl Iterator<Item = usize> {
+        self.seats_from(self.config.button)
+    }
+
+    /// Every seat once, starting left of `seat` and ending with it.
+    fn seats_from(&self, seat: usize) -> impl Iterator<Item = usize> {
+        let n = self.seats.len();
+        (1..=n).map(move |i| (seat + i) % n)
+    }
+
+    /// Runs dealer phases until the hand needs player input or ends.
+    fn advance(&mut self) -> Result<(), EngineError> {
+        loop {
+            if self.live_count() <= 1 {
+                self.award_uncontested();
+                return Ok(());
+            }
+            let Some(phase) = self.phases.get(self.phase_idx).cloned() else {
+                self.resolve_showdown();
+                return Ok(());
+            };
+            let runs = self.pending_runouts();
+            if runs > 1 {
+                return self.run_out(runs);
+            }
+            if let Some(stop) = self
+                .stops
+                .iter()
+                .find(|s| s.at == self.phase_idx && self.holds(&s.when))
+            {
+                self.events.push(Event::PhaseSkipped {
+                    index: self.phase_idx,
+                });
+                self.phase_idx = if stop.when.end_hand {
+                    self.phases.len()
+                } else {
+                    stop.end
+                };
+                continue;
+            }
+            if let Some(condition) = phase.condition.as_ref().filter(|c| !self.holds(c)) {
+                self.events.push(Event::PhaseSkipped {
+                    index: self.phase_idx,
+                });
+                self.phase_idx = if condition.end_hand {
+                    self.phases.len()
+                } else {
+                    self.phase_idx + 1
+                };
+                continue;
+            }
+            self.events.push(Event::PhaseStarted {
+                index: self.phase_idx,
+            });
+            match &phase.phase_type {
+                Some(phase::PhaseType::PlayerDeal(deal)) => self.deal_players(deal)?,
+                Some(phase::PhaseType::CommunityDeal(deal)) => self.deal_board(deal)?,
+                Some(phase::PhaseType::Ante(ante)) => self.collect_antes(ante),
+                Some(phase::PhaseType::BettingRound(round)) => {
+                    if let Some(state) = self.start_betting(round) {
+                        self.step = Step::Betting(state);
+                        return Ok(());
+                    }
+                    self.close_street();
+                }
+                Some(phase::PhaseType::Exchange(_)) => {
+                    let mut order: Vec<usize> = self
+                        .seats_from_button()
+                        .filter(|&s| self.seats[s].is_live())
+                        .collect();
+                    if !order.is_empty() {
+                        let to_act = order.remove(0);
+                        self.step = Step::Exchange {
+                            to_act,
+                            remaining: order,
+                        };
+                        return Ok(());
+                    }
+                }
+                Some(phase::PhaseType::PassCards(_)) => {
+                    let mut order: Vec<usize> = self
+                        .seats_from_button()
+                        .filter(|&s| self.seats[s].is_live())
+                        .collect();
+                    let to_act = order.remove(0);
+                    self.step = Step::Passing {
+                        to_act,
+                        remaining: order,
+                        chosen: Vec::new(),
+                    };
+                    return Ok(());
+                }
+                Some(phase::PhaseType::Reveal(reveal)) => {
+                    if let Some(step) = self.start_reveal(reveal) {
+                        self.step = step;
+                        return Ok(());
+                    }
+                }
+                Some(phase::PhaseType::Declare(_)) => {
+                    let mut order: Vec<usize> = self
+                        .seats_from_button()
+                        .filter(|&s| self.seats[s].is_live())
+                        .collect();
+                    let to_act = order.remove(0);
+                    self.step = Step::Declaring {
+                        to_act,
+                        remaining: order,
+                        chosen: Vec::new(),
+                    };
+                    return Ok(());
+                }
+                Some(phase::PhaseType::Buy(rules)) => {
+                    let price = self.buy_price(rules);
+                    let mut order: Vec<usize> = self
+                        .seats_from_button()
+                        .filter(|&s| self.seats[s].is_live() && self.seats[s].stack >= price)
+                        .collect();
+                    if !order.is_empty() {
+                        let to_act = order.remove(0);
+                        self.step = Step::Buying {
+                            to_act,
+                            remaining: order,
+                        };
+                        return Ok(());
+                    }
+                }
+                // Repeats are written out when the engine starts.
+                Some(phase::PhaseType::Repeat(_)) | None => {}
+            }
+            self.phase_idx += 1;
+        }
+    }
+
+    /// Whether a phase's condition holds right now.
+    fn holds(&self, condition: &phase::Condition) -> bool {
+        let measure = match condition.measure() {
+            Measure::Players => self.live_count() as u64,
+            Measure::ActivePlayers => self.seats.iter().filter(|s| s.can_act()).count() as u64,
+            Measure::Pot => self.pots.total(),
+            Measure::Unknown => return true,
+        };
+        let above = measure as i128 > i128::from(condition.above);
+        above != condition.negate
+    }
+
+    fn finish_phase(&mut self) -> Result<(), EngineError> {
+        self.close_street();
+        self.phase_idx += 1;
+        self.advance()
+    }
+
+    /// Returns any uncalled bet and starts a fresh street.
+    fn close_street(&mut self) {
+        if let Some((seat, amount)) = self.pots.return_uncalled() {
+            self.seats[seat].stack += amount;
+            self.events
+                .push(Event::UncalledBetReturned { seat, amount });
+        }
+        self.pots.end_street();
+    }
+
+    /// Whether community deals go to both boards of a double-board bomb pot.
+    fn double_board(&self) -> bool {
+        self.config.bomb_pot > 0
+            && self.config.bomb_pot_double_board
+            && self.game.community_board_count == 1
+    }
+
+    /// Moves up to `amount` from a seat's stack into the pot, returning what
+    /// was actually paid.
+    fn pay(&mut self, seat: usize, amount: Chips) -> Chips {
+        let paid = amount.min(self.seats[seat].stack);
+        self.seats[seat].stack -= paid;
+        self.pots.contribute(seat, paid);
+        paid
+    }
+
+    fn draw(&mut self, to: Destination) -> Result<Card, EngineError> {
+        let card = self.shoe.deal().ok_or(EngineError::DeckExhausted)?;
+        self.audit.append(AuditEntry::Draw { card, to });
+        Ok(card)
+    }
+
+    fn deal_players(&mut self, deal: &phase::PlayerDeal) -> Result<(), EngineError> {
+        let order: Vec<usize> = self
+            .seats_from_button()
+            .filter(|&s| self.seats[s].is_live())
+            .collect();
+        for _ in 0..deal.burn {
+            let card = self.draw(Destination::Burn)?;
+            self.burned.push(card);
+        }
+        let face_up = dealt_face_up(deal);
+        for seat in order {
+            let mut cards = Vec::with_capacity(face_up.len());
+            for &face_up in &face_up {
+                cards.push(DealtCard {
+                    card: self.draw(Destination::Seat(seat))?,
+                    face_up,
+                });
+            }
+            self.seats[seat].hole.extend(&cards);
+            self.events.push(Event::HoleCardsDealt { seat, cards });
+        }
+        Ok(())
+    }
+
+    fn deal_board(&mut self, deal: &phase::CommunityDeal) -> Result<(), EngineError> {
+        let first = deal.board_idx.max(0) as usize;
+        let last = if self.double_board() { 1 } else { first };
+        if self.boards.len() <= last {
+            self.boards.resize(last + 1, Vec::new());
+            self.hidden.resize(last + 1, Vec::new());
+        }
+        for board in first..=last {
+            for _ in 0..deal.burn {
+                let card = self.draw(Destination::Burn)?;
+                self.burned.push(card);
+            }
+            let mut cards = Vec::with_capacity(deal.cards.max(0) as usize);
+            for _ in 0..deal.cards {
+                cards.push(self.draw(Destination::Board(board))?);
+            }
+            if deal.face_down {
+                let count = cards.len();
+                self.hidden[board].extend(cards);
+                self.events.push(Event::HiddenBoardDealt { board, count });
+            } else {
+                self.boards[board].extend(&cards);
+                self.events.push(Event::BoardDealt { board, cards });
+            }
+        }
+        Ok(())
+    }
+
+    /// Turns up the cards a reveal phase names, or returns the step that
+    /// waits on players to pick theirs.
+    fn start_reveal(&mut self, reveal: &phase::Reveal) -> Option<Step> {
+        if reveal.community {
+            let first = reveal.board_idx.max(0) as usize;
+            let last = if self.double_board() { 1 } else { first };
+            for board in first..=last {
+                self.reveal_board(board, reveal.cards.max(0) as usize);
+            }
+            return None;
+        }
+        let mut order: Vec<usize> = self
+            .seats_from_button()
+            .filter(|&s| self.seats[s].is_live() && self.reveal_count(s, reveal) > 0)
+            .collect();
+        if reveal.choose {
+            if order.is_empty() {
+                return None;
+            }
+            let to_act = order.remove(0);
+            return Some(Step::Revealing {
+                to_act,
+                remaining: order,
+                chosen: Vec::new(),
+            });
+        }
+        for seat in order {
+            let cards: Vec<Card> = self.seats[seat]
+                .hole
+                .iter()
+                .filter(|c| !c.face_up)
+                .take(reveal.cards.max(0) as usize)
+                .map(|c| c.card)
+                .collect();
+            self.turn_up(seat, cards);
+        }
+        None
+    }
+
+    /// How many cards `seat` turns up in a reveal phase: the phase's count,
+    /// or every face-down card if they have fewer.
+    fn reveal_count(&self, seat: usize, reveal: &phase::Reveal) -> usize {
+        let face_down = self.seats[seat].hole.iter().filter(|c| !c.face_up).count();
+        face_down.min(reveal.cards.max(0) as usize)
+    }
+
+    /// Checks the cards `seat` picked to reveal and saves them in `chosen`.
+    fn choose_reveal(
+        &mut self,
+        seat: usize,
+        cards: &[Card],
+        chosen: &mut Vec<(usize, Vec<Card>)>,
+    ) -> Result<(), EngineError> {
+        let Some(phase::PhaseType::Reveal(rules)) = self
+            .phases
+            .get(self.phase_idx)
+            .and_then(|p| p.phase_type.clone())
+        else {
+            return Err(EngineError::HandOver);
+        };
+        let count = self.reveal_count(seat, &rules);
+        if cards.len() != count {
+            return Err(EngineError::InvalidReveal(format!(
+                "exactly {} cards must be revealed",
+                count
+            )));
+        }
+        for (i, card) in cards.iter().enumerate() {
+            if !self.seats[seat]
+                .hole
+                .iter()
+                .any(|c| c.card == *card && !c.face_up)
+            {
+                return Err(EngineError::InvalidReveal(format!(
+                    "{} is not a face-down card in hand",
+                    card
+                )));
+            }
+            if cards[..i].contains(card) {
+                return Err(EngineError::InvalidReveal(format!("{} listed twice", card)));
+            }
+        }
+        chosen.push((seat, cards.to_vec()));
+        Ok(())
+    }
+
+    /// Turns up the cards chosen in a reveal phase and ends the phase.
+    fn finish_revealing(&mut self, chosen: Vec<(usize, Vec<Card>)>) -> Result<(), EngineError> {
+        for (seat, cards) in chosen {
+            self.turn_up(seat, cards);
+        }
+        self.finish_phase()
+    }
+
+    fn turn_up(&mut self, seat: usize, cards: Vec<Card>) {
+        for card in &cards {
+            if let Some(c) = self.seats[seat]
+                .hole
+                .iter_mut()
+                .find(|c| c.card == *card && !c.face_up)
+            {
+                c.face_up = true;
+            }
+        }
+        if !cards.is_empty() {
+            self.events.push(Event::CardsRevealed { seat, cards });
+        }
+    }
+
+    /// Whether the declare phase in progress is made aloud, one seat at a
+    /// time.
+    fn declares_in_turn(&self) -> bool {
+        matches!(
+            self.phases.get(self.phase_idx).and_then(|p| p.phase_type.as_ref()),
+            Some(phase::PhaseType::Declare(rules)) if rules.in_turn
+        )
+    }
+
+    fn declare(&mut self, seat: usize, side: Side) {
+        self.declarations[seat] = Some(side);
+        self.events.push(Event::Declared { seat, side });
+    }
+
+    /// Shows the secret declarations, in the order they were made, and ends
+    /// the phase.
+    fn finish_declaring(&mut self, chosen: Vec<(usize, Side)>) -> Result<(), EngineError> {
+        for (seat, side) in chosen {
+            self.declare(seat, side);
+        }
+        self.finish_phase()
+    }
+
+    /// Moves up to `count` face-down cards onto the board, in the order they
+    /// were dealt.
+    fn reveal_board(&mut self, board: usize, count: usize) {
+        let Some(hidden) = self.hidden.get_mut(board) else {
+            return;
+        };
+        let cards: Vec<Card> = hidden.drain(..count.min(hidden.len())).collect();
+        if !cards.is_empty() {
+            self.boards[board].extend(&cards);
+            self.events.push(Event::BoardRevealed { board, cards });
+        }
+    }
+
+    /// Seats that post each of the configured blinds, smallest first.
+    fn blind_seats(&self) -> Vec<usize> {
+        if let Some(p) = &self.config.positions {
+            return p.small_blind.into_iter().chain([p.big_blind]).collect();
+        }
+        let mut live: Vec<usize> = self
+            .seats_from_button()
+            .filter(|&s| self.seats[s].is_live())
+            .collect();
+        // Heads-up, the button posts the small blind.
+        if live.len() == 2 {
+            live.swap(0, 1);
+        }
+        live.truncate(self.config.blinds.len());
+        live
+    }
+
+    fn collect_antes(&mut self, ante: &phase::Ante) {
+        let amount = self.config.ante;
+        if amount == 0 || self.config.bomb_pot > 0 {
+            return;
+        }
+        let payers: Vec<usize> = match ante.payer() {
+            phase::ante::Payer::BigBlind => {
+                let seats = self.blind_seats();
+                let first = self.seats_from_button().find(|&s| self.seats[s].can_act());
+                seats.last().copied().or(first).into_iter().collect()
+            }
+            _ => self
+                .seats_from_button()
+                .filter(|&s| self.seats[s].can_act())
+                .collect(),
+        };
+        for seat in payers {
+            // A player who can't cover the ante posts what they have and is
+            // all-in for the rest of the hand.
+            let paid = amount.min(self.seats[seat].stack);
+            self.seats[seat].stack -= paid;
+            self.pots.contribute_dead(seat, paid);
+            self.events.push(Event::AntePosted { seat, amount: paid });
+        }
+    }
+
+    /// Posts blinds in the first betting round, returning the seat of the last
+    /// blind posted.
+    fn post_blinds(&mut self) -> Option<usize> {
+        if let Some(positions) = self.config.positions.clone() {
+            return Some(self.post_positioned_blinds(&positions));
+        }
+        let posters = self.blind_seats();
+        let mut last = None;
+        let blinds = self.config.blinds.clone();
+        for (&seat, &amount) in posters.iter().zip(&blinds) {
+            let paid = self.pay(seat, amount);
+            if paid > 0 {
+                self.events.push(Event::BlindPosted { seat, amount: paid });
+            }
+            last = Some(seat);
+        }
+        last
+    }
+
+    fn post_positioned_blinds(&mut self, positions: &HandPositions) -> usize {
+        let blinds = &self.config.blinds;
+        let big = blinds.last().copied().unwrap_or(0);
+        let small = if blinds.len() >= 2 { blinds[0] } else { 0 };
+
+        let mut posts = Vec::new();
+        if let Some(seat) = positions.small_blind {
+            posts.push((seat, small));
+        }
+        posts.push((positions.big_blind, big));
+        for &(seat, missed) in &positions.owed {
+            if missed.big {
+                posts.push((seat, big));
+            }
+        }
+        for (seat, amount) in posts {
+            if amount > 0 && self.seats[seat].can_act() {
+                let paid = self.pay(seat, amount);
+                self.events.push(Event::BlindPosted { seat, amount: paid });
+            }
+        }
+        for &(seat, missed) in &positions.owed {
+            if missed.small && small > 0 && self.seats[seat].can_act() {
+                let paid = small.min(self.seats[seat].stack);
+                self.seats[seat].stack -= paid;
+                self.pots.contribute_dead(seat, paid);
+                self.events
+                    .push(Event::DeadBlindPosted { seat, amount: paid });
+            }
+        }
+        positions.big_blind
+    }
+
+    /// Posts the kill blind, topping up anything the killer already has in as
+    /// a blind. The killer then acts last before the flop. Returns false if
+    /// they couldn't post.
+    fn post_kill_blind(&mut self, kill: KillPot, big_blind: Chips) -> bool {
+        let seat = kill.seat;
+        if seat >= self.seats.len() || !self.seats[seat].can_act() {
+            return false;
+        }
+        let amount = kill.scale(big_blind);
+        let owed = amount.saturating_sub(self.pots.street_contribution(seat));
+        let paid = self.pay(seat, owed);
+        self.events
+            .push(Event::KillBlindPosted { seat, amount: paid });
+        true
+    }
+
+    /// Every player puts in the bomb pot amount, or all they have.
+    fn post_bomb_pot(&mut self) {
+        let amount = self.config.bomb_pot;
+        let posters: Vec<usize> = self
+            .seats_from_button()
+            .filter(|&s| self.seats[s].can_act())
+            .collect();
+        for seat in posters {
+            let paid = self.pay(seat, amount);
+            self.events
+                .push(Event::BombPotPosted { seat, amount: paid });
+        }
+    }
+
+    /// Posts any straddles after the blinds, returning the seat that action
+    /// starts after, the largest straddle, and the button if it straddled.
+    fn post_straddles(
+        &mut self,
+        last_blind: usize,
+        big_blind: Chips,
+    ) -> (usize, Chips, Option<usize>) {
+        let mut blinders = self.blind_seats();
+        blinders.push(last_blind);
+        let mut last = last_blind;
+        let mut amount = big_blind;
+        let mut button = None;
+        if self.config.straddles > 0 {
+            let order: Vec<usize> = self
+                .seats_from(last_blind)
+                .filter(|&s| self.seats[s].can_act() && !blinders.contains(&s))
+                .take(self.config.straddles)
+                .collect();
+            for seat in order {
+                amount *= 2;
+                let paid = self.pay(seat, amount);
+                self.events
+                    .push(Event::StraddlePosted { seat, amount: paid });
+                last = seat;
+            }
+        } else if self.config.button_straddle {
+            let seat = self.config.button;
+            if self.seats[seat].can_act() && !blinders.contains(&seat) {
+                amount *= 2;
+                let paid = self.pay(seat, amount);
+                self.events
+                    .push(Event::StraddlePosted { seat, amount: paid });
+                button = Some(seat);
+            }
+        }
+        (last, if amount > big_blind { amount } else { 0 }, button)
+    }
+
+    fn start_betting(&mut self, round: &phase::BettingRound) -> Option<BettingState> {
+        self.rounds_started += 1;
+        let big_blind = self.config.blinds.iter().copied().max().unwrap_or(0).max(1);
+        let mut min_bet = big_blind * round.min_bet.max(1) as Chips;
+
+        // Blinds go in once, in the first betting round, unless that round
+        // opens with a bring-in instead.
+        let mut last_blind = None;
+        let mut deferred = None;
+        if !self.blinds_posted && self.config.bomb_pot > 0 {
+            self.blinds_posted = true;
+            self.post_bomb_pot();
+            return None;
+        }
+        if !self.blinds_posted {
+            self.blinds_posted = true;
+            if round.bring_in.is_none() {
+                last_blind = self.post_blinds();
+            }
+            if let Some(last) = last_blind {
+                let (last, straddle, button) = self.post_straddles(last, big_blind);
+                last_blind = Some(last);
+                deferred = button;
+                // Raises must be at least the size of the largest straddle.
+                min_bet = min_bet.max(straddle);
+                if let Some(kill) = self.config.kill {
+                    if self.post_kill_blind(kill, big_blind) {
+                        deferred = Some(kill.seat);
+                    }
+                }
+            }
+        }
+        if let Some(kill) = self.config.kill {
+            min_bet = min_bet.max(kill.scale(big_blind * round.min_bet.max(1) as Chips));
+        }
+
+        let bettors = self
+            .seats
+            .iter()
+            .enumerate()
+            .map(|(i, s)| Bettor {
+                stack: s.stack,
+                bet: self.pots.street_contribution(i),
+                folded: s.folded,
+            })
+            .collect();
+        let mut state = BettingState::new(bettors, min_bet);
+        if let Some(seat) = deferred {
+            state.defer(seat);
+        }
+
+        let n = self.seats.len();
+        if let Some(bring_in) = &round.bring_in {
+            let actors: Vec<usize> = self
+                .seats_from_button()
+                .filter(|&s| self.seats[s].can_act())
+                .collect();
+            if actors.is_empty() {
+                return None;
+            }
+            let small_blind = self.config.blinds.iter().copied().min().unwrap_or(0);
+            let amount = small_blind * bring_in.amount.max(1) as Chips;
+            state.set_bring_in(amount, bring_in.allow_complete);
+            let first = self.bring_in_seat(&actors, bring_in.starter());
+            return state.begin(first).then_some(state);
+        }
+        let first = match (round.order(), last_blind) {
+            (BettingOrder::FollowBlinds, Some(last)) => (last + 1) % n,
+            (BettingOrder::BestFaceup, _) => {
+                let actors: Vec<usize> = (0..n).filter(|&s| self.seats[s].can_act()).collect();
+                if actors.is_empty() {
+                    return None;
+                }
+                self.best_faceup(&actors)
+            }
+            _ => (self.config.button + 1) % n,
+        };
+        state.begin(first).then_some(state)
+    }
+
+    /// The seat among `candidates` showing the strongest exposed cards; ties
+    /// go to the seat closest to the button's left.
+    fn best_faceup(&self, candidates: &[usize]) -> usize {
+        let strength = |seat: usize| {
+            let shown: Vec<Card> = self.seats[seat]
+                .hole
+                .iter()
+                .filter(|c| c.face_up)
+                .map(|c| c.card)
+                .collect();
+            handeval::evaluate(&shown)
+        };
+        let mut best = None;
+        for seat in self.seats_from_button().filter(|s| candidates.contains(s)) {
+            let s = strength(seat);
+            if best.is_none_or(|(_, b)| s > b) {
+                best = Some((seat, s));
+            }
+        }
+        best.map_or(candidates[0], |(seat, _)| seat)
+    }
+
+    /// The seat among `candidates` that must bring in: the one showing the
+    /// lowest upcard (or highest, for `Starter::Highest`). Equal ranks are
+    /// broken by suit in bridge order, spades highest.
+    fn bring_in_seat(&self, candidates: &[usize], starter: Starter) -> usize {
+        let upcards = |seat: usize| {
+            self.seats[seat]
+                .hole
+                .iter()
+                .filter(|c| c.face_up)
+                .map(|c| (c.card.rank, Reverse(c.card.suit)))
+        };
+        let found = match starter {
+            Starter::Highest => candidates
+                .iter()
+                .filter_map(|&s| upcards(s).max().map(|k| (k, s)))
+                .max(),
+            _ => candidates
+                .iter()
+                .filter_map(|&s| upcards(s).min().map(|k| (k, s)))
+                .min(),
+        };
+        found.map_or(candidates[0], |(_, seat)| seat)
+    }
+
+    fn exchange(&mut self, seat: usize, discards: &[Card]) -> Result<(), EngineError> {
+        let Some(phase::PhaseType::Exchange(rules)) = self
+            .phases
+            .get(self.phase_idx)
+            .and_then(|p| p.phase_type.clone())
+        else {
+            return Err(EngineError::HandOver);
+        };
+        if discards.len() > rules.max_exchange.max(0) as usize {
+            return Err(EngineError::InvalidDiscard(format!(
+                "at most {} cards may be exchanged",
+                rules.max_exchange
+            )));
+        }
+        let mut positions = Vec::with_capacity(discards.len());
+        for card in discards {
+            let pos = self.seats[seat]
+                .hole
+                .iter()
+                .position(|c| c.card == *card)
+                .ok_or_else(|| EngineError::InvalidDiscard(format!("{} is not in hand", card)))?;
+            if positions.contains(&pos) {
+                return Err(EngineError::InvalidDiscard(format!(
+                    "{} listed twice",
+                    card
+                )));
+            }
+            let face_up = self.seats[seat].hole[pos].face_up;
+            if (face_up && rules.lock_faceup) || (!face_up && rules.lock_facedown) {
+                return Err(EngineError::InvalidDiscard(format!("{} is locked", card)));
+            }
+            positions.push(pos);
+        }
+        if discards.len() > self.drawable(&rules) {
+            return Err(EngineError::DeckExhausted);
+        }
+        let reshuffle = rules.draw_source() == DrawSource::ReshuffleDiscards;
+        for pos in positions.iter().copied() {
+            if self.shoe.remaining() == 0 && reshuffle {
+                let count = self.discards.len();
+                let round = self.events.len() as u64;
+                self.shoe.restock(
+                    &std::mem::take(&mut self.discards),
+                    &self.config.shuffler,
+                    round,
+                );
+                self.audit.append(AuditEntry::shuffle(round, &self.shoe));
+                self.events.push(Event::DiscardsReshuffled { count });
+            }
+            let card = self.draw(Destination::Seat(seat))?;
+            self.seats[seat].hole[pos].card = card;
+        }
+        // Added only now, so that a player never draws their own discards.
+        self.discards.extend_from_slice(discards);
+        self.events.push(Event::CardsExchanged {
+            seat,
+            count: discards.len(),
+        });
+        if rules.public_discards && !discards.is_empty() {
+            self.events.push(Event::DiscardsShown {
+                seat,
+                cards: discards.to_vec(),
+            });
+        }
+        Ok(())
+    }
+
+    /// How many cards an exchange can still deal: what's left in the shoe,
+    /// and the discards if they're reshuffled once it runs out.
+    fn drawable(&self, rules: &phase::Exchange) -> usize {
+        match rules.draw_source() {
+            DrawSource::ReshuffleDiscards => self.shoe.remaining() + self.discards.len(),
+            _ => self.shoe.remaining(),
+        }
+    }
+
+    /// What a card costs in a buy phase.
+    fn buy_price(&self, rules: &phase::Buy) -> Chips {
+        let small_blind = self.config.blinds.iter().copied().min().unwrap_or(0);
+        small_blind * rules.price.max(0) as Chips
+    }
+
+    /// Charges `seat` for a card and deals it, in place of `discards` if the
+    /// phase replaces cards.
+    fn buy(&mut self, seat: usize, discards: &[Card]) -> Result<(), EngineError> {
+        let Some(phase::PhaseType::Buy(rules)) = self
+            .phases
+            .get(self.phase_idx)
+            .and_then(|p| p.phase_type.clone())
+        else {
+            return Err(EngineError::HandOver);
+        };
+        let pos = match (rules.replace, discards) {
+            (true, [card]) => Some(
+                self.seats[seat]
+                    .hole
+                    .iter()
+                    .position(|c| c.card == *card)
+                    .ok_or_else(|| EngineError::InvalidBuy(format!("{} is not in hand", card)))?,
+            ),
+            (false, []) => None,
+            (true, _) => {
+                return Err(EngineError::InvalidBuy(
+                    "exactly one card must be discarded".to_string(),
+                ))
+            }
+            (false, _) => {
+                return Err(EngineError::InvalidBuy(
+                    "the bought card doesn't replace one".to_string(),
+                ))
+            }
+        };
+        let price = self.buy_price(&rules);
+        let dealt = DealtCard {
+            card: self.draw(Destination::Seat(seat))?,
+            face_up: rules.face_up,
+        };
+        self.seats[seat].stack -= price;
+        self.pots.pay_fee(seat, price);
+        match pos {
+            Some(pos) => {
+                let old = std::mem::replace(&mut self.seats[seat].hole[pos], dealt);
+                self.discards.push(old.card);
+            }
+            None => self.seats[seat].hole.push(dealt),
+        }
+        self.events.push(Event::CardBought {
+            seat,
+            amount: price,
+            replaced: pos.is_some(),
+            shown: dealt.face_up.then_some(dealt.card),
+        });
+        Ok(())
+    }
+
+    /// Checks the cards `seat` passes, and hands them on at once in a
+    /// sequential pass or saves them in `chosen` otherwise.
+    fn pass(
+        &mut self,
+        seat: usize,
+        cards: &[Card],
+        chosen: &mut Vec<(usize, Vec<Card>)>,
+    ) -> Result<(), EngineError> {
+        let Some(phase::PhaseType::PassCards(rules)) = self
+            .phases
+            .get(self.phase_idx)
+            .and_then(|p| p.phase_type.clone())
+        else {
+            return Err(EngineError::HandOver);
+        };
+        let count = rules.cards.max(0) as usize;
+        if cards.len() != count {
+            return Err(EngineError::InvalidPass(format!(
+                "exactly {} cards must be passed",
+                count
+            )));
+        }
+        for (i, card) in cards.iter().enumerate() {
+            if !self.seats[seat].hole.iter().any(|c| c.card == *card) {
+                return Err(EngineError::InvalidPass(format!("{} is not in hand", card)));
+            }
+            if cards[..i].contains(card) {
+                return Err(EngineError::InvalidPass(format!("{} listed twice", card)));
+            }
+        }
+        if rules.sequential {
+            let to = self.pass_target(seat, rules.direction());
+            self.take_cards(seat, cards);
+            self.give_cards(seat, to, cards.to_vec());
+        } else {
+            chosen.push((seat, cards.to_vec()));
+        }
+        Ok(())
+    }
+
+    /// Moves the cards chosen in a simultaneous pass and ends the phase.
+    fn finish_passing(&mut self, chosen: Vec<(usize, Vec<Card>)>) -> Result<(), EngineError> {
+        if let Some(phase::PhaseType::PassCards(rules)) = self
+            .phases
+            .get(self.phase_idx)
+            .and_then(|p| p.phase_type.clone())
+        {
+            let moves: Vec<(usize, usize, Vec<Card>)> = chosen
+                .into_iter()
+                .map(|(from, cards)| (from, self.pass_target(from, rules.direction()), cards))
+                .collect();
+            for (from, _, cards) in &moves {
+                self.take_cards(*from, cards);
+            }
+            for (from, to, cards) in moves {
+                self.give_cards(from, to, cards);
+            }
+        }
+        self.finish_phase()
+    }
+
+    /// The live seat that `seat` passes to.
+    fn pass_target(&self, seat: usize, direction: Direction) -> usize {
+        let order: Vec<usize> = self
+            .seats_from(seat)
+            .filter(|&s| self.seats[s].is_live())
+            .collect();
+        // `order` ends with `seat` itself.
+        let n = order.len();
+        match direction {
+            Direction::Right => order[n.saturating_sub(2)],
+            Direction::Across => order[(n / 2).saturating_sub(1)],
+            Direction::Left | Direction::Unknown => order[0],
+        }
+    }
+
+    fn take_cards(&mut self, seat: usize, cards: &[Card]) {
+        for card in cards {
+            if let Some(i) = self.seats[seat].hole.iter().position(|c| c.card == *card) {
+                self.seats[seat].hole.remove(i);
+            }
+        }
+    }
+
+    fn give_cards(&mut self, from: usize, to: usize, cards: Vec<Card>) {
+        let count = cards.len();
+        self.seats[to]
+            .hole
+            .extend(cards.into_iter().map(|card| DealtCard {
+                card,
+                face_up: false,
+            }));
+        self.events.push(Event::CardsPassed { from, to, count });
+    }
+
+    /// Withholds rake and returns the pots left to award.
+    fn take_rake(&mut self) -> Vec<Pot> {
+        let Some(rake) = &self.game.rake else {
+            return self.pots.pots();
+        };
+        let flop_seen = self.boards.iter().any(|b| !b.is_empty()) || self.rounds_started > 1;
+        let (pots, amount) = self.pots.raked_pots(rake, flop_seen);
+        if amount > 0 {
+            self.rake = amount;
+            self.events.push(Event::RakeTaken { amount });
+        }
+        pots
+    }
+
+    /// Reveals a committed shuffle once its shoe won't be dealt from again.
+    fn reveal_shuffle(&mut self) {
+        if let Some(&Commitment { seed, salt }) = self.config.shuffler.commitment() {
+            self.events.push(Event::ShuffleRevealed { seed, salt });
+        }
+    }
+
+    fn complete(&mut self) {
+        if self.remaining_shoe().is_none() {
+            self.reveal_shuffle();
+        }
+        self.events.push(Event::HandComplete);
+        self.step = Step::Complete;
+    }
+
+    fn award_uncontested(&mut self) {
+        let pot: Chips = self.take_rake().iter().map(|p| p.amount).sum();
+        if let Some(winner) = self.seats.iter().position(Seat::is_live) {
+            self.seats[winner].stack += pot;
+            self.events.push(Event::PotAwarded {
+                seat: winner,
+                amount: pot,
+            });
+        }
+        self.complete();
+    }
+
+    /// How many times the rest of the hand should be run, or 1 if it should
+    /// play out normally. Multiple runs need everyone but at most one player
+    /// to be all-in with the bets matched, and nothing left but community
+    /// cards to deal. There are never more runs than the shoe can deal,
+    /// burns and every board included.
+    fn pending_runouts(&self) -> usize {
+        let runs = match self.config.runouts {
+            0 => self.game.all_in_runouts.max(1) as usize,
+            n => n as usize,
+        };
+        if runs <= 1 || self.seats.iter().filter(|s| s.can_act()).count() > 1 {
+            return 1;
+        }
+        let boards = if self.double_board() { 2 } else { 1 };
+        let mut cards = 0;
+        for phase in &self.phases[self.phase_idx..] {
+            match &phase.phase_type {
+                Some(phase::PhaseType::CommunityDeal(deal)) => {
+                    cards += (deal.cards.max(0) + deal.burn.max(0)) as usize * boards
+                }
+                Some(
+                    phase::PhaseType::PlayerDeal(_)
+                    | phase::PhaseType::Exchange(_)
+                    | phase::PhaseType::PassCards(_)
+                    | phase::PhaseType::Reveal(_)
+                    | phase::PhaseType::Declare(_)
+                    | phase::PhaseType::Buy(_),
+                ) => return 1,
+                _ => {}
+            }
+        }
+        if cards == 0 {
+            return 1;
+        }
+        runs.min(self.shoe.remaining() / cards).max(1)
+    }
+
+    /// Deals the remaining community cards `runs` times from the same deck,
+    /// splitting every pot evenly between the runouts.
+    fn run_out(&mut self, runs: usize) -> Result<(), EngineError> {
+        let deals: Vec<phase::CommunityDeal> = self.phases[self.phase_idx..]
+            .iter()
+            .filter_map(|p| match &p.phase_type {
+                Some(phase::PhaseType::CommunityDeal(deal)) => Some(deal.clone()),
+                _ => None,
+            })
+            .collect();
+        for board in 0..self.hidden.len() {
+            self.reveal_board(board, usize::MAX);
+        }
+        let base = self.boards.clone();
+        let pots = self.take_rake();
+        for run in 0..runs {
+            self.events.push(Event::RunoutStarted { run });
+            self.boards = base.clone();
+            for deal in &deals {
+                self.deal_board(deal)?;
+            }
+            let share: Vec<Pot> = pots
+                .iter()
+                .map(|p| Pot {
+                    amount: p.amount / runs as Chips
+                        + Chips::from((run as Chips) < p.amount % runs as Chips),
+                    eligible: p.eligible.clone(),
+                })
+                .collect();
+            self.show_down(&share);
+        }
+        self.boards = self.runouts[0].boards.clone();
+        self.phase_idx = self.phases.len();
+        self.complete();
+        Ok(())
+    }
+
+    fn resolve_showdown(&mut self) {
+        let pots = self.take_rake();
+        self.show_down(&pots);
+        self.complete();
+    }
+
+    /// The game with each scoring repeated once per board of a double-board
+    /// bomb pot, each copy using only its own board.
+    fn double_board_game(&self) -> GameStructure {
+        let mut game = self.game.clone();
+        let base = if game.scorings.is_empty() {
+            vec![crate::Scoring::default()]
+        } else {
+            std::mem::take(&mut game.scorings)
+        };
+        let any = crate::scoring::Range { min: -1, max: -1 };
+        let none = crate::scoring::Range { min: 0, max: 0 };
+        game.community_board_count = self.boards.len() as i32;
+        for board in 0..self.boards.len() {
+            for scoring in &base {
+                let mut scoring = scoring.clone();
+                scoring.community_cards = (0..self.boards.len())
+                    .map(|i| {
+                        if i == board {
+                            any.clone()
+                        } else {
+                            none.clone()
+                        }
+                    })
+                    .collect();
+                game.scorings.push(scoring);
+            }
+        }
+        game
+    }
+
+    /// Awards `pots` using the current boards, once any face-down community
+    /// cards are turned up, and records the runout.
+    fn show_down(&mut self, pots: &[Pot]) {
+        for board in 0..self.hidden.len() {
+            self.reveal_board(board, usize::MAX);
+        }
+        let holes: Vec<Vec<Card>> = self
+            .seats
+            .iter()
+            .map(|s| {
+                if s.is_live() {
+                    s.hole.iter().map(|c| c.card).collect()
+                } else {
+                    Vec::new()
+                }
+            })
+            .collect();
+        let game = if self.double_board() {
+            self.double_board_game()
+        } else {
+            self.game.clone()
+        };
+        let result = showdown::resolve_declared(
+            &game,
+            &holes,
+            &self.boards,
+            pots,
+            self.config.button,
+            &self.declarations,
+        );
+        for hand in &result.hands {
+            self.events.push(Event::HandShown {
+                seat: hand.seat,
+                scoring: hand.scoring,
+                rank: hand.rank,
+            });
+        }
+        for &(seat, amount) in result.pots.iter().flat_map(|p| &p.payouts) {
+            self.seats[seat].stack += amount;
+            self.events.push(Event::PotAwarded { seat, amount });
+        }
+        self.runouts.push(Runout {
+            boards: self.boards.clone(),
+            result,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::positions::MissedBlinds;
+    use crate::presets;
+
+    #[test]
+    fn test_preflop_setup() {
+        let engine = GameEngine::new(presets::holdem(), 3).unwrap();
+        assert_eq!(engine.status(), HandStatus::InProgress);
+        assert!(engine.seats().iter().all(|s| s.hole.len() == 2));
+        assert_eq!(engine.pot(), 3);
+        // Button is seat 0, blinds are seats 1 and 2, so seat 0 is first.
+        assert_eq!(engine.to_act(), Some(0));
+        assert_eq!(
+            engine.legal_actions(0),
+            vec![
+                LegalAction::Fold,
+                LegalAction::Call(2),
+                LegalAction::Raise { min: 4, max: 200 },
+                LegalAction::AllIn(200),
+            ]
+        );
+        assert!(engine.legal_actions(1).is_empty());
+    }
+
+    #[test]
+    fn test_fold_to_big_blind() {
+        let mut engine = GameEngine::new(presets::holdem(), 3).unwrap();
+        engine.apply_action(0, Action::Fold).unwrap();
+        engine.apply_action(1, Action::Fold).unwrap();
+        assert_eq!(engine.status(), HandStatus::Complete);
+        assert_eq!(engine.seats()[2].stack, 201);
+        assert!(engine
+            .events()
+            .contains(&Event::UncalledBetReturned { seat: 2, amount: 1 }));
+    }
+
+    #[test]
+    fn test_checked_down_to_showdown() {
+        let mut engine = GameEngine::new(presets::holdem(), 2).unwrap();
+        // Heads-up: button posts the small blind and acts first preflop.
+        engine.apply_action(0, Action::Call).unwrap();
+        engine.apply_action(1, Action::Check).unwrap();
+        for _ in 0..3 {
+            engine.apply_action(1, Action::Check).unwrap();
+            engine.apply_action(0, Action::Check).unwrap();
+        }
+        assert_eq!(engine.status(), HandStatus::Complete);
+        assert_eq!(engine.boards()[0].len(), 5);
+        assert_eq!(engine.burned().len(), 3);
+        assert_eq!(engine.pot(), 4);
+        let result = engine.showdown().unwrap();
+        assert_eq!(result.hands.len(), 2);
+        assert_eq!(engine.seats().iter().map(|s| s.stack).sum::<Chips>(), 400);
+    }
+
+    #[test]
+    fn test_rejects_out_of_turn_and_short_raise() {
+        let mut engine = GameEngine::new(presets::holdem(), 3).unwrap();
+        assert_eq!(
+            engine.apply_action(1, Action::Call),
+            Err(EngineError::NotYourTurn { seat: 1, to_act: 0 })
+        );
+        assert_eq!(
+            engine.apply_action(0, Action::Raise(3)),
+            Err(EngineError::Betting(BettingError::RaiseTooSmall {
+                amount: 3,
+                min: 4
+            }))
+        );
+        assert_eq!(
+            engine.apply_action(0, Action::Check),
+            Err(EngineError::Betting(BettingError::CannotCheck {
+                to_call: 2
+            }))
+        );
+        engine.apply_action(0, Action::Raise(6)).unwrap();
+        assert_eq!(engine.to_act(), Some(1));
+    }
+
+    #[test]
+    fn test_antes() {
+        let mut config = EngineConfig::new(3);
+        config.ante = 5;
+        config.stacks = vec![200, 3, 200];
+        let engine = GameEngine::with_config(presets::holdem(), config.clone()).unwrap();
+        // Seat 1 could only cover 3 of the ante and is all-in.
+        assert!(engine
+            .events()
+            .contains(&Event::AntePosted { seat: 1, amount: 3 }));
+        assert!(engine.seats()[1].is_all_in());
+        // Seat 2 posts the big blind; seat 1 had nothing left for the small.
+        assert_eq!(engine.pot(), 13 + 2);
+
+        let mut game = presets::holdem();
+        game.phases.insert(
+            0,
+            Phase {
+                phase_type: Some(phase::PhaseType::Ante(phase::Ante {
+                    payer: phase::ante::Payer::BigBlind.into(),
+                })),
+                ..Default::default()
+            },
+        );
+        config.stacks = vec![200; 3];
+        let engine = GameEngine::with_config(game, config).unwrap();
+        let antes: Vec<&Event> = engine
+            .events()
+            .iter()
+            .filter(|e| matches!(e, Event::AntePosted { .. }))
+            .collect();
+        assert_eq!(antes, vec![&Event::AntePosted { seat: 2, amount: 5 }]);
+    }
+
+    #[test]
+    fn test_positioned_blinds() {
+        let mut config = EngineConfig::new(4);
+        config.positions = Some(HandPositions {
+            button: 1,
+            dead_button: false,
+            small_blind: None,
+            big_blind: 2,
+            owed: vec![(
+                0,
+                MissedBlinds {
+                    small: true,
+                    big: true,
+                },
+            )],
+        });
+        let engine = GameEngine::with_config(presets::holdem(), config).unwrap();
+        assert_eq!(engine.button(), 1);
+        assert!(engine
+            .events()
+            .contains(&Event::DeadBlindPosted { seat: 0, amount: 1 }));
+        // Seat 0 posted a live big blind and seat 2 the blind in position.
+        assert_eq!(engine.pot(), 5);
+        assert_eq!(engine.to_act(), Some(3));
+    }
+
+    #[test]
+    fn test_invalid_seats() {
+        let error = |change: &dyn Fn(&mut EngineConfig)| {
+            let mut config = EngineConfig::new(4);
+            config.positions = Some(HandPositions {
+                button: 1,
+                dead_button: false,
+                small_blind: Some(2),
+                big_blind: 3,
+                owed: vec![(0, MissedBlinds::default())],
+            });
+            change(&mut config);
+            match GameEngine::with_config(presets::holdem(), config) {
+                Err(EngineError::InvalidSeat(why)) => why,
+                other => panic!("{:?}", other.map(|_| ())),
+            }
+        };
+        fn positions(config: &mut EngineConfig) -> &mut HandPositions {
+            config.positions.as_mut().unwrap()
+        }
+
+        assert_eq!(
+            error(&|c| positions(c).button = 4),
+            "the button is on seat 4, but the table has 4"
+        );
+        assert_eq!(
+            error(&|c| positions(c).small_blind = Some(9)),
+            "the small blind is on seat 9, but the table has 4"
+        );
+        assert_eq!(
+            error(&|c| positions(c).big_blind = 2),
+            "seat 2 has both blinds"
+        );
+        assert_eq!(
+            error(&|c| positions(c).owed.push((0, MissedBlinds::default()))),
+            "seat 0 owes blinds twice"
+        );
+        assert_eq!(
+            error(&|c| positions(c).owed[0].0 = 7),
+            "the seat owing blinds is on seat 7, but the table has 4"
+        );
+        assert_eq!(
+            error(&|c| {
+                c.positions = None;
+                c.kill = Some(KillPot {
+                    seat: 5,
+                    size: crate::blinds::kill::Size::Full,
+                })
+            }),
+            "the kill is on seat 5, but the table has 4"
+        );
+    }
+
+    #[test]
+    fn test_all_in_creates_side_pot() {
+        let mut config = EngineConfig::new(3);
+        config.stacks = vec![200, 50, 200];
+        let mut engine = GameEngine::with_config(presets::holdem(), config).unwrap();
+        engine.apply_action(0, Action::Raise(100)).unwrap();
+        engine.apply_action(1, Action::Call).unwrap();
+        engine.apply_action(2, Action::Call).unwrap();
+        // Seat 1 is all-in; the other two check it down.
+        for _ in 0..3 {
+            engine.apply_action(2, Action::Check).unwrap();
+            engine.apply_action(0, Action::Check).unwrap();
+        }
+        assert_eq!(engine.status(), HandStatus::Complete);
+        assert_eq!(
+            engine.pots(),
+            vec![
+                Pot {
+                    amount: 150,
+                    eligible: vec![0, 1, 2]
+                },
+                Pot {
+                    amount: 100,
+                    eligible: vec![0, 2]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stud_bring_in() {
+        let deal = |cards, face_up| Phase {
+            phase_type: Some(phase::PhaseType::PlayerDeal(phase::PlayerDeal {
+                cards,
+                face_up,
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        let game = GameStructure {
+            name: "Stud".to_string(),
+            phases: vec![
+                deal(2, false),
+                deal(1, true),
+                Phase {
+                    phase_type: Some(phase::PhaseType::BettingRound(phase::BettingRound {
+                        name: "Third street".to_string(),
+                        min_bet: 1,
+                        order: BettingOrder::BestFaceup.into(),
+                        bring_in: Some(phase::betting_round::BringIn {
+                            starter: Starter::Lowest.into(),
+                            amount: 1,
+                            allow_complete: true,
+                        }),
+                    })),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let mut config = EngineConfig::new(4);
+        config.blinds = vec![1, 4];
+        let mut engine = GameEngine::with_config(game, config).unwrap();
+
+        let upcard = |seat: usize| engine.seats()[seat].hole[2].card;
+        let lowest = (0..4)
+            .min_by_key(|&s| (upcard(s).rank, Reverse(upcard(s).suit)))
+            .unwrap();
+        assert_eq!(engine.to_act(), Some(lowest));
+        assert_eq!(engine.pot(), 0);
+        assert_eq!(
+            engine.legal_actions(lowest),
+            vec![
+                LegalAction::Bet { min: 1, max: 1 },
+                LegalAction::Bet { min: 4, max: 200 },
+                LegalAction::AllIn(200),
+            ]
+        );
+        engine.apply_action(lowest, Action::Bet(1)).unwrap();
+        let next = (lowest + 1) % 4;
+        assert_eq!(engine.to_act(), Some(next));
+        assert_eq!(
+            engine.legal_actions(next),
+            vec![
+                LegalAction::Fold,
+                LegalAction::Call(1),
+                LegalAction::Raise { min: 4, max: 200 },
+                LegalAction::AllIn(200),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_player_action_round_trip() {
+        for action in [
+            Action::Fold,
+            Action::Raise(40),
+            Action::AllIn,
+            Action::Discard(vec![Card::new(12, 1), Card::new(0, 3)]),
+            Action::Pass(vec![Card::new(3, 2)]),
+            Action::Reveal(vec![Card::new(5, 0)]),
+            Action::Declare(Side::Both),
+        ] {
+            let msg = crate::PlayerAction::from(&action);
+            assert_eq!(Action::try_from(&msg), Ok(action));
+        }
+        let bad = crate::PlayerAction {
+            kind: 99,
+            ..Default::default()
+        };
+        assert!(matches!(
+            Action::try_from(&bad),
+            Err(EngineError::MalformedAction(_))
+        ));
+    }
+
+    #[test]
+    fn test_run_it_twice() {
+        let mut config = EngineConfig::new(2);
+        config.runouts = 2;
+        config.stacks = vec![101, 100];
+        let mut engine = GameEngine::with_config(presets::holdem(), config).unwrap();
+        // Heads-up, the button (seat 0) posts the small blind and acts first.
+        engine.apply_action(0, Action::AllIn).unwrap();
+        engine.apply_action(1, Action::Call).unwrap();
+        assert_eq!(engine.status(), HandStatus::Complete);
+
+        let runouts = engine.runouts();
+        assert_eq!(runouts.len(), 2);
+        assert!(runouts.iter().all(|r| r.boards[0].len() == 5));
+        assert!(runouts[0].boards[0]
+            .iter()
+            .all(|c| !runouts[1].boards[0].contains(c)));
+        assert_eq!(
+            runouts
+                .iter()
+                .map(|r| r.result.pots[0].amount)
+                .sum::<Chips>(),
+            200
+        );
+        let stacks: Chips = engine.seats().iter().map(|s| s.stack).sum();
+        assert_eq!(stacks, 201);
+    }
+
+    #[test]
+    fn test_runouts_fit_the_shoe() {
+        // Eight Omaha hands leave 20 cards: two runs of a burn and a card
+        // for each of five, not three.
+        let mut config = EngineConfig::new(8);
+        config.runouts = 3;
+        let mut engine = GameEngine::with_config(presets::omaha(), config.clone()).unwrap();
+        while let Some(seat) = engine.to_act() {
+            engine.apply_action(seat, Action::AllIn).unwrap();
+        }
+        assert_eq!(engine.status(), HandStatus::Complete);
+        assert_eq!(engine.runouts().len(), 2);
+
+        // On two boards a run from the flop takes 16 of them, so the flop
+        // and turn are dealt once and only the river is run twice.
+        config.stacks = vec![10; 8];
+        config.bomb_pot = 10;
+        config.bomb_pot_double_board = true;
+        let engine = GameEngine::with_config(presets::omaha(), config).unwrap();
+        assert_eq!(engine.status(), HandStatus::Complete);
+        let runouts = engine.runouts();
+        assert_eq!(runouts.len(), 2);
+        for board in 0..2 {
+            assert_eq!(runouts[0].boards[board][..4], runouts[1].boards[board][..4]);
+            assert_ne!(runouts[0].boards[board][4], runouts[1].boards[board][4]);
+        }
+    }
+
+    #[test]
+    fn test_straddles() {
+        let mut config = EngineConfig::new(5);
+        config.straddles = 2;
+        let mut engine = GameEngine::with_config(presets::holdem(), config).unwrap();
+        // Blinds on seats 1 and 2, straddles of 4 and 8 on seats 3 and 4.
+        assert_eq!(engine.pot(), 1 + 2 + 4 + 8);
+        assert_eq!(engine.to_act(), Some(0));
+        assert!(engine
+            .legal_actions(0)
+            .contains(&LegalAction::Raise { min: 16, max: 200 }));
+        for seat in [0, 1, 2, 3] {
+            engine.apply_action(seat, Action::Call).unwrap();
+        }
+        // The double straddler has the option.
+        assert_eq!(engine.to_act(), Some(4));
+        engine.apply_action(4, Action::Check).unwrap();
+        assert_eq!(engine.pot(), 40);
+
+        let mut config = EngineConfig::new(4);
+        config.button_straddle = true;
+        let mut engine = GameEngine::with_config(presets::holdem(), config).unwrap();
+        assert_eq!(engine.to_act(), Some(3));
+        engine.apply_action(3, Action::Call).unwrap();
+        // The button is passed over until the blinds have acted.
+        assert_eq!(engine.to_act(), Some(1));
+    }
+
+    #[test]
+    fn test_rake() {
+        let mut game = presets::holdem();
+        game.rake = Some(crate::RakeConfig {
+            basis_points: 500,
+            cap: 3,
+            no_flop_no_drop: true,
+        });
+        // Folding preflop is not raked.
+        let mut engine = GameEngine::new(game.clone(), 3).unwrap();
+        engine.apply_action(0, Action::Fold).unwrap();
+        engine.apply_action(1, Action::Fold).unwrap();
+        assert_eq!(engine.rake(), 0);
+        assert_eq!(engine.seats()[2].stack, 201);
+
+        let mut engine = GameEngine::new(game, 3).unwrap();
+        engine.apply_action(0, Action::Raise(40)).unwrap();
+        engine.apply_action(1, Action::Call).unwrap();
+        engine.apply_action(2, Action::Call).unwrap();
+        for _ in 0..3 {
+            for seat in [1, 2, 0] {
+                engine.apply_action(seat, Action::Check).unwrap();
+            }
+        }
+        // 5% of 120 is 6, capped at 3.
+        assert_eq!(engine.rake(), 3);
+        assert!(engine.events().contains(&Event::RakeTaken { amount: 3 }));
+        let stacks: Chips = engine.seats().iter().map(|s| s.stack).sum();
+        assert_eq!(stacks, 600 - 3);
+    }
+
+    #[test]
+    fn test_double_board_bomb_pot() {
+        let mut config = EngineConfig::new(3);
+        config.bomb_pot = 10;
+        config.bomb_pot_double_board = true;
+        config.ante = 5;
+        let mut engine = GameEngine::with_config(presets::holdem(), config).unwrap();
+        // No blinds or antes, and action starts on the flop.
+        assert_eq!(engine.pot(), 30);
+        assert!(engine.boards().iter().all(|b| b.len() == 3));
+        assert_eq!(engine.legal_actions(1)[1], LegalAction::Check);
+        for _ in 0..3 {
+            for seat in [1, 2, 0] {
+                engine.apply_action(seat, Action::Check).unwrap();
+            }
+        }
+        let result = engine.showdown().unwrap();
+        assert_eq!(result.pots[0].splits.len(), 2);
+        assert_eq!(
+            result.pots[0]
+                .splits
+                .iter()
+                .map(|s| s.amount)
+                .sum::<Chips>(),
+            30
+        );
+    }
+
+    #[test]
+    fn test_exposed_cards_and_misdeal() {
+        let mut game = presets::holdem();
+        let mut engine = GameEngine::new(game.clone(), 3).unwrap();
+        let exposed = engine.seats()[1].hole[0].card;
+        engine.expose_card(1, exposed).unwrap();
+        assert_eq!(engine.burned(), &[exposed]);
+        assert_ne!(engine.seats()[1].hole[0].card, exposed);
+        assert_eq!(
+            engine.expose_card(1, exposed),
+            Err(EngineError::NotExposable(exposed))
+        );
+
+        game.dealing_errors = Some(crate::DealingErrors {
+            max_exposed: 2,
+            ..Default::default()
+        });
+        let mut engine = GameEngine::new(game, 3).unwrap();
+        for seat in [0, 2] {
+            let card = engine.seats()[seat].hole[1].card;
+            engine.expose_card(seat, card).unwrap();
+        }
+        assert_eq!(engine.status(), HandStatus::Misdeal);
+        assert!(engine.seats().iter().all(|s| s.stack == 200));
+        assert_eq!(engine.pot(), 0);
+
+        let mut redeal = engine.redeal().unwrap();
+        assert_eq!(redeal.status(), HandStatus::InProgress);
+        redeal.apply_action(0, Action::Call).unwrap();
+        assert_eq!(redeal.declare_misdeal(), Err(EngineError::MisdealTooLate));
+        let card = redeal.seats()[1].hole[0].card;
+        assert_eq!(
+            redeal.expose_card(1, card),
+            Err(EngineError::NotExposable(card))
+        );
+    }
+
+    #[test]
+    fn test_seeded_shuffle_is_recorded() {
+        let deal = |seed| {
+            let config = EngineConfig {
+                shuffler: Shuffler::seeded(seed),
+                ..EngineConfig::new(3)
+            };
+            GameEngine::with_config(presets::holdem(), config).unwrap()
+        };
+        let engine = deal(99);
+        assert_eq!(engine.events()[0], Event::Shuffled { seed: 99 });
+        let hole = |engine: &GameEngine| engine.seats()[0].hole.clone();
+        assert_eq!(hole(&engine), hole(&deal(99)));
+        assert_ne!(hole(&engine), hole(&deal(100)));
+        assert_eq!(
+            engine.redeal().unwrap().events()[0],
+            Event::Shuffled { seed: 100 }
+        );
+
+        // A hand dealt from the last hand's shoe isn't shuffled again.
+        let mut game = presets::holdem();
+        game.shoe = Some(crate::ShoeConfig {
+            decks: 2,
+            penetration: 50,
+        });
+        let config = EngineConfig {
+            shoe: GameEngine::new(game.clone(), 2)
+                .unwrap()
+                .remaining_shoe()
+                .cloned(),
+            ..EngineConfig::new(2)
+        };
+        let engine = GameEngine::with_config(game, config).unwrap();
+        assert!(!engine.events().contains(&Event::Shuffled { seed: 0 }));
+    }
+
+    #[test]
+    fn test_audit_log() {
+        use crate::audit::{AuditEntry, AuditLog, Destination};
+
+        let mut engine = GameEngine::new(presets::holdem(), 3).unwrap();
+        while let Some(seat) = engine.to_act() {
+            if engine.apply_action(seat, Action::Check).is_err() {
+                engine.apply_action(seat, Action::Call).unwrap();
+            }
+        }
+        let records = engine.audit_log().records();
+        assert!(matches!(
+            records[0].entry,
+            AuditEntry::Shuffle { round: 0, .. }
+        ));
+        let drawn = |to: fn(Destination) -> bool| -> Vec<Card> {
+            records
+                .iter()
+                .filter_map(|r| match r.entry {
+                    AuditEntry::Draw { card, to: d } if to(d) => Some(card),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(drawn(|d| d == Destination::Burn), engine.burned());
+        assert_eq!(
+            drawn(|d| matches!(d, Destination::Board(0))),
+            engine.boards()[0]
+        );
+        assert_eq!(drawn(|d| matches!(d, Destination::Seat(_))).len(), 6);
+
+        let signature = engine.audit_log().sign(b"key");
+        assert!(AuditLog::verify(records, b"key", &signature));
+    }
+
+    #[test]
+    fn test_draw_reshuffles_discards() {
+        // Twelve cards: two players take ten, leaving two in the deck.
+        let game = crate::builder::GameBuilder::new("tiny-draw")
+            .custom_deck(crate::Deck {
+                ranks: ["2", "3", "4", "5", "6", "7"].map(String::from).to_vec(),
+                suits: vec!["s".to_string(), "h".to_string()],
+                ..Default::default()
+            })
+            .deal_hole(5)
+            .phase(phase::PhaseType::Exchange(phase::Exchange {
+                max_exchange: 3,
+                draw_source: DrawSource::ReshuffleDiscards.into(),
+                public_discards: true,
+                ..Default::default()
+            }))
+            .high()
+            .build()
+            .unwrap();
+        // Both hands deal the same cards.
+        let config = EngineConfig {
+            shuffler: Shuffler::seeded(0),
+            ..EngineConfig::new(2)
+        };
+        let mut engine = GameEngine::with_config(game.clone(), config.clone()).unwrap();
+        let first: Vec<Card> = engine.seats()[1].hole[..2].iter().map(|c| c.card).collect();
+        engine
+            .apply_action(1, Action::Discard(first.clone()))
+            .unwrap();
+        assert_eq!(engine.discards(), &first[..]);
+        assert!(engine.events().contains(&Event::DiscardsShown {
+            seat: 1,
+            cards: first.clone(),
+        }));
+
+        // Two cards left in the deck and two discards: at most two to draw.
+        assert_eq!(
+            engine.legal_actions(0),
+            vec![LegalAction::Discard { max: 2 }]
+        );
+        let second: Vec<Card> = engine.seats()[0].hole[..2].iter().map(|c| c.card).collect();
+        assert_eq!(
+            engine.apply_action(
+                0,
+                Action::Discard(engine.seats()[0].hole[..3].iter().map(|c| c.card).collect())
+            ),
+            Err(EngineError::DeckExhausted)
+        );
+        engine
+            .apply_action(0, Action::Discard(second.clone()))
+            .unwrap();
+        assert!(engine
+            .events()
+            .contains(&Event::DiscardsReshuffled { count: 2 }));
+        let hand: Vec<Card> = engine.seats()[0].hole.iter().map(|c| c.card).collect();
+        assert!(first.iter().all(|c| hand.contains(c)));
+        assert_eq!(engine.discards(), &second[..]);
+        assert_eq!(engine.status(), HandStatus::Complete);
+
+        // Drawing only from the deck, the second player can't be served.
+        let mut game = game;
+        if let Some(phase::PhaseType::Exchange(e)) = &mut game.phases[1].phase_type {
+            e.draw_source = DrawSource::Deck.into();
+        }
+        let mut engine = GameEngine::with_config(game, config).unwrap();
+        assert_eq!(
+            engine.legal_actions(1),
+            vec![LegalAction::Discard { max: 2 }]
+        );
+        engine.apply_action(1, Action::Discard(first)).unwrap();
+        assert_eq!(
+            engine.legal_actions(0),
+            vec![LegalAction::Discard { max: 0 }]
+        );
+        assert_eq!(
+            engine.apply_action(0, Action::Discard(second)),
+            Err(EngineError::DeckExhausted)
+        );
+    }
+
+    #[test]
+    fn test_pass_cards() {
+        let hole = |engine: &GameEngine, seat: usize| -> Vec<Card> {
+            engine.seats()[seat].hole.iter().map(|c| c.card).collect()
+        };
+        let game = crate::builder::GameBuilder::new("pass")
+            .deal_hole(3)
+            .pass(1, Direction::Across)
+            .high()
+            .build()
+            .unwrap();
+        let mut engine = GameEngine::new(game.clone(), 4).unwrap();
+        assert_eq!(
+            engine.legal_actions(1),
+            vec![LegalAction::Pass { count: 1 }]
+        );
+        assert!(matches!(
+            engine.apply_action(1, Action::Pass(vec![])),
+            Err(EngineError::InvalidPass(_))
+        ));
+        let passed: Vec<Card> = (0..4).map(|s| hole(&engine, s)[0]).collect();
+        for seat in [1, 2, 3] {
+            engine
+                .apply_action(seat, Action::Pass(vec![passed[seat]]))
+                .unwrap();
+        }
+        // Nothing changes hands until everyone has chosen.
+        assert_eq!(hole(&engine, 3)[0], passed[3]);
+        engine
+            .apply_action(0, Action::Pass(vec![passed[0]]))
+            .unwrap();
+        for seat in 0..4 {
+            assert_eq!(hole(&engine, seat).len(), 3);
+            assert_eq!(hole(&engine, seat)[2], passed[(seat + 2) % 4]);
+        }
+        assert!(engine.events().contains(&Event::CardsPassed {
+            from: 1,
+            to: 3,
+            count: 1
+        }));
+        assert_eq!(engine.status(), HandStatus::Complete);
+
+        // Passing left one at a time, a card can go round more than once.
+        let mut game = game;
+        game.phases[1].phase_type = Some(phase::PhaseType::PassCards(phase::PassCards {
+            cards: 1,
+            direction: Direction::Left.into(),
+            sequential: true,
+        }));
+        let mut engine = GameEngine::new(game, 3).unwrap();
+        let card = hole(&engine, 1)[0];
+        engine.apply_action(1, Action::Pass(vec![card])).unwrap();
+        assert_eq!(hole(&engine, 2).last(), Some(&card));
+        engine.apply_action(2, Action::Pass(vec![card])).unwrap();
+        assert_eq!(hole(&engine, 0).last(), Some(&card));
+        assert!(!hole(&engine, 2).contains(&card));
+    }
+
+    #[test]
+    fn test_redacted() {
+        let dealt = Event::HoleCardsDealt {
+            seat: 1,
+            cards: vec![
+                DealtCard {
+                    card: Card::new(12, 0),
+                    face_up: false,
+                },
+                DealtCard {
+                    card: Card::new(0, 1),
+                    face_up: true,
+                },
+            ],
+        };
+        assert_eq!(dealt.redacted(Audience::Seat(1)), dealt);
+        let public = dealt.redacted(Audience::Spectator);
+        assert_eq!(public.redacted(Audience::Seat(0)), public);
+        let Event::HoleCardsDealt { cards, .. } = public else {
+            panic!("{:?}", public);
+        };
+        assert_eq!(
+            cards.iter().map(|c| c.card).collect::<Vec<_>>(),
+            [Card::new(0, 1)]
+        );
+
+        let discard = Event::Acted {
+            seat: 1,
+            action: Action::Discard(vec![Card::new(3, 2)]),
+        };
+        assert_eq!(discard.redacted(Audience::Seat(1)), discard);
+        assert_eq!(
+            discard.redacted(Audience::Seat(2)),
+            Event::Acted {
+                seat: 1,
+                action: Action::Discard(Vec::new()),
+            }
+        );
+        let reveal = Event::Acted {
+            seat: 1,
+            action: Action::Reveal(vec![Card::new(3, 2)]),
+        };
+        assert_eq!(reveal.redacted(Audience::Spectator), reveal);
+        let exposed = Event::CardExposed {
+            seat: 0,
+            card: Card::new(5, 3),
+            replacement: Some(Card::new(6, 3)),
+        };
+        assert_eq!(
+            exposed.redacted(Audience::Spectator),
+            Event::CardExposed {
+                seat: 0,
+                card: Card::new(5, 3),
+                replacement: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_reveal() {
+        let face_up = |engine: &GameEngine, seat: usize| -> Vec<Card> {
+            engine.seats()[seat]
+                .hole
+                .iter()
+                .filter(|c| c.face_up)
+                .map(|c| c.card)
+                .collect()
+        };
+        let mut game = crate::builder::GameBuilder::new("reveal")
+            .deal_hole(3)
+            .community(2)
+            .reveal(1, false)
+            .reveal(1, true)
+            .phase(phase::PhaseType::Reveal(phase::Reveal {
+                cards: 1,
+                community: true,
+                ..Default::default()
+            }))
+            .high()
+            .build()
+            .unwrap();
+        let Some(phase::PhaseType::CommunityDeal(deal)) = &mut game.phases[1].phase_type else {
+            unreachable!()
+        };
+        deal.face_down = true;
+
+        let mut engine = GameEngine::new(game, 3).unwrap();
+        assert!(engine.boards()[0].is_empty());
+        assert!(engine
+            .events()
+            .contains(&Event::HiddenBoardDealt { board: 0, count: 2 }));
+        // The first reveal turns up everyone's first card without asking.
+        for seat in 0..3 {
+            let first = engine.seats()[seat].hole[0].card;
+            assert_eq!(face_up(&engine, seat), vec![first]);
+        }
+        assert_eq!(
+            engine.legal_actions(1),
+            vec![LegalAction::Reveal { count: 1 }]
+        );
+        let shown = engine.seats()[1].hole[0].card;
+        assert!(matches!(
+            engine.apply_action(1, Action::Reveal(vec![shown])),
+            Err(EngineError::InvalidReveal(_))
+        ));
+        let picks: Vec<Card> = (0..3).map(|s| engine.seats()[s].hole[2].card).collect();
+        for seat in [1, 2] {
+            engine
+                .apply_action(seat, Action::Reveal(vec![picks[seat]]))
+                .unwrap();
+        }
+        // Nothing is turned up until everyone has chosen.
+        assert_eq!(face_up(&engine, 1).len(), 1);
+        engine
+            .apply_action(0, Action::Reveal(vec![picks[0]]))
+            .unwrap();
+        assert!(engine.events().contains(&Event::CardsRevealed {
+            seat: 1,
+            cards: vec![picks[1]]
+        }));
+        // One board card is revealed by the last phase and the other at
+        // showdown.
+        assert_eq!(engine.status(), HandStatus::Complete);
+        assert_eq!(engine.boards()[0].len(), 2);
+        let reveals = engine
+            .events()
+            .iter()
+            .filter(|e| matches!(e, Event::BoardRevealed { .. }))
+            .count();
+        assert_eq!(reveals, 2);
+    }
+
+    #[test]
+    fn test_declare() {
+        let declared = |engine: &GameEngine| {
+            engine
+                .events()
+                .iter()
+                .filter(|e| matches!(e, Event::Declared { .. }))
+                .count()
+        };
+        let mut game = crate::builder::GameBuilder::new("declare")
+            .deal_hole(5)
+            .declare(false)
+            .high()
+            .ace_five_low("")
+            .build()
+            .unwrap();
+        let mut engine = GameEngine::new(game.clone(), 3).unwrap();
+        assert_eq!(engine.legal_actions(1), vec![LegalAction::Declare]);
+        assert_eq!(
+            engine.apply_action(1, Action::Declare(Side::Unknown)),
+            Err(EngineError::IllegalAction(Action::Declare(Side::Unknown)))
+        );
+        engine.apply_action(1, Action::Declare(Side::High)).unwrap();
+        engine.apply_action(2, Action::Declare(Side::Low)).unwrap();
+        // Declarations stay secret until everyone has made one.
+        assert_eq!(declared(&engine), 0);
+        engine.apply_action(0, Action::Declare(Side::Both)).unwrap();
+        assert_eq!(declared(&engine), 3);
+        assert_eq!(
+            engine.declarations(),
+            &[Some(Side::Both), Some(Side::High), Some(Side::Low)]
+        );
+        assert_eq!(engine.status(), HandStatus::Complete);
+
+        game.phases[1].phase_type =
+            Some(phase::PhaseType::Declare(phase::Declare { in_turn: true }));
+        let mut engine = GameEngine::new(game, 3).unwrap();
+        engine.apply_action(1, Action::Declare(Side::High)).unwrap();
+        assert_eq!(
+            engine.events().last(),
+            Some(&Event::Declared {
+                seat: 1,
+                side: Side::High
+            })
+        );
+    }
+
+    #[test]
+    fn test_conditional_phases() {
+        let condition = |measure: Measure, above, end_hand| phase::Condition {
+            measure: measure.into(),
+            above,
+            end_hand,
+            ..Default::default()
+        };
+        let game = crate::builder::GameBuilder::new("conditional")
+            .deal_hole(2)
+            .betting("Pre-flop")
+            .community(3)
+            .only_if(condition(Measure::Players, 2, false))
+            .betting("Flop")
+            .community(2)
+            .only_if(condition(Measure::Pot, 100, true))
+            .betting("Turn")
+            .build()
+            .unwrap();
+        let mut engine = GameEngine::new(game, 3).unwrap();
+        engine.apply_action(0, Action::Fold).unwrap();
+        engine.apply_action(1, Action::Call).unwrap();
+        engine.apply_action(2, Action::Check).unwrap();
+        // With two players left there's no flop, but there is still betting.
+        assert!(engine.events().contains(&Event::PhaseSkipped { index: 2 }));
+        assert_eq!(engine.status(), HandStatus::InProgress);
+        engine.apply_action(1, Action::Check).unwrap();
+        engine.apply_action(2, Action::Check).unwrap();
+        // The pot is too small for the turn, so the hand ends there.
+        assert!(engine.events().contains(&Event::PhaseSkipped { index: 4 }));
+        assert_eq!(engine.status(), HandStatus::Complete);
+        assert!(engine.boards()[0].is_empty());
+        assert!(engine.showdown().is_some());
+    }
+
+    #[test]
+    fn test_buy() {
+        let mut game = crate::builder::GameBuilder::new("buy")
+            .deal_hole(2)
+            .betting("Pre-flop")
+            .buy(2, false)
+            .build()
+            .unwrap();
+        let mut engine = GameEngine::new(game.clone(), 3).unwrap();
+        engine.apply_action(0, Action::Fold).unwrap();
+        engine.apply_action(1, Action::Call).unwrap();
+        engine.apply_action(2, Action::Check).unwrap();
+        assert_eq!(
+            engine.legal_actions(1),
+            vec![
+                LegalAction::Check,
+                LegalAction::Buy {
+                    price: 2,
+                    replace: false
+                }
+            ]
+        );
+        engine.apply_action(1, Action::Buy(Vec::new())).unwrap();
+        assert_eq!(engine.seats()[1].hole.len(), 3);
+        assert_eq!(engine.seats()[1].stack, 196);
+        assert_eq!(engine.pot(), 6);
+        engine.apply_action(2, Action::Check).unwrap();
+        assert!(engine.events().contains(&Event::BuyDeclined { seat: 2 }));
+        assert_eq!(engine.status(), HandStatus::Complete);
+
+        game.phases[2].phase_type = Some(phase::PhaseType::Buy(phase::Buy {
+            price: 2,
+            replace: true,
+            face_up: true,
+        }));
+        let mut engine = GameEngine::new(game, 3).unwrap();
+        engine.apply_action(0, Action::Fold).unwrap();
+        engine.apply_action(1, Action::Call).unwrap();
+        engine.apply_action(2, Action::Check).unwrap();
+        assert!(matches!(
+            engine.apply_action(1, Action::Buy(Vec::new())),
+            Err(EngineError::InvalidBuy(_))
+        ));
+        let old = engine.seats()[1].hole[0].card;
+        engine.apply_action(1, Action::Buy(vec![old])).unwrap();
+        let new = engine.seats()[1].hole[0];
+        assert!(new.face_up);
+        assert_eq!(engine.seats()[1].hole.len(), 2);
+        assert_eq!(engine.discards(), &[old]);
+        assert!(engine.events().contains(&Event::CardBought {
+            seat: 1,
+            amount: 2,
+            replaced: true,
+            shown: Some(new.card),
+        }));
+    }
+
+    #[test]
+    fn test_repeat() {
+        let street = crate::builder::GameBuilder::new("street")
+            .deal_up(1)
+            .betting("Street")
+            .build()
+            .unwrap();
+        let mut game = crate::builder::GameBuilder::new("repeat")
+            .deal_hole(2)
+            .betting("Pre-flop")
+            .repeat(3, street.phases)
+            .build()
+            .unwrap();
+        if let Some(phase::PhaseType::Repeat(repeat)) = &mut game.phases[2].phase_type {
+            repeat.until = Some(phase::Condition {
+                measure: Measure::Pot.into(),
+                above: 10,
+                ..Default::default()
+            });
+        }
+        let mut engine = GameEngine::new(game, 3).unwrap();
+        assert_eq!(engine.phases().len(), 8);
+        engine.apply_action(0, Action::Fold).unwrap();
+        engine.apply_action(1, Action::Call).unwrap();
+        engine.apply_action(2, Action::Check).unwrap();
+        engine.apply_action(1, Action::Bet(4)).unwrap();
+        engine.apply_action(2, Action::Call).unwrap();
+        // The pot passed 10 on the first street, so there's no second.
+        assert!(engine.events().contains(&Event::PhaseSkipped { index: 4 }));
+        assert_eq!(engine.status(), HandStatus::Complete);
+        assert_eq!(engine.seats()[1].hole.len(), 3);
+    }
+
+    #[test]
+    fn test_dead_hand() {
+        let mut engine = GameEngine::new(presets::holdem(), 3).unwrap();
+        engine.declare_dead_hand(2).unwrap();
+        assert_eq!(engine.declare_dead_hand(2), Err(EngineError::NotInHand(2)));
+        assert_eq!(engine.to_act(), Some(0));
+        engine.apply_action(0, Action::Fold).unwrap();
+        // The dead big blind stays in the pot.
+        assert_eq!(engine.status(), HandStatus::Complete);
+        assert_eq!(engine.seats()[1].stack, 202);
+        assert_eq!(engine.seats()[2].stack, 198);
+    }
+
+    #[test]
+    fn test_burn_policy() {
+        let burned = |burns: Option<crate::BurnPolicy>| {
+            let mut game = presets::holdem();
+            game.burns = burns;
+            let mut engine = GameEngine::new(game, 3).unwrap();
+            while let Some(seat) = engine.to_act() {
+                let action = if engine.legal_actions(seat).contains(&LegalAction::Check) {
+                    Action::Check
+                } else {
+                    Action::Call
+                };
+                engine.apply_action(seat, action).unwrap();
+            }
+            engine.burned().len()
+        };
+        assert_eq!(burned(None), 3);
+        let policy = |mode: crate::burn_policy::Mode, cards| {
+            Some(crate::BurnPolicy {
+                mode: mode.into(),
+                cards,
+            })
+        };
+        assert_eq!(burned(policy(crate::burn_policy::Mode::Never, 0)), 0);
+        assert_eq!(burned(policy(crate::burn_policy::Mode::EveryStreet, 2)), 6);
+    }
+
+    #[test]
+    fn test_multi_deck_shoe() {
+        let mut game = presets::holdem();
+        game.shoe = Some(crate::ShoeConfig {
+            decks: 2,
+            penetration: 50,
+        });
+        let mut config = EngineConfig::new(3);
+        let mut hands = 0;
+        loop {
+            let mut engine = GameEngine::with_config(game.clone(), config.clone()).unwrap();
+            engine.apply_action(0, Action::Fold).unwrap();
+            engine.apply_action(1, Action::Fold).unwrap();
+            hands += 1;
+            match engine.remaining_shoe() {
+                // Each hand deals six hole cards from the same 104-card shoe.
+                Some(shoe) => assert_eq!(shoe.remaining(), 104 - 6 * hands),
+                None => break,
+            }
+            config.shoe = engine.remaining_shoe().cloned();
+        }
+        // The cut card comes out once half of the shoe has been dealt.
+        assert_eq!(hands, 9);
+        assert_eq!(
+            GameEngine::new(presets::holdem(), 3)
+                .unwrap()
+                .remaining_shoe(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_too_many_players() {
+        assert_eq!(
+            GameEngine::new(presets::seven_card_stud(), 8).map(|_| ()),
+            Err(EngineError::TooManyPlayers { players: 8, max: 7 })
+        );
+        // A seat without chips isn't dealt in.
+        let mut config = EngineConfig::new(8);
+        config.stacks[7] = 0;
+        assert!(GameEngine::with_config(presets::seven_card_stud(), config).is_ok());
+
+        // A kept shoe is reshuffled once it can't deal the board and three
+        // hands, 14 cards, even before the cut card comes out.
+        let mut game = presets::holdem();
+        game.shoe = Some(crate::ShoeConfig {
+            decks: 1,
+            penetration: 100,
+        });
+        let mut config = EngineConfig::new(3);
+        for hands in 1..=8 {
+            let mut engine = GameEngine::with_config(game.clone(), config.clone()).unwrap();
+            let left = if hands == 8 { 46 } else { 52 - 6 * hands };
+            assert_eq!(engine.shoe().remaining(), left);
+            engine.apply_action(0, Action::Fold).unwrap();
+            engine.apply_action(1, Action::Fold).unwrap();
+            config.shoe = engine.remaining_shoe().cloned();
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Where randomness comes from. Everything in the library that needs
+//! random numbers, shuffling decks and assigning seats, takes an
+//! [`EntropySource`], so operators with their own certified generators can
+//! plug them in.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+
+use crate::cards::SplitMix64;
+
+/// A source of uniformly random 64-bit numbers.
+pub trait EntropySource {
+    fn next_u64(&mut self) -> u64;
+
+    /// A uniform number below `bound`, which must be nonzero. Rejects the
+    /// top of the range that doesn't divide evenly, so there is no modulo
+    /// bias.
+    fn below(&mut self, bound: u64) -> u64 {
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let n = self.next_u64();
+            if n < zone {
+                return n % bound;
+            }
+        }
+    }
+}
+
+impl EntropySource for SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.next()
+    }
+}
+
+/// Numbers from the operating system's cryptographically secure generator,
+/// read from `/dev/urandom`.
+pub struct OsEntropy {
+    source: File,
+}
+
+impl OsEntropy {
+    pub fn new() -> io::Result<Self> {
+        Ok(OsEntropy {
+            source: File::open("/dev/urandom")?,
+        })
+    }
+}
+
+impl EntropySource for OsEntropy {
+    /// Panics if the system's generator can't be read: a shuffle must never
+    /// fall back to anything weaker.
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.source
+            .read_exact(&mut bytes)
+            .expect("system random number generator unavailable");
+        u64::from_le_bytes(bytes)
+    }
+}
+
+/// An injected source that can be cloned into configs and shared between
+/// tables. Clones draw from the same source, and compare equal only to
+/// each other.
+#[derive(Clone)]
+pub struct SharedSource(Arc<Mutex<dyn EntropySource + Send>>);
+
+impl SharedSource {
+    pub fn new(source: impl EntropySource + Send + 'static) -> Self {
+        SharedSource(Arc::new(Mutex::new(source)))
+    }
+}
+
+impl EntropySource for SharedSource {
+    fn next_u64(&mut self) -> u64 {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).next_u64()
+    }
+}
+
+impl PartialEq for SharedSource {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedSource {}
+
+impl fmt::Debug for SharedSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedSource")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_source() {
+        let mut a = SharedSource::new(SplitMix64(3));
+        let mut b = a.clone();
+        let mut direct = SplitMix64(3);
+        assert_eq!(a.next_u64(), direct.next_u64());
+        // The clone carries on from where the original left off.
+        assert_eq!(b.next_u64(), direct.next_u64());
+        assert_eq!(a, b);
+        assert_ne!(a, SharedSource::new(SplitMix64(3)));
+        assert!((0..100).all(|_| a.below(6) < 6));
+    }
+}
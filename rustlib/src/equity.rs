@@ -0,0 +1,765 @@
+//! Hand equities: how often each hand wins if the rest of the cards come
+//! at random, for bots and analysis tools.
+
+use std::fmt;
+
+use crate::batch;
+use crate::cards::{Card, Shoe, SplitMix64};
+use crate::engine::{dealt_face_up, Chips};
+use crate::handeval::{Evaluator, HandRank, IncrementalEvaluator};
+use crate::parallel;
+use crate::pot::Pot;
+use crate::range::Range;
+use crate::showdown::{resolve_showdown, Scorer};
+use crate::{phase, repeat, GameStructure};
+
+/// The pot every trial is played for. Divisible by every number of ways up
+/// to 16 that a pot can be split, so shares come out exact.
+const POT: Chips = 720_720;
+
+/// One hand's results over every trial, as percentages.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Equity {
+    /// Trials the hand won the whole pot.
+    pub win: f64,
+    /// Trials the hand won part of the pot.
+    pub tie: f64,
+    /// Trials the hand won nothing.
+    pub lose: f64,
+
+    /// Average share of the pot won, which counts a split by the share
+    /// taken, so the hands' equities add up to 100.
+    pub equity: f64,
+
+    /// Half the width of the 95% confidence interval for `equity`, in
+    /// percentage points. 0 for exact results.
+    pub margin: f64,
+}
+
+impl Equity {
+    /// The 95% confidence interval for `equity`.
+    pub fn interval(&self) -> (f64, f64) {
+        (
+            (self.equity - self.margin).max(0.0),
+            (self.equity + self.margin).min(100.0),
+        )
+    }
+}
+
+/// Why equities can't be worked out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EquityError {
+    /// A known card isn't in the deck, or is given twice.
+    UnavailableCard(Card),
+    /// A hand has more cards than the game deals a player.
+    TooManyHoleCards { seat: usize, dealt: usize },
+    /// A board has more cards than the game deals it.
+    TooManyBoardCards { board: usize, dealt: usize },
+    /// There aren't enough cards left to deal the rest of the hands.
+    NotEnoughCards,
+    /// Every combination in a player's range clashes with cards already
+    /// taken.
+    EmptyRange { seat: usize },
+}
+
+impl fmt::Display for EquityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EquityError::UnavailableCard(card) => {
+                write!(f, "{} isn't in the deck or is given twice", card)
+            }
+            EquityError::TooManyHoleCards { seat, dealt } => write!(
+                f,
+                "seat {} has more cards than the {} the game deals",
+                seat, dealt
+            ),
+            EquityError::TooManyBoardCards { board, dealt } => write!(
+                f,
+                "board {} has more cards than the {} the game deals",
+                board, dealt
+            ),
+            EquityError::NotEnoughCards => {
+                write!(f, "the deck doesn't have enough cards to finish the hand")
+            }
+            EquityError::EmptyRange { seat } => {
+                write!(f, "seat {} has no hands left in its range", seat)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EquityError {}
+
+/// How many cards `game` deals each player and each board, counting every
+/// pass of a repeat. Draws, passes and buys don't change the counts.
+pub fn cards_dealt(game: &GameStructure) -> (usize, Vec<usize>) {
+    let mut hole = 0;
+    let mut boards = vec![0; game.community_board_count.max(0) as usize];
+    for p in repeat::expand(&game.phases).phases {
+        match &p.phase_type {
+            Some(phase::PhaseType::PlayerDeal(deal)) => hole += dealt_face_up(deal).len(),
+            Some(phase::PhaseType::CommunityDeal(deal)) => {
+                let board = deal.board_idx.max(0) as usize;
+                if boards.len() <= board {
+                    boards.resize(board + 1, 0);
+                }
+                boards[board] += deal.cards.max(0) as usize;
+            }
+            _ => {}
+        }
+    }
+    (hole, boards)
+}
+
+/// Works out how each hand fares over `trials` finishes of a hand of
+/// `game` in which each player holds `hands[seat]` and each board shows
+/// `boards[board]` so far. Hands and boards with fewer cards than the game
+/// deals are filled in, so an empty hand stands for any holding.
+///
+/// If there are no more possible finishes than `trials`, every one is
+/// played out once and the results are exact (see [`enumerate`]);
+/// otherwise they're sampled at random (see [`sample`]).
+pub fn simulate(
+    game: &GameStructure,
+    hands: &[Vec<Card>],
+    boards: &[Vec<Card>],
+    trials: usize,
+    seed: u64,
+) -> Result<Vec<Equity>, EquityError> {
+    let deal = Deal::new(game, hands, boards)?;
+    if deal
+        .runouts(hands, boards)
+        .is_some_and(|n| n <= trials as u128)
+    {
+        return Ok(deal.enumerate(game, hands, boards));
+    }
+    Ok(deal.sample(game, hands, boards, trials, seed))
+}
+
+/// Like [`simulate`], sharing the random finishes out over `threads`
+/// threads, or one per core if 0. Each trial is seeded on its own, so the
+/// results are the same as [`simulate`]'s however many threads there are.
+pub fn simulate_parallel(
+    game: &GameStructure,
+    hands: &[Vec<Card>],
+    boards: &[Vec<Card>],
+    trials: usize,
+    seed: u64,
+    threads: usize,
+) -> Result<Vec<Equity>, EquityError> {
+    let deal = Deal::new(game, hands, boards)?;
+    if deal
+        .runouts(hands, boards)
+        .is_some_and(|n| n <= trials as u128)
+    {
+        return Ok(deal.enumerate(game, hands, boards));
+    }
+    let threads = match threads {
+        0 => parallel::available_threads(),
+        n => n,
+    };
+    let chunk = trials.div_ceil(threads).max(1);
+    let tallies = parallel::fan_out(trials.div_ceil(chunk), threads, |i| {
+        deal.tally(
+            game,
+            hands,
+            boards,
+            i * chunk..trials.min((i + 1) * chunk),
+            seed,
+        )
+    });
+    let mut tally = Tally::new(hands.len());
+    for t in tallies {
+        tally.merge(t);
+    }
+    Ok(tally.equities(false))
+}
+
+/// Like [`simulate`], playing out `trials` random finishes however few
+/// there are. The same `seed` always gives the same results.
+pub fn sample(
+    game: &GameStructure,
+    hands: &[Vec<Card>],
+    boards: &[Vec<Card>],
+    trials: usize,
+    seed: u64,
+) -> Result<Vec<Equity>, EquityError> {
+    Ok(Deal::new(game, hands, boards)?.sample(game, hands, boards, trials, seed))
+}
+
+/// Like [`simulate`], playing out every possible finish once, so the
+/// results are exact and `margin` is 0. The number of finishes grows
+/// quickly with the cards left to deal: a hold'em turn and river for two
+/// known hands is 990.
+pub fn enumerate(
+    game: &GameStructure,
+    hands: &[Vec<Card>],
+    boards: &[Vec<Card>],
+) -> Result<Vec<Equity>, EquityError> {
+    Ok(Deal::new(game, hands, boards)?.enumerate(game, hands, boards))
+}
+
+/// Like [`sample`], with each player's hole cards drawn from a range
+/// instead of known: every trial picks a combination from each range in
+/// proportion to its weights, skipping ones that clash with the boards or
+/// an earlier player's pick. The rest of each hand, if the game deals more
+/// than two cards, is random.
+pub fn sample_ranges(
+    game: &GameStructure,
+    ranges: &[Range],
+    boards: &[Vec<Card>],
+    trials: usize,
+    seed: u64,
+) -> Result<Vec<Equity>, EquityError> {
+    let mut rng = SplitMix64(seed);
+    let mut tally = Tally::new(ranges.len());
+    for _ in 0..trials {
+        let mut dead: Vec<Card> = boards.iter().flatten().copied().collect();
+        let mut hands = Vec::with_capacity(ranges.len());
+        for (seat, range) in ranges.iter().enumerate() {
+            let combo = range
+                .pick(&mut rng, &dead)
+                .ok_or(EquityError::EmptyRange { seat })?;
+            dead.extend(combo);
+            hands.push(combo.to_vec());
+        }
+        let deal = Deal::new(game, &hands, boards)?;
+        let mut shoe = deal.shoe.clone();
+        shoe.shuffle(rng.next());
+        let (holes, boards) = deal.fill(&hands, boards, &mut shoe);
+        tally.add(&shares(game, &holes, &boards));
+    }
+    Ok(tally.equities(false))
+}
+
+/// What's known of a hand, and the cards left to finish it with.
+struct Deal {
+    hole: usize,
+    boards: Vec<usize>,
+    shoe: Shoe,
+}
+
+impl Deal {
+    fn new(
+        game: &GameStructure,
+        hands: &[Vec<Card>],
+        boards: &[Vec<Card>],
+    ) -> Result<Self, EquityError> {
+        let (hole, mut sizes) = cards_dealt(game);
+        if sizes.len() < boards.len() {
+            sizes.resize(boards.len(), 0);
+        }
+        if let Some(seat) = hands.iter().position(|h| h.len() > hole) {
+            return Err(EquityError::TooManyHoleCards { seat, dealt: hole });
+        }
+        if let Some(board) = (0..boards.len()).find(|&b| boards[b].len() > sizes[b]) {
+            return Err(EquityError::TooManyBoardCards {
+                board,
+                dealt: sizes[board],
+            });
+        }
+        let mut shoe = Shoe::for_game(game);
+        for &card in hands.iter().chain(boards).flatten() {
+            if !shoe.remove(&[card]) {
+                return Err(EquityError::UnavailableCard(card));
+            }
+        }
+        let known = hands.iter().chain(boards).map(Vec::len).sum::<usize>();
+        let needed = hole * hands.len() + sizes.iter().sum::<usize>() - known;
+        if needed > shoe.remaining() {
+            return Err(EquityError::NotEnoughCards);
+        }
+        Ok(Deal {
+            hole,
+            boards: sizes,
+            shoe,
+        })
+    }
+
+    /// How many cards each hand, then each board, is missing.
+    fn missing(&self, hands: &[Vec<Card>], boards: &[Vec<Card>]) -> Vec<usize> {
+        let holes = hands.iter().map(|h| self.hole - h.len());
+        let boards =
+            (0..self.boards.len()).map(|b| self.boards[b] - boards.get(b).map_or(0, Vec::len));
+        holes.chain(boards).collect()
+    }
+
+    /// The number of different ways to finish the hand, or `None` if it
+    /// doesn't fit in a `u128`.
+    fn runouts(&self, hands: &[Vec<Card>], boards: &[Vec<Card>]) -> Option<u128> {
+        let mut left = self.shoe.remaining() as u128;
+        let mut total: u128 = 1;
+        for k in self.missing(hands, boards) {
+            // left choose k, multiplied in one factor at a time so each
+            // step divides exactly.
+            for i in 0..k as u128 {
+                total = total.checked_mul(left - i)? / (i + 1);
+            }
+            left -= k as u128;
+        }
+        Some(total)
+    }
+
+    fn sample(
+        &self,
+        game: &GameStructure,
+        hands: &[Vec<Card>],
+        boards: &[Vec<Card>],
+        trials: usize,
+        seed: u64,
+    ) -> Vec<Equity> {
+        self.tally(game, hands, boards, 0..trials, seed)
+            .equities(false)
+    }
+
+    /// Plays out the random finishes numbered `trials`.
+    fn tally(
+        &self,
+        game: &GameStructure,
+        hands: &[Vec<Card>],
+        boards: &[Vec<Card>],
+        trials: std::ops::Range<usize>,
+        seed: u64,
+    ) -> Tally {
+        let mut showdowns = Showdowns::new(game, self, hands.len());
+        for trial in trials {
+            let mut shoe = self.shoe.clone();
+            shoe.shuffle(seed.wrapping_add(trial as u64));
+            let (holes, boards) = self.fill(hands, boards, &mut shoe);
+            showdowns.add(&holes, &boards);
+        }
+        showdowns.finish()
+    }
+
+    fn enumerate(
+        &self,
+        game: &GameStructure,
+        hands: &[Vec<Card>],
+        boards: &[Vec<Card>],
+    ) -> Vec<Equity> {
+        let mut spots = hands.to_vec();
+        spots.extend((0..self.boards.len()).map(|b| boards.get(b).cloned().unwrap_or_default()));
+        let sizes: Vec<usize> = hands
+            .iter()
+            .map(|_| self.hole)
+            .chain(self.boards.iter().copied())
+            .collect();
+        let mut shoe = self.shoe.clone();
+        let left: Vec<Card> = std::iter::from_fn(|| shoe.deal()).collect();
+        let mut showdowns = Showdowns::new(game, self, hands.len());
+        let mut finish = |spots: &[Vec<Card>]| {
+            let (holes, boards) = spots.split_at(hands.len());
+            showdowns.add(holes, boards);
+        };
+        let mut used = vec![false; left.len()];
+        each_finish(&mut spots, &sizes, 0, &left, &mut used, 0, &mut finish);
+        showdowns.finish().equities(true)
+    }
+
+    /// The hands and boards with their missing cards dealt from `shoe`.
+    fn fill(
+        &self,
+        hands: &[Vec<Card>],
+        boards: &[Vec<Card>],
+        shoe: &mut Shoe,
+    ) -> (Vec<Vec<Card>>, Vec<Vec<Card>>) {
+        let mut deal = |known: Option<&Vec<Card>>, size: usize| {
+            let mut cards = known.cloned().unwrap_or_default();
+            while cards.len() < size {
+                cards.extend(shoe.deal());
+            }
+            cards
+        };
+        let holes = hands.iter().map(|h| deal(Some(h), self.hole)).collect();
+        let boards = (0..self.boards.len())
+            .map(|b| deal(boards.get(b), self.boards[b]))
+            .collect();
+        (holes, boards)
+    }
+}
+
+/// Each hand's share of the pot at showdown, in chips out of [`POT`].
+fn shares(game: &GameStructure, holes: &[Vec<Card>], boards: &[Vec<Card>]) -> Vec<Chips> {
+    let pot = Pot {
+        amount: POT,
+        eligible: (0..holes.len()).collect(),
+    };
+    let result = resolve_showdown(game, holes, boards, &[pot], 0);
+    (0..holes.len()).map(|seat| result.winnings(seat)).collect()
+}
+
+/// Finished hands waiting to be scored into a tally. When every player
+/// ends up with seven cards from a single plain deck and the game ranks
+/// them all as one high hand, the hands are ranked in batches with
+/// [`batch::evaluate_seven`]. Other games ranked as one high hand are
+/// ranked as they come with an [`IncrementalEvaluator`] per player, which
+/// keeps the work on the cards one finish shares with the last. Anything
+/// else has each showdown resolved in full.
+struct Showdowns<'a> {
+    game: &'a GameStructure,
+    batched: Option<Evaluator>,
+    incremental: Vec<IncrementalEvaluator>,
+    players: usize,
+    pending: Vec<[Card; 7]>,
+    tally: Tally,
+}
+
+impl<'a> Showdowns<'a> {
+    /// Finishes scored per batch, each with a hand per player.
+    const FINISHES: usize = 2 * batch::LANES;
+
+    fn new(game: &'a GameStructure, deal: &Deal, players: usize) -> Self {
+        let scorers = Scorer::for_game(game);
+        let plain_deck = {
+            let mut seen = 0u64;
+            deal.shoe.cards().iter().all(|c| {
+                let bit = 1u64 << ((c.rank as u32 & 0xF) * 4 + (c.suit as u32 & 3));
+                let fresh = c.suit < 4 && c.rank < 16 && seen & bit == 0;
+                seen |= bit;
+                fresh
+            })
+        };
+        let plain_high = match scorers.as_slice() {
+            [scorer] => scorer.plain_high().copied(),
+            _ => None,
+        };
+        let batched = plain_high.filter(|_| {
+            plain_deck && players > 0 && deal.hole + deal.boards.iter().sum::<usize>() == 7
+        });
+        let incremental = match plain_high {
+            Some(evaluator) if batched.is_none() => {
+                vec![IncrementalEvaluator::new(evaluator); players]
+            }
+            _ => Vec::new(),
+        };
+        Showdowns {
+            game,
+            batched,
+            incremental,
+            players,
+            pending: Vec::new(),
+            tally: Tally::new(players),
+        }
+    }
+
+    fn add(&mut self, holes: &[Vec<Card>], boards: &[Vec<Card>]) {
+        if !self.incremental.is_empty() {
+            let ranks: Vec<HandRank> = holes
+                .iter()
+                .zip(&mut self.incremental)
+                .map(|(hole, evaluator)| {
+                    let cards: Vec<Card> = hole
+                        .iter()
+                        .chain(boards.iter().flatten())
+                        .copied()
+                        .collect();
+                    match cards.split_last() {
+                        Some((&last, rest)) => {
+                            evaluator.set(rest);
+                            evaluator.rank_with(last)
+                        }
+                        None => {
+                            evaluator.set(&[]);
+                            evaluator.rank()
+                        }
+                    }
+                })
+                .collect();
+            self.tally.add(&split(&ranks));
+            return;
+        }
+        if self.batched.is_none() {
+            self.tally.add(&shares(self.game, holes, boards));
+            return;
+        }
+        for hole in holes {
+            let mut cards = hole.iter().chain(boards.iter().flatten()).copied();
+            self.pending
+                .push(std::array::from_fn(|_| cards.next().unwrap_or(Card::JOKER)));
+        }
+        if self.pending.len() >= Self::FINISHES * self.players {
+            self.flush();
+        }
+    }
+
+    /// Ranks the pending hands and splits each finish's pot between its
+    /// best hands.
+    fn flush(&mut self) {
+        let Some(evaluator) = &self.batched else {
+            return;
+        };
+        let ranks = batch::evaluate_seven(evaluator, &self.pending);
+        for finish in ranks.chunks(self.players) {
+            self.tally.add(&split(finish));
+        }
+        self.pending.clear();
+    }
+
+    fn finish(mut self) -> Tally {
+        self.flush();
+        self.tally
+    }
+}
+
+/// Each hand's share of the pot when `ranks` are the hands at showdown,
+/// split evenly between the best.
+fn split(ranks: &[HandRank]) -> Vec<Chips> {
+    let best = ranks.iter().max();
+    let winners = ranks.iter().filter(|&r| Some(r) == best).count() as Chips;
+    ranks
+        .iter()
+        .map(|r| if Some(r) == best { POT / winners } else { 0 })
+        .collect()
+}
+
+/// Calls `finish` once for every way of dealing the unused `left` cards
+/// into `spots`, from `spot` on, until each holds `sizes[spot]` cards. Cards
+/// go into a spot in the order of `left`, starting at `from`, so each
+/// combination comes up once.
+fn each_finish(
+    spots: &mut Vec<Vec<Card>>,
+    sizes: &[usize],
+    spot: usize,
+    left: &[Card],
+    used: &mut [bool],
+    from: usize,
+    finish: &mut dyn FnMut(&[Vec<Card>]),
+) {
+    if spot == spots.len() {
+        finish(spots);
+        return;
+    }
+    if spots[spot].len() >= sizes[spot] {
+        each_finish(spots, sizes, spot + 1, left, used, 0, finish);
+        return;
+    }
+    for i in from..left.len() {
+        if used[i] {
+            continue;
+        }
+        used[i] = true;
+        spots[spot].push(left[i]);
+        each_finish(spots, sizes, spot, left, used, i + 1, finish);
+        spots[spot].pop();
+        used[i] = false;
+    }
+}
+
+/// Running totals of each hand's results, in whole chips so that tallies
+/// added up in any order come out the same.
+struct Tally {
+    trials: usize,
+    wins: Vec<usize>,
+    ties: Vec<usize>,
+    shares: Vec<Chips>,
+    squares: Vec<u128>,
+}
+
+impl Tally {
+    fn new(hands: usize) -> Self {
+        Tally {
+            trials: 0,
+            wins: vec![0; hands],
+            ties: vec![0; hands],
+            shares: vec![0; hands],
+            squares: vec![0; hands],
+        }
+    }
+
+    fn add(&mut self, shares: &[Chips]) {
+        self.trials += 1;
+        for (seat, &share) in shares.iter().enumerate() {
+            if share >= POT {
+                self.wins[seat] += 1;
+            } else if share > 0 {
+                self.ties[seat] += 1;
+            }
+            self.shares[seat] += share;
+            self.squares[seat] += share as u128 * share as u128;
+        }
+    }
+
+    /// Adds in the totals of another tally of the same hands.
+    fn merge(&mut self, other: Tally) {
+        self.trials += other.trials;
+        for seat in 0..self.wins.len() {
+            self.wins[seat] += other.wins[seat];
+            self.ties[seat] += other.ties[seat];
+            self.shares[seat] += other.shares[seat];
+            self.squares[seat] += other.squares[seat];
+        }
+    }
+
+    /// The results as percentages, with no margin of error if every
+    /// finish was played out.
+    fn equities(&self, exact: bool) -> Vec<Equity> {
+        if self.trials == 0 {
+            return vec![Equity::default(); self.wins.len()];
+        }
+        let n = self.trials as f64;
+        let pot = POT as f64;
+        (0..self.wins.len())
+            .map(|seat| {
+                let mean = self.shares[seat] as f64 / (n * pot);
+                let square = self.squares[seat] as f64 / (n * pot * pot);
+                let variance = (square - mean * mean).max(0.0);
+                let win = self.wins[seat] as f64 / n;
+                let tie = self.ties[seat] as f64 / n;
+                Equity {
+                    win: 100.0 * win,
+                    tie: 100.0 * tie,
+                    lose: 100.0 * (1.0 - win - tie),
+                    equity: 100.0 * mean,
+                    margin: if exact {
+                        0.0
+                    } else {
+                        100.0 * 1.96 * (variance / n).sqrt()
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::parse_cards;
+    use crate::presets;
+
+    fn cards(s: &str) -> Vec<Card> {
+        parse_cards(s).unwrap()
+    }
+
+    #[test]
+    fn test_simulate() {
+        let game = presets::holdem();
+        let hands = [cards("Ac Ad"), cards("7h 2s")];
+        let equities = simulate(&game, &hands, &[], 2000, 1).unwrap();
+        assert!((80.0..95.0).contains(&equities[0].equity), "{:?}", equities);
+        assert!((equities[0].equity + equities[1].equity - 100.0).abs() < 1e-9);
+        assert!(equities[0].margin > 0.0 && equities[0].margin < 3.0);
+        let (low, high) = equities[0].interval();
+        assert!(low < equities[0].equity && equities[0].equity < high);
+        assert_eq!(simulate(&game, &hands, &[], 2000, 1).unwrap(), equities);
+        for threads in [1, 3, 0] {
+            let parallel = simulate_parallel(&game, &hands, &[], 2000, 1, threads);
+            assert_eq!(parallel.unwrap(), equities);
+        }
+
+        // A board that already gives both hands the same straight.
+        let board = [cards("Ts Js Qd Kh Ac")];
+        let hands = [cards("2c 3d"), cards("4c 5d")];
+        let equities = simulate(&game, &hands, &board, 10, 1).unwrap();
+        assert_eq!(equities[0].tie, 100.0);
+        assert_eq!(equities[1].equity, 50.0);
+        assert_eq!(equities[1].margin, 0.0);
+    }
+
+    #[test]
+    fn test_enumerate() {
+        let game = presets::holdem();
+        let hands = [cards("As Ks"), cards("Qd Qh")];
+        let board = [cards("Qs 7s 2d 3c")];
+        // Seven of the nine spades left make the flush; the other two pair
+        // the board and fill up the queens.
+        let equities = enumerate(&game, &hands, &board).unwrap();
+        assert!((equities[0].equity - 700.0 / 44.0).abs() < 1e-9);
+        assert_eq!(equities[0].margin, 0.0);
+        assert_eq!(simulate(&game, &hands, &board, 44, 3).unwrap(), equities);
+        assert_ne!(simulate(&game, &hands, &board, 43, 3).unwrap(), equities);
+
+        let deal = |board: &[Vec<Card>]| Deal::new(&game, &hands, board).unwrap();
+        assert_eq!(deal(&[]).runouts(&hands, &[]), Some(1_712_304));
+        let flop = [cards("Qs 7s 2d")];
+        assert_eq!(deal(&flop).runouts(&hands, &flop), Some(990));
+    }
+
+    #[test]
+    fn test_batched_showdowns() {
+        let game = presets::holdem();
+        let hands = [cards("Ac Kd"), cards("7h 7s"), cards("")];
+        let batched = simulate(&game, &hands, &[], 3000, 5).unwrap();
+        // A limit that allows every card rules out the batch evaluator
+        // without changing the hands.
+        let mut general = game.clone();
+        general.scorings = vec![crate::Scoring {
+            hand_cards: Some(crate::scoring::Range { min: 0, max: -1 }),
+            ..Default::default()
+        }];
+        let deal = Deal::new(&game, &hands, &[]).unwrap();
+        assert!(Showdowns::new(&game, &deal, 3).batched.is_some());
+        assert!(Showdowns::new(&general, &deal, 3).batched.is_none());
+        assert_eq!(simulate(&general, &hands, &[], 3000, 5).unwrap(), batched);
+        let board = [cards("Ah 7c 2d")];
+        assert_eq!(
+            enumerate(&general, &hands[..2], &board).unwrap(),
+            enumerate(&game, &hands[..2], &board).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_incremental_showdowns() {
+        let game = presets::five_card_draw();
+        let hands = [cards("Ks Kd 7c 7d"), cards("Ah Qh Jh Th")];
+        let mut general = game.clone();
+        general.scorings = vec![crate::Scoring {
+            hand_cards: Some(crate::scoring::Range { min: 0, max: -1 }),
+            ..Default::default()
+        }];
+        let deal = Deal::new(&game, &hands, &[]).unwrap();
+        let showdowns = Showdowns::new(&game, &deal, 2);
+        assert!(showdowns.batched.is_none() && showdowns.incremental.len() == 2);
+        assert!(Showdowns::new(&general, &deal, 2).incremental.is_empty());
+        let holdem = presets::holdem();
+        let deal = Deal::new(&holdem, &[], &[]).unwrap();
+        assert!(Showdowns::new(&holdem, &deal, 2).incremental.is_empty());
+
+        assert_eq!(
+            enumerate(&general, &hands, &[]).unwrap(),
+            enumerate(&game, &hands, &[]).unwrap()
+        );
+        assert_eq!(
+            simulate(&general, &hands[..1], &[], 1000, 2).unwrap(),
+            simulate(&game, &hands[..1], &[], 1000, 2).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sample_ranges() {
+        let game = presets::holdem();
+        let aces: Range = "AA".parse().unwrap();
+        let wide: Range = "22+, A2s+, K9o+".parse().unwrap();
+        let equities = sample_ranges(&game, &[aces.clone(), wide], &[], 500, 2).unwrap();
+        assert!(equities[0].equity > 70.0, "{:?}", equities);
+        let board = [cards("As Ah Kd")];
+        assert_eq!(
+            sample_ranges(&game, &[aces.clone(), aces], &board, 10, 2),
+            Err(EquityError::EmptyRange { seat: 1 })
+        );
+    }
+
+    #[test]
+    fn test_hi_lo_and_unknown_cards() {
+        let game = presets::omaha_hi_lo();
+        // The nut low and a hand that can't make one, on a low board.
+        let board = [cards("2c 3d 7h Ks Qc")];
+        let hands = [cards("Ad 4h Jc Jd"), cards("Ks Kd 9c 9d")];
+        assert_eq!(
+            simulate(&game, &hands, &board, 10, 1),
+            Err(EquityError::UnavailableCard(Card::new(11, 0)))
+        );
+        let hands = [cards("Ad 4h Jc Jd"), cards("Kh Kd 9c 9d")];
+        let equities = simulate(&game, &hands, &board, 10, 1).unwrap();
+        assert_eq!(equities[0].equity, 50.0);
+        assert_eq!(equities[0].tie, 100.0);
+
+        let equities = simulate(&game, &[cards("As Ah"), vec![]], &[], 200, 7).unwrap();
+        assert_eq!(equities.len(), 2);
+        assert_eq!(cards_dealt(&game), (4, vec![5]),);
+        assert_eq!(
+            simulate(&game, &[cards("As Ah Ks Kh Qs")], &[], 1, 0),
+            Err(EquityError::TooManyHoleCards { seat: 0, dealt: 4 })
+        );
+    }
+}
@@ -0,0 +1,606 @@
+//! A hand's [`Event`]s as [`GameEvent`] messages, the form every client and
+//! transport shares, and back again.
+//!
+//! A message is for an audience, so it carries only what they may see: a
+//! message read back gives the event as [`Event::redacted`] for them.
+//! Messages are written as JSON by [`encode`].
+
+use crate::cards::Card;
+use crate::engine::{Action, Audience, Chips, DealtCard, EngineError, Event};
+use crate::fingerprint::base64;
+use crate::gamedef::json::Value;
+use crate::gamedef::protojson::Object;
+use crate::handeval::HandRank;
+use crate::phase::declare::Side;
+use crate::{
+    forced_bet, game_event, player_action, Acted, BoardCards, CardBought, CardExposed,
+    CardsExchanged, CardsPassed, Declared, ForcedBet, GameEvent, HandShown, HiddenBoardDealt,
+    HoleCards, PlayerAction, SeatCards, SeatChips, ShuffleRevealed,
+};
+
+fn chips(amount: Chips) -> i64 {
+    amount as i64
+}
+
+fn index(i: usize) -> i32 {
+    i as i32
+}
+
+fn cards(cards: &[Card]) -> Vec<crate::Card> {
+    cards.iter().map(|&c| c.into()).collect()
+}
+
+/// A seat's cards: all of them for its own player, the face-up ones for
+/// anyone else.
+pub fn hole_cards(seat: usize, dealt: &[DealtCard], audience: Audience) -> HoleCards {
+    let shown: Vec<&DealtCard> = dealt
+        .iter()
+        .filter(|c| audience.sees_card(seat, c))
+        .collect();
+    HoleCards {
+        seat: index(seat),
+        cards: shown.iter().map(|c| c.card.into()).collect(),
+        face_up: shown.iter().map(|c| c.face_up).collect(),
+        hidden: (dealt.len() - shown.len()) as i32,
+    }
+}
+
+impl Event {
+    /// The event as a message for `audience`.
+    pub fn message_for(&self, audience: Audience) -> GameEvent {
+        use game_event::Kind;
+        let forced = |kind: forced_bet::Kind, seat: usize, amount: Chips| {
+            Kind::ForcedBet(ForcedBet {
+                kind: kind.into(),
+                seat: index(seat),
+                amount: chips(amount),
+            })
+        };
+        let seat_chips = |seat: usize, amount: Chips| SeatChips {
+            seat: index(seat),
+            amount: chips(amount),
+        };
+        let seat_cards = |seat: usize, shown: &[Card]| SeatCards {
+            seat: index(seat),
+            cards: cards(shown),
+        };
+        let board_cards = |board: usize, shown: &[Card]| BoardCards {
+            board: index(board),
+            cards: cards(shown),
+        };
+        let kind = match self {
+            Event::Shuffled { seed } => Kind::Shuffled(*seed),
+            Event::ShuffleCommitted { hash } => Kind::ShuffleCommitted(hash.to_vec()),
+            Event::ShuffleRevealed { seed, salt } => Kind::ShuffleRevealed(ShuffleRevealed {
+                seed: *seed,
+                salt: salt.to_vec(),
+            }),
+            Event::PhaseStarted { index: i } => Kind::PhaseStarted(index(*i)),
+            Event::PhaseSkipped { index: i } => Kind::PhaseSkipped(index(*i)),
+            Event::HoleCardsDealt { seat, cards } => {
+                Kind::HoleCardsDealt(hole_cards(*seat, cards, audience))
+            }
+            Event::BoardDealt { board, cards } => Kind::BoardDealt(board_cards(*board, cards)),
+            Event::HiddenBoardDealt { board, count } => Kind::HiddenBoardDealt(HiddenBoardDealt {
+                board: index(*board),
+                count: index(*count),
+            }),
+            Event::CardsRevealed { seat, cards } => Kind::CardsRevealed(seat_cards(*seat, cards)),
+            Event::BoardRevealed { board, cards } => {
+                Kind::BoardRevealed(board_cards(*board, cards))
+            }
+            Event::AntePosted { seat, amount } => forced(forced_bet::Kind::Ante, *seat, *amount),
+            Event::BlindPosted { seat, amount } => forced(forced_bet::Kind::Blind, *seat, *amount),
+            Event::DeadBlindPosted { seat, amount } => {
+                forced(forced_bet::Kind::DeadBlind, *seat, *amount)
+            }
+            Event::StraddlePosted { seat, amount } => {
+                forced(forced_bet::Kind::Straddle, *seat, *amount)
+            }
+            Event::BombPotPosted { seat, amount } => {
+                forced(forced_bet::Kind::BombPot, *seat, *amount)
+            }
+            Event::KillBlindPosted { seat, amount } => {
+                forced(forced_bet::Kind::KillBlind, *seat, *amount)
+            }
+            Event::UncalledBetReturned { seat, amount } => {
+                Kind::UncalledBetReturned(seat_chips(*seat, *amount))
+            }
+            Event::RakeTaken { amount } => Kind::RakeTaken(chips(*amount)),
+            Event::Acted { seat, action } => {
+                let action = match audience.sees(*seat) {
+                    true => PlayerAction::from(action),
+                    false => PlayerAction::from(&action.redacted()),
+                };
+                Kind::Acted(Acted {
+                    seat: index(*seat),
+                    action: Some(action),
+                })
+            }
+            Event::CardsExchanged { seat, count } => Kind::CardsExchanged(CardsExchanged {
+                seat: index(*seat),
+                count: index(*count),
+            }),
+            Event::CardsPassed { from, to, count } => Kind::CardsPassed(CardsPassed {
+                from: index(*from),
+                to: index(*to),
+                count: index(*count),
+            }),
+            Event::DiscardsShown { seat, cards } => Kind::DiscardsShown(seat_cards(*seat, cards)),
+            Event::DiscardsReshuffled { count } => Kind::DiscardsReshuffled(index(*count)),
+            Event::RunoutStarted { run } => Kind::RunoutStarted(index(*run)),
+            Event::HandShown {
+                seat,
+                scoring,
+                rank,
+            } => Kind::HandShown(HandShown {
+                seat: index(*seat),
+                scoring: index(*scoring),
+                description: rank.describe(),
+                rank: rank.to_bits(),
+            }),
+            Event::PotAwarded { seat, amount } => Kind::PotAwarded(seat_chips(*seat, *amount)),
+            Event::CardExposed {
+                seat,
+                card,
+                replacement,
+            } => Kind::CardExposed(CardExposed {
+                seat: index(*seat),
+                card: Some((*card).into()),
+                replacement: replacement.filter(|_| audience.sees(*seat)).map(Into::into),
+            }),
+            Event::Declared { seat, side } => Kind::Declared(Declared {
+                seat: index(*seat),
+                side: (*side).into(),
+            }),
+            Event::CardBought {
+                seat,
+                amount,
+                replaced,
+                shown,
+            } => Kind::CardBought(CardBought {
+                seat: index(*seat),
+                amount: chips(*amount),
+                replaced: *replaced,
+                shown: shown.map(Into::into),
+            }),
+            Event::BuyDeclined { seat } => Kind::BuyDeclined(index(*seat)),
+            Event::HandDeclaredDead { seat } => Kind::HandDeclaredDead(index(*seat)),
+            Event::HandComplete => Kind::HandComplete(true),
+            Event::Misdeal => Kind::Misdeal(true),
+        };
+        GameEvent { kind: Some(kind) }
+    }
+}
+
+fn malformed(why: impl Into<String>) -> EngineError {
+    EngineError::MalformedEvent(why.into())
+}
+
+fn number(n: i32) -> Result<usize, EngineError> {
+    usize::try_from(n).map_err(|_| malformed(format!("negative number {}", n)))
+}
+
+fn amount(n: i64) -> Result<Chips, EngineError> {
+    Chips::try_from(n).map_err(|_| malformed(format!("negative amount {}", n)))
+}
+
+fn card(card: &crate::Card) -> Result<Card, EngineError> {
+    Card::try_from(card).map_err(|e| malformed(e.to_string()))
+}
+
+fn card_list(cards: &[crate::Card]) -> Result<Vec<Card>, EngineError> {
+    cards.iter().map(card).collect()
+}
+
+fn bytes<const N: usize>(what: &str, bytes: &[u8]) -> Result<[u8; N], EngineError> {
+    bytes
+        .try_into()
+        .map_err(|_| malformed(format!("a {} has {} bytes, not {}", what, N, bytes.len())))
+}
+
+impl TryFrom<&GameEvent> for Event {
+    type Error = EngineError;
+
+    fn try_from(msg: &GameEvent) -> Result<Self, Self::Error> {
+        use game_event::Kind;
+        let kind = msg.kind.as_ref().ok_or_else(|| malformed("no event"))?;
+        Ok(match kind {
+            Kind::Shuffled(seed) => Event::Shuffled { seed: *seed },
+            Kind::ShuffleCommitted(hash) => Event::ShuffleCommitted {
+                hash: bytes("shuffle hash", hash)?,
+            },
+            Kind::ShuffleRevealed(revealed) => Event::ShuffleRevealed {
+                seed: revealed.seed,
+                salt: bytes("shuffle salt", &revealed.salt)?,
+            },
+            Kind::PhaseStarted(i) => Event::PhaseStarted { index: number(*i)? },
+            Kind::PhaseSkipped(i) => Event::PhaseSkipped { index: number(*i)? },
+            Kind::HoleCardsDealt(hole) => {
+                if hole.cards.len() != hole.face_up.len() {
+                    return Err(malformed(format!(
+                        "{} cards dealt, but {} face up or down",
+                        hole.cards.len(),
+                        hole.face_up.len()
+                    )));
+                }
+                Event::HoleCardsDealt {
+                    seat: number(hole.seat)?,
+                    cards: card_list(&hole.cards)?
+                        .into_iter()
+                        .zip(&hole.face_up)
+                        .map(|(card, &face_up)| DealtCard { card, face_up })
+                        .collect(),
+                }
+            }
+            Kind::BoardDealt(board) => Event::BoardDealt {
+                board: number(board.board)?,
+                cards: card_list(&board.cards)?,
+            },
+            Kind::HiddenBoardDealt(hidden) => Event::HiddenBoardDealt {
+                board: number(hidden.board)?,
+                count: number(hidden.count)?,
+            },
+            Kind::CardsRevealed(revealed) => Event::CardsRevealed {
+                seat: number(revealed.seat)?,
+                cards: card_list(&revealed.cards)?,
+            },
+            Kind::BoardRevealed(board) => Event::BoardRevealed {
+                board: number(board.board)?,
+                cards: card_list(&board.cards)?,
+            },
+            Kind::ForcedBet(bet) => {
+                let (seat, amount) = (number(bet.seat)?, amount(bet.amount)?);
+                match bet.kind() {
+                    forced_bet::Kind::Ante => Event::AntePosted { seat, amount },
+                    forced_bet::Kind::Blind => Event::BlindPosted { seat, amount },
+                    forced_bet::Kind::DeadBlind => Event::DeadBlindPosted { seat, amount },
+                    forced_bet::Kind::Straddle => Event::StraddlePosted { seat, amount },
+                    forced_bet::Kind::BombPot => Event::BombPotPosted { seat, amount },
+                    forced_bet::Kind::KillBlind => Event::KillBlindPosted { seat, amount },
+                    forced_bet::Kind::Unknown => {
+                        return Err(malformed(format!("unknown forced bet {}", bet.kind)))
+                    }
+                }
+            }
+            Kind::UncalledBetReturned(returned) => Event::UncalledBetReturned {
+                seat: number(returned.seat)?,
+                amount: amount(returned.amount)?,
+            },
+            Kind::RakeTaken(rake) => Event::RakeTaken {
+                amount: amount(*rake)?,
+            },
+            Kind::Acted(acted) => Event::Acted {
+                seat: number(acted.seat)?,
+                action: Action::try_from(
+                    acted
+                        .action
+                        .as_ref()
+                        .ok_or_else(|| malformed("an action with no action"))?,
+                )?,
+            },
+            Kind::CardsExchanged(exchanged) => Event::CardsExchanged {
+                seat: number(exchanged.seat)?,
+                count: number(exchanged.count)?,
+            },
+            Kind::CardsPassed(passed) => Event::CardsPassed {
+                from: number(passed.from)?,
+                to: number(passed.to)?,
+                count: number(passed.count)?,
+            },
+            Kind::DiscardsShown(shown) => Event::DiscardsShown {
+                seat: number(shown.seat)?,
+                cards: card_list(&shown.cards)?,
+            },
+            Kind::DiscardsReshuffled(count) => Event::DiscardsReshuffled {
+                count: number(*count)?,
+            },
+            Kind::RunoutStarted(run) => Event::RunoutStarted { run: number(*run)? },
+            Kind::HandShown(shown) => Event::HandShown {
+                seat: number(shown.seat)?,
+                scoring: number(shown.scoring)?,
+                rank: HandRank::from_bits(shown.rank),
+            },
+            Kind::PotAwarded(awarded) => Event::PotAwarded {
+                seat: number(awarded.seat)?,
+                amount: amount(awarded.amount)?,
+            },
+            Kind::CardExposed(exposed) => Event::CardExposed {
+                seat: number(exposed.seat)?,
+                card: card(
+                    exposed
+                        .card
+                        .as_ref()
+                        .ok_or_else(|| malformed("an exposed card with no card"))?,
+                )?,
+                replacement: exposed.replacement.as_ref().map(card).transpose()?,
+            },
+            Kind::Declared(declared) => match declared.side() {
+                Side::Unknown => {
+                    return Err(malformed(format!("unknown side {}", declared.side)));
+                }
+                side => Event::Declared {
+                    seat: number(declared.seat)?,
+                    side,
+                },
+            },
+            Kind::CardBought(bought) => Event::CardBought {
+                seat: number(bought.seat)?,
+                amount: amount(bought.amount)?,
+                replaced: bought.replaced,
+                shown: bought.shown.as_ref().map(card).transpose()?,
+            },
+            Kind::BuyDeclined(seat) => Event::BuyDeclined {
+                seat: number(*seat)?,
+            },
+            Kind::HandDeclaredDead(seat) => Event::HandDeclaredDead {
+                seat: number(*seat)?,
+            },
+            Kind::HandComplete(_) => Event::HandComplete,
+            Kind::Misdeal(_) => Event::Misdeal,
+        })
+    }
+}
+
+fn action_kind(value: i32) -> Option<&'static str> {
+    player_action::Kind::try_from(value)
+        .ok()
+        .map(|k| k.as_str_name())
+}
+
+fn forced_kind(value: i32) -> Option<&'static str> {
+    forced_bet::Kind::try_from(value)
+        .ok()
+        .map(|k| k.as_str_name())
+}
+
+fn side(value: i32) -> Option<&'static str> {
+    Side::try_from(value).ok().map(|s| s.as_str_name())
+}
+
+/// A card as `Card` is written in JSON.
+pub fn encode_card(card: &crate::Card) -> Value {
+    Object::default()
+        .int("rank", card.rank)
+        .int("suit", card.suit)
+        .build()
+}
+
+pub fn encode_cards(cards: &[crate::Card]) -> Vec<Value> {
+    cards.iter().map(encode_card).collect()
+}
+
+pub fn encode_action(action: &PlayerAction) -> Value {
+    Object::default()
+        .enumeration("kind", action.kind, action_kind)
+        .int64("amount", action.amount)
+        .list("cards", encode_cards(&action.cards))
+        .enumeration("side", action.side, side)
+        .build()
+}
+
+/// `HoleCards`, for a message to add to.
+pub fn encode_hole(hole: &HoleCards) -> Object {
+    Object::default()
+        .int("seat", hole.seat)
+        .list("cards", encode_cards(&hole.cards))
+        .list(
+            "faceUp",
+            hole.face_up.iter().map(|&up| Value::Bool(up)).collect(),
+        )
+        .int("hidden", hole.hidden)
+}
+
+/// `event` in the canonical protobuf JSON mapping, as the server sends it
+/// to WebSocket clients and hand files keep it.
+pub fn encode(event: &GameEvent) -> Value {
+    use game_event::Kind;
+    let Some(kind) = &event.kind else {
+        return Object::default().build();
+    };
+    let seat_cards = |seat: i32, cards: &[crate::Card]| {
+        Object::default()
+            .int("seat", seat)
+            .list("cards", encode_cards(cards))
+            .build()
+    };
+    let board_cards = |board: i32, cards: &[crate::Card]| {
+        Object::default()
+            .int("board", board)
+            .list("cards", encode_cards(cards))
+            .build()
+    };
+    let seat_chips = |seat: i32, amount: i64| {
+        Object::default()
+            .int("seat", seat)
+            .int64("amount", amount)
+            .build()
+    };
+    let int = |n: i32| Value::Number(n.into());
+    let (key, value) = match kind {
+        Kind::Shuffled(seed) => ("shuffled", Value::String(seed.to_string())),
+        Kind::ShuffleCommitted(hash) => ("shuffleCommitted", Value::String(base64(hash))),
+        Kind::ShuffleRevealed(revealed) => (
+            "shuffleRevealed",
+            Object::default()
+                .uint64("seed", revealed.seed)
+                .string("salt", &base64(&revealed.salt))
+                .build(),
+        ),
+        Kind::PhaseStarted(index) => ("phaseStarted", int(*index)),
+        Kind::PhaseSkipped(index) => ("phaseSkipped", int(*index)),
+        Kind::HoleCardsDealt(hole) => ("holeCardsDealt", encode_hole(hole).build()),
+        Kind::BoardDealt(b) => ("boardDealt", board_cards(b.board, &b.cards)),
+        Kind::HiddenBoardDealt(hidden) => (
+            "hiddenBoardDealt",
+            Object::default()
+                .int("board", hidden.board)
+                .int("count", hidden.count)
+                .build(),
+        ),
+        Kind::CardsRevealed(s) => ("cardsRevealed", seat_cards(s.seat, &s.cards)),
+        Kind::BoardRevealed(b) => ("boardRevealed", board_cards(b.board, &b.cards)),
+        Kind::ForcedBet(bet) => (
+            "forcedBet",
+            Object::default()
+                .enumeration("kind", bet.kind, forced_kind)
+                .int("seat", bet.seat)
+                .int64("amount", bet.amount)
+                .build(),
+        ),
+        Kind::UncalledBetReturned(s) => ("uncalledBetReturned", seat_chips(s.seat, s.amount)),
+        Kind::RakeTaken(amount) => ("rakeTaken", Value::String(amount.to_string())),
+        Kind::Acted(acted) => (
+            "acted",
+            Object::default()
+                .int("seat", acted.seat)
+                .message("action", acted.action.as_ref().map(encode_action))
+                .build(),
+        ),
+        Kind::CardsExchanged(exchanged) => (
+            "cardsExchanged",
+            Object::default()
+                .int("seat", exchanged.seat)
+                .int("count", exchanged.count)
+                .build(),
+        ),
+        Kind::CardsPassed(passed) => (
+            "cardsPassed",
+            Object::default()
+                .int("from", passed.from)
+                .int("to", passed.to)
+                .int("count", passed.count)
+                .build(),
+        ),
+        Kind::DiscardsShown(s) => ("discardsShown", seat_cards(s.seat, &s.cards)),
+        Kind::DiscardsReshuffled(count) => ("discardsReshuffled", int(*count)),
+        Kind::RunoutStarted(run) => ("runoutStarted", int(*run)),
+        Kind::HandShown(shown) => (
+            "handShown",
+            Object::default()
+                .int("seat", shown.seat)
+                .int("scoring", shown.scoring)
+                .string("description", &shown.description)
+                .uint("rank", shown.rank)
+                .build(),
+        ),
+        Kind::PotAwarded(s) => ("potAwarded", seat_chips(s.seat, s.amount)),
+        Kind::CardExposed(exposed) => (
+            "cardExposed",
+            Object::default()
+                .int("seat", exposed.seat)
+                .message("card", exposed.card.as_ref().map(encode_card))
+                .message("replacement", exposed.replacement.as_ref().map(encode_card))
+                .build(),
+        ),
+        Kind::Declared(declared) => (
+            "declared",
+            Object::default()
+                .int("seat", declared.seat)
+                .enumeration("side", declared.side, side)
+                .build(),
+        ),
+        Kind::CardBought(bought) => (
+            "cardBought",
+            Object::default()
+                .int("seat", bought.seat)
+                .int64("amount", bought.amount)
+                .bool("replaced", bought.replaced)
+                .message("shown", bought.shown.as_ref().map(encode_card))
+                .build(),
+        ),
+        Kind::BuyDeclined(seat) => ("buyDeclined", int(*seat)),
+        Kind::HandDeclaredDead(seat) => ("handDeclaredDead", int(*seat)),
+        Kind::HandComplete(done) => ("handComplete", Value::Bool(*done)),
+        Kind::Misdeal(misdeal) => ("misdeal", Value::Bool(*misdeal)),
+    };
+    Object::default().field(key, value).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::GameEngine;
+    use crate::presets;
+    use crate::strategy::{Passive, Strategy};
+    use crate::view::GameView;
+
+    #[test]
+    fn test_hidden_cards() {
+        let dealt = [
+            DealtCard {
+                card: Card::new(12, 0),
+                face_up: false,
+            },
+            DealtCard {
+                card: Card::new(0, 1),
+                face_up: true,
+            },
+        ];
+        let event = Event::HoleCardsDealt {
+            seat: 2,
+            cards: dealt.to_vec(),
+        };
+        let seen = |audience| match event.message_for(audience).kind {
+            Some(game_event::Kind::HoleCardsDealt(hole)) => hole,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(seen(Audience::Seat(2)).cards.len(), 2);
+        assert_eq!(seen(Audience::Seat(2)).face_up, [false, true]);
+        assert_eq!(seen(Audience::Seat(2)).hidden, 0);
+        for audience in [Audience::Spectator, Audience::Seat(0)] {
+            assert_eq!(seen(audience).cards, [Card::new(0, 1).into()]);
+            assert_eq!(seen(audience).hidden, 1);
+        }
+
+        let event = Event::Acted {
+            seat: 2,
+            action: Action::Discard(vec![Card::new(12, 0)]),
+        };
+        let discarded = |audience| match event.message_for(audience).kind {
+            Some(game_event::Kind::Acted(acted)) => acted.action.unwrap().cards.len(),
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(discarded(Audience::Seat(2)), 1);
+        assert_eq!(discarded(Audience::Spectator), 0);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut engine = GameEngine::new(presets::five_card_draw(), 3).unwrap();
+        while let Some(seat) = engine.to_act() {
+            let action = Passive.act(&GameView::new(&engine, seat));
+            engine.apply_action(seat, action).unwrap();
+        }
+        for audience in [Audience::Seat(0), Audience::Spectator] {
+            for event in engine.events() {
+                let message = event.message_for(audience);
+                assert_eq!(Event::try_from(&message), Ok(event.redacted(audience)));
+            }
+        }
+        assert!(engine
+            .events()
+            .iter()
+            .any(|e| matches!(e, Event::HandShown { .. })));
+    }
+
+    #[test]
+    fn test_malformed() {
+        let bad = |kind| Event::try_from(&GameEvent { kind: Some(kind) });
+        assert!(Event::try_from(&GameEvent::default()).is_err());
+        assert_eq!(
+            bad(game_event::Kind::PhaseStarted(-1)),
+            Err(EngineError::MalformedEvent(
+                "negative number -1".to_string()
+            ))
+        );
+        assert!(bad(game_event::Kind::ShuffleCommitted(vec![0; 31])).is_err());
+        assert!(bad(game_event::Kind::ForcedBet(ForcedBet::default())).is_err());
+        assert!(bad(game_event::Kind::Acted(Acted::default())).is_err());
+        assert!(bad(game_event::Kind::HoleCardsDealt(HoleCards {
+            cards: vec![Card::new(0, 0).into()],
+            ..HoleCards::default()
+        }))
+        .is_err());
+    }
+}
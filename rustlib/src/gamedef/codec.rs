@@ -0,0 +1,799 @@
+//! Conversion between [`GameStructure`] and the canonical protobuf JSON
+//! mapping: lowerCamelCase keys, enums by name, 64-bit integers as strings,
+//! default values omitted and oneof members written under their own names.
+//!
+//! Decoding also accepts the original snake_case field names, enum numbers
+//! and numeric 64-bit integers, as the protobuf JSON spec requires.
+
+use std::collections::HashMap;
+
+use super::json::Value;
+use super::protojson::{
+    self, boolean, enum_value, error, fields, int, int64, items, set_oneof, unknown, Object, Result,
+};
+use crate::dealing_errors::ExposedCard;
+use crate::deck::JokerRule;
+use crate::phase::ante::Payer;
+use crate::phase::betting_round::bring_in::Starter;
+use crate::phase::betting_round::BettingOrder;
+use crate::phase::condition::Measure;
+use crate::phase::exchange::DrawSource;
+use crate::phase::pass_cards::Direction;
+use crate::{
+    burn_policy, deck, game_structure, phase, scoring, BurnPolicy, DealingErrors, Deck,
+    GameStructure, HandRanking, HandType, Parameter, Phase, PhaseOverride, RakeConfig, Scoring,
+    ShoeConfig, StandardDeck,
+};
+
+/// Proto enums, looked up by name or number.
+trait ProtoEnum {
+    fn name(value: i32) -> Option<&'static str>;
+    fn value(name: &str) -> Option<i32>;
+}
+
+macro_rules! proto_enums {
+    ($($ty:ty),* $(,)?) => {$(
+        impl ProtoEnum for $ty {
+            fn name(value: i32) -> Option<&'static str> {
+                <$ty>::try_from(value).ok().map(|e| e.as_str_name())
+            }
+            fn value(name: &str) -> Option<i32> {
+                <$ty>::from_str_name(name).map(i32::from)
+            }
+        }
+    )*};
+}
+
+proto_enums!(
+    BettingOrder,
+    Starter,
+    DrawSource,
+    Direction,
+    JokerRule,
+    Measure,
+    Payer,
+    HandRanking,
+    HandType,
+    StandardDeck,
+    ExposedCard,
+    burn_policy::Mode,
+);
+
+fn strings(values: &[String]) -> Vec<Value> {
+    values.iter().map(|s| Value::String(s.clone())).collect()
+}
+
+/// The canonical JSON form of `game`.
+pub fn encode(game: &GameStructure) -> Value {
+    let mut obj = Object::default()
+        .string("id", &game.id)
+        .string("name", &game.name);
+    obj = match &game.deck {
+        Some(game_structure::Deck::StandardDeck(d)) => {
+            obj.field("standardDeck", enum_value(*d, StandardDeck::name))
+        }
+        Some(game_structure::Deck::CustomDeck(d)) => obj.field("customDeck", encode_deck(d)),
+        None => obj,
+    };
+    obj.list("phases", game.phases.iter().map(encode_phase).collect())
+        .int("communityBoardCount", game.community_board_count)
+        .list(
+            "scorings",
+            game.scorings.iter().map(encode_scoring).collect(),
+        )
+        .int("allInRunouts", game.all_in_runouts)
+        .message("rake", game.rake.as_ref().map(encode_rake))
+        .message(
+            "dealingErrors",
+            game.dealing_errors.as_ref().map(encode_dealing_errors),
+        )
+        .int("version", game.version)
+        .string("extends", &game.extends)
+        .list(
+            "phaseOverrides",
+            game.phase_overrides
+                .iter()
+                .map(encode_phase_override)
+                .collect(),
+        )
+        .list(
+            "parameters",
+            game.parameters.iter().map(encode_parameter).collect(),
+        )
+        .string("description", &game.description)
+        .map("localizedNames", &game.localized_names)
+        .map("localizedDescriptions", &game.localized_descriptions)
+        .message("shoe", game.shoe.as_ref().map(encode_shoe))
+        .message("burns", game.burns.as_ref().map(encode_burns))
+        .build()
+}
+
+pub(super) fn encode_parameter(p: &Parameter) -> Value {
+    Object::default()
+        .string("name", &p.name)
+        .string("description", &p.description)
+        .string("defaultValue", &p.default_value)
+        .build()
+}
+
+fn encode_phase_override(o: &PhaseOverride) -> Value {
+    Object::default()
+        .int("index", o.index)
+        .message("phase", o.phase.as_ref().map(encode_phase))
+        .build()
+}
+
+fn encode_deck(deck: &Deck) -> Value {
+    let wilds = deck
+        .wilds
+        .iter()
+        .map(|w| {
+            Object::default()
+                .string("rank", &w.rank)
+                .list("suits", strings(&w.suits))
+                .build()
+        })
+        .collect();
+    let cards = deck
+        .cards
+        .iter()
+        .map(|c| {
+            Object::default()
+                .string("rank", &c.rank)
+                .string("suit", &c.suit)
+                .int("copies", c.copies)
+                .bool("wild", c.wild)
+                .int("points", c.points)
+                .build()
+        })
+        .collect();
+    Object::default()
+        .list("suits", strings(&deck.suits))
+        .list("ranks", strings(&deck.ranks))
+        .int("jokers", deck.jokers)
+        .list("wilds", wilds)
+        .list("cards", cards)
+        .enumeration("jokerRule", deck.joker_rule, JokerRule::name)
+        .build()
+}
+
+fn encode_phase(p: &Phase) -> Value {
+    let obj = Object::default();
+    let obj = match &p.phase_type {
+        Some(phase::PhaseType::PlayerDeal(deal)) => obj.field(
+            "playerDeal",
+            Object::default()
+                .int("cards", deal.cards)
+                .bool("faceUp", deal.face_up)
+                .list(
+                    "faceUpCards",
+                    deal.face_up_cards.iter().map(|&b| Value::Bool(b)).collect(),
+                )
+                .int("burn", deal.burn)
+                .build(),
+        ),
+        Some(phase::PhaseType::CommunityDeal(deal)) => obj.field(
+            "communityDeal",
+            Object::default()
+                .string("name", &deal.name)
+                .int("cards", deal.cards)
+                .int("boardIdx", deal.board_idx)
+                .int("burn", deal.burn)
+                .bool("faceDown", deal.face_down)
+                .build(),
+        ),
+        Some(phase::PhaseType::BettingRound(round)) => {
+            let bring_in = round.bring_in.as_ref().map(|b| {
+                Object::default()
+                    .enumeration("starter", b.starter, Starter::name)
+                    .int("amount", b.amount)
+                    .bool("allowComplete", b.allow_complete)
+                    .build()
+            });
+            obj.field(
+                "bettingRound",
+                Object::default()
+                    .string("name", &round.name)
+                    .int("minBet", round.min_bet)
+                    .enumeration("order", round.order, BettingOrder::name)
+                    .message("bringIn", bring_in)
+                    .build(),
+            )
+        }
+        Some(phase::PhaseType::Exchange(exchange)) => obj.field(
+            "exchange",
+            Object::default()
+                .int("maxExchange", exchange.max_exchange)
+                .bool("lockFaceup", exchange.lock_faceup)
+                .bool("lockFacedown", exchange.lock_facedown)
+                .enumeration("drawSource", exchange.draw_source, DrawSource::name)
+                .bool("publicDiscards", exchange.public_discards)
+                .build(),
+        ),
+        Some(phase::PhaseType::Ante(ante)) => obj.field(
+            "ante",
+            Object::default()
+                .enumeration("payer", ante.payer, Payer::name)
+                .build(),
+        ),
+        Some(phase::PhaseType::PassCards(pass)) => obj.field(
+            "passCards",
+            Object::default()
+                .int("cards", pass.cards)
+                .enumeration("direction", pass.direction, Direction::name)
+                .bool("sequential", pass.sequential)
+                .build(),
+        ),
+        Some(phase::PhaseType::Reveal(reveal)) => obj.field(
+            "reveal",
+            Object::default()
+                .int("cards", reveal.cards)
+                .bool("choose", reveal.choose)
+                .bool("community", reveal.community)
+                .int("boardIdx", reveal.board_idx)
+                .build(),
+        ),
+        Some(phase::PhaseType::Declare(declare)) => obj.field(
+            "declare",
+            Object::default().bool("inTurn", declare.in_turn).build(),
+        ),
+        Some(phase::PhaseType::Buy(buy)) => obj.field(
+            "buy",
+            Object::default()
+                .int("price", buy.price)
+                .bool("replace", buy.replace)
+                .bool("faceUp", buy.face_up)
+                .build(),
+        ),
+        Some(phase::PhaseType::Repeat(repeat)) => obj.field(
+            "repeat",
+            Object::default()
+                .int("times", repeat.times)
+                .list("phases", repeat.phases.iter().map(encode_phase).collect())
+                .message("until", repeat.until.as_ref().map(encode_condition))
+                .build(),
+        ),
+        None => obj,
+    };
+    obj.message("condition", p.condition.as_ref().map(encode_condition))
+        .build()
+}
+
+fn encode_condition(c: &phase::Condition) -> Value {
+    Object::default()
+        .enumeration("measure", c.measure, Measure::name)
+        .int64("above", c.above)
+        .bool("negate", c.negate)
+        .bool("endHand", c.end_hand)
+        .build()
+}
+
+fn encode_range(range: &scoring::Range) -> Value {
+    Object::default()
+        .int("min", range.min)
+        .int("max", range.max)
+        .build()
+}
+
+fn encode_scoring(s: &Scoring) -> Value {
+    let mut obj = Object::default().string("name", &s.name);
+    obj = match &s.ranking {
+        Some(scoring::Ranking::StandardRanking(r)) => {
+            obj.field("standardRanking", enum_value(*r, HandRanking::name))
+        }
+        Some(scoring::Ranking::CustomHandOrder(order)) => obj.field(
+            "customHandOrder",
+            Object::default()
+                .list(
+                    "handTypes",
+                    order
+                        .hand_types
+                        .iter()
+                        .map(|&t| enum_value(t, HandType::name))
+                        .collect(),
+                )
+                .build(),
+        ),
+        Some(scoring::Ranking::PointsExpression(e)) => obj.string("pointsExpression", e),
+        None => obj,
+    };
+    obj.bool(
+        "topCardCanFormLowStraight",
+        s.top_card_can_form_low_straight,
+    )
+    .bool("wraparoundStraight", s.wraparound_straight)
+    .bool("lo", s.lo)
+    .string("loQualifier", &s.lo_qualifier)
+    .bool("topCardCanBeLow", s.top_card_can_be_low)
+    .message("handCards", s.hand_cards.as_ref().map(encode_range))
+    .list(
+        "communityCards",
+        s.community_cards.iter().map(encode_range).collect(),
+    )
+    .bool("naturalWilds", s.natural_wilds)
+    .build()
+}
+
+fn encode_rake(rake: &RakeConfig) -> Value {
+    Object::default()
+        .int("basisPoints", rake.basis_points)
+        .int64("cap", rake.cap)
+        .bool("noFlopNoDrop", rake.no_flop_no_drop)
+        .build()
+}
+
+fn encode_burns(burns: &BurnPolicy) -> Value {
+    Object::default()
+        .enumeration("mode", burns.mode, burn_policy::Mode::name)
+        .int("cards", burns.cards)
+        .build()
+}
+
+fn encode_shoe(shoe: &ShoeConfig) -> Value {
+    Object::default()
+        .int("decks", shoe.decks)
+        .int("penetration", shoe.penetration)
+        .build()
+}
+
+fn encode_dealing_errors(rules: &DealingErrors) -> Value {
+    Object::default()
+        .enumeration("exposedCard", rules.exposed_card, ExposedCard::name)
+        .int("maxExposed", rules.max_exposed)
+        .build()
+}
+
+/// A string. Numbers are accepted too, so that YAML documents can leave
+/// ranks like 10 unquoted.
+fn string(value: &Value, path: &str) -> Result<String> {
+    match value {
+        Value::Number(n) => Ok(n.to_string()),
+        _ => protojson::string(value, path),
+    }
+}
+
+fn string_list(value: &Value, path: &str) -> Result<Vec<String>> {
+    items(value, path)?
+        .into_iter()
+        .map(|(v, p)| string(v, &p))
+        .collect()
+}
+
+/// A map with string values. Unlike message fields, the keys are kept as
+/// written.
+fn string_map(value: &Value, path: &str) -> Result<HashMap<String, String>> {
+    let Value::Object(entries) = value else {
+        return error(path, "expected an object");
+    };
+    let mut map = HashMap::new();
+    for (key, v) in entries {
+        let path = format!("{}.{}", path, key);
+        if map.insert(key.clone(), string(v, &path)?).is_some() {
+            return error(&path, "field given more than once");
+        }
+    }
+    Ok(map)
+}
+
+fn enumeration<E: ProtoEnum>(value: &Value, path: &str) -> Result<i32> {
+    protojson::enumeration(value, path, E::value)
+}
+
+/// Reads a game from its JSON form.
+pub fn decode(value: &Value) -> Result<GameStructure> {
+    let mut game = GameStructure::default();
+    for (key, v, path) in fields(value, "")? {
+        match key.as_str() {
+            "id" => game.id = string(v, &path)?,
+            "name" => game.name = string(v, &path)?,
+            "standard_deck" => set_oneof(
+                &mut game.deck,
+                game_structure::Deck::StandardDeck(enumeration::<StandardDeck>(v, &path)?),
+                &path,
+            )?,
+            "custom_deck" => set_oneof(
+                &mut game.deck,
+                game_structure::Deck::CustomDeck(decode_deck(v, &path)?),
+                &path,
+            )?,
+            "phases" => {
+                game.phases = items(v, &path)?
+                    .into_iter()
+                    .map(|(v, p)| decode_phase(v, &p))
+                    .collect::<Result<_>>()?
+            }
+            "community_board_count" => game.community_board_count = int(v, &path)?,
+            "scorings" => {
+                game.scorings = items(v, &path)?
+                    .into_iter()
+                    .map(|(v, p)| decode_scoring(v, &p))
+                    .collect::<Result<_>>()?
+            }
+            "all_in_runouts" => game.all_in_runouts = int(v, &path)?,
+            "rake" => game.rake = Some(decode_rake(v, &path)?),
+            "dealing_errors" => game.dealing_errors = Some(decode_dealing_errors(v, &path)?),
+            "version" => game.version = int(v, &path)?,
+            "extends" => game.extends = string(v, &path)?,
+            "phase_overrides" => {
+                game.phase_overrides = items(v, &path)?
+                    .into_iter()
+                    .map(|(v, p)| decode_phase_override(v, &p))
+                    .collect::<Result<_>>()?
+            }
+            "parameters" => game.parameters = decode_parameters(v, &path)?,
+            "description" => game.description = string(v, &path)?,
+            "localized_names" => game.localized_names = string_map(v, &path)?,
+            "localized_descriptions" => game.localized_descriptions = string_map(v, &path)?,
+            "shoe" => game.shoe = Some(decode_shoe(v, &path)?),
+            "burns" => game.burns = Some(decode_burns(v, &path)?),
+            _ => return unknown(&path),
+        }
+    }
+    Ok(game)
+}
+
+pub(super) fn decode_parameters(value: &Value, path: &str) -> Result<Vec<Parameter>> {
+    items(value, path)?
+        .into_iter()
+        .map(|(v, path)| {
+            let mut p = Parameter::default();
+            for (key, v, path) in fields(v, &path)? {
+                match key.as_str() {
+                    "name" => p.name = string(v, &path)?,
+                    "description" => p.description = string(v, &path)?,
+                    "default_value" => p.default_value = string(v, &path)?,
+                    _ => return unknown(&path),
+                }
+            }
+            Ok(p)
+        })
+        .collect()
+}
+
+fn decode_phase_override(value: &Value, path: &str) -> Result<PhaseOverride> {
+    let mut o = PhaseOverride::default();
+    for (key, v, path) in fields(value, path)? {
+        match key.as_str() {
+            "index" => o.index = int(v, &path)?,
+            "phase" => o.phase = Some(decode_phase(v, &path)?),
+            _ => return unknown(&path),
+        }
+    }
+    Ok(o)
+}
+
+fn decode_deck(value: &Value, path: &str) -> Result<Deck> {
+    let mut d = Deck::default();
+    for (key, v, path) in fields(value, path)? {
+        match key.as_str() {
+            "suits" => d.suits = string_list(v, &path)?,
+            "ranks" => d.ranks = string_list(v, &path)?,
+            "jokers" => d.jokers = int(v, &path)?,
+            "wilds" => {
+                for (v, path) in items(v, &path)? {
+                    let mut wild = deck::Wild::default();
+                    for (key, v, path) in fields(v, &path)? {
+                        match key.as_str() {
+                            "rank" => wild.rank = string(v, &path)?,
+                            "suits" => wild.suits = string_list(v, &path)?,
+                            _ => return unknown(&path),
+                        }
+                    }
+                    d.wilds.push(wild);
+                }
+            }
+            "cards" => {
+                for (v, path) in items(v, &path)? {
+                    let mut card = deck::CardSpec::default();
+                    for (key, v, path) in fields(v, &path)? {
+                        match key.as_str() {
+                            "rank" => card.rank = string(v, &path)?,
+                            "suit" => card.suit = string(v, &path)?,
+                            "copies" => card.copies = int(v, &path)?,
+                            "wild" => card.wild = boolean(v, &path)?,
+                            "points" => card.points = int(v, &path)?,
+                            _ => return unknown(&path),
+                        }
+                    }
+                    d.cards.push(card);
+                }
+            }
+            "joker_rule" => d.joker_rule = enumeration::<JokerRule>(v, &path)?,
+            _ => return unknown(&path),
+        }
+    }
+    Ok(d)
+}
+
+fn decode_phase(value: &Value, path: &str) -> Result<Phase> {
+    let mut p = Phase::default();
+    for (key, v, path) in fields(value, path)? {
+        let phase_type = match key.as_str() {
+            "condition" => {
+                p.condition = Some(decode_condition(v, &path)?);
+                continue;
+            }
+            "player_deal" => {
+                let mut deal = phase::PlayerDeal::default();
+                for (key, v, path) in fields(v, &path)? {
+                    match key.as_str() {
+                        "cards" => deal.cards = int(v, &path)?,
+                        "face_up" => deal.face_up = boolean(v, &path)?,
+                        "face_up_cards" => {
+                            deal.face_up_cards = items(v, &path)?
+                                .into_iter()
+                                .map(|(v, p)| boolean(v, &p))
+                                .collect::<Result<_>>()?
+                        }
+                        "burn" => deal.burn = int(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                phase::PhaseType::PlayerDeal(deal)
+            }
+            "community_deal" => {
+                let mut deal = phase::CommunityDeal::default();
+                for (key, v, path) in fields(v, &path)? {
+                    match key.as_str() {
+                        "name" => deal.name = string(v, &path)?,
+                        "cards" => deal.cards = int(v, &path)?,
+                        "board_idx" => deal.board_idx = int(v, &path)?,
+                        "burn" => deal.burn = int(v, &path)?,
+                        "face_down" => deal.face_down = boolean(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                phase::PhaseType::CommunityDeal(deal)
+            }
+            "betting_round" => phase::PhaseType::BettingRound(decode_betting_round(v, &path)?),
+            "exchange" => {
+                let mut exchange = phase::Exchange::default();
+                for (key, v, path) in fields(v, &path)? {
+                    match key.as_str() {
+                        "max_exchange" => exchange.max_exchange = int(v, &path)?,
+                        "lock_faceup" => exchange.lock_faceup = boolean(v, &path)?,
+                        "lock_facedown" => exchange.lock_facedown = boolean(v, &path)?,
+                        "draw_source" => {
+                            exchange.draw_source = enumeration::<DrawSource>(v, &path)?
+                        }
+                        "public_discards" => exchange.public_discards = boolean(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                phase::PhaseType::Exchange(exchange)
+            }
+            "ante" => {
+                let mut ante = phase::Ante::default();
+                for (key, v, path) in fields(v, &path)? {
+                    match key.as_str() {
+                        "payer" => ante.payer = enumeration::<Payer>(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                phase::PhaseType::Ante(ante)
+            }
+            "pass_cards" => {
+                let mut pass = phase::PassCards::default();
+                for (key, v, path) in fields(v, &path)? {
+                    match key.as_str() {
+                        "cards" => pass.cards = int(v, &path)?,
+                        "direction" => pass.direction = enumeration::<Direction>(v, &path)?,
+                        "sequential" => pass.sequential = boolean(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                phase::PhaseType::PassCards(pass)
+            }
+            "reveal" => {
+                let mut reveal = phase::Reveal::default();
+                for (key, v, path) in fields(v, &path)? {
+                    match key.as_str() {
+                        "cards" => reveal.cards = int(v, &path)?,
+                        "choose" => reveal.choose = boolean(v, &path)?,
+                        "community" => reveal.community = boolean(v, &path)?,
+                        "board_idx" => reveal.board_idx = int(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                phase::PhaseType::Reveal(reveal)
+            }
+            "declare" => {
+                let mut declare = phase::Declare::default();
+                for (key, v, path) in fields(v, &path)? {
+                    match key.as_str() {
+                        "in_turn" => declare.in_turn = boolean(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                phase::PhaseType::Declare(declare)
+            }
+            "buy" => {
+                let mut buy = phase::Buy::default();
+                for (key, v, path) in fields(v, &path)? {
+                    match key.as_str() {
+                        "price" => buy.price = int(v, &path)?,
+                        "replace" => buy.replace = boolean(v, &path)?,
+                        "face_up" => buy.face_up = boolean(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                phase::PhaseType::Buy(buy)
+            }
+            "repeat" => {
+                let mut repeat = phase::Repeat::default();
+                for (key, v, path) in fields(v, &path)? {
+                    match key.as_str() {
+                        "times" => repeat.times = int(v, &path)?,
+                        "phases" => {
+                            repeat.phases = items(v, &path)?
+                                .into_iter()
+                                .map(|(v, p)| decode_phase(v, &p))
+                                .collect::<Result<_>>()?
+                        }
+                        "until" => repeat.until = Some(decode_condition(v, &path)?),
+                        _ => return unknown(&path),
+                    }
+                }
+                phase::PhaseType::Repeat(repeat)
+            }
+            _ => return unknown(&path),
+        };
+        set_oneof(&mut p.phase_type, phase_type, &path)?;
+    }
+    Ok(p)
+}
+
+fn decode_condition(value: &Value, path: &str) -> Result<phase::Condition> {
+    let mut c = phase::Condition::default();
+    for (key, v, path) in fields(value, path)? {
+        match key.as_str() {
+            "measure" => c.measure = enumeration::<Measure>(v, &path)?,
+            "above" => c.above = int64(v, &path)?,
+            "negate" => c.negate = boolean(v, &path)?,
+            "end_hand" => c.end_hand = boolean(v, &path)?,
+            _ => return unknown(&path),
+        }
+    }
+    Ok(c)
+}
+
+fn decode_betting_round(value: &Value, path: &str) -> Result<phase::BettingRound> {
+    let mut round = phase::BettingRound::default();
+    for (key, v, path) in fields(value, path)? {
+        match key.as_str() {
+            "name" => round.name = string(v, &path)?,
+            "min_bet" => round.min_bet = int(v, &path)?,
+            "order" => round.order = enumeration::<BettingOrder>(v, &path)?,
+            "bring_in" => {
+                let mut bring_in = phase::betting_round::BringIn::default();
+                for (key, v, path) in fields(v, &path)? {
+                    match key.as_str() {
+                        "starter" => bring_in.starter = enumeration::<Starter>(v, &path)?,
+                        "amount" => bring_in.amount = int(v, &path)?,
+                        "allow_complete" => bring_in.allow_complete = boolean(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                round.bring_in = Some(bring_in);
+            }
+            _ => return unknown(&path),
+        }
+    }
+    Ok(round)
+}
+
+fn decode_range(value: &Value, path: &str) -> Result<scoring::Range> {
+    let mut range = scoring::Range::default();
+    for (key, v, path) in fields(value, path)? {
+        match key.as_str() {
+            "min" => range.min = int(v, &path)?,
+            "max" => range.max = int(v, &path)?,
+            _ => return unknown(&path),
+        }
+    }
+    Ok(range)
+}
+
+fn decode_scoring(value: &Value, path: &str) -> Result<Scoring> {
+    let mut s = Scoring::default();
+    for (key, v, path) in fields(value, path)? {
+        match key.as_str() {
+            "name" => s.name = string(v, &path)?,
+            "standard_ranking" => set_oneof(
+                &mut s.ranking,
+                scoring::Ranking::StandardRanking(enumeration::<HandRanking>(v, &path)?),
+                &path,
+            )?,
+            "custom_hand_order" => {
+                let mut order = scoring::CustomHandOrder::default();
+                for (key, v, path) in fields(v, &path)? {
+                    match key.as_str() {
+                        "hand_types" => {
+                            order.hand_types = items(v, &path)?
+                                .into_iter()
+                                .map(|(v, p)| enumeration::<HandType>(v, &p))
+                                .collect::<Result<_>>()?
+                        }
+                        _ => return unknown(&path),
+                    }
+                }
+                set_oneof(
+                    &mut s.ranking,
+                    scoring::Ranking::CustomHandOrder(order),
+                    &path,
+                )?
+            }
+            "points_expression" => set_oneof(
+                &mut s.ranking,
+                scoring::Ranking::PointsExpression(string(v, &path)?),
+                &path,
+            )?,
+            "top_card_can_form_low_straight" => {
+                s.top_card_can_form_low_straight = boolean(v, &path)?
+            }
+            "wraparound_straight" => s.wraparound_straight = boolean(v, &path)?,
+            "lo" => s.lo = boolean(v, &path)?,
+            "lo_qualifier" => s.lo_qualifier = string(v, &path)?,
+            "top_card_can_be_low" => s.top_card_can_be_low = boolean(v, &path)?,
+            "hand_cards" => s.hand_cards = Some(decode_range(v, &path)?),
+            "community_cards" => {
+                s.community_cards = items(v, &path)?
+                    .into_iter()
+                    .map(|(v, p)| decode_range(v, &p))
+                    .collect::<Result<_>>()?
+            }
+            "natural_wilds" => s.natural_wilds = boolean(v, &path)?,
+            _ => return unknown(&path),
+        }
+    }
+    Ok(s)
+}
+
+fn decode_rake(value: &Value, path: &str) -> Result<RakeConfig> {
+    let mut rake = RakeConfig::default();
+    for (key, v, path) in fields(value, path)? {
+        match key.as_str() {
+            "basis_points" => rake.basis_points = int(v, &path)?,
+            "cap" => rake.cap = int64(v, &path)?,
+            "no_flop_no_drop" => rake.no_flop_no_drop = boolean(v, &path)?,
+            _ => return unknown(&path),
+        }
+    }
+    Ok(rake)
+}
+
+fn decode_burns(value: &Value, path: &str) -> Result<BurnPolicy> {
+    let mut burns = BurnPolicy::default();
+    for (key, v, path) in fields(value, path)? {
+        match key.as_str() {
+            "mode" => burns.mode = enumeration::<burn_policy::Mode>(v, &path)?,
+            "cards" => burns.cards = int(v, &path)?,
+            _ => return unknown(&path),
+        }
+    }
+    Ok(burns)
+}
+
+fn decode_shoe(value: &Value, path: &str) -> Result<ShoeConfig> {
+    let mut shoe = ShoeConfig::default();
+    for (key, v, path) in fields(value, path)? {
+        match key.as_str() {
+            "decks" => shoe.decks = int(v, &path)?,
+            "penetration" => shoe.penetration = int(v, &path)?,
+            _ => return unknown(&path),
+        }
+    }
+    Ok(shoe)
+}
+
+fn decode_dealing_errors(value: &Value, path: &str) -> Result<DealingErrors> {
+    let mut rules = DealingErrors::default();
+    for (key, v, path) in fields(value, path)? {
+        match key.as_str() {
+            "exposed_card" => rules.exposed_card = enumeration::<ExposedCard>(v, &path)?,
+            "max_exposed" => rules.max_exposed = int(v, &path)?,
+            _ => return unknown(&path),
+        }
+    }
+    Ok(rules)
+}
@@ -0,0 +1,231 @@
+//! Field-level differences between two games, for reviewing edits.
+//!
+//! Games are compared through their canonical JSON form, so changes are
+//! reported with the same paths and values that a saved definition uses.
+//! Fields left at their defaults are absent in that form, so setting one
+//! shows up as an addition and clearing one as a removal.
+
+use std::fmt;
+
+use super::codec;
+use super::json::Value;
+use crate::GameStructure;
+
+/// How one value differs.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChangeKind {
+    Added(Value),
+    Removed(Value),
+    Changed { from: Value, to: Value },
+}
+
+/// One difference, located by a path such as `phases[2].bettingRound.minBet`.
+/// Indexes into repeated fields count from the old game for removals and
+/// from the new game otherwise.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    pub path: String,
+    pub kind: ChangeKind,
+}
+
+/// The differences between two games, in document order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GameDiff {
+    pub changes: Vec<Change>,
+}
+
+impl GameDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// One line per change: `+` for additions, `-` for removals and `~` for
+/// changed values.
+impl fmt::Display for GameDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            match &change.kind {
+                ChangeKind::Added(v) => writeln!(f, "+ {}: {}", change.path, v)?,
+                ChangeKind::Removed(v) => writeln!(f, "- {}: {}", change.path, v)?,
+                ChangeKind::Changed { from, to } => {
+                    writeln!(f, "~ {}: {} -> {}", change.path, from, to)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What changed going from `a` to `b`.
+pub fn diff(a: &GameStructure, b: &GameStructure) -> GameDiff {
+    let mut out = GameDiff::default();
+    compare(&codec::encode(a), &codec::encode(b), "", &mut out.changes);
+    out
+}
+
+fn child(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn compare(a: &Value, b: &Value, path: &str, out: &mut Vec<Change>) {
+    if a == b {
+        return;
+    }
+    match (a, b) {
+        (Value::Object(x), Value::Object(y)) => {
+            for (key, va) in x {
+                match y.iter().find(|(k, _)| k == key) {
+                    Some((_, vb)) => compare(va, vb, &child(path, key), out),
+                    None => out.push(Change {
+                        path: child(path, key),
+                        kind: ChangeKind::Removed(va.clone()),
+                    }),
+                }
+            }
+            for (key, vb) in y {
+                if !x.iter().any(|(k, _)| k == key) {
+                    out.push(Change {
+                        path: child(path, key),
+                        kind: ChangeKind::Added(vb.clone()),
+                    });
+                }
+            }
+        }
+        (Value::Array(x), Value::Array(y)) => compare_items(x, y, path, out),
+        _ => out.push(Change {
+            path: path.to_string(),
+            kind: ChangeKind::Changed {
+                from: a.clone(),
+                to: b.clone(),
+            },
+        }),
+    }
+}
+
+/// Lines up the unchanged items of two lists, so that inserting a phase
+/// reports one addition rather than a change to every later phase. Between
+/// unchanged items, removed and added items are paired up and compared
+/// field by field.
+fn compare_items(x: &[Value], y: &[Value], path: &str, out: &mut Vec<Change>) {
+    // lcs[i][j] is the length of the longest common subsequence of x[i..]
+    // and y[j..].
+    let mut lcs = vec![vec![0usize; y.len() + 1]; x.len() + 1];
+    for i in (0..x.len()).rev() {
+        for j in (0..y.len()).rev() {
+            lcs[i][j] = if x[i] == y[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let flush = |removed: &mut Vec<usize>, added: &mut Vec<usize>, out: &mut Vec<Change>| {
+        for k in 0..removed.len().max(added.len()) {
+            let item = |index: usize| format!("{}[{}]", path, index);
+            match (removed.get(k), added.get(k)) {
+                (Some(&r), Some(&a)) => compare(&x[r], &y[a], &item(a), out),
+                (Some(&r), None) => out.push(Change {
+                    path: item(r),
+                    kind: ChangeKind::Removed(x[r].clone()),
+                }),
+                (None, Some(&a)) => out.push(Change {
+                    path: item(a),
+                    kind: ChangeKind::Added(y[a].clone()),
+                }),
+                (None, None) => unreachable!(),
+            }
+        }
+        removed.clear();
+        added.clear();
+    };
+    while i < x.len() || j < y.len() {
+        if i < x.len() && j < y.len() && x[i] == y[j] {
+            flush(&mut removed, &mut added, out);
+            i += 1;
+            j += 1;
+        } else if j == y.len() || (i < x.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            removed.push(i);
+            i += 1;
+        } else {
+            added.push(j);
+            j += 1;
+        }
+    }
+    flush(&mut removed, &mut added, out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GameBuilder;
+    use crate::gamedef::json;
+
+    fn holdem() -> GameBuilder {
+        GameBuilder::new("holdem")
+            .deal_hole(2)
+            .betting("Pre-flop")
+            .community(3)
+            .betting("Flop")
+    }
+
+    #[test]
+    fn test_diff() {
+        let a = holdem().build().unwrap();
+        assert!(diff(&a, &a).is_empty());
+
+        let b = holdem()
+            .short_deck()
+            .community(1)
+            .betting("Turn")
+            .build()
+            .unwrap();
+        assert_eq!(
+            diff(&a, &b).to_string(),
+            "~ standardDeck: \"DECK_POKER\" -> \"DECK_SHORT_DECK\"\n\
+             + phases[4]: {\"communityDeal\":{\"cards\":1}}\n\
+             + phases[5]: {\"bettingRound\":{\"name\":\"Turn\",\"minBet\":1,\"order\":\"BETTING_ORDER_LEFT_OF_DEALER\"}}\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_changed_phase() {
+        let a = holdem().build().unwrap();
+        let mut b = a.clone();
+        b.phases.remove(0);
+        if let Some(crate::phase::PhaseType::BettingRound(r)) = &mut b.phases[2].phase_type {
+            r.min_bet = 5;
+        }
+        b.name = String::new();
+        let changes = diff(&a, &b).changes;
+        assert_eq!(
+            changes,
+            vec![
+                Change {
+                    path: "name".to_string(),
+                    kind: ChangeKind::Removed(Value::String("holdem".to_string())),
+                },
+                Change {
+                    path: "phases[0]".to_string(),
+                    kind: ChangeKind::Removed(
+                        json::parse(r#"{"playerDeal": {"cards": 2}}"#).unwrap()
+                    ),
+                },
+                Change {
+                    path: "phases[2].bettingRound.minBet".to_string(),
+                    kind: ChangeKind::Changed {
+                        from: Value::Number(1.0),
+                        to: Value::Number(5.0),
+                    },
+                },
+            ]
+        );
+    }
+}
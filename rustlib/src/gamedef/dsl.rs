@@ -0,0 +1,1384 @@
+//! A compact text form for game definitions, one statement per line (or
+//! separated by `;`), with `#` comments:
+//!
+//! ```text
+//! id "holdem"; name "Texas Hold'em"; deck standard
+//! deal 2 down
+//! bet "Pre-flop" min 1 follow-blinds
+//! board 3 "Flop"; bet "Flop" min 1 left-of-dealer
+//! score standard
+//! ```
+//!
+//! Statements:
+//!
+//! - `id "…"`, `name [LOCALE] "…"`, `description [LOCALE] "…"`, where
+//!   LOCALE is a language tag such as `de` or `pt-BR`
+//! - `deck standard`, `deck short`, or
+//!   `deck [ranks R…] [suits S…] [jokers N [bug]]`, with `wild RANK [SUIT…]` and
+//!   `card RANK SUIT [copies N] [wild] [points N]` to list the cards of a
+//!   deck that isn't every rank in every suit
+//! - `shoe DECKS [penetration PERCENT]`
+//! - `burns per-deal | every-street [N] | never`
+//! - `ante [every-player | big-blind]`
+//! - `deal N [down | up] [burn B]`, or several of `N (down | up)` for a
+//!   deal that mixes the two, such as `deal 2 down 1 up`
+//! - `board N [on B] [burn K] [down] ["name"]`, and `boards N` to set the
+//!   board count
+//! - `bet ["name"] [min N] [follow-blinds | left-of-dealer | best-faceup]
+//!   [bring-in lowest | highest [amount N] [complete]]`
+//! - `draw N [lock-up] [lock-down] [reshuffle] [public]`
+//! - `pass N [left | right | across] [sequential]`
+//! - `reveal N [choose] [community [on B]]`
+//! - `declare [in-turn]`
+//! - `buy PRICE [replace] [up]`
+//! - `repeat N [until CONDITION [then showdown]]`, followed by the phases to
+//!   repeat and then `end`
+//!
+//! Any phase, and any phase in an `override`, may end with a condition,
+//! `if (players | active | pot) (> | <=) N [else showdown]`, to skip the
+//! phase (or, with `else showdown`, the rest of the hand) unless the number
+//! of players in the hand, players who can still bet, or chips in the pot
+//! passes the test.
+//! - `score ["name"] [standard | ace-five | deuce-seven | badugi |
+//!   short-deck | short-deck-high-straight | order TYPE… |
+//!   points "EXPRESSION"] [low]
+//!   [qualifier R] [top-low]
+//!   [low-straight] [wraparound] [natural-wilds] [hand RANGE]
+//!   [community RANGE…]`, where a RANGE is `N`, `N-M` or `any`
+//! - `runouts N`
+//! - `rake BASIS_POINTS [cap N] [no-flop-no-drop]`
+//! - `exposed [burn | play-on | redeal] [max N]`
+//! - `extends "BASE"`, and `override N | all PHASE` to change the fields a
+//!   phase statement sets in inherited phase N, or in every inherited phase
+//!   of that kind (see [`crate::resolve`])
+//! - `param NAME [default VALUE] ["description"]` declares a template
+//!   parameter, which any later word or string can refer to as `${NAME}`
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::json::SyntaxError;
+use super::migrations::CURRENT_VERSION;
+use super::params;
+use crate::dealing_errors::ExposedCard;
+use crate::deck::JokerRule;
+use crate::phase::ante::Payer;
+use crate::phase::betting_round::bring_in::Starter;
+use crate::phase::betting_round::{BettingOrder, BringIn};
+use crate::phase::condition::Measure;
+use crate::phase::exchange::DrawSource;
+use crate::phase::pass_cards::Direction;
+use crate::repeat;
+use crate::{
+    burn_policy, deck, game_structure, phase, scoring, BurnPolicy, DealingErrors, Deck,
+    GameStructure, HandRanking, HandType, Parameter, Phase, PhaseOverride, RakeConfig, Scoring,
+    ShoeConfig, StandardDeck,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+}
+
+#[derive(Clone, Debug)]
+struct Spanned {
+    token: Token,
+    line: usize,
+    column: usize,
+}
+
+/// Splits the source into statements of tokens.
+fn tokenize(text: &str) -> Result<Vec<Vec<Spanned>>, SyntaxError> {
+    let mut statements = vec![Vec::new()];
+    for (i, line) in text.lines().enumerate() {
+        let mut chars = line.char_indices().peekable();
+        while let Some(&(start, c)) = chars.peek() {
+            let column = line[..start].chars().count() + 1;
+            match c {
+                '#' => break,
+                ';' => {
+                    chars.next();
+                    statements.push(Vec::new());
+                }
+                c if c.is_whitespace() => {
+                    chars.next();
+                }
+                '"' => {
+                    chars.next();
+                    let mut s = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '"')) => break,
+                            Some((_, '\\')) => match chars.next() {
+                                Some((_, c)) => s.push(c),
+                                None => break,
+                            },
+                            Some((_, c)) => s.push(c),
+                            None => {
+                                return Err(SyntaxError {
+                                    line: i + 1,
+                                    column,
+                                    message: "unterminated string".to_string(),
+                                })
+                            }
+                        }
+                    }
+                    statements.last_mut().unwrap().push(Spanned {
+                        token: Token::Quoted(s),
+                        line: i + 1,
+                        column,
+                    });
+                }
+                _ => {
+                    let mut s = String::new();
+                    while let Some(&(_, c)) = chars.peek() {
+                        if c.is_whitespace() || c == ';' || c == '"' || c == '#' {
+                            break;
+                        }
+                        s.push(c);
+                        chars.next();
+                    }
+                    statements.last_mut().unwrap().push(Spanned {
+                        token: Token::Word(s),
+                        line: i + 1,
+                        column,
+                    });
+                }
+            }
+        }
+        statements.push(Vec::new());
+    }
+    statements.retain(|s| !s.is_empty());
+    Ok(statements)
+}
+
+/// The tokens of one statement, consumed front to back.
+struct Statement {
+    tokens: std::collections::VecDeque<Spanned>,
+    /// Where the statement ends, for errors about missing arguments.
+    end: (usize, usize),
+}
+
+impl Statement {
+    fn new(tokens: Vec<Spanned>) -> Self {
+        let last = tokens.last().expect("statements are never empty");
+        Statement {
+            end: (last.line, last.column + 1),
+            tokens: tokens.into(),
+        }
+    }
+
+    fn error<T>(&self, at: Option<&Spanned>, message: String) -> Result<T, SyntaxError> {
+        let (line, column) = at.map_or(self.end, |t| (t.line, t.column));
+        Err(SyntaxError {
+            line,
+            column,
+            message,
+        })
+    }
+
+    fn peek_word(&self) -> Option<&str> {
+        match self.tokens.front().map(|t| &t.token) {
+            Some(Token::Word(w)) => Some(w),
+            _ => None,
+        }
+    }
+
+    /// Consumes `word` if it comes next.
+    fn eat(&mut self, word: &str) -> bool {
+        if self.peek_word() == Some(word) {
+            self.tokens.pop_front();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn word(&mut self, what: &str) -> Result<String, SyntaxError> {
+        match self.tokens.pop_front() {
+            Some(Spanned {
+                token: Token::Word(w),
+                ..
+            }) => Ok(w),
+            other => self.error(other.as_ref(), format!("expected {}", what)),
+        }
+    }
+
+    /// A bare word or a quoted string.
+    fn text(&mut self, what: &str) -> Result<String, SyntaxError> {
+        match self.tokens.pop_front() {
+            Some(Spanned {
+                token: Token::Word(w) | Token::Quoted(w),
+                ..
+            }) => Ok(w),
+            None => self.error(None, format!("expected {}", what)),
+        }
+    }
+
+    fn quoted(&mut self) -> Option<String> {
+        match self.tokens.front().map(|t| &t.token) {
+            Some(Token::Quoted(_)) => match self.tokens.pop_front().map(|t| t.token) {
+                Some(Token::Quoted(s)) => Some(s),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn int(&mut self, what: &str) -> Result<i32, SyntaxError> {
+        let token = self.tokens.front().cloned();
+        let word = self.word(what)?;
+        match word.parse() {
+            Ok(n) => Ok(n),
+            Err(_) => self.error(token.as_ref(), format!("expected {}", what)),
+        }
+    }
+
+    /// Words up to the next keyword in `stops`.
+    fn words_until(&mut self, stops: &[&str]) -> Vec<String> {
+        let mut words = Vec::new();
+        while let Some(w) = self.peek_word() {
+            if stops.contains(&w) {
+                break;
+            }
+            words.push(w.to_string());
+            self.tokens.pop_front();
+        }
+        words
+    }
+
+    fn range(&mut self) -> Result<scoring::Range, SyntaxError> {
+        let token = self.tokens.front().cloned();
+        let word = self.word("a card range")?;
+        if word == "any" {
+            return Ok(scoring::Range { min: -1, max: -1 });
+        }
+        let (min, max) = word.split_once('-').unwrap_or((&word, &word));
+        match (min.parse(), max.parse()) {
+            (Ok(min), Ok(max)) => Ok(scoring::Range { min, max }),
+            _ => self.error(token.as_ref(), format!("invalid card range {:?}", word)),
+        }
+    }
+
+    /// Fails if anything is left over.
+    fn finish(&self) -> Result<(), SyntaxError> {
+        match self.tokens.front() {
+            None => Ok(()),
+            Some(t) => {
+                let shown = match &t.token {
+                    Token::Word(w) => w.clone(),
+                    Token::Quoted(s) => format!("{:?}", s),
+                };
+                self.error(Some(t), format!("unexpected {}", shown))
+            }
+        }
+    }
+}
+
+fn phase(phase_type: phase::PhaseType) -> Phase {
+    Phase {
+        phase_type: Some(phase_type),
+        ..Default::default()
+    }
+}
+
+/// The phase described by the rest of a statement starting with `keyword`,
+/// with its condition if it has one.
+fn parse_conditional(keyword: &str, st: &mut Statement) -> Result<Option<Phase>, SyntaxError> {
+    let Some(phase_type) = parse_phase(keyword, st)? else {
+        return Ok(None);
+    };
+    let mut p = phase(phase_type);
+    if st.eat("if") {
+        p.condition = Some(parse_condition(st, "else")?);
+    }
+    Ok(Some(p))
+}
+
+/// `MEASURE (> | <=) N`, then `showdown` after `follow` if the hand should
+/// end there.
+fn parse_condition(st: &mut Statement, follow: &str) -> Result<phase::Condition, SyntaxError> {
+    let at = st.tokens.front().cloned();
+    let measure = match st.word("players, active or pot")?.as_str() {
+        "players" => Measure::Players,
+        "active" => Measure::ActivePlayers,
+        "pot" => Measure::Pot,
+        other => return st.error(at.as_ref(), format!("unknown measure {:?}", other)),
+    };
+    let at = st.tokens.front().cloned();
+    let negate = match st.word("> or <=")?.as_str() {
+        ">" => false,
+        "<=" => true,
+        other => return st.error(at.as_ref(), format!("expected > or <=, not {:?}", other)),
+    };
+    let above = st.int("a number")?.into();
+    let end_hand = st.eat(follow);
+    let at = st.tokens.front().cloned();
+    if end_hand && !st.eat("showdown") {
+        return st.error(at.as_ref(), format!("expected showdown after {}", follow));
+    }
+    Ok(phase::Condition {
+        measure: measure.into(),
+        above,
+        negate,
+        end_hand,
+    })
+}
+
+/// Compiles DSL source into a game.
+pub fn parse(text: &str) -> Result<GameStructure, SyntaxError> {
+    parse_with(text, &[])
+}
+
+/// As [`parse`], with values for some of the `param`s. References to them
+/// are filled in before the statements are read, so they can stand for any
+/// word or string.
+pub fn parse_with(text: &str, args: &[(&str, &str)]) -> Result<GameStructure, SyntaxError> {
+    let mut game = GameStructure {
+        version: CURRENT_VERSION,
+        ..Default::default()
+    };
+    let is_param = |tokens: &Vec<Spanned>| tokens[0].token == Token::Word("param".to_string());
+    let (declarations, statements): (Vec<_>, Vec<_>) =
+        tokenize(text)?.into_iter().partition(is_param);
+    let at = declarations
+        .first()
+        .map_or((1, 1), |t| (t[0].line, t[0].column));
+    for tokens in declarations {
+        let mut st = Statement::new(tokens);
+        st.word("param")?;
+        game.parameters.push(parse_param(&mut st)?);
+        st.finish()?;
+    }
+    let values = params::bind(&mut game.parameters, args).map_err(|message| SyntaxError {
+        line: at.0,
+        column: at.1,
+        message,
+    })?;
+    let mut boards: Option<i32> = None;
+    // Repeats whose `end` hasn't been reached, innermost last.
+    let mut open: Vec<(Phase, Spanned)> = Vec::new();
+    for mut tokens in statements {
+        for t in &mut tokens {
+            let (Token::Word(s) | Token::Quoted(s)) = &mut t.token;
+            match params::expand(s, &values) {
+                Ok(Some((expanded, _))) => *s = expanded,
+                Ok(None) => {}
+                Err(message) => {
+                    return Err(SyntaxError {
+                        line: t.line,
+                        column: t.column,
+                        message,
+                    })
+                }
+            }
+        }
+        let first = tokens[0].clone();
+        let mut st = Statement::new(tokens);
+        let keyword = st.word("a statement")?;
+        match keyword.as_str() {
+            "id" => game.id = st.text("an id")?,
+            "name" => parse_localized(&mut st, &mut game.name, &mut game.localized_names)?,
+            "description" => parse_localized(
+                &mut st,
+                &mut game.description,
+                &mut game.localized_descriptions,
+            )?,
+            "deck" => parse_deck(&mut st, &mut game)?,
+            "shoe" => {
+                let decks = st.int("a deck count")?;
+                let penetration = if st.eat("penetration") {
+                    st.int("a percentage")?
+                } else {
+                    0
+                };
+                game.shoe = Some(ShoeConfig { decks, penetration });
+            }
+            "burns" => {
+                let at = st.tokens.front().cloned();
+                let mode = match st.word("per-deal, every-street or never")?.as_str() {
+                    "per-deal" => burn_policy::Mode::PerDeal,
+                    "every-street" => burn_policy::Mode::EveryStreet,
+                    "never" => burn_policy::Mode::Never,
+                    other => {
+                        return st.error(at.as_ref(), format!("unknown burn mode {:?}", other))
+                    }
+                };
+                let cards = if mode == burn_policy::Mode::EveryStreet && st.peek_word().is_some() {
+                    st.int("a card count")?
+                } else {
+                    0
+                };
+                game.burns = Some(BurnPolicy {
+                    mode: mode.into(),
+                    cards,
+                });
+            }
+            "wild" => {
+                let rank = st.text("a rank")?;
+                let suits = st.words_until(&[]);
+                let wild = deck::Wild { rank, suits };
+                match &mut game.deck {
+                    Some(game_structure::Deck::CustomDeck(d)) => d.wilds.push(wild),
+                    _ => {
+                        return st.error(Some(&first), "wild cards need a custom deck".to_string())
+                    }
+                }
+            }
+            "card" => {
+                let mut card = deck::CardSpec {
+                    rank: st.text("a rank")?,
+                    suit: st.text("a suit")?,
+                    ..Default::default()
+                };
+                loop {
+                    if st.eat("copies") {
+                        card.copies = st.int("a number of copies")?;
+                    } else if st.eat("wild") {
+                        card.wild = true;
+                    } else if st.eat("points") {
+                        card.points = st.int("a point value")?;
+                    } else {
+                        break;
+                    }
+                }
+                match &mut game.deck {
+                    Some(game_structure::Deck::CustomDeck(d)) => d.cards.push(card),
+                    _ => return st.error(Some(&first), "cards need a custom deck".to_string()),
+                }
+            }
+            "extends" => game.extends = st.text("a base game")?,
+            "override" => {
+                let index = if st.eat("all") {
+                    -1
+                } else {
+                    st.int("a phase number or \"all\"")?
+                };
+                let at = st.tokens.front().cloned();
+                let keyword = st.word("a phase")?;
+                let Some(phase) = parse_conditional(&keyword, &mut st)? else {
+                    return st.error(at.as_ref(), format!("{:?} is not a phase", keyword));
+                };
+                game.phase_overrides.push(PhaseOverride {
+                    index,
+                    phase: Some(phase),
+                });
+            }
+            "boards" => boards = Some(st.int("a board count")?),
+            "score" => game.scorings.push(parse_score(&mut st)?),
+            "runouts" => game.all_in_runouts = st.int("a runout count")?,
+            "rake" => {
+                let mut rake = RakeConfig {
+                    basis_points: st.int("basis points")?,
+                    ..Default::default()
+                };
+                loop {
+                    if st.eat("cap") {
+                        rake.cap = st.int("a cap")?.into();
+                    } else if st.eat("no-flop-no-drop") {
+                        rake.no_flop_no_drop = true;
+                    } else {
+                        break;
+                    }
+                }
+                game.rake = Some(rake);
+            }
+            "exposed" => {
+                let policy = if st.eat("burn") {
+                    ExposedCard::Burn
+                } else if st.eat("play-on") {
+                    ExposedCard::PlayOn
+                } else if st.eat("redeal") {
+                    ExposedCard::Redeal
+                } else {
+                    ExposedCard::Unknown
+                };
+                let max_exposed = if st.eat("max") {
+                    st.int("a card count")?
+                } else {
+                    0
+                };
+                game.dealing_errors = Some(DealingErrors {
+                    exposed_card: policy.into(),
+                    max_exposed,
+                });
+            }
+            "end" => match open.pop() {
+                Some((repeat, _)) => add_phase(&mut game, &mut open, repeat),
+                None => return st.error(Some(&first), "end without a repeat".to_string()),
+            },
+            other => match parse_conditional(other, &mut st)? {
+                Some(p) if matches!(&p.phase_type, Some(phase::PhaseType::Repeat(_))) => {
+                    open.push((p, first.clone()))
+                }
+                Some(p) => add_phase(&mut game, &mut open, p),
+                None => return st.error(Some(&first), format!("unknown statement {:?}", other)),
+            },
+        }
+        st.finish()?;
+    }
+    if let Some((_, at)) = open.last() {
+        return Err(SyntaxError {
+            line: at.line,
+            column: at.column,
+            message: "repeat without an end".to_string(),
+        });
+    }
+    game.community_board_count = boards.unwrap_or_else(|| derived_boards(&game));
+    Ok(game)
+}
+
+/// Adds `p` to the innermost open repeat, or to the game if there is none.
+fn add_phase(game: &mut GameStructure, open: &mut [(Phase, Spanned)], p: Phase) {
+    match open.last_mut().map(|(r, _)| &mut r.phase_type) {
+        Some(Some(phase::PhaseType::Repeat(repeat))) => repeat.phases.push(p),
+        _ => game.phases.push(p),
+    }
+}
+
+/// The phase described by a statement starting with `keyword`, or `None` if
+/// it doesn't describe a phase.
+fn parse_phase(keyword: &str, st: &mut Statement) -> Result<Option<phase::PhaseType>, SyntaxError> {
+    let phase_type = match keyword {
+        "ante" => {
+            let payer = if st.eat("big-blind") {
+                Payer::BigBlind
+            } else if st.eat("every-player") {
+                Payer::EveryPlayer
+            } else {
+                Payer::Unknown
+            };
+            phase::PhaseType::Ante(phase::Ante {
+                payer: payer.into(),
+            })
+        }
+        "pass" => {
+            let cards = st.int("a card count")?;
+            let direction = if st.eat("right") {
+                Direction::Right
+            } else if st.eat("across") {
+                Direction::Across
+            } else if st.eat("left") {
+                Direction::Left
+            } else {
+                Direction::Unknown
+            };
+            let sequential = st.eat("sequential");
+            phase::PhaseType::PassCards(phase::PassCards {
+                cards,
+                direction: direction.into(),
+                sequential,
+            })
+        }
+        "reveal" => {
+            let mut reveal = phase::Reveal {
+                cards: st.int("a card count")?,
+                choose: st.eat("choose"),
+                ..Default::default()
+            };
+            if st.eat("community") {
+                reveal.community = true;
+                if st.eat("on") {
+                    reveal.board_idx = st.int("a board number")?;
+                }
+            }
+            phase::PhaseType::Reveal(reveal)
+        }
+        "repeat" => phase::PhaseType::Repeat(phase::Repeat {
+            times: st.int("a number of times")?,
+            until: if st.eat("until") {
+                Some(parse_condition(st, "then")?)
+            } else {
+                None
+            },
+            ..Default::default()
+        }),
+        "declare" => phase::PhaseType::Declare(phase::Declare {
+            in_turn: st.eat("in-turn"),
+        }),
+        "buy" => phase::PhaseType::Buy(phase::Buy {
+            price: st.int("a price")?,
+            replace: st.eat("replace"),
+            face_up: st.eat("up"),
+        }),
+        "deal" => {
+            let mut groups = Vec::new();
+            loop {
+                let cards = st.int("a card count")?;
+                let face_up = st.eat("up");
+                if !face_up {
+                    st.eat("down");
+                }
+                groups.push((cards, face_up));
+                if st.peek_word().is_none_or(|w| w.parse::<i32>().is_err()) {
+                    break;
+                }
+            }
+            let burn = if st.eat("burn") {
+                st.int("a card count")?
+            } else {
+                0
+            };
+            match groups[..] {
+                [(cards, face_up)] => phase::PhaseType::PlayerDeal(phase::PlayerDeal {
+                    cards,
+                    face_up,
+                    burn,
+                    ..Default::default()
+                }),
+                _ => phase::PhaseType::PlayerDeal(phase::PlayerDeal {
+                    face_up_cards: groups
+                        .iter()
+                        .flat_map(|&(cards, face_up)| vec![face_up; cards.max(0) as usize])
+                        .collect(),
+                    burn,
+                    ..Default::default()
+                }),
+            }
+        }
+        "board" => {
+            let cards = st.int("a card count")?;
+            let board_idx = if st.eat("on") {
+                st.int("a board number")?
+            } else {
+                0
+            };
+            let burn = if st.eat("burn") {
+                st.int("a card count")?
+            } else {
+                0
+            };
+            let face_down = st.eat("down");
+            let name = st.quoted().unwrap_or_default();
+            phase::PhaseType::CommunityDeal(phase::CommunityDeal {
+                name,
+                cards,
+                board_idx,
+                burn,
+                face_down,
+            })
+        }
+        "bet" => phase::PhaseType::BettingRound(parse_bet(st)?),
+        "draw" => {
+            let max_exchange = st.int("a card count")?;
+            let mut exchange = phase::Exchange {
+                max_exchange,
+                ..Default::default()
+            };
+            loop {
+                if st.eat("lock-up") {
+                    exchange.lock_faceup = true;
+                } else if st.eat("lock-down") {
+                    exchange.lock_facedown = true;
+                } else if st.eat("reshuffle") {
+                    exchange.draw_source = DrawSource::ReshuffleDiscards.into();
+                } else if st.eat("public") {
+                    exchange.public_discards = true;
+                } else {
+                    break;
+                }
+            }
+            phase::PhaseType::Exchange(exchange)
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(phase_type))
+}
+
+/// One more than the highest board any phase deals to.
+fn derived_boards(game: &GameStructure) -> i32 {
+    repeat::expand(&game.phases)
+        .phases
+        .iter()
+        .filter_map(|p| match &p.phase_type {
+            Some(phase::PhaseType::CommunityDeal(d)) => Some(d.board_idx + 1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// The text of a `name` or `description` statement, which goes in `map`
+/// if it is preceded by a locale.
+fn parse_localized(
+    st: &mut Statement,
+    default: &mut String,
+    map: &mut HashMap<String, String>,
+) -> Result<(), SyntaxError> {
+    let text = st.text("some text")?;
+    if st.tokens.is_empty() {
+        *default = text;
+    } else {
+        map.insert(text, st.text("some text")?);
+    }
+    Ok(())
+}
+
+fn parse_param(st: &mut Statement) -> Result<Parameter, SyntaxError> {
+    let mut p = Parameter {
+        name: st.text("a parameter name")?,
+        ..Default::default()
+    };
+    if st.eat("default") {
+        p.default_value = st.text("a default value")?;
+    }
+    p.description = st.quoted().unwrap_or_default();
+    Ok(p)
+}
+
+fn parse_deck(st: &mut Statement, game: &mut GameStructure) -> Result<(), SyntaxError> {
+    if st.eat("standard") {
+        game.deck = Some(game_structure::Deck::StandardDeck(
+            StandardDeck::DeckPoker.into(),
+        ));
+        return Ok(());
+    }
+    if st.eat("short") {
+        game.deck = Some(game_structure::Deck::StandardDeck(
+            StandardDeck::DeckShortDeck.into(),
+        ));
+        return Ok(());
+    }
+    let mut deck = Deck::default();
+    loop {
+        if st.eat("ranks") {
+            deck.ranks = st.words_until(&["suits", "jokers"]);
+        } else if st.eat("suits") {
+            deck.suits = st.words_until(&["ranks", "jokers"]);
+        } else if st.eat("jokers") {
+            deck.jokers = st.int("a joker count")?;
+            if st.eat("bug") {
+                deck.joker_rule = JokerRule::Bug.into();
+            }
+        } else {
+            break;
+        }
+    }
+    game.deck = Some(game_structure::Deck::CustomDeck(deck));
+    Ok(())
+}
+
+fn parse_bet(st: &mut Statement) -> Result<phase::BettingRound, SyntaxError> {
+    let mut round = phase::BettingRound {
+        name: st.quoted().unwrap_or_default(),
+        ..Default::default()
+    };
+    loop {
+        if st.eat("min") {
+            round.min_bet = st.int("a minimum bet")?;
+        } else if st.eat("follow-blinds") {
+            round.order = BettingOrder::FollowBlinds.into();
+        } else if st.eat("left-of-dealer") {
+            round.order = BettingOrder::LeftOfDealer.into();
+        } else if st.eat("best-faceup") {
+            round.order = BettingOrder::BestFaceup.into();
+        } else if st.eat("bring-in") {
+            let starter = if st.eat("highest") {
+                Starter::Highest
+            } else {
+                st.eat("lowest");
+                Starter::Lowest
+            };
+            let mut bring_in = BringIn {
+                starter: starter.into(),
+                ..Default::default()
+            };
+            loop {
+                if st.eat("amount") {
+                    bring_in.amount = st.int("a bring-in amount")?;
+                } else if st.eat("complete") {
+                    bring_in.allow_complete = true;
+                } else {
+                    break;
+                }
+            }
+            round.bring_in = Some(bring_in);
+        } else {
+            break;
+        }
+    }
+    Ok(round)
+}
+
+const RANKINGS: [(&str, HandRanking); 6] = [
+    ("standard", HandRanking::RankingStandard),
+    ("ace-five", HandRanking::RankingAceFive),
+    ("deuce-seven", HandRanking::RankingDeuceSeven),
+    ("badugi", HandRanking::RankingBadugi),
+    ("short-deck", HandRanking::RankingShortDeck),
+    (
+        "short-deck-high-straight",
+        HandRanking::RankingShortDeckHighStraight,
+    ),
+];
+
+fn parse_score(st: &mut Statement) -> Result<Scoring, SyntaxError> {
+    let mut s = Scoring {
+        name: st.quoted().unwrap_or_default(),
+        ..Default::default()
+    };
+    loop {
+        if let Some(&(_, ranking)) = RANKINGS.iter().find(|(w, _)| st.peek_word() == Some(w)) {
+            st.tokens.pop_front();
+            s.ranking = Some(scoring::Ranking::StandardRanking(ranking.into()));
+        } else if st.eat("order") {
+            let mut order = scoring::CustomHandOrder::default();
+            while let Some(t) = st.peek_word().and_then(HandType::from_str_name) {
+                order.hand_types.push(t.into());
+                st.tokens.pop_front();
+            }
+            s.ranking = Some(scoring::Ranking::CustomHandOrder(order));
+        } else if st.eat("points") {
+            s.ranking = Some(scoring::Ranking::PointsExpression(
+                st.text("an expression")?,
+            ));
+        } else if st.eat("low") {
+            s.lo = true;
+        } else if st.eat("qualifier") {
+            s.lo_qualifier = st.text("a rank")?;
+        } else if st.eat("top-low") {
+            s.top_card_can_be_low = true;
+        } else if st.eat("low-straight") {
+            s.top_card_can_form_low_straight = true;
+        } else if st.eat("wraparound") {
+            s.wraparound_straight = true;
+        } else if st.eat("natural-wilds") {
+            s.natural_wilds = true;
+        } else if st.eat("hand") {
+            s.hand_cards = Some(st.range()?);
+        } else if st.eat("community") {
+            while st
+                .peek_word()
+                .is_some_and(|w| w == "any" || w.starts_with(|c: char| c.is_ascii_digit()))
+            {
+                s.community_cards.push(st.range()?);
+            }
+        } else {
+            break;
+        }
+    }
+    Ok(s)
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn sorted(map: &HashMap<String, String>) -> Vec<(&String, &String)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort();
+    entries
+}
+
+fn range(r: &scoring::Range) -> String {
+    match (r.min, r.max) {
+        (-1, -1) => "any".to_string(),
+        (min, max) if min == max => min.to_string(),
+        (min, max) => format!("{}-{}", min, max),
+    }
+}
+
+/// Writes `game` in the DSL, one statement per line. Parsing the output
+/// gives back the same game.
+pub fn print(game: &GameStructure) -> String {
+    let mut out = String::new();
+    let mut line = |s: String| {
+        out.push_str(&s);
+        out.push('\n');
+    };
+    if !game.id.is_empty() {
+        line(format!("id {}", quote(&game.id)));
+    }
+    if !game.name.is_empty() {
+        line(format!("name {}", quote(&game.name)));
+    }
+    for (locale, name) in sorted(&game.localized_names) {
+        line(format!("name {} {}", quote(locale), quote(name)));
+    }
+    if !game.description.is_empty() {
+        line(format!("description {}", quote(&game.description)));
+    }
+    for (locale, text) in sorted(&game.localized_descriptions) {
+        line(format!("description {} {}", quote(locale), quote(text)));
+    }
+    match &game.deck {
+        Some(game_structure::Deck::StandardDeck(d)) if *d == StandardDeck::DeckShortDeck as i32 => {
+            line("deck short".to_string())
+        }
+        Some(game_structure::Deck::StandardDeck(_)) => line("deck standard".to_string()),
+        Some(game_structure::Deck::CustomDeck(d)) => {
+            let mut s = "deck".to_string();
+            if !d.ranks.is_empty() {
+                write!(s, " ranks {}", d.ranks.join(" ")).unwrap();
+            }
+            if !d.suits.is_empty() {
+                write!(s, " suits {}", d.suits.join(" ")).unwrap();
+            }
+            if d.jokers != 0 {
+                write!(s, " jokers {}", d.jokers).unwrap();
+                if d.joker_rule() == JokerRule::Bug {
+                    s.push_str(" bug");
+                }
+            }
+            line(s);
+            for card in &d.cards {
+                let mut s = format!("card {} {}", quote(&card.rank), quote(&card.suit));
+                if card.copies != 0 {
+                    write!(s, " copies {}", card.copies).unwrap();
+                }
+                if card.wild {
+                    s.push_str(" wild");
+                }
+                if card.points != 0 {
+                    write!(s, " points {}", card.points).unwrap();
+                }
+                line(s);
+            }
+            for wild in &d.wilds {
+                let mut s = format!("wild {}", quote(&wild.rank));
+                for suit in &wild.suits {
+                    write!(s, " {}", suit).unwrap();
+                }
+                line(s);
+            }
+        }
+        None => {}
+    }
+    if let Some(shoe) = &game.shoe {
+        let mut s = format!("shoe {}", shoe.decks);
+        if shoe.penetration != 0 {
+            write!(s, " penetration {}", shoe.penetration).unwrap();
+        }
+        line(s);
+    }
+    if let Some(burns) = &game.burns {
+        let mut s = match burns.mode() {
+            burn_policy::Mode::Unknown | burn_policy::Mode::PerDeal => "burns per-deal",
+            burn_policy::Mode::EveryStreet => "burns every-street",
+            burn_policy::Mode::Never => "burns never",
+        }
+        .to_string();
+        if burns.cards != 0 {
+            write!(s, " {}", burns.cards).unwrap();
+        }
+        line(s);
+    }
+    for p in &game.parameters {
+        let mut s = format!("param {}", quote(&p.name));
+        if !p.default_value.is_empty() {
+            write!(s, " default {}", quote(&p.default_value)).unwrap();
+        }
+        if !p.description.is_empty() {
+            write!(s, " {}", quote(&p.description)).unwrap();
+        }
+        line(s);
+    }
+    if !game.extends.is_empty() {
+        line(format!("extends {}", quote(&game.extends)));
+    }
+    print_phases(&game.phases, "", &mut line);
+    for o in &game.phase_overrides {
+        if let Some(p) = &o.phase {
+            let Some(t) = &p.phase_type else {
+                continue;
+            };
+            let index = match o.index {
+                i if i < 0 => "all".to_string(),
+                i => i.to_string(),
+            };
+            line(format!(
+                "override {} {}{}",
+                index,
+                print_phase(t),
+                print_condition(p)
+            ));
+        }
+    }
+    if game.community_board_count != derived_boards(game) {
+        line(format!("boards {}", game.community_board_count));
+    }
+    for s in &game.scorings {
+        line(print_score(s));
+    }
+    if game.all_in_runouts != 0 {
+        line(format!("runouts {}", game.all_in_runouts));
+    }
+    if let Some(rake) = &game.rake {
+        let mut s = format!("rake {}", rake.basis_points);
+        if rake.cap != 0 {
+            write!(s, " cap {}", rake.cap).unwrap();
+        }
+        if rake.no_flop_no_drop {
+            s.push_str(" no-flop-no-drop");
+        }
+        line(s);
+    }
+    if let Some(rules) = &game.dealing_errors {
+        let mut s = match rules.exposed_card() {
+            ExposedCard::Unknown => "exposed",
+            ExposedCard::Burn => "exposed burn",
+            ExposedCard::PlayOn => "exposed play-on",
+            ExposedCard::Redeal => "exposed redeal",
+        }
+        .to_string();
+        if rules.max_exposed != 0 {
+            write!(s, " max {}", rules.max_exposed).unwrap();
+        }
+        line(s);
+    }
+    out
+}
+
+fn print_phase(p: &phase::PhaseType) -> String {
+    match p {
+        phase::PhaseType::PlayerDeal(d) => {
+            let side = |face_up| if face_up { "up" } else { "down" };
+            let mut s = "deal".to_string();
+            if d.face_up_cards.is_empty() {
+                write!(s, " {} {}", d.cards, side(d.face_up)).unwrap();
+            }
+            for run in d.face_up_cards.chunk_by(|a, b| a == b) {
+                write!(s, " {} {}", run.len(), side(run[0])).unwrap();
+            }
+            if d.burn != 0 {
+                write!(s, " burn {}", d.burn).unwrap();
+            }
+            s
+        }
+        phase::PhaseType::CommunityDeal(d) => {
+            let mut s = format!("board {}", d.cards);
+            if d.board_idx != 0 {
+                write!(s, " on {}", d.board_idx).unwrap();
+            }
+            if d.burn != 0 {
+                write!(s, " burn {}", d.burn).unwrap();
+            }
+            if d.face_down {
+                s.push_str(" down");
+            }
+            if !d.name.is_empty() {
+                write!(s, " {}", quote(&d.name)).unwrap();
+            }
+            s
+        }
+        phase::PhaseType::BettingRound(r) => print_bet(r),
+        phase::PhaseType::Exchange(e) => {
+            let mut s = format!("draw {}", e.max_exchange);
+            if e.lock_faceup {
+                s.push_str(" lock-up");
+            }
+            if e.lock_facedown {
+                s.push_str(" lock-down");
+            }
+            if e.draw_source() == DrawSource::ReshuffleDiscards {
+                s.push_str(" reshuffle");
+            }
+            if e.public_discards {
+                s.push_str(" public");
+            }
+            s
+        }
+        phase::PhaseType::Ante(a) => match a.payer() {
+            Payer::Unknown => "ante",
+            Payer::EveryPlayer => "ante every-player",
+            Payer::BigBlind => "ante big-blind",
+        }
+        .to_string(),
+        phase::PhaseType::PassCards(p) => {
+            let mut s = format!("pass {}", p.cards);
+            match p.direction() {
+                Direction::Left => s.push_str(" left"),
+                Direction::Right => s.push_str(" right"),
+                Direction::Across => s.push_str(" across"),
+                Direction::Unknown => {}
+            }
+            if p.sequential {
+                s.push_str(" sequential");
+            }
+            s
+        }
+        phase::PhaseType::Reveal(r) => {
+            let mut s = format!("reveal {}", r.cards);
+            if r.choose {
+                s.push_str(" choose");
+            }
+            if r.community {
+                s.push_str(" community");
+                if r.board_idx != 0 {
+                    write!(s, " on {}", r.board_idx).unwrap();
+                }
+            }
+            s
+        }
+        phase::PhaseType::Repeat(r) => {
+            let mut s = format!("repeat {}", r.times);
+            if let Some(until) = &r.until {
+                write!(s, " until {}", print_test(until, "then")).unwrap();
+            }
+            s
+        }
+        phase::PhaseType::Declare(d) => if d.in_turn {
+            "declare in-turn"
+        } else {
+            "declare"
+        }
+        .to_string(),
+        phase::PhaseType::Buy(b) => {
+            let mut s = format!("buy {}", b.price);
+            if b.replace {
+                s.push_str(" replace");
+            }
+            if b.face_up {
+                s.push_str(" up");
+            }
+            s
+        }
+    }
+}
+
+/// A statement for each phase, with a repeat's phases indented between it
+/// and its `end`.
+fn print_phases(phases: &[Phase], indent: &str, line: &mut impl FnMut(String)) {
+    for p in phases {
+        let Some(t) = &p.phase_type else {
+            continue;
+        };
+        line(format!(
+            "{}{}{}",
+            indent,
+            print_phase(t),
+            print_condition(p)
+        ));
+        if let phase::PhaseType::Repeat(repeat) = t {
+            print_phases(&repeat.phases, &format!("{}  ", indent), line);
+            line(format!("{}end", indent));
+        }
+    }
+}
+
+/// The phase's condition as it ends a statement, with a leading space.
+fn print_condition(p: &Phase) -> String {
+    match &p.condition {
+        Some(c) => format!(" if {}", print_test(c, "else")),
+        None => String::new(),
+    }
+}
+
+/// A condition as `parse_condition` reads it.
+fn print_test(c: &phase::Condition, follow: &str) -> String {
+    let measure = match c.measure() {
+        Measure::Players | Measure::Unknown => "players",
+        Measure::ActivePlayers => "active",
+        Measure::Pot => "pot",
+    };
+    let op = if c.negate { "<=" } else { ">" };
+    let mut s = format!("{} {} {}", measure, op, c.above);
+    if c.end_hand {
+        write!(s, " {} showdown", follow).unwrap();
+    }
+    s
+}
+
+fn print_bet(r: &phase::BettingRound) -> String {
+    let mut s = "bet".to_string();
+    if !r.name.is_empty() {
+        write!(s, " {}", quote(&r.name)).unwrap();
+    }
+    if r.min_bet != 0 {
+        write!(s, " min {}", r.min_bet).unwrap();
+    }
+    match r.order() {
+        BettingOrder::Unknown => {}
+        BettingOrder::FollowBlinds => s.push_str(" follow-blinds"),
+        BettingOrder::LeftOfDealer => s.push_str(" left-of-dealer"),
+        BettingOrder::BestFaceup => s.push_str(" best-faceup"),
+    }
+    if let Some(b) = &r.bring_in {
+        s.push_str(match b.starter() {
+            Starter::Highest => " bring-in highest",
+            _ => " bring-in lowest",
+        });
+        if b.amount != 0 {
+            write!(s, " amount {}", b.amount).unwrap();
+        }
+        if b.allow_complete {
+            s.push_str(" complete");
+        }
+    }
+    s
+}
+
+fn print_score(sc: &Scoring) -> String {
+    let mut s = "score".to_string();
+    if !sc.name.is_empty() {
+        write!(s, " {}", quote(&sc.name)).unwrap();
+    }
+    match &sc.ranking {
+        Some(scoring::Ranking::StandardRanking(r)) => {
+            if let Some((word, _)) = RANKINGS.iter().find(|(_, k)| *k as i32 == *r) {
+                write!(s, " {}", word).unwrap();
+            }
+        }
+        Some(scoring::Ranking::CustomHandOrder(order)) => {
+            s.push_str(" order");
+            for t in order.hand_types() {
+                write!(s, " {}", t.as_str_name()).unwrap();
+            }
+        }
+        Some(scoring::Ranking::PointsExpression(e)) => {
+            write!(s, " points {}", quote(e)).unwrap();
+        }
+        None => {}
+    }
+    if sc.lo {
+        s.push_str(" low");
+    }
+    if !sc.lo_qualifier.is_empty() {
+        write!(s, " qualifier {}", quote(&sc.lo_qualifier)).unwrap();
+    }
+    if sc.top_card_can_be_low {
+        s.push_str(" top-low");
+    }
+    if sc.top_card_can_form_low_straight {
+        s.push_str(" low-straight");
+    }
+    if sc.wraparound_straight {
+        s.push_str(" wraparound");
+    }
+    if sc.natural_wilds {
+        s.push_str(" natural-wilds");
+    }
+    if let Some(r) = &sc.hand_cards {
+        write!(s, " hand {}", range(r)).unwrap();
+    }
+    if !sc.community_cards.is_empty() {
+        s.push_str(" community");
+        for r in &sc.community_cards {
+            write!(s, " {}", range(r)).unwrap();
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GameBuilder;
+
+    #[test]
+    fn test_parse_dsl() {
+        let game = parse(
+            "# Omaha hi-lo\n\
+             id \"omaha8\"; name \"Omaha Hi-Lo\"; deck standard\n\
+             deal 4 down\n\
+             bet \"Pre-flop\" min 1 follow-blinds\n\
+             board 3 \"Flop\"; bet \"Flop\" min 1 left-of-dealer\n\
+             score standard hand 2 community 3\n\
+             score \"Lo\" ace-five low qualifier 8 top-low hand 2 community 3\n",
+        )
+        .unwrap();
+        let expected = GameBuilder::new("omaha8")
+            .name("Omaha Hi-Lo")
+            .deal_hole(4)
+            .betting("Pre-flop")
+            .community_on(0, 3)
+            .betting("Flop")
+            .high()
+            .ace_five_low("8")
+            .build()
+            .unwrap();
+        assert_eq!(game.phases.len(), 4);
+        assert_eq!(game.community_board_count, 1);
+        assert_eq!(game.phases[0], expected.phases[0]);
+        assert_eq!(game.phases[1], expected.phases[1]);
+        assert_eq!(
+            game.scorings[1].hand_cards,
+            Some(scoring::Range { min: 2, max: 2 })
+        );
+        assert_eq!(game.scorings[1].lo_qualifier, "8");
+        assert_eq!(parse(&print(&game)), Ok(game));
+    }
+
+    #[test]
+    fn test_print_round_trip() {
+        let mut game = parse(
+            "id razz; name de \"Razz (deutsch)\"; description \"Lowest hand wins\"\n\
+             deck ranks A 2 3 4 5 6 7 8 9 10 J Q K suits s h d c jokers 1\n\
+             wild 2 s h; card A s copies 2 wild points 11; card 10 h\n\
+             ante big-blind; deal 2 down; deal 1 up burn 1; deal 1 down 2 up 1 down\n\
+             bet \"Third\" min 1 bring-in highest amount 1 complete\n\
+             draw 2 lock-up reshuffle public; pass 1 across sequential; board 1 on 1 burn 1 down\n\
+             reveal 1 choose; reveal 1 community on 1 if active > 1; declare in-turn\n\
+             buy 2 replace up; buy 1\n\
+             bet if pot <= 100 else showdown\n\
+             repeat 2 until active <= 1 then showdown\n  deal 1 up\n  repeat 1 if players > 2; bet; end\nend\n\
+             score order FLUSH STRAIGHT community 0 any\n\
+             score \"Hearts\" points \"count(suit == 'h') * 2\" low\n\
+             burns every-street 2; runouts 2; rake 500 cap 3 no-flop-no-drop; exposed redeal max 2\n\
+             extends stud; override all bet min 2; override 4 deal 1 down",
+        )
+        .unwrap();
+        assert_eq!(game.community_board_count, 2);
+        assert_eq!(game.extends, "stud");
+        assert_eq!(game.localized_names["de"], "Razz (deutsch)");
+        assert_eq!(game.name, "");
+        assert_eq!(game.phase_overrides.len(), 2);
+        assert_eq!(game.phase_overrides[1].index, 4);
+        assert_eq!(parse(&print(&game)), Ok(game.clone()));
+        game.community_board_count = 3;
+        assert!(print(&game).contains("boards 3\n"));
+        assert_eq!(parse(&print(&game)), Ok(game));
+    }
+
+    #[test]
+    fn test_params() {
+        let text = "name \"${hole}-card game\"\n\
+                    param hole default 2 \"Cards dealt to each player\"\n\
+                    param min\n\
+                    deal ${hole}; bet min ${min}";
+        let game = parse_with(text, &[("min", "5")]).unwrap();
+        assert_eq!(game.name, "2-card game");
+        assert_eq!(
+            game.phases[1].phase_type,
+            Some(phase::PhaseType::BettingRound(phase::BettingRound {
+                min_bet: 5,
+                ..Default::default()
+            }))
+        );
+        assert_eq!(game.parameters[0].description, "Cards dealt to each player");
+        assert_eq!(game.parameters[1].default_value, "5");
+        assert_eq!(parse(&print(&game)), Ok(game.clone()));
+
+        let err = parse(text).unwrap_err();
+        assert_eq!((err.line, err.column), (4, 23));
+        assert_eq!(err.message, "parameter \"min\" has no value");
+        let err = parse_with(text, &[("max", "5")]).unwrap_err();
+        assert_eq!((err.line, err.column), (2, 1));
+    }
+
+    #[test]
+    fn test_dsl_errors() {
+        let err = parse("deal 2 down\nbet min x").unwrap_err();
+        assert_eq!(
+            (err.line, err.column, err.message.as_str()),
+            (2, 9, "expected a minimum bet")
+        );
+        let err = parse("deal 2 sideways").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 8));
+        let err = parse("shuffle").unwrap_err();
+        assert_eq!(err.message, "unknown statement \"shuffle\"");
+        let err = parse("override all score").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 14));
+        assert_eq!(err.message, "\"score\" is not a phase");
+        let err = parse("deal").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 2));
+        let err = parse("deal 2; end").unwrap_err();
+        assert_eq!(err.message, "end without a repeat");
+        let err = parse("repeat 2\nrepeat 3; bet; end").unwrap_err();
+        assert_eq!(
+            (err.line, err.column, err.message.as_str()),
+            (1, 1, "repeat without an end")
+        );
+    }
+}
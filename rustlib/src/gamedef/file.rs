@@ -0,0 +1,231 @@
+//! Game definitions saved as binary protobuf, framed so that damaged files
+//! are rejected rather than decoded into a different game.
+//!
+//! A file is the magic bytes `SNAPGAME`, the schema version it was written
+//! with and the CRC-32 of the payload (both little-endian `u32`s), then the
+//! length-delimited `GameStructure`.
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use prost::Message;
+
+use super::migrations::CURRENT_VERSION;
+use crate::GameStructure;
+
+const MAGIC: &[u8; 8] = b"SNAPGAME";
+const HEADER_LEN: usize = MAGIC.len() + 8;
+
+/// Why a game file couldn't be read or written.
+#[derive(Debug)]
+pub enum FileError {
+    Io(io::Error),
+    /// The file doesn't start with the magic bytes, so it isn't a game file.
+    NotAGameFile,
+    /// Written by a newer build, with a schema this one doesn't know.
+    UnsupportedVersion(u32),
+    /// The file ends before the payload does.
+    Truncated,
+    /// The payload doesn't match its checksum.
+    Corrupt {
+        expected: u32,
+        actual: u32,
+    },
+    /// There is more data after the payload.
+    TrailingData,
+    Decode(prost::DecodeError),
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::Io(e) => e.fmt(f),
+            FileError::NotAGameFile => write!(f, "not a game file"),
+            FileError::UnsupportedVersion(v) => write!(
+                f,
+                "written for schema version {}, but only versions up to {} are supported",
+                v, CURRENT_VERSION
+            ),
+            FileError::Truncated => write!(f, "the file is truncated"),
+            FileError::Corrupt { expected, actual } => write!(
+                f,
+                "checksum mismatch: expected {:08x}, got {:08x}",
+                expected, actual
+            ),
+            FileError::TrailingData => write!(f, "unexpected data after the game"),
+            FileError::Decode(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for FileError {}
+
+impl From<io::Error> for FileError {
+    fn from(e: io::Error) -> Self {
+        FileError::Io(e)
+    }
+}
+
+/// Saving and loading games as framed binary files.
+pub trait GameFile: Sized {
+    fn write_to_path(&self, path: impl AsRef<Path>) -> Result<(), FileError>;
+    fn read_from_path(path: impl AsRef<Path>) -> Result<Self, FileError>;
+}
+
+impl GameFile for GameStructure {
+    fn write_to_path(&self, path: impl AsRef<Path>) -> Result<(), FileError> {
+        Ok(std::fs::write(
+            path,
+            frame(&self.encode_length_delimited_to_vec()),
+        )?)
+    }
+
+    fn read_from_path(path: impl AsRef<Path>) -> Result<Self, FileError> {
+        let bytes = std::fs::read(path)?;
+        GameStructure::decode_length_delimited(unframe(&bytes)?).map_err(FileError::Decode)
+    }
+}
+
+/// A framed file holding `message`, an encoded `GameStructure`, byte for
+/// byte, so that fields this build doesn't know are kept.
+pub fn frame_message(message: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(message.len() + 10);
+    let mut len = message.len() as u64;
+    while len >= 0x80 {
+        payload.push(len as u8 | 0x80);
+        len >>= 7;
+    }
+    payload.push(len as u8);
+    payload.extend_from_slice(message);
+    frame(&payload)
+}
+
+/// The encoded `GameStructure` in a framed file, exactly as it was written.
+pub fn unframe_message(bytes: &[u8]) -> Result<&[u8], FileError> {
+    let payload = unframe(bytes)?;
+    let (_, prefix) = varint(payload).ok_or(FileError::Truncated)?;
+    Ok(&payload[prefix..])
+}
+
+/// `payload` with the file header in front.
+fn frame(payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_LEN + payload.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&(CURRENT_VERSION as u32).to_le_bytes());
+    out.extend_from_slice(&crc32(payload).to_le_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+/// The payload of a framed file, once the header and length prefix have
+/// been checked.
+fn unframe(bytes: &[u8]) -> Result<&[u8], FileError> {
+    if !bytes.starts_with(MAGIC) {
+        return Err(if MAGIC.starts_with(bytes) {
+            FileError::Truncated
+        } else {
+            FileError::NotAGameFile
+        });
+    }
+    if bytes.len() < HEADER_LEN {
+        return Err(FileError::Truncated);
+    }
+    let word = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+    let version = word(MAGIC.len());
+    if version > CURRENT_VERSION as u32 {
+        return Err(FileError::UnsupportedVersion(version));
+    }
+    let expected = word(MAGIC.len() + 4);
+    let payload = &bytes[HEADER_LEN..];
+    let (len, prefix) = varint(payload).ok_or(FileError::Truncated)?;
+    let end = (prefix as u64)
+        .checked_add(len)
+        .filter(|&end| end <= payload.len() as u64)
+        .ok_or(FileError::Truncated)?;
+    if end < payload.len() as u64 {
+        return Err(FileError::TrailingData);
+    }
+    let actual = crc32(payload);
+    if actual != expected {
+        return Err(FileError::Corrupt { expected, actual });
+    }
+    Ok(payload)
+}
+
+/// The varint at the start of `bytes`, and its length.
+fn varint(bytes: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, &b) in bytes.iter().enumerate().take(10) {
+        value |= u64::from(b & 0x7f) << (7 * i);
+        if b & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+/// CRC-32 as used by zlib and PNG.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in bytes {
+        crc ^= u32::from(b);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_frame() {
+        // A length prefix of 3 and a three-byte message.
+        let payload = [3, 0x0a, 0x01, b'x'];
+        let file = frame(&payload);
+        assert_eq!(&file[..8], b"SNAPGAME");
+        assert_eq!(unframe(&file).unwrap(), payload);
+
+        let mut corrupt = file.clone();
+        corrupt[HEADER_LEN + 3] = b'y';
+        assert!(matches!(unframe(&corrupt), Err(FileError::Corrupt { .. })));
+        for len in [4, HEADER_LEN, file.len() - 1] {
+            assert!(
+                matches!(unframe(&file[..len]), Err(FileError::Truncated)),
+                "{}",
+                len
+            );
+        }
+        let mut longer = file.clone();
+        longer.push(0);
+        assert!(matches!(unframe(&longer), Err(FileError::TrailingData)));
+        assert!(matches!(
+            unframe(b"{\"id\": \"holdem\"}"),
+            Err(FileError::NotAGameFile)
+        ));
+        let message: Vec<u8> = (0..200).map(|i| i as u8).collect();
+        let framed = frame_message(&message);
+        assert_eq!(&framed[HEADER_LEN..HEADER_LEN + 2], [200, 1]);
+        assert_eq!(unframe_message(&framed).unwrap(), message);
+
+        let mut newer = file;
+        newer[8] = 99;
+        assert_eq!(
+            unframe(&newer).unwrap_err().to_string(),
+            "written for schema version 99, but only versions up to 1 are supported"
+        );
+    }
+}
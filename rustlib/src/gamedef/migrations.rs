@@ -0,0 +1,165 @@
+//! Upgrades saved game definitions written for older versions of the schema.
+//!
+//! Migrations run on the JSON document before it is decoded, so they can
+//! rename fields and move them between messages without the decoder having
+//! to know about old names. Each entry of [`MIGRATIONS`] upgrades a document
+//! by one version; when the proto changes incompatibly, add a step and bump
+//! [`CURRENT_VERSION`].
+
+use super::json::Value;
+use super::protojson::{snake_case, FieldError};
+
+/// The schema version this build writes.
+pub const CURRENT_VERSION: i32 = 1;
+
+type Migration = fn(&mut Value) -> Result<(), FieldError>;
+
+/// `MIGRATIONS[n]` upgrades a version `n` document to version `n + 1`.
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize] = [
+    // Version 1 introduced the version field itself; unversioned documents
+    // otherwise have the same layout.
+    |_| Ok(()),
+];
+
+/// Upgrades a JSON-form game in place to [`CURRENT_VERSION`].
+pub fn upgrade(value: &mut Value) -> Result<(), FieldError> {
+    upgrade_with(value, &MIGRATIONS)
+}
+
+fn upgrade_with(value: &mut Value, migrations: &[Migration]) -> Result<(), FieldError> {
+    let error = |message: String| FieldError {
+        path: "version".to_string(),
+        message,
+    };
+    let version = match field_mut(value, "version") {
+        None | Some(Value::Null) => 0,
+        Some(Value::Number(n)) if n.fract() == 0.0 && *n >= 0.0 => *n as usize,
+        Some(other) => return Err(error(format!("{} is not a valid version", other))),
+    };
+    if version > migrations.len() {
+        return Err(error(format!(
+            "written for schema version {}, but only versions up to {} are supported",
+            version,
+            migrations.len()
+        )));
+    }
+    if !matches!(value, Value::Object(_)) {
+        // Left for the decoder to report.
+        return Ok(());
+    }
+    for migrate in &migrations[version..] {
+        migrate(value)?;
+    }
+    let current = Value::Number(migrations.len() as f64);
+    match field_mut(value, "version") {
+        Some(v) => *v = current,
+        None => {
+            if let Value::Object(entries) = value {
+                entries.push(("version".to_string(), current));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The field of an object called `name`, written in either snake_case or
+/// camelCase.
+pub fn field_mut<'a>(object: &'a mut Value, name: &str) -> Option<&'a mut Value> {
+    let Value::Object(entries) = object else {
+        return None;
+    };
+    entries
+        .iter_mut()
+        .find(|(key, _)| snake_case(key) == name)
+        .map(|(_, v)| v)
+}
+
+/// Renames the field `from` of an object to `to`, if it is present.
+pub fn rename(object: &mut Value, from: &str, to: &str) {
+    if let Value::Object(entries) = object {
+        if let Some(entry) = entries.iter_mut().find(|(key, _)| snake_case(key) == from) {
+            entry.0 = to.to_string();
+        }
+    }
+}
+
+/// Moves the listed fields of an object into a new nested message called
+/// `into`. Does nothing if none of them are present.
+pub fn split(object: &mut Value, fields: &[&str], into: &str) {
+    let Value::Object(entries) = object else {
+        return;
+    };
+    let (moved, kept) = std::mem::take(entries)
+        .into_iter()
+        .partition(|(key, _)| fields.contains(&snake_case(key).as_str()));
+    *entries = kept;
+    if !moved.is_empty() {
+        entries.push((into.to_string(), Value::Object(moved)));
+    }
+}
+
+/// Applies `f` to each element of the repeated field `name`.
+pub fn for_each(object: &mut Value, name: &str, mut f: impl FnMut(&mut Value)) {
+    if let Some(Value::Array(items)) = field_mut(object, name) {
+        items.iter_mut().for_each(&mut f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gamedef::json;
+
+    #[test]
+    fn test_upgrade() {
+        let mut value = json::parse(r#"{"id": "holdem"}"#).unwrap();
+        upgrade(&mut value).unwrap();
+        assert_eq!(value.to_string(), r#"{"id":"holdem","version":1}"#);
+
+        let mut value = json::parse(r#"{"version": 7}"#).unwrap();
+        assert_eq!(
+            upgrade(&mut value).unwrap_err().to_string(),
+            "version: written for schema version 7, but only versions up to 1 are supported"
+        );
+        let mut value = json::parse(r#"{"version": "1"}"#).unwrap();
+        assert!(upgrade(&mut value).is_err());
+    }
+
+    #[test]
+    fn test_migration_steps() {
+        // A hypothetical history: version 1 renamed `minimum` to `minBet`,
+        // and version 2 moved the rake fields into their own message.
+        let migrations: [Migration; 2] = [
+            |game| {
+                for_each(game, "phases", |phase| {
+                    if let Some(round) = field_mut(phase, "betting_round") {
+                        rename(round, "minimum", "minBet");
+                    }
+                });
+                Ok(())
+            },
+            |game| {
+                split(game, &["rake_basis_points", "rake_cap"], "rake");
+                if let Some(rake) = field_mut(game, "rake") {
+                    rename(rake, "rake_basis_points", "basisPoints");
+                    rename(rake, "rake_cap", "cap");
+                }
+                Ok(())
+            },
+        ];
+        let mut value = json::parse(
+            r#"{"phases": [{"bettingRound": {"minimum": 2}}, {"playerDeal": {}}],
+                "rakeBasisPoints": 500, "rake_cap": "3", "version": 0}"#,
+        )
+        .unwrap();
+        upgrade_with(&mut value, &migrations).unwrap();
+        assert_eq!(
+            value.to_string(),
+            r#"{"phases":[{"bettingRound":{"minBet":2}},{"playerDeal":{}}],"version":2,"rake":{"basisPoints":500,"cap":"3"}}"#
+        );
+
+        let mut value = json::parse(r#"{"rakeCap": 1, "version": 1}"#).unwrap();
+        upgrade_with(&mut value, &migrations).unwrap();
+        assert_eq!(value.to_string(), r#"{"version":2,"rake":{"cap":1}}"#);
+    }
+}
@@ -0,0 +1,319 @@
+//! Reading and writing game definitions outside of the binary proto format.
+//!
+//! Games are saved as canonical protobuf JSON, so the files can be written
+//! by hand and read by any protobuf implementation. They can also be loaded
+//! from and saved as YAML, which is easier to write by hand, or written in
+//! a compact text DSL (see [`dsl`]). Definitions saved for older versions
+//! of the schema are upgraded by [`migrations`] as they are read, and
+//! template parameters are filled in by [`params`]. For compact storage,
+//! [`file`] saves the binary proto form with a checksum.
+
+pub mod codec;
+pub mod diff;
+pub mod dsl;
+pub mod file;
+pub mod json;
+pub mod migrations;
+pub mod params;
+pub mod protojson;
+pub mod yaml;
+
+use std::fmt;
+
+use crate::GameStructure;
+
+pub use diff::{diff, GameDiff};
+pub use file::GameFile;
+
+/// Why a game definition couldn't be loaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The text isn't well-formed.
+    Syntax(json::SyntaxError),
+    /// The document doesn't describe a game.
+    Field(protojson::FieldError),
+    /// As `Field`, at a 1-based line and column of the source.
+    FieldAt {
+        line: usize,
+        column: usize,
+        error: protojson::FieldError,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Syntax(e) => e.fmt(f),
+            Error::Field(e) => e.fmt(f),
+            Error::FieldAt {
+                line,
+                column,
+                error,
+            } => write!(f, "{}:{}: {}", line, column, error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<json::SyntaxError> for Error {
+    fn from(e: json::SyntaxError) -> Self {
+        Error::Syntax(e)
+    }
+}
+
+impl From<protojson::FieldError> for Error {
+    fn from(e: protojson::FieldError) -> Self {
+        Error::Field(e)
+    }
+}
+
+/// `game` as indented canonical protobuf JSON.
+pub fn to_json(game: &GameStructure) -> String {
+    codec::encode(game).pretty()
+}
+
+/// Reads a game from protobuf JSON, with its parameters (see [`params`])
+/// at their defaults.
+pub fn from_json(text: &str) -> Result<GameStructure, Error> {
+    from_json_with(text, &[])
+}
+
+/// As [`from_json`], with values for some of the parameters.
+pub fn from_json_with(text: &str, args: &[(&str, &str)]) -> Result<GameStructure, Error> {
+    let mut value = json::parse(text)?;
+    migrations::upgrade(&mut value)?;
+    params::instantiate(&mut value, args)?;
+    Ok(codec::decode(&value)?)
+}
+
+/// Reads a game from YAML, using the same field names and values as the
+/// JSON form. Errors give the line and column of the offending key.
+pub fn from_yaml(text: &str) -> Result<GameStructure, Error> {
+    from_yaml_with(text, &[])
+}
+
+/// As [`from_yaml`], with values for some of the parameters.
+pub fn from_yaml_with(text: &str, args: &[(&str, &str)]) -> Result<GameStructure, Error> {
+    let mut doc = yaml::parse(text)?;
+    migrations::upgrade(&mut doc.value)
+        .and_then(|()| params::instantiate(&mut doc.value, args))
+        .and_then(|()| codec::decode(&doc.value))
+        .map_err(|error| match doc.position(&error.path) {
+            Some((line, column)) => Error::FieldAt {
+                line,
+                column,
+                error,
+            },
+            None => Error::Field(error),
+        })
+}
+
+/// `game` as YAML, with the field names and values of the JSON form.
+pub fn to_yaml(game: &GameStructure) -> String {
+    yaml::print(&codec::encode(game))
+}
+
+/// `game` in the text DSL.
+pub fn to_dsl(game: &GameStructure) -> String {
+    dsl::print(game)
+}
+
+/// Reads a game from the text DSL, with its parameters at their defaults.
+pub fn from_dsl(text: &str) -> Result<GameStructure, Error> {
+    from_dsl_with(text, &[])
+}
+
+/// As [`from_dsl`], with values for some of the parameters.
+pub fn from_dsl_with(text: &str, args: &[(&str, &str)]) -> Result<GameStructure, Error> {
+    Ok(dsl::parse_with(text, args)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GameBuilder;
+    use crate::phase::betting_round::{bring_in, BettingOrder, BringIn};
+    use crate::{dealing_errors, deck, game_structure, phase, DealingErrors, Deck, RakeConfig};
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut game = GameBuilder::new("stud")
+            .name("Seven Card Stud")
+            .custom_deck(Deck {
+                suits: vec!["♠".to_string(), "♥".to_string()],
+                jokers: 1,
+                joker_rule: deck::JokerRule::Bug.into(),
+                wilds: vec![deck::Wild {
+                    rank: "2".to_string(),
+                    suits: vec![],
+                }],
+                ..Default::default()
+            })
+            .ante(phase::ante::Payer::EveryPlayer)
+            .deal_hole(2)
+            .deal_up(1)
+            .betting_round(phase::BettingRound {
+                name: "Third card".to_string(),
+                bring_in: Some(BringIn {
+                    starter: bring_in::Starter::Lowest.into(),
+                    allow_complete: true,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .reveal(1, true)
+            .high()
+            .ace_five_low("8")
+            .build()
+            .unwrap();
+        game.rake = Some(RakeConfig {
+            basis_points: 500,
+            cap: 1 << 60,
+            no_flop_no_drop: false,
+        });
+        game.dealing_errors = Some(DealingErrors {
+            exposed_card: dealing_errors::ExposedCard::PlayOn.into(),
+            max_exposed: 0,
+        });
+        game.shoe = Some(crate::ShoeConfig {
+            decks: 2,
+            penetration: 75,
+        });
+
+        game.phases[2].condition = Some(crate::phase::Condition {
+            measure: crate::phase::condition::Measure::ActivePlayers.into(),
+            above: 1,
+            end_hand: true,
+            ..Default::default()
+        });
+        game.extends = "stud-base".to_string();
+        game.phase_overrides = vec![crate::PhaseOverride {
+            index: -1,
+            phase: Some(game.phases[1].clone()),
+        }];
+        game.description = "Three down, four up".to_string();
+        game.localized_names
+            .insert("zh-Hant".to_string(), "七張梭哈".to_string());
+        game.localized_names
+            .insert("de".to_string(), "Seven Card Stud".to_string());
+
+        let text = to_json(&game);
+        assert!(!text.contains("\"betting_round\""));
+        assert!(text.contains("\"bettingRound\": {"));
+        assert!(text.contains("\"starter\": \"STARTER_LOWEST\""));
+        assert!(text.contains("\"cap\": \"1152921504606846976\""));
+        assert!(text.contains("\"phaseOverrides\": ["));
+        assert!(text.contains("\"jokerRule\": \"JOKER_RULE_BUG\""));
+        assert!(text.contains("\"localizedNames\": {\n    \"de\": "));
+        assert_eq!(from_json(&text), Ok(game));
+    }
+
+    #[test]
+    fn test_json_input_forms() {
+        let game = from_json(
+            r#"{
+              "id": "holdem",
+              "standard_deck": 1,
+              "phases": [
+                {"playerDeal": {"cards": 2}},
+                {"betting_round": {"order": "BETTING_ORDER_FOLLOW_BLINDS", "minBet": null}}
+              ],
+              "rake": {"cap": 10}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            game.deck,
+            Some(game_structure::Deck::StandardDeck(
+                crate::StandardDeck::DeckPoker.into()
+            ))
+        );
+        assert_eq!(game.phases.len(), 2);
+        match &game.phases[1].phase_type {
+            Some(phase::PhaseType::BettingRound(r)) => {
+                assert_eq!(r.order(), BettingOrder::FollowBlinds);
+                assert_eq!(r.min_bet, 0);
+            }
+            other => panic!("expected a betting round, got {:?}", other),
+        }
+        assert_eq!(game.rake.unwrap().cap, 10);
+        assert_eq!(game.version, migrations::CURRENT_VERSION);
+    }
+
+    #[test]
+    fn test_json_errors() {
+        let err = |text: &str| from_json(text).unwrap_err().to_string();
+        assert_eq!(
+            err(r#"{"phases": [{}, {"bettingRound": {"order": "SIDEWAYS"}}]}"#),
+            "phases[1].bettingRound.order: unknown value \"SIDEWAYS\""
+        );
+        assert_eq!(
+            err(r#"{"phases": [{"ante": {}, "exchange": {}}]}"#),
+            "phases[0].exchange: only one of these fields may be set"
+        );
+        assert_eq!(err(r#"{"nmae": "x"}"#), "nmae: unknown field");
+        assert_eq!(
+            err(r#"{"communityBoardCount": 1.5}"#),
+            "communityBoardCount: 1.5 is not a valid integer here"
+        );
+        assert_eq!(err("{\n\"id\" 1}"), "2:6: expected ':'");
+        assert_eq!(
+            err(r#"{"version": 99}"#),
+            "version: written for schema version 99, but only versions up to 1 are supported"
+        );
+    }
+
+    #[test]
+    fn test_yaml() {
+        let game = from_yaml(
+            "id: razz\n\
+             customDeck:\n  \
+               ranks: [A, 2, 3, 4, 5, 6, 7, 8, 9, 10, J, Q, K]\n\
+             phases:\n\
+             - playerDeal: {cards: 2}\n\
+             - playerDeal:\n    \
+                 cards: 1\n    \
+                 face_up: true\n\
+             scorings:\n\
+             - lo: true\n  \
+               loQualifier: 8\n",
+        )
+        .unwrap();
+        match &game.deck {
+            Some(game_structure::Deck::CustomDeck(d)) => assert_eq!(d.ranks[9], "10"),
+            other => panic!("expected a custom deck, got {:?}", other),
+        }
+        assert_eq!(game.phases.len(), 2);
+        assert_eq!(game.scorings[0].lo_qualifier, "8");
+
+        let template = "parameters:\n- name: n\nphases:\n- playerDeal:\n    cards: \"${n}\"\n";
+        let game = from_yaml_with(template, &[("n", "3")]).unwrap();
+        assert_eq!(
+            game.phases[0].phase_type,
+            Some(phase::PhaseType::PlayerDeal(phase::PlayerDeal {
+                cards: 3,
+                ..Default::default()
+            }))
+        );
+        assert_eq!(
+            from_yaml(template).unwrap_err().to_string(),
+            "5:5: phases[0].playerDeal.cards: parameter \"n\" has no value"
+        );
+
+        for game in [crate::presets::holdem(), crate::presets::five_card_draw()] {
+            assert_eq!(from_yaml(&to_yaml(&game)).unwrap(), game);
+        }
+
+        let err = from_yaml("phases:\n- bettingRound:\n    order: SIDEWAYS\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "3:5: phases[0].bettingRound.order: unknown value \"SIDEWAYS\""
+        );
+        let err = from_yaml("phases:\n- playerDeal: {cards: 2, up: true}\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "2:26: phases[0].playerDeal.up: unknown field"
+        );
+    }
+}
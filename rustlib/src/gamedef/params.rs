@@ -0,0 +1,198 @@
+//! Template parameters: a definition can declare `parameters` and refer to
+//! them from any string as `${name}`, so one file can be instantiated at
+//! different stakes or card counts. Numeric fields accept decimal strings,
+//! so `"minBet": "${min_bet}"` works as well as a name of `"${stakes} NL"`.
+//! A string that is exactly a reference to a parameter whose value is
+//! `true` or `false` becomes a boolean. Write `$${` for a literal `${`.
+
+use std::collections::HashMap;
+
+use super::codec;
+use super::json::Value;
+use super::protojson::{snake_case, FieldError};
+use crate::Parameter;
+
+/// The value of each declared parameter, or `None` if it has neither an
+/// argument nor a default.
+pub(super) type Values = HashMap<String, Option<String>>;
+
+/// Fills in the parameters of a JSON-form game. `args` gives values by
+/// name; other parameters take their defaults. Afterwards each declaration's
+/// default is the value that was used.
+pub fn instantiate(value: &mut Value, args: &[(&str, &str)]) -> Result<(), FieldError> {
+    let Value::Object(entries) = value else {
+        // Left for the decoder to report.
+        return Ok(());
+    };
+    let declared = entries
+        .iter()
+        .position(|(key, _)| snake_case(key) == "parameters");
+    let Some(index) = declared else {
+        let values = bind(&mut [], args).map_err(|message| FieldError {
+            path: "parameters".to_string(),
+            message,
+        })?;
+        return substitute_all(value, &values, "");
+    };
+    let (key, declared) = entries.remove(index);
+    let mut params = codec::decode_parameters(&declared, &key)?;
+    let values = bind(&mut params, args).map_err(|message| FieldError {
+        path: key.clone(),
+        message,
+    })?;
+    substitute_all(value, &values, "")?;
+    if let Value::Object(entries) = value {
+        let declared = Value::Array(params.iter().map(codec::encode_parameter).collect());
+        entries.insert(index, (key, declared));
+    }
+    Ok(())
+}
+
+/// Picks the value of each parameter from `args` or its default, and records
+/// it as the default.
+pub(super) fn bind(params: &mut [Parameter], args: &[(&str, &str)]) -> Result<Values, String> {
+    if let Some((name, _)) = args
+        .iter()
+        .find(|(name, _)| !params.iter().any(|p| p.name == *name))
+    {
+        return Err(format!("no parameter named {:?}", name));
+    }
+    let mut values = HashMap::new();
+    for p in params {
+        if let Some((_, v)) = args.iter().rev().find(|(name, _)| *name == p.name) {
+            p.default_value = v.to_string();
+        }
+        let value = Some(p.default_value.clone()).filter(|v| !v.is_empty());
+        values.insert(p.name.clone(), value);
+    }
+    Ok(values)
+}
+
+fn substitute_all(value: &mut Value, values: &Values, path: &str) -> Result<(), FieldError> {
+    match value {
+        Value::String(s) => {
+            let expanded = expand(s, values).map_err(|message| FieldError {
+                path: path.to_string(),
+                message,
+            })?;
+            *value = match expanded {
+                Some((s, true)) if s == "true" => Value::Bool(true),
+                Some((s, true)) if s == "false" => Value::Bool(false),
+                Some((s, _)) => Value::String(s),
+                None => return Ok(()),
+            };
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                substitute_all(item, values, &format!("{}[{}]", path, i))?;
+            }
+        }
+        Value::Object(entries) => {
+            for (key, v) in entries {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                substitute_all(v, values, &child)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// `s` with its references filled in, and whether it was nothing but a
+/// single reference; or `None` if it has no references or escapes.
+pub(super) fn expand(s: &str, values: &Values) -> Result<Option<(String, bool)>, String> {
+    if !s.contains("${") {
+        return Ok(None);
+    }
+    let mut out = String::new();
+    let mut rest = s;
+    let mut whole = false;
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start..];
+        if let Some(tail) = after.strip_prefix("$${") {
+            out.push_str("${");
+            rest = tail;
+        } else if let Some(tail) = after.strip_prefix("${") {
+            let Some(end) = tail.find('}') else {
+                return Err(format!("unterminated reference in {:?}", s));
+            };
+            let name = tail[..end].trim();
+            let value = match values.get(name) {
+                Some(Some(v)) => v,
+                Some(None) => return Err(format!("parameter {:?} has no value", name)),
+                None => return Err(format!("unknown parameter {:?}", name)),
+            };
+            whole = after.len() == s.len() && end + 3 == s.len();
+            out.push_str(value);
+            rest = &tail[end + 1..];
+        } else {
+            out.push('$');
+            rest = &after[1..];
+        }
+    }
+    out.push_str(rest);
+    Ok(Some((out, whole)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gamedef::json;
+
+    fn run(text: &str, args: &[(&str, &str)]) -> Result<String, String> {
+        let mut value = json::parse(text).unwrap();
+        instantiate(&mut value, args)
+            .map(|()| value.to_string())
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_instantiate() {
+        let text = r#"{
+            "name": "${stakes} Hold'em",
+            "parameters": [{"name": "stakes", "defaultValue": "1/2"}, {"name": "up"}],
+            "phases": [{"playerDeal": {"cards": 2, "faceUp": "${up}"}}],
+            "id": "$${literal}"
+        }"#;
+        assert_eq!(
+            run(text, &[("up", "true")]),
+            Ok(concat!(
+                r#"{"name":"1/2 Hold'em","#,
+                r#""parameters":[{"name":"stakes","defaultValue":"1/2"},"#,
+                r#"{"name":"up","defaultValue":"true"}],"#,
+                r#""phases":[{"playerDeal":{"cards":2,"faceUp":true}}],"id":"${literal}"}"#
+            )
+            .to_string())
+        );
+        assert!(run(text, &[("stakes", "5/10"), ("up", "no")])
+            .unwrap()
+            .starts_with(r#"{"name":"5/10 Hold'em","#));
+    }
+
+    #[test]
+    fn test_instantiate_errors() {
+        let text =
+            r#"{"parameters": [{"name": "n"}], "phases": [{"playerDeal": {"cards": "${n}"}}]}"#;
+        assert_eq!(
+            run(text, &[]),
+            Err("phases[0].playerDeal.cards: parameter \"n\" has no value".to_string())
+        );
+        assert_eq!(
+            run(text, &[("m", "2")]),
+            Err("parameters: no parameter named \"m\"".to_string())
+        );
+        assert_eq!(
+            run(r#"{"name": "${x}"}"#, &[]),
+            Err("name: unknown parameter \"x\"".to_string())
+        );
+        assert_eq!(
+            run(r#"{"name": "${x"}"#, &[]),
+            Err("name: unterminated reference in \"${x\"".to_string())
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! The pieces of the canonical protobuf JSON mapping that every message
+//! shares, for [`super::codec`] and for other crates' messages: building
+//! objects that leave out default values, and reading fields back with
+//! the path to each one for errors.
+//!
+//! Enums are passed as functions between their numbers and names, such as
+//! those prost generates, so any crate's enums can be used.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::json::Value;
+
+/// A document that is valid JSON but not a valid message. `path` locates
+/// the offending value, e.g. `phases[2].bettingRound.order`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldError {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl std::error::Error for FieldError {}
+
+pub type Result<T> = std::result::Result<T, FieldError>;
+
+pub fn error<T>(path: &str, message: impl Into<String>) -> Result<T> {
+    Err(FieldError {
+        path: path.to_string(),
+        message: message.into(),
+    })
+}
+
+/// Builds a JSON object, leaving out fields at their default values.
+#[derive(Default)]
+pub struct Object(Vec<(String, Value)>);
+
+impl Object {
+    pub fn field(mut self, key: &str, value: Value) -> Self {
+        self.0.push((key.to_string(), value));
+        self
+    }
+
+    pub fn string(self, key: &str, value: &str) -> Self {
+        if value.is_empty() {
+            return self;
+        }
+        self.field(key, Value::String(value.to_string()))
+    }
+
+    pub fn int(self, key: &str, value: i32) -> Self {
+        if value == 0 {
+            return self;
+        }
+        self.field(key, Value::Number(value.into()))
+    }
+
+    pub fn uint(self, key: &str, value: u32) -> Self {
+        if value == 0 {
+            return self;
+        }
+        self.field(key, Value::Number(value.into()))
+    }
+
+    pub fn int64(self, key: &str, value: i64) -> Self {
+        if value == 0 {
+            return self;
+        }
+        self.field(key, Value::String(value.to_string()))
+    }
+
+    pub fn uint64(self, key: &str, value: u64) -> Self {
+        if value == 0 {
+            return self;
+        }
+        self.field(key, Value::String(value.to_string()))
+    }
+
+    pub fn bool(self, key: &str, value: bool) -> Self {
+        if !value {
+            return self;
+        }
+        self.field(key, Value::Bool(true))
+    }
+
+    /// An enum field, by the name `name` gives its value.
+    pub fn enumeration(self, key: &str, value: i32, name: fn(i32) -> Option<&'static str>) -> Self {
+        if value == 0 {
+            return self;
+        }
+        self.field(key, enum_value(value, name))
+    }
+
+    pub fn message(self, key: &str, value: Option<Value>) -> Self {
+        match value {
+            Some(v) => self.field(key, v),
+            None => self,
+        }
+    }
+
+    pub fn list(self, key: &str, values: Vec<Value>) -> Self {
+        if values.is_empty() {
+            return self;
+        }
+        self.field(key, Value::Array(values))
+    }
+
+    /// A map field, with keys sorted so that the output is canonical.
+    pub fn map(self, key: &str, values: &HashMap<String, String>) -> Self {
+        if values.is_empty() {
+            return self;
+        }
+        let mut entries: Vec<(String, Value)> = values
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        self.field(key, Value::Object(entries))
+    }
+
+    pub fn build(self) -> Value {
+        Value::Object(self.0)
+    }
+}
+
+/// An enum value by its name, or its number if `name` doesn't know it.
+pub fn enum_value(value: i32, name: fn(i32) -> Option<&'static str>) -> Value {
+    match name(value) {
+        Some(name) => Value::String(name.to_string()),
+        None => Value::Number(value.into()),
+    }
+}
+
+/// The fields of a JSON object, with keys normalized to snake_case and the
+/// path to each value. Null values are treated as absent.
+pub fn fields<'a>(value: &'a Value, path: &str) -> Result<Vec<(String, &'a Value, String)>> {
+    let Value::Object(entries) = value else {
+        return error(path, "expected an object");
+    };
+    let mut fields: Vec<(String, &Value, String)> = Vec::new();
+    for (key, v) in entries {
+        let child = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", path, key)
+        };
+        let name = snake_case(key);
+        if fields.iter().any(|(n, _, _)| *n == name) {
+            return error(&child, "field given more than once");
+        }
+        if *v != Value::Null {
+            fields.push((name, v, child));
+        }
+    }
+    Ok(fields)
+}
+
+pub fn snake_case(key: &str) -> String {
+    let mut out = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            out.push('_');
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+pub fn unknown<T>(path: &str) -> Result<T> {
+    error(path, "unknown field")
+}
+
+pub fn items<'a>(value: &'a Value, path: &str) -> Result<Vec<(&'a Value, String)>> {
+    let Value::Array(items) = value else {
+        return error(path, "expected a list");
+    };
+    Ok(items
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (v, format!("{}[{}]", path, i)))
+        .collect())
+}
+
+pub fn string(value: &Value, path: &str) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        _ => error(path, "expected a string"),
+    }
+}
+
+pub fn boolean(value: &Value, path: &str) -> Result<bool> {
+    match value {
+        Value::Bool(b) => Ok(*b),
+        _ => error(path, "expected true or false"),
+    }
+}
+
+/// An integer, given as a number or a decimal string.
+pub fn integer(value: &Value, path: &str, min: f64, max: f64) -> Result<f64> {
+    let n = match value {
+        Value::Number(n) => *n,
+        Value::String(s) => match s.trim().parse::<f64>() {
+            Ok(n) => n,
+            Err(_) => return error(path, "expected an integer"),
+        },
+        _ => return error(path, "expected an integer"),
+    };
+    if n.fract() != 0.0 || n < min || n > max {
+        return error(path, format!("{} is not a valid integer here", n));
+    }
+    Ok(n)
+}
+
+pub fn int(value: &Value, path: &str) -> Result<i32> {
+    integer(value, path, i32::MIN.into(), i32::MAX.into()).map(|n| n as i32)
+}
+
+pub fn uint(value: &Value, path: &str) -> Result<u32> {
+    integer(value, path, 0.0, u32::MAX.into()).map(|n| n as u32)
+}
+
+pub fn int64(value: &Value, path: &str) -> Result<i64> {
+    // Above 2^53 an f64 can't hold every integer, so parse strings directly.
+    if let Value::String(s) = value {
+        if let Ok(n) = s.trim().parse::<i64>() {
+            return Ok(n);
+        }
+    }
+    integer(value, path, i64::MIN as f64, i64::MAX as f64).map(|n| n as i64)
+}
+
+pub fn uint64(value: &Value, path: &str) -> Result<u64> {
+    if let Value::String(s) = value {
+        if let Ok(n) = s.trim().parse::<u64>() {
+            return Ok(n);
+        }
+    }
+    integer(value, path, 0.0, u64::MAX as f64).map(|n| n as u64)
+}
+
+/// An enum, by a name `from_name` knows or by number.
+pub fn enumeration(value: &Value, path: &str, from_name: fn(&str) -> Option<i32>) -> Result<i32> {
+    match value {
+        Value::String(name) => match from_name(name) {
+            Some(v) => Ok(v),
+            None => error(path, format!("unknown value {:?}", name)),
+        },
+        Value::Number(_) => int(value, path),
+        _ => error(path, "expected an enum name"),
+    }
+}
+
+/// Sets a oneof, rejecting a second member.
+pub fn set_oneof<T>(slot: &mut Option<T>, value: T, path: &str) -> Result<()> {
+    if slot.is_some() {
+        return error(path, "only one of these fields may be set");
+    }
+    *slot = Some(value);
+    Ok(())
+}
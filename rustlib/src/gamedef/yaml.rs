@@ -0,0 +1,676 @@
+//! A YAML reader for the subset of YAML that game definitions need: block
+//! and flow mappings and sequences, plain and quoted scalars, and comments.
+//! Anchors, tags and multi-line scalars are rejected.
+//!
+//! Documents are read into the same [`Value`] tree as JSON, along with where
+//! each key and list item was written so that errors can point at them.
+//! [`print`] writes a tree back out in block style, which reads back the
+//! same.
+
+use std::collections::HashMap;
+
+use super::json::{SyntaxError, Value};
+
+/// A parsed document and the 1-based line and column of every key and list
+/// item, by the path [`super::codec`] uses in its errors.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document {
+    pub value: Value,
+    pub positions: HashMap<String, (usize, usize)>,
+}
+
+impl Document {
+    /// Where the value at `path`, or failing that its closest enclosing
+    /// value, was written.
+    pub fn position(&self, path: &str) -> Option<(usize, usize)> {
+        let mut path = path;
+        loop {
+            if let Some(&pos) = self.positions.get(path) {
+                return Some(pos);
+            }
+            let cut = path.rfind(['.', '['])?;
+            path = &path[..cut];
+        }
+    }
+}
+
+struct Line {
+    number: usize,
+    indent: usize,
+    text: String,
+}
+
+/// Parses a YAML document.
+pub fn parse(text: &str) -> Result<Document, SyntaxError> {
+    let mut lines = Vec::new();
+    for (i, raw) in text.lines().enumerate() {
+        let number = i + 1;
+        let indent = raw.len() - raw.trim_start_matches(' ').len();
+        if raw[indent..].starts_with('\t') {
+            return Err(syntax(
+                number,
+                indent + 1,
+                "tabs can't be used for indentation",
+            ));
+        }
+        let text = strip_comment(&raw[indent..]).trim_end().to_string();
+        if text.is_empty() || (indent == 0 && (text == "---" || text == "...")) {
+            continue;
+        }
+        lines.push(Line {
+            number,
+            indent,
+            text,
+        });
+    }
+    let mut parser = Parser {
+        lines,
+        pos: 0,
+        positions: HashMap::new(),
+    };
+    let value = match parser.lines.first() {
+        Some(first) => {
+            let indent = first.indent;
+            parser.block(indent, "")?
+        }
+        None => Value::Object(Vec::new()),
+    };
+    if let Some(line) = parser.lines.get(parser.pos) {
+        return Err(syntax(
+            line.number,
+            line.indent + 1,
+            "unexpected indentation",
+        ));
+    }
+    Ok(Document {
+        value,
+        positions: parser.positions,
+    })
+}
+
+fn syntax(line: usize, column: usize, message: &str) -> SyntaxError {
+    SyntaxError {
+        line,
+        column,
+        message: message.to_string(),
+    }
+}
+
+/// Drops a trailing `# comment`, ignoring `#` inside quotes or words.
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    let mut prev = ' ';
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if (c == '"' || c == '\'') && (prev.is_whitespace() || "[{,:".contains(prev)) => {
+                quote = Some(c)
+            }
+            None if c == '#' && prev.is_whitespace() => return &text[..i],
+            None => {}
+        }
+        prev = c;
+    }
+    text
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn is_sequence_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+struct Parser {
+    lines: Vec<Line>,
+    pos: usize,
+    positions: HashMap<String, (usize, usize)>,
+}
+
+impl Parser {
+    fn current(&self) -> Option<&Line> {
+        self.lines.get(self.pos)
+    }
+
+    /// A mapping, sequence or scalar whose lines start at `indent`.
+    fn block(&mut self, indent: usize, path: &str) -> Result<Value, SyntaxError> {
+        let line = &self.lines[self.pos];
+        if is_sequence_item(&line.text) {
+            self.sequence(indent, path)
+        } else if split_key(&line.text).is_some() {
+            self.mapping(indent, path)
+        } else {
+            let (number, column, text) = (line.number, line.indent + 1, line.text.clone());
+            self.pos += 1;
+            inline(&text, number, column, path, &mut self.positions)
+        }
+    }
+
+    fn mapping(&mut self, indent: usize, path: &str) -> Result<Value, SyntaxError> {
+        let mut entries: Vec<(String, Value)> = Vec::new();
+        while let Some(line) = self.current() {
+            if line.indent < indent || is_sequence_item(&line.text) {
+                break;
+            }
+            let (number, column) = (line.number, line.indent + 1);
+            if line.indent > indent {
+                return Err(syntax(number, column, "unexpected indentation"));
+            }
+            let text = line.text.clone();
+            let Some((key, rest)) = split_key(&text) else {
+                return Err(syntax(number, column, "expected `key: value`"));
+            };
+            let key = unquote_key(&key, number, column)?;
+            if entries.iter().any(|(k, _)| *k == key) {
+                return Err(syntax(number, column, &format!("duplicate key {:?}", key)));
+            }
+            let child = child_path(path, &key);
+            self.positions.insert(child.clone(), (number, column));
+            self.pos += 1;
+            let value = if rest.is_empty() {
+                match self.current() {
+                    Some(next) if next.indent > indent => {
+                        let indent = next.indent;
+                        self.block(indent, &child)?
+                    }
+                    // A sequence may sit at the same indent as its key.
+                    Some(next) if next.indent == indent && is_sequence_item(&next.text) => {
+                        self.sequence(indent, &child)?
+                    }
+                    _ => Value::Null,
+                }
+            } else {
+                let offset = text.len() - rest.len();
+                inline(rest, number, column + offset, &child, &mut self.positions)?
+            };
+            entries.push((key, value));
+        }
+        Ok(Value::Object(entries))
+    }
+
+    fn sequence(&mut self, indent: usize, path: &str) -> Result<Value, SyntaxError> {
+        let mut items = Vec::new();
+        while let Some(line) = self.current() {
+            if line.indent != indent || !is_sequence_item(&line.text) {
+                if line.indent > indent {
+                    return Err(syntax(
+                        line.number,
+                        line.indent + 1,
+                        "unexpected indentation",
+                    ));
+                }
+                break;
+            }
+            let child = format!("{}[{}]", path, items.len());
+            let position = (line.number, line.indent + 1);
+            let text = line.text.clone();
+            self.positions.insert(child.clone(), position);
+            let rest = text[1..].trim_start();
+            if rest.is_empty() {
+                self.pos += 1;
+                let value = match self.current() {
+                    Some(next) if next.indent > indent => {
+                        let indent = next.indent;
+                        self.block(indent, &child)?
+                    }
+                    _ => Value::Null,
+                };
+                items.push(value);
+                continue;
+            }
+            // Treat the text after the dash as a line of its own, indented
+            // to where it starts, so that `- key: value` can continue with
+            // more keys on the following lines.
+            let inner = indent + (text.len() - rest.len());
+            let rest = rest.to_string();
+            let line = &mut self.lines[self.pos];
+            line.indent = inner;
+            line.text = rest;
+            items.push(self.block(inner, &child)?);
+        }
+        Ok(Value::Array(items))
+    }
+}
+
+/// Splits `key: value` (or `key:`) into the key and the trimmed value.
+fn split_key(text: &str) -> Option<(String, &str)> {
+    if text.starts_with(['[', '{']) {
+        return None;
+    }
+    let end = if let Some(q) = text.chars().next().filter(|&c| c == '"' || c == '\'') {
+        text[1..].find(q)? + 2
+    } else {
+        0
+    };
+    let colon = end + text[end..].find(':')?;
+    let after = &text[colon + 1..];
+    if !after.is_empty() && !after.starts_with(' ') {
+        return None;
+    }
+    Some((text[..colon].trim_end().to_string(), after.trim()))
+}
+
+fn unquote_key(key: &str, line: usize, column: usize) -> Result<String, SyntaxError> {
+    if key.starts_with(['"', '\'']) {
+        let mut flow = Flow {
+            chars: key.char_indices().peekable(),
+            text: key,
+            line,
+            column,
+        };
+        return flow.quoted();
+    }
+    Ok(key.to_string())
+}
+
+/// Parses a value written on one line: a scalar, or a flow sequence or
+/// mapping.
+fn inline(
+    text: &str,
+    line: usize,
+    column: usize,
+    path: &str,
+    positions: &mut HashMap<String, (usize, usize)>,
+) -> Result<Value, SyntaxError> {
+    if text.starts_with(['|', '>']) {
+        return Err(syntax(line, column, "multi-line scalars are not supported"));
+    }
+    if text.starts_with(['&', '*', '!']) {
+        return Err(syntax(
+            line,
+            column,
+            "anchors, aliases and tags are not supported",
+        ));
+    }
+    let mut flow = Flow {
+        chars: text.char_indices().peekable(),
+        text,
+        line,
+        column,
+    };
+    if !text.starts_with(['[', '{', '"', '\'']) {
+        return Ok(plain(text));
+    }
+    let value = flow.value(path, positions)?;
+    flow.skip_spaces();
+    if let Some(&(i, _)) = flow.chars.peek() {
+        return Err(syntax(line, column + i, "unexpected text after the value"));
+    }
+    Ok(value)
+}
+
+/// Types a plain scalar as YAML's core schema would.
+fn plain(text: &str) -> Value {
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => Value::Null,
+        "true" | "True" | "TRUE" => Value::Bool(true),
+        "false" | "False" | "FALSE" => Value::Bool(false),
+        _ => {
+            let numeric = text.starts_with(|c: char| c.is_ascii_digit() || "-+.".contains(c))
+                && !text.contains(['_', ' ']);
+            match text.parse::<f64>() {
+                Ok(n) if numeric && n.is_finite() => Value::Number(n),
+                _ => Value::String(text.to_string()),
+            }
+        }
+    }
+}
+
+/// A cursor over one line of flow-style YAML.
+struct Flow<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    text: &'a str,
+    line: usize,
+    column: usize,
+}
+
+impl Flow<'_> {
+    fn error(&mut self, message: &str) -> SyntaxError {
+        let offset = self.chars.peek().map_or(self.text.len(), |&(i, _)| i);
+        syntax(self.line, self.column + offset, message)
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.chars.peek().is_some_and(|&(_, c)| c == ' ') {
+            self.chars.next();
+        }
+    }
+
+    fn value(
+        &mut self,
+        path: &str,
+        positions: &mut HashMap<String, (usize, usize)>,
+    ) -> Result<Value, SyntaxError> {
+        self.skip_spaces();
+        match self.chars.peek().map(|&(_, c)| c) {
+            Some('[') => {
+                self.chars.next();
+                let mut items = Vec::new();
+                loop {
+                    self.skip_spaces();
+                    if self.chars.peek().is_some_and(|&(_, c)| c == ']') {
+                        self.chars.next();
+                        return Ok(Value::Array(items));
+                    }
+                    let child = format!("{}[{}]", path, items.len());
+                    let offset = self.chars.peek().map_or(0, |&(i, _)| i);
+                    positions.insert(child.clone(), (self.line, self.column + offset));
+                    items.push(self.value(&child, positions)?);
+                    self.skip_spaces();
+                    match self.chars.next() {
+                        Some((_, ',')) => continue,
+                        Some((_, ']')) => return Ok(Value::Array(items)),
+                        _ => return Err(self.error("expected ',' or ']'")),
+                    }
+                }
+            }
+            Some('{') => {
+                self.chars.next();
+                let mut entries: Vec<(String, Value)> = Vec::new();
+                loop {
+                    self.skip_spaces();
+                    if self.chars.peek().is_some_and(|&(_, c)| c == '}') {
+                        self.chars.next();
+                        return Ok(Value::Object(entries));
+                    }
+                    let offset = self.chars.peek().map_or(0, |&(i, _)| i);
+                    let key = match self.scalar(":")? {
+                        Value::String(s) => s,
+                        other => other.to_string(),
+                    };
+                    self.skip_spaces();
+                    if self.chars.next().map(|(_, c)| c) != Some(':') {
+                        return Err(self.error("expected ':'"));
+                    }
+                    let child = child_path(path, &key);
+                    positions.insert(child.clone(), (self.line, self.column + offset));
+                    let value = self.value(&child, positions)?;
+                    entries.push((key, value));
+                    self.skip_spaces();
+                    match self.chars.next() {
+                        Some((_, ',')) => continue,
+                        Some((_, '}')) => return Ok(Value::Object(entries)),
+                        _ => return Err(self.error("expected ',' or '}'")),
+                    }
+                }
+            }
+            Some(_) => self.scalar(",]}"),
+            None => Ok(Value::Null),
+        }
+    }
+
+    /// A quoted scalar, or a plain one ending before any of `stops`.
+    fn scalar(&mut self, stops: &str) -> Result<Value, SyntaxError> {
+        match self.chars.peek().map(|&(_, c)| c) {
+            Some('"' | '\'') => Ok(Value::String(self.quoted()?)),
+            _ => {
+                let start = self.chars.peek().map_or(self.text.len(), |&(i, _)| i);
+                let mut end = start;
+                while let Some(&(i, c)) = self.chars.peek() {
+                    if stops.contains(c) {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    self.chars.next();
+                }
+                Ok(plain(self.text[start..end].trim()))
+            }
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String, SyntaxError> {
+        let Some((_, quote)) = self.chars.next() else {
+            return Err(self.error("expected a string"));
+        };
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, c)) if c == quote => {
+                    // In single quotes, '' is an escaped quote.
+                    if quote == '\'' && self.chars.peek().is_some_and(|&(_, c)| c == '\'') {
+                        self.chars.next();
+                        s.push('\'');
+                        continue;
+                    }
+                    return Ok(s);
+                }
+                Some((_, '\\')) if quote == '"' => {
+                    let c = match self.chars.next().map(|(_, c)| c) {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('0') => '\0',
+                        Some('u') => {
+                            let mut code = 0;
+                            for _ in 0..4 {
+                                let digit = self.chars.next().and_then(|(_, c)| c.to_digit(16));
+                                match digit {
+                                    Some(d) => code = code * 16 + d,
+                                    None => return Err(self.error("invalid \\u escape")),
+                                }
+                            }
+                            match char::from_u32(code) {
+                                Some(c) => c,
+                                None => return Err(self.error("invalid \\u escape")),
+                            }
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    s.push(c);
+                }
+                Some((_, c)) => s.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+}
+
+/// `value` as a block-style YAML document.
+pub fn print(value: &Value) -> String {
+    let mut out = String::new();
+    match value {
+        Value::Object(entries) if !entries.is_empty() => write_entries(&mut out, entries, 0),
+        Value::Array(items) if !items.is_empty() => write_items(&mut out, items, 0),
+        scalar => {
+            write_scalar(&mut out, scalar);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn write_entries(out: &mut String, entries: &[(String, Value)], indent: usize) {
+    for (i, (key, value)) in entries.iter().enumerate() {
+        // The first key of a list item goes on the dash's line.
+        if i > 0 || !out.ends_with("- ") {
+            out.push_str(&" ".repeat(indent));
+        }
+        write_scalar(out, &Value::String(key.clone()));
+        out.push(':');
+        write_nested(out, value, indent);
+    }
+}
+
+fn write_items(out: &mut String, items: &[Value], indent: usize) {
+    for item in items {
+        out.push_str(&" ".repeat(indent));
+        match item {
+            Value::Object(entries) if !entries.is_empty() => {
+                out.push_str("- ");
+                write_entries(out, entries, indent + 2);
+            }
+            _ => {
+                out.push('-');
+                write_nested(out, item, indent);
+            }
+        }
+    }
+}
+
+/// The rest of the line after a key or dash at `indent`, and the lines
+/// nested under it.
+fn write_nested(out: &mut String, value: &Value, indent: usize) {
+    match value {
+        Value::Object(entries) if !entries.is_empty() => {
+            out.push('\n');
+            write_entries(out, entries, indent + 2);
+        }
+        Value::Array(items) if !items.is_empty() => {
+            out.push('\n');
+            write_items(out, items, indent + 2);
+        }
+        scalar => {
+            out.push(' ');
+            write_scalar(out, scalar);
+            out.push('\n');
+        }
+    }
+}
+
+/// A scalar, or an empty mapping or sequence, on one line. Strings are
+/// quoted if they'd read back as something else.
+fn write_scalar(out: &mut String, value: &Value) {
+    match value {
+        Value::String(s) if reads_back(s) => out.push_str(s),
+        Value::Array(_) => out.push_str("[]"),
+        Value::Object(_) => out.push_str("{}"),
+        // JSON's double-quoted strings are YAML's too.
+        value => out.push_str(&value.to_string()),
+    }
+}
+
+/// Whether `s` written plain is read back as the same string.
+fn reads_back(s: &str) -> bool {
+    plain(s) == Value::String(s.to_string())
+        && s.trim() == s
+        && !s.starts_with(|c: char| "[]{},&*!|>'\"%@`#?-".contains(c))
+        && !s.contains(": ")
+        && !s.contains(" #")
+        && !s.ends_with(':')
+        && !s.contains(|c: char| c.is_control())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_yaml() {
+        let doc = parse(
+            "# Hold'em\n\
+             id: holdem\n\
+             name: \"Texas Hold'em\"   # quoted\n\
+             phases:\n\
+             - playerDeal: {cards: 2}\n\
+             - bettingRound:\n    \
+                 name: Pre-flop\n    \
+                 order: BETTING_ORDER_FOLLOW_BLINDS\n\
+             customDeck:\n  \
+               ranks: [2, 'J', \"Q\"]\n  \
+               jokers: 0\n",
+        )
+        .unwrap();
+        let obj = |entries: Vec<(&str, Value)>| {
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v))
+                    .collect(),
+            )
+        };
+        let s = |v: &str| Value::String(v.to_string());
+        assert_eq!(
+            doc.value,
+            obj(vec![
+                ("id", s("holdem")),
+                ("name", s("Texas Hold'em")),
+                (
+                    "phases",
+                    Value::Array(vec![
+                        obj(vec![(
+                            "playerDeal",
+                            obj(vec![("cards", Value::Number(2.0))])
+                        )]),
+                        obj(vec![(
+                            "bettingRound",
+                            obj(vec![
+                                ("name", s("Pre-flop")),
+                                ("order", s("BETTING_ORDER_FOLLOW_BLINDS")),
+                            ])
+                        )]),
+                    ])
+                ),
+                (
+                    "customDeck",
+                    obj(vec![
+                        (
+                            "ranks",
+                            Value::Array(vec![Value::Number(2.0), s("J"), s("Q")])
+                        ),
+                        ("jokers", Value::Number(0.0)),
+                    ])
+                ),
+            ])
+        );
+        assert_eq!(doc.position("phases[1].bettingRound.order"), Some((8, 5)));
+        assert_eq!(doc.position("phases[0].playerDeal.cards"), Some((5, 16)));
+        assert_eq!(doc.position("customDeck.ranks[1]"), Some((10, 14)));
+        assert_eq!(doc.position("phases[1].bettingRound.order.x"), Some((8, 5)));
+    }
+
+    #[test]
+    fn test_print() {
+        let value = parse(
+            "id: holdem\n\
+             name: 'Hold''em: \"no limit\"'\n\
+             tags: []\n\
+             phases:\n\
+             - playerDeal: {cards: 2}\n\
+             - {}\n\
+             - [[1, '2'], -3, ' x', '#', 'null', 'a: b']\n",
+        )
+        .unwrap()
+        .value;
+        let text = print(&value);
+        assert_eq!(
+            text,
+            "id: holdem\n\
+             name: \"Hold'em: \\\"no limit\\\"\"\n\
+             tags: []\n\
+             phases:\n  \
+               - playerDeal:\n      \
+                   cards: 2\n  \
+               - {}\n  \
+               -\n    \
+                 -\n      \
+                   - 1\n      \
+                   - \"2\"\n    \
+                 - -3\n    \
+                 - \" x\"\n    \
+                 - \"#\"\n    \
+                 - \"null\"\n    \
+                 - \"a: b\"\n"
+        );
+        assert_eq!(parse(&text).unwrap().value, value);
+    }
+
+    #[test]
+    fn test_yaml_syntax_errors() {
+        let err = parse("a: 1\n   b: 2\n").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 4));
+        let err = parse("a: 1\na: 2\n").unwrap_err();
+        assert_eq!(err.message, "duplicate key \"a\"");
+        let err = parse("a: |\n  text\n").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 4));
+        assert!(parse("a: [1, 2\n").is_err());
+    }
+}
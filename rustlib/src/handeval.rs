@@ -0,0 +1,994 @@
+//! Poker hand evaluation.
+//!
+//! Hands of any size are reduced to their best five cards and summarized as a
+//! [`HandRank`], which orders like the hands themselves: a better hand always
+//! has a greater rank. Evaluation works on per-suit rank bitmasks, so it does
+//! no allocation and costs about the same for 5, 6 or 7 cards.
+
+use std::fmt;
+
+use crate::cards::{Card, RANK_CHARS};
+use crate::locale::{hand_terms, HandTerms};
+use crate::HandType;
+
+/// Opaque, totally ordered strength of a hand. Greater is better.
+///
+/// Layout, most significant first: a flag for low hands, 7 bits of category
+/// strength, 4 bits of [`HandType`], then up to five 4-bit ranks that break
+/// ties within the category (e.g. the trips rank, then the pair rank, for a
+/// full house). Low hands store their strength and ranks inverted, so that
+/// the lowest hand still has the greatest rank. High and low ranks are not
+/// meant to be compared with each other.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HandRank(u32);
+
+impl HandRank {
+    fn new(strength: u8, hand_type: HandType, ranks: &[u8]) -> Self {
+        let mut v = (strength as u32) << 24 | (hand_type as u32) << 20;
+        for (i, &r) in ranks.iter().take(5).enumerate() {
+            v |= (r as u32 & 0xF) << (16 - 4 * i);
+        }
+        HandRank(v)
+    }
+
+    /// A low hand: lower cards make it better. Straights and flushes don't
+    /// count, and ranks are counted from 1 for the deck's lowest rank, with 0
+    /// for an ace playing low.
+    fn low(hand_type: HandType, ranks: &[u8]) -> Self {
+        let inverted: Vec<u8> = ranks.iter().map(|&r| 15 - r).collect();
+        HandRank(LOW_FLAG | HandRank::new(15 - hand_type as u8, hand_type, &inverted).0)
+    }
+
+    /// A badugi hand of `ranks.len()` cards. More cards always win, and then
+    /// lower cards, like any low hand.
+    fn badugi(ranks: &[u8]) -> Self {
+        let inverted: Vec<u8> = ranks.iter().map(|&r| 15 - r).collect();
+        HandRank(LOW_FLAG | HandRank::new(ranks.len() as u8, HandType::NoPair, &inverted).0)
+    }
+
+    /// A hand scored by points rather than as a poker hand, where more
+    /// points win, or fewer if `lo` is set. Totals beyond about half a
+    /// million either way rank as if they were at that limit.
+    pub fn from_points(points: i64, lo: bool) -> Self {
+        let biased = (points.clamp(-POINTS_BIAS, POINTS_BIAS - 1) + POINTS_BIAS) as u32;
+        if lo {
+            HandRank(LOW_FLAG | (0xF_FFFF - biased))
+        } else {
+            HandRank(biased)
+        }
+    }
+
+    /// The rank as a number that orders as the ranks do, for carrying in
+    /// a message.
+    pub fn to_bits(self) -> u32 {
+        self.0
+    }
+
+    /// The rank that [`HandRank::to_bits`] gave as `bits`.
+    pub fn from_bits(bits: u32) -> Self {
+        HandRank(bits)
+    }
+
+    /// The total of a hand scored by points, or `None` for a poker hand.
+    pub fn points(self) -> Option<i64> {
+        if self.0 & !LOW_FLAG >= 1 << 20 {
+            return None;
+        }
+        let biased = if self.is_low() {
+            0xF_FFFF - (self.0 & 0xF_FFFF)
+        } else {
+            self.0
+        };
+        Some(biased as i64 - POINTS_BIAS)
+    }
+
+    /// The hand in English words, such as "Full House, Kings over Fours".
+    /// Ranks are named as in the standard deck.
+    pub fn describe(self) -> String {
+        self.describe_with(&HandTerms::ENGLISH)
+    }
+
+    /// The hand in words in `locale`, or in English if there are no terms
+    /// for it (see [`crate::locale::hand_terms`]).
+    pub fn describe_in(self, locale: &str) -> String {
+        self.describe_with(hand_terms(locale))
+    }
+
+    /// The hand in words built from `terms`.
+    pub fn describe_with(self, terms: &HandTerms) -> String {
+        if let Some(points) = self.points() {
+            return terms.points.replace("{n}", &points.to_string());
+        }
+        let ranks = self.standard_ranks();
+        let template = match self.hand_type() {
+            _ if self.is_badugi() => terms.badugi,
+            HandType::NoPair if self.is_low() => terms.low,
+            HandType::StraightFlush if !self.is_low() && ranks[0] == 12 => terms.royal_flush,
+            t => terms.hand_types[t as usize],
+        };
+        let mut text = template.replace("{n}", &self.strength().to_string());
+        for (i, &r) in ranks.iter().enumerate() {
+            let name = |names: &[&'static str; 13]| names.get(r).copied().unwrap_or("?");
+            text = text
+                .replace(&format!("{{r{}}}", i + 1), name(&terms.ranks))
+                .replace(&format!("{{p{}}}", i + 1), name(&terms.plural_ranks));
+        }
+        text
+    }
+
+    /// The five cards' ranks in compact notation, such as "KKK44", or the
+    /// total for a points hand. Hands of fewer than five cards are filled
+    /// out with deuces (or aces, for low hands), as their ranks don't say
+    /// how many cards there were.
+    pub fn describe_short(self) -> String {
+        if let Some(points) = self.points() {
+            return points.to_string();
+        }
+        let r = self.standard_ranks();
+        let repeat = |counts: &[usize]| -> Vec<usize> {
+            counts
+                .iter()
+                .zip(r)
+                .flat_map(|(&n, rank)| std::iter::repeat_n(rank, n))
+                .collect()
+        };
+        let ranks = match self.hand_type() {
+            _ if self.is_badugi() => r[..self.strength()].to_vec(),
+            HandType::Straight | HandType::StraightFlush => (0..5)
+                .map(|i| (r[0] as isize - i).rem_euclid(13) as usize)
+                .collect(),
+            HandType::Pair => repeat(&[2, 1, 1, 1]),
+            HandType::TwoPair => repeat(&[2, 2, 1]),
+            HandType::ThreeOfAKind => repeat(&[3, 1, 1]),
+            HandType::FullHouse | HandType::FlushHouse => repeat(&[3, 2]),
+            HandType::FourOfAKind => repeat(&[4, 1]),
+            HandType::FiveOfAKind | HandType::FlushFive => repeat(&[5]),
+            _ => r.to_vec(),
+        };
+        ranks
+            .iter()
+            .map(|&r| RANK_CHARS.get(r).map_or('?', |&c| c as char))
+            .collect()
+    }
+
+    /// The tie-breaking ranks as standard deck ordinals, with low hands'
+    /// ordinals turned back into the cards' ranks.
+    fn standard_ranks(self) -> [usize; 5] {
+        self.ranks().map(|r| match (self.is_low(), r) {
+            (true, 0) => 12,
+            (true, r) => r as usize - 1,
+            (false, r) => r as usize,
+        })
+    }
+
+    fn is_badugi(self) -> bool {
+        self.is_low() && self.hand_type() == HandType::NoPair && self.strength() <= 4
+    }
+
+    /// The category strength, which for a badugi is how many cards it has.
+    fn strength(self) -> usize {
+        ((self.0 >> 24) & 0x7F) as usize
+    }
+
+    /// Whether this rank came from a low (lowball) evaluation.
+    pub fn is_low(self) -> bool {
+        self.0 & LOW_FLAG != 0
+    }
+
+    pub fn hand_type(self) -> HandType {
+        HandType::try_from(((self.0 >> 20) & 0xF) as i32).unwrap_or_default()
+    }
+
+    /// The tie-breaking ranks, most significant first. Only the first few are
+    /// meaningful for some categories (one for a straight, two for quads);
+    /// the rest are zero.
+    ///
+    /// Low hands use low ordinals: 0 is an ace playing low, 1 the deck's
+    /// lowest rank, and so on.
+    pub fn ranks(self) -> [u8; 5] {
+        let mut out = [0; 5];
+        for (i, r) in out.iter_mut().enumerate() {
+            let nibble = ((self.0 >> (16 - 4 * i)) & 0xF) as u8;
+            *r = match (self.is_low(), nibble) {
+                (true, 0) => 0,
+                (true, n) => 15 - n,
+                (false, n) => n,
+            };
+        }
+        out
+    }
+
+    /// The raw ordering key.
+    pub fn value(self) -> u32 {
+        self.0
+    }
+
+    /// The rank whose ordering key is `value`, as packed by the batch
+    /// evaluator.
+    pub(crate) fn from_value(value: u32) -> Self {
+        HandRank(value)
+    }
+}
+
+impl fmt::Debug for HandRank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HandRank({:?}, {:?})", self.hand_type(), self.ranks())
+    }
+}
+
+const LOW_FLAG: u32 = 1 << 31;
+
+/// Added to a points total to store it in the 20 bits below the hand type,
+/// which is left as `HandType::Unknown`.
+const POINTS_BIAS: i64 = 1 << 19;
+
+/// Rules for forming hands with a particular deck.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Evaluator {
+    /// Lowest rank ordinal in the deck (4 for a 6+ deck).
+    pub low_rank: u8,
+
+    /// Highest rank ordinal in the deck.
+    pub high_rank: u8,
+
+    /// Whether the top rank can play low in a straight (A-2-3-4-5).
+    pub ace_low_straight: bool,
+
+    /// The categories a hand can make, strongest first. A hand ranks as the
+    /// first category it makes.
+    pub order: [HandType; 10],
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+/// The usual order of hand categories, strongest first.
+pub const STANDARD_ORDER: [HandType; 10] = [
+    HandType::FiveOfAKind,
+    HandType::StraightFlush,
+    HandType::FourOfAKind,
+    HandType::FullHouse,
+    HandType::Flush,
+    HandType::Straight,
+    HandType::ThreeOfAKind,
+    HandType::TwoPair,
+    HandType::Pair,
+    HandType::NoPair,
+];
+
+/// Short-deck order: with fewer cards per suit a flush is harder to make
+/// than a full house, and trips than a straight.
+pub const SHORT_DECK_ORDER: [HandType; 10] = [
+    HandType::FiveOfAKind,
+    HandType::StraightFlush,
+    HandType::FourOfAKind,
+    HandType::Flush,
+    HandType::FullHouse,
+    HandType::ThreeOfAKind,
+    HandType::Straight,
+    HandType::TwoPair,
+    HandType::Pair,
+    HandType::NoPair,
+];
+
+impl Evaluator {
+    /// Standard high poker with a 52-card deck.
+    pub const STANDARD: Evaluator = Evaluator {
+        low_rank: 0,
+        high_rank: 12,
+        ace_low_straight: true,
+        order: STANDARD_ORDER,
+    };
+
+    /// Six-plus poker with the 36-card deck, where A-6-7-8-9 is the lowest
+    /// straight.
+    pub const SHORT_DECK: Evaluator = Evaluator {
+        low_rank: 4,
+        order: SHORT_DECK_ORDER,
+        ..Self::STANDARD
+    };
+
+    /// The evaluator with `order` ranking the categories, strongest first.
+    /// Categories missing from `order` rank below the listed ones, in their
+    /// usual order.
+    pub fn with_order(mut self, order: &[HandType]) -> Self {
+        let listed = order.iter().filter(|t| STANDARD_ORDER.contains(t));
+        let rest = STANDARD_ORDER.iter().filter(|t| !order.contains(t));
+        for (slot, &t) in self.order.iter_mut().zip(listed.chain(rest)) {
+            *slot = t;
+        }
+        self
+    }
+
+    /// Best high hand that can be made from `cards`. Fewer than five cards
+    /// are ranked by their pairs and high cards alone, as in partial stud
+    /// hands.
+    pub fn evaluate(&self, cards: &[Card]) -> HandRank {
+        self.evaluate_wild(cards, 0, 0)
+    }
+
+    /// Best high hand that can be made from the natural `cards` together
+    /// with `wilds` wild cards, which can stand for any card, and `bugs`,
+    /// which can only stand for an ace or complete a straight or flush. Five
+    /// of a kind beats a straight flush.
+    pub fn evaluate_wild(&self, cards: &[Card], wilds: usize, bugs: usize) -> HandRank {
+        self.rank_tally(&Tally::new(self, cards, wilds, bugs))
+    }
+
+    fn rank_tally(&self, tally: &Tally) -> HandRank {
+        self.order
+            .iter()
+            .find_map(|&t| Some(self.rank(t, &self.make(tally, t)?)))
+            .unwrap_or_else(|| self.rank(HandType::NoPair, &bit_ranks(top_bits(tally.all, 5))))
+    }
+
+    /// The tie-breaking ranks of the best `hand_type` in `t`, if it makes
+    /// one.
+    fn make(&self, t: &Tally, hand_type: HandType) -> Option<Vec<u8>> {
+        let flush_suits = || (0..4).filter(|&s| t.suit_counts[s] + t.fill >= 5);
+        let kickers = |mask: u16, n| bit_ranks(top_bits(t.all & !mask, n));
+        match hand_type {
+            HandType::FiveOfAKind => Some(vec![self.group(t, 5, None)?]),
+            HandType::StraightFlush => flush_suits()
+                .filter_map(|s| self.straight_high_filled(t.suits[s], t.fill))
+                .max()
+                .map(|high| vec![high]),
+            HandType::FourOfAKind => {
+                let q = self.group(t, 4, None)?;
+                Some([vec![q], kickers(1 << q, 1)].concat())
+            }
+            // The best trips and pair, sharing the wild cards between them.
+            HandType::FullHouse => self.ranks().rev().find_map(|trips| {
+                let need = 3u8.saturating_sub(t.counts[trips as usize]);
+                if need > t.wilds {
+                    return None;
+                }
+                let pair = self.ranks().rev().find(|&p| {
+                    p != trips && need + 2u8.saturating_sub(t.counts[p as usize]) <= t.wilds
+                })?;
+                Some(vec![trips, pair])
+            }),
+            HandType::Flush => flush_suits()
+                .map(|s| self.fill_top(t.suits[s], t.fill))
+                .max()
+                .map(bit_ranks),
+            HandType::Straight => self
+                .straight_high_filled(t.natural, t.fill)
+                .map(|high| vec![high]),
+            HandType::ThreeOfAKind => {
+                let trips = self.group(t, 3, None)?;
+                Some([vec![trips], kickers(1 << trips, 2)].concat())
+            }
+            // A wild card turns any pair into trips, so two pair is always
+            // natural.
+            HandType::TwoPair if t.wilds == 0 => {
+                let high = self.group(t, 2, None)?;
+                let low = self.group(t, 2, Some(high))?;
+                Some([vec![high, low], kickers(1 << high | 1 << low, 1)].concat())
+            }
+            HandType::Pair => {
+                let pair = self.group(t, 2, None)?;
+                Some([vec![pair], kickers(1 << pair, 3)].concat())
+            }
+            HandType::NoPair => Some(kickers(0, 5)),
+            _ => None,
+        }
+    }
+
+    /// Highest rank, other than `except`, that the wild cards can bring to
+    /// `n` of a kind.
+    fn group(&self, t: &Tally, n: u8, except: Option<u8>) -> Option<u8> {
+        self.ranks()
+            .rev()
+            .find(|&r| Some(r) != except && t.counts[r as usize] + t.wilds >= n)
+    }
+
+    fn ranks(&self) -> std::ops::RangeInclusive<u8> {
+        self.low_rank..=self.high_rank
+    }
+
+    /// Best low hand that can be made from `cards`: the five lowest cards,
+    /// with as few paired ranks as possible. Straights and flushes don't
+    /// count against a low hand. If `ace_low` is set, the top rank plays
+    /// below the bottom rank, as in ace-to-five lowball.
+    pub fn evaluate_low(&self, cards: &[Card], ace_low: bool) -> HandRank {
+        self.evaluate_low_wild(cards, ace_low, 0)
+    }
+
+    /// Best low hand from the natural `cards` and `wilds` wild cards, each
+    /// of which plays as the lowest rank the hand is missing.
+    pub fn evaluate_low_wild(&self, cards: &[Card], ace_low: bool, wilds: usize) -> HandRank {
+        let mut counts = [0u8; 16];
+        for c in cards {
+            counts[self.low_ordinal(c.rank, ace_low) as usize] += 1;
+        }
+        // Ordinals a wild card can take.
+        let open = if ace_low {
+            0..=(self.high_rank - self.low_rank) as usize
+        } else {
+            1..=(self.high_rank - self.low_rank + 1) as usize
+        };
+
+        // Take each rank once, lowest first, then pair up the lowest ranks
+        // if that isn't enough cards.
+        let mut picked = [0u8; 16];
+        let mut n = 0;
+        let mut wilds = wilds;
+        'fill: for copy in 1..=4 {
+            for r in 0..16 {
+                if n == 5 {
+                    break 'fill;
+                }
+                if counts[r] >= copy {
+                    picked[r] += 1;
+                    n += 1;
+                } else if copy == 1 && wilds > 0 && open.contains(&r) {
+                    picked[r] += 1;
+                    n += 1;
+                    wilds -= 1;
+                }
+            }
+        }
+
+        let mut groups: Vec<(u8, u8)> = (0..16u8)
+            .filter(|&r| picked[r as usize] > 0)
+            .map(|r| (picked[r as usize], r))
+            .collect();
+        groups.sort_unstable_by(|a, b| b.cmp(a));
+        let ranks: Vec<u8> = groups.iter().map(|&(_, r)| r).collect();
+        let hand_type = match (groups.first().map(|g| g.0), groups.get(1).map(|g| g.0)) {
+            (Some(4), _) => HandType::FourOfAKind,
+            (Some(3), Some(2)) => HandType::FullHouse,
+            (Some(3), _) => HandType::ThreeOfAKind,
+            (Some(2), Some(2)) => HandType::TwoPair,
+            (Some(2), _) => HandType::Pair,
+            _ => HandType::NoPair,
+        };
+        HandRank::low(hand_type, &ranks)
+    }
+
+    /// Best deuce-to-seven low hand that can be made from `cards`: the five
+    /// cards that make the weakest high hand. Unlike [`Self::evaluate_low`],
+    /// straights and flushes count against the hand and the top rank only
+    /// plays high, so 7-5-4-3-2 of mixed suits is the best hand.
+    pub fn evaluate_deuce_seven(&self, cards: &[Card]) -> HandRank {
+        let high = Evaluator {
+            ace_low_straight: false,
+            order: STANDARD_ORDER,
+            ..*self
+        };
+        let n = cards.len();
+        let worst = if n <= 5 {
+            high.evaluate(cards)
+        } else {
+            let mut hand = [Card::JOKER; 5];
+            (0u32..1 << n.min(31))
+                .filter(|m| m.count_ones() == 5)
+                .map(|m| {
+                    let picked = (0..n).filter(|i| m & (1 << i) != 0);
+                    for (slot, i) in hand.iter_mut().zip(picked) {
+                        *slot = cards[i];
+                    }
+                    high.evaluate(&hand)
+                })
+                .min()
+                .unwrap_or_else(|| high.evaluate(&[]))
+        };
+        let ranks: Vec<u8> = worst
+            .ranks()
+            .iter()
+            .map(|&r| self.low_ordinal(r, false))
+            .collect();
+        HandRank::low(worst.hand_type(), &ranks)
+    }
+
+    /// Best badugi that can be made from `cards`: the most cards, up to
+    /// four, with no two of the same rank or suit, and then the lowest.
+    /// Aces play low. The ranks are reported highest first, and a hand of
+    /// fewer than four cards leaves the rest as zero.
+    pub fn evaluate_badugi(&self, cards: &[Card]) -> HandRank {
+        let n = cards.len().min(16);
+        let mut best: Option<HandRank> = None;
+        for m in 1u32..1 << n {
+            if m.count_ones() > 4 {
+                continue;
+            }
+            let (mut ranks, mut suits) = (0u16, 0u16);
+            let mut hand = Vec::with_capacity(4);
+            for (i, c) in cards[..n].iter().enumerate() {
+                if m & (1 << i) == 0 {
+                    continue;
+                }
+                let rank = self.low_ordinal(c.rank, true);
+                if ranks & (1 << rank) != 0 || suits & (1 << (c.suit & 0xF)) != 0 {
+                    hand.clear();
+                    break;
+                }
+                ranks |= 1 << rank;
+                suits |= 1 << (c.suit & 0xF);
+                hand.push(rank);
+            }
+            if hand.is_empty() {
+                continue;
+            }
+            hand.sort_unstable_by(|a, b| b.cmp(a));
+            let rank = HandRank::badugi(&hand);
+            best = best.max(Some(rank));
+        }
+        best.unwrap_or_else(|| HandRank::badugi(&[]))
+    }
+
+    /// The ordinal of `rank` in a low hand: 0 for an ace playing low, 1 for
+    /// the deck's lowest rank, and so on.
+    fn low_ordinal(&self, rank: u8, ace_low: bool) -> u8 {
+        if ace_low && rank == self.high_rank {
+            0
+        } else {
+            (rank.saturating_sub(self.low_rank) + 1).min(15)
+        }
+    }
+
+    fn rank(&self, hand_type: HandType, ranks: &[u8]) -> HandRank {
+        let place = self.order.iter().position(|&t| t == hand_type);
+        let strength = place.map_or(0, |i| (self.order.len() - i) as u8);
+        HandRank::new(strength, hand_type, ranks)
+    }
+
+    /// Highest rank of a five-card straight within `mask`, with up to
+    /// `fill` missing ranks made up by wild cards. A wheel reports its top
+    /// as the fourth-lowest rank (the five, in a standard deck).
+    fn straight_high_filled(&self, mask: u16, fill: u8) -> Option<u8> {
+        let missing = |window: u16| 5 - (mask & window).count_ones() as u8;
+        for high in (self.low_rank + 4..=self.high_rank).rev() {
+            if missing(0x1F << (high - 4)) <= fill {
+                return Some(high);
+            }
+        }
+        if self.ace_low_straight && self.high_rank >= self.low_rank + 4 {
+            let wheel = (1u16 << self.high_rank) | (0xF << self.low_rank);
+            if missing(wheel) <= fill {
+                return Some(self.low_rank + 3);
+            }
+        }
+        None
+    }
+
+    /// The five highest ranks of a flush in the suit whose ranks are
+    /// `mask`, with wild cards taking the highest ranks it is missing.
+    fn fill_top(&self, mask: u16, mut fill: u8) -> u16 {
+        let mut out = 0u16;
+        for r in (self.low_rank..=self.high_rank).rev() {
+            if out.count_ones() == 5 {
+                break;
+            }
+            if mask & (1 << r) != 0 {
+                out |= 1 << r;
+            } else if fill > 0 {
+                out |= 1 << r;
+                fill -= 1;
+            }
+        }
+        out
+    }
+}
+
+/// The cards of a hand, counted up for [`Evaluator::evaluate_wild`].
+#[derive(Clone, Copy, Debug)]
+struct Tally {
+    /// Cards of each rank, with the bugs counted as aces.
+    counts: [u8; 16],
+    suits: [u16; 4],
+    suit_counts: [u8; 4],
+
+    /// Ranks of the natural cards.
+    natural: u16,
+
+    /// Ranks of the natural cards and of any bugs playing as aces.
+    all: u16,
+
+    /// Fully wild cards, capped at five.
+    wilds: u8,
+
+    /// Cards that can complete a straight or flush: the wild cards and the
+    /// bugs.
+    fill: u8,
+}
+
+impl Tally {
+    fn new(evaluator: &Evaluator, cards: &[Card], wilds: usize, bugs: usize) -> Self {
+        let mut t = Tally {
+            counts: [0; 16],
+            suits: [0; 4],
+            suit_counts: [0; 4],
+            natural: 0,
+            all: 0,
+            wilds: wilds.min(5) as u8,
+            fill: (wilds + bugs).min(5) as u8,
+        };
+        for c in cards {
+            t.add(*c);
+        }
+        t.all = t.natural;
+        if bugs > 0 {
+            let ace = evaluator.high_rank as usize & 0xF;
+            t.counts[ace] = t.counts[ace].saturating_add(bugs.min(5) as u8);
+            t.all |= 1 << ace;
+        }
+        t
+    }
+
+    /// Counts one more natural card.
+    fn add(&mut self, c: Card) {
+        self.counts[c.rank as usize & 0xF] += 1;
+        self.natural |= 1 << (c.rank & 0xF);
+        self.all = self.natural;
+        if (c.suit as usize) < 4 {
+            self.suits[c.suit as usize] |= 1 << c.rank;
+            self.suit_counts[c.suit as usize] += 1;
+        }
+    }
+}
+
+/// Ranks hands that differ only in their last few cards, such as the same
+/// hole cards and flop with many turns and rivers, without counting the
+/// shared cards up again for each. It keeps the counts after each card in
+/// turn, so [`Self::set`] only redoes the cards past those the new hand
+/// shares with the last, and [`Self::rank_with`] ranks one card more
+/// without keeping it.
+///
+/// Hands rank as with [`Evaluator::evaluate`].
+#[derive(Clone, Debug)]
+pub struct IncrementalEvaluator {
+    evaluator: Evaluator,
+    cards: Vec<Card>,
+    /// The counts after no cards, after the first, and so on.
+    tallies: Vec<Tally>,
+}
+
+impl IncrementalEvaluator {
+    pub fn new(evaluator: Evaluator) -> Self {
+        IncrementalEvaluator {
+            evaluator,
+            cards: Vec::new(),
+            tallies: vec![Tally::new(&evaluator, &[], 0, 0)],
+        }
+    }
+
+    /// The cards in the hand, in the order they were added.
+    pub fn cards(&self) -> &[Card] {
+        &self.cards
+    }
+
+    /// Adds `card` to the hand.
+    pub fn push(&mut self, card: Card) {
+        let mut tally = *self.tallies.last().unwrap();
+        tally.add(card);
+        self.tallies.push(tally);
+        self.cards.push(card);
+    }
+
+    /// Takes the last card added back out of the hand.
+    pub fn pop(&mut self) -> Option<Card> {
+        let card = self.cards.pop()?;
+        self.tallies.pop();
+        Some(card)
+    }
+
+    /// Makes the hand `cards`, keeping the counts for as many leading
+    /// cards as it already has in the same order.
+    pub fn set(&mut self, cards: &[Card]) {
+        let shared = self
+            .cards
+            .iter()
+            .zip(cards)
+            .take_while(|(a, b)| a == b)
+            .count();
+        while self.cards.len() > shared {
+            self.pop();
+        }
+        for &card in &cards[shared..] {
+            self.push(card);
+        }
+    }
+
+    /// The rank of the hand.
+    pub fn rank(&self) -> HandRank {
+        self.evaluator.rank_tally(self.tallies.last().unwrap())
+    }
+
+    /// The rank of the hand with `card` added, leaving the hand as it is.
+    pub fn rank_with(&self, card: Card) -> HandRank {
+        let mut tally = *self.tallies.last().unwrap();
+        tally.add(card);
+        self.evaluator.rank_tally(&tally)
+    }
+}
+
+/// Evaluates the best high hand with standard rules.
+pub fn evaluate(cards: &[Card]) -> HandRank {
+    Evaluator::STANDARD.evaluate(cards)
+}
+
+/// Keeps only the `n` highest set bits of `mask`.
+fn top_bits(mut mask: u16, n: usize) -> u16 {
+    while mask.count_ones() as usize > n {
+        mask &= mask - 1;
+    }
+    mask
+}
+
+/// Set bits of `mask` as ranks, highest first.
+fn bit_ranks(mask: u16) -> Vec<u8> {
+    (0..16u8).rev().filter(|&r| mask & (1 << r) != 0).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::parse_cards;
+
+    fn eval(s: &str) -> HandRank {
+        evaluate(&parse_cards(s).unwrap())
+    }
+
+    #[test]
+    fn test_categories() {
+        assert_eq!(eval("As Ks Qs Js Ts").hand_type(), HandType::StraightFlush);
+        assert_eq!(eval("9c 9d 9h 9s 2c").hand_type(), HandType::FourOfAKind);
+        assert_eq!(eval("9c 9d 9h 2s 2c").hand_type(), HandType::FullHouse);
+        assert_eq!(eval("Ah 9h 7h 4h 2h").hand_type(), HandType::Flush);
+        assert_eq!(eval("Ah 2c 3d 4s 5h").hand_type(), HandType::Straight);
+        assert_eq!(eval("9c 9d 9h 3s 2c").hand_type(), HandType::ThreeOfAKind);
+        assert_eq!(eval("9c 9d 3h 3s 2c").hand_type(), HandType::TwoPair);
+        assert_eq!(eval("9c 9d 4h 3s 2c").hand_type(), HandType::Pair);
+        assert_eq!(eval("Kc 9d 4h 3s 2c").hand_type(), HandType::NoPair);
+    }
+
+    #[test]
+    fn test_ordering_and_kickers() {
+        assert!(eval("Ah 2c 3d 4s 5h") < eval("2h 3c 4d 5s 6h"));
+        assert!(eval("Kc Kd 4h 3s 2c") < eval("Kc Kd 5h 3s 2c"));
+        assert!(eval("9c 9d 3h 3s Ac") > eval("9c 9d 3h 3s Kc"));
+        // Equal hands compare equal regardless of suits.
+        assert_eq!(eval("Ah Kd 9c 7s 2h"), eval("Ad Kh 9s 7c 2d"));
+    }
+
+    #[test]
+    fn test_seven_cards() {
+        // Flush beats the straight on board.
+        assert_eq!(eval("Ah 2h 6h 7c 8h 9h Td").hand_type(), HandType::Flush);
+        // Two trips make a full house using the higher trips.
+        assert_eq!(eval("Kc Kd Kh 4s 4c 4d 2c"), eval("Kc Kd Kh 4s 4c"));
+        // Three pairs play the top two with the best remaining kicker.
+        assert_eq!(eval("Ac Ad Kh Ks 3c 3d Qs"), eval("Ac Ad Kh Ks Qs"));
+        // Partial stud hands: a pair beats any high card.
+        assert!(eval("2c 2d") > eval("Ac Kd Qh"));
+    }
+
+    #[test]
+    fn test_ace_five_low() {
+        let low = |s: &str| Evaluator::STANDARD.evaluate_low(&parse_cards(s).unwrap(), true);
+        // The wheel is the best low; straights and flushes don't count.
+        assert!(low("Ah 2h 3h 4h 5h") > low("Ac 2d 3h 4s 6c"));
+        assert_eq!(low("Ah 2h 3h 4h 5h").hand_type(), HandType::NoPair);
+        assert_eq!(low("Ah 2h 3h 4h 5h").ranks(), [4, 3, 2, 1, 0]);
+        // Lower top card wins, then the next card down.
+        assert!(low("7c 5d 4h 3s 2c") > low("8c 3d 2h As 4c"));
+        assert!(low("8c 5d 4h 3s 2c") > low("8c 6d 2h As 4c"));
+        // Any unpaired hand beats a pair; the best five of seven play.
+        assert!(low("Kc Qd Jh 9s 8c") > low("Ac Ad 2h 3s 4c"));
+        assert_eq!(low("Kc Kd 7h 5s 4c 3d 2h"), low("7h 5s 4c 3d 2h"));
+        assert!(low("Ac Ad 2h 3s 4c").is_low());
+    }
+
+    #[test]
+    fn test_deuce_seven_low() {
+        let low = |s: &str| Evaluator::STANDARD.evaluate_deuce_seven(&parse_cards(s).unwrap());
+        // Aces are high, so the wheel is only ace-high.
+        assert!(low("7c 5d 4h 3s 2c") > low("7c 6d 4h 3s 2c"));
+        assert!(low("8c 5d 4h 3s 2c") > low("Ah 2c 3d 4s 5h"));
+        assert_eq!(low("Ah 2c 3d 4s 5h").hand_type(), HandType::NoPair);
+        assert_eq!(low("7c 5d 4h 3s 2c").ranks(), [6, 4, 3, 2, 1]);
+        // Straights and flushes count against the hand: even a pair beats
+        // them.
+        assert_eq!(low("6c 5d 4h 3s 2c").hand_type(), HandType::Straight);
+        assert!(low("2c 2d 3h 4s 5c") > low("6c 5d 4h 3s 2c"));
+        assert!(low("Kc Qd Jh 9s 8c") > low("9h 7h 5h 3h 2h"));
+        // The best five of seven play.
+        assert_eq!(low("Kc Kd 7h 5s 4c 3d 2h"), low("7h 5s 4c 3d 2h"));
+        assert!(low("7h 5s 4c 3d 2h").is_low());
+    }
+
+    #[test]
+    fn test_badugi() {
+        let badugi = |s: &str| Evaluator::STANDARD.evaluate_badugi(&parse_cards(s).unwrap());
+        // Any four-card badugi beats any three-card hand.
+        assert!(badugi("Kc Qd Jh Ts") > badugi("Ac 2d 3h 4h"));
+        assert_eq!(badugi("4c 3d 2h As").ranks(), [3, 2, 1, 0, 0]);
+        assert!(badugi("4c 3d 2h As") > badugi("5c 3d 2h As"));
+        // Pairs and suited cards play only one of each.
+        assert_eq!(badugi("Ac Ad 2h 3s"), badugi("Ac 2h 3s"));
+        assert_eq!(badugi("Ac 2c 3c 4c"), badugi("Ac"));
+        assert!(badugi("Ac 2d 3h 5h") > badugi("Ac 2d 4h 5h"));
+    }
+
+    #[test]
+    fn test_short_deck() {
+        let short = |s: &str| Evaluator::SHORT_DECK.evaluate(&parse_cards(s).unwrap());
+        assert_eq!(short("Ah 6c 7d 8s 9h").hand_type(), HandType::Straight);
+        assert_eq!(short("Ah 6c 7d 8s 9h").ranks()[0], 7);
+        assert!(short("Ah 6c 7d 8s 9h") < short("6h 7c 8d 9s Th"));
+        assert!(short("Ah Th 9h 7h 6h") > short("Kc Kd Kh 6s 6c"));
+        assert!(short("6c 6d 6h Js Qc") > short("Ah Kc Qd Js Th"));
+        // Seven cards with trips and a straight play the trips.
+        assert_eq!(
+            short("6c 6d 6h 7s 8c 9d Th").hand_type(),
+            HandType::ThreeOfAKind
+        );
+        // Both a flush and a full house play the flush.
+        assert_eq!(
+            short("Kh Kd Kc 9h 9s 7h 6h").hand_type(),
+            HandType::FullHouse
+        );
+        assert_eq!(
+            short("Kh Kd Kc 9h 9s 7h 6h Jh").hand_type(),
+            HandType::Flush
+        );
+
+        let order = Evaluator::STANDARD.with_order(&[HandType::Flush]);
+        assert_eq!(order.order[0], HandType::Flush);
+        assert_eq!(order.order[1..5], STANDARD_ORDER[..4]);
+        assert_eq!(order.order[5..], STANDARD_ORDER[5..]);
+    }
+
+    #[test]
+    fn test_wild_cards() {
+        let wild =
+            |s: &str, w, bugs| Evaluator::STANDARD.evaluate_wild(&parse_cards(s).unwrap(), w, bugs);
+        // Five of a kind beats a royal flush.
+        assert_eq!(wild("9c 9d 9h 9s", 1, 0).hand_type(), HandType::FiveOfAKind);
+        assert!(wild("9c 9d 9h 9s", 1, 0) > eval("As Ks Qs Js Ts"));
+        assert_eq!(wild("Ac Kc Qc Jc", 1, 0).ranks()[0], 12);
+        assert_eq!(
+            wild("Ac Kc Qc Jc", 1, 0).hand_type(),
+            HandType::StraightFlush
+        );
+        // The wild card fills the gap, and prefers the higher straight.
+        assert_eq!(wild("9c Td Qh Ks", 1, 0), eval("9c Td Jh Qs Kd"));
+        assert_eq!(wild("2c 3d 4h 5s", 1, 0), eval("2c 3d 4h 5s 6d"));
+        // Wild cards make the best trips and pair they can, and the highest
+        // flush.
+        assert_eq!(wild("Ks Kd 7h 7c", 1, 0), eval("Ks Kd Kh 7h 7c"));
+        assert_eq!(wild("Kh 9h 7h 2h", 1, 0), eval("Ah Kh 9h 7h 2h"));
+        assert_eq!(wild("Kh 9d 7s 2c", 2, 0), eval("Kh Kd Ks 9d 7s"));
+        assert_eq!(wild("", 5, 0).hand_type(), HandType::FiveOfAKind);
+        // The bug only plays as an ace, or to complete a straight or flush.
+        assert_eq!(wild("Kc Kd 7h 5s", 0, 1), eval("Kc Kd Ah 7h 5s"));
+        assert_eq!(wild("Ac Ad 7h 5s", 0, 1), eval("Ac Ad Ah 7h 5s"));
+        assert_eq!(wild("8c 9d Th Js", 0, 1), eval("8c 9d Th Js Qd"));
+        assert_eq!(wild("Kh 9h 7h 2h", 0, 1), eval("Ah Kh 9h 7h 2h"));
+        // In a low hand, a wild card is the lowest card missing.
+        let low =
+            |s: &str, w| Evaluator::STANDARD.evaluate_low_wild(&parse_cards(s).unwrap(), true, w);
+        assert_eq!(low("2c 3d 4h 5s", 1).ranks(), [4, 3, 2, 1, 0]);
+        assert_eq!(low("Ac 3d 4h 5s", 1).ranks(), [4, 3, 2, 1, 0]);
+        assert_eq!(low("Ac 2d 3h 4s 5c 6d", 1), low("Ac 2d 3h 4s 5c", 0));
+    }
+
+    #[test]
+    fn test_describe() {
+        for (hand, long, short) in [
+            ("Kc Kd Kh 4s 4c", "Full House, Kings over Fours", "KKK44"),
+            ("As Ks Qs Js Ts", "Royal Flush", "AKQJT"),
+            ("5c 4d 3h 2s Ac", "Straight, Five High", "5432A"),
+            ("9c 9d 3h 3s 2c", "Two Pair, Nines and Threes", "99332"),
+            ("Qc 9d 4h 3s 2c", "High Card, Queen", "Q9432"),
+        ] {
+            assert_eq!(eval(hand).describe(), long);
+            assert_eq!(eval(hand).describe_short(), short);
+        }
+        assert_eq!(
+            eval("Kc Kd Kh 4s 4c").describe_in("de-CH"),
+            "Full House, Könige und Vieren"
+        );
+
+        let cards = |s: &str| parse_cards(s).unwrap();
+        let low = Evaluator::STANDARD.evaluate_low(&cards("8c 6d 5h 3s 2c"), true);
+        assert_eq!(low.describe(), "Eight-Six Low");
+        assert_eq!(low.describe_short(), "86532");
+        let wheel = Evaluator::STANDARD.evaluate_low(&cards("5c 4d 3h 2s Ac"), true);
+        assert_eq!(wheel.describe_short(), "5432A");
+        let badugi = Evaluator::STANDARD.evaluate_badugi(&cards("7c 4d 3h As"));
+        assert_eq!(badugi.describe(), "4-Card Badugi, Seven High");
+        assert_eq!(badugi.describe_short(), "743A");
+        assert_eq!(HandRank::from_points(3, false).describe(), "3 Points");
+        assert_eq!(HandRank::from_points(-2, true).describe_short(), "-2");
+    }
+
+    #[test]
+    fn test_points() {
+        for lo in [false, true] {
+            for points in [-3, 0, 41] {
+                assert_eq!(HandRank::from_points(points, lo).points(), Some(points));
+            }
+        }
+        assert!(HandRank::from_points(5, false) > HandRank::from_points(-5, false));
+        assert!(HandRank::from_points(5, true) < HandRank::from_points(-5, true));
+        assert_eq!(
+            HandRank::from_points(i64::MAX, false).points(),
+            Some(POINTS_BIAS - 1)
+        );
+        assert_eq!(
+            evaluate(&parse_cards("2c 3d 4h 5s 7c").unwrap()).points(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_incremental() {
+        let deck: Vec<Card> = (0..4)
+            .flat_map(|s| (0..13).map(move |r| Card::new(r, s)))
+            .collect();
+        let mut rng = crate::cards::SplitMix64(3);
+        let mut incremental = IncrementalEvaluator::new(Evaluator::SHORT_DECK);
+        for _ in 0..2000 {
+            let mut cards = deck.clone();
+            crate::shuffle::fisher_yates(&mut cards, &mut rng);
+            // Hold the first four cards most of the time, as a Monte Carlo
+            // loop over the last board cards would.
+            let keep = if cards[0].rank < 3 { 0 } else { 4 };
+            let mut hand = incremental.cards().to_vec();
+            hand.truncate(keep);
+            let fresh: Vec<Card> = cards
+                .iter()
+                .filter(|c| !hand.contains(c))
+                .copied()
+                .collect();
+            hand.extend(&fresh[..6 - hand.len()]);
+            incremental.set(&hand);
+            assert_eq!(incremental.cards(), hand.as_slice());
+            assert_eq!(incremental.rank(), Evaluator::SHORT_DECK.evaluate(&hand));
+            let river = *cards.iter().find(|c| !hand.contains(c)).unwrap();
+            hand.push(river);
+            assert_eq!(
+                incremental.rank_with(river),
+                Evaluator::SHORT_DECK.evaluate(&hand)
+            );
+        }
+        assert_eq!(
+            incremental.pop().map(|_| incremental.cards().len()),
+            Some(5)
+        );
+        incremental.set(&[]);
+        assert_eq!(incremental.pop(), None);
+        assert_eq!(incremental.rank(), Evaluator::SHORT_DECK.evaluate(&[]));
+    }
+
+    #[test]
+    fn test_all_five_card_hands() {
+        let deck: Vec<Card> = (0..4)
+            .flat_map(|s| (0..13).map(move |r| Card::new(r, s)))
+            .collect();
+        let mut counts = [0u32; 10];
+        let n = deck.len();
+        for a in 0..n {
+            for b in a + 1..n {
+                for c in b + 1..n {
+                    for d in c + 1..n {
+                        for e in d + 1..n {
+                            let hand = [deck[a], deck[b], deck[c], deck[d], deck[e]];
+                            counts[evaluate(&hand).hand_type() as usize] += 1;
+                        }
+                    }
+                }
+            }
+        }
+        assert_eq!(
+            counts[1..],
+            [1302540, 1098240, 123552, 54912, 10200, 5108, 3744, 624, 40]
+        );
+    }
+}
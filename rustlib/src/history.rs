@@ -0,0 +1,250 @@
+//! Hand files: a hand played to the end, as a JSON object that the CLI
+//! saves for `snapfold replay` and the server keeps in its history.
+//!
+//! A hand file holds the game definition, the players' names, and the hand
+//! packed as a [`CompactState`] in hex. The engine is deterministic, so
+//! playing the packed inputs back deals the same cards and produces the
+//! same events. A hand a server dealt also says which table it was at and
+//! when. The events are written out too, as a spectator saw them, for
+//! readers without an engine; they aren't read back, since the packed
+//! hand has them all.
+
+use crate::compact::{CompactError, CompactState};
+use crate::engine::{Audience, GameEngine};
+use crate::events;
+use crate::fingerprint::{hex, unhex};
+use crate::gamedef::json::{self, Value};
+use crate::gamedef::protojson::{fields, items, string, uint64, FieldError, Result};
+use crate::gamedef::{self, codec};
+use crate::GameStructure;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HandFile {
+    pub game: GameStructure,
+    /// By seat, empty for a seat no one sat in.
+    pub names: Vec<String>,
+    pub hand: CompactState,
+    /// The server table the hand was dealt at, if it was.
+    pub table: Option<Table>,
+    pub time: Option<Time>,
+}
+
+/// Where a server dealt a hand.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Table {
+    pub id: String,
+    /// The hand's number at the table, from 1.
+    pub hand: u64,
+    /// Who each seat's player signed in as, on a server that signs players
+    /// in.
+    pub users: Vec<Option<String>>,
+}
+
+/// When a hand started and ended, in seconds since the Unix epoch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Time {
+    pub started: u64,
+    pub ended: u64,
+}
+
+impl HandFile {
+    pub fn new(engine: &GameEngine, names: &[String]) -> std::result::Result<Self, CompactError> {
+        Ok(HandFile {
+            game: engine.game().clone(),
+            names: names.to_vec(),
+            hand: CompactState::capture(engine)?,
+            table: None,
+            time: None,
+        })
+    }
+
+    /// The file as JSON. The events are left out if the hand doesn't play
+    /// back.
+    pub fn to_json(&self) -> Value {
+        let game = codec::encode(&self.game);
+        let names = self.names.iter().cloned().map(Value::String).collect();
+        let mut fields = vec![
+            ("game".to_string(), game),
+            ("players".to_string(), Value::Array(names)),
+            ("hand".to_string(), Value::String(hex(self.hand.as_bytes()))),
+        ];
+        if let Some(table) = &self.table {
+            let users = table
+                .users
+                .iter()
+                .map(|user| user.clone().map_or(Value::Null, Value::String))
+                .collect();
+            let table = Value::Object(vec![
+                ("id".to_string(), Value::String(table.id.clone())),
+                ("hand".to_string(), Value::Number(table.hand as f64)),
+                ("users".to_string(), Value::Array(users)),
+            ]);
+            fields.push(("table".to_string(), table));
+        }
+        if let Some(time) = &self.time {
+            let time = Value::Object(vec![
+                ("started".to_string(), Value::Number(time.started as f64)),
+                ("ended".to_string(), Value::Number(time.ended as f64)),
+            ]);
+            fields.push(("time".to_string(), time));
+        }
+        if let Ok(engine) = self.hand.restore(self.game.clone()) {
+            let events = engine
+                .events()
+                .iter()
+                .map(|e| events::encode(&e.message_for(Audience::Spectator)))
+                .collect();
+            fields.push(("events".to_string(), Value::Array(events)));
+        }
+        Value::Object(fields)
+    }
+
+    /// Reads a hand file, without checking that the hand plays back.
+    pub fn parse(text: &str) -> std::result::Result<Self, String> {
+        let value = json::parse(text).map_err(|e| e.message)?;
+        if !matches!(value, Value::Object(_)) {
+            return Err("a hand file is a JSON object".to_string());
+        }
+        let (mut game, mut names, mut hand, mut table, mut time) = (None, None, None, None, None);
+        for (key, v, path) in fields(&value, "").map_err(|e| e.to_string())? {
+            match key.as_str() {
+                "game" => {
+                    game = Some(
+                        gamedef::from_json(&v.to_string())
+                            .map_err(|e| format!("in \"game\": {}", e))?,
+                    )
+                }
+                "players" => names = Some(names_from(v, &path).map_err(|e| e.to_string())?),
+                "hand" => {
+                    let text = string(v, &path).map_err(|e| e.to_string())?;
+                    hand = Some(unhex(&text).ok_or("\"hand\" isn't hex")?);
+                }
+                "table" => table = Some(table_from(v, &path).map_err(|e| e.to_string())?),
+                "time" => time = Some(time_from(v, &path).map_err(|e| e.to_string())?),
+                _ => {}
+            }
+        }
+        let missing = |name: &str| format!("no {:?} field", name);
+        Ok(HandFile {
+            game: game.ok_or_else(|| missing("game"))?,
+            names: names.ok_or_else(|| missing("players"))?,
+            hand: CompactState::from_bytes(&hand.ok_or_else(|| missing("hand"))?),
+            table,
+            time,
+        })
+    }
+}
+
+fn names_from(value: &Value, path: &str) -> Result<Vec<String>> {
+    items(value, path)?
+        .into_iter()
+        .map(|(v, p)| string(v, &p))
+        .collect()
+}
+
+fn table_from(value: &Value, path: &str) -> Result<Table> {
+    let mut table = Table {
+        id: String::new(),
+        hand: 0,
+        users: Vec::new(),
+    };
+    for (key, v, path) in fields(value, path)? {
+        match key.as_str() {
+            "id" => table.id = string(v, &path)?,
+            "hand" => table.hand = uint64(v, &path)?,
+            "users" => {
+                let Value::Array(users) = v else {
+                    return Err(FieldError {
+                        path,
+                        message: "expected a list".to_string(),
+                    });
+                };
+                table.users = users
+                    .iter()
+                    .enumerate()
+                    .map(|(i, user)| match user {
+                        Value::Null => Ok(None),
+                        user => string(user, &format!("{}[{}]", path, i)).map(Some),
+                    })
+                    .collect::<Result<_>>()?;
+            }
+            _ => {}
+        }
+    }
+    Ok(table)
+}
+
+fn time_from(value: &Value, path: &str) -> Result<Time> {
+    let mut time = Time {
+        started: 0,
+        ended: 0,
+    };
+    for (key, v, path) in fields(value, path)? {
+        match key.as_str() {
+            "started" => time.started = uint64(v, &path)?,
+            "ended" => time.ended = uint64(v, &path)?,
+            _ => {}
+        }
+    }
+    Ok(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Action;
+    use crate::presets;
+
+    #[test]
+    fn test_hand_file() {
+        let engine = GameEngine::new(presets::omaha(), 3).unwrap();
+        let names = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let file = HandFile::new(&engine, &names).unwrap();
+        let text = file.to_json().pretty();
+        assert_eq!(HandFile::parse(&text).unwrap(), file);
+        assert!(HandFile::parse("[]").is_err());
+        assert!(HandFile::parse(&text.replace("\"hand\"", "\"hands\"")).is_err());
+    }
+
+    #[test]
+    fn test_server_hand() {
+        let mut engine = GameEngine::new(presets::holdem(), 2).unwrap();
+        while let Some(seat) = engine.to_act() {
+            engine.apply_action(seat, Action::Fold).unwrap();
+        }
+        let file = HandFile {
+            table: Some(Table {
+                id: "t1".to_string(),
+                hand: 4,
+                users: vec![Some("ann".to_string()), None],
+            }),
+            time: Some(Time {
+                started: 1_700_000_000,
+                ended: 1_700_000_060,
+            }),
+            ..HandFile::new(&engine, &["ann".to_string(), "bob".to_string()]).unwrap()
+        };
+        let value = file.to_json();
+        assert_eq!(HandFile::parse(&value.to_string()).unwrap(), file);
+
+        // The events are there for anyone reading the file, with the hole
+        // cards face down.
+        let Value::Object(fields) = &value else {
+            panic!("{}", value);
+        };
+        let (_, events) = fields.iter().find(|(key, _)| key == "events").unwrap();
+        let Value::Array(events) = events else {
+            panic!("{}", events);
+        };
+        assert_eq!(events.len(), engine.events().len());
+        assert!(events
+            .iter()
+            .any(|e| e.to_string() == r#"{"holeCardsDealt":{"hidden":2}}"#));
+
+        let bad = value.to_string().replace(r#""hand":4"#, r#""hand":-4"#);
+        assert_eq!(
+            HandFile::parse(&bad).err().unwrap(),
+            "table.hand: -4 is not a valid integer here"
+        );
+    }
+}
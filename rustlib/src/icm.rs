@@ -0,0 +1,165 @@
+//! Tournament equity under the Independent Chip Model: what a stack is
+//! worth in prize money, for bots and analysis tools weighing tournament
+//! decisions.
+//!
+//! Finishing places follow Malmuth-Harville: each player takes first
+//! with probability in proportion to their stack, then the rest play on
+//! for second in the same way, and so on down the payout table.
+
+use std::collections::HashMap;
+
+use crate::engine::Chips;
+
+/// Each player's expected share of the prize pool, by seat. `payouts`
+/// is the prize for each finishing place from first down; places beyond
+/// the table pay nothing.
+///
+/// The work grows with the number of ways to fill the paid places, so
+/// long payout tables against full fields get slow. At most 64 players.
+pub fn equities(stacks: &[Chips], payouts: &[f64]) -> Vec<f64> {
+    let stacks: Vec<f64> = stacks.iter().map(|&s| s as f64).collect();
+    harville(&stacks, payouts)
+}
+
+/// Each player's expected prize money over the outcomes of a decision,
+/// given as (probability, stacks afterwards). The probabilities should
+/// add up to 1.
+pub fn expected_equities(outcomes: &[(f64, Vec<Chips>)], payouts: &[f64]) -> Vec<f64> {
+    let mut total = Vec::new();
+    for (probability, stacks) in outcomes {
+        let equity = equities(stacks, payouts);
+        total.resize(equity.len().max(total.len()), 0.0);
+        for (t, e) in total.iter_mut().zip(equity) {
+            *t += probability * e;
+        }
+    }
+    total
+}
+
+/// What a chip EV is worth in prize money: the change in `seat`'s
+/// equity if their stack moves by `chip_ev`, with the difference coming
+/// from (or going to) the other players in proportion to their stacks.
+pub fn chip_ev_to_dollars(stacks: &[Chips], payouts: &[f64], seat: usize, chip_ev: f64) -> f64 {
+    let before: Vec<f64> = stacks.iter().map(|&s| s as f64).collect();
+    let total: f64 = before.iter().sum();
+    let others = total - before[seat];
+    let mine = (before[seat] + chip_ev).clamp(0.0, total);
+    let after: Vec<f64> = before
+        .iter()
+        .enumerate()
+        .map(|(i, &s)| {
+            if i == seat {
+                mine
+            } else if others > 0.0 {
+                s * (total - mine) / others
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    harville(&after, payouts)[seat] - harville(&before, payouts)[seat]
+}
+
+/// Malmuth-Harville equities, working through the paid places one at a
+/// time with the chance of each set of players having taken the places
+/// above. Once only empty stacks are left, they split what remains.
+fn harville(stacks: &[f64], payouts: &[f64]) -> Vec<f64> {
+    assert!(stacks.len() <= 64, "at most 64 players");
+    let mut equity = vec![0.0; stacks.len()];
+    let everyone: f64 = stacks.iter().sum();
+    // Chance of each set of players, as a bitmask, taking the places so far.
+    let mut placed: HashMap<u64, f64> = HashMap::from([(0, 1.0)]);
+    for (place, &payout) in payouts.iter().enumerate().take(stacks.len()) {
+        let mut next = HashMap::new();
+        for (&mask, &chance) in &placed {
+            let left = |i: &usize| mask & (1 << *i) == 0;
+            let remaining: f64 = everyone
+                - (0..stacks.len())
+                    .filter(|i| !left(i))
+                    .map(|i| stacks[i])
+                    .sum::<f64>();
+            if remaining <= 0.0 {
+                let players: Vec<usize> = (0..stacks.len()).filter(left).collect();
+                let share: f64 =
+                    payouts[place..].iter().take(players.len()).sum::<f64>() / players.len() as f64;
+                for i in players {
+                    equity[i] += chance * share;
+                }
+                continue;
+            }
+            for i in (0..stacks.len()).filter(left) {
+                let p = chance * stacks[i] / remaining;
+                if p == 0.0 {
+                    continue;
+                }
+                equity[i] += p * payout;
+                *next.entry(mask | (1 << i)).or_insert(0.0) += p;
+            }
+        }
+        placed = next;
+    }
+    equity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn test_equities() {
+        let equity = equities(&[3000, 2000, 1000], &[50.0, 30.0, 20.0]);
+        // First half the time, second 35% and third 15%.
+        assert_close(equity[0], 38.5);
+        assert_close(equity.iter().sum(), 100.0);
+        assert!(equity[0] > equity[1] && equity[1] > equity[2]);
+
+        assert_eq!(equities(&[500, 500], &[70.0, 30.0]), vec![50.0, 50.0]);
+        // A winner-take-all prize is just the share of the chips.
+        assert_close(equities(&[3000, 1000], &[100.0])[0], 75.0);
+    }
+
+    #[test]
+    fn test_busted_players() {
+        let equity = equities(&[1000, 0, 0], &[50.0, 30.0, 20.0]);
+        assert_eq!(equity, vec![50.0, 25.0, 25.0]);
+        assert_eq!(equities(&[1000, 0, 1000], &[60.0, 40.0])[1], 0.0);
+    }
+
+    #[test]
+    fn test_expected_equities() {
+        // A flip for everything heads up, against not playing.
+        let payouts = [65.0, 35.0];
+        let flip = expected_equities(&[(0.5, vec![2000, 0]), (0.5, vec![0, 2000])], &payouts);
+        assert_eq!(flip, vec![50.0, 50.0]);
+
+        // Three-handed, busting costs more than doubling up earns.
+        let payouts = [50.0, 30.0, 20.0];
+        let stacks = [1000, 1000, 1000];
+        let fold = equities(&stacks, &payouts)[0];
+        let call = expected_equities(
+            &[(0.5, vec![2000, 0, 1000]), (0.5, vec![0, 2000, 1000])],
+            &payouts,
+        )[0];
+        assert!(call < fold);
+    }
+
+    #[test]
+    fn test_chip_ev_to_dollars() {
+        let stacks = [3000, 2000, 1000];
+        let payouts = [50.0, 30.0, 20.0];
+        assert_close(chip_ev_to_dollars(&stacks, &payouts, 0, 0.0), 0.0);
+        // Chips won are worth less than chips lost.
+        let gain = chip_ev_to_dollars(&stacks, &payouts, 0, 600.0);
+        let loss = chip_ev_to_dollars(&stacks, &payouts, 0, -600.0);
+        assert!(gain > 0.0 && loss < 0.0 && gain < -loss);
+        // Winning every chip takes first place.
+        assert_close(
+            chip_ev_to_dollars(&stacks, &payouts, 2, 5000.0),
+            50.0 - equities(&stacks, &payouts)[2],
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Kill pots: raised limits for the hand after a player wins a big pot
+//! outright (or two in a row), with that player posting a kill blind.
+
+use crate::blinds::kill::{Size, Trigger};
+use crate::blinds::Kill;
+use crate::engine::{Chips, Event, GameEngine, HandStatus};
+
+/// A hand played for raised limits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KillPot {
+    /// Seat that posts the kill blind.
+    pub seat: usize,
+    pub size: Size,
+}
+
+impl KillPot {
+    /// `chips` scaled up to the kill limits.
+    pub fn scale(self, chips: Chips) -> Chips {
+        match self.size {
+            Size::Half => chips + chips / 2,
+            _ => chips * 2,
+        }
+    }
+}
+
+/// Carries kill state from one hand to the next.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KillTracker {
+    rule: Kill,
+
+    /// Who won the whole of the last big enough pot, if anyone.
+    last_scooper: Option<usize>,
+}
+
+impl KillTracker {
+    pub fn new(rule: &Kill) -> Self {
+        KillTracker {
+            rule: rule.clone(),
+            last_scooper: None,
+        }
+    }
+
+    /// Records a finished hand, returning the kill for the next hand if this
+    /// one triggered it.
+    pub fn record(&mut self, engine: &GameEngine) -> Option<KillPot> {
+        if engine.status() != HandStatus::Complete {
+            return None;
+        }
+        let big_blind = engine.config().blinds.iter().copied().max().unwrap_or(0);
+        let big_enough = engine.pot() >= big_blind * self.rule.min_pot.max(0) as Chips;
+        let scooper = scooper(engine.events()).filter(|_| big_enough);
+
+        let triggered = match self.rule.trigger() {
+            Trigger::Consecutive => scooper.is_some() && scooper == self.last_scooper,
+            _ => scooper.is_some(),
+        };
+        // A kill starts the count over.
+        self.last_scooper = if triggered { None } else { scooper };
+        let size = match self.rule.size() {
+            Size::Half => Size::Half,
+            _ => Size::Full,
+        };
+        scooper
+            .filter(|_| triggered)
+            .map(|seat| KillPot { seat, size })
+    }
+}
+
+/// The only seat awarded chips in a hand, if there was just one.
+fn scooper(events: &[Event]) -> Option<usize> {
+    let mut winners = events.iter().filter_map(|e| match *e {
+        Event::PotAwarded { seat, amount } if amount > 0 => Some(seat),
+        _ => None,
+    });
+    let first = winners.next()?;
+    winners.all(|s| s == first).then_some(first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{Action, EngineConfig};
+    use crate::presets;
+
+    /// A hand where seat 0 raises and everyone folds to them.
+    fn won_by_seat_0() -> GameEngine {
+        let mut engine = GameEngine::new(presets::holdem(), 3).unwrap();
+        engine.apply_action(0, Action::Raise(10)).unwrap();
+        engine.apply_action(1, Action::Fold).unwrap();
+        engine.apply_action(2, Action::Fold).unwrap();
+        engine
+    }
+
+    #[test]
+    fn test_scoop_trigger() {
+        let mut tracker = KillTracker::new(&Kill {
+            size: Size::Half.into(),
+            trigger: Trigger::Scoop.into(),
+            min_pot: 0,
+        });
+        let kill = tracker.record(&won_by_seat_0()).unwrap();
+        assert_eq!(kill.seat, 0);
+        assert_eq!(kill.scale(4), 6);
+
+        // The pot was 3 blinds less than the 4 big blinds needed.
+        let mut tracker = KillTracker::new(&Kill {
+            min_pot: 4,
+            ..Default::default()
+        });
+        assert_eq!(tracker.record(&won_by_seat_0()), None);
+    }
+
+    #[test]
+    fn test_consecutive_trigger() {
+        let mut tracker = KillTracker::new(&Kill {
+            size: Size::Full.into(),
+            trigger: Trigger::Consecutive.into(),
+            min_pot: 0,
+        });
+        assert_eq!(tracker.record(&won_by_seat_0()), None);
+        let kill = tracker.record(&won_by_seat_0()).unwrap();
+        assert_eq!(kill.scale(4), 8);
+        assert_eq!(tracker.record(&won_by_seat_0()), None);
+    }
+
+    #[test]
+    fn test_kill_blind() {
+        let mut config = EngineConfig::new(4);
+        config.kill = Some(KillPot {
+            seat: 0,
+            size: Size::Full,
+        });
+        let mut engine = GameEngine::with_config(presets::holdem(), config).unwrap();
+        // Blinds of 1 and 2 from seats 1 and 2, then seat 0 kills for 4.
+        assert!(engine
+            .events()
+            .contains(&Event::KillBlindPosted { seat: 0, amount: 4 }));
+        assert_eq!(engine.to_act(), Some(3));
+        // Bets are at the kill limits.
+        assert!(engine
+            .legal_actions(3)
+            .contains(&crate::engine::LegalAction::Raise { min: 8, max: 200 }));
+        for seat in [3, 1, 2] {
+            engine.apply_action(seat, Action::Call).unwrap();
+        }
+        assert_eq!(engine.to_act(), Some(0));
+    }
+}
@@ -1,6 +1,43 @@
 // Import the generated proto module
 pub use gamedef_proto3::snapfold::gamedef::*;
 
+pub mod audit;
+pub mod batch;
+pub mod betting;
+pub mod builder;
+pub mod cards;
+pub mod compact;
+pub mod engine;
+pub mod entropy;
+pub mod equity;
+pub mod events;
+pub mod fingerprint;
+pub mod gamedef;
+pub mod handeval;
+pub mod history;
+pub mod icm;
+pub mod kill;
+pub mod lint;
+pub mod locale;
+pub mod mental;
+mod parallel;
+pub mod points;
+pub mod pokerstars;
+pub mod positions;
+pub mod pot;
+pub mod presets;
+pub mod range;
+pub mod repeat;
+pub mod resolve;
+pub mod showdown;
+pub mod shuffle;
+pub mod simulate;
+pub mod solver;
+pub mod strategy;
+pub mod tournament;
+pub mod validation;
+pub mod view;
+
 /// A simple greeting function from the shared Rust library
 pub fn get_greeting(name: &str) -> String {
     if name.is_empty() {
@@ -10,34 +47,6 @@ pub fn get_greeting(name: &str) -> String {
     }
 }
 
-/// Creates a sample poker game structure
-pub fn create_sample_game() -> GameStructure {
-    GameStructure {
-        id: "test-game".to_string(),
-        name: "Test Texas Hold'em".to_string(),
-        deck: Some(game_structure::Deck::StandardDeck(
-            StandardDeck::DeckPoker.into(),
-        )),
-        phases: vec![
-            Phase {
-                phase_type: Some(phase::PhaseType::PlayerDeal(phase::PlayerDeal {
-                    cards: 2,
-                    face_up: false,
-                })),
-            },
-            Phase {
-                phase_type: Some(phase::PhaseType::BettingRound(phase::BettingRound {
-                    name: "Pre-flop".to_string(),
-                    min_bet: 1,
-                    order: phase::betting_round::BettingOrder::FollowBlinds.into(),
-                })),
-            },
-        ],
-        community_board_count: 1,
-        scorings: vec![],
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -50,17 +59,14 @@ mod tests {
 
     #[test]
     fn test_proto_instantiation() {
-        let game = create_sample_game();
+        let game = presets::holdem();
 
         // Verify the proto message was created correctly
-        assert_eq!(game.id, "test-game");
-        assert_eq!(game.name, "Test Texas Hold'em");
-        assert_eq!(game.phases.len(), 2);
+        assert_eq!(game.id, "holdem");
+        assert_eq!(game.name, "Texas Hold'em");
+        assert_eq!(game.phases.len(), 8);
         assert_eq!(game.community_board_count, 1);
 
-        // Print the proto message (for debugging)
-        println!("Created game structure: {:?}", game);
-
         // Verify the first phase is a player deal
         match &game.phases[0].phase_type {
             Some(phase::PhaseType::PlayerDeal(deal)) => {
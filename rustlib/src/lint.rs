@@ -0,0 +1,321 @@
+//! Checks for game definitions that are legal but probably not what was
+//! meant. Unlike [`crate::validation`], nothing here stops a game from being
+//! played.
+
+use std::fmt;
+
+use crate::engine::dealt_face_up;
+use crate::phase::betting_round::BettingOrder;
+use crate::{phase, repeat, scoring, GameStructure, Phase};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Worth a second look, but often intended.
+    Info,
+    /// Very likely a mistake.
+    Warning,
+}
+
+/// The part of the game a lint is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Span {
+    Game,
+    /// An index into `GameStructure.phases`.
+    Phase(usize),
+    /// An index into `GameStructure.scorings`.
+    Scoring(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LintKind {
+    /// Two betting rounds have names that differ only in case or spacing.
+    SimilarRoundNames { name: String, other: usize },
+    /// A betting round has no name, so players can't tell it apart.
+    UnnamedRound,
+    /// A betting round with no minimum bet.
+    ZeroMinBet,
+    /// A draw that lets nobody exchange any cards.
+    EmptyExchange,
+    /// Cards are dealt face up, but betting never depends on the face-up
+    /// cards, so showing them only gives away information.
+    UnusedFaceUpCards,
+    /// Every player card is dealt face up.
+    NoHiddenCards,
+    /// A scoring requires more cards from the hand than players are dealt.
+    TooFewHandCards { required: i32, dealt: i32 },
+    /// A scoring requires more cards from a board than are dealt to it.
+    TooFewBoardCards {
+        board: usize,
+        required: i32,
+        dealt: i32,
+    },
+    /// A scoring is identical to an earlier one, so it always has the same
+    /// winners.
+    DuplicateScoring { same_as: usize },
+}
+
+/// One finding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lint {
+    pub severity: Severity,
+    pub span: Span,
+    pub kind: LintKind,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Info => f.write_str("info: ")?,
+            Severity::Warning => f.write_str("warning: ")?,
+        }
+        match self.span {
+            Span::Game => {}
+            Span::Phase(i) => write!(f, "phase {}: ", i)?,
+            Span::Scoring(i) => write!(f, "scoring {}: ", i)?,
+        }
+        self.kind.fmt(f)
+    }
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintKind::SimilarRoundNames { name, other } => {
+                write!(f, "name {:?} is easily confused with phase {}", name, other)
+            }
+            LintKind::UnnamedRound => write!(f, "betting round has no name"),
+            LintKind::ZeroMinBet => write!(f, "betting round has no minimum bet"),
+            LintKind::EmptyExchange => write!(f, "draw allows no cards to be exchanged"),
+            LintKind::UnusedFaceUpCards => write!(
+                f,
+                "cards are dealt face up, but no betting round depends on them"
+            ),
+            LintKind::NoHiddenCards => write!(f, "every player card is dealt face up"),
+            LintKind::TooFewHandCards { required, dealt } => write!(
+                f,
+                "needs {} cards from the hand, but only {} are dealt",
+                required, dealt
+            ),
+            LintKind::TooFewBoardCards {
+                board,
+                required,
+                dealt,
+            } => write!(
+                f,
+                "needs {} cards from board {}, but only {} are dealt to it",
+                required, board, dealt
+            ),
+            LintKind::DuplicateScoring { same_as } => {
+                write!(f, "identical to scoring {}", same_as)
+            }
+        }
+    }
+}
+
+/// Every lint for `game`: those about phases in phase order, then those
+/// about the whole game, then those about scorings.
+pub fn lint(game: &GameStructure) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    let mut push = |severity, span, kind| {
+        lints.push(Lint {
+            severity,
+            span,
+            kind,
+        })
+    };
+
+    let mut rounds: Vec<(usize, String)> = Vec::new();
+    let mut face_up_deal = None;
+    let mut uses_face_up = false;
+    let (mut up, mut down) = (0, 0);
+    let mut board_cards = vec![0; game.community_board_count.max(0) as usize];
+    let mut count_deal = |i: usize, p: &Phase| match &p.phase_type {
+        Some(phase::PhaseType::PlayerDeal(deal)) => {
+            for face_up in dealt_face_up(deal) {
+                if face_up {
+                    up += 1;
+                    face_up_deal.get_or_insert(i);
+                } else {
+                    down += 1;
+                }
+            }
+        }
+        Some(phase::PhaseType::CommunityDeal(deal)) => {
+            if let Some(n) = board_cards.get_mut(deal.board_idx.max(0) as usize) {
+                *n += deal.cards;
+            }
+        }
+        _ => {}
+    };
+    for (i, p) in game.phases.iter().enumerate() {
+        match &p.phase_type {
+            Some(phase::PhaseType::PlayerDeal(_) | phase::PhaseType::CommunityDeal(_)) => {
+                count_deal(i, p)
+            }
+            // Only the cards a repeat deals are checked, counting every pass.
+            Some(phase::PhaseType::Repeat(_)) => {
+                for q in repeat::expand(std::slice::from_ref(p)).phases {
+                    count_deal(i, &q);
+                }
+            }
+            Some(phase::PhaseType::BettingRound(round)) => {
+                uses_face_up |=
+                    round.order() == BettingOrder::BestFaceup || round.bring_in.is_some();
+                if round.min_bet == 0 {
+                    push(Severity::Warning, Span::Phase(i), LintKind::ZeroMinBet);
+                }
+                let key = round.name.trim().to_lowercase();
+                if key.is_empty() {
+                    push(Severity::Info, Span::Phase(i), LintKind::UnnamedRound);
+                } else if let Some((other, _)) = rounds.iter().find(|(_, k)| *k == key) {
+                    // Exact duplicates are validation errors.
+                    let exact = matches!(
+                        &game.phases[*other].phase_type,
+                        Some(phase::PhaseType::BettingRound(r)) if r.name == round.name
+                    );
+                    if !exact {
+                        push(
+                            Severity::Warning,
+                            Span::Phase(i),
+                            LintKind::SimilarRoundNames {
+                                name: round.name.clone(),
+                                other: *other,
+                            },
+                        );
+                    }
+                } else {
+                    rounds.push((i, key));
+                }
+            }
+            Some(phase::PhaseType::Exchange(exchange)) => {
+                if exchange.max_exchange == 0 {
+                    push(Severity::Warning, Span::Phase(i), LintKind::EmptyExchange);
+                }
+            }
+            Some(
+                phase::PhaseType::Ante(_)
+                | phase::PhaseType::PassCards(_)
+                | phase::PhaseType::Reveal(_)
+                | phase::PhaseType::Declare(_)
+                | phase::PhaseType::Buy(_),
+            )
+            | None => {}
+        }
+    }
+    if let Some(i) = face_up_deal {
+        if down == 0 {
+            push(Severity::Warning, Span::Game, LintKind::NoHiddenCards);
+        } else if !uses_face_up {
+            push(Severity::Info, Span::Phase(i), LintKind::UnusedFaceUpCards);
+        }
+    }
+
+    let min = |r: &scoring::Range| r.min.max(0);
+    for (i, s) in game.scorings.iter().enumerate() {
+        if let Some(r) = &s.hand_cards {
+            if min(r) > up + down {
+                push(
+                    Severity::Warning,
+                    Span::Scoring(i),
+                    LintKind::TooFewHandCards {
+                        required: min(r),
+                        dealt: up + down,
+                    },
+                );
+            }
+        }
+        for (board, r) in s.community_cards.iter().enumerate() {
+            let dealt = board_cards.get(board).copied().unwrap_or(0);
+            if min(r) > dealt {
+                push(
+                    Severity::Warning,
+                    Span::Scoring(i),
+                    LintKind::TooFewBoardCards {
+                        board,
+                        required: min(r),
+                        dealt,
+                    },
+                );
+            }
+        }
+        if let Some(same_as) = game.scorings[..i].iter().position(|t| t == s) {
+            push(
+                Severity::Warning,
+                Span::Scoring(i),
+                LintKind::DuplicateScoring { same_as },
+            );
+        }
+    }
+    lints.sort_by_key(|l| match l.span {
+        Span::Phase(i) => (0, i),
+        Span::Game => (1, 0),
+        Span::Scoring(i) => (2, i),
+    });
+    lints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GameBuilder;
+    use crate::presets;
+
+    #[test]
+    fn test_presets_are_clean() {
+        for game in presets::all() {
+            assert_eq!(lint(&game), vec![], "{}", game.id);
+        }
+    }
+
+    #[test]
+    fn test_lints() {
+        let game = GameBuilder::new("odd")
+            .deal_hole(2)
+            .deal_up(1)
+            .betting("Flop")
+            .community(3)
+            .betting_round(phase::BettingRound {
+                name: "flop ".to_string(),
+                ..Default::default()
+            })
+            .exchange(0)
+            .betting("")
+            .high()
+            .high()
+            .scoring(crate::Scoring {
+                hand_cards: Some(scoring::Range { min: 4, max: 4 }),
+                community_cards: vec![scoring::Range { min: 5, max: 5 }],
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        let lints: Vec<String> = lint(&game).iter().map(|l| l.to_string()).collect();
+        assert_eq!(
+            lints,
+            vec![
+                "info: phase 1: cards are dealt face up, but no betting round depends on them",
+                "warning: phase 4: betting round has no minimum bet",
+                "warning: phase 4: name \"flop \" is easily confused with phase 2",
+                "warning: phase 5: draw allows no cards to be exchanged",
+                "info: phase 6: betting round has no name",
+                "warning: scoring 1: identical to scoring 0",
+                "warning: scoring 2: needs 4 cards from the hand, but only 3 are dealt",
+                "warning: scoring 2: needs 5 cards from board 0, but only 3 are dealt to it",
+            ]
+        );
+
+        let open = GameBuilder::new("open")
+            .deal_up(5)
+            .betting("Bet")
+            .build()
+            .unwrap();
+        assert_eq!(
+            lint(&open),
+            vec![Lint {
+                severity: Severity::Warning,
+                span: Span::Game,
+                kind: LintKind::NoHiddenCards,
+            }]
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Translated names and descriptions (see `GameStructure.localized_names`),
+//! and the words used to describe hands.
+
+use std::collections::HashMap;
+
+use crate::GameStructure;
+
+/// Text for a game in a given locale.
+pub trait Localized {
+    /// The game's name in `locale`, a language tag such as `"pt-BR"`.
+    fn display_name(&self, locale: &str) -> &str;
+    /// The game's description in `locale`.
+    fn display_description(&self, locale: &str) -> &str;
+}
+
+impl Localized for GameStructure {
+    fn display_name(&self, locale: &str) -> &str {
+        lookup(entries(&self.localized_names), locale).unwrap_or(&self.name)
+    }
+
+    fn display_description(&self, locale: &str) -> &str {
+        lookup(entries(&self.localized_descriptions), locale).unwrap_or(&self.description)
+    }
+}
+
+/// Words for describing hands in one language, for
+/// [`crate::handeval::HandRank::describe_with`].
+///
+/// Templates refer to the hand's tie-breaking ranks by position: `{r1}` is
+/// the first rank's name and `{p1}` its plural, `{r2}` the second, and so
+/// on. `{n}` is the number of cards in a badugi, or a points total.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HandTerms {
+    /// One template per [`crate::HandType`], indexed by its value.
+    pub hand_types: [&'static str; 13],
+    pub royal_flush: &'static str,
+
+    /// A low hand with no pair.
+    pub low: &'static str,
+    pub badugi: &'static str,
+    pub points: &'static str,
+
+    /// Names of the standard deck's ranks, lowest first.
+    pub ranks: [&'static str; 13],
+    pub plural_ranks: [&'static str; 13],
+}
+
+impl HandTerms {
+    pub const ENGLISH: HandTerms = HandTerms {
+        hand_types: [
+            "",
+            "High Card, {r1}",
+            "Pair of {p1}",
+            "Two Pair, {p1} and {p2}",
+            "Three of a Kind, {p1}",
+            "Straight, {r1} High",
+            "Flush, {r1} High",
+            "Full House, {p1} over {p2}",
+            "Four of a Kind, {p1}",
+            "Straight Flush, {r1} High",
+            "Five of a Kind, {p1}",
+            "Flush House, {p1} over {p2}",
+            "Flush Five, {p1}",
+        ],
+        royal_flush: "Royal Flush",
+        low: "{r1}-{r2} Low",
+        badugi: "{n}-Card Badugi, {r1} High",
+        points: "{n} Points",
+        ranks: [
+            "Two", "Three", "Four", "Five", "Six", "Seven", "Eight", "Nine", "Ten", "Jack",
+            "Queen", "King", "Ace",
+        ],
+        plural_ranks: [
+            "Twos", "Threes", "Fours", "Fives", "Sixes", "Sevens", "Eights", "Nines", "Tens",
+            "Jacks", "Queens", "Kings", "Aces",
+        ],
+    };
+
+    pub const GERMAN: HandTerms = HandTerms {
+        hand_types: [
+            "",
+            "Höchste Karte {r1}",
+            "Ein Paar {p1}",
+            "Zwei Paare, {p1} und {p2}",
+            "Drilling, {p1}",
+            "Straße bis {r1}",
+            "Flush bis {r1}",
+            "Full House, {p1} und {p2}",
+            "Vierling, {p1}",
+            "Straight Flush bis {r1}",
+            "Fünfling, {p1}",
+            "Flush House, {p1} und {p2}",
+            "Flush Five, {p1}",
+        ],
+        royal_flush: "Royal Flush",
+        low: "{r1}-{r2}-Low",
+        badugi: "Badugi mit {n} Karten, {r1} hoch",
+        points: "{n} Punkte",
+        ranks: [
+            "Zwei", "Drei", "Vier", "Fünf", "Sechs", "Sieben", "Acht", "Neun", "Zehn", "Bube",
+            "Dame", "König", "Ass",
+        ],
+        plural_ranks: [
+            "Zweien", "Dreien", "Vieren", "Fünfen", "Sechsen", "Siebenen", "Achten", "Neunen",
+            "Zehnen", "Buben", "Damen", "Könige", "Asse",
+        ],
+    };
+}
+
+/// The hand terms for `locale`, looked up like a game's translated name,
+/// with English for locales that have none.
+pub fn hand_terms(locale: &str) -> &'static HandTerms {
+    let terms = [("en", &HandTerms::ENGLISH), ("de", &HandTerms::GERMAN)];
+    lookup(terms.into_iter(), locale).unwrap_or(&HandTerms::ENGLISH)
+}
+
+fn entries(map: &HashMap<String, String>) -> impl Iterator<Item = (&str, &str)> + Clone {
+    map.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+}
+
+/// The translation for `locale`, or failing that for the nearest more
+/// general locale: "zh-Hant-TW", then "zh-Hant", then "zh". Tags are
+/// compared ignoring case, and `_` is accepted in place of `-`.
+fn lookup<'a, T: ?Sized>(
+    translations: impl Iterator<Item = (&'a str, &'a T)> + Clone,
+    locale: &str,
+) -> Option<&'a T> {
+    if let Some((_, text)) = translations.clone().find(|(k, _)| *k == locale) {
+        return Some(text);
+    }
+    let normalize = |tag: &str| tag.replace('_', "-").to_ascii_lowercase();
+    let mut tag = normalize(locale);
+    loop {
+        let found = translations
+            .clone()
+            .filter(|(k, _)| normalize(k) == tag)
+            .min_by(|a, b| a.0.cmp(b.0));
+        if let Some((_, text)) = found {
+            return Some(text);
+        }
+        tag.truncate(tag.rfind('-')?);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets;
+
+    #[test]
+    fn test_display_name() {
+        let mut game = presets::holdem();
+        game.description = "Two cards each and five on the board.".to_string();
+        for (locale, name) in [
+            ("de", "Texas Hold'em (de)"),
+            ("pt_BR", "Texas Hold'em (BR)"),
+        ] {
+            game.localized_names
+                .insert(locale.to_string(), name.to_string());
+        }
+        game.localized_descriptions
+            .insert("de".to_string(), "Zwei Karten".to_string());
+
+        assert_eq!(game.display_name("de"), "Texas Hold'em (de)");
+        assert_eq!(game.display_name("de-AT"), "Texas Hold'em (de)");
+        assert_eq!(game.display_name("DE"), "Texas Hold'em (de)");
+        assert_eq!(game.display_name("pt-br"), "Texas Hold'em (BR)");
+        assert_eq!(game.display_name("pt"), "Texas Hold'em");
+        assert_eq!(game.display_name("fr"), "Texas Hold'em");
+        assert_eq!(game.display_name(""), "Texas Hold'em");
+        assert_eq!(game.display_description("de-CH"), "Zwei Karten");
+        assert_eq!(
+            game.display_description("en-US"),
+            "Two cards each and five on the board."
+        );
+    }
+
+    #[test]
+    fn test_hand_terms() {
+        assert_eq!(hand_terms("de-AT"), &HandTerms::GERMAN);
+        assert_eq!(hand_terms("en-GB"), &HandTerms::ENGLISH);
+        assert_eq!(hand_terms("fr"), &HandTerms::ENGLISH);
+    }
+}
@@ -0,0 +1,179 @@
+//! Experimental mental poker: dealing without a trusted dealer, for
+//! peer-to-peer games.
+//!
+//! Cards are locked with the SRA commutative cipher, `x^key mod p`, so
+//! locks can be put on and taken off in any order. Every player in turn
+//! locks each card of the deck with their own key and shuffles it. Once
+//! they all have, nobody knows where any card is. To show a card to one
+//! player, every other player takes off their lock and the owner takes
+//! off the last one. To turn it face up, everyone takes theirs off.
+//!
+//! The modulus is a 64-bit safe prime, which keeps the arithmetic in
+//! machine words but is far too small to stand up to a determined
+//! attacker. This is for trying the protocol out, not for money.
+
+use crate::cards::Card;
+use crate::entropy::EntropySource;
+use crate::shuffle::fisher_yates;
+
+/// The prime modulus, 2^64 - 1469. (p - 1) / 2 is prime too, so the
+/// squares mod p form a group of prime order.
+pub const MODULUS: u64 = 0xffff_ffff_ffff_fa43;
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// The inverse of `a` mod `m`, if they are coprime.
+fn inverse(a: u64, m: u64) -> Option<u64> {
+    let (mut r0, mut r1) = (m as i128, a as i128);
+    let (mut t0, mut t1) = (0i128, 1i128);
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (t0, t1) = (t1, t0 - q * t1);
+    }
+    (r0 == 1).then(|| t0.rem_euclid(m as i128) as u64)
+}
+
+/// One player's secret lock. Keys are never shared; a player only ever
+/// hands over cards with their lock taken off.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Key {
+    lock: u64,
+    unlock: u64,
+}
+
+impl Key {
+    /// A fresh key. Use a secure generator such as
+    /// [`crate::entropy::OsEntropy`] for real games.
+    pub fn generate<R: EntropySource + ?Sized>(rng: &mut R) -> Self {
+        loop {
+            let lock = rng.below(MODULUS - 3) + 2;
+            if let Some(unlock) = inverse(lock, MODULUS - 1) {
+                return Key { lock, unlock };
+            }
+        }
+    }
+
+    /// Puts this key's lock on a card.
+    pub fn lock(&self, card: u64) -> u64 {
+        pow_mod(card, self.lock, MODULUS)
+    }
+
+    /// Takes this key's lock off a card, whatever other locks it has.
+    pub fn unlock(&self, card: u64) -> u64 {
+        pow_mod(card, self.unlock, MODULUS)
+    }
+}
+
+/// The public numbering of a deck's cards, agreed on before the shuffle.
+/// Each card is a different square mod [`MODULUS`]: the cipher can't hide
+/// whether a number is a square, so every card has to look the same.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Encoding {
+    cards: Vec<(u64, Card)>,
+}
+
+impl Encoding {
+    pub fn new(cards: &[Card]) -> Self {
+        let cards = cards
+            .iter()
+            .enumerate()
+            .map(|(i, &card)| {
+                let x = i as u64 + 2;
+                (mul_mod(x, x, MODULUS), card)
+            })
+            .collect();
+        Encoding { cards }
+    }
+
+    /// The unlocked deck, in the agreed order, to start the first round.
+    pub fn deck(&self) -> Vec<u64> {
+        self.cards.iter().map(|&(x, _)| x).collect()
+    }
+
+    /// The card a fully unlocked number stands for.
+    pub fn card(&self, value: u64) -> Option<Card> {
+        self.cards
+            .iter()
+            .find(|&&(x, _)| x == value)
+            .map(|&(_, c)| c)
+    }
+}
+
+/// One player's turn at the shuffle: locks every card with `key`, then
+/// shuffles the deck. Every player takes a turn, passing the deck on.
+pub fn shuffle_round<R: EntropySource + ?Sized>(deck: &mut [u64], key: &Key, rng: &mut R) {
+    for card in deck.iter_mut() {
+        *card = key.lock(*card);
+    }
+    fisher_yates(deck, rng);
+}
+
+/// Takes the locks of `keys` off a card. With a player's own key left
+/// out, the result is what they are handed to unlock privately.
+pub fn unlock_all(card: u64, keys: &[&Key]) -> u64 {
+    keys.iter().fold(card, |card, key| key.unlock(card))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::{SplitMix64, STANDARD_RANKS, STANDARD_SUITS};
+
+    #[test]
+    fn test_locks_commute() {
+        let mut rng = SplitMix64(1);
+        let (a, b) = (Key::generate(&mut rng), Key::generate(&mut rng));
+        let card = Encoding::new(&[Card::new(5, 2)]).deck()[0];
+        assert_eq!(a.lock(b.lock(card)), b.lock(a.lock(card)));
+        assert_eq!(a.unlock(b.unlock(b.lock(a.lock(card)))), card);
+        assert_ne!(a.lock(card), card);
+        assert_eq!(inverse(3, 7), Some(5));
+        assert_eq!(inverse(4, 8), None);
+    }
+
+    #[test]
+    fn test_deal_without_a_dealer() {
+        let cards: Vec<Card> = (0..STANDARD_RANKS.len() as u8)
+            .flat_map(|r| (0..STANDARD_SUITS.len() as u8).map(move |s| Card::new(r, s)))
+            .collect();
+        let encoding = Encoding::new(&cards);
+        let mut rng = SplitMix64(7);
+        let keys: Vec<Key> = (0..3).map(|_| Key::generate(&mut rng)).collect();
+        let mut deck = encoding.deck();
+        for key in &keys {
+            shuffle_round(&mut deck, key, &mut rng);
+        }
+        assert!(deck.iter().all(|&x| encoding.card(x).is_none()));
+
+        // The top card goes to player 1: the others unlock it for them.
+        let handed = unlock_all(deck[0], &[&keys[0], &keys[2]]);
+        assert_eq!(encoding.card(handed), None);
+        let private = encoding.card(keys[1].unlock(handed)).unwrap();
+
+        // Turned face up, every card is still in the deck once.
+        let all: Vec<&Key> = keys.iter().collect();
+        let mut shown: Vec<Card> = deck
+            .iter()
+            .map(|&x| encoding.card(unlock_all(x, &all)).unwrap())
+            .collect();
+        assert_eq!(shown[0], private);
+        shown.sort();
+        assert_eq!(shown, cards);
+    }
+}
@@ -0,0 +1,57 @@
+//! Spreading independent tasks over threads.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Runs `run` on each of `tasks` task numbers, on up to `threads` threads,
+/// and returns the results in task order, so the outcome doesn't depend
+/// on how the tasks were shared out. A panic in a task is passed on.
+pub(crate) fn fan_out<T: Send>(
+    tasks: usize,
+    threads: usize,
+    run: impl Fn(usize) -> T + Sync,
+) -> Vec<T> {
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, T)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.clamp(1, tasks.max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let task = next.fetch_add(1, Ordering::Relaxed);
+                        if task >= tasks {
+                            return done;
+                        }
+                        done.push((task, run(task)));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .collect()
+    });
+    results.sort_by_key(|&(task, _)| task);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// The number of threads to use when the caller leaves it to us.
+pub(crate) fn available_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fan_out_keeps_order() {
+        for threads in [1, 3, 16] {
+            assert_eq!(
+                fan_out(10, threads, |i| i * i),
+                (0..10).map(|i| i * i).collect::<Vec<_>>()
+            );
+        }
+        assert!(fan_out(0, 4, |i| i).is_empty());
+    }
+}
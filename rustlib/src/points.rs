@@ -0,0 +1,632 @@
+//! Points scoring: a small expression language for house games that rank
+//! hands by a total rather than as poker hands.
+//!
+//! An expression is worked out over the cards a player plays and gives a
+//! whole number of points. The highest total wins, or the lowest for a `lo`
+//! scoring. From loosest to tightest binding, an expression is made of:
+//!
+//! - `a or b`, `a and b` and `not a`, where 0 is false and any other number
+//!   true
+//! - comparisons `==`, `!=`, `<`, `<=`, `>` and `>=`, giving 1 or 0
+//! - `+`, `-`, `*`, `/` and `%`, and a leading `-`. Dividing by zero gives 0
+//! - whole numbers, parentheses, `cards` (how many cards the hand has), and
+//!   the functions `if(c, a, b)`, `min(a, …)` and `max(a, …)`
+//! - `count(e)`, the number of cards for which `e` is true; `sum(e)`, the
+//!   total of `e` over the cards; `most(e)`, the size of the largest group
+//!   of cards with the same `e`; and `distinct(e)`, how many different
+//!   values `e` takes
+//!
+//! Inside `count`, `sum`, `most` and `distinct`, `e` can use the card's
+//! `rank` and `suit`, its `points` from the deck definition, and `joker`
+//! (1 for a joker). Ranks and suits are numbered from 0 in the order the
+//! deck lists them, and compare with the deck's labels in quotes, so
+//! `count(suit == "♥")` scores the most hearts and `count(rank >= "10")` the
+//! most tens and better. A joker's rank and suit are -1.
+//!
+//! For example, pinochle-style melds could score as
+//! `sum(points) + if(count(rank == "Q" and suit == "♠") > 0 and
+//! count(rank == "J" and suit == "♦") > 0, 40, 0)`.
+
+use std::fmt;
+
+use crate::cards::{Card, Shoe};
+
+/// A malformed expression, located by the 1-based character it was found
+/// at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExprError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl std::error::Error for ExprError {}
+
+/// A parsed expression, ready to score hands dealt from one deck.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    root: Expr,
+
+    /// Cards the deck gives a point value.
+    points: Vec<(Card, i32)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Num(i64),
+    Cards,
+    Attr(Attr),
+    /// A quoted label, only allowed while parsing a comparison with a rank
+    /// or suit.
+    Label(String, usize),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    If(Box<[Expr; 3]>),
+    Extreme {
+        max: bool,
+        args: Vec<Expr>,
+    },
+    Aggregate(Aggregate, Box<Expr>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Attr {
+    Rank,
+    Suit,
+    Points,
+    Joker,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Aggregate {
+    Count,
+    Sum,
+    Most,
+    Distinct,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(i64),
+    Word(String),
+    Str(String),
+    Sym(&'static str),
+}
+
+const SYMBOLS: [&str; 14] = [
+    "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "(", ")", ",",
+];
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ExprError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let digits: String = chars[start..i].iter().collect();
+            let n = digits.parse().map_err(|_| ExprError {
+                column,
+                message: format!("{} is too large", digits),
+            })?;
+            tokens.push((Token::Num(n), column));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((Token::Word(chars[start..i].iter().collect()), column));
+        } else if c == '"' || c == '\'' {
+            let end = chars[i + 1..]
+                .iter()
+                .position(|&d| d == c)
+                .ok_or_else(|| ExprError {
+                    column,
+                    message: "unterminated label".to_string(),
+                })?;
+            tokens.push((
+                Token::Str(chars[i + 1..i + 1 + end].iter().collect()),
+                column,
+            ));
+            i += end + 2;
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let sym = SYMBOLS
+                .iter()
+                .find(|s| rest.starts_with(*s))
+                .ok_or_else(|| ExprError {
+                    column,
+                    message: format!("unexpected {:?}", c),
+                })?;
+            tokens.push((Token::Sym(sym), column));
+            i += sym.len();
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    next: usize,
+    shoe: &'a Shoe,
+
+    /// Whether the parser is inside `count`, `sum`, `most` or `distinct`,
+    /// where card attributes are in scope.
+    in_card: bool,
+
+    /// Just past the end of the source, for errors at the end.
+    end: usize,
+}
+
+impl Parser<'_> {
+    fn column(&self) -> usize {
+        self.tokens.get(self.next).map_or(self.end, |(_, c)| *c)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ExprError> {
+        Err(ExprError {
+            column: self.column(),
+            message: message.into(),
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(t, _)| t)
+    }
+
+    fn eat_sym(&mut self, sym: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Sym(s)) if *s == sym);
+        self.next += found as usize;
+        found
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Word(w)) if w == word);
+        self.next += found as usize;
+        found
+    }
+
+    fn expect(&mut self, sym: &str) -> Result<(), ExprError> {
+        if self.eat_sym(sym) {
+            Ok(())
+        } else {
+            self.error(format!("expected {:?}", sym))
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, ExprError> {
+        let mut left = self.and()?;
+        while self.eat_word("or") {
+            left = Expr::Binary(Op::Or, Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, ExprError> {
+        let mut left = self.not()?;
+        while self.eat_word("and") {
+            left = Expr::Binary(Op::And, Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, ExprError> {
+        if self.eat_word("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ExprError> {
+        let left = self.sum()?;
+        let op = [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ]
+        .into_iter()
+        .find(|(sym, _)| self.eat_sym(sym));
+        let Some((_, op)) = op else {
+            return Ok(left);
+        };
+        let right = self.sum()?;
+        let (left, right) = match (left, right) {
+            (Expr::Attr(attr), Expr::Label(label, column)) => {
+                (Expr::Attr(attr), self.label(attr, &label, column)?)
+            }
+            (Expr::Label(label, column), Expr::Attr(attr)) => {
+                (self.label(attr, &label, column)?, Expr::Attr(attr))
+            }
+            pair => pair,
+        };
+        Ok(Expr::Binary(op, Box::new(left), Box::new(right)))
+    }
+
+    /// The number a rank or suit label stands for.
+    fn label(&self, attr: Attr, label: &str, column: usize) -> Result<Expr, ExprError> {
+        let labels: Vec<String> = match attr {
+            Attr::Rank => self.shoe.rank_labels().to_vec(),
+            Attr::Suit => self.shoe.suit_labels().to_vec(),
+            _ => Vec::new(),
+        };
+        match labels.iter().position(|l| l == label) {
+            Some(i) => Ok(Expr::Num(i as i64)),
+            None => Err(ExprError {
+                column,
+                message: match attr {
+                    Attr::Rank | Attr::Suit => format!("the deck has no {:?}", label),
+                    _ => "a label can only be compared with rank or suit".to_string(),
+                },
+            }),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, ExprError> {
+        let mut left = self.product()?;
+        loop {
+            let op = if self.eat_sym("+") {
+                Op::Add
+            } else if self.eat_sym("-") {
+                Op::Sub
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, ExprError> {
+        let mut left = self.unary()?;
+        loop {
+            let op = if self.eat_sym("*") {
+                Op::Mul
+            } else if self.eat_sym("/") {
+                Op::Div
+            } else if self.eat_sym("%") {
+                Op::Rem
+            } else {
+                return Ok(left);
+            };
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        if self.eat_sym("-") {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, ExprError> {
+        let column = self.column();
+        let Some(token) = self.peek().cloned() else {
+            return self.error("expected a value");
+        };
+        self.next += 1;
+        match token {
+            Token::Num(n) => Ok(Expr::Num(n)),
+            Token::Str(label) => Ok(Expr::Label(label, column)),
+            Token::Sym("(") => {
+                let e = self.or()?;
+                self.expect(")")?;
+                Ok(e)
+            }
+            Token::Sym(s) => {
+                self.next -= 1;
+                self.error(format!("unexpected {:?}", s))
+            }
+            Token::Word(word) => self.word(&word),
+        }
+    }
+
+    fn word(&mut self, word: &str) -> Result<Expr, ExprError> {
+        let attr = match word {
+            "cards" => return Ok(Expr::Cards),
+            "rank" => Some(Attr::Rank),
+            "suit" => Some(Attr::Suit),
+            "points" => Some(Attr::Points),
+            "joker" => Some(Attr::Joker),
+            _ => None,
+        };
+        if let Some(attr) = attr {
+            if !self.in_card {
+                self.next -= 1;
+                return self.error(format!(
+                    "{} can only be used inside count, sum, most or distinct",
+                    word
+                ));
+            }
+            return Ok(Expr::Attr(attr));
+        }
+        let aggregate = match word {
+            "count" => Some(Aggregate::Count),
+            "sum" => Some(Aggregate::Sum),
+            "most" => Some(Aggregate::Most),
+            "distinct" => Some(Aggregate::Distinct),
+            _ => None,
+        };
+        if !matches!(word, "if" | "min" | "max") && aggregate.is_none() {
+            self.next -= 1;
+            return self.error(format!("unknown name {:?}", word));
+        }
+        if aggregate.is_some() && self.in_card {
+            self.next -= 1;
+            return self.error(format!("{} can't be used inside another one", word));
+        }
+        self.expect("(")?;
+        let outer = self.in_card;
+        self.in_card |= aggregate.is_some();
+        let mut args = vec![self.or()?];
+        while self.eat_sym(",") {
+            args.push(self.or()?);
+        }
+        self.in_card = outer;
+        self.expect(")")?;
+        match (word, aggregate) {
+            (_, Some(aggregate)) if args.len() == 1 => {
+                Ok(Expr::Aggregate(aggregate, Box::new(args.remove(0))))
+            }
+            ("if", _) if args.len() == 3 => {
+                let [c, a, b]: [Expr; 3] = args.try_into().expect("three arguments");
+                Ok(Expr::If(Box::new([c, a, b])))
+            }
+            ("min" | "max", None) => Ok(Expr::Extreme {
+                max: word == "max",
+                args,
+            }),
+            _ => self.error(format!("wrong number of arguments to {}", word)),
+        }
+    }
+}
+
+/// The first label left outside a comparison with a rank or suit.
+fn stray_label(e: &Expr) -> Option<usize> {
+    match e {
+        Expr::Label(_, column) => Some(*column),
+        Expr::Not(e) | Expr::Neg(e) | Expr::Aggregate(_, e) => stray_label(e),
+        Expr::Binary(_, a, b) => stray_label(a).or_else(|| stray_label(b)),
+        Expr::If(args) => args.iter().find_map(stray_label),
+        Expr::Extreme { args, .. } => args.iter().find_map(stray_label),
+        Expr::Num(_) | Expr::Cards | Expr::Attr(_) => None,
+    }
+}
+
+impl Expression {
+    /// Parses `source`, resolving rank and suit labels against `shoe`'s
+    /// deck.
+    pub fn parse(source: &str, shoe: &Shoe) -> Result<Self, ExprError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            next: 0,
+            shoe,
+            in_card: false,
+            end: source.chars().count() + 1,
+        };
+        let root = parser.or()?;
+        if parser.peek().is_some() {
+            return parser.error("unexpected text after the expression");
+        }
+        if let Some(column) = stray_label(&root) {
+            return Err(ExprError {
+                column,
+                message: "a label can only be compared with rank or suit".to_string(),
+            });
+        }
+        let mut points = Vec::new();
+        for rank in 0..shoe.rank_labels().len() {
+            for suit in 0..shoe.suit_labels().len() {
+                let card = Card::new(rank as u8, suit as u8);
+                match shoe.points(card) {
+                    0 => {}
+                    p => points.push((card, p)),
+                }
+            }
+        }
+        Ok(Expression { root, points })
+    }
+
+    /// Points scored by `cards`.
+    pub fn evaluate(&self, cards: &[Card]) -> i64 {
+        self.eval(&self.root, cards, None)
+    }
+
+    fn eval(&self, e: &Expr, cards: &[Card], card: Option<Card>) -> i64 {
+        let eval = |e: &Expr| self.eval(e, cards, card);
+        match e {
+            Expr::Num(n) => *n,
+            Expr::Cards => cards.len() as i64,
+            Expr::Attr(attr) => card.map_or(0, |c| self.attr(*attr, c)),
+            Expr::Label(..) => 0,
+            Expr::Not(e) => (eval(e) == 0) as i64,
+            Expr::Neg(e) => eval(e).wrapping_neg(),
+            Expr::Binary(Op::And, a, b) => (eval(a) != 0 && eval(b) != 0) as i64,
+            Expr::Binary(Op::Or, a, b) => (eval(a) != 0 || eval(b) != 0) as i64,
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (eval(a), eval(b));
+                match op {
+                    Op::Eq => (a == b) as i64,
+                    Op::Ne => (a != b) as i64,
+                    Op::Lt => (a < b) as i64,
+                    Op::Le => (a <= b) as i64,
+                    Op::Gt => (a > b) as i64,
+                    Op::Ge => (a >= b) as i64,
+                    Op::Add => a.saturating_add(b),
+                    Op::Sub => a.saturating_sub(b),
+                    Op::Mul => a.saturating_mul(b),
+                    Op::Div => a.checked_div(b).unwrap_or(0),
+                    Op::Rem => a.checked_rem(b).unwrap_or(0),
+                    Op::And | Op::Or => unreachable!("handled above"),
+                }
+            }
+            Expr::If(args) => {
+                if eval(&args[0]) != 0 {
+                    eval(&args[1])
+                } else {
+                    eval(&args[2])
+                }
+            }
+            Expr::Extreme { max, args } => {
+                let values = args.iter().map(eval);
+                if *max {
+                    values.max().unwrap_or(0)
+                } else {
+                    values.min().unwrap_or(0)
+                }
+            }
+            Expr::Aggregate(aggregate, e) => {
+                let mut values: Vec<i64> = cards
+                    .iter()
+                    .map(|&c| self.eval(e, cards, Some(c)))
+                    .collect();
+                match aggregate {
+                    Aggregate::Count => values.iter().filter(|&&v| v != 0).count() as i64,
+                    Aggregate::Sum => values.iter().fold(0, |a, &v| a.saturating_add(v)),
+                    Aggregate::Most => {
+                        values.sort_unstable();
+                        values
+                            .chunk_by(|a, b| a == b)
+                            .map(|g| g.len() as i64)
+                            .max()
+                            .unwrap_or(0)
+                    }
+                    Aggregate::Distinct => {
+                        values.sort_unstable();
+                        values.dedup();
+                        values.len() as i64
+                    }
+                }
+            }
+        }
+    }
+
+    fn attr(&self, attr: Attr, card: Card) -> i64 {
+        let joker = card == Card::JOKER;
+        match attr {
+            Attr::Rank if joker => -1,
+            Attr::Suit if joker => -1,
+            Attr::Rank => card.rank as i64,
+            Attr::Suit => card.suit as i64,
+            Attr::Points => self
+                .points
+                .iter()
+                .find(|(c, _)| *c == card)
+                .map_or(0, |&(_, p)| p as i64),
+            Attr::Joker => joker as i64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::parse_cards;
+
+    fn score(source: &str, hand: &str) -> i64 {
+        Expression::parse(source, &Shoe::standard())
+            .unwrap()
+            .evaluate(&parse_cards(hand).unwrap())
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(score("count(suit == '♥')", "Ah 2h 3c Kh 9s"), 3);
+        assert_eq!(score("count(rank >= \"10\")", "Ah Th 3c Kh 9s"), 3);
+        assert_eq!(score("sum(rank) + cards * 2", "2c 3d 4h"), 6 + 3);
+        assert_eq!(score("most(suit)", "Ac 2d 3c Kc 9s"), 3);
+        assert_eq!(score("distinct(rank)", "Ac Ad 3c 3h 9s"), 3);
+        assert_eq!(score("-(1 + 2) * 3 % 4", ""), -1);
+        assert_eq!(score("7 / 0 + max(1, 5, 2) - min(4, 3)", ""), 2);
+        let bonus = "if(count(rank == 'Q' and suit == '♠') > 0 \
+                     and count(rank == 'J' and suit == '♦') > 0, 40, 0)";
+        assert_eq!(score(bonus, "Qs Jd 2c"), 40);
+        assert_eq!(score(bonus, "Qs Jc 2c"), 0);
+        assert_eq!(score("not 0 or 0", ""), 1);
+        assert_eq!(
+            score("sum(if(rank == 'A', 11, min(rank + 2, 10)))", "As Kd 5c"),
+            26
+        );
+    }
+
+    #[test]
+    fn test_points_and_jokers() {
+        let mut deck = crate::Deck {
+            jokers: 1,
+            ..Default::default()
+        };
+        deck.cards.push(crate::deck::CardSpec {
+            rank: "A".to_string(),
+            suit: "♠".to_string(),
+            points: 11,
+            ..Default::default()
+        });
+        deck.cards.push(crate::deck::CardSpec {
+            rank: "2".to_string(),
+            suit: "♠".to_string(),
+            ..Default::default()
+        });
+        let game = crate::GameStructure {
+            deck: Some(crate::game_structure::Deck::CustomDeck(deck)),
+            ..Default::default()
+        };
+        let shoe = Shoe::for_game(&game);
+        let hand = [Card::new(12, 0), Card::new(0, 0), Card::JOKER];
+        let score = |s: &str| Expression::parse(s, &shoe).unwrap().evaluate(&hand);
+        assert_eq!(score("sum(points)"), 11);
+        assert_eq!(score("count(joker)"), 1);
+        assert_eq!(score("count(rank < '3')"), 2);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |s: &str| Expression::parse(s, &Shoe::standard()).unwrap_err();
+        assert_eq!(
+            error("rank == 'A'"),
+            ExprError {
+                column: 1,
+                message: "rank can only be used inside count, sum, most or distinct".to_string()
+            }
+        );
+        assert_eq!(error("count(rank == 'Z')").column, 15);
+        assert_eq!(error("count('A')").column, 7);
+        assert_eq!(error("count(sum(rank))").column, 7);
+        assert_eq!(error("if(1, 2)").message, "wrong number of arguments to if");
+        assert_eq!(error("hearts").message, "unknown name \"hearts\"");
+        assert_eq!(error("1 +").column, 4);
+        assert_eq!(error("(1").message, "expected \")\"");
+        assert_eq!(error("1 2").message, "unexpected text after the expression");
+    }
+}
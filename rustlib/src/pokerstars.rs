@@ -0,0 +1,492 @@
+//! Finished hands written out as PokerStars hand histories, the text that
+//! trackers such as PokerTracker and Hold'em Manager import.
+//!
+//! Only what PokerStars itself deals can be written: no-limit Hold'em,
+//! Omaha and Omaha Hi/Lo, on one board, run out once. A history shows the
+//! hole cards of one player, its hero, if it has one, and of every hand
+//! shown down.
+
+use std::fmt;
+
+use crate::cards::Card;
+use crate::engine::{Action, Chips, Event, GameEngine, HandStatus};
+use crate::GameStructure;
+
+/// What a history says about a hand that the engine doesn't know.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header<'a> {
+    /// The hand's number, unique among those a tracker imports.
+    pub hand: u64,
+    pub table: &'a str,
+    /// When the hand started, in seconds since the Unix epoch.
+    pub started: u64,
+    /// The players' names, by seat.
+    pub names: &'a [String],
+    /// The seat whose hole cards are shown.
+    pub hero: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExportError {
+    /// The hand is still being played, or was a misdeal.
+    NotOver,
+    /// The hand has something a PokerStars history can't say.
+    Unsupported(String),
+    /// The header doesn't fit the hand.
+    Header(String),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::NotOver => write!(f, "the hand isn't over"),
+            ExportError::Unsupported(what) => {
+                write!(f, "a PokerStars hand history can't show {}", what)
+            }
+            ExportError::Header(why) => write!(f, "{}", why),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+/// What PokerStars calls `game`, found from its id or the game it extends.
+fn game_name(game: &GameStructure) -> Option<&'static str> {
+    [game.id.as_str(), game.extends.as_str()]
+        .iter()
+        .find_map(|id| match *id {
+            "holdem" => Some("Hold'em No Limit"),
+            "omaha" => Some("Omaha No Limit"),
+            "omaha-hilo" => Some("Omaha Hi/Lo No Limit"),
+            _ => None,
+        })
+}
+
+/// The date and time `seconds` after the Unix epoch, in UTC, as
+/// `2024/03/09 18:05:00`.
+fn date(seconds: u64) -> String {
+    let (days, time) = (seconds / 86_400, seconds % 86_400);
+    // Howard Hinnant's civil_from_days, for days since 1970-01-01.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{}/{:02}/{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+fn cards(cards: &[Card]) -> String {
+    let cards: Vec<String> = cards.iter().map(Card::to_string).collect();
+    format!("[{}]", cards.join(" "))
+}
+
+/// The streets, as a history names them where seats fold.
+const STREETS: [&str; 4] = ["before Flop", "on the Flop", "on the Turn", "on the River"];
+
+/// Chips as the hand goes: each seat's stack, what it has put in on this
+/// street, and the bet to match.
+struct Ledger {
+    stacks: Vec<Chips>,
+    street: Vec<Chips>,
+    bet: Chips,
+}
+
+impl Ledger {
+    fn put(&mut self, seat: usize, amount: Chips) {
+        self.stacks[seat] -= amount;
+        self.street[seat] += amount;
+        self.bet = self.bet.max(self.street[seat]);
+    }
+
+    fn next_street(&mut self) {
+        self.street.iter_mut().for_each(|c| *c = 0);
+        self.bet = 0;
+    }
+
+    /// `action` by `seat` in words, as it changes the chips.
+    fn act(&mut self, seat: usize, action: &Action) -> Result<String, ExportError> {
+        let (stack, street, bet) = (self.stacks[seat], self.street[seat], self.bet);
+        let text = match *action {
+            Action::Fold => return Ok("folds".to_string()),
+            Action::Check => return Ok("checks".to_string()),
+            Action::Call => {
+                let amount = bet.saturating_sub(street).min(stack);
+                self.put(seat, amount);
+                format!("calls {}", amount)
+            }
+            Action::Bet(to) => {
+                self.put(seat, to - street);
+                format!("bets {}", to - street)
+            }
+            Action::Raise(to) => {
+                self.put(seat, to - street);
+                format!("raises {} to {}", to - bet, to)
+            }
+            Action::AllIn => {
+                self.put(seat, stack);
+                match street + stack {
+                    to if to <= bet => format!("calls {}", stack),
+                    _ if bet == 0 => format!("bets {}", stack),
+                    to => format!("raises {} to {}", to - bet, to),
+                }
+            }
+            ref action => {
+                return Err(ExportError::Unsupported(format!("a {:?}", action)));
+            }
+        };
+        match self.stacks[seat] {
+            0 => Ok(text + " and is all-in"),
+            _ => Ok(text),
+        }
+    }
+}
+
+/// The finished hand in `engine` as a PokerStars hand history, ending in a
+/// blank line.
+pub fn hand_history(engine: &GameEngine, header: &Header) -> Result<String, ExportError> {
+    let game = engine.game();
+    let name = game_name(game)
+        .ok_or_else(|| ExportError::Unsupported(format!("a game of {}", game.name)))?;
+    if engine.status() != HandStatus::Complete {
+        return Err(ExportError::NotOver);
+    }
+    let config = engine.config();
+    let seats = config.stacks.len();
+    if header.names.len() != seats {
+        return Err(ExportError::Header(format!(
+            "{} names for {} seats",
+            header.names.len(),
+            seats
+        )));
+    }
+    if header.hero.is_some_and(|hero| hero >= seats) {
+        return Err(ExportError::Header(format!(
+            "the hero isn't one of the {} seats",
+            seats
+        )));
+    }
+    if engine.runouts().len() > 1 {
+        return Err(ExportError::Unsupported(
+            "a board run more than once".to_string(),
+        ));
+    }
+    let (Some(&small), Some(&big)) = (config.blinds.first(), config.blinds.last()) else {
+        return Err(ExportError::Unsupported(
+            "a hand without blinds".to_string(),
+        ));
+    };
+    let who = |seat: usize| header.names[seat].as_str();
+    let hole =
+        |seat: usize| -> Vec<Card> { engine.seats()[seat].hole.iter().map(|c| c.card).collect() };
+    let events = engine.events();
+
+    let mut out = format!(
+        "PokerStars Hand #{}: {} ({}/{}) - {} UTC\n",
+        header.hand,
+        name,
+        small,
+        big,
+        date(header.started)
+    );
+    out += &format!(
+        "Table '{}' {}-max Seat #{} is the button\n",
+        header.table,
+        seats,
+        engine.button() + 1
+    );
+    for (seat, &stack) in config.stacks.iter().enumerate() {
+        if stack > 0 {
+            out += &format!("Seat {}: {} ({} in chips)\n", seat + 1, who(seat), stack);
+        }
+    }
+
+    // The forced bets come first, though the engine posts the blinds once
+    // the cards are dealt.
+    let mut ledger = Ledger {
+        stacks: config.stacks.clone(),
+        street: vec![0; seats],
+        bet: 0,
+    };
+    let blinds = events
+        .iter()
+        .filter(|e| matches!(e, Event::BlindPosted { .. }))
+        .count();
+    let mut positions = vec![Vec::new(); seats];
+    positions[engine.button()].push("button");
+    let mut posted = 0;
+    for event in events {
+        let line = match *event {
+            Event::AntePosted { seat, amount } => {
+                ledger.stacks[seat] -= amount;
+                format!("{}: posts the ante {}", who(seat), amount)
+            }
+            Event::BlindPosted { seat, amount } => {
+                ledger.put(seat, amount);
+                posted += 1;
+                let blind = match posted < blinds {
+                    true => "small blind",
+                    false => "big blind",
+                };
+                positions[seat].push(blind);
+                format!("{}: posts {} {}", who(seat), blind, amount)
+            }
+            Event::DeadBlindPosted { seat, amount } => {
+                // Only the big blind's part is live.
+                ledger.stacks[seat] -= amount;
+                ledger.street[seat] += amount.min(big);
+                ledger.bet = ledger.bet.max(ledger.street[seat]);
+                format!("{}: posts small & big blinds {}", who(seat), amount)
+            }
+            Event::StraddlePosted { seat, amount } => {
+                ledger.put(seat, amount);
+                format!("{}: posts straddle {}", who(seat), amount)
+            }
+            Event::BombPotPosted { .. } => {
+                return Err(ExportError::Unsupported("a bomb pot".to_string()))
+            }
+            Event::KillBlindPosted { .. } => {
+                return Err(ExportError::Unsupported("a kill pot".to_string()))
+            }
+            _ => continue,
+        };
+        out += &line;
+        out.push('\n');
+    }
+    out += "*** HOLE CARDS ***\n";
+    if let Some(hero) = header.hero.filter(|&hero| config.stacks[hero] > 0) {
+        out += &format!("Dealt to {} {}\n", who(hero), cards(&hole(hero)));
+    }
+
+    let mut board: Vec<Card> = Vec::new();
+    let mut street = 0;
+    let mut folded = vec![None; seats];
+    let mut shown = vec![false; seats];
+    let mut won: Vec<Chips> = vec![0; seats];
+    let mut showdown = false;
+    let mut rake = 0;
+    let describe = |seat: usize| -> String {
+        let hands: Vec<String> = events
+            .iter()
+            .filter_map(|e| match e {
+                Event::HandShown {
+                    seat: s,
+                    scoring,
+                    rank,
+                } if *s == seat => Some((*scoring, rank.describe())),
+                _ => None,
+            })
+            .map(|(scoring, text)| match game.scorings.len() {
+                1 => text,
+                _ if game.scorings[scoring].lo => format!("LO: {}", text),
+                _ => format!("HI: {}", text),
+            })
+            .collect();
+        hands.join("; ")
+    };
+    for event in events {
+        let line = match event {
+            Event::BoardDealt {
+                board: 0,
+                cards: dealt,
+            } => {
+                let before = cards(&board);
+                board.extend(dealt);
+                ledger.next_street();
+                street = match board.len() {
+                    3 => 1,
+                    4 => 2,
+                    5 => 3,
+                    _ => return Err(ExportError::Unsupported("a board of this size".to_string())),
+                };
+                match street {
+                    1 => format!("*** FLOP *** {}", cards(&board)),
+                    2 => format!("*** TURN *** {} {}", before, cards(dealt)),
+                    _ => format!("*** RIVER *** {} {}", before, cards(dealt)),
+                }
+            }
+            Event::BoardDealt { .. } | Event::HiddenBoardDealt { .. } => {
+                return Err(ExportError::Unsupported("more than one board".to_string()))
+            }
+            Event::Acted { seat, action } => {
+                if *action == Action::Fold {
+                    folded[*seat] = Some(street);
+                }
+                format!("{}: {}", who(*seat), ledger.act(*seat, action)?)
+            }
+            Event::UncalledBetReturned { seat, amount } => {
+                format!("Uncalled bet ({}) returned to {}", amount, who(*seat))
+            }
+            Event::RakeTaken { amount } => {
+                rake += amount;
+                continue;
+            }
+            Event::HandShown { seat, .. } => {
+                if shown[*seat] {
+                    continue;
+                }
+                shown[*seat] = true;
+                let line = format!(
+                    "{}: shows {} ({})",
+                    who(*seat),
+                    cards(&hole(*seat)),
+                    describe(*seat)
+                );
+                match std::mem::replace(&mut showdown, true) {
+                    true => line,
+                    false => format!("*** SHOW DOWN ***\n{}", line),
+                }
+            }
+            Event::PotAwarded { seat, amount } => {
+                won[*seat] += amount;
+                let line = format!("{} collected {} from pot", who(*seat), amount);
+                match showdown {
+                    true => line,
+                    false => format!("{}\n{}: doesn't show hand", line, who(*seat)),
+                }
+            }
+            _ => continue,
+        };
+        out += &line;
+        out.push('\n');
+    }
+
+    out += "*** SUMMARY ***\n";
+    out += &format!(
+        "Total pot {} | Rake {}\n",
+        won.iter().sum::<Chips>() + rake,
+        rake
+    );
+    if !board.is_empty() {
+        out += &format!("Board {}\n", cards(&board));
+    }
+    for (seat, &stack) in config.stacks.iter().enumerate() {
+        if stack == 0 {
+            continue;
+        }
+        let place: String = positions[seat]
+            .iter()
+            .map(|p| format!(" ({})", p))
+            .collect();
+        let result = match (folded[seat], shown[seat], won[seat]) {
+            (Some(street), _, _) => format!("folded {}", STREETS[street]),
+            (None, true, 0) => format!(
+                "showed {} and lost with {}",
+                cards(&hole(seat)),
+                describe(seat)
+            ),
+            (None, true, won) => format!(
+                "showed {} and won ({}) with {}",
+                cards(&hole(seat)),
+                won,
+                describe(seat)
+            ),
+            (None, false, 0) => "mucked".to_string(),
+            (None, false, won) => format!("collected ({})", won),
+        };
+        out += &format!("Seat {}: {}{} {}\n", seat + 1, who(seat), place, result);
+    }
+    out.push('\n');
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::EngineConfig;
+    use crate::presets;
+    use crate::shuffle::Shuffler;
+
+    #[test]
+    fn test_date() {
+        assert_eq!(date(0), "1970/01/01 00:00:00");
+        assert_eq!(date(951_827_696), "2000/02/29 12:34:56");
+        assert_eq!(date(1_735_689_599), "2024/12/31 23:59:59");
+    }
+
+    #[test]
+    fn test_hand_history() {
+        let config = EngineConfig {
+            shuffler: Shuffler::seeded(3),
+            ..EngineConfig::new(3)
+        };
+        let mut engine = GameEngine::with_config(presets::holdem(), config).unwrap();
+        let names = ["Ann", "Bo", "Cy"].map(String::from);
+        let header = Header {
+            hand: 7,
+            table: "t1",
+            started: 1_700_000_000,
+            names: &names,
+            hero: Some(0),
+        };
+        assert_eq!(hand_history(&engine, &header), Err(ExportError::NotOver));
+
+        // The button raises, the small blind folds, and the big blind calls
+        // then checks it down.
+        let button = engine.button();
+        let (small, big) = ((button + 1) % 3, (button + 2) % 3);
+        engine.apply_action(button, Action::Raise(6)).unwrap();
+        engine.apply_action(small, Action::Fold).unwrap();
+        engine.apply_action(big, Action::Call).unwrap();
+        while let Some(seat) = engine.to_act() {
+            engine.apply_action(seat, Action::Check).unwrap();
+        }
+        let text = hand_history(&engine, &header).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines[..2],
+            [
+                "PokerStars Hand #7: Hold'em No Limit (1/2) - 2023/11/14 22:13:20 UTC",
+                &format!("Table 't1' 3-max Seat #{} is the button", button + 1),
+            ]
+        );
+        assert_eq!(lines[2], "Seat 1: Ann (200 in chips)");
+        assert_eq!(lines[5], format!("{}: posts small blind 1", names[small]));
+        assert_eq!(lines[6], format!("{}: posts big blind 2", names[big]));
+        assert_eq!(lines[7], "*** HOLE CARDS ***");
+        assert!(lines[8].starts_with("Dealt to Ann ["));
+        assert_eq!(lines[9], format!("{}: raises 4 to 6", names[button]));
+        assert_eq!(lines[11], format!("{}: calls 4", names[big]));
+        assert!(lines[12].starts_with("*** FLOP *** ["));
+        assert!(text.contains("\n*** RIVER *** ["));
+        assert!(text.contains("\n*** SHOW DOWN ***\n"));
+        assert!(text.contains("\nTotal pot 13 | Rake 0\n"));
+        assert!(text.contains(&format!(
+            "\nSeat {}: {} (small blind) folded before Flop\n",
+            small + 1,
+            names[small]
+        )));
+        assert!(text.ends_with("\n\n"));
+
+        let ledger = || Ledger {
+            stacks: vec![10, 50, 50],
+            street: vec![0, 2, 0],
+            bet: 20,
+        };
+        let all_in = |seat| ledger().act(seat, &Action::AllIn).unwrap();
+        assert_eq!(all_in(0), "calls 10 and is all-in");
+        assert_eq!(all_in(1), "raises 32 to 52 and is all-in");
+        let mut unsupported = header;
+        unsupported.names = &names[..2];
+        assert!(matches!(
+            hand_history(&engine, &unsupported),
+            Err(ExportError::Header(_))
+        ));
+        let stud = GameEngine::new(presets::seven_card_stud(), 3).unwrap();
+        assert!(matches!(
+            hand_history(&stud, &header),
+            Err(ExportError::Unsupported(_))
+        ));
+    }
+}
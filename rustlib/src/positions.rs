@@ -0,0 +1,271 @@
+//! Button and blind positions, and how they move between hands.
+//!
+//! Positions follow the "dead button" rule: the big blind always advances to
+//! the next active player, the small blind goes to whoever had the big blind
+//! last hand, and the button goes to last hand's small-blind seat. When those
+//! seats have been vacated the small blind or button is dead for the hand,
+//! so no player skips or double-posts a blind. Players sitting out when the
+//! big blind passes them owe the blinds they missed when they come back.
+
+use crate::entropy::EntropySource;
+use crate::shuffle::fisher_yates;
+
+/// Occupancy of a seat at the start of a hand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeatStatus {
+    Empty,
+
+    /// Occupied, but not dealt in this hand.
+    SittingOut,
+
+    /// Dealt in this hand.
+    Active,
+}
+
+/// Blinds a player skipped while sitting out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MissedBlinds {
+    pub small: bool,
+    pub big: bool,
+}
+
+impl MissedBlinds {
+    pub fn any(self) -> bool {
+        self.small || self.big
+    }
+}
+
+/// Seats `players` players at random at a table of `seats` seats: the
+/// seat of each player, in the order given.
+pub fn assign_seats<R: EntropySource + ?Sized>(
+    players: usize,
+    seats: usize,
+    source: &mut R,
+) -> Vec<usize> {
+    assert!(players <= seats, "more players than seats");
+    let mut order: Vec<usize> = (0..seats).collect();
+    fisher_yates(&mut order, source);
+    order.truncate(players);
+    order
+}
+
+/// Where the button and blinds are for a single hand.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HandPositions {
+    pub button: usize,
+
+    /// The button is on a seat that isn't dealt in.
+    pub dead_button: bool,
+
+    /// `None` when the small blind is dead because its seat was vacated.
+    pub small_blind: Option<usize>,
+
+    pub big_blind: usize,
+
+    /// Active players returning from sitting out who must post the blinds
+    /// they missed: the big blind live, the small blind dead.
+    pub owed: Vec<(usize, MissedBlinds)>,
+}
+
+/// Tracks the button and blinds across hands at one table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TablePositions {
+    button: usize,
+
+    /// Seat of last hand's small blind, even if it was dead.
+    small_blind: Option<usize>,
+    big_blind: Option<usize>,
+    missed: Vec<MissedBlinds>,
+}
+
+impl TablePositions {
+    /// A table with `seats` seats whose first hand has the button at
+    /// `button` (or the next active seat after it).
+    pub fn new(seats: usize, button: usize) -> Self {
+        TablePositions {
+            button,
+            small_blind: None,
+            big_blind: None,
+            missed: vec![MissedBlinds::default(); seats],
+        }
+    }
+
+    /// A table with `seats` seats and the first button drawn at random.
+    pub fn with_random_button<R: EntropySource + ?Sized>(seats: usize, source: &mut R) -> Self {
+        Self::new(seats, source.below(seats as u64) as usize)
+    }
+
+    pub fn seats(&self) -> usize {
+        self.missed.len()
+    }
+
+    /// The blinds `seat` currently owes.
+    pub fn missed(&self, seat: usize) -> MissedBlinds {
+        self.missed[seat]
+    }
+
+    /// Forgets a seat's missed blinds, e.g. when its player leaves.
+    pub fn clear_missed(&mut self, seat: usize) {
+        self.missed[seat] = MissedBlinds::default();
+    }
+
+    /// Assigns positions for the next hand and records them. Returns `None`
+    /// if fewer than two seats are active.
+    pub fn next_hand(&mut self, status: &[SeatStatus]) -> Option<HandPositions> {
+        let n = self.seats();
+        assert_eq!(status.len(), n, "status must cover every seat");
+        let active = |s: usize| status[s] == SeatStatus::Active;
+        let next_active = |from: usize| (1..=n).map(|i| (from + i) % n).find(|&s| active(s));
+        let active_count = (0..n).filter(|&s| active(s)).count();
+        if active_count < 2 {
+            return None;
+        }
+
+        let (button, small_pos, big_blind) = match self.big_blind {
+            None => {
+                let button = if active(self.button) {
+                    self.button
+                } else {
+                    next_active(self.button)?
+                };
+                let small = if active_count == 2 {
+                    button
+                } else {
+                    next_active(button)?
+                };
+                (button, small, next_active(small)?)
+            }
+            Some(prev_bb) => {
+                let big_blind = next_active(prev_bb)?;
+                // Sitting-out players the big blind skipped owe both blinds.
+                let mut s = (prev_bb + 1) % n;
+                while s != big_blind {
+                    if status[s] == SeatStatus::SittingOut {
+                        self.missed[s] = MissedBlinds {
+                            small: true,
+                            big: true,
+                        };
+                    }
+                    s = (s + 1) % n;
+                }
+                if active_count == 2 {
+                    // Heads-up, the button posts the small blind.
+                    let other = next_active(big_blind)?;
+                    (other, other, big_blind)
+                } else {
+                    let button = self.small_blind.unwrap_or(self.button);
+                    (button, prev_bb, big_blind)
+                }
+            }
+        };
+
+        self.button = button;
+        self.small_blind = Some(small_pos);
+        self.big_blind = Some(big_blind);
+
+        let mut owed = Vec::new();
+        for seat in (0..n).filter(|&s| active(s)) {
+            let missed = std::mem::take(&mut self.missed[seat]);
+            // Posting a blind in position settles the debt.
+            if missed.any() && seat != big_blind && seat != small_pos {
+                owed.push((seat, missed));
+            }
+        }
+
+        Some(HandPositions {
+            button,
+            dead_button: !active(button),
+            small_blind: active(small_pos).then_some(small_pos),
+            big_blind,
+            owed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use SeatStatus::*;
+
+    #[test]
+    fn test_rotation() {
+        let mut table = TablePositions::new(4, 0);
+        let status = [Active; 4];
+        let first = table.next_hand(&status).unwrap();
+        assert_eq!(
+            (first.button, first.small_blind, first.big_blind),
+            (0, Some(1), 2)
+        );
+        let second = table.next_hand(&status).unwrap();
+        assert_eq!(
+            (second.button, second.small_blind, second.big_blind),
+            (1, Some(2), 3)
+        );
+    }
+
+    #[test]
+    fn test_dead_small_blind_and_button_after_bust() {
+        let mut table = TablePositions::new(5, 0);
+        table.next_hand(&[Active; 5]).unwrap();
+        // Seat 2, last hand's big blind, busts.
+        let hand = table
+            .next_hand(&[Active, Active, Empty, Active, Active])
+            .unwrap();
+        assert_eq!(hand.small_blind, None);
+        assert_eq!(hand.big_blind, 3);
+        assert_eq!(hand.button, 1);
+        // Next hand the button lands on the empty seat.
+        let hand = table
+            .next_hand(&[Active, Active, Empty, Active, Active])
+            .unwrap();
+        assert_eq!(hand.button, 2);
+        assert!(hand.dead_button);
+        assert_eq!((hand.small_blind, hand.big_blind), (Some(3), 4));
+    }
+
+    #[test]
+    fn test_missed_blinds_are_owed_on_return() {
+        let mut table = TablePositions::new(4, 0);
+        table.next_hand(&[Active; 4]).unwrap();
+        // Seat 3 sits out as the big blind reaches it.
+        let hand = table
+            .next_hand(&[Active, Active, Active, SittingOut])
+            .unwrap();
+        assert_eq!(hand.big_blind, 0);
+        assert!(table.missed(3).big);
+        let hand = table.next_hand(&[Active; 4]).unwrap();
+        assert_eq!(hand.big_blind, 1);
+        assert_eq!(
+            hand.owed,
+            vec![(
+                3,
+                MissedBlinds {
+                    small: true,
+                    big: true
+                }
+            )]
+        );
+        assert!(!table.missed(3).any());
+    }
+
+    #[test]
+    fn test_heads_up_button_posts_small_blind() {
+        let mut table = TablePositions::new(3, 0);
+        table.next_hand(&[Active; 3]).unwrap();
+        let hand = table.next_hand(&[Active, Empty, Active]).unwrap();
+        assert_eq!(hand.big_blind, 0);
+        assert_eq!((hand.button, hand.small_blind), (2, Some(2)));
+    }
+
+    #[test]
+    fn test_random_seating() {
+        let mut source = crate::cards::SplitMix64(5);
+        let mut seats = assign_seats(6, 9, &mut source);
+        assert_eq!(seats.len(), 6);
+        seats.sort();
+        seats.dedup();
+        assert_eq!(seats.len(), 6);
+        assert!(seats.iter().all(|&s| s < 9));
+        assert!(TablePositions::with_random_button(9, &mut source).button < 9);
+    }
+}
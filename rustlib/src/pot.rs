@@ -0,0 +1,299 @@
+//! Pot accounting: per-street contributions, side pots for all-in players,
+//! and who is eligible to win each pot.
+
+use crate::engine::Chips;
+use crate::RakeConfig;
+
+/// A main or side pot and the seats that can win it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pot {
+    pub amount: Chips,
+
+    /// Seats still in the hand that contributed enough to contest this pot,
+    /// in seat order.
+    pub eligible: Vec<usize>,
+}
+
+/// Tracks every seat's contributions over a hand.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PotManager {
+    /// Chips put in over the whole hand, per seat.
+    total: Vec<Chips>,
+
+    /// Chips put in during the current street, per seat.
+    street: Vec<Chips>,
+
+    /// Chips paid to the pot as a price, such as for a bought card, per
+    /// seat. Every seat still in the hand contests them, so unlike a bet
+    /// they never make a side pot.
+    fees: Vec<Chips>,
+
+    folded: Vec<bool>,
+}
+
+impl PotManager {
+    pub fn new(players: usize) -> Self {
+        PotManager {
+            total: vec![0; players],
+            street: vec![0; players],
+            fees: vec![0; players],
+            folded: vec![false; players],
+        }
+    }
+
+    pub fn contribute(&mut self, seat: usize, amount: Chips) {
+        self.total[seat] += amount;
+        self.street[seat] += amount;
+    }
+
+    /// Adds dead money (such as a missed small blind) that goes into the pot
+    /// without counting towards the seat's bet on this street.
+    pub fn contribute_dead(&mut self, seat: usize, amount: Chips) {
+        self.total[seat] += amount;
+    }
+
+    /// Adds chips `seat` paid as a price, which go to the main pot.
+    pub fn pay_fee(&mut self, seat: usize, amount: Chips) {
+        self.fees[seat] += amount;
+    }
+
+    /// Marks a seat as no longer contesting any pot. Its chips stay in.
+    pub fn fold(&mut self, seat: usize) {
+        self.folded[seat] = true;
+    }
+
+    pub fn is_folded(&self, seat: usize) -> bool {
+        self.folded[seat]
+    }
+
+    /// Chips `seat` has put in over the whole hand.
+    pub fn contributed(&self, seat: usize) -> Chips {
+        self.total[seat] + self.fees[seat]
+    }
+
+    /// Chips `seat` has put in on the current street.
+    pub fn street_contribution(&self, seat: usize) -> Chips {
+        self.street[seat]
+    }
+
+    /// All chips in the middle.
+    pub fn total(&self) -> Chips {
+        self.total.iter().chain(&self.fees).sum()
+    }
+
+    /// Closes the current street. Street contributions are folded into the
+    /// hand totals (which already include them) and reset.
+    pub fn end_street(&mut self) {
+        self.street.iter_mut().for_each(|s| *s = 0);
+    }
+
+    /// Takes back the part of the largest bet on this street that nobody
+    /// else matched, returning the seat and the refunded amount. A folded
+    /// seat (whose hand was ruled dead) forfeits it.
+    pub fn return_uncalled(&mut self) -> Option<(usize, Chips)> {
+        let (top, &max) = self.street.iter().enumerate().max_by_key(|&(_, &c)| c)?;
+        let second = self
+            .street
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != top)
+            .map(|(_, &c)| c)
+            .max()
+            .unwrap_or(0);
+        let excess = max - second;
+        if excess == 0 || self.folded[top] {
+            return None;
+        }
+        self.total[top] -= excess;
+        self.street[top] -= excess;
+        Some((top, excess))
+    }
+
+    /// The pots left to award after withholding rake, and the rake taken.
+    /// Rake comes out of the main pot first. `flop_seen` is whether the
+    /// hand got far enough to be raked under no-flop-no-drop.
+    pub fn raked_pots(&self, rake: &RakeConfig, flop_seen: bool) -> (Vec<Pot>, Chips) {
+        let mut pots = self.pots();
+        if rake.no_flop_no_drop && !flop_seen {
+            return (pots, 0);
+        }
+        let mut taken = self.total() * rake.basis_points.max(0) as Chips / 10_000;
+        if rake.cap > 0 {
+            taken = taken.min(rake.cap as Chips);
+        }
+        let mut owed = taken;
+        for pot in &mut pots {
+            let cut = owed.min(pot.amount);
+            pot.amount -= cut;
+            owed -= cut;
+        }
+        pots.retain(|p| p.amount > 0);
+        (pots, taken - owed)
+    }
+
+    /// Splits the chips into a main pot and side pots.
+    ///
+    /// Each distinct contribution level among players still in the hand caps
+    /// a pot; a player is eligible for every pot up to their own level.
+    /// Folded players' chips fill the pots they reach but make them eligible
+    /// for nothing. Pots are returned main pot first.
+    pub fn pots(&self) -> Vec<Pot> {
+        let mut levels: Vec<Chips> = (0..self.total.len())
+            .filter(|&s| !self.folded[s])
+            .map(|s| self.total[s])
+            .collect();
+        levels.sort_unstable();
+        levels.dedup();
+
+        let mut pots: Vec<Pot> = Vec::new();
+        let mut prev = 0;
+        for &level in &levels {
+            let amount: Chips = self.total.iter().map(|&c| c.min(level) - c.min(prev)).sum();
+            let eligible: Vec<usize> = (0..self.total.len())
+                .filter(|&s| !self.folded[s] && self.total[s] >= level)
+                .collect();
+            prev = level;
+            if amount == 0 {
+                continue;
+            }
+            match pots.last_mut() {
+                Some(last) if last.eligible == eligible => last.amount += amount,
+                _ => pots.push(Pot { amount, eligible }),
+            }
+        }
+
+        // Dead money above every live player's level (only possible if
+        // everyone who matched it later folded) goes to the last pot.
+        let leftover: Chips = self.total.iter().map(|&c| c - c.min(prev)).sum();
+        if leftover > 0 {
+            match pots.last_mut() {
+                Some(last) => last.amount += leftover,
+                None => pots.push(Pot {
+                    amount: leftover,
+                    eligible: Vec::new(),
+                }),
+            }
+        }
+
+        let fees: Chips = self.fees.iter().sum();
+        if fees > 0 {
+            match pots.first_mut() {
+                Some(main) => main.amount += fees,
+                None => pots.push(Pot {
+                    amount: fees,
+                    eligible: (0..self.total.len()).filter(|&s| !self.folded[s]).collect(),
+                }),
+            }
+        }
+        pots
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_pot() {
+        let mut pots = PotManager::new(3);
+        for seat in 0..3 {
+            pots.contribute(seat, 10);
+        }
+        pots.fold(1);
+        assert_eq!(
+            pots.pots(),
+            vec![Pot {
+                amount: 30,
+                eligible: vec![0, 2]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_side_pots_for_multiple_all_ins() {
+        let mut pots = PotManager::new(4);
+        // Seat 0 all-in for 20, seat 1 all-in for 50, seats 2 and 3 put in
+        // 100 each, seat 3 folds after contributing 100.
+        pots.contribute(0, 20);
+        pots.contribute(1, 50);
+        pots.contribute(2, 100);
+        pots.contribute(3, 100);
+        pots.fold(3);
+        assert_eq!(
+            pots.pots(),
+            vec![
+                Pot {
+                    amount: 80,
+                    eligible: vec![0, 1, 2]
+                },
+                Pot {
+                    amount: 90,
+                    eligible: vec![1, 2]
+                },
+                Pot {
+                    amount: 100,
+                    eligible: vec![2]
+                },
+            ]
+        );
+        assert_eq!(pots.total(), 270);
+    }
+
+    #[test]
+    fn test_fees_go_to_main_pot() {
+        let mut pots = PotManager::new(3);
+        pots.contribute(0, 10);
+        for seat in 1..3 {
+            pots.contribute(seat, 30);
+        }
+        pots.pay_fee(2, 5);
+        assert_eq!(pots.contributed(2), 35);
+        assert_eq!(pots.total(), 75);
+        assert_eq!(
+            pots.pots(),
+            vec![
+                Pot {
+                    amount: 35,
+                    eligible: vec![0, 1, 2]
+                },
+                Pot {
+                    amount: 40,
+                    eligible: vec![1, 2]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rake() {
+        let mut pots = PotManager::new(3);
+        pots.contribute(0, 10);
+        pots.contribute(1, 50);
+        pots.contribute(2, 50);
+        let rake = RakeConfig {
+            basis_points: 1000,
+            cap: 0,
+            no_flop_no_drop: true,
+        };
+        assert_eq!(pots.raked_pots(&rake, false).1, 0);
+        let (raked, taken) = pots.raked_pots(&rake, true);
+        assert_eq!(taken, 11);
+        assert_eq!(raked[0].amount, 19);
+        assert_eq!(raked[1].amount, 80);
+        let capped = RakeConfig { cap: 5, ..rake };
+        assert_eq!(pots.raked_pots(&capped, true).1, 5);
+    }
+
+    #[test]
+    fn test_return_uncalled() {
+        let mut pots = PotManager::new(2);
+        pots.contribute(0, 10);
+        pots.contribute(1, 40);
+        assert_eq!(pots.return_uncalled(), Some((1, 30)));
+        assert_eq!(pots.return_uncalled(), None);
+        assert_eq!(pots.street_contribution(1), 10);
+        pots.end_street();
+        assert_eq!(pots.street_contribution(1), 0);
+        assert_eq!(pots.contributed(1), 10);
+    }
+}
@@ -0,0 +1,334 @@
+//! Ready-made definitions of the standard casino games.
+
+use crate::builder::GameBuilder;
+use crate::phase::ante::Payer;
+use crate::phase::betting_round::bring_in::Starter;
+use crate::phase::betting_round::{BettingOrder, BringIn};
+use crate::{phase, scoring, GameStructure, HandRanking, Scoring};
+
+/// The short names accepted by [`by_name`], which are also the ids of the
+/// games.
+pub const NAMES: [&str; 10] = [
+    "holdem",
+    "omaha",
+    "omaha-hilo",
+    "stud",
+    "stud-hilo",
+    "razz",
+    "draw",
+    "triple-draw",
+    "badugi",
+    "short-deck",
+];
+
+/// The preset called `name`, one of [`NAMES`].
+pub fn by_name(name: &str) -> Option<GameStructure> {
+    match name {
+        "holdem" => Some(holdem()),
+        "omaha" => Some(omaha()),
+        "omaha-hilo" => Some(omaha_hi_lo()),
+        "stud" => Some(seven_card_stud()),
+        "stud-hilo" => Some(seven_card_stud_hi_lo()),
+        "razz" => Some(razz()),
+        "draw" => Some(five_card_draw()),
+        "triple-draw" => Some(deuce_seven_triple_draw()),
+        "badugi" => Some(badugi()),
+        "short-deck" => Some(short_deck_holdem()),
+        _ => None,
+    }
+}
+
+/// Every preset, in the order of [`NAMES`].
+pub fn all() -> Vec<GameStructure> {
+    NAMES.iter().filter_map(|name| by_name(name)).collect()
+}
+
+/// `hole` cards per player and a five-card board dealt over the flop, turn
+/// and river.
+fn flop_game(id: &str, name: &str, hole: i32) -> GameBuilder {
+    let mut b = GameBuilder::new(id)
+        .name(name)
+        .deal_hole(hole)
+        .betting("Pre-flop");
+    for (street, cards) in [("Flop", 3), ("Turn", 1), ("River", 1)] {
+        b = b.street(0, 1, cards).betting(street);
+    }
+    b
+}
+
+/// Scoring that uses exactly two hole cards and three from the board.
+fn omaha_scoring(s: Scoring) -> Scoring {
+    Scoring {
+        hand_cards: Some(scoring::Range { min: 2, max: 2 }),
+        community_cards: vec![scoring::Range { min: 3, max: 3 }],
+        ..s
+    }
+}
+
+fn high() -> Scoring {
+    Scoring {
+        ranking: Some(scoring::Ranking::StandardRanking(
+            HandRanking::RankingStandard.into(),
+        )),
+        ..Default::default()
+    }
+}
+
+/// Ace-to-five low, only for hands with no card above `qualifier` unless it
+/// is empty.
+fn low(qualifier: &str) -> Scoring {
+    Scoring {
+        name: "Lo".to_string(),
+        ranking: Some(scoring::Ranking::StandardRanking(
+            HandRanking::RankingAceFive.into(),
+        )),
+        lo: true,
+        lo_qualifier: qualifier.to_string(),
+        top_card_can_be_low: true,
+        ..Default::default()
+    }
+}
+
+/// Seven cards per player, two down and one up before the first round, then
+/// three more up and a last one down. The first round is opened by a
+/// bring-in from the lowest upcard for `Starter::Lowest`; later rounds are
+/// led by the best hand showing.
+fn stud_game(id: &str, name: &str, starter: Starter) -> GameBuilder {
+    let mut b = GameBuilder::new(id)
+        .name(name)
+        .ante(Payer::EveryPlayer)
+        .deal_down_up(2, 1)
+        .betting_round(phase::BettingRound {
+            name: "Third street".to_string(),
+            min_bet: 1,
+            order: BettingOrder::BestFaceup.into(),
+            bring_in: Some(BringIn {
+                starter: starter.into(),
+                amount: 1,
+                allow_complete: true,
+            }),
+        });
+    for (street, face_up) in [
+        ("Fourth street", true),
+        ("Fifth street", true),
+        ("Sixth street", true),
+        ("Seventh street", false),
+    ] {
+        b = b
+            .phase(phase::PhaseType::PlayerDeal(phase::PlayerDeal {
+                cards: 1,
+                face_up,
+                ..Default::default()
+            }))
+            .betting_round(phase::BettingRound {
+                name: street.to_string(),
+                min_bet: 1,
+                order: BettingOrder::BestFaceup.into(),
+                ..Default::default()
+            });
+    }
+    b
+}
+
+pub fn holdem() -> GameStructure {
+    flop_game("holdem", "Texas Hold'em", 2)
+        .build()
+        .expect("preset is valid")
+}
+
+pub fn omaha() -> GameStructure {
+    flop_game("omaha", "Omaha", 4)
+        .scoring(omaha_scoring(high()))
+        .build()
+        .expect("preset is valid")
+}
+
+/// Omaha with half of each pot going to the best eight-or-better low.
+pub fn omaha_hi_lo() -> GameStructure {
+    flop_game("omaha-hilo", "Omaha Hi-Lo", 4)
+        .scoring(omaha_scoring(high()))
+        .scoring(omaha_scoring(low("8")))
+        .build()
+        .expect("preset is valid")
+}
+
+pub fn seven_card_stud() -> GameStructure {
+    stud_game("stud", "Seven-Card Stud", Starter::Lowest)
+        .build()
+        .expect("preset is valid")
+}
+
+/// Seven-card stud with half of each pot going to the best eight-or-better
+/// low, or all of it to the high hand if no low qualifies.
+pub fn seven_card_stud_hi_lo() -> GameStructure {
+    stud_game("stud-hilo", "Seven-Card Stud Hi-Lo", Starter::Lowest)
+        .scoring(high())
+        .scoring(low("8"))
+        .build()
+        .expect("preset is valid")
+}
+
+/// Seven-card stud for the lowest hand, straights and flushes ignored. The
+/// highest upcard brings it in.
+pub fn razz() -> GameStructure {
+    stud_game("razz", "Razz", Starter::Highest)
+        .scoring(Scoring {
+            name: String::new(),
+            ..low("")
+        })
+        .build()
+        .expect("preset is valid")
+}
+
+/// Five cards down, a betting round, one draw of up to five cards, and a
+/// second round.
+pub fn five_card_draw() -> GameStructure {
+    GameBuilder::new("draw")
+        .name("Five-Card Draw")
+        .deal_hole(5)
+        .betting("Before the draw")
+        .exchange(5)
+        .betting("After the draw")
+        .build()
+        .expect("preset is valid")
+}
+
+/// `cards` down and three draws, each between betting rounds, for the best
+/// low under `ranking`. Discards are reshuffled if the deck runs out.
+fn triple_draw(id: &str, name: &str, cards: i32, ranking: HandRanking) -> GameStructure {
+    let mut b = GameBuilder::new(id)
+        .name(name)
+        .deal_hole(cards)
+        .betting("Before the first draw");
+    for round in [
+        "After the first draw",
+        "After the second draw",
+        "After the third draw",
+    ] {
+        b = b
+            .phase(phase::PhaseType::Exchange(phase::Exchange {
+                max_exchange: cards,
+                draw_source: phase::exchange::DrawSource::ReshuffleDiscards.into(),
+                ..Default::default()
+            }))
+            .betting(round);
+    }
+    b.scoring(Scoring {
+        ranking: Some(scoring::Ranking::StandardRanking(ranking.into())),
+        lo: true,
+        ..Default::default()
+    })
+    .build()
+    .expect("preset is valid")
+}
+
+/// Triple draw for the best deuce-to-seven low.
+pub fn deuce_seven_triple_draw() -> GameStructure {
+    triple_draw(
+        "triple-draw",
+        "2-7 Triple Draw",
+        5,
+        HandRanking::RankingDeuceSeven,
+    )
+}
+
+/// Four cards and three draws, for the best badugi.
+pub fn badugi() -> GameStructure {
+    triple_draw("badugi", "Badugi", 4, HandRanking::RankingBadugi)
+}
+
+/// Hold'em with the deuces through fives removed. Flushes beat full houses
+/// and trips beat straights, and A-6-7-8-9 is the lowest straight.
+pub fn short_deck_holdem() -> GameStructure {
+    flop_game("short-deck", "Short-Deck Hold'em", 2)
+        .short_deck()
+        .scoring(Scoring {
+            ranking: Some(scoring::Ranking::StandardRanking(
+                HandRanking::RankingShortDeck.into(),
+            )),
+            ..Default::default()
+        })
+        .build()
+        .expect("preset is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{GameEngine, HandStatus};
+    use crate::validation::validate;
+
+    #[test]
+    fn test_presets() {
+        let games = all();
+        assert_eq!(games.len(), NAMES.len());
+        for (game, name) in games.iter().zip(NAMES) {
+            assert_eq!(game.id, name);
+            assert_eq!(validate(game), Ok(()));
+            let engine = GameEngine::new(game.clone(), 4).unwrap();
+            assert_eq!(engine.status(), HandStatus::InProgress, "{}", name);
+        }
+        assert_eq!(by_name("omaha-hilo").unwrap().scorings.len(), 2);
+        assert_eq!(by_name("pineapple"), None);
+    }
+
+    #[test]
+    fn test_short_deck() {
+        let scorer = &crate::showdown::Scorer::for_game(&short_deck_holdem())[0];
+        let best = |s: &str| scorer.best_hand(&crate::cards::parse_cards(s).unwrap(), &[]);
+        assert!(best("Ah Th 9h 7h 6h") > best("Kc Kd Kh 6s 6c"));
+        assert!(best("Ah 6c 7d 8s 9h") > best("Ac Ad Kh Ks Qc"));
+    }
+
+    #[test]
+    fn test_stud_deal() {
+        let engine = GameEngine::new(seven_card_stud(), 3).unwrap();
+        for seat in engine.seats() {
+            let face_up: Vec<bool> = seat.hole.iter().map(|c| c.face_up).collect();
+            assert_eq!(face_up, [false, false, true]);
+        }
+    }
+
+    #[test]
+    fn test_eight_or_better() {
+        let scorers = crate::showdown::Scorer::for_game(&omaha_hi_lo());
+        let low = |hole: &str| {
+            let boards = [crate::cards::parse_cards("2c 3d 7h Ks Qc").unwrap()];
+            scorers[1].best_hand(&crate::cards::parse_cards(hole).unwrap(), &boards)
+        };
+        // Only one low card in the hand can't make a low with the board's
+        // three.
+        assert_eq!(low("Ac Kd Kh Jd"), None);
+        assert!(low("Ac 4d Kh Jd").is_some());
+        assert!(low("Ac 8d Kh Jd") < low("Ac 4d Kh Jd"));
+        let stud = crate::showdown::Scorer::for_game(&seven_card_stud_hi_lo());
+        let stud_low = |s: &str| stud[1].best_hand(&crate::cards::parse_cards(s).unwrap(), &[]);
+        assert_eq!(stud_low("Ac 2d 3h 6s 9s Jc Qd"), None);
+        assert!(stud_low("Ac 2d 3h 8s 6s Jc Qd").is_some());
+    }
+
+    #[test]
+    fn test_triple_draw() {
+        let scorer = &crate::showdown::Scorer::for_game(&deuce_seven_triple_draw())[0];
+        let best = |s: &str| scorer.best_hand(&crate::cards::parse_cards(s).unwrap(), &[]);
+        assert!(best("7c 5d 4h 3s 2c") > best("Ac 2d 3h 4s 5c"));
+        assert!(best("8c 6d 4h 3s 2c") > best("8h 6h 4h 3h 2h"));
+    }
+
+    #[test]
+    fn test_holdem() {
+        let game = holdem();
+        assert_eq!(game.name, "Texas Hold'em");
+        assert_eq!(game.phases.len(), 8);
+        assert_eq!(game.community_board_count, 1);
+        let dealt: i32 = game
+            .phases
+            .iter()
+            .filter_map(|p| match &p.phase_type {
+                Some(phase::PhaseType::CommunityDeal(d)) => Some(d.cards),
+                _ => None,
+            })
+            .sum();
+        assert_eq!(dealt, 5);
+    }
+}
@@ -0,0 +1,433 @@
+//! Ranges of two-card starting hands in the usual shorthand, such as
+//! `"AKs, 77+, A5s-A2s, 25%"`, expanded into the combinations of cards they
+//! stand for.
+//!
+//! A range is a comma-separated list of:
+//!
+//! - a pair (`77`), or two ranks with `s` for suited, `o` for offsuit, or
+//!   neither for both (`AKs`, `AKo`, `AK`)
+//! - any of those with `+`: pairs up to aces (`77+`), or the second card up
+//!   to one below the first (`ATs+` is ATs, AJs, AQs and AKs)
+//! - two of those with a dash, for everything between: `A5s-A2s`, `99-66`
+//! - a particular combination: `AhKh`
+//! - `N%`, or `top N%`, for the strongest starting hands making up that share
+//!   of the 1,326 combinations, ranked by the Chen formula
+//!
+//! Any item can end with `:W` to give its combinations a weight from 0 to 1,
+//! say how often the hand is played. Items listed later override earlier
+//! ones, so `AA:0.5, 22+` holds aces at full weight.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::cards::{Card, SplitMix64};
+
+/// Two hole cards, the higher card first (for a pair, the one with the
+/// later suit).
+pub type Combo = [Card; 2];
+
+/// Total number of two-card combinations in a 52-card deck.
+const COMBOS: usize = 1326;
+
+const RANKS: &[u8; 13] = b"23456789TJQKA";
+
+/// A weighted set of two-card hands.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Range {
+    /// In the order they were first added.
+    combos: Vec<(Combo, f64)>,
+}
+
+/// An item of a range that couldn't be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RangeError {
+    pub item: String,
+    pub message: String,
+}
+
+impl fmt::Display for RangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.item, self.message)
+    }
+}
+
+impl std::error::Error for RangeError {}
+
+/// A starting hand regardless of suits: two ranks, highest first, and for
+/// unpaired ranks whether it's suited (`Some(true)`), offsuit or either.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Class {
+    high: u8,
+    low: u8,
+    suited: Option<bool>,
+}
+
+impl Class {
+    fn combos(self) -> Vec<Combo> {
+        let mut combos = Vec::new();
+        for a in 0..4 {
+            for b in 0..4 {
+                let pair_ok = self.high != self.low || a < b;
+                let suits_ok = match self.suited {
+                    _ if self.high == self.low => true,
+                    Some(true) => a == b,
+                    Some(false) => a != b,
+                    None => true,
+                };
+                if pair_ok && suits_ok {
+                    combos.push(combo(Card::new(self.high, a), Card::new(self.low, b)));
+                }
+            }
+        }
+        combos
+    }
+
+    /// The Chen formula's score for the hand, doubled to keep it whole.
+    fn chen(self) -> i32 {
+        let points = |r: u8| match r {
+            12 => 20,
+            11 => 16,
+            10 => 14,
+            9 => 12,
+            r => r as i32 + 2,
+        };
+        if self.high == self.low {
+            return (2 * points(self.high)).max(10);
+        }
+        let mut score = points(self.high);
+        if self.suited == Some(true) {
+            score += 4;
+        }
+        let gap = self.high - self.low - 1;
+        score -= match gap {
+            0 => 0,
+            1 => 2,
+            2 => 4,
+            3 => 8,
+            _ => 10,
+        };
+        if gap <= 1 && self.high < 10 {
+            score += 2;
+        }
+        // The formula rounds half points up.
+        score + score % 2
+    }
+}
+
+/// Every starting hand, strongest first by the Chen formula, with ties going
+/// to the higher cards.
+fn ranked_classes() -> Vec<Class> {
+    let mut classes = Vec::with_capacity(169);
+    for high in 0..13 {
+        for low in 0..=high {
+            for suited in [true, false] {
+                if high != low || suited {
+                    classes.push(Class {
+                        high,
+                        low,
+                        suited: (high != low).then_some(suited),
+                    });
+                }
+            }
+        }
+    }
+    classes.sort_by_key(|c| {
+        (
+            std::cmp::Reverse(c.chen()),
+            std::cmp::Reverse((c.high, c.low, c.suited)),
+        )
+    });
+    classes
+}
+
+fn rank(c: char) -> Option<u8> {
+    RANKS
+        .iter()
+        .position(|&r| r as char == c.to_ascii_uppercase())
+        .map(|r| r as u8)
+}
+
+fn parse_class(s: &str) -> Option<Class> {
+    let mut chars = s.chars();
+    let (a, b) = (rank(chars.next()?)?, rank(chars.next()?)?);
+    let suited = match chars.next() {
+        None => None,
+        Some('s' | 'S') if a != b => Some(true),
+        Some('o' | 'O') if a != b => Some(false),
+        Some(_) => return None,
+    };
+    if chars.next().is_some() {
+        return None;
+    }
+    Some(Class {
+        high: a.max(b),
+        low: a.min(b),
+        suited,
+    })
+}
+
+fn combo(a: Card, b: Card) -> Combo {
+    if a > b {
+        [a, b]
+    } else {
+        [b, a]
+    }
+}
+
+impl Range {
+    /// The range of every combination, at full weight.
+    pub fn all() -> Self {
+        let mut range = Range::default();
+        for class in ranked_classes() {
+            range.extend(class.combos(), 1.0);
+        }
+        range
+    }
+
+    /// Each combination with its weight, in the order the range lists them.
+    pub fn combos(&self) -> impl Iterator<Item = (Combo, f64)> + '_ {
+        self.combos.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.combos.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.combos.is_empty()
+    }
+
+    /// The weight given to `cards`, in either order, or 0 if the range
+    /// doesn't hold them.
+    pub fn weight(&self, cards: Combo) -> f64 {
+        let cards = combo(cards[0], cards[1]);
+        self.combos
+            .iter()
+            .find(|(c, _)| *c == cards)
+            .map_or(0.0, |&(_, w)| w)
+    }
+
+    /// The range without the combinations that use any of `dead`, such as
+    /// cards on the board.
+    pub fn without(&self, dead: &[Card]) -> Range {
+        Range {
+            combos: self
+                .combos
+                .iter()
+                .filter(|(c, _)| !c.iter().any(|card| dead.contains(card)))
+                .copied()
+                .collect(),
+        }
+    }
+
+    /// Picks a combination not using any of `dead` at random, in
+    /// proportion to the weights, or `None` if none is left.
+    pub(crate) fn pick(&self, rng: &mut SplitMix64, dead: &[Card]) -> Option<Combo> {
+        let live = self.without(dead);
+        let total: f64 = live.combos.iter().map(|(_, w)| w).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut target = (rng.next() >> 11) as f64 / (1u64 << 53) as f64 * total;
+        for &(c, w) in &live.combos {
+            if target < w {
+                return Some(c);
+            }
+            target -= w;
+        }
+        live.combos
+            .iter()
+            .rev()
+            .find(|(_, w)| *w > 0.0)
+            .map(|&(c, _)| c)
+    }
+
+    fn extend(&mut self, combos: Vec<Combo>, weight: f64) {
+        for c in combos {
+            match self.combos.iter_mut().find(|(d, _)| *d == c) {
+                Some(entry) => entry.1 = weight,
+                None => self.combos.push((c, weight)),
+            }
+        }
+    }
+
+    fn parse_item(&mut self, item: &str) -> Result<(), String> {
+        let (hands, weight) = match item.split_once(':') {
+            Some((hands, w)) => match w.trim().parse::<f64>() {
+                Ok(w) if (0.0..=1.0).contains(&w) => (hands.trim(), w),
+                _ => return Err("the weight must be a number from 0 to 1".to_string()),
+            },
+            None => (item, 1.0),
+        };
+        if let Some(share) = hands
+            .strip_prefix("top")
+            .unwrap_or(hands)
+            .trim()
+            .strip_suffix('%')
+        {
+            let share: f64 = match share.trim().parse() {
+                Ok(p) if (0.0..=100.0).contains(&p) => p,
+                _ => return Err("the share must be a percentage from 0 to 100".to_string()),
+            };
+            let wanted = (share / 100.0 * COMBOS as f64).round() as usize;
+            let mut taken = 0;
+            for class in ranked_classes() {
+                if taken >= wanted {
+                    break;
+                }
+                let combos = class.combos();
+                taken += combos.len();
+                self.extend(combos, weight);
+            }
+            return Ok(());
+        }
+        if hands.chars().count() == 4 && hands.chars().nth(1).is_some_and(char::is_alphabetic) {
+            if let Ok(cards) = crate::cards::parse_cards(hands) {
+                if cards[0] == cards[1] {
+                    return Err("a combination needs two different cards".to_string());
+                }
+                self.extend(vec![combo(cards[0], cards[1])], weight);
+                return Ok(());
+            }
+        }
+        let classes = if let Some((from, to)) = hands.split_once('-') {
+            let (from, to) = match (parse_class(from.trim()), parse_class(to.trim())) {
+                (Some(a), Some(b)) => (a, b),
+                _ => return Err("expected hands such as A5s-A2s".to_string()),
+            };
+            let pairs = from.high == from.low && to.high == to.low;
+            if !pairs && (from.high != to.high || from.suited != to.suited) {
+                return Err("a span of hands must keep the first card and suits".to_string());
+            }
+            (from.low.min(to.low)..=from.low.max(to.low))
+                .map(|r| Class {
+                    high: if pairs { r } else { from.high },
+                    low: r,
+                    suited: from.suited,
+                })
+                .collect()
+        } else {
+            let (hand, plus) = match hands.strip_suffix('+') {
+                Some(hand) => (hand, true),
+                None => (hands, false),
+            };
+            let Some(class) = parse_class(hand) else {
+                return Err("expected a hand such as AKs, 77 or AhKh".to_string());
+            };
+            match (plus, class.high == class.low) {
+                (false, _) => vec![class],
+                (true, true) => (class.low..13)
+                    .map(|r| Class {
+                        high: r,
+                        low: r,
+                        ..class
+                    })
+                    .collect(),
+                (true, false) => (class.low..class.high)
+                    .map(|r| Class { low: r, ..class })
+                    .collect(),
+            }
+        };
+        for class in classes {
+            self.extend(class.combos(), weight);
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Range {
+    type Err = RangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut range = Range::default();
+        for item in s.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            range.parse_item(item).map_err(|message| RangeError {
+                item: item.to_string(),
+                message,
+            })?;
+        }
+        Ok(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::parse_cards;
+
+    fn range(s: &str) -> Range {
+        s.parse().unwrap()
+    }
+
+    fn combo_of(s: &str) -> Combo {
+        let cards = parse_cards(s).unwrap();
+        [cards[0], cards[1]]
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(range("AKs").len(), 4);
+        assert_eq!(range("AKo").len(), 12);
+        assert_eq!(range("AK, 77").len(), 22);
+        assert_eq!(range("77+").len(), 8 * 6);
+        assert_eq!(range("ATs+").len(), 16);
+        assert_eq!(range("A5s-A2s").len(), 16);
+        assert_eq!(range("99-66").len(), 24);
+        assert_eq!(range("AhKh, KhAh").len(), 1);
+        assert_eq!(range("AKs, 77+, A5s-A2s").len(), 4 + 48 + 16);
+        assert!(range("A5s-A2s").weight(combo_of("3d Ad")) == 1.0);
+        assert!(range("A5s-A2s").weight(combo_of("Ad 3c")) == 0.0);
+        assert_eq!(Range::all().len(), COMBOS);
+    }
+
+    #[test]
+    fn test_weights_and_dead_cards() {
+        let r = range("AA:0.5, 22+, KK:0.25");
+        assert_eq!(r.weight(combo_of("As Ah")), 1.0);
+        assert_eq!(r.weight(combo_of("Ks Kh")), 0.25);
+        assert_eq!(r.without(&parse_cards("As").unwrap()).len(), 13 * 6 - 3);
+        let mut rng = SplitMix64(5);
+        let dead = parse_cards("As Ah Ad").unwrap();
+        for _ in 0..20 {
+            let picked = r.pick(&mut rng, &dead).unwrap();
+            assert!(!picked.iter().any(|c| dead.contains(c)));
+        }
+        assert_eq!(
+            range("AA").pick(&mut rng, &parse_cards("As Ah Ad").unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_top_share() {
+        // Aces are the Chen formula's best hand, and kings next.
+        assert_eq!(range("top 0.4%"), range("AA"));
+        assert_eq!(range("0.9%"), range("AA, KK"));
+        let top = range("25%");
+        assert!(top.len() >= COMBOS / 4 && top.len() < COMBOS / 4 + 16);
+        assert!(top.weight(combo_of("Ah Kh")) == 1.0);
+        assert!(top.weight(combo_of("7c 2d")) == 0.0);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |s: &str| s.parse::<Range>().unwrap_err();
+        assert_eq!(
+            error("AKs, ZZ"),
+            RangeError {
+                item: "ZZ".to_string(),
+                message: "expected a hand such as AKs, 77 or AhKh".to_string()
+            }
+        );
+        assert_eq!(error("AKs:2").item, "AKs:2");
+        assert_eq!(
+            error("A5s-K2s").message,
+            "a span of hands must keep the first card and suits"
+        );
+        assert_eq!(
+            error("150%").message,
+            "the share must be a percentage from 0 to 100"
+        );
+        assert_eq!(error("77s").item, "77s");
+    }
+}
@@ -0,0 +1,131 @@
+//! Writing out `Repeat` phases as the flat list of phases the engine plays,
+//! with the checks that cut a repetition short.
+
+use crate::{phase, Phase};
+
+/// A check made just before the phase at `at` is played: if `when` holds,
+/// play jumps to `end`, or to showdown if `when.end_hand` is set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stop {
+    pub at: usize,
+    pub end: usize,
+    pub when: phase::Condition,
+}
+
+/// A game's phases with every repeat written out in full.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Expanded {
+    pub phases: Vec<Phase>,
+
+    /// In the order they should be checked: where two share an `at`, the
+    /// one for the outer repeat comes first.
+    pub stops: Vec<Stop>,
+}
+
+/// Writes out each repeat's phases once per pass, nested repeats included.
+/// A repeat's `until` becomes a stop before every pass after the first, and
+/// a condition on the repeat itself a stop before the first pass.
+pub fn expand(phases: &[Phase]) -> Expanded {
+    let mut expanded = Expanded::default();
+    expanded.push_all(phases);
+    expanded
+}
+
+impl Expanded {
+    fn push_all(&mut self, phases: &[Phase]) {
+        for p in phases {
+            match &p.phase_type {
+                Some(phase::PhaseType::Repeat(repeat)) => self.push_repeat(p, repeat),
+                _ => self.phases.push(p.clone()),
+            }
+        }
+    }
+
+    fn push_repeat(&mut self, p: &Phase, repeat: &phase::Repeat) {
+        let start = self.phases.len();
+        let first = self.stops.len();
+        // Indices into `stops` of this repeat's own stops, whose end isn't
+        // known until every pass is written out.
+        let mut own = Vec::new();
+        if let Some(condition) = &p.condition {
+            own.push(self.stops.len());
+            self.stops.push(Stop {
+                at: start,
+                end: start,
+                when: phase::Condition {
+                    negate: !condition.negate,
+                    ..condition.clone()
+                },
+            });
+        }
+        for pass in 0..repeat.times.max(0) {
+            if let Some(until) = repeat.until.as_ref().filter(|_| pass > 0) {
+                own.push(self.stops.len());
+                self.stops.push(Stop {
+                    at: self.phases.len(),
+                    end: start,
+                    when: until.clone(),
+                });
+            }
+            self.push_all(&repeat.phases);
+        }
+        let end = self.phases.len();
+        if end == start {
+            self.stops.truncate(first);
+            return;
+        }
+        for i in own {
+            self.stops[i].end = end;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GameBuilder;
+    use crate::phase::condition::Measure;
+
+    #[test]
+    fn test_expand() {
+        let street = GameBuilder::new("street")
+            .deal_up(1)
+            .betting("Street")
+            .build()
+            .unwrap();
+        let until = phase::Condition {
+            measure: Measure::ActivePlayers.into(),
+            above: 1,
+            negate: true,
+            ..Default::default()
+        };
+        let game = GameBuilder::new("stud")
+            .deal_hole(2)
+            .phase(phase::PhaseType::Repeat(phase::Repeat {
+                times: 3,
+                phases: street.phases.clone(),
+                until: Some(until.clone()),
+            }))
+            .deal_hole(1)
+            .build()
+            .unwrap();
+        let expanded = expand(&game.phases);
+        assert_eq!(expanded.phases.len(), 8);
+        assert_eq!(expanded.phases[5..7], street.phases[..]);
+        assert_eq!(
+            expanded.stops,
+            vec![
+                Stop {
+                    at: 3,
+                    end: 7,
+                    when: until.clone()
+                },
+                Stop {
+                    at: 5,
+                    end: 7,
+                    when: until
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,331 @@
+//! Expands games defined as variants of another game (see
+//! `GameStructure.extends`) into complete definitions.
+
+use std::fmt;
+
+use crate::{phase, GameStructure, Phase, PhaseOverride};
+
+/// Why a variant couldn't be resolved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResolveError {
+    /// No game has the id named in `extends`.
+    UnknownBase(String),
+    /// The games extend each other in a loop, listed from the first one
+    /// resolved.
+    Cycle(Vec<String>),
+    /// A phase override names a phase the inherited game doesn't have.
+    NoSuchPhase { game: String, index: i32 },
+    /// A phase override changes a phase of a different kind.
+    PhaseKindMismatch { game: String, index: usize },
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::UnknownBase(id) => write!(f, "unknown base game {:?}", id),
+            ResolveError::Cycle(ids) => write!(f, "games extend each other: {}", ids.join(" -> ")),
+            ResolveError::NoSuchPhase { game, index } => {
+                write!(f, "{}: the base game has no phase {}", game, index)
+            }
+            ResolveError::PhaseKindMismatch { game, index } => write!(
+                f,
+                "{}: override for phase {} is a different kind of phase",
+                game, index
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+/// The complete definition of `game`, with its base (and the base's base,
+/// and so on) looked up by id with `lookup`, e.g. [`crate::presets::by_name`].
+/// Games that don't extend anything are returned unchanged.
+pub fn resolve<F>(game: &GameStructure, lookup: F) -> Result<GameStructure, ResolveError>
+where
+    F: Fn(&str) -> Option<GameStructure>,
+{
+    let mut chain = vec![game.clone()];
+    while !chain.last().unwrap().extends.is_empty() {
+        let base_id = chain.last().unwrap().extends.clone();
+        if chain.iter().any(|g| g.id == base_id) {
+            let mut ids: Vec<String> = chain.iter().map(|g| g.id.clone()).collect();
+            ids.push(base_id);
+            return Err(ResolveError::Cycle(ids));
+        }
+        chain.push(lookup(&base_id).ok_or(ResolveError::UnknownBase(base_id))?);
+    }
+    let mut resolved = chain.pop().unwrap();
+    while let Some(variant) = chain.pop() {
+        resolved = apply(resolved, variant)?;
+    }
+    Ok(resolved)
+}
+
+/// `base` with the fields that `variant` sets.
+fn apply(mut base: GameStructure, variant: GameStructure) -> Result<GameStructure, ResolveError> {
+    fn set<T: Default + PartialEq>(slot: &mut T, value: T) {
+        if value != T::default() {
+            *slot = value;
+        }
+    }
+    let GameStructure {
+        id,
+        name,
+        deck,
+        phases,
+        community_board_count,
+        scorings,
+        all_in_runouts,
+        rake,
+        dealing_errors,
+        version,
+        extends: _,
+        phase_overrides,
+        parameters,
+        description,
+        localized_names,
+        localized_descriptions,
+        shoe,
+        burns,
+    } = variant;
+    set(&mut base.id, id);
+    set(&mut base.name, name);
+    set(&mut base.deck, deck);
+    set(&mut base.phases, phases);
+    set(&mut base.community_board_count, community_board_count);
+    set(&mut base.scorings, scorings);
+    set(&mut base.all_in_runouts, all_in_runouts);
+    set(&mut base.rake, rake);
+    set(&mut base.dealing_errors, dealing_errors);
+    set(&mut base.shoe, shoe);
+    set(&mut base.burns, burns);
+    set(&mut base.version, version);
+    set(&mut base.parameters, parameters);
+    set(&mut base.description, description);
+    base.localized_names.extend(localized_names);
+    base.localized_descriptions.extend(localized_descriptions);
+    base.extends = String::new();
+    for o in &phase_overrides {
+        override_phases(&mut base.phases, o, &base.id)?;
+    }
+    Ok(base)
+}
+
+fn override_phases(
+    phases: &mut [Phase],
+    o: &PhaseOverride,
+    game: &str,
+) -> Result<(), ResolveError> {
+    let Some(with) = o.phase.as_ref().and_then(|p| p.phase_type.as_ref()) else {
+        return Ok(());
+    };
+    let condition = o.phase.as_ref().and_then(|p| p.condition.clone());
+    if o.index >= 0 {
+        let index = o.index as usize;
+        let target = phases
+            .get_mut(index)
+            .and_then(|p| p.phase_type.as_mut())
+            .ok_or(ResolveError::NoSuchPhase {
+                game: game.to_string(),
+                index: o.index,
+            })?;
+        if !merge_phase(target, with) {
+            return Err(ResolveError::PhaseKindMismatch {
+                game: game.to_string(),
+                index,
+            });
+        }
+        if condition.is_some() {
+            phases[index].condition = condition;
+        }
+    } else {
+        for p in phases.iter_mut() {
+            if p.phase_type.as_mut().is_some_and(|t| merge_phase(t, with)) && condition.is_some() {
+                p.condition = condition.clone();
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Copies the non-default fields of `with` into `target`, if they are the
+/// same kind of phase.
+fn merge_phase(target: &mut phase::PhaseType, with: &phase::PhaseType) -> bool {
+    fn set<T: Clone + Default + PartialEq>(slot: &mut T, value: &T) {
+        if *value != T::default() {
+            *slot = value.clone();
+        }
+    }
+    use phase::PhaseType::*;
+    match (target, with) {
+        (PlayerDeal(t), PlayerDeal(w)) => {
+            set(&mut t.cards, &w.cards);
+            set(&mut t.face_up, &w.face_up);
+            set(&mut t.face_up_cards, &w.face_up_cards);
+            set(&mut t.burn, &w.burn);
+        }
+        (CommunityDeal(t), CommunityDeal(w)) => {
+            set(&mut t.name, &w.name);
+            set(&mut t.cards, &w.cards);
+            set(&mut t.board_idx, &w.board_idx);
+            set(&mut t.burn, &w.burn);
+            set(&mut t.face_down, &w.face_down);
+        }
+        (BettingRound(t), BettingRound(w)) => {
+            set(&mut t.name, &w.name);
+            set(&mut t.min_bet, &w.min_bet);
+            set(&mut t.order, &w.order);
+            set(&mut t.bring_in, &w.bring_in);
+        }
+        (Exchange(t), Exchange(w)) => {
+            set(&mut t.max_exchange, &w.max_exchange);
+            set(&mut t.lock_faceup, &w.lock_faceup);
+            set(&mut t.lock_facedown, &w.lock_facedown);
+            set(&mut t.draw_source, &w.draw_source);
+            set(&mut t.public_discards, &w.public_discards);
+        }
+        (Ante(t), Ante(w)) => set(&mut t.payer, &w.payer),
+        (PassCards(t), PassCards(w)) => {
+            set(&mut t.cards, &w.cards);
+            set(&mut t.direction, &w.direction);
+            set(&mut t.sequential, &w.sequential);
+        }
+        (Reveal(t), Reveal(w)) => {
+            set(&mut t.cards, &w.cards);
+            set(&mut t.choose, &w.choose);
+            set(&mut t.community, &w.community);
+            set(&mut t.board_idx, &w.board_idx);
+        }
+        (Declare(t), Declare(w)) => set(&mut t.in_turn, &w.in_turn),
+        (Buy(t), Buy(w)) => {
+            set(&mut t.price, &w.price);
+            set(&mut t.replace, &w.replace);
+            set(&mut t.face_up, &w.face_up);
+        }
+        (Repeat(t), Repeat(w)) => {
+            set(&mut t.times, &w.times);
+            set(&mut t.phases, &w.phases);
+            set(&mut t.until, &w.until);
+        }
+        _ => return false,
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game_structure, presets, StandardDeck};
+
+    fn min_bets(game: &GameStructure) -> Vec<i32> {
+        game.phases
+            .iter()
+            .filter_map(|p| match &p.phase_type {
+                Some(phase::PhaseType::BettingRound(r)) => Some(r.min_bet),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve_variant() {
+        // Hold'em, but short deck and with a minimum bet of 5.
+        let variant = GameStructure {
+            id: "short-holdem".to_string(),
+            name: "Short Deck Hold'em".to_string(),
+            deck: Some(game_structure::Deck::StandardDeck(
+                StandardDeck::DeckShortDeck.into(),
+            )),
+            extends: "holdem".to_string(),
+            phase_overrides: vec![PhaseOverride {
+                index: -1,
+                phase: Some(Phase {
+                    phase_type: Some(phase::PhaseType::BettingRound(phase::BettingRound {
+                        min_bet: 5,
+                        ..Default::default()
+                    })),
+                    ..Default::default()
+                }),
+            }],
+            ..Default::default()
+        };
+        let game = resolve(&variant, presets::by_name).unwrap();
+        let holdem = presets::holdem();
+        assert_eq!(game.id, "short-holdem");
+        assert_eq!(game.deck, variant.deck);
+        assert_eq!(game.phases.len(), holdem.phases.len());
+        assert_eq!(min_bets(&game), vec![5, 5, 5, 5]);
+        assert_eq!(game.scorings, holdem.scorings);
+        assert!(game.extends.is_empty());
+
+        // A variant of the variant, overriding one phase by index.
+        let mut deeper = GameStructure {
+            id: "deeper".to_string(),
+            extends: "short-holdem".to_string(),
+            phase_overrides: vec![PhaseOverride {
+                index: 1,
+                phase: variant.phase_overrides[0].phase.clone().map(|mut p| {
+                    if let Some(phase::PhaseType::BettingRound(r)) = &mut p.phase_type {
+                        r.min_bet = 2;
+                    }
+                    p.condition = Some(phase::Condition {
+                        measure: phase::condition::Measure::Pot.into(),
+                        above: 10,
+                        ..Default::default()
+                    });
+                    p
+                }),
+            }],
+            ..Default::default()
+        };
+        let lookup = |id: &str| match id {
+            "short-holdem" => Some(variant.clone()),
+            _ => presets::by_name(id),
+        };
+        let game = resolve(&deeper, lookup).unwrap();
+        assert_eq!(game.name, "Short Deck Hold'em");
+        assert_eq!(min_bets(&game), vec![2, 5, 5, 5]);
+        assert!(game.phases[1].condition.is_some());
+        assert!(game.phases[3].condition.is_none());
+
+        deeper.phase_overrides[0].index = 0;
+        assert_eq!(
+            resolve(&deeper, lookup),
+            Err(ResolveError::PhaseKindMismatch {
+                game: "deeper".to_string(),
+                index: 0
+            })
+        );
+        deeper.phase_overrides[0].index = 8;
+        assert_eq!(
+            resolve(&deeper, lookup).unwrap_err().to_string(),
+            "deeper: the base game has no phase 8"
+        );
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        let holdem = presets::holdem();
+        assert_eq!(resolve(&holdem, |_| None), Ok(holdem));
+
+        let game = |id: &str, extends: &str| GameStructure {
+            id: id.to_string(),
+            extends: extends.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve(&game("a", "nope"), presets::by_name),
+            Err(ResolveError::UnknownBase("nope".to_string()))
+        );
+        let lookup = |id: &str| match id {
+            "b" => Some(game("b", "c")),
+            "c" => Some(game("c", "a")),
+            _ => None,
+        };
+        assert_eq!(
+            resolve(&game("a", "b"), lookup).unwrap_err().to_string(),
+            "games extend each other: a -> b -> c -> a"
+        );
+    }
+}
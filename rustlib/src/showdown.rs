@@ -0,0 +1,771 @@
+//! Showdown: ranking the remaining hands and splitting each pot among its
+//! winners.
+
+use crate::cards::{Card, Shoe};
+use crate::deck::JokerRule;
+use crate::engine::Chips;
+use crate::handeval::{Evaluator, HandRank, SHORT_DECK_ORDER};
+use crate::phase::declare::Side;
+use crate::points::Expression;
+use crate::pot::Pot;
+use crate::{scoring, GameStructure, HandRanking, HandType, Scoring};
+
+/// Ranks hands for one [`Scoring`] rule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scorer {
+    scoring: Scoring,
+    evaluator: Evaluator,
+
+    /// Natural cards that play as wild under this scoring.
+    wilds: Vec<Card>,
+
+    /// Whether jokers play as the bug rather than fully wild.
+    bug: bool,
+
+    /// For qualified low scorings, the highest card (as a low ordinal) a
+    /// hand may hold and still play.
+    qualifier: Option<u8>,
+
+    /// For points scorings, the parsed expression, or `None` if it doesn't
+    /// parse (which validation reports) and no hand scores.
+    expression: Option<Expression>,
+}
+
+impl Scorer {
+    /// A scorer for a game played with the standard deck.
+    pub fn new(scoring: &Scoring) -> Self {
+        Self::with_shoe(scoring, &Shoe::standard())
+    }
+
+    /// One scorer per scoring rule in the game, or a single standard
+    /// high-hand scorer if the game doesn't define any.
+    pub fn for_game(game: &GameStructure) -> Vec<Self> {
+        let shoe = Shoe::for_game(game);
+        if game.scorings.is_empty() {
+            return vec![Self::with_shoe(&Scoring::default(), &shoe)];
+        }
+        game.scorings
+            .iter()
+            .map(|s| Self::with_shoe(s, &shoe))
+            .collect()
+    }
+
+    fn with_shoe(scoring: &Scoring, shoe: &Shoe) -> Self {
+        let (low_rank, high_rank) = shoe.rank_range();
+        let top = shoe.rank_labels().get(high_rank as usize);
+        let evaluator = Evaluator {
+            low_rank,
+            high_rank,
+            ace_low_straight: top.is_some_and(|r| r == "A")
+                || scoring.top_card_can_form_low_straight,
+            ..Evaluator::STANDARD
+        };
+        let evaluator = match &scoring.ranking {
+            Some(scoring::Ranking::StandardRanking(r)) => {
+                match HandRanking::try_from(*r).unwrap_or_default() {
+                    HandRanking::RankingShortDeck => evaluator.with_order(&SHORT_DECK_ORDER),
+                    // Only the flush and full house trade places.
+                    HandRanking::RankingShortDeckHighStraight => evaluator.with_order(&[
+                        HandType::FiveOfAKind,
+                        HandType::StraightFlush,
+                        HandType::FourOfAKind,
+                        HandType::Flush,
+                        HandType::FullHouse,
+                    ]),
+                    _ => evaluator,
+                }
+            }
+            Some(scoring::Ranking::CustomHandOrder(order)) => {
+                evaluator.with_order(&order.hand_types().collect::<Vec<_>>())
+            }
+            Some(scoring::Ranking::PointsExpression(_)) | None => evaluator,
+        };
+        let qualifier = if scoring.lo && !scoring.lo_qualifier.is_empty() {
+            shoe.rank_labels()
+                .iter()
+                .position(|r| *r == scoring.lo_qualifier)
+                .map(|i| (i as u8).saturating_sub(evaluator.low_rank) + 1)
+        } else {
+            None
+        };
+        Scorer {
+            scoring: scoring.clone(),
+            evaluator,
+            wilds: if scoring.natural_wilds {
+                Vec::new()
+            } else {
+                shoe.wild_cards().to_vec()
+            },
+            bug: shoe.joker_rule() == JokerRule::Bug,
+            qualifier,
+            expression: match &scoring.ranking {
+                Some(scoring::Ranking::PointsExpression(e)) => Expression::parse(e, shoe).ok(),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn scoring(&self) -> &Scoring {
+        &self.scoring
+    }
+
+    /// The evaluator, if this scorer ranks every card a player holds and
+    /// every board card as one plain high hand: no low, points or wild
+    /// cards, and no limits on the cards used. Such hands can be ranked
+    /// with [`crate::batch::evaluate_seven`].
+    pub(crate) fn plain_high(&self) -> Option<&Evaluator> {
+        let plain = !self.scoring.lo
+            && !matches!(
+                self.scoring.ranking,
+                Some(scoring::Ranking::PointsExpression(_))
+            )
+            && self.wilds.is_empty()
+            && self.scoring.hand_cards.is_none()
+            && self.scoring.community_cards.is_empty();
+        plain.then_some(&self.evaluator)
+    }
+
+    /// Whether the top rank plays below the bottom rank in low hands.
+    fn ace_low(&self) -> bool {
+        let ace_five = matches!(
+            self.scoring.ranking,
+            Some(scoring::Ranking::StandardRanking(r)) if r == HandRanking::RankingAceFive as i32
+        );
+        ace_five || self.scoring.top_card_can_be_low
+    }
+
+    /// Best hand a player can make from their hole cards and the boards, or
+    /// `None` if they have no cards, can't use as many as the scoring
+    /// requires, or their hand doesn't meet the scoring's low qualifier.
+    pub fn best_hand(&self, hole: &[Card], boards: &[Vec<Card>]) -> Option<HandRank> {
+        let (picks, size) = match self.picks(hole, boards) {
+            Ok(picks) => picks,
+            Err(all) => return self.rank(all),
+        };
+        let mut best = None;
+        each_pick(&picks, size, &mut Vec::with_capacity(size), &mut |chosen| {
+            best = best.max(self.rank(chosen.to_vec()));
+        });
+        best
+    }
+
+    /// As [`Scorer::best_hand`], with the cards that make the hand: five of
+    /// them, or as many as the scoring lets a player use if that's fewer.
+    /// A points scoring that counts every card a player holds plays them
+    /// all.
+    pub fn best_cards(&self, hole: &[Card], boards: &[Vec<Card>]) -> Option<(HandRank, Vec<Card>)> {
+        let best = self.best_hand(hole, boards)?;
+        let (picks, size) = match self.picks(hole, boards) {
+            Ok(picks) => picks,
+            Err(all)
+                if matches!(
+                    self.scoring.ranking,
+                    Some(scoring::Ranking::PointsExpression(_))
+                ) =>
+            {
+                return Some((best, all))
+            }
+            Err(all) => {
+                let size = all.len().min(5);
+                (vec![subsets(&all, size)], size)
+            }
+        };
+        let mut found = None;
+        each_pick(&picks, size, &mut Vec::with_capacity(size), &mut |chosen| {
+            if found.is_none() && self.rank(chosen.to_vec()) == Some(best) {
+                found = Some(chosen.to_vec());
+            }
+        });
+        found.map(|cards| (best, cards))
+    }
+
+    /// For the hole cards and each board, every allowed choice of cards from
+    /// it, with the number of cards a hand is made of. Or, if the scoring
+    /// doesn't limit the cards used, `Err` with all of them.
+    #[allow(clippy::type_complexity)]
+    fn picks(
+        &self,
+        hole: &[Card],
+        boards: &[Vec<Card>],
+    ) -> Result<(Vec<Vec<Vec<Card>>>, usize), Vec<Card>> {
+        let mut sources = vec![(hole, self.scoring.hand_cards.as_ref())];
+        sources.extend(
+            boards
+                .iter()
+                .enumerate()
+                .map(|(i, b)| (b.as_slice(), self.scoring.community_cards.get(i)))
+                .filter(|(_, r)| r.is_none_or(|r| r.max != 0)),
+        );
+        let bounds: Vec<(usize, usize)> = sources
+            .iter()
+            .map(|(cards, r)| match r {
+                Some(r) if r.max >= 0 => (r.min.max(0) as usize, cards.len().min(r.max as usize)),
+                Some(r) => (r.min.max(0) as usize, cards.len()),
+                None => (0, cards.len()),
+            })
+            .collect();
+        if bounds
+            .iter()
+            .zip(&sources)
+            .all(|((min, max), (cards, _))| *min == 0 && *max == cards.len())
+        {
+            return Err(sources
+                .iter()
+                .flat_map(|(cards, _)| *cards)
+                .copied()
+                .collect());
+        }
+        // Every way of taking an allowed number of cards from the hand and
+        // from each board that adds up to a five-card hand, or to as many
+        // cards as the ranges allow if that's fewer.
+        let size = bounds.iter().map(|(_, max)| max).sum::<usize>().min(5);
+        let picks = sources
+            .iter()
+            .zip(&bounds)
+            .map(|((cards, _), (min, max))| {
+                (*min..=*max.min(&size))
+                    .flat_map(|k| subsets(cards, k))
+                    .collect()
+            })
+            .collect();
+        Ok((picks, size))
+    }
+
+    /// Ranks exactly the given cards, or `None` if there are none or they
+    /// don't meet the low qualifier.
+    fn rank(&self, mut cards: Vec<Card>) -> Option<HandRank> {
+        if cards.is_empty() {
+            return None;
+        }
+        if let Some(scoring::Ranking::PointsExpression(_)) = self.scoring.ranking {
+            let points = self.expression.as_ref()?.evaluate(&cards);
+            return Some(HandRank::from_points(points, self.scoring.lo));
+        }
+        let dealt = cards.len();
+        cards.retain(|c| *c != Card::JOKER);
+        let jokers = dealt - cards.len();
+        let (naturals, wilds): (Vec<Card>, Vec<Card>) =
+            cards.into_iter().partition(|c| !self.wilds.contains(c));
+        let (wild, bug) = if self.bug {
+            (wilds.len(), jokers)
+        } else {
+            (wilds.len() + jokers, 0)
+        };
+        if !self.scoring.lo {
+            return Some(self.evaluator.evaluate_wild(&naturals, wild, bug));
+        }
+        let ranking = match self.scoring.ranking {
+            Some(scoring::Ranking::StandardRanking(r)) => HandRanking::try_from(r).ok(),
+            _ => None,
+        };
+        let rank = match ranking {
+            Some(HandRanking::RankingDeuceSeven) => {
+                let cards: Vec<Card> = naturals.into_iter().chain(wilds).collect();
+                self.evaluator.evaluate_deuce_seven(&cards)
+            }
+            Some(HandRanking::RankingBadugi) => {
+                let cards: Vec<Card> = naturals.into_iter().chain(wilds).collect();
+                self.evaluator.evaluate_badugi(&cards)
+            }
+            // In a low hand the bug plays as the lowest card missing, just
+            // like a wild card.
+            _ => self
+                .evaluator
+                .evaluate_low_wild(&naturals, self.ace_low(), wild + bug),
+        };
+        match self.qualifier {
+            Some(max) if rank.hand_type() != HandType::NoPair || rank.ranks()[0] > max => None,
+            _ => Some(rank),
+        }
+    }
+}
+
+/// A hand shown down for one scoring rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShownHand {
+    pub seat: usize,
+
+    /// Index into the game's scorings.
+    pub scoring: usize,
+    pub rank: HandRank,
+}
+
+/// The part of a pot awarded by one scoring rule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Split {
+    pub scoring: usize,
+    pub amount: Chips,
+
+    /// Seats that tied for the best hand under this scoring, in seat order.
+    pub winners: Vec<usize>,
+}
+
+/// How one pot was divided.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PotResult {
+    pub amount: Chips,
+
+    /// Every seat that won part of the pot, in seat order.
+    pub winners: Vec<usize>,
+
+    /// The pot's share for each scoring rule, in scoring order. Scorings
+    /// that no eligible hand qualified for are left out, and their share
+    /// goes to the others.
+    pub splits: Vec<Split>,
+
+    /// Chips paid to each winner across all splits, in seat order. Sums to
+    /// `amount`.
+    pub payouts: Vec<(usize, Chips)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShowdownResult {
+    /// Each contender's best hand for each scoring, in seat order.
+    pub hands: Vec<ShownHand>,
+
+    /// Results for each pot, main pot first.
+    pub pots: Vec<PotResult>,
+}
+
+impl ShowdownResult {
+    /// Total chips won by `seat` across all pots.
+    pub fn winnings(&self, seat: usize) -> Chips {
+        self.pots
+            .iter()
+            .flat_map(|p| &p.payouts)
+            .filter(|(s, _)| *s == seat)
+            .map(|(_, amount)| amount)
+            .sum()
+    }
+}
+
+/// Splits `amount` evenly among `winners`. Odd chips go one at a time to the
+/// winners closest to the left of the button.
+pub fn split_pot(
+    amount: Chips,
+    winners: &[usize],
+    button: usize,
+    seats: usize,
+) -> Vec<(usize, Chips)> {
+    if winners.is_empty() {
+        return Vec::new();
+    }
+    let share = amount / winners.len() as Chips;
+    let mut odd = amount % winners.len() as Chips;
+    let mut payouts: Vec<(usize, Chips)> = winners.iter().map(|&w| (w, share)).collect();
+
+    let mut by_position: Vec<usize> = (0..payouts.len()).collect();
+    by_position.sort_by_key(|&i| (payouts[i].0 + seats - button - 1) % seats.max(1));
+    for i in by_position {
+        if odd == 0 {
+            break;
+        }
+        payouts[i].1 += 1;
+        odd -= 1;
+    }
+    payouts
+}
+
+/// Ranks every eligible hand and awards each pot.
+///
+/// `holes` is indexed by seat; seats with no cards (or that aren't eligible
+/// for any pot) are ignored. With several scoring rules, each pot is split
+/// evenly between the rules that have a qualifying hand, any odd chip going
+/// to the earlier rule (the high hand, in hi-lo games). `button` and `seats`
+/// determine who gets odd chips from a split between tied hands.
+pub fn resolve_showdown(
+    game: &GameStructure,
+    holes: &[Vec<Card>],
+    boards: &[Vec<Card>],
+    pots: &[Pot],
+    button: usize,
+) -> ShowdownResult {
+    resolve_declared(game, holes, boards, pots, button, &[])
+}
+
+/// Like [`resolve_showdown`], for declare hi-lo games. `declared` is indexed
+/// by seat: a seat that declared high only contends for scorings without
+/// `lo`, one that declared low only for scorings with it, and one that
+/// declared both is out of a pot unless it wins or ties every scoring. Seats
+/// without a declaration contend for everything. If every contender for a
+/// pot is out, the pot is chopped between them.
+pub fn resolve_declared(
+    game: &GameStructure,
+    holes: &[Vec<Card>],
+    boards: &[Vec<Card>],
+    pots: &[Pot],
+    button: usize,
+    declared: &[Option<Side>],
+) -> ShowdownResult {
+    let side = |seat: usize| declared.get(seat).copied().flatten();
+    let scorers = Scorer::for_game(game);
+    let contending: Vec<usize> = (0..holes.len())
+        .filter(|seat| pots.iter().any(|p| p.eligible.contains(seat)))
+        .collect();
+    // ranks[scoring][seat]
+    let ranks: Vec<Vec<Option<HandRank>>> = scorers
+        .iter()
+        .map(|scorer| {
+            (0..holes.len())
+                .map(|seat| {
+                    contending
+                        .contains(&seat)
+                        .then(|| scorer.best_hand(&holes[seat], boards))
+                        .flatten()
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut result = ShowdownResult {
+        hands: Vec::new(),
+        pots: Vec::with_capacity(pots.len()),
+    };
+    for &seat in &contending {
+        for (scoring, r) in ranks.iter().enumerate() {
+            if let Some(rank) = r[seat] {
+                result.hands.push(ShownHand {
+                    seat,
+                    scoring,
+                    rank,
+                });
+            }
+        }
+    }
+
+    let plays = |seat: usize, scoring: usize| match side(seat) {
+        Some(Side::High) => !scorers[scoring].scoring().lo,
+        Some(Side::Low) => scorers[scoring].scoring().lo,
+        Some(Side::Both | Side::Unknown) | None => true,
+    };
+    for pot in pots {
+        let mut contenders = pot.eligible.clone();
+        let mut winning = loop {
+            let mut winning: Vec<(usize, Vec<usize>)> = Vec::new();
+            for (scoring, r) in ranks.iter().enumerate() {
+                let seats = contenders.iter().copied().filter(|&s| plays(s, scoring));
+                let best = seats.clone().filter_map(|s| r[s]).max();
+                let winners: Vec<usize> =
+                    seats.filter(|&s| best.is_some() && r[s] == best).collect();
+                if !winners.is_empty() {
+                    winning.push((scoring, winners));
+                }
+            }
+            // Declaring both ways means winning every way.
+            let before = contenders.len();
+            contenders.retain(|&s| {
+                side(s) != Some(Side::Both)
+                    || (0..ranks.len())
+                        .all(|scoring| winning.iter().any(|(i, w)| *i == scoring && w.contains(&s)))
+            });
+            if contenders.len() == before {
+                break winning;
+            }
+        };
+        if winning.is_empty() && !pot.eligible.is_empty() {
+            // Nobody has a hand to show (or qualifies anywhere): chop it.
+            winning.push((0, pot.eligible.clone()));
+        }
+
+        let ways = winning.len().max(1) as Chips;
+        let mut odd = pot.amount % ways;
+        let mut splits = Vec::with_capacity(winning.len());
+        let mut payouts: Vec<(usize, Chips)> = Vec::new();
+        for (scoring, winners) in winning {
+            let amount = pot.amount / ways + Chips::from(odd > 0);
+            odd = odd.saturating_sub(1);
+            for (seat, won) in split_pot(amount, &winners, button, holes.len()) {
+                match payouts.iter_mut().find(|(s, _)| *s == seat) {
+                    Some(p) => p.1 += won,
+                    None => payouts.push((seat, won)),
+                }
+            }
+            splits.push(Split {
+                scoring,
+                amount,
+                winners,
+            });
+        }
+        payouts.sort_unstable();
+        result.pots.push(PotResult {
+            amount: pot.amount,
+            winners: payouts.iter().map(|&(s, _)| s).collect(),
+            splits,
+            payouts,
+        });
+    }
+    result
+}
+
+/// Calls `f` with each way of completing `chosen` to `size` cards with one
+/// pick from each of `picks`.
+fn each_pick(
+    picks: &[Vec<Vec<Card>>],
+    size: usize,
+    chosen: &mut Vec<Card>,
+    f: &mut dyn FnMut(&[Card]),
+) {
+    let Some((first, rest)) = picks.split_first() else {
+        if chosen.len() == size {
+            f(chosen);
+        }
+        return;
+    };
+    for pick in first {
+        if chosen.len() + pick.len() > size {
+            continue;
+        }
+        chosen.extend(pick);
+        each_pick(rest, size, chosen, f);
+        chosen.truncate(chosen.len() - pick.len());
+    }
+}
+
+/// Every `k`-card subset of `cards`, in order.
+fn subsets(cards: &[Card], k: usize) -> Vec<Vec<Card>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+    if cards.len() < k {
+        return Vec::new();
+    }
+    let mut with: Vec<Vec<Card>> = subsets(&cards[1..], k - 1);
+    for s in &mut with {
+        s.insert(0, cards[0]);
+    }
+    with.extend(subsets(&cards[1..], k));
+    with
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::parse_cards;
+
+    fn cards(s: &str) -> Vec<Card> {
+        parse_cards(s).unwrap()
+    }
+
+    #[test]
+    fn test_side_pot_goes_to_best_eligible_hand() {
+        let holes = vec![cards("Ac Ad"), cards("Kc Kd"), cards("2c 7d")];
+        let boards = vec![cards("Ah Kh 9s 4c 3d")];
+        let pots = vec![
+            Pot {
+                amount: 30,
+                eligible: vec![0, 1, 2],
+            },
+            Pot {
+                amount: 40,
+                eligible: vec![1, 2],
+            },
+        ];
+        let result = resolve_showdown(&GameStructure::default(), &holes, &boards, &pots, 0);
+        assert_eq!(result.pots[0].winners, vec![0]);
+        assert_eq!(result.pots[1].winners, vec![1]);
+        assert_eq!(result.winnings(0), 30);
+        assert_eq!(result.winnings(1), 40);
+        assert_eq!(result.winnings(2), 0);
+    }
+
+    #[test]
+    fn test_split_with_odd_chip() {
+        let holes = vec![cards("2c 3d"), cards("2d 3c"), cards("4h 5h")];
+        let boards = vec![cards("As Ks Qs Js 9h")];
+        let pots = vec![Pot {
+            amount: 11,
+            eligible: vec![0, 1],
+        }];
+        // Button on seat 0: seat 1 is first to its left and gets the odd chip.
+        let result = resolve_showdown(&GameStructure::default(), &holes, &boards, &pots, 0);
+        assert_eq!(result.pots[0].payouts, vec![(0, 5), (1, 6)]);
+    }
+
+    #[test]
+    fn test_split_pot_order() {
+        assert_eq!(
+            split_pot(10, &[1, 3, 5], 4, 6),
+            vec![(1, 3), (3, 3), (5, 4)]
+        );
+        assert_eq!(
+            split_pot(11, &[1, 3, 5], 4, 6),
+            vec![(1, 4), (3, 3), (5, 4)]
+        );
+    }
+
+    fn hi_lo() -> GameStructure {
+        GameStructure {
+            scorings: vec![
+                Scoring {
+                    name: "Hi".to_string(),
+                    ..Default::default()
+                },
+                Scoring {
+                    name: "Lo".to_string(),
+                    ranking: Some(scoring::Ranking::StandardRanking(
+                        HandRanking::RankingAceFive.into(),
+                    )),
+                    lo: true,
+                    lo_qualifier: "8".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_hi_lo_split() {
+        // Seat 0 has the nut low, seat 1 trips for high, seat 2 a worse low.
+        let holes = vec![cards("Ac 2d"), cards("Kc Kd"), cards("3c 6d")];
+        let boards = vec![cards("Kh 4s 5c 8d Js")];
+        let pots = vec![Pot {
+            amount: 61,
+            eligible: vec![0, 1, 2],
+        }];
+        let result = resolve_showdown(&hi_lo(), &holes, &boards, &pots, 2);
+        let pot = &result.pots[0];
+        assert_eq!(pot.splits[0].winners, vec![1]);
+        assert_eq!(pot.splits[1].winners, vec![0]);
+        // The odd chip goes to the high hand.
+        assert_eq!(pot.payouts, vec![(0, 30), (1, 31)]);
+        assert_eq!(pot.winners, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_unqualified_low_gives_high_the_pot() {
+        let holes = vec![cards("Ac 9d"), cards("Kc Kd")];
+        let boards = vec![cards("Kh Qs Tc 9c 2s")];
+        let pots = vec![Pot {
+            amount: 40,
+            eligible: vec![0, 1],
+        }];
+        let result = resolve_showdown(&hi_lo(), &holes, &boards, &pots, 0);
+        assert_eq!(result.pots[0].splits.len(), 1);
+        assert_eq!(result.pots[0].payouts, vec![(1, 40)]);
+        // Nobody shows a low hand.
+        assert!(result.hands.iter().all(|h| h.scoring == 0));
+    }
+
+    #[test]
+    fn test_declare_both_ways() {
+        // Seat 0 has the nut low but loses the high to seat 1's trips.
+        let holes = vec![cards("Ac 2d"), cards("Kc Kd"), cards("3c 6d")];
+        let boards = vec![cards("Kh 4s 5c 8d Js")];
+        let pots = vec![Pot {
+            amount: 61,
+            eligible: vec![0, 1, 2],
+        }];
+        let declared = [Some(Side::Both), Some(Side::High), Some(Side::Low)];
+        let result = resolve_declared(&hi_lo(), &holes, &boards, &pots, 2, &declared);
+        // Declaring both and losing one way forfeits the low to seat 2.
+        assert_eq!(result.pots[0].payouts, vec![(1, 31), (2, 30)]);
+
+        let declared = [Some(Side::Both), Some(Side::Low), Some(Side::Low)];
+        let result = resolve_declared(&hi_lo(), &holes, &boards, &pots, 2, &declared);
+        assert_eq!(result.pots[0].payouts, vec![(0, 61)]);
+    }
+
+    #[test]
+    fn test_wild_cards() {
+        // Deuces wild, with a natural low.
+        let deck = crate::Deck {
+            wilds: vec![crate::deck::Wild {
+                rank: "2".to_string(),
+                suits: vec![],
+            }],
+            jokers: 1,
+            ..Default::default()
+        };
+        let game = GameStructure {
+            deck: Some(crate::game_structure::Deck::CustomDeck(deck)),
+            scorings: vec![
+                Scoring::default(),
+                Scoring {
+                    lo: true,
+                    natural_wilds: true,
+                    ranking: Some(scoring::Ranking::StandardRanking(
+                        HandRanking::RankingAceFive.into(),
+                    )),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let scorers = Scorer::for_game(&game);
+        let high = |s: &str| scorers[0].best_hand(&cards(s), &[]).unwrap();
+        assert_eq!(high("2c 2d Jk 9s 9h").hand_type(), HandType::FiveOfAKind);
+        assert_eq!(high("2c 5s 6s 7s Qs").hand_type(), HandType::Flush);
+        let low = scorers[1].best_hand(&cards("2c 2d 3h 4s 5c"), &[]).unwrap();
+        assert_eq!(low.hand_type(), HandType::Pair);
+        let joker = scorers[1].best_hand(&cards("Jk 2d 3h 4s 5c"), &[]).unwrap();
+        assert_eq!(joker.ranks(), [4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_points_scoring() {
+        let game = crate::builder::GameBuilder::new("hearts")
+            .deal_hole(5)
+            .high()
+            .points("Hearts", "count(suit == '♥')")
+            .build()
+            .unwrap();
+        let holes = vec![cards("Ah Kh Qh Jh Th"), cards("2h 3h 4h 7s 8s")];
+        let pots = vec![Pot {
+            amount: 20,
+            eligible: vec![0, 1],
+        }];
+        let result = resolve_showdown(&game, &holes, &[], &pots, 0);
+        assert_eq!(result.pots[0].payouts, vec![(0, 20)]);
+
+        let fewest = Scorer::new(&Scoring {
+            ranking: game.scorings[1].ranking.clone(),
+            lo: true,
+            ..Default::default()
+        });
+        let rank = |s: &str| fewest.best_hand(&cards(s), &[]).unwrap();
+        assert!(rank("2h 3s 4s") > rank("2h 3h 4s"));
+        assert_eq!(rank("2h 3h 4s").points(), Some(2));
+    }
+
+    #[test]
+    fn test_hand_and_board_card_counts() {
+        let omaha = Scorer::for_game(&crate::presets::omaha()).remove(0);
+        let board = vec![cards("2h 7c 8d 9s 3c")];
+        let best = |scorer: &Scorer, hole: &str| scorer.best_hand(&cards(hole), &board).unwrap();
+        // Four hearts in the hand and one on the board are no flush: the hand
+        // plays two hole cards and three from the board.
+        assert_eq!(
+            best(&Scorer::new(&Scoring::default()), "Ah Kh Qh Jh").hand_type(),
+            HandType::Flush
+        );
+        assert_eq!(best(&omaha, "Ah Kh Qh Jh").hand_type(), HandType::NoPair);
+        assert_eq!(best(&omaha, "Th 6h Qc Kd").hand_type(), HandType::Straight);
+        assert_eq!(omaha.best_hand(&cards("Ah"), &board), None);
+
+        // At most one hole card: the pocket pair can't play as trips.
+        let one = Scorer::new(&Scoring {
+            hand_cards: Some(scoring::Range { min: 0, max: 1 }),
+            ..Default::default()
+        });
+        assert_eq!(best(&one, "9c 9d").hand_type(), HandType::Pair);
+    }
+
+    #[test]
+    fn test_best_cards() {
+        let board = vec![cards("2h 7c 8d 9s 3c")];
+        let omaha = Scorer::for_game(&crate::presets::omaha()).remove(0);
+        let (rank, used) = omaha.best_cards(&cards("Th 6h Qc Kd"), &board).unwrap();
+        assert_eq!(rank.hand_type(), HandType::Straight);
+        assert_eq!(used, cards("Th 6h 7c 8d 9s"));
+
+        let any = Scorer::new(&Scoring::default());
+        let (rank, used) = any.best_cards(&cards("Ah Kh Qh Jh"), &board).unwrap();
+        assert_eq!(rank.hand_type(), HandType::Flush);
+        assert_eq!(used, cards("Ah Kh Qh Jh 2h"));
+        assert_eq!(any.best_hand(&cards("Ah Kh Qh Jh"), &board), Some(rank));
+        let (_, used) = any.best_cards(&cards("Ah Kh"), &[]).unwrap();
+        assert_eq!(used, cards("Ah Kh"));
+        assert_eq!(any.best_cards(&[], &[]), None);
+    }
+}
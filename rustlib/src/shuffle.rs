@@ -0,0 +1,349 @@
+//! Deck shuffling, with the random number generator injectable so that
+//! simulations and tests can reproduce exact deals.
+//!
+//! Real games shuffle with [`Shuffler::Secure`], the default, which draws
+//! every number from the operating system's cryptographic generator. The
+//! shuffle itself is [`fisher_yates`], short enough to audit at a glance:
+//! each card is swapped with one chosen uniformly from those not yet
+//! placed, so every order of the deck is equally likely.
+
+use std::fmt;
+
+use crate::cards::{Card, Shoe, SplitMix64};
+use crate::engine::Event;
+use crate::entropy::{EntropySource, OsEntropy, SharedSource};
+use crate::fingerprint::sha256;
+use crate::GameStructure;
+
+/// Shuffles `cards` in place with a Fisher-Yates shuffle driven by `rng`.
+pub fn fisher_yates<T, R: EntropySource + ?Sized>(cards: &mut [T], rng: &mut R) {
+    for i in (1..cards.len()).rev() {
+        let j = rng.below(i as u64 + 1) as usize;
+        cards.swap(i, j);
+    }
+}
+
+/// How the engine shuffles its decks.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Shuffler {
+    /// Unpredictable, from OS entropy through [`OsEntropy`]. Nothing is recorded
+    /// that could reproduce the deal.
+    #[default]
+    Secure,
+    /// Deterministic: the same seed always deals the same cards. The engine
+    /// records the seed with [`crate::engine::Event::Shuffled`].
+    Seeded(u64),
+    /// Provably fair: deals like `Seeded` with a secret seed, publishing a
+    /// hash of it before the hand and the seed itself once the shoe is
+    /// done with, so players can check the deal with [`verify_deal`].
+    Committed(Commitment),
+    /// Draws from an injected source, such as a certified hardware
+    /// generator. Like `Secure`, nothing is recorded.
+    Source(SharedSource),
+    /// Deals orders recorded from an earlier hand again, as when a
+    /// [`crate::compact::CompactState`] is restored: the shuffle numbered
+    /// `round` leaves the cards in the order recorded for it, bottom first.
+    /// A shuffle with nothing recorded, or of other cards, is secure.
+    Recorded(Vec<(u64, Vec<Card>)>),
+}
+
+/// The secret behind a provably fair shuffle. The salt keeps the seed from
+/// being found by hashing every possible seed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Commitment {
+    pub seed: u64,
+    pub salt: [u8; 16],
+}
+
+impl Commitment {
+    /// A fresh seed and salt from OS entropy. Panics if there is none.
+    pub fn generate() -> Self {
+        let mut rng = OsEntropy::new().expect("system random number generator unavailable");
+        let mut salt = [0; 16];
+        salt[..8].copy_from_slice(&rng.next_u64().to_le_bytes());
+        salt[8..].copy_from_slice(&rng.next_u64().to_le_bytes());
+        Commitment {
+            seed: rng.next_u64(),
+            salt,
+        }
+    }
+
+    /// What is published before the hand: the SHA-256 of the seed, as 8
+    /// little-endian bytes, followed by the salt.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut data = self.seed.to_le_bytes().to_vec();
+        data.extend_from_slice(&self.salt);
+        sha256(&data)
+    }
+}
+
+impl Shuffler {
+    /// The default shuffler, for real games.
+    pub fn secure() -> Self {
+        Shuffler::Secure
+    }
+
+    /// A reproducible shuffler, for simulations and tests.
+    pub fn seeded(seed: u64) -> Self {
+        Shuffler::Seeded(seed)
+    }
+
+    /// A shuffler drawing on `source`.
+    pub fn with_source(source: impl EntropySource + Send + 'static) -> Self {
+        Shuffler::Source(SharedSource::new(source))
+    }
+
+    /// A provably fair shuffler with a fresh secret seed.
+    pub fn committed() -> Self {
+        Shuffler::Committed(Commitment::generate())
+    }
+
+    /// The seed, if shuffles are reproducible and the seed isn't secret.
+    pub fn seed(&self) -> Option<u64> {
+        match *self {
+            Shuffler::Seeded(seed) => Some(seed),
+            _ => None,
+        }
+    }
+
+    /// The secret of a provably fair shuffler.
+    pub fn commitment(&self) -> Option<&Commitment> {
+        match self {
+            Shuffler::Committed(commitment) => Some(commitment),
+            _ => None,
+        }
+    }
+
+    /// Shuffles `cards`. `round` tells apart the shuffles made during one
+    /// hand, so that reshuffling the discards in a draw doesn't repeat the
+    /// deck's order. Panics if a secure shuffle can't get OS entropy.
+    pub fn shuffle(&self, cards: &mut [Card], round: u64) {
+        match self {
+            Shuffler::Secure => {
+                let mut rng = OsEntropy::new().expect("system random number generator unavailable");
+                fisher_yates(cards, &mut rng)
+            }
+            Shuffler::Seeded(seed) | Shuffler::Committed(Commitment { seed, .. }) => {
+                fisher_yates(cards, &mut SplitMix64(seed.wrapping_add(round)))
+            }
+            Shuffler::Source(source) => fisher_yates(cards, &mut source.clone()),
+            Shuffler::Recorded(orders) => {
+                let recorded = orders.iter().find(|(r, order)| {
+                    let (mut a, mut b) = (order.clone(), cards.to_vec());
+                    a.sort_unstable();
+                    b.sort_unstable();
+                    *r == round && a == b
+                });
+                match recorded {
+                    Some((_, order)) => cards.copy_from_slice(order),
+                    None => Shuffler::Secure.shuffle(cards, round),
+                }
+            }
+        }
+    }
+
+    /// The shuffler for the next deal, as when a misdealt hand is dealt
+    /// again. A committed shuffler gets a new secret, since the old one is
+    /// revealed.
+    pub fn next(&self) -> Self {
+        match self {
+            Shuffler::Seeded(seed) => Shuffler::Seeded(seed.wrapping_add(1)),
+            Shuffler::Committed(_) => Shuffler::committed(),
+            Shuffler::Recorded(_) => Shuffler::Secure,
+            other => other.clone(),
+        }
+    }
+}
+
+/// Why a provably fair deal failed to check out.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FairnessError {
+    /// The events have no [`Event::ShuffleCommitted`].
+    NotCommitted,
+    /// The events have no [`Event::ShuffleRevealed`] after the commitment.
+    NotRevealed,
+    /// The revealed seed and salt don't hash to the commitment.
+    HashMismatch,
+    /// A card was dealt that the revealed seed doesn't put there.
+    CardOutOfOrder(Card),
+}
+
+impl fmt::Display for FairnessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FairnessError::NotCommitted => write!(f, "the shuffle wasn't committed to"),
+            FairnessError::NotRevealed => write!(f, "the shuffle's seed wasn't revealed"),
+            FairnessError::HashMismatch => {
+                write!(f, "the revealed seed doesn't match the commitment")
+            }
+            FairnessError::CardOutOfOrder(card) => {
+                write!(f, "{} wasn't dealt where the seed puts it", card)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FairnessError {}
+
+/// Checks a provably fair deal from the hand's published events: that the
+/// revealed seed matches the commitment, and that every card dealt came off
+/// the deck that seed shuffles, in order. Burned and face-down community
+/// cards aren't shown and so can't be checked, and checking stops if the
+/// discards are reshuffled. For a shoe kept over several hands, pass the
+/// events of all of them.
+pub fn verify_deal(game: &GameStructure, events: &[Event]) -> Result<(), FairnessError> {
+    let start = events
+        .iter()
+        .position(|e| matches!(e, Event::ShuffleCommitted { .. }))
+        .ok_or(FairnessError::NotCommitted)?;
+    let Event::ShuffleCommitted { hash } = events[start] else {
+        unreachable!()
+    };
+    let commitment = events[start..]
+        .iter()
+        .find_map(|e| match *e {
+            Event::ShuffleRevealed { seed, salt } => Some(Commitment { seed, salt }),
+            _ => None,
+        })
+        .ok_or(FairnessError::NotRevealed)?;
+    if commitment.hash() != hash {
+        return Err(FairnessError::HashMismatch);
+    }
+
+    let mut shoe = Shoe::for_game(game);
+    shoe.shuffle_with(&Shuffler::Committed(commitment), 0);
+    let mut deck = std::iter::from_fn(|| shoe.deal());
+    for event in &events[start..] {
+        let cards: Vec<Card> = match event {
+            Event::HoleCardsDealt { cards, .. } => cards.iter().map(|c| c.card).collect(),
+            Event::BoardDealt { cards, .. } => cards.clone(),
+            Event::DiscardsReshuffled { .. } => break,
+            _ => continue,
+        };
+        for card in cards {
+            if !deck.any(|c| c == card) {
+                return Err(FairnessError::CardOutOfOrder(card));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts up from zero, to check the shuffle's arithmetic.
+    struct Counter(u64);
+
+    impl EntropySource for Counter {
+        fn next_u64(&mut self) -> u64 {
+            self.0 += 1;
+            self.0 - 1
+        }
+    }
+
+    #[test]
+    fn test_fisher_yates() {
+        let mut cards: Vec<Card> = (0..4).map(|r| Card::new(r, 0)).collect();
+        fisher_yates(&mut cards, &mut Counter(0));
+        // Swaps 3 with 0, then 2 with 1, then 1 with 0.
+        let ranks: Vec<u8> = cards.iter().map(|c| c.rank).collect();
+        assert_eq!(ranks, vec![2, 3, 1, 0]);
+    }
+
+    #[test]
+    fn test_seeded_shuffles_repeat() {
+        let deck: Vec<Card> = (0..13)
+            .flat_map(|rank| (0..4).map(move |suit| Card::new(rank, suit)))
+            .collect();
+        let shuffled = |shuffler: &Shuffler, round| {
+            let mut cards = deck.clone();
+            shuffler.shuffle(&mut cards, round);
+            cards
+        };
+        let shuffler = Shuffler::seeded(7);
+        assert_eq!(shuffled(&shuffler, 0), shuffled(&shuffler, 0));
+        assert_ne!(shuffled(&shuffler, 0), shuffled(&shuffler, 1));
+        assert_ne!(shuffled(&shuffler, 0), shuffled(&shuffler.next(), 0));
+        assert_eq!(shuffler.seed(), Some(7));
+        // An injected source draws the same numbers as seeding does.
+        let injected = Shuffler::with_source(SplitMix64(7));
+        assert_eq!(shuffled(&injected, 0), shuffled(&shuffler, 0));
+        assert_eq!(injected.seed(), None);
+    }
+
+    #[test]
+    fn test_secure_shuffle() {
+        let deck: Vec<Card> = (0..13)
+            .flat_map(|rank| (0..4).map(move |suit| Card::new(rank, suit)))
+            .collect();
+        let shuffled = || {
+            let mut cards = deck.clone();
+            Shuffler::secure().shuffle(&mut cards, 0);
+            cards
+        };
+        let (a, b) = (shuffled(), shuffled());
+        // One chance in 52! of a false failure.
+        assert_ne!(a, b);
+        let mut sorted = a.clone();
+        sorted.sort();
+        assert_eq!(sorted, deck);
+        assert_eq!(Shuffler::default().seed(), None);
+    }
+
+    #[test]
+    fn test_verify_deal() {
+        use crate::engine::{Action, EngineConfig, GameEngine};
+        use crate::presets;
+
+        let config = EngineConfig {
+            shuffler: Shuffler::committed(),
+            ..EngineConfig::new(3)
+        };
+        let mut engine = GameEngine::with_config(presets::holdem(), config).unwrap();
+        assert!(matches!(engine.events()[0], Event::ShuffleCommitted { .. }));
+        assert_eq!(
+            verify_deal(engine.game(), engine.events()),
+            Err(FairnessError::NotRevealed)
+        );
+        // Everyone calls and checks down.
+        while let Some(seat) = engine.to_act() {
+            if engine.apply_action(seat, Action::Check).is_err() {
+                engine.apply_action(seat, Action::Call).unwrap();
+            }
+        }
+        assert!(matches!(engine.events().last(), Some(Event::HandComplete)));
+        let game = engine.game().clone();
+        let mut events = engine.events().to_vec();
+        assert_eq!(verify_deal(&game, &events), Ok(()));
+
+        // A card swapped for one from elsewhere in the deck.
+        let first = events
+            .iter()
+            .position(|e| matches!(e, Event::HoleCardsDealt { .. }));
+        let Event::HoleCardsDealt { cards, .. } = &mut events[first.unwrap()] else {
+            unreachable!()
+        };
+        cards[0].card = if cards[0].card == Card::new(0, 0) {
+            Card::new(1, 0)
+        } else {
+            Card::new(0, 0)
+        };
+        assert!(matches!(
+            verify_deal(&game, &events),
+            Err(FairnessError::CardOutOfOrder(_))
+        ));
+
+        let mut events = engine.events().to_vec();
+        events[0] = Event::ShuffleCommitted { hash: [0; 32] };
+        assert_eq!(
+            verify_deal(&game, &events),
+            Err(FairnessError::HashMismatch)
+        );
+        assert_eq!(
+            verify_deal(&game, &events[1..]),
+            Err(FairnessError::NotCommitted)
+        );
+    }
+}
@@ -0,0 +1,471 @@
+//! Plays many complete hands of a game between bots and sums up what
+//! happened, to check that a new game definition plays the way it was
+//! meant to before it goes live.
+
+use std::fmt;
+
+use crate::engine::{Chips, EngineConfig, EngineError, Event, GameEngine};
+use crate::parallel;
+use crate::shuffle::Shuffler;
+use crate::strategy::Strategy;
+use crate::view::GameView;
+use crate::{phase, GameStructure};
+
+/// More actions than any sane hand takes: a game that gets this far is
+/// taken to loop forever.
+const MAX_ACTIONS: usize = 10_000;
+
+/// Totals over every simulated hand.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationReport {
+    pub hands: usize,
+    /// Chips awarded per hand, rake included.
+    pub pots: Distribution,
+    /// One entry per strategy, in seat order.
+    pub seats: Vec<SeatReport>,
+    /// One entry per phase as the engine plays them, with repeats written
+    /// out.
+    pub phases: Vec<PhaseReport>,
+    /// Hands that went to a showdown.
+    pub showdowns: usize,
+}
+
+impl SimulationReport {
+    /// Percentage of hands that went to a showdown.
+    pub fn showdown_rate(&self) -> f64 {
+        percent(self.showdowns, self.hands)
+    }
+
+    /// Adds in a report on more hands of the same game and players.
+    fn merge(&mut self, other: SimulationReport) {
+        if other.hands == 0 {
+            return;
+        }
+        if self.hands == 0 {
+            *self = other;
+            return;
+        }
+        self.hands += other.hands;
+        self.pots.total += other.pots.total;
+        self.pots.min = self.pots.min.min(other.pots.min);
+        self.pots.max = self.pots.max.max(other.pots.max);
+        for (seat, theirs) in self.seats.iter_mut().zip(other.seats) {
+            seat.hands_won += theirs.hands_won;
+            seat.net += theirs.net;
+        }
+        if self.phases.is_empty() {
+            self.phases = other.phases;
+        } else {
+            for (phase, theirs) in self.phases.iter_mut().zip(other.phases) {
+                phase.played += theirs.played;
+                phase.actions += theirs.actions;
+            }
+        }
+        self.showdowns += other.showdowns;
+        self.summarize();
+    }
+
+    /// Works out the averages and rates from the totals.
+    fn summarize(&mut self) {
+        if self.hands == 0 {
+            self.pots.min = 0;
+        } else {
+            self.pots.mean = self.pots.total as f64 / self.hands as f64;
+        }
+        for seat in &mut self.seats {
+            seat.win_rate = percent(seat.hands_won, self.hands);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Distribution {
+    pub total: Chips,
+    pub mean: f64,
+    pub min: Chips,
+    pub max: Chips,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SeatReport {
+    /// Hands in which the seat won at least part of a pot.
+    pub hands_won: usize,
+    /// Percentage of hands in which the seat won at least part of a pot.
+    pub win_rate: f64,
+    /// Chips won less chips lost, over every hand.
+    pub net: i64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PhaseReport {
+    /// The betting round's name, or the kind of phase.
+    pub label: String,
+    /// Hands in which the phase was played rather than skipped or cut off
+    /// by the hand ending.
+    pub played: usize,
+    /// Player actions taken during the phase, over every hand.
+    pub actions: usize,
+}
+
+impl PhaseReport {
+    /// Actions per hand the phase was played in: how long it lasts.
+    pub fn mean_actions(&self) -> f64 {
+        if self.played == 0 {
+            0.0
+        } else {
+            self.actions as f64 / self.played as f64
+        }
+    }
+}
+
+/// One hand of a simulation, as [`simulate_games_with`] passes it on.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HandResult {
+    pub hand: usize,
+    pub button: usize,
+    /// Chips awarded, rake included.
+    pub pot: Chips,
+    pub showdown: bool,
+    /// Each seat's chips won less chips lost.
+    pub net: Vec<i64>,
+}
+
+/// Why a simulation stopped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SimulationError {
+    /// The engine refused to deal the hand, or refused a strategy's action.
+    Engine { hand: usize, error: EngineError },
+    /// The hand went on past 10,000 actions.
+    Stuck { hand: usize },
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulationError::Engine { hand, error } => write!(f, "hand {}: {}", hand, error),
+            SimulationError::Stuck { hand } => {
+                write!(
+                    f,
+                    "hand {} didn't finish after {} actions",
+                    hand, MAX_ACTIONS
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+/// Hands played by one set of strategies in [`simulate_games_parallel`].
+const BLOCK: usize = 256;
+
+/// Plays `hands` hands of `game`, one seat per strategy, and reports on
+/// them. Every hand starts from [`EngineConfig::new`]'s stacks with the
+/// button moved on one seat. Hand `i` is shuffled with seed `i`, so the
+/// same strategies deal and play out the same way every time.
+pub fn simulate_games(
+    game: &GameStructure,
+    strategies: &[Box<dyn Strategy>],
+    hands: usize,
+) -> Result<SimulationReport, SimulationError> {
+    play(game, strategies, 0..hands, 0, &mut |_| {})
+}
+
+/// Like [`simulate_games`], with hand `i` shuffled with seed `seed + i`
+/// rather than `i`, and each hand's result passed to `each` as it
+/// finishes.
+pub fn simulate_games_with(
+    game: &GameStructure,
+    strategies: &[Box<dyn Strategy>],
+    hands: usize,
+    seed: u64,
+    mut each: impl FnMut(&HandResult),
+) -> Result<SimulationReport, SimulationError> {
+    play(game, strategies, 0..hands, seed, &mut each)
+}
+
+/// Like [`simulate_games`], sharing the hands out over `threads` threads,
+/// or one per core if 0. The hands are played in blocks of 256, each
+/// by strategies of its own from `strategies(block)`; the block number is
+/// there to seed any randomness from, so that the report is the same
+/// however many threads there are. With strategies that don't learn or
+/// draw random numbers, it's the same as [`simulate_games`]'s too.
+pub fn simulate_games_parallel<F>(
+    game: &GameStructure,
+    strategies: F,
+    hands: usize,
+    threads: usize,
+) -> Result<SimulationReport, SimulationError>
+where
+    F: Fn(usize) -> Vec<Box<dyn Strategy>> + Sync,
+{
+    let threads = match threads {
+        0 => parallel::available_threads(),
+        n => n,
+    };
+    let blocks = parallel::fan_out(hands.div_ceil(BLOCK), threads, |block| {
+        let range = block * BLOCK..hands.min((block + 1) * BLOCK);
+        play(game, &strategies(block), range, 0, &mut |_| {})
+    });
+    let mut report = play(game, &strategies(0), 0..0, 0, &mut |_| {})?;
+    for block in blocks {
+        report.merge(block?);
+    }
+    Ok(report)
+}
+
+/// Plays the hands numbered `hands`, the first of them shuffled with
+/// `seed`.
+fn play(
+    game: &GameStructure,
+    strategies: &[Box<dyn Strategy>],
+    hands: std::ops::Range<usize>,
+    seed: u64,
+    each: &mut dyn FnMut(&HandResult),
+) -> Result<SimulationReport, SimulationError> {
+    let players = strategies.len();
+    let mut report = SimulationReport {
+        hands: hands.len(),
+        pots: Distribution {
+            min: Chips::MAX,
+            ..Default::default()
+        },
+        seats: vec![SeatReport::default(); players],
+        phases: Vec::new(),
+        showdowns: 0,
+    };
+    for hand in hands {
+        let error = |error| SimulationError::Engine { hand, error };
+        let config = EngineConfig {
+            button: hand % players.max(1),
+            shuffler: Shuffler::seeded(seed.wrapping_add(hand as u64)),
+            ..EngineConfig::new(players)
+        };
+        let stacks = config.stacks.clone();
+        let mut engine = GameEngine::with_config(game.clone(), config).map_err(error)?;
+        play_out(&mut engine, hand, |seat| strategies[seat].as_ref())?;
+
+        if report.phases.is_empty() {
+            report.phases = engine
+                .phases()
+                .iter()
+                .map(|p| PhaseReport {
+                    label: label(p),
+                    ..Default::default()
+                })
+                .collect();
+        }
+        let mut pot = 0;
+        let mut won = vec![false; players];
+        let mut phase = None;
+        for event in engine.events() {
+            match *event {
+                Event::PhaseStarted { index } => {
+                    report.phases[index].played += 1;
+                    phase = Some(index);
+                }
+                Event::Acted { .. } => {
+                    if let Some(index) = phase {
+                        report.phases[index].actions += 1;
+                    }
+                }
+                Event::PotAwarded { seat, amount } => {
+                    pot += amount;
+                    won[seat] |= amount > 0;
+                }
+                Event::RakeTaken { amount } => pot += amount,
+                _ => {}
+            }
+        }
+        report.pots.total += pot;
+        report.pots.min = report.pots.min.min(pot);
+        report.pots.max = report.pots.max.max(pot);
+        let result = HandResult {
+            hand,
+            button: engine.button(),
+            pot,
+            showdown: engine.showdown().is_some(),
+            net: (0..players)
+                .map(|seat| engine.seats()[seat].stack as i64 - stacks[seat] as i64)
+                .collect(),
+        };
+        report.showdowns += usize::from(result.showdown);
+        for (seat, stats) in report.seats.iter_mut().enumerate() {
+            stats.hands_won += usize::from(won[seat]);
+            stats.net += result.net[seat];
+        }
+        each(&result);
+    }
+
+    report.summarize();
+    Ok(report)
+}
+
+/// Plays `engine`'s hand, number `hand`, to the end, each seat acting as
+/// `strategy(seat)` says.
+pub(crate) fn play_out<'a>(
+    engine: &mut GameEngine,
+    hand: usize,
+    strategy: impl Fn(usize) -> &'a dyn Strategy,
+) -> Result<(), SimulationError> {
+    let mut actions = 0;
+    while let Some(seat) = engine.to_act() {
+        actions += 1;
+        if actions > MAX_ACTIONS {
+            return Err(SimulationError::Stuck { hand });
+        }
+        let action = strategy(seat).act(&GameView::new(engine, seat));
+        engine
+            .apply_action(seat, action)
+            .map_err(|error| SimulationError::Engine { hand, error })?;
+    }
+    Ok(())
+}
+
+fn percent(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        100.0 * count as f64 / total as f64
+    }
+}
+
+fn label(p: &crate::Phase) -> String {
+    use phase::PhaseType;
+    match &p.phase_type {
+        Some(PhaseType::BettingRound(round)) if !round.name.is_empty() => {
+            return round.name.clone()
+        }
+        Some(PhaseType::BettingRound(_)) => "betting round",
+        Some(PhaseType::PlayerDeal(_)) => "deal",
+        Some(PhaseType::CommunityDeal(_)) => "community deal",
+        Some(PhaseType::Exchange(_)) => "exchange",
+        Some(PhaseType::Ante(_)) => "ante",
+        Some(PhaseType::PassCards(_)) => "pass",
+        Some(PhaseType::Reveal(_)) => "reveal",
+        Some(PhaseType::Declare(_)) => "declare",
+        Some(PhaseType::Repeat(_)) => "repeat",
+        Some(PhaseType::Buy(_)) => "buy",
+        None => "phase",
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::SplitMix64;
+    use crate::presets;
+    use crate::strategy::{Passive, PotOdds, Random};
+
+    #[test]
+    fn test_passive_players_always_show_down() {
+        let strategies: Vec<Box<dyn Strategy>> = vec![Box::new(Passive), Box::new(Passive)];
+        let report = simulate_games(&presets::holdem(), &strategies, 50).unwrap();
+        assert_eq!(report.hands, 50);
+        assert_eq!(report.showdowns, 50);
+        assert_eq!(report.showdown_rate(), 100.0);
+        // Limped and checked down: every pot is the two big blinds.
+        assert_eq!((report.pots.min, report.pots.max), (4, 4));
+        assert_eq!(report.seats.iter().map(|s| s.net).sum::<i64>(), 0);
+        assert!(report.seats.iter().all(|s| s.hands_won > 0));
+
+        let preflop = &report.phases[report
+            .phases
+            .iter()
+            .position(|p| p.played > 0 && p.actions > 0)
+            .unwrap()];
+        // The small blind calls and the big blind checks.
+        assert_eq!(preflop.mean_actions(), 2.0);
+    }
+
+    #[test]
+    fn test_random_players() {
+        let strategies: Vec<Box<dyn Strategy>> = (0..4)
+            .map(|i| Box::new(Random::new(SplitMix64(i))) as Box<dyn Strategy>)
+            .collect();
+        let report = simulate_games(&presets::holdem(), &strategies, 200).unwrap();
+        assert_eq!(report.seats.iter().map(|s| s.net).sum::<i64>(), 0);
+        assert!(report.showdowns < 200);
+        assert!(report.pots.max > report.pots.min);
+        let again = simulate_games(
+            &presets::holdem(),
+            &(0..4)
+                .map(|i| Box::new(Random::new(SplitMix64(i))) as Box<dyn Strategy>)
+                .collect::<Vec<_>>(),
+            200,
+        )
+        .unwrap();
+        assert_eq!(report, again);
+    }
+
+    #[test]
+    fn test_hand_results() {
+        let random = || -> Vec<Box<dyn Strategy>> {
+            (0..3)
+                .map(|i| Box::new(Random::new(SplitMix64(i))) as Box<dyn Strategy>)
+                .collect()
+        };
+        let game = presets::holdem();
+        let mut results = Vec::new();
+        let report =
+            simulate_games_with(&game, &random(), 100, 0, |r| results.push(r.clone())).unwrap();
+        assert_eq!(report, simulate_games(&game, &random(), 100).unwrap());
+        assert_eq!(results.len(), 100);
+        assert!(results.iter().enumerate().all(|(i, r)| r.hand == i));
+        assert_eq!(
+            results.iter().map(|r| r.pot).sum::<Chips>(),
+            report.pots.total
+        );
+        assert_eq!(
+            results.iter().filter(|r| r.showdown).count(),
+            report.showdowns
+        );
+        for seat in 0..3 {
+            let net: i64 = results.iter().map(|r| r.net[seat]).sum();
+            assert_eq!(net, report.seats[seat].net);
+        }
+        let reseeded = simulate_games_with(&game, &random(), 100, 42, |_| {}).unwrap();
+        assert_ne!(reseeded, report);
+    }
+
+    #[test]
+    fn test_pot_odds_beats_random() {
+        let strategies: Vec<Box<dyn Strategy>> = vec![
+            Box::new(PotOdds {
+                trials: 200,
+                seed: 1,
+            }),
+            Box::new(Random::new(SplitMix64(1))),
+        ];
+        let report = simulate_games(&presets::holdem(), &strategies, 100).unwrap();
+        assert!(report.seats[0].net > 0, "{:?}", report.seats);
+    }
+
+    #[test]
+    fn test_parallel() {
+        let random = |block: usize| -> Vec<Box<dyn Strategy>> {
+            (0..3)
+                .map(|i| {
+                    Box::new(Random::new(SplitMix64((block * 3 + i) as u64))) as Box<dyn Strategy>
+                })
+                .collect()
+        };
+        let game = presets::holdem();
+        let report = simulate_games_parallel(&game, random, 600, 1).unwrap();
+        assert_eq!(report.hands, 600);
+        assert_eq!(report.seats.iter().map(|s| s.net).sum::<i64>(), 0);
+        for threads in [2, 0] {
+            assert_eq!(
+                simulate_games_parallel(&game, random, 600, threads).unwrap(),
+                report
+            );
+        }
+
+        let passive = |_| -> Vec<Box<dyn Strategy>> { vec![Box::new(Passive), Box::new(Passive)] };
+        assert_eq!(
+            simulate_games_parallel(&game, passive, 600, 4).unwrap(),
+            simulate_games(&game, &passive(0), 600).unwrap()
+        );
+    }
+}
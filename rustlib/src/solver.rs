@@ -0,0 +1,596 @@
+//! Approximate equilibrium strategies for small heads-up games, by
+//! counterfactual regret minimization (CFR), for research into how a game
+//! definition plays.
+//!
+//! The real game is far too big to solve, so it's abstracted two ways.
+//! Players bet only the sizes in [`Abstraction::bet_sizes`], as fractions
+//! of the pot, and only so many times a round. And cards are replaced by
+//! buckets: hands are ranked by how strong they end up at showdown, under
+//! the game's first scoring rule, and split into equal bands, so that a
+//! player knows which band their hand will finish in but not the
+//! opponent's. Which buckets meet, and how the pot is split when they do,
+//! comes from dealing out sample hands of the game itself.
+//!
+//! The betting follows the game's betting rounds, heads up with blinds:
+//! the small blind acts first in the first round and the big blind first
+//! after that. Antes, bring-ins and draws aren't modelled.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::cards::Shoe;
+use crate::engine::{Action, Chips};
+use crate::equity::cards_dealt;
+use crate::handeval::HandRank;
+use crate::pot::Pot;
+use crate::showdown::{resolve_showdown, Scorer};
+use crate::{phase, repeat, GameStructure};
+
+/// Game trees bigger than this are refused.
+const MAX_NODES: usize = 1 << 20;
+
+/// How far the game is simplified.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Abstraction {
+    /// Hand-strength bands to split the hands into.
+    pub buckets: usize,
+    /// Bets and raises allowed, as fractions of the pot.
+    pub bet_sizes: Vec<f64>,
+    /// Bets and raises allowed per betting round.
+    pub max_raises: usize,
+    /// Each player's stack at the start of the hand, blind included.
+    pub stack: Chips,
+    /// The small and big blinds.
+    pub blinds: [Chips; 2],
+    /// Hands dealt out to find the buckets and their showdowns.
+    pub samples: usize,
+    pub seed: u64,
+}
+
+impl Default for Abstraction {
+    fn default() -> Self {
+        Abstraction {
+            buckets: 8,
+            bet_sizes: vec![0.5, 1.0],
+            max_raises: 2,
+            stack: 200,
+            blinds: [1, 2],
+            samples: 20_000,
+            seed: 0,
+        }
+    }
+}
+
+/// Why a game can't be solved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SolverError {
+    /// The game has no betting rounds.
+    NoBettingRounds,
+    /// The deck can't deal two players a full hand.
+    NotEnoughCards,
+    /// The abstraction has no buckets, or asks for no sample hands.
+    NoBuckets,
+    /// The betting tree would have more than about a million nodes.
+    TooLarge,
+}
+
+impl fmt::Display for SolverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolverError::NoBettingRounds => write!(f, "the game has no betting rounds"),
+            SolverError::NotEnoughCards => {
+                write!(f, "the deck can't deal a hand to two players")
+            }
+            SolverError::NoBuckets => write!(f, "the abstraction needs buckets and samples"),
+            SolverError::TooLarge => write!(
+                f,
+                "the betting tree has more than {} nodes; allow fewer bet sizes or raises",
+                MAX_NODES
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SolverError {}
+
+/// A point in the betting tree.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node {
+    /// `player` (0 for the small blind) chooses among `actions`, each
+    /// leading to the node numbered alongside it.
+    Decision {
+        player: usize,
+        actions: Vec<(Action, usize)>,
+    },
+    /// `folder` folded, having put in `contributed[folder]`.
+    Fold {
+        folder: usize,
+        contributed: [Chips; 2],
+    },
+    /// The hands are shown down.
+    Showdown { contributed: [Chips; 2] },
+}
+
+/// Betting state while the tree is built.
+#[derive(Clone, Copy)]
+struct State {
+    round: usize,
+    /// Chips each player has put in over the hand.
+    contributed: [Chips; 2],
+    /// Chips each player has put in this round.
+    bets: [Chips; 2],
+    acted: [bool; 2],
+    raises: usize,
+    to_act: usize,
+}
+
+/// A CFR solver for one game and abstraction.
+pub struct Solver {
+    abstraction: Abstraction,
+    rounds: usize,
+    nodes: Vec<Node>,
+
+    /// The sampled hand ranks, sorted, bounding the buckets.
+    ranks: Vec<Option<HandRank>>,
+
+    /// Each pairing of the small blind's and big blind's buckets, with how
+    /// often it comes up.
+    deals: Vec<(usize, usize, f64)>,
+
+    /// The small blind's average share of the pot, from 0 to 1, by the two
+    /// buckets.
+    shares: Vec<Vec<f64>>,
+
+    /// Per decision node, the cumulative regrets and strategy weights,
+    /// indexed by `bucket * actions + action`.
+    regrets: Vec<Vec<f64>>,
+    strategy_sums: Vec<Vec<f64>>,
+    iterations: usize,
+}
+
+impl Solver {
+    /// Builds the abstract game: deals out the sample hands for the
+    /// buckets and lays out the betting tree.
+    pub fn new(game: &GameStructure, abstraction: Abstraction) -> Result<Self, SolverError> {
+        let rounds = repeat::expand(&game.phases)
+            .phases
+            .iter()
+            .filter(|p| matches!(p.phase_type, Some(phase::PhaseType::BettingRound(_))))
+            .count();
+        if rounds == 0 {
+            return Err(SolverError::NoBettingRounds);
+        }
+        if abstraction.buckets == 0 || abstraction.samples == 0 {
+            return Err(SolverError::NoBuckets);
+        }
+        let mut solver = Solver {
+            abstraction,
+            rounds,
+            nodes: Vec::new(),
+            ranks: Vec::new(),
+            deals: Vec::new(),
+            shares: Vec::new(),
+            regrets: Vec::new(),
+            strategy_sums: Vec::new(),
+            iterations: 0,
+        };
+        solver.sample(game)?;
+        let [small, big] = solver
+            .abstraction
+            .blinds
+            .map(|b| b.min(solver.abstraction.stack));
+        solver.build(State {
+            round: 0,
+            contributed: [small, big],
+            bets: [small, big],
+            acted: [false; 2],
+            raises: 0,
+            to_act: 0,
+        })?;
+        let buckets = solver.abstraction.buckets;
+        solver.regrets = solver
+            .nodes
+            .iter()
+            .map(|n| match n {
+                Node::Decision { actions, .. } => vec![0.0; actions.len() * buckets],
+                _ => Vec::new(),
+            })
+            .collect();
+        solver.strategy_sums = solver.regrets.clone();
+        Ok(solver)
+    }
+
+    /// Deals the sample hands, and works out the buckets and how each
+    /// pairing of them fares.
+    fn sample(&mut self, game: &GameStructure) -> Result<(), SolverError> {
+        let (hole, board_sizes) = cards_dealt(game);
+        let scorer = &Scorer::for_game(game)[0];
+        let pot = Pot {
+            amount: 2,
+            eligible: vec![0, 1],
+        };
+        let mut hands = Vec::with_capacity(self.abstraction.samples);
+        for i in 0..self.abstraction.samples {
+            let mut shoe = Shoe::for_game(game);
+            shoe.shuffle(self.abstraction.seed.wrapping_add(i as u64));
+            let mut deal = |n: usize| -> Result<Vec<_>, SolverError> {
+                (0..n)
+                    .map(|_| shoe.deal().ok_or(SolverError::NotEnoughCards))
+                    .collect()
+            };
+            let holes = vec![deal(hole)?, deal(hole)?];
+            let boards = board_sizes
+                .iter()
+                .map(|&n| deal(n))
+                .collect::<Result<Vec<_>, _>>()?;
+            let ranks = [0, 1].map(|seat| scorer.best_hand(&holes[seat], &boards));
+            let result = resolve_showdown(game, &holes, &boards, std::slice::from_ref(&pot), 0);
+            hands.push((ranks, result.winnings(0) as f64 / 2.0));
+        }
+        self.ranks = hands.iter().flat_map(|(r, _)| *r).collect();
+        self.ranks.sort();
+
+        let buckets = self.abstraction.buckets;
+        let mut counts: HashMap<(usize, usize), (usize, f64)> = HashMap::new();
+        for ([small, big], share) in hands {
+            let entry = counts
+                .entry((self.bucket(small), self.bucket(big)))
+                .or_default();
+            entry.0 += 1;
+            entry.1 += share;
+        }
+        self.shares = vec![vec![0.5; buckets]; buckets];
+        let mut deals: Vec<_> = counts
+            .into_iter()
+            .map(|((small, big), (count, share))| {
+                self.shares[small][big] = share / count as f64;
+                (small, big, count as f64 / self.abstraction.samples as f64)
+            })
+            .collect();
+        deals.sort_by_key(|&(small, big, _)| (small, big));
+        self.deals = deals;
+        Ok(())
+    }
+
+    /// The bucket a hand finishing with `rank` falls in, from 0 for the
+    /// weakest. `None` is a hand that doesn't qualify at all.
+    pub fn bucket(&self, rank: Option<HandRank>) -> usize {
+        let below = self.ranks.partition_point(|&r| r < rank);
+        (below * self.abstraction.buckets / self.ranks.len().max(1))
+            .min(self.abstraction.buckets - 1)
+    }
+
+    /// Adds the node for `state` and everything below it, returning its
+    /// number.
+    fn build(&mut self, state: State) -> Result<usize, SolverError> {
+        if self.nodes.len() >= MAX_NODES {
+            return Err(SolverError::TooLarge);
+        }
+        let id = self.nodes.len();
+        let p = state.to_act;
+        let stack = self.abstraction.stack;
+        self.nodes.push(Node::Decision {
+            player: p,
+            actions: Vec::new(),
+        });
+
+        let top = state.bets[0].max(state.bets[1]);
+        let owed = top - state.bets[p];
+        let left = stack - state.contributed[p];
+        let mut options: Vec<(Action, State)> = Vec::new();
+        let after = |chips: Chips| {
+            let mut next = state;
+            next.contributed[p] += chips;
+            next.bets[p] += chips;
+            next.acted[p] = true;
+            next.to_act = 1 - p;
+            next
+        };
+        if owed > 0 {
+            options.push((Action::Fold, state));
+            options.push((Action::Call, after(owed.min(left))));
+        } else {
+            options.push((Action::Check, after(0)));
+        }
+        let opponent_left = stack - state.contributed[1 - p];
+        if state.raises < self.abstraction.max_raises && opponent_left > 0 && left > owed {
+            let pot = state.contributed[0] + state.contributed[1] + owed;
+            let mut totals: Vec<Chips> = self
+                .abstraction
+                .bet_sizes
+                .iter()
+                .map(|&f| {
+                    let size = ((pot as f64 * f).round() as Chips).max(self.abstraction.blinds[1]);
+                    (top + size).min(state.bets[p] + left)
+                })
+                .collect();
+            totals.sort_unstable();
+            totals.dedup();
+            for to in totals {
+                let all_in = to == state.bets[p] + left;
+                let action = match (all_in, top) {
+                    (true, _) => Action::AllIn,
+                    (false, 0) => Action::Bet(to),
+                    (false, _) => Action::Raise(to),
+                };
+                let mut next = after(to - state.bets[p]);
+                next.raises += 1;
+                options.push((action, next));
+            }
+        }
+
+        let mut actions = Vec::with_capacity(options.len());
+        for (action, next) in options {
+            let child = if action == Action::Fold {
+                self.leaf(Node::Fold {
+                    folder: p,
+                    contributed: state.contributed,
+                })
+            } else if next.acted == [true; 2] && next.bets[0] == next.bets[1] {
+                // The round is closed.
+                let anyone_all_in = next.contributed.contains(&stack);
+                if anyone_all_in || next.round + 1 == self.rounds {
+                    self.leaf(Node::Showdown {
+                        contributed: next.contributed,
+                    })
+                } else {
+                    self.build(State {
+                        round: next.round + 1,
+                        bets: [0; 2],
+                        acted: [false; 2],
+                        raises: 0,
+                        to_act: 1,
+                        ..next
+                    })?
+                }
+            } else {
+                self.build(next)?
+            };
+            actions.push((action, child));
+        }
+        self.nodes[id] = Node::Decision { player: p, actions };
+        Ok(id)
+    }
+
+    fn leaf(&mut self, node: Node) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+
+    /// Runs `iterations` more passes of CFR over every deal.
+    pub fn train(&mut self, iterations: usize) {
+        for _ in 0..iterations {
+            for i in 0..self.deals.len() {
+                let (small, big, chance) = self.deals[i];
+                self.cfr(0, [small, big], [1.0, 1.0], chance);
+            }
+            self.iterations += 1;
+        }
+    }
+
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// The betting tree, with the first hand's decision at 0.
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// The small blind's winnings in chips at a finished node.
+    fn payoff(&self, node: usize, [small, big]: [usize; 2]) -> f64 {
+        match &self.nodes[node] {
+            Node::Fold {
+                folder,
+                contributed,
+            } => {
+                let lost = contributed[*folder] as f64;
+                if *folder == 0 {
+                    -lost
+                } else {
+                    lost
+                }
+            }
+            Node::Showdown { contributed } => {
+                let pot = (contributed[0] + contributed[1]) as f64;
+                self.shares[small][big] * pot - contributed[0] as f64
+            }
+            Node::Decision { .. } => unreachable!("decisions have no payoff"),
+        }
+    }
+
+    /// One CFR pass below `node` for the players holding `buckets`, reached
+    /// with probability `reach` by each player's choices and `chance` by
+    /// the deal. Returns the small blind's expected winnings.
+    fn cfr(&mut self, node: usize, buckets: [usize; 2], reach: [f64; 2], chance: f64) -> f64 {
+        let Node::Decision { player, actions } = &self.nodes[node] else {
+            return self.payoff(node, buckets);
+        };
+        let (player, children) = (*player, actions.iter().map(|a| a.1).collect::<Vec<_>>());
+        let n = children.len();
+        let at = buckets[player] * n;
+        let strategy = regret_matching(&self.regrets[node][at..at + n]);
+        let mut values = vec![0.0; n];
+        let mut value = 0.0;
+        for (a, &child) in children.iter().enumerate() {
+            let mut reach = reach;
+            reach[player] *= strategy[a];
+            values[a] = self.cfr(child, buckets, reach, chance);
+            value += strategy[a] * values[a];
+        }
+        let sign = if player == 0 { 1.0 } else { -1.0 };
+        let others = reach[1 - player] * chance;
+        for a in 0..n {
+            self.regrets[node][at + a] += others * sign * (values[a] - value);
+            self.strategy_sums[node][at + a] += reach[player] * chance * strategy[a];
+        }
+        value
+    }
+
+    /// The average strategy over every iteration at decision `node` for a
+    /// player holding `bucket`: each action with how often to take it.
+    /// That's the approximate equilibrium, where the latest strategy
+    /// isn't.
+    pub fn average_strategy(&self, node: usize, bucket: usize) -> Vec<(Action, f64)> {
+        let Node::Decision { actions, .. } = &self.nodes[node] else {
+            return Vec::new();
+        };
+        let n = actions.len();
+        let sums = &self.strategy_sums[node][bucket * n..(bucket + 1) * n];
+        let total: f64 = sums.iter().sum();
+        actions
+            .iter()
+            .zip(sums)
+            .map(|((action, _), &s)| {
+                let p = if total > 0.0 {
+                    s / total
+                } else {
+                    1.0 / n as f64
+                };
+                (action.clone(), p)
+            })
+            .collect()
+    }
+
+    /// How much, in chips per hand, a player could win on average by
+    /// switching to the best response to the other's average strategy, if
+    /// each takes each seat half the time. Zero at an equilibrium of the
+    /// abstract game.
+    pub fn exploitability(&self) -> f64 {
+        let buckets = self.abstraction.buckets;
+        let mut total = 0.0;
+        for player in 0..2 {
+            for mine in 0..buckets {
+                // The chance of each opposing bucket alongside this one.
+                let mut weights = vec![0.0; buckets];
+                for &(small, big, chance) in &self.deals {
+                    let (me, them) = if player == 0 {
+                        (small, big)
+                    } else {
+                        (big, small)
+                    };
+                    if me == mine {
+                        weights[them] += chance;
+                    }
+                }
+                total += self.best_response(0, player, mine, &weights);
+            }
+        }
+        total / 2.0
+    }
+
+    /// The most `player` holding `mine` can win below `node`, against
+    /// opposing buckets reached with `weights`.
+    fn best_response(&self, node: usize, player: usize, mine: usize, weights: &[f64]) -> f64 {
+        match &self.nodes[node] {
+            Node::Decision {
+                player: acting,
+                actions,
+            } if *acting == player => actions
+                .iter()
+                .map(|&(_, child)| self.best_response(child, player, mine, weights))
+                .fold(f64::NEG_INFINITY, f64::max),
+            Node::Decision { actions, .. } => {
+                let strategies: Vec<_> = (0..weights.len())
+                    .map(|them| self.average_strategy(node, them))
+                    .collect();
+                (0..actions.len())
+                    .map(|a| {
+                        let reached: Vec<f64> = weights
+                            .iter()
+                            .zip(&strategies)
+                            .map(|(w, s)| w * s[a].1)
+                            .collect();
+                        self.best_response(actions[a].1, player, mine, &reached)
+                    })
+                    .sum()
+            }
+            _ => weights
+                .iter()
+                .enumerate()
+                .filter(|(_, &w)| w > 0.0)
+                .map(|(them, &w)| {
+                    let buckets = if player == 0 {
+                        [mine, them]
+                    } else {
+                        [them, mine]
+                    };
+                    let small = self.payoff(node, buckets);
+                    w * if player == 0 { small } else { -small }
+                })
+                .sum(),
+        }
+    }
+}
+
+/// Plays each action in proportion to its positive regret, or evenly if
+/// none has any.
+fn regret_matching(regrets: &[f64]) -> Vec<f64> {
+    let positive: f64 = regrets.iter().map(|r| r.max(0.0)).sum();
+    if positive > 0.0 {
+        regrets.iter().map(|r| r.max(0.0) / positive).collect()
+    } else {
+        vec![1.0 / regrets.len() as f64; regrets.len()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets;
+
+    fn small_game() -> Abstraction {
+        Abstraction {
+            buckets: 4,
+            bet_sizes: vec![1.0],
+            max_raises: 1,
+            stack: 20,
+            samples: 4000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tree() {
+        let solver = Solver::new(&presets::holdem(), small_game()).unwrap();
+        let Node::Decision { player, actions } = &solver.nodes()[0] else {
+            panic!("the hand starts with a decision");
+        };
+        assert_eq!(*player, 0);
+        let moves: Vec<&Action> = actions.iter().map(|(a, _)| a).collect();
+        assert_eq!(moves, [&Action::Fold, &Action::Call, &Action::Raise(6)]);
+        // Every line ends in a fold or a showdown, with no one over their
+        // stack.
+        for node in solver.nodes() {
+            if let Node::Fold { contributed, .. } | Node::Showdown { contributed } = node {
+                assert!(contributed.iter().all(|&c| c <= 20));
+            }
+        }
+        assert_eq!(solver.deals.iter().map(|d| d.2).sum::<f64>().round(), 1.0);
+        assert!(solver.bucket(None) == 0 && solver.bucket(*solver.ranks.last().unwrap()) == 3);
+        assert_eq!(
+            Solver::new(&crate::GameStructure::default(), small_game()).err(),
+            Some(SolverError::NoBettingRounds)
+        );
+    }
+
+    #[test]
+    fn test_training_converges() {
+        let mut solver = Solver::new(&presets::holdem(), small_game()).unwrap();
+        let untrained = solver.exploitability();
+        solver.train(200);
+        let trained = solver.exploitability();
+        assert!(trained < untrained / 5.0, "{} vs {}", trained, untrained);
+        assert!(trained >= -1e-9);
+        for bucket in 0..4 {
+            let strategy = solver.average_strategy(0, bucket);
+            assert!((strategy.iter().map(|(_, p)| p).sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+        // The best hands never fold to the first raise.
+        let raise = match &solver.nodes()[0] {
+            Node::Decision { actions, .. } => actions[2].1,
+            _ => unreachable!(),
+        };
+        assert!(solver.average_strategy(raise, 3)[0].1 < 0.05);
+    }
+}
@@ -0,0 +1,229 @@
+//! Bots: strategies that choose a seat's action, for simulations and for
+//! filling empty seats.
+
+use std::sync::Mutex;
+
+use crate::cards::Card;
+use crate::engine::{Action, LegalAction};
+use crate::entropy::EntropySource;
+use crate::equity;
+use crate::phase::declare::Side;
+use crate::view::GameView;
+
+/// Chooses actions for a seat.
+pub trait Strategy {
+    /// The action to take when it is the seat's turn. `view.legal` is
+    /// never empty.
+    fn act(&self, view: &GameView) -> Action;
+}
+
+/// Never bets or raises: checks when it can and calls otherwise, stands
+/// pat, turns up and passes its first cards, declares high and doesn't
+/// buy.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Passive;
+
+impl Strategy for Passive {
+    fn act(&self, view: &GameView) -> Action {
+        if view.can(|a| matches!(a, LegalAction::Check)) {
+            return Action::Check;
+        }
+        if view.can(|a| matches!(a, LegalAction::Call(_))) {
+            return Action::Call;
+        }
+        match view.legal[0] {
+            LegalAction::Discard { .. } => Action::Discard(Vec::new()),
+            LegalAction::Pass { count } => {
+                Action::Pass(view.hole.iter().take(count).map(|c| c.card).collect())
+            }
+            LegalAction::Reveal { count } => Action::Reveal(
+                view.hole
+                    .iter()
+                    .filter(|c| !c.face_up)
+                    .take(count)
+                    .map(|c| c.card)
+                    .collect(),
+            ),
+            LegalAction::Declare => Action::Declare(Side::High),
+            LegalAction::AllIn(_) => Action::AllIn,
+            _ => Action::Fold,
+        }
+    }
+}
+
+/// Picks uniformly among the legal actions, with bet sizes and cards
+/// drawn at random too. Good for shaking out game definitions, not for
+/// winning.
+pub struct Random<R> {
+    source: Mutex<R>,
+}
+
+impl<R: EntropySource> Random<R> {
+    pub fn new(source: R) -> Self {
+        Random {
+            source: Mutex::new(source),
+        }
+    }
+}
+
+impl<R: EntropySource> Strategy for Random<R> {
+    fn act(&self, view: &GameView) -> Action {
+        let mut source = self.source.lock().unwrap_or_else(|e| e.into_inner());
+        let source = &mut *source;
+        let hole = || view.hole.iter().map(|c| c.card);
+        let choice = view.legal[source.below(view.legal.len() as u64) as usize].clone();
+        match choice {
+            LegalAction::Fold => Action::Fold,
+            LegalAction::Check => Action::Check,
+            LegalAction::Call(_) => Action::Call,
+            LegalAction::Bet { min, max } => Action::Bet(between(source, min, max)),
+            LegalAction::Raise { min, max } => Action::Raise(between(source, min, max)),
+            LegalAction::AllIn(_) => Action::AllIn,
+            LegalAction::Discard { max } => {
+                let count = between(source, 0, max.min(view.hole.len()) as u64) as usize;
+                Action::Discard(pick(source, hole(), count))
+            }
+            LegalAction::Pass { count } => Action::Pass(pick(source, hole(), count)),
+            LegalAction::Reveal { count } => {
+                let face_down = view.hole.iter().filter(|c| !c.face_up).map(|c| c.card);
+                Action::Reveal(pick(source, face_down, count))
+            }
+            LegalAction::Declare => {
+                let sides = [Side::High, Side::Low, Side::Both];
+                Action::Declare(sides[between(source, 0, 2) as usize])
+            }
+            LegalAction::Buy { replace, .. } => {
+                Action::Buy(pick(source, hole(), usize::from(replace)))
+            }
+        }
+    }
+}
+
+/// A uniform number from `min` to `max` inclusive.
+fn between(source: &mut impl EntropySource, min: u64, max: u64) -> u64 {
+    min + source.below(max - min + 1)
+}
+
+/// `count` of `cards`, chosen at random.
+fn pick(
+    source: &mut impl EntropySource,
+    cards: impl Iterator<Item = Card>,
+    count: usize,
+) -> Vec<Card> {
+    let mut cards: Vec<Card> = cards.collect();
+    crate::shuffle::fisher_yates(&mut cards, source);
+    cards.truncate(count);
+    cards
+}
+
+/// Bets by its chances: samples its equity against the other live hands,
+/// of which it knows only the face-up cards, and calls when that beats the
+/// price the pot offers. With half as much again as its fair share of the
+/// pot (three quarters, heads up) it bets or raises the minimum instead.
+/// Anything
+/// other than betting it leaves to [`Passive`], as it does games it can't
+/// work equities out for.
+#[derive(Clone, Copy, Debug)]
+pub struct PotOdds {
+    /// Random finishes sampled per decision.
+    pub trials: usize,
+    /// Seeds the sampling, so the same spot is judged the same way.
+    pub seed: u64,
+}
+
+impl Default for PotOdds {
+    fn default() -> Self {
+        PotOdds {
+            trials: 500,
+            seed: 0,
+        }
+    }
+}
+
+impl PotOdds {
+    /// The share of the pot the seat can expect, from 0 to 1.
+    fn equity(&self, view: &GameView) -> Option<f64> {
+        let mut hands = vec![view.hole.iter().map(|c| c.card).collect()];
+        hands.extend(
+            view.seats
+                .iter()
+                .enumerate()
+                .filter(|&(seat, s)| seat != view.seat && !s.folded)
+                .map(|(_, s)| s.shown.clone()),
+        );
+        let equities =
+            equity::sample(view.game, &hands, view.boards, self.trials, self.seed).ok()?;
+        Some(equities[0].equity / 100.0)
+    }
+}
+
+impl Strategy for PotOdds {
+    fn act(&self, view: &GameView) -> Action {
+        let betting = view.legal.iter().all(|a| {
+            matches!(
+                a,
+                LegalAction::Fold
+                    | LegalAction::Check
+                    | LegalAction::Call(_)
+                    | LegalAction::Bet { .. }
+                    | LegalAction::Raise { .. }
+                    | LegalAction::AllIn(_)
+            )
+        });
+        let Some(equity) = betting.then(|| self.equity(view)).flatten() else {
+            return Passive.act(view);
+        };
+        if equity >= 1.5 / view.live() as f64 {
+            for option in &view.legal {
+                match *option {
+                    LegalAction::Bet { min, .. } => return Action::Bet(min),
+                    LegalAction::Raise { min, .. } => return Action::Raise(min),
+                    _ => {}
+                }
+            }
+        }
+        match view.to_call() {
+            Some(call) if equity < call as f64 / (view.pot + call) as f64 => Action::Fold,
+            _ => Passive.act(view),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{EngineConfig, GameEngine};
+    use crate::presets;
+    use crate::shuffle::Shuffler;
+
+    #[test]
+    fn test_pot_odds() {
+        let bot = PotOdds::default();
+        for hand in 0..4 {
+            let config = EngineConfig {
+                shuffler: Shuffler::seeded(hand),
+                ..EngineConfig::new(2)
+            };
+            let mut engine = GameEngine::with_config(presets::holdem(), config).unwrap();
+            let seat = engine.to_act().unwrap();
+            let view = GameView::new(&engine, seat);
+            // Heads up, any hand is worth the small blind's price.
+            assert!(bot.act(&view) != Action::Fold);
+
+            // Facing a shove, it calls exactly when its equity is worth
+            // the price.
+            engine.apply_action(seat, Action::AllIn).unwrap();
+            let seat = engine.to_act().unwrap();
+            let view = GameView::new(&engine, seat);
+            let call = view.to_call().unwrap();
+            let price = call as f64 / (view.pot + call) as f64;
+            let equity = bot.equity(&view).unwrap();
+            let expected = if equity < price {
+                Action::Fold
+            } else {
+                Action::Call
+            };
+            assert_eq!(bot.act(&view), expected);
+        }
+    }
+}
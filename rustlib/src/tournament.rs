@@ -0,0 +1,805 @@
+//! Multi-table tournaments: blinds that go up level by level, players
+//! knocked out as they lose their chips, tables broken and balanced as the
+//! field shrinks, and the prize pool paid out by finishing place.
+//!
+//! A [`Coordinator`] runs one event over many tables playing at once. It
+//! says when each table may deal and at what level, and is told how each
+//! hand left the stacks; in return it moves players between tables as they
+//! bust, plays hand-for-hand on the bubble, and draws seats afresh for the
+//! final table.
+//!
+//! [`run_tournament`] plays a whole tournament between bots with one,
+//! in rounds of a hand at every table that can deal. Levels last a given
+//! number of rounds, so every table sees the same blinds at once, as it
+//! would with a tournament clock.
+
+use std::cmp::Reverse;
+use std::fmt;
+
+use crate::cards::SplitMix64;
+use crate::engine::{Chips, EngineConfig, GameEngine};
+use crate::shuffle::{fisher_yates, Shuffler};
+use crate::simulate::{play_out, SimulationError};
+use crate::strategy::Strategy;
+use crate::GameStructure;
+
+/// More rounds than a tournament whose blinds go up takes: one that gets
+/// this far is taken not to finish.
+const MAX_ROUNDS: usize = 100_000;
+
+/// The forced bets for a level.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Level {
+    /// Blinds, smallest first, as in [`EngineConfig::blinds`].
+    pub blinds: Vec<Chips>,
+    pub ante: Chips,
+}
+
+/// How a tournament is run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Structure {
+    /// Chips each player starts with.
+    pub stack: Chips,
+    /// The levels in order. Play stays at the last one once it's reached.
+    pub levels: Vec<Level>,
+    /// How many rounds each level lasts.
+    pub rounds_per_level: usize,
+    /// Seats at each table.
+    pub table_size: usize,
+    /// Each paid place's share of the prize pool, first place first. The
+    /// shares are scaled to add up to 1.
+    pub payouts: Vec<f64>,
+}
+
+impl Structure {
+    /// The level in force in round `round`, counting from 0.
+    pub fn level(&self, round: usize) -> usize {
+        (round / self.rounds_per_level.max(1)).min(self.levels.len().saturating_sub(1))
+    }
+}
+
+/// A common payout table for `players` entrants: the top tenth of the
+/// field (at least one place) is paid, each place's share in proportion to
+/// one over the place.
+pub fn default_payouts(players: usize) -> Vec<f64> {
+    let paid = (players / 10).max(1);
+    (1..=paid).map(|place| 1.0 / place as f64).collect()
+}
+
+/// How one player finished.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Finish {
+    pub player: usize,
+    /// 1 for the winner.
+    pub place: usize,
+    /// The round the player was knocked out in, or `None` for the winner.
+    pub round: Option<usize>,
+    /// The player's share of the prize pool.
+    pub prize: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TournamentReport {
+    /// Rounds played.
+    pub rounds: usize,
+    /// Hands played, over every table.
+    pub hands: usize,
+    /// The level play ended at, an index into [`Structure::levels`].
+    pub level: usize,
+    /// Every player's finish, first place first.
+    pub finishes: Vec<Finish>,
+}
+
+/// Why a tournament couldn't be played.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TournamentError {
+    /// Fewer than two players, fewer than two seats a table, or no levels.
+    Structure(String),
+    /// A hand couldn't be played.
+    Hand(SimulationError),
+    /// More than one player was left after 100,000 rounds, as when the
+    /// blinds never go up and no bot ever bets.
+    Unfinished { rounds: usize, players: usize },
+}
+
+impl fmt::Display for TournamentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TournamentError::Structure(message) => f.write_str(message),
+            TournamentError::Hand(e) => e.fmt(f),
+            TournamentError::Unfinished { rounds, players } => write!(
+                f,
+                "{} players were still in after {} rounds",
+                players, rounds
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TournamentError {}
+
+/// A table's players, and where its hands have got to.
+struct Table {
+    /// The player in each of the table's seats, if it is taken.
+    seats: Vec<Option<usize>>,
+    /// The seat that had the button last hand, if the table has dealt.
+    button: Option<usize>,
+    /// Who was dealt into the hand being played, by seat, and the chips
+    /// they started it with.
+    playing: Option<Vec<Option<(usize, Chips)>>>,
+    /// Whether the table has dealt in this round of hand-for-hand play.
+    dealt: bool,
+    broken: bool,
+}
+
+impl Table {
+    fn count(&self) -> usize {
+        self.seats.iter().flatten().count()
+    }
+
+    /// The taken seats, starting with the one after `seat`.
+    fn after(&self, seat: usize) -> Vec<usize> {
+        let size = self.seats.len();
+        (1..=size)
+            .map(|i| (seat + i) % size)
+            .filter(|&s| self.seats[s].is_some())
+            .collect()
+    }
+
+    /// The seat the button goes to next hand.
+    fn next_button(&self) -> Option<usize> {
+        let last = self.button.unwrap_or(self.seats.len() - 1);
+        self.after(last).first().copied()
+    }
+}
+
+/// What a table is to deal: who sits where, with what, and at which level.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Deal {
+    /// The player in each seat, if it is taken.
+    pub seats: Vec<Option<usize>>,
+    /// Each seat's chips, 0 for an empty seat, as for
+    /// [`EngineConfig::stacks`].
+    pub stacks: Vec<Chips>,
+    pub button: usize,
+    /// An index into [`Structure::levels`].
+    pub level: usize,
+}
+
+/// A player sent from one seat to another, each a table and a seat there.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Move {
+    pub player: usize,
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+}
+
+/// Runs one tournament over tables that each play at their own pace.
+///
+/// A table asks to [`deal`](Coordinator::deal) when it is ready for a hand,
+/// and reports how it went with [`finish`](Coordinator::finish). Players
+/// only move between hands at the table they leave, though they may join
+/// a table mid-hand, to sit in from its next one. Tables are broken,
+/// those with the fewest players first, as soon as the rest can seat the
+/// field, and players moved from the longest tables to the shortest until
+/// no two differ by more than one. The player moved is the one due the
+/// big blind next, so no one misses their blinds or posts them twice.
+///
+/// When one more player is left than will be paid, play goes
+/// hand-for-hand: each table deals once and waits for every other to
+/// finish before any deals again, and players knocked out in the same
+/// round of it finish in order of the chips they started their hand with.
+/// New levels from [`set_level`](Coordinator::set_level) come in at the
+/// start of each table's next hand or, hand-for-hand, of the next round.
+pub struct Coordinator {
+    structure: Structure,
+    tables: Vec<Table>,
+    stacks: Vec<Chips>,
+    /// Players knocked out, first out first.
+    out: Vec<usize>,
+    /// Players knocked out but not yet placed, with the chips they started
+    /// their last hand with.
+    busted: Vec<(usize, Chips)>,
+    level: usize,
+    /// The level to go to as soon as the tables can.
+    clock: usize,
+    hand_for_hand: bool,
+    /// Set once the field is down to one table, until its seats are drawn.
+    final_draw: bool,
+    rng: SplitMix64,
+}
+
+impl Coordinator {
+    /// Seats `players` players, numbered from 0, at random from `seed`,
+    /// spread evenly over as few tables as `structure` lets them fit at.
+    pub fn new(structure: Structure, players: usize, seed: u64) -> Result<Self, TournamentError> {
+        if players < 2 {
+            return Err(TournamentError::Structure(
+                "a tournament needs at least two players".to_string(),
+            ));
+        }
+        if structure.table_size < 2 {
+            return Err(TournamentError::Structure(
+                "tables need at least two seats".to_string(),
+            ));
+        }
+        if structure.levels.is_empty() {
+            return Err(TournamentError::Structure(
+                "the structure has no levels".to_string(),
+            ));
+        }
+        let mut rng = SplitMix64::new(seed);
+        let mut order: Vec<usize> = (0..players).collect();
+        fisher_yates(&mut order, &mut rng);
+        let count = players.div_ceil(structure.table_size);
+        let tables = (0..count)
+            .map(|t| {
+                let mut seats = vec![None; structure.table_size];
+                for (seat, &player) in order.iter().skip(t).step_by(count).enumerate() {
+                    seats[seat] = Some(player);
+                }
+                Table {
+                    seats,
+                    button: None,
+                    playing: None,
+                    dealt: false,
+                    broken: false,
+                }
+            })
+            .collect();
+        let mut coordinator = Coordinator {
+            stacks: vec![structure.stack; players],
+            structure,
+            tables,
+            out: Vec::new(),
+            busted: Vec::new(),
+            level: 0,
+            clock: 0,
+            hand_for_hand: false,
+            final_draw: false,
+            rng,
+        };
+        coordinator.hand_for_hand = coordinator.on_bubble();
+        Ok(coordinator)
+    }
+
+    /// The tables still in play.
+    pub fn tables(&self) -> Vec<usize> {
+        (0..self.tables.len())
+            .filter(|&t| !self.tables[t].broken)
+            .collect()
+    }
+
+    /// The player in each of `table`'s seats, if it is taken.
+    pub fn seats(&self, table: usize) -> &[Option<usize>] {
+        &self.tables[table].seats
+    }
+
+    pub fn stack(&self, player: usize) -> Chips {
+        self.stacks[player]
+    }
+
+    /// Players with chips left.
+    pub fn left(&self) -> usize {
+        self.stacks.iter().filter(|&&s| s > 0).count()
+    }
+
+    /// Players knocked out and placed, first out first.
+    pub fn out(&self) -> &[usize] {
+        &self.out
+    }
+
+    /// The level in force, an index into [`Structure::levels`].
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    pub fn hand_for_hand(&self) -> bool {
+        self.hand_for_hand
+    }
+
+    /// Whether one player has every chip.
+    pub fn is_over(&self) -> bool {
+        self.left() <= 1
+    }
+
+    /// Goes to `level`, or the last there is, as soon as the tables can.
+    pub fn set_level(&mut self, level: usize) {
+        self.clock = level.min(self.structure.levels.len() - 1);
+        if !self.hand_for_hand {
+            self.level = self.clock;
+        }
+    }
+
+    /// The hand for `table` to deal, moving its button on; or `None` if it
+    /// is broken, is playing a hand or has too few players for one, is
+    /// waiting for the other tables hand-for-hand, or the tournament is
+    /// over.
+    pub fn deal(&mut self, table: usize) -> Option<Deal> {
+        let over = self.is_over();
+        let t = &mut self.tables[table];
+        if over || t.broken || t.playing.is_some() || t.count() < 2 {
+            return None;
+        }
+        if self.hand_for_hand && t.dealt {
+            return None;
+        }
+        let button = t.next_button()?;
+        t.button = Some(button);
+        t.dealt = true;
+        let stacks: Vec<Chips> = t
+            .seats
+            .iter()
+            .map(|p| p.map_or(0, |p| self.stacks[p]))
+            .collect();
+        t.playing = Some(
+            t.seats
+                .iter()
+                .zip(&stacks)
+                .map(|(p, &stack)| p.map(|p| (p, stack)))
+                .collect(),
+        );
+        Some(Deal {
+            seats: t.seats.clone(),
+            stacks,
+            button,
+            level: self.level,
+        })
+    }
+
+    /// Takes the stacks, by seat, that the hand `table` was dealt left its
+    /// players with, and returns the players moved to other seats as a
+    /// result.
+    ///
+    /// Panics if the table isn't playing a hand, or `stacks` has too few
+    /// seats.
+    pub fn finish(&mut self, table: usize, stacks: &[Chips]) -> Vec<Move> {
+        let dealt = self.tables[table]
+            .playing
+            .take()
+            .expect("a hand being played");
+        let mut busted: Vec<(usize, Chips)> = Vec::new();
+        for (seat, dealt) in dealt.into_iter().enumerate() {
+            let Some((player, start)) = dealt else {
+                continue;
+            };
+            self.stacks[player] = stacks[seat];
+            if stacks[seat] == 0 {
+                self.tables[table].seats[seat] = None;
+                busted.push((player, start));
+            }
+        }
+        self.busted.extend(busted);
+        if !self.hand_for_hand {
+            self.place();
+            if self.on_bubble() {
+                // The hands being played finish before the first round.
+                self.hand_for_hand = true;
+                self.tables.iter_mut().for_each(|t| t.dealt = true);
+            }
+        }
+        let mut moves = Vec::new();
+        self.rebalance(&mut moves);
+        let waiting = self
+            .tables
+            .iter()
+            .any(|t| !t.broken && (t.playing.is_some() || (!t.dealt && t.count() >= 2)));
+        if self.hand_for_hand && (!waiting || self.is_over()) {
+            // The round is over.
+            self.place();
+            self.tables.iter_mut().for_each(|t| t.dealt = false);
+            self.level = self.clock;
+            self.hand_for_hand = self.on_bubble();
+        }
+        moves
+    }
+
+    /// Places the players knocked out since last time: of those, the
+    /// bigger stack finishes higher.
+    fn place(&mut self) {
+        self.busted.sort_by_key(|&(_, start)| start);
+        self.out
+            .extend(self.busted.drain(..).map(|(player, _)| player));
+    }
+
+    /// Whether the next player out is the last who won't be paid.
+    fn on_bubble(&self) -> bool {
+        let paid = self.structure.payouts.iter().filter(|&&s| s > 0.0).count();
+        self.tables().len() > 1 && self.left() == paid + 1
+    }
+
+    /// Breaks tables and balances the rest, as far as the tables between
+    /// hands allow, then draws the final table's seats once it is free.
+    fn rebalance(&mut self, moves: &mut Vec<Move>) {
+        let needed = self.left().div_ceil(self.structure.table_size).max(1);
+        while self.tables().len() > needed {
+            let Some(broken) = self
+                .tables()
+                .into_iter()
+                .filter(|&t| self.tables[t].playing.is_none())
+                .min_by_key(|&t| (self.tables[t].count(), Reverse(t)))
+            else {
+                break;
+            };
+            self.tables[broken].broken = true;
+            for seat in 0..self.structure.table_size {
+                if self.tables[broken].seats[seat].is_some() {
+                    self.send((broken, seat), moves);
+                }
+            }
+            self.final_draw = self.tables().len() == 1;
+        }
+        loop {
+            let tables = self.tables();
+            let short = tables.iter().map(|&t| self.tables[t].count()).min();
+            let long = tables
+                .iter()
+                .copied()
+                .filter(|&t| self.tables[t].playing.is_none())
+                .max_by_key(|&t| self.tables[t].count());
+            let (Some(short), Some(long)) = (short, long) else {
+                break;
+            };
+            let t = &self.tables[long];
+            if t.count() <= short + 1 {
+                break;
+            }
+            let order = t.after(t.next_button().expect("players at the table"));
+            // After the button, the small blind and the big; heads-up, the
+            // button is the small blind.
+            let big_blind = if order.len() == 2 { order[0] } else { order[1] };
+            self.send((long, big_blind), moves);
+        }
+        let tables = self.tables();
+        if self.final_draw && tables.len() == 1 && self.tables[tables[0]].playing.is_none() {
+            self.final_draw = false;
+            self.draw(tables[0], moves);
+        }
+    }
+
+    /// Moves the player from `from` to the first open seat at the table
+    /// with the fewest players.
+    fn send(&mut self, from: (usize, usize), moves: &mut Vec<Move>) {
+        let player = self.tables[from.0].seats[from.1]
+            .take()
+            .expect("a player to move");
+        let table = self
+            .tables()
+            .into_iter()
+            .min_by_key(|&t| self.tables[t].count())
+            .expect("a table left");
+        let seat = self.tables[table]
+            .seats
+            .iter()
+            .position(Option::is_none)
+            .expect("enough seats for the field");
+        self.tables[table].seats[seat] = Some(player);
+        moves.push(Move {
+            player,
+            from,
+            to: (table, seat),
+        });
+    }
+
+    /// Seats `table`'s players afresh, at random, from the first seat on,
+    /// the first of them to have the button.
+    fn draw(&mut self, table: usize, moves: &mut Vec<Move>) {
+        let t = &mut self.tables[table];
+        let mut players: Vec<(usize, usize)> = t
+            .seats
+            .iter()
+            .enumerate()
+            .filter_map(|(seat, p)| Some(((*p)?, seat)))
+            .collect();
+        fisher_yates(&mut players, &mut self.rng);
+        t.seats = vec![None; t.seats.len()];
+        t.button = None;
+        for (seat, (player, from)) in players.into_iter().enumerate() {
+            t.seats[seat] = Some(player);
+            if from != seat {
+                moves.push(Move {
+                    player,
+                    from: (table, from),
+                    to: (table, seat),
+                });
+            }
+        }
+    }
+}
+
+/// Plays a tournament of `game`, one player per strategy, under
+/// `structure`, with a [`Coordinator`]. Players are seated at random from
+/// `seed`, and hand `i` (counting over every table) is shuffled with seed
+/// `seed + i`, so the same strategies play out the same tournament every
+/// time.
+pub fn run_tournament(
+    game: &GameStructure,
+    structure: &Structure,
+    strategies: &[Box<dyn Strategy>],
+    seed: u64,
+) -> Result<TournamentReport, TournamentError> {
+    let players = strategies.len();
+    let mut coordinator = Coordinator::new(structure.clone(), players, seed)?;
+    // The round each player in `coordinator.out()` was knocked out in.
+    let mut rounds_out: Vec<usize> = Vec::new();
+    let mut hands = 0;
+    let mut round = 0;
+    while !coordinator.is_over() {
+        if round == MAX_ROUNDS {
+            return Err(TournamentError::Unfinished {
+                rounds: round,
+                players: coordinator.left(),
+            });
+        }
+        coordinator.set_level(structure.level(round));
+        let deals: Vec<(usize, Deal)> = coordinator
+            .tables()
+            .into_iter()
+            .filter_map(|t| Some((t, coordinator.deal(t)?)))
+            .collect();
+        for (table, deal) in deals {
+            let level = &structure.levels[deal.level];
+            let config = EngineConfig {
+                stacks: deal.stacks.clone(),
+                blinds: level.blinds.clone(),
+                ante: level.ante,
+                button: deal.button,
+                shuffler: Shuffler::seeded(seed.wrapping_add(hands as u64)),
+                ..EngineConfig::new(deal.seats.len())
+            };
+            let mut engine = GameEngine::with_config(game.clone(), config).map_err(|error| {
+                TournamentError::Hand(SimulationError::Engine { hand: hands, error })
+            })?;
+            play_out(&mut engine, hands, |seat| {
+                strategies[deal.seats[seat].expect("dealt in")].as_ref()
+            })
+            .map_err(TournamentError::Hand)?;
+            hands += 1;
+            let stacks: Vec<Chips> = engine.seats().iter().map(|s| s.stack).collect();
+            coordinator.finish(table, &stacks);
+        }
+        rounds_out.resize(coordinator.out().len(), round);
+        round += 1;
+    }
+
+    let total: f64 = structure.payouts.iter().sum();
+    let prize = |place: usize| match structure.payouts.get(place - 1) {
+        Some(share) if total > 0.0 => share / total,
+        _ => 0.0,
+    };
+    let winner = (0..players)
+        .find(|&p| coordinator.stack(p) > 0)
+        .unwrap_or(0);
+    let mut finishes = vec![Finish {
+        player: winner,
+        place: 1,
+        round: None,
+        prize: prize(1),
+    }];
+    let out = coordinator.out().iter().zip(&rounds_out);
+    for (i, (&player, &round)) in out.rev().enumerate() {
+        finishes.push(Finish {
+            player,
+            place: i + 2,
+            round: Some(round),
+            prize: prize(i + 2),
+        });
+    }
+    Ok(TournamentReport {
+        rounds: round,
+        hands,
+        level: coordinator.level(),
+        finishes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets;
+    use crate::strategy::Random;
+
+    fn structure() -> Structure {
+        Structure {
+            stack: 100,
+            levels: vec![
+                Level {
+                    blinds: vec![1, 2],
+                    ante: 0,
+                },
+                Level {
+                    blinds: vec![5, 10],
+                    ante: 1,
+                },
+                Level {
+                    blinds: vec![25, 50],
+                    ante: 5,
+                },
+            ],
+            rounds_per_level: 5,
+            table_size: 6,
+            payouts: vec![50.0, 30.0, 20.0],
+        }
+    }
+
+    fn bots(players: usize) -> Vec<Box<dyn Strategy>> {
+        (0..players)
+            .map(|p| Box::new(Random::new(SplitMix64::new(p as u64))) as Box<dyn Strategy>)
+            .collect()
+    }
+
+    #[test]
+    fn test_run_tournament() {
+        let report = run_tournament(&presets::holdem(), &structure(), &bots(20), 3).unwrap();
+        assert_eq!(report.finishes.len(), 20);
+        let mut seen: Vec<usize> = report.finishes.iter().map(|f| f.player).collect();
+        seen.sort();
+        assert_eq!(seen, (0..20).collect::<Vec<_>>());
+        for (i, finish) in report.finishes.iter().enumerate() {
+            assert_eq!(finish.place, i + 1);
+        }
+        assert_eq!(report.finishes[0].round, None);
+        // Players out earlier finish lower.
+        let rounds: Vec<usize> = report.finishes[1..]
+            .iter()
+            .map(|f| f.round.unwrap())
+            .collect();
+        assert!(rounds.windows(2).all(|w| w[0] >= w[1]), "{:?}", rounds);
+        assert_eq!(report.finishes[0].prize, 0.5);
+        assert_eq!(report.finishes[2].prize, 0.2);
+        assert_eq!(report.finishes[3].prize, 0.0);
+        assert!(report.hands >= report.rounds);
+
+        let again = run_tournament(&presets::holdem(), &structure(), &bots(20), 3).unwrap();
+        assert_eq!(again, report);
+    }
+
+    /// Finishes `table`'s hand with each player dealt in left with what
+    /// `stack` says, given their stack before.
+    fn finish(
+        coordinator: &mut Coordinator,
+        table: usize,
+        deal: &Deal,
+        stack: impl Fn(usize, Chips) -> Chips,
+    ) -> Vec<Move> {
+        let stacks: Vec<Chips> = deal
+            .seats
+            .iter()
+            .zip(&deal.stacks)
+            .map(|(p, &s)| p.map_or(0, |p| stack(p, s)))
+            .collect();
+        coordinator.finish(table, &stacks)
+    }
+
+    fn counts(coordinator: &Coordinator) -> Vec<usize> {
+        coordinator
+            .tables()
+            .iter()
+            .map(|&t| coordinator.seats(t).iter().flatten().count())
+            .collect()
+    }
+
+    #[test]
+    fn test_balance() {
+        let mut c = Coordinator::new(structure(), 11, 7).unwrap();
+        assert_eq!(counts(&c), [6, 5]);
+        let mut seated: Vec<usize> = (0..2).flat_map(|t| c.seats(t).to_vec()).flatten().collect();
+        seated.sort();
+        assert_eq!(seated, (0..11).collect::<Vec<_>>());
+
+        // Two players bust at the first table, which leaves the tables
+        // one apart.
+        let deal = c.deal(0).unwrap();
+        assert_eq!(deal.button, 0);
+        assert_eq!(c.deal(0), None);
+        let gone = [deal.seats[1].unwrap(), deal.seats[2].unwrap()];
+        let moves = finish(
+            &mut c,
+            0,
+            &deal,
+            |p, s| if gone.contains(&p) { 0 } else { s },
+        );
+        assert_eq!((moves, counts(&c)), (vec![], vec![4, 5]));
+        assert_eq!(c.out().len(), 2);
+
+        // Two more bust there while the second table plays, so it can only
+        // send a player across once its hand is over: the one due the big
+        // blind, two seats after the next button.
+        let next = c.deal(1).unwrap();
+        let deal = c.deal(0).unwrap();
+        let gone = [deal.seats[3].unwrap(), deal.seats[4].unwrap()];
+        let moves = finish(
+            &mut c,
+            0,
+            &deal,
+            |p, s| if gone.contains(&p) { 0 } else { s },
+        );
+        assert_eq!((moves, counts(&c)), (vec![], vec![2, 5]));
+        let moves = finish(&mut c, 1, &next, |_, s| s);
+        assert_eq!(counts(&c), [3, 4]);
+        assert_eq!(
+            moves,
+            [Move {
+                player: next.seats[3].unwrap(),
+                from: (1, 3),
+                to: (0, 1),
+            }]
+        );
+
+        // With six left, the shorter table is broken into the other, whose
+        // seats are drawn afresh for the final table.
+        let deal = c.deal(0).unwrap();
+        let busted = deal.seats.iter().flatten().copied().next().unwrap();
+        let moves = finish(&mut c, 0, &deal, |p, s| if p == busted { 0 } else { s });
+        assert_eq!(c.tables(), [1]);
+        assert_eq!(counts(&c), [6]);
+        assert!(moves.iter().filter(|m| m.from.0 == 0).count() == 2);
+        assert!(moves.iter().any(|m| m.from.0 == 1 && m.to.0 == 1));
+        assert_eq!(c.deal(0), None);
+        assert_eq!(c.deal(1).unwrap().button, 0);
+    }
+
+    #[test]
+    fn test_hand_for_hand() {
+        let s = Structure {
+            table_size: 3,
+            ..structure()
+        };
+        let mut c = Coordinator::new(s, 6, 1).unwrap();
+        assert!(!c.hand_for_hand());
+        // A player busts at each table, which leaves four for three paid
+        // places.
+        for table in [0, 1] {
+            let deal = c.deal(table).unwrap();
+            let gone = deal.seats[0].unwrap();
+            finish(&mut c, table, &deal, |p, s| if p == gone { 0 } else { s });
+        }
+        assert!(c.hand_for_hand());
+        assert_eq!(counts(&c), [2, 2]);
+
+        // Each table deals once a round, and a new level waits for the
+        // next round.
+        let first = c.deal(0).unwrap();
+        finish(&mut c, 0, &first, |_, s| s * 2);
+        assert_eq!(c.deal(0), None);
+        c.set_level(1);
+        let second = c.deal(1).unwrap();
+        assert_eq!(second.level, 0);
+        finish(&mut c, 1, &second, |_, s| s);
+        assert_eq!(c.level(), 1);
+
+        // Players out in the same round finish by the chips they started
+        // the hand with: the first table's player, who had doubled up,
+        // finishes higher though their hand ended first.
+        let first = c.deal(0).unwrap();
+        let second = c.deal(1).unwrap();
+        assert_eq!(first.level, 1);
+        let big = first.seats.iter().flatten().copied().next().unwrap();
+        let small = second.seats.iter().flatten().copied().next().unwrap();
+        let out = c.out().to_vec();
+        finish(&mut c, 0, &first, |p, s| if p == big { 0 } else { s });
+        assert_eq!(c.out(), out);
+        assert_eq!(c.tables(), [1], "broken into the table still playing");
+        finish(&mut c, 1, &second, |p, s| if p == small { 0 } else { s });
+        assert_eq!(c.out()[2..], [small, big]);
+        assert!(!c.hand_for_hand());
+        assert_eq!(c.left(), 2);
+        assert_eq!(counts(&c), [2]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut s = structure();
+        assert!(matches!(
+            run_tournament(&presets::holdem(), &s, &bots(1), 0),
+            Err(TournamentError::Structure(_))
+        ));
+        s.levels.clear();
+        assert!(matches!(
+            run_tournament(&presets::holdem(), &s, &bots(4), 0),
+            Err(TournamentError::Structure(_))
+        ));
+        assert_eq!(default_payouts(35).len(), 3);
+        assert_eq!(default_payouts(5), [1.0]);
+    }
+}
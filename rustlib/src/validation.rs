@@ -0,0 +1,648 @@
+//! Semantic checks on a [`GameStructure`] that the proto schema can't
+//! express, such as phases arriving in an order the engine can't play.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::cards::{deck_labels, Shoe};
+use crate::engine::{apply_burns, dealt_face_up};
+use crate::phase::betting_round::BettingOrder;
+use crate::phase::condition::Measure;
+use crate::points::{ExprError, Expression};
+use crate::repeat;
+use crate::{game_structure, phase, scoring, GameStructure, Phase};
+
+/// A problem with a game definition. Phase-specific errors carry the index
+/// of the phase in `GameStructure.phases`, or for a phase inside a repeat,
+/// the index of the repeat.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    NoPhases,
+    NoScorings,
+    /// A betting round comes before any cards are dealt to players.
+    BettingBeforeDeal {
+        phase: usize,
+    },
+    /// A deal gives out no cards.
+    EmptyDeal {
+        phase: usize,
+    },
+    /// A community deal targets a board the game doesn't have.
+    NoSuchBoard {
+        phase: usize,
+        board: i32,
+    },
+    NegativeMinBet {
+        phase: usize,
+        min_bet: i32,
+    },
+    NegativeExchange {
+        phase: usize,
+    },
+    /// A passing phase passes no cards.
+    EmptyPass {
+        phase: usize,
+    },
+    /// A reveal phase turns up no cards.
+    EmptyReveal {
+        phase: usize,
+    },
+    /// A declare phase in a game without both high and low scorings.
+    DeclareWithoutHiLo {
+        phase: usize,
+    },
+    /// A buy phase with a negative price.
+    NegativePrice {
+        phase: usize,
+    },
+    /// A buy phase replaces cards before any are dealt.
+    ReplaceBeforeDeal {
+        phase: usize,
+    },
+    /// A repeat plays no phases.
+    EmptyRepeat {
+        phase: usize,
+    },
+    /// A phase's condition doesn't say what it measures.
+    UnknownMeasure {
+        phase: usize,
+    },
+    /// Betting starts with the best face-up hand before any card was dealt
+    /// face up.
+    NoFaceUpCards {
+        phase: usize,
+    },
+    /// Two phases of the same kind share a name.
+    DuplicatePhaseName {
+        phase: usize,
+        name: String,
+    },
+    /// Every scoring has a low qualifier, so a pot could go unawarded.
+    AllScoringsQualified,
+    /// A scoring lists card limits for more boards than the game has.
+    TooManyBoardRanges {
+        scoring: usize,
+    },
+    /// A points scoring's expression doesn't parse.
+    InvalidPointsExpression {
+        scoring: usize,
+        error: ExprError,
+    },
+    /// A shoe with a negative number of decks, or a penetration that isn't
+    /// a percentage.
+    InvalidShoe {
+        decks: i32,
+        penetration: i32,
+    },
+    /// A burn policy that burns a negative number of cards.
+    NegativeBurns {
+        cards: i32,
+    },
+    /// A card listed in a custom deck has a rank or suit the deck doesn't.
+    UnknownDeckCard {
+        card: usize,
+        rank: String,
+        suit: String,
+    },
+    /// A hand for two players deals more cards than the deck holds.
+    DeckTooSmall {
+        deck: usize,
+        needed: usize,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::NoPhases => write!(f, "the game has no phases"),
+            ValidationError::NoScorings => write!(f, "the game has no scorings"),
+            ValidationError::BettingBeforeDeal { phase } => {
+                write!(f, "phase {}: betting before any cards are dealt", phase)
+            }
+            ValidationError::EmptyDeal { phase } => write!(f, "phase {}: deals no cards", phase),
+            ValidationError::NoSuchBoard { phase, board } => {
+                write!(f, "phase {}: deals to missing board {}", phase, board)
+            }
+            ValidationError::NegativeMinBet { phase, min_bet } => {
+                write!(f, "phase {}: negative min_bet {}", phase, min_bet)
+            }
+            ValidationError::NegativeExchange { phase } => {
+                write!(f, "phase {}: negative max_exchange", phase)
+            }
+            ValidationError::EmptyPass { phase } => write!(f, "phase {}: passes no cards", phase),
+            ValidationError::EmptyReveal { phase } => {
+                write!(f, "phase {}: reveals no cards", phase)
+            }
+            ValidationError::DeclareWithoutHiLo { phase } => write!(
+                f,
+                "phase {}: players declare high or low, but the game doesn't score both",
+                phase
+            ),
+            ValidationError::NegativePrice { phase } => {
+                write!(f, "phase {}: negative price", phase)
+            }
+            ValidationError::ReplaceBeforeDeal { phase } => {
+                write!(f, "phase {}: replaces a card before any are dealt", phase)
+            }
+            ValidationError::EmptyRepeat { phase } => {
+                write!(f, "phase {}: repeats no phases", phase)
+            }
+            ValidationError::UnknownMeasure { phase } => {
+                write!(f, "phase {}: the condition has no measure", phase)
+            }
+            ValidationError::NoFaceUpCards { phase } => write!(
+                f,
+                "phase {}: betting starts with the best face-up hand, but no cards are face up",
+                phase
+            ),
+            ValidationError::DuplicatePhaseName { phase, name } => {
+                write!(f, "phase {}: name {:?} is already used", phase, name)
+            }
+            ValidationError::AllScoringsQualified => {
+                write!(f, "every scoring has a low qualifier")
+            }
+            ValidationError::TooManyBoardRanges { scoring } => write!(
+                f,
+                "scoring {}: community_cards has more entries than there are boards",
+                scoring
+            ),
+            ValidationError::InvalidPointsExpression { scoring, error } => {
+                write!(f, "scoring {}: points expression, {}", scoring, error)
+            }
+            ValidationError::InvalidShoe { decks, penetration } => write!(
+                f,
+                "shoe of {} decks with {}% penetration is invalid",
+                decks, penetration
+            ),
+            ValidationError::NegativeBurns { cards } => {
+                write!(f, "the burn policy burns {} cards", cards)
+            }
+            ValidationError::UnknownDeckCard { card, rank, suit } => write!(
+                f,
+                "deck card {}: {:?} of {:?} isn't one of the deck's ranks and suits",
+                card, rank, suit
+            ),
+            ValidationError::DeckTooSmall { deck, needed } => write!(
+                f,
+                "a hand for two players deals {} cards, but the deck has {}",
+                needed, deck
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Checks that `game` can be played, returning every problem found.
+pub fn validate(game: &GameStructure) -> Result<(), Vec<ValidationError>> {
+    let mut errors = Vec::new();
+    if game.phases.is_empty() {
+        errors.push(ValidationError::NoPhases);
+    }
+
+    let mut checker = PhaseChecker {
+        game,
+        dealt: false,
+        face_up: false,
+        names: HashMap::new(),
+        errors,
+    };
+    for (index, p) in game.phases.iter().enumerate() {
+        checker.check(index, p);
+    }
+    let mut errors = checker.errors;
+
+    if game.scorings.is_empty() {
+        errors.push(ValidationError::NoScorings);
+    } else if game
+        .scorings
+        .iter()
+        .all(|s| s.lo && !s.lo_qualifier.is_empty())
+    {
+        errors.push(ValidationError::AllScoringsQualified);
+    }
+    for (index, scoring) in game.scorings.iter().enumerate() {
+        if scoring.community_cards.len() > game.community_board_count.max(0) as usize {
+            errors.push(ValidationError::TooManyBoardRanges { scoring: index });
+        }
+        if let Some(scoring::Ranking::PointsExpression(e)) = &scoring.ranking {
+            if let Err(error) = Expression::parse(e, &Shoe::for_game(game)) {
+                errors.push(ValidationError::InvalidPointsExpression {
+                    scoring: index,
+                    error,
+                });
+            }
+        }
+    }
+
+    if let Some(game_structure::Deck::CustomDeck(deck)) = &game.deck {
+        let (ranks, suits) = deck_labels(deck);
+        for (index, card) in deck.cards.iter().enumerate() {
+            if !ranks.contains(&card.rank.as_str()) || !suits.contains(&card.suit.as_str()) {
+                errors.push(ValidationError::UnknownDeckCard {
+                    card: index,
+                    rank: card.rank.clone(),
+                    suit: card.suit.clone(),
+                });
+            }
+        }
+    }
+
+    if max_seats(game) < 2 {
+        errors.push(ValidationError::DeckTooSmall {
+            deck: Shoe::for_game(game).remaining(),
+            needed: cards_dealt(game, 2),
+        });
+    }
+
+    if let Some(burns) = game.burns.as_ref().filter(|b| b.cards < 0) {
+        errors.push(ValidationError::NegativeBurns { cards: burns.cards });
+    }
+    if let Some(shoe) = &game.shoe {
+        if shoe.decks < 0 || !(0..=100).contains(&shoe.penetration) {
+            errors.push(ValidationError::InvalidShoe {
+                decks: shoe.decks,
+                penetration: shoe.penetration,
+            });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// The cards a hand of `game` deals if every phase is played and no one
+/// folds: those shared by the table, burns and community cards, and those
+/// dealt to each player. Draws and bought cards aren't counted, as players
+/// can only take what's left.
+fn card_budget(game: &GameStructure) -> (usize, usize) {
+    let mut phases = repeat::expand(&game.phases).phases;
+    if let Some(burns) = &game.burns {
+        apply_burns(&mut phases, burns);
+    }
+    let (mut shared, mut each) = (0, 0);
+    for p in &phases {
+        match &p.phase_type {
+            Some(phase::PhaseType::PlayerDeal(deal)) => {
+                shared += deal.burn.max(0) as usize;
+                each += dealt_face_up(deal).len();
+            }
+            Some(phase::PhaseType::CommunityDeal(deal)) => {
+                shared += (deal.burn.max(0) + deal.cards.max(0)) as usize;
+            }
+            _ => {}
+        }
+    }
+    (shared, each)
+}
+
+/// The most cards a hand of `game` for `seats` players can deal.
+pub fn cards_dealt(game: &GameStructure, seats: usize) -> usize {
+    let (shared, each) = card_budget(game);
+    shared + each * seats
+}
+
+/// The most players a fresh deck or shoe for `game` can deal a whole hand
+/// to.
+pub fn max_seats(game: &GameStructure) -> usize {
+    let deck = Shoe::for_game(game).remaining();
+    match card_budget(game) {
+        (_, 0) => usize::MAX,
+        (shared, each) => deck.saturating_sub(shared) / each,
+    }
+}
+
+/// Checks phases in the order they are played, tracking what has been dealt
+/// so far.
+struct PhaseChecker<'a> {
+    game: &'a GameStructure,
+    dealt: bool,
+    face_up: bool,
+    names: HashMap<(&'static str, String), usize>,
+    errors: Vec<ValidationError>,
+}
+
+impl<'a> PhaseChecker<'a> {
+    /// Checks `p`, reporting problems against `index`: its own index, or the
+    /// index of the repeat it belongs to.
+    fn check(&mut self, index: usize, p: &'a Phase) {
+        if p.condition
+            .as_ref()
+            .is_some_and(|c| c.measure() == Measure::Unknown)
+        {
+            self.errors
+                .push(ValidationError::UnknownMeasure { phase: index });
+        }
+        let (kind, name) = match &p.phase_type {
+            Some(phase::PhaseType::PlayerDeal(deal)) => {
+                let face_up = dealt_face_up(deal);
+                if face_up.is_empty() {
+                    self.errors
+                        .push(ValidationError::EmptyDeal { phase: index });
+                }
+                self.dealt = true;
+                self.face_up |= face_up.contains(&true);
+                return;
+            }
+            Some(phase::PhaseType::CommunityDeal(deal)) => {
+                if deal.cards <= 0 {
+                    self.errors
+                        .push(ValidationError::EmptyDeal { phase: index });
+                }
+                if deal.board_idx < 0 || deal.board_idx >= self.game.community_board_count {
+                    self.errors.push(ValidationError::NoSuchBoard {
+                        phase: index,
+                        board: deal.board_idx,
+                    });
+                }
+                ("community_deal", &deal.name)
+            }
+            Some(phase::PhaseType::BettingRound(round)) => {
+                if !self.dealt {
+                    self.errors
+                        .push(ValidationError::BettingBeforeDeal { phase: index });
+                }
+                if round.min_bet < 0 {
+                    self.errors.push(ValidationError::NegativeMinBet {
+                        phase: index,
+                        min_bet: round.min_bet,
+                    });
+                }
+                if round.order() == BettingOrder::BestFaceup && !self.face_up {
+                    self.errors
+                        .push(ValidationError::NoFaceUpCards { phase: index });
+                }
+                ("betting_round", &round.name)
+            }
+            Some(phase::PhaseType::Exchange(exchange)) => {
+                if exchange.max_exchange < 0 {
+                    self.errors
+                        .push(ValidationError::NegativeExchange { phase: index });
+                }
+                return;
+            }
+            Some(phase::PhaseType::PassCards(pass)) => {
+                if pass.cards <= 0 {
+                    self.errors
+                        .push(ValidationError::EmptyPass { phase: index });
+                }
+                return;
+            }
+            Some(phase::PhaseType::Reveal(reveal)) => {
+                if reveal.cards <= 0 {
+                    self.errors
+                        .push(ValidationError::EmptyReveal { phase: index });
+                }
+                if reveal.community
+                    && (reveal.board_idx < 0 || reveal.board_idx >= self.game.community_board_count)
+                {
+                    self.errors.push(ValidationError::NoSuchBoard {
+                        phase: index,
+                        board: reveal.board_idx,
+                    });
+                } else if !reveal.community {
+                    self.face_up = true;
+                }
+                return;
+            }
+            Some(phase::PhaseType::Declare(_)) => {
+                let lo = self.game.scorings.iter().filter(|s| s.lo).count();
+                if lo == 0 || lo == self.game.scorings.len() {
+                    self.errors
+                        .push(ValidationError::DeclareWithoutHiLo { phase: index });
+                }
+                return;
+            }
+            Some(phase::PhaseType::Buy(buy)) => {
+                if buy.price < 0 {
+                    self.errors
+                        .push(ValidationError::NegativePrice { phase: index });
+                }
+                if buy.replace && !self.dealt {
+                    self.errors
+                        .push(ValidationError::ReplaceBeforeDeal { phase: index });
+                }
+                self.face_up |= buy.face_up;
+                return;
+            }
+            Some(phase::PhaseType::Repeat(repeat)) => {
+                if repeat.times <= 0 || repeat.phases.is_empty() {
+                    self.errors
+                        .push(ValidationError::EmptyRepeat { phase: index });
+                }
+                if repeat
+                    .until
+                    .as_ref()
+                    .is_some_and(|c| c.measure() == Measure::Unknown)
+                {
+                    self.errors
+                        .push(ValidationError::UnknownMeasure { phase: index });
+                }
+                for p in &repeat.phases {
+                    self.check(index, p);
+                }
+                return;
+            }
+            Some(phase::PhaseType::Ante(_)) | None => return,
+        };
+        if name.is_empty() {
+            return;
+        }
+        if self.names.insert((kind, name.clone()), index).is_some() {
+            self.errors.push(ValidationError::DuplicatePhaseName {
+                phase: index,
+                name: name.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GameBuilder;
+    use crate::{presets, Phase, Scoring};
+
+    #[test]
+    fn test_valid_game() {
+        let game = GameBuilder::new("holdem")
+            .deal_hole(2)
+            .betting("Pre-flop")
+            .community(3)
+            .betting("Flop")
+            .build()
+            .unwrap();
+        assert_eq!(validate(&game), Ok(()));
+    }
+
+    #[test]
+    fn test_max_seats() {
+        // The board and its three burns leave 44 cards.
+        assert_eq!(cards_dealt(&presets::holdem(), 9), 26);
+        assert_eq!(max_seats(&presets::holdem()), 22);
+        assert_eq!(max_seats(&presets::omaha()), 11);
+        assert_eq!(max_seats(&presets::short_deck_holdem()), 14);
+        // Seven cards each from 52, with nothing shared.
+        assert_eq!(max_seats(&presets::seven_card_stud()), 7);
+        assert_eq!(max_seats(&presets::razz()), 7);
+        // Draws come from what's left, so they don't count.
+        assert_eq!(max_seats(&presets::five_card_draw()), 10);
+
+        // Six cards can't deal two hands and a board of three.
+        let game = GameBuilder::new("tiny")
+            .custom_deck(crate::Deck {
+                ranks: ["2", "3", "4"].map(String::from).to_vec(),
+                suits: vec!["s".to_string(), "h".to_string()],
+                ..Default::default()
+            })
+            .deal_hole(2)
+            .betting("Pre-flop")
+            .community(3)
+            .build();
+        assert_eq!(
+            game,
+            Err(vec![ValidationError::DeckTooSmall { deck: 6, needed: 7 }])
+        );
+    }
+
+    #[test]
+    fn test_reports_every_error() {
+        let mut game = presets::holdem();
+        game.phases.truncate(2);
+        game.scorings.clear();
+        game.community_board_count = 0;
+        game.phases.swap(0, 1);
+        for _ in 0..2 {
+            game.phases.push(Phase {
+                phase_type: Some(phase::PhaseType::CommunityDeal(phase::CommunityDeal {
+                    name: "Flop".to_string(),
+                    cards: 3,
+                    board_idx: 0,
+                    burn: 0,
+                    face_down: false,
+                })),
+                ..Default::default()
+            });
+        }
+        game.phases.push(Phase {
+            phase_type: Some(phase::PhaseType::BettingRound(phase::BettingRound {
+                name: "Pre-flop".to_string(),
+                min_bet: -1,
+                order: BettingOrder::BestFaceup.into(),
+                ..Default::default()
+            })),
+            ..Default::default()
+        });
+        game.phases.push(Phase {
+            phase_type: Some(phase::PhaseType::PassCards(phase::PassCards::default())),
+            ..Default::default()
+        });
+        game.phases.push(Phase {
+            phase_type: Some(phase::PhaseType::Reveal(phase::Reveal {
+                community: true,
+                board_idx: 1,
+                ..Default::default()
+            })),
+            ..Default::default()
+        });
+        game.phases.push(Phase {
+            phase_type: Some(phase::PhaseType::Declare(phase::Declare::default())),
+            condition: Some(phase::Condition::default()),
+        });
+        game.phases.push(Phase {
+            phase_type: Some(phase::PhaseType::Buy(phase::Buy {
+                price: -1,
+                ..Default::default()
+            })),
+            ..Default::default()
+        });
+        assert_eq!(
+            validate(&game),
+            Err(vec![
+                ValidationError::BettingBeforeDeal { phase: 0 },
+                ValidationError::NoSuchBoard { phase: 2, board: 0 },
+                ValidationError::NoSuchBoard { phase: 3, board: 0 },
+                ValidationError::DuplicatePhaseName {
+                    phase: 3,
+                    name: "Flop".to_string()
+                },
+                ValidationError::NegativeMinBet {
+                    phase: 4,
+                    min_bet: -1
+                },
+                ValidationError::NoFaceUpCards { phase: 4 },
+                ValidationError::DuplicatePhaseName {
+                    phase: 4,
+                    name: "Pre-flop".to_string()
+                },
+                ValidationError::EmptyPass { phase: 5 },
+                ValidationError::EmptyReveal { phase: 6 },
+                ValidationError::NoSuchBoard { phase: 6, board: 1 },
+                ValidationError::UnknownMeasure { phase: 7 },
+                ValidationError::DeclareWithoutHiLo { phase: 7 },
+                ValidationError::NegativePrice { phase: 8 },
+                ValidationError::NoScorings,
+            ])
+        );
+
+        game.phases.clear();
+        game.deck = Some(game_structure::Deck::CustomDeck(crate::Deck {
+            ranks: vec!["9".to_string(), "10".to_string()],
+            cards: vec![crate::deck::CardSpec {
+                rank: "A".to_string(),
+                suit: "♠".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }));
+        let qualified = Scoring {
+            lo: true,
+            lo_qualifier: "8".to_string(),
+            ..Default::default()
+        };
+        game.scorings = vec![
+            qualified.clone(),
+            Scoring {
+                ranking: Some(scoring::Ranking::PointsExpression(
+                    "count(rank == 'A')".to_string(),
+                )),
+                ..qualified
+            },
+        ];
+        game.shoe = Some(crate::ShoeConfig {
+            decks: 6,
+            penetration: 120,
+        });
+        game.burns = Some(crate::BurnPolicy {
+            cards: -1,
+            ..Default::default()
+        });
+        assert_eq!(
+            validate(&game),
+            Err(vec![
+                ValidationError::NoPhases,
+                ValidationError::AllScoringsQualified,
+                ValidationError::InvalidPointsExpression {
+                    scoring: 1,
+                    error: ExprError {
+                        column: 15,
+                        message: "the deck has no \"A\"".to_string()
+                    }
+                },
+                ValidationError::UnknownDeckCard {
+                    card: 0,
+                    rank: "A".to_string(),
+                    suit: "♠".to_string()
+                },
+                ValidationError::NegativeBurns { cards: -1 },
+                ValidationError::InvalidShoe {
+                    decks: 6,
+                    penetration: 120
+                },
+            ])
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! One seat's view of a hand: what a player in that seat may see, for bots
+//! and for anything else that acts on a player's behalf. A [`HandView`]
+//! message carries the same view, or a spectator's, to someone else.
+
+use crate::cards::Card;
+use crate::engine::{Audience, Chips, DealtCard, GameEngine, LegalAction};
+use crate::{hand_view, GameStructure, HandView, Phase};
+
+/// Everything on the table, and the seat's own cards, but not the other
+/// players' face-down cards or the deck.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameView<'a> {
+    pub game: &'a GameStructure,
+    pub seat: usize,
+
+    /// The seat's own cards, face down and face up.
+    pub hole: &'a [DealtCard],
+
+    /// Every seat, this one included, as the table sees it.
+    pub seats: Vec<SeatView>,
+
+    /// The face-up community cards on each board.
+    pub boards: &'a [Vec<Card>],
+
+    pub button: usize,
+
+    /// Total chips committed by all players this hand.
+    pub pot: Chips,
+
+    /// The phase waiting on players, if any.
+    pub phase: Option<&'a Phase>,
+
+    /// The actions open to the seat. Empty unless it is the seat's turn.
+    pub legal: Vec<LegalAction>,
+}
+
+/// A seat as the rest of the table sees it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SeatView {
+    pub stack: Chips,
+    pub folded: bool,
+
+    /// The seat's face-up cards.
+    pub shown: Vec<Card>,
+
+    /// How many cards the seat holds face down.
+    pub hidden: usize,
+}
+
+impl<'a> GameView<'a> {
+    /// The hand in `engine` as `seat` sees it.
+    pub fn new(engine: &'a GameEngine, seat: usize) -> Self {
+        let seats = engine
+            .seats()
+            .iter()
+            .enumerate()
+            .map(|(other, s)| {
+                let (shown, hidden): (Vec<&DealtCard>, Vec<&DealtCard>) = s
+                    .hole
+                    .iter()
+                    .partition(|c| Audience::Spectator.sees_card(other, c));
+                SeatView {
+                    stack: s.stack,
+                    folded: s.folded,
+                    shown: shown.iter().map(|c| c.card).collect(),
+                    hidden: hidden.len(),
+                }
+            })
+            .collect();
+        GameView {
+            game: engine.game(),
+            seat,
+            hole: &engine.seats()[seat].hole,
+            seats,
+            boards: engine.boards(),
+            button: engine.button(),
+            pot: engine.pot(),
+            phase: engine.current_phase(),
+            legal: engine.legal_actions(seat),
+        }
+    }
+
+    /// The chips it costs to call, if calling is open to the seat.
+    pub fn to_call(&self) -> Option<Chips> {
+        self.legal.iter().find_map(|a| match a {
+            LegalAction::Call(chips) => Some(*chips),
+            _ => None,
+        })
+    }
+
+    /// The players still contesting the pot, this one included.
+    pub fn live(&self) -> usize {
+        self.seats.iter().filter(|s| !s.folded).count()
+    }
+
+    /// Whether any of the open actions is `matches`.
+    pub fn can(&self, matches: impl Fn(&LegalAction) -> bool) -> bool {
+        self.legal.iter().any(matches)
+    }
+}
+
+impl GameEngine {
+    /// The hand as `audience` may see it. The cards they may not see are
+    /// left out rather than hidden, so they can't leak from the message.
+    pub fn view_for(&self, audience: Audience) -> HandView {
+        let seats = self
+            .seats()
+            .iter()
+            .enumerate()
+            .map(|(seat, s)| {
+                let (shown, hidden): (Vec<&DealtCard>, Vec<&DealtCard>) =
+                    s.hole.iter().partition(|c| audience.sees_card(seat, c));
+                hand_view::Seat {
+                    stack: s.stack as i64,
+                    folded: s.folded,
+                    cards: shown.iter().map(|c| c.card.into()).collect(),
+                    face_up: shown.iter().map(|c| c.face_up).collect(),
+                    hidden: hidden.len() as i32,
+                }
+            })
+            .collect();
+        let boards = self
+            .boards()
+            .iter()
+            .zip(self.hidden_boards())
+            .map(|(cards, hidden)| hand_view::Board {
+                cards: cards.iter().map(|&c| c.into()).collect(),
+                hidden: hidden as i32,
+            })
+            .collect();
+        let index = |seat: Option<usize>| seat.map_or(-1, |s| s as i32);
+        HandView {
+            seat: index(match audience {
+                Audience::Seat(seat) => Some(seat),
+                Audience::Spectator => None,
+            }),
+            seats,
+            boards,
+            button: self.button() as i32,
+            pot: self.pot() as i64,
+            to_act: index(self.to_act()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::presets;
+
+    #[test]
+    fn test_view_hides_other_hands() {
+        let engine = GameEngine::new(presets::seven_card_stud(), 3).unwrap();
+        let seat = engine.to_act().unwrap();
+        let view = GameView::new(&engine, seat);
+        assert_eq!(view.hole, engine.seats()[seat].hole.as_slice());
+        assert!(!view.legal.is_empty());
+        assert_eq!(view.live(), 3);
+        for (other, seen) in view.seats.iter().enumerate() {
+            let hole = &engine.seats()[other].hole;
+            // Two down and one up on third street.
+            assert_eq!((seen.shown.len(), seen.hidden), (1, 2));
+            assert!(hole.iter().any(|c| c.face_up && c.card == seen.shown[0]));
+        }
+        let waiting = GameView::new(&engine, (seat + 1) % 3);
+        assert!(waiting.legal.is_empty() && waiting.to_call().is_none());
+    }
+
+    #[test]
+    fn test_view_for() {
+        let engine = GameEngine::new(presets::seven_card_stud(), 3).unwrap();
+        let view = engine.view_for(Audience::Seat(1));
+        assert_eq!((view.seat, view.seats.len()), (1, 3));
+        assert_eq!(view.to_act, engine.to_act().unwrap() as i32);
+        for (seat, seen) in view.seats.iter().enumerate() {
+            let hole = &engine.seats()[seat].hole;
+            if seat == 1 {
+                let cards: Vec<crate::Card> = hole.iter().map(|c| c.card.into()).collect();
+                assert_eq!(seen.cards, cards);
+                assert_eq!(seen.face_up, [false, false, true]);
+                assert_eq!(seen.hidden, 0);
+            } else {
+                assert_eq!((seen.cards.len(), seen.hidden), (1, 2));
+                assert_eq!(seen.face_up, [true]);
+            }
+        }
+        let spectator = engine.view_for(Audience::Spectator);
+        assert_eq!(spectator.seat, -1);
+        assert!(spectator.seats.iter().all(|s| s.hidden == 2));
+    }
+}
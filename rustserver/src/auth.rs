@@ -0,0 +1,146 @@
+//! Who a player is, from a token the server signed for them, so that a
+//! seat belongs to the person who took it rather than to whoever has its
+//! player id.
+//!
+//! A token is `NAME.EXPIRES.MAC`: the player's name, the Unix time the
+//! token stops working, and the HMAC-SHA256 of `NAME.EXPIRES` under the
+//! server's key, in hex. gRPC clients send it in an `authorization:
+//! Bearer TOKEN` header with each request; WebSocket clients send it once,
+//! in an `authenticate` message.
+
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rustlib::fingerprint::{hex, hmac};
+
+use crate::table::TableError;
+
+/// The fewest bytes a key may have.
+pub const MIN_KEY: usize = 16;
+
+/// The secret tokens are signed with.
+#[derive(Clone)]
+pub struct Key(Vec<u8>);
+
+impl Key {
+    pub fn new(secret: Vec<u8>) -> Result<Self, String> {
+        if secret.len() < MIN_KEY {
+            return Err(format!("a key needs at least {} bytes", MIN_KEY));
+        }
+        Ok(Key(secret))
+    }
+
+    /// Reads a key from the file at `path`, ignoring a final newline.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let mut secret = fs::read(path).map_err(|e| format!("can't read {}: {}", path, e))?;
+        while secret.last().is_some_and(|b| b"\r\n".contains(b)) {
+            secret.pop();
+        }
+        Key::new(secret).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// A token for `name` that works until the Unix time `expires`.
+    pub fn issue(&self, name: &str, expires: u64) -> Result<String, String> {
+        if name.is_empty() || name.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(format!("{:?} can't be signed in as", name));
+        }
+        let claims = format!("{}.{}", name, expires);
+        Ok(format!("{}.{}", claims, hex(&self.mac(&claims))))
+    }
+
+    /// The name `token` was issued for, if it is signed with this key and
+    /// hasn't expired by the Unix time `now`.
+    pub fn verify(&self, token: &str, now: u64) -> Result<String, TableError> {
+        let bad = || TableError::Unauthenticated("the token isn't valid".to_string());
+        let (claims, mac) = token.rsplit_once('.').ok_or_else(bad)?;
+        let (name, expires) = claims.rsplit_once('.').ok_or_else(bad)?;
+        let expected = hex(&self.mac(claims));
+        // Compare every byte, so the time taken doesn't say how much of
+        // a forged signature was right.
+        let differences = expected
+            .bytes()
+            .zip(mac.bytes())
+            .fold(0, |d, (a, b)| d | (a ^ b));
+        if name.is_empty() || mac.len() != expected.len() || differences != 0 {
+            return Err(bad());
+        }
+        let expires: u64 = expires.parse().map_err(|_| bad())?;
+        if expires <= now {
+            return Err(TableError::Unauthenticated(
+                "the token has expired".to_string(),
+            ));
+        }
+        Ok(name.to_string())
+    }
+
+    fn mac(&self, message: &str) -> [u8; 32] {
+        hmac(&self.0, message.as_bytes())
+    }
+}
+
+/// Whether requests must say who they come from, and how to check.
+#[derive(Clone, Default)]
+pub struct Auth {
+    key: Option<Key>,
+}
+
+impl Auth {
+    /// Requires every request to carry a token signed with `key`.
+    pub fn with_key(key: Key) -> Self {
+        Auth { key: Some(key) }
+    }
+
+    /// Who `token` says sent a request, or `None` if the server doesn't
+    /// sign players in.
+    pub fn identify(&self, token: Option<&str>) -> Result<Option<String>, TableError> {
+        let Some(key) = &self.key else {
+            return Ok(None);
+        };
+        let token = token.ok_or_else(|| {
+            TableError::Unauthenticated("the server needs a token to say who you are".to_string())
+        })?;
+        key.verify(token, now()).map(Some)
+    }
+
+    pub fn required(&self) -> bool {
+        self.key.is_some()
+    }
+}
+
+/// The Unix time.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens() {
+        let key = Key::new(b"a key for the tests".to_vec()).unwrap();
+        assert!(Key::new(b"short".to_vec()).is_err());
+        let token = key.issue("alice.smith", 1000).unwrap();
+        assert!(token.starts_with("alice.smith.1000."));
+        assert_eq!(key.verify(&token, 999), Ok("alice.smith".to_string()));
+        assert_eq!(
+            key.verify(&token, 1000),
+            Err(TableError::Unauthenticated(
+                "the token has expired".to_string()
+            ))
+        );
+        let forged = token.replace("1000", "9999");
+        assert!(key.verify(&forged, 999).is_err());
+        let other = Key::new(b"another key for the tests".to_vec()).unwrap();
+        assert!(other.verify(&token, 999).is_err());
+        assert!(key.verify("nonsense", 0).is_err());
+        assert!(key.issue("two words", 1000).is_err());
+
+        let auth = Auth::with_key(key);
+        assert!(auth.identify(None).is_err());
+        assert_eq!(Auth::default().identify(None), Ok(None));
+    }
+}
@@ -0,0 +1,175 @@
+//! What players say at a table, and the moderation it passes through
+//! before anyone else sees it.
+//!
+//! A message is checked for length and stray control characters, then
+//! judged by the server's [`ChatFilter`], if it has one, which may let it
+//! through, change its text, or turn it down. What gets through goes to
+//! every watcher of the table in its event stream, blanked for those
+//! whose player has muted the speaker.
+
+use std::collections::HashSet;
+use std::fs;
+
+use service_proto3::snapfold::server::chat::Emote;
+
+use crate::table::TableError;
+
+/// The most characters a message may have.
+pub const MAX_CHAT: usize = 200;
+
+/// A message, as a filter is shown it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Said<'a> {
+    pub table_id: &'a str,
+    /// The name the speaker goes by at the table, and who they signed in
+    /// as, if the server signs players in.
+    pub name: &'a str,
+    pub user: Option<&'a str>,
+    pub text: &'a str,
+}
+
+/// What a filter makes of a message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Allow,
+    /// Send this text instead.
+    Replace(String),
+    /// Send nothing, and tell the speaker why.
+    Reject(String),
+}
+
+/// Judges every message before it is sent, so a server can keep its
+/// tables civil however it likes.
+pub trait ChatFilter: Send + Sync {
+    fn judge(&self, said: &Said) -> Verdict;
+}
+
+impl<F: Fn(&Said) -> Verdict + Send + Sync> ChatFilter for F {
+    fn judge(&self, said: &Said) -> Verdict {
+        self(said)
+    }
+}
+
+/// The text to send for a message of `text` and `emote`, or why it can't
+/// be sent.
+pub fn check(text: &str, emote: Emote) -> Result<String, TableError> {
+    let bad = |message: &str| Err(TableError::BadRequest(message.to_string()));
+    let text = text.trim();
+    if text.is_empty() && emote == Emote::Unspecified {
+        return bad("nothing to say");
+    }
+    if text.chars().count() > MAX_CHAT {
+        return bad("a message has at most 200 characters");
+    }
+    if text.chars().any(char::is_control) {
+        return bad("a message can't have control characters");
+    }
+    Ok(text.to_string())
+}
+
+/// A filter that stars out words on a list, whatever their case, and
+/// turns down messages with any of the words on a second list.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Words {
+    starred: HashSet<String>,
+    banned: HashSet<String>,
+}
+
+impl Words {
+    /// The filter for `words`, where those starting with `!` are banned
+    /// and the rest are starred.
+    pub fn new<'a>(words: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut filter = Words::default();
+        for word in words {
+            match word.strip_prefix('!') {
+                Some(word) => filter.banned.insert(word.to_lowercase()),
+                None => filter.starred.insert(word.to_lowercase()),
+            };
+        }
+        filter
+    }
+
+    /// Reads the words from the file at `path`, one to a line as
+    /// [`Words::new`] takes them, skipping blank lines and those starting
+    /// with `#`.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("can't read {}: {}", path, e))?;
+        Ok(Words::new(
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#')),
+        ))
+    }
+}
+
+impl ChatFilter for Words {
+    fn judge(&self, said: &Said) -> Verdict {
+        let mut text = String::with_capacity(said.text.len());
+        let mut starred = false;
+        let mut word = String::new();
+        for c in said.text.chars().chain(Some(' ')) {
+            if c.is_alphanumeric() || c == '\'' {
+                word.push(c);
+                continue;
+            }
+            let lower = word.to_lowercase();
+            if self.banned.contains(&lower) {
+                return Verdict::Reject(format!("{:?} isn't allowed", word));
+            }
+            if self.starred.contains(&lower) {
+                text.extend(word.chars().map(|_| '*'));
+                starred = true;
+            } else {
+                text.push_str(&word);
+            }
+            word.clear();
+            text.push(c);
+        }
+        text.pop();
+        if starred {
+            Verdict::Replace(text)
+        } else {
+            Verdict::Allow
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        assert_eq!(
+            check("  gl all ", Emote::Unspecified),
+            Ok("gl all".to_string())
+        );
+        assert_eq!(check("", Emote::NiceHand), Ok(String::new()));
+        assert!(check(" ", Emote::Unspecified).is_err());
+        assert!(check("a\u{7}", Emote::Unspecified).is_err());
+        assert!(check(&"x".repeat(MAX_CHAT), Emote::Unspecified).is_ok());
+        assert!(check(&"é".repeat(MAX_CHAT + 1), Emote::Unspecified).is_err());
+    }
+
+    #[test]
+    fn test_words() {
+        let words = Words::new(["darn", "heck", "!rigged"]);
+        let said = |text| Said {
+            table_id: "t1",
+            name: "ann",
+            user: None,
+            text,
+        };
+        assert_eq!(words.judge(&said("well played")), Verdict::Allow);
+        assert_eq!(
+            words.judge(&said("Darn it, what the HECK!")),
+            Verdict::Replace("**** it, what the ****!".to_string())
+        );
+        // Only whole words are starred.
+        assert_eq!(words.judge(&said("darned hecks")), Verdict::Allow);
+        assert_eq!(
+            words.judge(&said("darn, RIGGED")),
+            Verdict::Reject("\"RIGGED\" isn't allowed".to_string())
+        );
+    }
+}
@@ -0,0 +1,702 @@
+//! WebSocket clients: each connection's requests, and the events of the
+//! tables it subscribes to.
+//!
+//! Everything a connection is sent goes through one bounded queue to a
+//! thread that writes it, so events arrive in the order the tables emitted
+//! them and a subscription's reply arrives before its first event. Replies
+//! wait for room in the queue, which slows a client that sends requests
+//! faster than it reads. Events can't wait, since they are sent with the
+//! table locked: a client that lets the queue fill is disconnected with
+//! status 1013 and has to subscribe again.
+//!
+//! On a server that signs players in, a connection's first request is an
+//! `authenticate` message, and the rest are made as whoever it names.
+//! Actions, chat and new tables count against the connection's budgets,
+//! and the player's, as [`limits`] says.
+//!
+//! Every heartbeat the server sends a ping frame, and times the pong that
+//! answers it: the round trip is taken as the latency of each player the
+//! connection streams events for, which their turns allow for. A
+//! connection that sends nothing back for three pings in a row is
+//! dropped.
+//!
+//! With [`Tls`], every connection is encrypted, from the first byte of
+//! its handshake.
+
+use std::io::{self, BufReader, ErrorKind};
+use std::net::{Shutdown, SocketAddr, TcpListener};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use prost::Message as _;
+use service_proto3::snapfold::server::{
+    client_message, reply, server_message, ClientMessage, Reply, ServerMessage,
+    StreamEventsRequest, TableEvent,
+};
+
+use crate::auth::Auth;
+use crate::json;
+use crate::limits::{self, Limiter};
+use crate::table::{self, Sink, TableError, Tables};
+use crate::tls::{Stream, Tls};
+use crate::websocket::{self, Message, Protocol};
+
+/// How many messages may wait to be written to a connection.
+pub const QUEUE: usize = 256;
+
+/// Status codes for closing a connection.
+const PROTOCOL_ERROR: u16 = 1002;
+const TRY_AGAIN_LATER: u16 = 1013;
+
+/// Pings a connection may leave unanswered, sending nothing at all, before
+/// it is dropped.
+const MISSED_PINGS: u32 = 3;
+
+/// Answers each WebSocket connection to `listener` on a thread of its own,
+/// pinging each every `heartbeat`, and over `tls` if there is any.
+pub fn serve(
+    listener: TcpListener,
+    tables: Arc<Tables>,
+    auth: Auth,
+    limiter: Limiter,
+    heartbeat: Duration,
+    tls: Option<Tls>,
+) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("warning: can't accept a connection: {}", e);
+                continue;
+            }
+        };
+        let tables = Arc::clone(&tables);
+        let auth = auth.clone();
+        let limiter = limiter.clone();
+        let tls = tls.clone();
+        thread::spawn(move || {
+            let peer = stream.peer_addr().ok();
+            let stream = match &tls {
+                Some(tls) => tls.wrap(stream),
+                None => Ok(Stream::Plain(stream)),
+            };
+            let connected =
+                stream.and_then(|stream| connection(stream, &tables, &auth, &limiter, heartbeat));
+            if let Err(e) = connected {
+                if let Some(peer) = peer {
+                    eprintln!("warning: websocket {}: {}", peer, e);
+                }
+            }
+        });
+    }
+}
+
+enum Outgoing {
+    Message(ServerMessage),
+    Frame(Message),
+}
+
+/// The sending end of a connection's queue.
+#[derive(Clone)]
+struct Outbox {
+    queue: SyncSender<Outgoing>,
+    /// Set once an event found the queue full.
+    slow: Arc<AtomicBool>,
+    /// Set once the connection has ended.
+    closed: Arc<AtomicBool>,
+    pulse: Arc<Mutex<Pulse>>,
+}
+
+/// Whether a connection is still there, and how long its pings take.
+struct Pulse {
+    /// Pings sent since anything was last heard from the connection.
+    unanswered: u32,
+    /// The pings sent so far, and when the last was if it is unanswered.
+    pings: u64,
+    waiting: Option<Instant>,
+    round_trip: Option<Duration>,
+    /// The tables and player ids the connection streams events for.
+    players: Vec<(String, String)>,
+}
+
+impl Outbox {
+    fn new(capacity: usize) -> (Self, Receiver<Outgoing>) {
+        let (queue, receiver) = mpsc::sync_channel(capacity);
+        let outbox = Outbox {
+            queue,
+            slow: Arc::new(AtomicBool::new(false)),
+            closed: Arc::new(AtomicBool::new(false)),
+            pulse: Arc::new(Mutex::new(Pulse {
+                unanswered: 0,
+                pings: 0,
+                waiting: None,
+                round_trip: None,
+                players: Vec::new(),
+            })),
+        };
+        (outbox, receiver)
+    }
+
+    fn pulse(&self) -> std::sync::MutexGuard<'_, Pulse> {
+        self.pulse.lock().expect("pulse lock")
+    }
+
+    /// Notes that the connection streams the events of `player` at table
+    /// `table_id`, and gives the player its latency if it is known.
+    fn streams(&self, tables: &Tables, table_id: &str, player: &str) {
+        let mut pulse = self.pulse();
+        pulse
+            .players
+            .push((table_id.to_string(), player.to_string()));
+        if let Some(round_trip) = pulse.round_trip {
+            tables.latency(table_id, player, round_trip);
+        }
+    }
+
+    /// Times the pong with `payload`, if it answers the last ping, and
+    /// gives the round trip to every player the connection streams for.
+    fn pong(&self, tables: &Tables, payload: &[u8]) {
+        let mut pulse = self.pulse();
+        if payload != pulse.pings.to_be_bytes() {
+            return;
+        }
+        let Some(sent) = pulse.waiting.take() else {
+            return;
+        };
+        let round_trip = sent.elapsed();
+        pulse.round_trip = Some(round_trip);
+        for (table_id, player) in &pulse.players {
+            tables.latency(table_id, player, round_trip);
+        }
+    }
+
+    /// Queues `out`, waiting for room. False once the connection is gone.
+    fn send(&self, out: Outgoing) -> bool {
+        self.queue.send(out).is_ok()
+    }
+
+    /// Queues `out` if there is room, or gives up on the connection if
+    /// there isn't.
+    fn offer(&self, out: Outgoing) -> bool {
+        if self.slow.load(Ordering::Relaxed) {
+            return false;
+        }
+        match self.queue.try_send(out) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.slow.store(true, Ordering::Relaxed);
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+
+    /// A sink that queues the events of table `table_id`.
+    fn sink(&self, table_id: String) -> Box<dyn Sink> {
+        Box::new(TableSink {
+            outbox: self.clone(),
+            table_id,
+        })
+    }
+}
+
+struct TableSink {
+    outbox: Outbox,
+    table_id: String,
+}
+
+impl Sink for TableSink {
+    fn send(&mut self, event: TableEvent) -> bool {
+        self.outbox.offer(Outgoing::Message(ServerMessage {
+            kind: Some(server_message::Kind::Event(event)),
+            table_id: self.table_id.clone(),
+        }))
+    }
+
+    fn closed(&self) -> bool {
+        self.outbox.closed.load(Ordering::Relaxed) || self.outbox.slow.load(Ordering::Relaxed)
+    }
+}
+
+/// The answer to request `id`.
+fn reply(id: u64, result: Result<Option<reply::Result>, TableError>) -> Outgoing {
+    let reply = match result {
+        Ok(result) => Reply {
+            id,
+            error: String::new(),
+            result,
+        },
+        Err(e) => Reply {
+            id,
+            error: e.to_string(),
+            result: None,
+        },
+    };
+    Outgoing::Message(ServerMessage {
+        kind: Some(server_message::Kind::Reply(reply)),
+        table_id: String::new(),
+    })
+}
+
+fn connection(
+    stream: Stream,
+    tables: &Tables,
+    auth: &Auth,
+    limiter: &Limiter,
+    heartbeat: Duration,
+) -> io::Result<()> {
+    let peer = stream.peer_addr().ok();
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream.try_clone()?;
+    let protocol = websocket::accept(&mut reader, &mut writer)?;
+    let (outbox, queue) = Outbox::new(QUEUE);
+    let slow = Arc::clone(&outbox.slow);
+    let writing = thread::spawn(move || write_queue(writer, protocol, queue, &slow));
+    {
+        let stream = stream.try_clone()?;
+        let outbox = outbox.clone();
+        thread::spawn(move || beat(stream, &outbox, heartbeat));
+    }
+    let reader = websocket::Reader::new(reader);
+    let result = read_requests(reader, tables, auth, limiter, peer, &outbox);
+    if let Err(e) = &result {
+        outbox.send(Outgoing::Frame(Message::Close(Some((
+            PROTOCOL_ERROR,
+            e.to_string(),
+        )))));
+    }
+    // Sinks at tables still hold the queue; dropping the outbox doesn't
+    // close it, so the writer is told to stop, and the tables that the
+    // connection has gone.
+    outbox.closed.store(true, Ordering::Relaxed);
+    outbox.send(Outgoing::Frame(Message::Close(None)));
+    let _ = writing.join();
+    let _ = stream.shutdown(Shutdown::Both);
+    result
+}
+
+/// Writes what is queued for a connection until it closes, then shuts the
+/// socket down so the reader stops too.
+fn write_queue(
+    mut stream: Stream,
+    protocol: Protocol,
+    queue: Receiver<Outgoing>,
+    slow: &AtomicBool,
+) -> io::Result<()> {
+    let result = (|| {
+        for out in queue {
+            if slow.load(Ordering::Relaxed) {
+                let reason = "too far behind the table".to_string();
+                return websocket::write(
+                    &mut stream,
+                    &Message::Close(Some((TRY_AGAIN_LATER, reason))),
+                );
+            }
+            let frame = match out {
+                Outgoing::Message(message) => match protocol {
+                    Protocol::Json => Message::Text(json::encode(&message)),
+                    Protocol::Proto => Message::Binary(message.encode_to_vec()),
+                },
+                Outgoing::Frame(frame) => frame,
+            };
+            websocket::write(&mut stream, &frame)?;
+            if let Message::Close(_) = frame {
+                break;
+            }
+        }
+        Ok(())
+    })();
+    let _ = stream.shutdown(Shutdown::Both);
+    result
+}
+
+/// Pings the connection every `heartbeat` until it ends, and shuts it down
+/// once it has sent nothing back for [`MISSED_PINGS`] pings.
+fn beat(stream: Stream, outbox: &Outbox, heartbeat: Duration) {
+    loop {
+        thread::sleep(heartbeat);
+        if outbox.closed.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut pulse = outbox.pulse();
+        if pulse.unanswered >= MISSED_PINGS {
+            let _ = stream.shutdown(Shutdown::Both);
+            return;
+        }
+        pulse.unanswered += 1;
+        pulse.pings += 1;
+        pulse.waiting = Some(Instant::now());
+        let ping = Message::Ping(pulse.pings.to_be_bytes().to_vec());
+        drop(pulse);
+        if !outbox.send(Outgoing::Frame(ping)) {
+            return;
+        }
+    }
+}
+
+/// Answers a connection's messages until it closes. A clean close or a
+/// dropped connection is `Ok`; a protocol error isn't.
+fn read_requests<R: io::Read>(
+    mut reader: websocket::Reader<R>,
+    tables: &Tables,
+    auth: &Auth,
+    limiter: &Limiter,
+    peer: Option<SocketAddr>,
+    outbox: &Outbox,
+) -> io::Result<()> {
+    // Who the connection signed in as.
+    let mut user = None;
+    loop {
+        let message = match reader.read() {
+            Ok(message) => message,
+            Err(e) if e.kind() == ErrorKind::InvalidData => return Err(e),
+            Err(_) => return Ok(()),
+        };
+        outbox.pulse().unanswered = 0;
+        let request = match message {
+            Message::Text(text) => json::decode(&text),
+            Message::Binary(data) => {
+                ClientMessage::decode(data.as_slice()).map_err(|e| json::BadMessage {
+                    id: 0,
+                    message: e.to_string(),
+                })
+            }
+            Message::Ping(data) => {
+                outbox.send(Outgoing::Frame(Message::Pong(data)));
+                continue;
+            }
+            Message::Pong(data) => {
+                outbox.pong(tables, &data);
+                continue;
+            }
+            Message::Close(status) => {
+                let echo = status.map(|(code, _)| (code, String::new()));
+                outbox.send(Outgoing::Frame(Message::Close(echo)));
+                return Ok(());
+            }
+        };
+        let answered = match request {
+            Ok(request) => handle(request, tables, auth, limiter, peer, &mut user, outbox),
+            Err(bad) => outbox.send(reply(bad.id, Err(TableError::BadRequest(bad.message)))),
+        };
+        if !answered {
+            return Ok(());
+        }
+    }
+}
+
+/// Answers `request` from `user`, who an `authenticate` request changes,
+/// connected from `peer` and limited by `limiter`. False once the
+/// connection is gone.
+fn handle(
+    request: ClientMessage,
+    tables: &Tables,
+    auth: &Auth,
+    limiter: &Limiter,
+    peer: Option<SocketAddr>,
+    user: &mut Option<String>,
+    outbox: &Outbox,
+) -> bool {
+    use client_message::Kind;
+    let id = request.id;
+    let authenticating = matches!(request.kind, Some(Kind::Authenticate(_)));
+    if auth.required() && user.is_none() && !authenticating {
+        let message = "send an authenticate message first".to_string();
+        return outbox.send(reply(id, Err(TableError::Unauthenticated(message))));
+    }
+    let limited = match &request.kind {
+        Some(Kind::CreateTable(_)) => Some(limits::Kind::Table),
+        Some(Kind::SubmitAction(_)) => Some(limits::Kind::Action),
+        Some(Kind::SendChat(_)) => Some(limits::Kind::Chat),
+        _ => None,
+    };
+    if let Some(kind) = limited {
+        let who = limits::who(peer, user.as_deref());
+        if let Err(e) = limiter.check(kind, &who, Instant::now()) {
+            return outbox.send(reply(id, Err(e)));
+        }
+    }
+    let result = match request.kind {
+        None => Err(TableError::BadRequest("no request".to_string())),
+        Some(Kind::Authenticate(authenticate)) => {
+            auth.identify(Some(&authenticate.token)).map(|signed_in| {
+                *user = signed_in;
+                None
+            })
+        }
+        Some(Kind::CreateTable(create)) => tables
+            .create(create)
+            .map(|created| Some(reply::Result::Created(created))),
+        Some(Kind::JoinTable(join)) => tables
+            .join(&join, user.as_deref())
+            .map(|joined| Some(reply::Result::Joined(joined))),
+        Some(Kind::SubmitAction(submit)) => tables.submit(&submit, user.as_deref()).map(|()| None),
+        Some(Kind::ListTables(list)) => Ok(Some(reply::Result::Tables(tables.list(&list)))),
+        Some(Kind::ReserveSeat(reserve)) => tables
+            .reserve(&reserve)
+            .map(|reserved| Some(reply::Result::Reserved(reserved))),
+        Some(Kind::LeaveTable(leave)) => tables.leave(&leave, user.as_deref()).map(|()| None),
+        Some(Kind::ListHands(list)) => tables
+            .hands(&list, user.as_deref())
+            .map(|hands| Some(reply::Result::Hands(hands))),
+        Some(Kind::SendChat(send)) => tables.chat(&send, user.as_deref()).map(|()| None),
+        Some(Kind::Mute(mute)) => tables.mute(&mute, user.as_deref()).map(|()| None),
+        Some(Kind::Ping(ping)) => tables.ping(&ping, user.as_deref()).map(|()| None),
+        Some(Kind::Subscribe(subscribe)) => {
+            return self::subscribe(id, subscribe, tables, user.as_deref(), outbox)
+        }
+    };
+    outbox.send(reply(id, result))
+}
+
+fn subscribe(
+    id: u64,
+    request: StreamEventsRequest,
+    tables: &Tables,
+    user: Option<&str>,
+    outbox: &Outbox,
+) -> bool {
+    let table = match tables.get(&request.table_id) {
+        Ok(table) => table,
+        Err(e) => return outbox.send(reply(id, Err(e))),
+    };
+    let delay = match table::delay(&request) {
+        Ok(delay) => delay,
+        Err(e) => return outbox.send(reply(id, Err(e))),
+    };
+    let mut table = table::lock(&table);
+    let player = Some(request.player_id.as_str()).filter(|id| !id.is_empty());
+    if let Some(Err(e)) = player.map(|p| table.authorize(p, user)) {
+        drop(table);
+        return outbox.send(reply(id, Err(e)));
+    }
+    // With the table locked, nothing it emits can come before the reply.
+    if !outbox.offer(reply(id, Ok(None))) {
+        return false;
+    }
+    let sink = outbox.sink(request.table_id.clone());
+    if let Some(delay) = delay {
+        table.spectate(delay, sink);
+        return true;
+    }
+    let after = Some(request.after).filter(|&after| after > 0);
+    table
+        .watch(player, after, sink)
+        .expect("the player was just found");
+    drop(table);
+    if let Some(player) = player {
+        outbox.streams(tables, &request.table_id, player);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustlib::gamedef::json::Value;
+    use std::io::{BufRead, Read, Write};
+    use std::net::TcpStream;
+
+    #[test]
+    fn test_outbox() {
+        let (outbox, queue) = Outbox::new(2);
+        let mut sink = outbox.sink("t1".to_string());
+        assert!(sink.send(Default::default()));
+        assert!(sink.send(Default::default()));
+        assert!(!sink.closed());
+        assert!(!sink.send(Default::default()));
+        assert!(outbox.slow.load(Ordering::Relaxed));
+        // Once a connection has fallen behind, nothing more is queued.
+        queue.recv().unwrap();
+        assert!(!sink.send(Default::default()));
+        assert!(sink.closed());
+        drop(queue);
+        assert!(!outbox.send(reply(1, Ok(None))));
+    }
+
+    /// Reads one unmasked frame from the server.
+    fn read_frame(stream: &mut impl Read) -> (u8, Vec<u8>) {
+        let mut head = [0; 2];
+        stream.read_exact(&mut head).unwrap();
+        let len = match head[1] {
+            126 => {
+                let mut len = [0; 2];
+                stream.read_exact(&mut len).unwrap();
+                u16::from_be_bytes(len) as usize
+            }
+            127 => {
+                let mut len = [0; 8];
+                stream.read_exact(&mut len).unwrap();
+                u64::from_be_bytes(len) as usize
+            }
+            len => len as usize,
+        };
+        let mut payload = vec![0; len];
+        stream.read_exact(&mut payload).unwrap();
+        (head[0], payload)
+    }
+
+    fn read_json(stream: &mut impl Read) -> Value {
+        let (head, payload) = read_frame(stream);
+        assert_eq!(head, 0x81);
+        rustlib::gamedef::json::parse(std::str::from_utf8(&payload).unwrap()).unwrap()
+    }
+
+    fn get<'a>(value: &'a Value, path: &[&str]) -> Option<&'a Value> {
+        path.iter().try_fold(value, |value, key| match value {
+            Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        })
+    }
+
+    fn text<'a>(value: &'a Value, path: &[&str]) -> &'a str {
+        match get(value, path) {
+            Some(Value::String(s)) => s,
+            other => panic!("{:?} at {:?} in {}", other, path, value),
+        }
+    }
+
+    /// Connects to a server pinging every `heartbeat`, returning the
+    /// stream and a reader past the handshake.
+    fn connect(heartbeat: Duration) -> (TcpStream, BufReader<TcpStream>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            serve(
+                listener,
+                Arc::new(Tables::default()),
+                Auth::default(),
+                Limiter::default(),
+                heartbeat,
+                None,
+            )
+        });
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "GET / HTTP/1.1\r\nHost: test\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\
+             Sec-WebSocket-Protocol: snapfold.json\r\n\r\n"
+        )
+        .unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert!(line.starts_with("HTTP/1.1 101 "), "{}", line);
+        while line != "\r\n" {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+        }
+        (stream, reader)
+    }
+
+    #[test]
+    fn test_connection() {
+        let (mut stream, mut reader) = connect(Duration::from_secs(3600));
+        let mut send = |text: String| {
+            stream
+                .write_all(&websocket::client_frames(0x1, text.as_bytes(), 1000))
+                .unwrap()
+        };
+
+        let game = rustlib::gamedef::to_json(&rustlib::presets::holdem());
+        send(format!(
+            r#"{{"id": 1, "createTable": {{"game": {}, "seats": 2, "stack": 100}}}}"#,
+            game
+        ));
+        let created = read_json(&mut reader);
+        assert_eq!(text(&created, &["reply", "id"]), "1");
+        let table_id = text(&created, &["reply", "created", "tableId"]).to_string();
+
+        let mut ids = Vec::new();
+        for (id, name) in [(2, "alice"), (3, "bob")] {
+            send(format!(
+                r#"{{"id": {}, "joinTable": {{"tableId": "{}", "name": "{}"}}}}"#,
+                id, table_id, name
+            ));
+            let joined = read_json(&mut reader);
+            ids.push(text(&joined, &["reply", "joined", "playerId"]).to_string());
+        }
+
+        send(format!(
+            r#"{{"id": 4, "subscribe": {{"tableId": "{}", "playerId": "nobody"}}}}"#,
+            table_id
+        ));
+        let refused = read_json(&mut reader);
+        assert_eq!(
+            text(&refused, &["reply", "error"]),
+            "no player at the table has that id"
+        );
+
+        // The reply comes first, then the hand so far in order, with
+        // alice's own cards face up.
+        send(format!(
+            r#"{{"id": 5, "subscribe": {{"tableId": "{}", "playerId": "{}"}}}}"#,
+            table_id, ids[0]
+        ));
+        let subscribed = read_json(&mut reader);
+        assert_eq!(text(&subscribed, &["reply", "id"]), "5");
+        assert!(get(&subscribed, &["reply", "error"]).is_none());
+        let mut sequence = 0;
+        let mut own_cards = None;
+        loop {
+            let event = read_json(&mut reader);
+            assert_eq!(text(&event, &["tableId"]), table_id);
+            let next: u64 = text(&event, &["event", "sequence"]).parse().unwrap();
+            assert!(next > sequence, "{} after {}", next, sequence);
+            sequence = next;
+            if let Some(Value::Array(cards)) =
+                get(&event, &["event", "game", "holeCardsDealt", "cards"])
+            {
+                if get(&event, &["event", "game", "holeCardsDealt", "seat"]).is_none() {
+                    own_cards = Some(cards.len());
+                }
+            }
+            if get(&event, &["event", "turn"]).is_some() {
+                break;
+            }
+        }
+        assert_eq!(own_cards, Some(2));
+
+        send(r#"{"id": 6, "submitAction": {}}"#.to_string());
+        let refused = read_json(&mut reader);
+        assert!(text(&refused, &["reply", "error"]).starts_with("no table"));
+
+        stream
+            .write_all(&websocket::client_frames(0x9, b"ping", 125))
+            .unwrap();
+        assert_eq!(read_frame(&mut reader), (0x8a, b"ping".to_vec()));
+        stream
+            .write_all(&websocket::client_frames(0x8, &1000u16.to_be_bytes(), 125))
+            .unwrap();
+        assert_eq!(
+            read_frame(&mut reader),
+            (0x88, 1000u16.to_be_bytes().to_vec())
+        );
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        let heartbeat = Duration::from_millis(50);
+        let (mut stream, mut reader) = connect(heartbeat);
+        // A connection that answers its pings is kept...
+        for ping in 1..=5u64 {
+            assert_eq!(read_frame(&mut reader), (0x89, ping.to_be_bytes().to_vec()));
+            stream
+                .write_all(&websocket::client_frames(0xa, &ping.to_be_bytes(), 125))
+                .unwrap();
+        }
+        // ...and one that stops is dropped after three more.
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        let pings: Vec<&[u8]> = rest.chunks(10).map(|frame| &frame[2..]).collect();
+        assert_eq!(pings, [6u64, 7, 8].map(u64::to_be_bytes));
+    }
+}
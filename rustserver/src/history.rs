@@ -0,0 +1,473 @@
+//! Every hand played to the end at the server's tables, kept for settling
+//! disputes and for players to look back on. Hands are held in memory
+//! and, given a file, appended to it one JSON object per line, to be read
+//! back when the server starts again. Each line is a hand file as
+//! `snapfold play --record` saves one, with the table and times added.
+//!
+//! A hand is kept as its game and a [`CompactState`] that plays back into
+//! the engine, which has the hand's every card. Its events are filtered for
+//! whoever asks for them just as they were at the table.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use rustlib::compact::CompactState;
+use rustlib::engine::Audience;
+use rustlib::history::{self, HandFile, Time};
+use rustlib::GameStructure;
+use service_proto3::snapfold::server::{HandRecord, ListHandsRequest, ListHandsResponse};
+
+use crate::table::TableError;
+
+/// Hands on a page if the request doesn't say, and the most it may ask for.
+const PAGE: usize = 20;
+const MAX_PAGE: usize = 100;
+
+/// The player dealt into a seat.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Seated {
+    pub name: String,
+    /// Who they signed in as, on a server that signs players in.
+    pub user: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hand {
+    pub table_id: String,
+    pub hand: u64,
+    /// When the hand started and ended, in seconds since the Unix epoch.
+    pub started: u64,
+    pub ended: u64,
+    /// By seat.
+    pub players: Vec<Option<Seated>>,
+    pub game: GameStructure,
+    pub state: CompactState,
+}
+
+/// The hands played, oldest first.
+#[derive(Default)]
+pub struct History {
+    hands: Mutex<Vec<Hand>>,
+    file: Option<Mutex<File>>,
+}
+
+impl History {
+    /// The hands in the file at `path`, which is created if it doesn't
+    /// exist, and added to as more hands are played.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("can't open {}: {}", path.display(), e))?;
+        let mut hands = Vec::new();
+        for (n, line) in BufReader::new(&file).lines().enumerate() {
+            let line = line.map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let hand = decode(&line).map_err(|e| format!("{}:{}: {}", path.display(), n + 1, e))?;
+            hands.push(hand);
+        }
+        Ok(History {
+            hands: Mutex::new(hands),
+            file: Some(Mutex::new(file)),
+        })
+    }
+
+    /// Keeps `hand`, writing it to the file first if there is one.
+    pub fn record(&self, hand: Hand) -> io::Result<()> {
+        if let Some(file) = &self.file {
+            let mut file = file.lock().expect("history file lock");
+            writeln!(file, "{}", encode(&hand))?;
+        }
+        self.hands.lock().expect("history lock").push(hand);
+        Ok(())
+    }
+
+    /// The page of hands `request` asks for, newest first, with their
+    /// events as `user`, if signed in, may see them.
+    pub fn list(
+        &self,
+        request: &ListHandsRequest,
+        user: Option<&str>,
+    ) -> Result<ListHandsResponse, TableError> {
+        let bad = |message: String| TableError::BadRequest(message);
+        let size = match request.page_size {
+            0 => PAGE,
+            n => usize::try_from(n)
+                .map_err(|_| bad("negative page size".to_string()))?
+                .min(MAX_PAGE),
+        };
+        // A page token is the position of the last hand on the page before.
+        let before = match request.page_token.as_str() {
+            "" => usize::MAX,
+            token => token
+                .parse()
+                .map_err(|_| bad(format!("bad page token {:?}", token)))?,
+        };
+        let (page, more) = {
+            let hands = self.hands.lock().expect("history lock");
+            let mut matching = (0..hands.len().min(before))
+                .rev()
+                .filter(|&i| matches(&hands[i], request));
+            let page: Vec<(usize, Hand)> = matching
+                .by_ref()
+                .take(size)
+                .map(|i| (i, hands[i].clone()))
+                .collect();
+            (page, matching.next().is_some())
+        };
+        let next_page_token = match page.last() {
+            Some((last, _)) if more => last.to_string(),
+            _ => String::new(),
+        };
+        Ok(ListHandsResponse {
+            hands: page
+                .iter()
+                .filter_map(|(_, hand)| record(hand, user))
+                .collect(),
+            next_page_token,
+        })
+    }
+}
+
+fn matches(hand: &Hand, request: &ListHandsRequest) -> bool {
+    let started = hand.started as i64;
+    let played = |p: &Seated| p.name == request.player || p.user.as_ref() == Some(&request.player);
+    (request.table_id.is_empty() || hand.table_id == request.table_id)
+        && (request.player.is_empty() || hand.players.iter().flatten().any(played))
+        && started >= request.since
+        && (request.until == 0 || started < request.until)
+}
+
+/// `hand` as `user` may see it: with their own cards if they were dealt
+/// in, and otherwise as a spectator would. A hand that doesn't play back
+/// is left out, with a warning.
+fn record(hand: &Hand, user: Option<&str>) -> Option<HandRecord> {
+    let engine = match hand.state.restore(hand.game.clone()) {
+        Ok(engine) => engine,
+        Err(e) => {
+            eprintln!(
+                "warning: hand {} at table {} doesn't play back: {}",
+                hand.hand, hand.table_id, e
+            );
+            return None;
+        }
+    };
+    let seat = user.and_then(|user| {
+        hand.players
+            .iter()
+            .position(|p| p.as_ref().is_some_and(|p| p.user.as_deref() == Some(user)))
+    });
+    let audience = seat.map_or(Audience::Spectator, Audience::Seat);
+    Some(HandRecord {
+        table_id: hand.table_id.clone(),
+        hand: hand.hand,
+        game_name: hand.game.name.clone(),
+        started: hand.started as i64,
+        ended: hand.ended as i64,
+        players: hand
+            .players
+            .iter()
+            .map(|p| p.as_ref().map(|p| p.name.clone()).unwrap_or_default())
+            .collect(),
+        button: engine.button() as i32,
+        stacks: engine.config().stacks.iter().map(|&s| s as i64).collect(),
+        results: engine.seats().iter().map(|s| s.stack as i64).collect(),
+        events: engine
+            .events()
+            .iter()
+            .map(|e| e.message_for(audience))
+            .collect(),
+        seat: seat.map_or(-1, |s| s as i32),
+    })
+}
+
+/// `hand` as a line of the history file, a [`HandFile`] as the CLI would
+/// save it, so that `snapfold replay` can play it back.
+fn encode(hand: &Hand) -> String {
+    let file = HandFile {
+        game: hand.game.clone(),
+        names: hand
+            .players
+            .iter()
+            .map(|p| p.as_ref().map(|p| p.name.clone()).unwrap_or_default())
+            .collect(),
+        hand: hand.state.clone(),
+        table: Some(history::Table {
+            id: hand.table_id.clone(),
+            hand: hand.hand,
+            users: hand
+                .players
+                .iter()
+                .map(|p| p.as_ref().and_then(|p| p.user.clone()))
+                .collect(),
+        }),
+        time: Some(Time {
+            started: hand.started,
+            ended: hand.ended,
+        }),
+    };
+    file.to_json().to_string()
+}
+
+/// A line of the history file, checking that the hand plays back.
+fn decode(line: &str) -> Result<Hand, String> {
+    let file = HandFile::parse(line)?;
+    let table = file.table.ok_or("no \"table\" field")?;
+    let time = file.time.ok_or("no \"time\" field")?;
+    file.hand
+        .restore(file.game.clone())
+        .map_err(|e| format!("the hand doesn't play back: {}", e))?;
+    let players = file
+        .names
+        .into_iter()
+        .enumerate()
+        .map(|(seat, name)| {
+            let user = table.users.get(seat).cloned().flatten();
+            (!name.is_empty() || user.is_some()).then_some(Seated { name, user })
+        })
+        .collect();
+    Ok(Hand {
+        table_id: table.id,
+        hand: table.hand,
+        started: time.started,
+        ended: time.ended,
+        players,
+        game: file.game,
+        state: file.hand,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::Limits;
+    use crate::table::{self, Settings, Table, Tables};
+    use rustlib::engine::{Action, GameEngine};
+    use rustlib::game_event;
+    use rustlib::presets;
+    use service_proto3::snapfold::server::create_table_request::Timeout;
+    use std::time::Duration;
+
+    /// Opens a heads-up table for `players`, signed in under their names,
+    /// and plays `hands` hands there, each won when the first to act folds.
+    fn play(tables: &Tables, players: [&str; 2], hands: usize) -> String {
+        let settings = Settings {
+            seats: 2,
+            stack: 100,
+            blinds: vec![1, 2],
+            ante: 0,
+            start: 2,
+            timeout: Duration::from_secs(30),
+            on_timeout: Timeout::CheckOrFold,
+        };
+        let id = tables.open(Table::new(presets::holdem(), settings).unwrap());
+        let table = tables.get(&id).unwrap();
+        let mut table = table::lock(&table);
+        let ids: Vec<String> = players
+            .iter()
+            .map(|&p| table.join(p, Some(p)).unwrap().1)
+            .collect();
+        for _ in 0..hands {
+            // Only the player whose turn it is may fold.
+            assert!(ids.iter().any(|id| table.act(id, Action::Fold).is_ok()));
+        }
+        id
+    }
+
+    fn hands(response: &ListHandsResponse) -> Vec<(&str, u64)> {
+        response
+            .hands
+            .iter()
+            .map(|h| (h.table_id.as_str(), h.hand))
+            .collect()
+    }
+
+    #[test]
+    fn test_list() {
+        let tables = Tables::new(History::default(), &Limits::default());
+        let t1 = play(&tables, ["ann", "bob"], 3);
+        let t2 = play(&tables, ["ann", "cy"], 2);
+        let list = |request: ListHandsRequest| tables.hands(&request, None).unwrap();
+
+        let all = list(ListHandsRequest::default());
+        assert_eq!(
+            hands(&all),
+            [(&*t2, 2), (&*t2, 1), (&*t1, 3), (&*t1, 2), (&*t1, 1)]
+        );
+        assert_eq!(all.next_page_token, "");
+        let hand = &all.hands[0];
+        assert_eq!(hand.game_name, presets::holdem().name);
+        assert_eq!(hand.players, ["ann", "cy"]);
+        assert!(hand.started <= hand.ended);
+        assert_eq!(
+            hand.results.iter().sum::<i64>(),
+            hand.stacks.iter().sum::<i64>()
+        );
+
+        let count = |request| list(request).hands.len();
+        let table_id = t1.clone();
+        assert_eq!(
+            count(ListHandsRequest {
+                table_id,
+                ..Default::default()
+            }),
+            3
+        );
+        for (player, played) in [("ann", 5), ("bob", 3), ("cy", 2), ("dee", 0)] {
+            let player = player.to_string();
+            assert_eq!(
+                count(ListHandsRequest {
+                    player,
+                    ..Default::default()
+                }),
+                played
+            );
+        }
+        let now = hand.started;
+        let since = |since, until| {
+            count(ListHandsRequest {
+                since,
+                until,
+                ..Default::default()
+            })
+        };
+        assert_eq!(since(0, now + 1), 5);
+        assert_eq!(since(now + 1, 0), 0);
+        assert_eq!(since(0, now), 0);
+
+        // Pages follow on from each other's tokens.
+        let mut paged = Vec::new();
+        let mut page_token = String::new();
+        loop {
+            let page = list(ListHandsRequest {
+                page_size: 2,
+                page_token,
+                ..Default::default()
+            });
+            assert!(page.hands.len() <= 2);
+            paged.extend(page.hands);
+            if page.next_page_token.is_empty() {
+                break;
+            }
+            page_token = page.next_page_token;
+        }
+        assert_eq!(paged, all.hands);
+        for bad in [
+            ListHandsRequest {
+                page_size: -1,
+                ..Default::default()
+            },
+            ListHandsRequest {
+                page_token: "x".to_string(),
+                ..Default::default()
+            },
+        ] {
+            assert!(matches!(
+                tables.hands(&bad, None),
+                Err(TableError::BadRequest(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_visibility() {
+        let tables = Tables::new(History::default(), &Limits::default());
+        let t1 = play(&tables, ["ann", "bob"], 1);
+        let request = ListHandsRequest {
+            table_id: t1,
+            ..Default::default()
+        };
+        // How many of each seat's cards were shown, and how many hidden.
+        let holes = |user| {
+            let response = tables.hands(&request, user).unwrap();
+            let hand = &response.hands[0];
+            let mut holes: Vec<(i32, usize, usize)> = hand
+                .events
+                .iter()
+                .filter_map(|e| match &e.kind {
+                    Some(game_event::Kind::HoleCardsDealt(hole)) => {
+                        Some((hole.seat, hole.cards.len(), hole.hidden as usize))
+                    }
+                    _ => None,
+                })
+                .collect();
+            holes.sort();
+            (hand.seat, holes)
+        };
+        assert_eq!(holes(Some("bob")), (1, vec![(0, 0, 2), (1, 2, 0)]));
+        assert_eq!(holes(Some("ann")), (0, vec![(0, 2, 0), (1, 0, 2)]));
+        for user in [None, Some("cy")] {
+            assert_eq!(holes(user), (-1, vec![(0, 0, 2), (1, 0, 2)]));
+        }
+    }
+
+    #[test]
+    fn test_file() {
+        let path = std::env::temp_dir().join(format!("snapfold-history-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut engine = GameEngine::new(presets::holdem(), 2).unwrap();
+        while let Some(seat) = engine.to_act() {
+            engine.apply_action(seat, Action::Fold).unwrap();
+        }
+        let hand = Hand {
+            table_id: "t1".to_string(),
+            hand: 1,
+            started: 1_700_000_000,
+            ended: 1_700_000_060,
+            players: vec![
+                Some(Seated {
+                    name: "ann".to_string(),
+                    user: Some("ann".to_string()),
+                }),
+                Some(Seated {
+                    name: "bob".to_string(),
+                    user: None,
+                }),
+            ],
+            game: presets::holdem(),
+            state: CompactState::capture(&engine).unwrap(),
+        };
+        assert_eq!(decode(&encode(&hand)), Ok(hand.clone()));
+
+        let history = History::open(&path).unwrap();
+        history.record(hand.clone()).unwrap();
+        drop(history);
+        let history = History::open(&path).unwrap();
+        assert_eq!(*history.hands.lock().unwrap(), std::slice::from_ref(&hand));
+        let listed = history
+            .list(&ListHandsRequest::default(), Some("ann"))
+            .unwrap();
+        assert_eq!(listed.hands[0].seat, 0);
+        drop(history);
+
+        std::fs::write(&path, format!("{}\n{{\"players\": 1}}\n", encode(&hand))).unwrap();
+        let error = History::open(&path).err().unwrap();
+        assert!(error.ends_with(":2: players: expected a list"), "{}", error);
+        let mut other = hand.clone();
+        other.state = CompactState::from_bytes(&[0]);
+        std::fs::write(&path, format!("\n{}\n", encode(&other))).unwrap();
+        let error = History::open(&path).err().unwrap();
+        assert!(
+            error.starts_with(&format!(
+                "{}:2: the hand doesn't play back: ",
+                path.display()
+            )),
+            "{}",
+            error
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        // One kept in memory is left out of the list.
+        let history = History::default();
+        history.record(other).unwrap();
+        history.record(hand.clone()).unwrap();
+        let listed = history.list(&ListHandsRequest::default(), None).unwrap();
+        assert_eq!(listed.hands.len(), 1);
+    }
+}
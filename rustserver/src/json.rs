@@ -0,0 +1,735 @@
+//! WebSocket messages in the canonical protobuf JSON mapping, for clients
+//! on the `snapfold.json` subprotocol: lowerCamelCase keys, enums by name,
+//! 64-bit integers and bytes as strings, and default values left out
+//! except for the members of oneofs.
+//!
+//! Decoding also accepts snake_case keys, enum numbers and numeric 64-bit
+//! integers. A game is read as a game definition file is, so definitions
+//! written for older schemas are upgraded.
+
+use rustlib::events;
+use rustlib::gamedef::json::{self, Value};
+use rustlib::gamedef::protojson::{
+    boolean, enumeration, fields, int, int64, items, set_oneof, string, uint, uint64, unknown,
+    Object, Result,
+};
+use rustlib::gamedef::{codec, migrations, params};
+use rustlib::phase::declare::Side;
+use rustlib::player_action::Kind as ActionKind;
+use rustlib::{Card, PlayerAction};
+use service_proto3::snapfold::server::{
+    chat, client_message, create_table_request, reply, server_message, table_event,
+    AuthenticateRequest, ClientMessage, CreateTableRequest, HandRecord, JoinTableRequest,
+    LeaveTableRequest, ListHandsRequest, ListTablesRequest, MuteRequest, PingRequest, Reply,
+    ReserveSeatRequest, SendChatRequest, ServerMessage, StreamEventsRequest, SubmitActionRequest,
+    TableEvent, TableSnapshot, TableSummary, TurnToAct,
+};
+
+/// A client message that couldn't be read, and the id to answer with if
+/// the message had one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BadMessage {
+    pub id: u64,
+    pub message: String,
+}
+
+fn action_kind(value: i32) -> Option<&'static str> {
+    ActionKind::try_from(value).ok().map(|k| k.as_str_name())
+}
+
+fn emote(value: i32) -> Option<&'static str> {
+    chat::Emote::try_from(value).ok().map(|e| e.as_str_name())
+}
+
+fn int64s(values: &[i64]) -> Vec<Value> {
+    values
+        .iter()
+        .map(|v| Value::String(v.to_string()))
+        .collect()
+}
+
+/// `message` as one line of JSON.
+pub fn encode(message: &ServerMessage) -> String {
+    let obj = match &message.kind {
+        Some(server_message::Kind::Reply(reply)) => {
+            Object::default().field("reply", encode_reply(reply))
+        }
+        Some(server_message::Kind::Event(event)) => {
+            Object::default().field("event", encode_event(event))
+        }
+        None => Object::default(),
+    };
+    obj.string("tableId", &message.table_id).build().to_string()
+}
+
+fn encode_reply(reply: &Reply) -> Value {
+    let obj = Object::default()
+        .uint64("id", reply.id)
+        .string("error", &reply.error);
+    match &reply.result {
+        Some(reply::Result::Created(created)) => obj.field(
+            "created",
+            Object::default()
+                .string("tableId", &created.table_id)
+                .build(),
+        ),
+        Some(reply::Result::Joined(joined)) => obj.field(
+            "joined",
+            Object::default()
+                .int("seat", joined.seat)
+                .string("playerId", &joined.player_id)
+                .int("waiting", joined.waiting)
+                .build(),
+        ),
+        Some(reply::Result::Tables(list)) => obj.field(
+            "tables",
+            Object::default()
+                .list("tables", list.tables.iter().map(encode_summary).collect())
+                .build(),
+        ),
+        Some(reply::Result::Reserved(reserved)) => obj.field(
+            "reserved",
+            Object::default()
+                .int("seat", reserved.seat)
+                .string("reservation", &reserved.reservation)
+                .int("seconds", reserved.seconds)
+                .build(),
+        ),
+        Some(reply::Result::Hands(list)) => obj.field(
+            "hands",
+            Object::default()
+                .list("hands", list.hands.iter().map(encode_hand).collect())
+                .string("nextPageToken", &list.next_page_token)
+                .build(),
+        ),
+        None => obj,
+    }
+    .build()
+}
+
+fn encode_hand(hand: &HandRecord) -> Value {
+    Object::default()
+        .string("tableId", &hand.table_id)
+        .uint64("hand", hand.hand)
+        .string("gameName", &hand.game_name)
+        .int64("started", hand.started)
+        .int64("ended", hand.ended)
+        .list(
+            "players",
+            hand.players
+                .iter()
+                .map(|p| Value::String(p.clone()))
+                .collect(),
+        )
+        .int("button", hand.button)
+        .list("stacks", int64s(&hand.stacks))
+        .list("results", int64s(&hand.results))
+        .list("events", hand.events.iter().map(events::encode).collect())
+        .int("seat", hand.seat)
+        .build()
+}
+
+fn encode_summary(table: &TableSummary) -> Value {
+    Object::default()
+        .string("tableId", &table.table_id)
+        .string("gameId", &table.game_id)
+        .string("gameName", &table.game_name)
+        .int("seats", table.seats)
+        .int("seated", table.seated)
+        .int("reserved", table.reserved)
+        .int("waiting", table.waiting)
+        .int64("stack", table.stack)
+        .list("blinds", int64s(&table.blinds))
+        .int64("ante", table.ante)
+        .int("start", table.start)
+        .uint64("hands", table.hands)
+        .build()
+}
+
+fn encode_event(event: &TableEvent) -> Value {
+    use table_event::Kind;
+    let obj = Object::default().uint64("sequence", event.sequence);
+    let Some(kind) = &event.kind else {
+        return obj.build();
+    };
+    let (key, value) = match kind {
+        Kind::SeatTaken(taken) => (
+            "seatTaken",
+            Object::default()
+                .int("seat", taken.seat)
+                .string("name", &taken.name)
+                .int64("stack", taken.stack)
+                .bool("yours", taken.yours),
+        ),
+        Kind::HandStarted(started) => (
+            "handStarted",
+            Object::default()
+                .uint64("hand", started.hand)
+                .int("button", started.button)
+                .list("stacks", int64s(&started.stacks)),
+        ),
+        Kind::Game(game) => return obj.field("game", events::encode(game)).build(),
+        Kind::Turn(turn) => ("turn", encode_turn(turn)),
+        Kind::HandEnded(ended) => (
+            "handEnded",
+            Object::default().list("stacks", int64s(&ended.stacks)),
+        ),
+        Kind::HoleCards(hole) => ("holeCards", events::encode_hole(hole)),
+        Kind::SeatLeft(seat) => {
+            return obj.field("seatLeft", Value::Number((*seat).into())).build();
+        }
+        Kind::Presence(presence) => (
+            "presence",
+            Object::default()
+                .int("seat", presence.seat)
+                .bool("away", presence.away),
+        ),
+        Kind::Snapshot(snapshot) => ("snapshot", encode_snapshot(snapshot)),
+        Kind::Chat(chat) => (
+            "chat",
+            Object::default()
+                .string("name", &chat.name)
+                .int("seat", chat.seat)
+                .string("text", &chat.text)
+                .enumeration("emote", chat.emote, emote)
+                .bool("muted", chat.muted),
+        ),
+    };
+    obj.field(key, value.build()).build()
+}
+
+fn encode_turn(turn: &TurnToAct) -> Object {
+    Object::default()
+        .int("seat", turn.seat)
+        .list(
+            "legal",
+            turn.legal
+                .iter()
+                .map(|legal| {
+                    Object::default()
+                        .enumeration("kind", legal.kind, action_kind)
+                        .int64("min", legal.min)
+                        .int64("max", legal.max)
+                        .int("count", legal.count)
+                        .build()
+                })
+                .collect(),
+        )
+        .uint("timeMs", turn.time_ms)
+}
+
+fn encode_snapshot(snapshot: &TableSnapshot) -> Object {
+    let seats = snapshot
+        .seats
+        .iter()
+        .map(|seat| {
+            Object::default()
+                .string("name", &seat.name)
+                .int64("stack", seat.stack)
+                .bool("dealt", seat.dealt)
+                .bool("folded", seat.folded)
+                .message(
+                    "cards",
+                    seat.cards.as_ref().map(|h| events::encode_hole(h).build()),
+                )
+                .bool("away", seat.away)
+                .bool("reserved", seat.reserved)
+                .build()
+        })
+        .collect();
+    let boards = snapshot
+        .boards
+        .iter()
+        .map(|b| {
+            Object::default()
+                .int("board", b.board)
+                .list("cards", events::encode_cards(&b.cards))
+                .build()
+        })
+        .collect();
+    Object::default()
+        .uint64("hands", snapshot.hands)
+        .bool("playing", snapshot.playing)
+        .int("button", snapshot.button)
+        .list("seats", seats)
+        .list("boards", boards)
+        .int64("pot", snapshot.pot)
+        .message(
+            "turn",
+            snapshot.turn.as_ref().map(|t| encode_turn(t).build()),
+        )
+}
+
+/// Reads a client message from the text of a frame.
+pub fn decode(text: &str) -> std::result::Result<ClientMessage, BadMessage> {
+    let value = json::parse(text).map_err(|e| BadMessage {
+        id: 0,
+        message: e.to_string(),
+    })?;
+    // Find the id first, so that any other problem can be answered.
+    let id = fields(&value, "")
+        .ok()
+        .and_then(|fields| fields.into_iter().find(|(name, _, _)| name == "id"))
+        .and_then(|(_, v, path)| uint64(v, &path).ok())
+        .unwrap_or(0);
+    decode_client_message(&value).map_err(|e| BadMessage {
+        id,
+        message: e.to_string(),
+    })
+}
+
+fn decode_client_message(value: &Value) -> Result<ClientMessage> {
+    use client_message::Kind;
+    let mut message = ClientMessage::default();
+    for (name, v, path) in fields(value, "")? {
+        let kind = match name.as_str() {
+            "id" => {
+                message.id = uint64(v, &path)?;
+                continue;
+            }
+            "create_table" => Kind::CreateTable(decode_create_table(v, &path)?),
+            "join_table" => {
+                let mut join = JoinTableRequest::default();
+                for (name, v, path) in fields(v, &path)? {
+                    match name.as_str() {
+                        "table_id" => join.table_id = string(v, &path)?,
+                        "name" => join.name = string(v, &path)?,
+                        "reservation" => join.reservation = string(v, &path)?,
+                        "wait" => join.wait = boolean(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                Kind::JoinTable(join)
+            }
+            "subscribe" => {
+                let mut subscribe = StreamEventsRequest::default();
+                for (name, v, path) in fields(v, &path)? {
+                    match name.as_str() {
+                        "table_id" => subscribe.table_id = string(v, &path)?,
+                        "player_id" => subscribe.player_id = string(v, &path)?,
+                        "after" => subscribe.after = uint64(v, &path)?,
+                        "delay_seconds" => subscribe.delay_seconds = int(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                Kind::Subscribe(subscribe)
+            }
+            "submit_action" => {
+                let mut submit = SubmitActionRequest::default();
+                for (name, v, path) in fields(v, &path)? {
+                    match name.as_str() {
+                        "table_id" => submit.table_id = string(v, &path)?,
+                        "player_id" => submit.player_id = string(v, &path)?,
+                        "action" => submit.action = Some(decode_action(v, &path)?),
+                        _ => return unknown(&path),
+                    }
+                }
+                Kind::SubmitAction(submit)
+            }
+            "list_tables" => {
+                let mut list = ListTablesRequest::default();
+                for (name, v, path) in fields(v, &path)? {
+                    match name.as_str() {
+                        "game_id" => list.game_id = string(v, &path)?,
+                        "open" => list.open = boolean(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                Kind::ListTables(list)
+            }
+            "reserve_seat" => {
+                let mut reserve = ReserveSeatRequest::default();
+                for (name, v, path) in fields(v, &path)? {
+                    match name.as_str() {
+                        "table_id" => reserve.table_id = string(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                Kind::ReserveSeat(reserve)
+            }
+            "leave_table" => {
+                let mut leave = LeaveTableRequest::default();
+                for (name, v, path) in fields(v, &path)? {
+                    match name.as_str() {
+                        "table_id" => leave.table_id = string(v, &path)?,
+                        "player_id" => leave.player_id = string(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                Kind::LeaveTable(leave)
+            }
+            "list_hands" => {
+                let mut list = ListHandsRequest::default();
+                for (name, v, path) in fields(v, &path)? {
+                    match name.as_str() {
+                        "table_id" => list.table_id = string(v, &path)?,
+                        "player" => list.player = string(v, &path)?,
+                        "since" => list.since = int64(v, &path)?,
+                        "until" => list.until = int64(v, &path)?,
+                        "page_size" => list.page_size = int(v, &path)?,
+                        "page_token" => list.page_token = string(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                Kind::ListHands(list)
+            }
+            "send_chat" => {
+                let mut send = SendChatRequest::default();
+                for (name, v, path) in fields(v, &path)? {
+                    match name.as_str() {
+                        "table_id" => send.table_id = string(v, &path)?,
+                        "player_id" => send.player_id = string(v, &path)?,
+                        "text" => send.text = string(v, &path)?,
+                        "emote" => {
+                            send.emote = enumeration(v, &path, |name| {
+                                chat::Emote::from_str_name(name).map(i32::from)
+                            })?
+                        }
+                        _ => return unknown(&path),
+                    }
+                }
+                Kind::SendChat(send)
+            }
+            "mute" => {
+                let mut mute = MuteRequest::default();
+                for (name, v, path) in fields(v, &path)? {
+                    match name.as_str() {
+                        "table_id" => mute.table_id = string(v, &path)?,
+                        "player_id" => mute.player_id = string(v, &path)?,
+                        "name" => mute.name = string(v, &path)?,
+                        "muted" => mute.muted = boolean(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                Kind::Mute(mute)
+            }
+            "ping" => {
+                let mut ping = PingRequest::default();
+                for (name, v, path) in fields(v, &path)? {
+                    match name.as_str() {
+                        "table_id" => ping.table_id = string(v, &path)?,
+                        "player_id" => ping.player_id = string(v, &path)?,
+                        "round_trip_ms" => ping.round_trip_ms = uint(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                Kind::Ping(ping)
+            }
+            "authenticate" => {
+                let mut authenticate = AuthenticateRequest::default();
+                for (name, v, path) in fields(v, &path)? {
+                    match name.as_str() {
+                        "token" => authenticate.token = string(v, &path)?,
+                        _ => return unknown(&path),
+                    }
+                }
+                Kind::Authenticate(authenticate)
+            }
+            _ => return unknown(&path),
+        };
+        set_oneof(&mut message.kind, kind, &path)?;
+    }
+    Ok(message)
+}
+
+fn decode_create_table(value: &Value, path: &str) -> Result<CreateTableRequest> {
+    let mut create = CreateTableRequest::default();
+    for (name, v, path) in fields(value, path)? {
+        match name.as_str() {
+            "game" => {
+                let mut game = v.clone();
+                let decoded = migrations::upgrade(&mut game)
+                    .and_then(|()| params::instantiate(&mut game, &[]))
+                    .and_then(|()| codec::decode(&game))
+                    .map_err(|mut e| {
+                        e.path = match e.path.as_str() {
+                            "" => path.clone(),
+                            inner => format!("{}.{}", path, inner),
+                        };
+                        e
+                    })?;
+                create.game = Some(decoded);
+            }
+            "seats" => create.seats = int(v, &path)?,
+            "stack" => create.stack = int64(v, &path)?,
+            "blinds" => {
+                create.blinds = items(v, &path)?
+                    .into_iter()
+                    .map(|(v, p)| int64(v, &p))
+                    .collect::<Result<_>>()?
+            }
+            "ante" => create.ante = int64(v, &path)?,
+            "start" => create.start = int(v, &path)?,
+            "timeout_seconds" => create.timeout_seconds = int(v, &path)?,
+            "on_timeout" => {
+                create.on_timeout = enumeration(v, &path, |name| {
+                    create_table_request::Timeout::from_str_name(name).map(i32::from)
+                })?
+            }
+            _ => return unknown(&path),
+        }
+    }
+    Ok(create)
+}
+
+fn decode_action(value: &Value, path: &str) -> Result<PlayerAction> {
+    let mut action = PlayerAction::default();
+    for (name, v, path) in fields(value, path)? {
+        match name.as_str() {
+            "kind" => {
+                action.kind = enumeration(v, &path, |name| {
+                    ActionKind::from_str_name(name).map(i32::from)
+                })?
+            }
+            "amount" => action.amount = int64(v, &path)?,
+            "cards" => {
+                for (v, path) in items(v, &path)? {
+                    let mut card = Card::default();
+                    for (name, v, path) in fields(v, &path)? {
+                        match name.as_str() {
+                            "rank" => card.rank = int(v, &path)?,
+                            "suit" => card.suit = int(v, &path)?,
+                            _ => return unknown(&path),
+                        }
+                    }
+                    action.cards.push(card);
+                }
+            }
+            "side" => {
+                action.side =
+                    enumeration(v, &path, |name| Side::from_str_name(name).map(i32::from))?
+            }
+            _ => return unknown(&path),
+        }
+    }
+    Ok(action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustlib::{game_event, Acted, GameEvent, ShuffleRevealed};
+    use service_proto3::snapfold::server::{
+        Chat, CreateTableResponse, HandStarted, ListHandsResponse, Presence,
+    };
+
+    #[test]
+    fn test_decode() {
+        let message = decode(
+            r#"{"id": "7", "submitAction": {"tableId": "t1", "player_id": "abc",
+                "action": {"kind": "KIND_DISCARD", "cards": [{"rank": 12}, {"rank": 3, "suit": 2}]}}}"#,
+        )
+        .unwrap();
+        assert_eq!(message.id, 7);
+        let Some(client_message::Kind::SubmitAction(submit)) = message.kind else {
+            panic!("{:?}", message.kind);
+        };
+        assert_eq!(submit.table_id, "t1");
+        assert_eq!(submit.player_id, "abc");
+        let action = submit.action.unwrap();
+        assert_eq!(action.kind(), ActionKind::Discard);
+        assert_eq!(
+            action.cards,
+            [Card { rank: 12, suit: 0 }, Card { rank: 3, suit: 2 }]
+        );
+
+        let game = rustlib::gamedef::to_json(&rustlib::presets::holdem());
+        let message = decode(&format!(
+            r#"{{"id": 1, "createTable": {{"game": {}, "seats": 6, "stack": "200", "blinds": [1, 2],
+                "timeoutSeconds": 10, "onTimeout": "TIMEOUT_FOLD"}}}}"#,
+            game
+        ))
+        .unwrap();
+        let Some(client_message::Kind::CreateTable(create)) = message.kind else {
+            panic!("{:?}", message.kind);
+        };
+        assert_eq!(create.game, Some(rustlib::presets::holdem()));
+        assert_eq!(create.timeout_seconds, 10);
+        assert_eq!(create.on_timeout(), create_table_request::Timeout::Fold);
+        assert_eq!(
+            (create.seats, create.stack, create.blinds),
+            (6, 200, vec![1, 2])
+        );
+
+        let message = decode(r#"{"id": 5, "listTables": {"gameId": "holdem", "open": true}}"#);
+        assert_eq!(
+            message.unwrap().kind,
+            Some(client_message::Kind::ListTables(ListTablesRequest {
+                game_id: "holdem".to_string(),
+                open: true,
+            }))
+        );
+
+        let message = decode(
+            r#"{"id": 6, "listHands": {"player": "ann", "since": "1700000000", "pageSize": 5,
+                "pageToken": "12"}}"#,
+        );
+        assert_eq!(
+            message.unwrap().kind,
+            Some(client_message::Kind::ListHands(ListHandsRequest {
+                player: "ann".to_string(),
+                since: 1_700_000_000,
+                page_size: 5,
+                page_token: "12".to_string(),
+                ..Default::default()
+            }))
+        );
+
+        let message = decode(
+            r#"{"id": 7, "sendChat": {"tableId": "t1", "playerId": "abc", "text": "gl",
+                "emote": "EMOTE_GOOD_LUCK"}}"#,
+        );
+        assert_eq!(
+            message.unwrap().kind,
+            Some(client_message::Kind::SendChat(SendChatRequest {
+                table_id: "t1".to_string(),
+                player_id: "abc".to_string(),
+                text: "gl".to_string(),
+                emote: chat::Emote::GoodLuck.into(),
+            }))
+        );
+
+        let message = decode(r#"{"id": 8, "ping": {"tableId": "t1", "roundTripMs": 40}}"#);
+        assert_eq!(
+            message.unwrap().kind,
+            Some(client_message::Kind::Ping(PingRequest {
+                table_id: "t1".to_string(),
+                round_trip_ms: 40,
+                ..Default::default()
+            }))
+        );
+
+        let bad = |text| decode(text).unwrap_err();
+        assert_eq!(bad("{").id, 0);
+        assert_eq!(
+            bad(r#"{"id": 3, "joinTable": {"seat": 1}}"#),
+            BadMessage {
+                id: 3,
+                message: "joinTable.seat: unknown field".to_string()
+            }
+        );
+        assert_eq!(
+            bad(r#"{"id": 4, "joinTable": {}, "subscribe": {}}"#).message,
+            "subscribe: only one of these fields may be set"
+        );
+        assert!(bad(r#"{"submitAction": {"action": {"kind": "KIND_RUN"}}}"#)
+            .message
+            .starts_with("submitAction.action.kind: unknown value"));
+    }
+
+    #[test]
+    fn test_encode() {
+        let reply = ServerMessage {
+            kind: Some(server_message::Kind::Reply(Reply {
+                id: 2,
+                error: String::new(),
+                result: Some(reply::Result::Created(CreateTableResponse {
+                    table_id: "t1".to_string(),
+                })),
+            })),
+            table_id: String::new(),
+        };
+        assert_eq!(
+            encode(&reply),
+            r#"{"reply":{"id":"2","created":{"tableId":"t1"}}}"#
+        );
+        let hands = ServerMessage {
+            kind: Some(server_message::Kind::Reply(Reply {
+                id: 3,
+                error: String::new(),
+                result: Some(reply::Result::Hands(ListHandsResponse {
+                    hands: vec![HandRecord {
+                        table_id: "t1".to_string(),
+                        hand: 4,
+                        players: vec!["ann".to_string(), String::new()],
+                        results: vec![90, 110],
+                        events: vec![GameEvent {
+                            kind: Some(game_event::Kind::PhaseStarted(0)),
+                        }],
+                        seat: -1,
+                        ..Default::default()
+                    }],
+                    next_page_token: "9".to_string(),
+                })),
+            })),
+            table_id: String::new(),
+        };
+        assert_eq!(
+            encode(&hands),
+            r#"{"reply":{"id":"3","hands":{"hands":[{"tableId":"t1","hand":"4","players":["ann",""],"results":["90","110"],"events":[{"phaseStarted":0}],"seat":-1}],"nextPageToken":"9"}}}"#
+        );
+
+        let event = |sequence, kind| ServerMessage {
+            kind: Some(server_message::Kind::Event(TableEvent {
+                sequence,
+                kind: Some(kind),
+            })),
+            table_id: "t1".to_string(),
+        };
+        let game = |kind| table_event::Kind::Game(GameEvent { kind: Some(kind) });
+        assert_eq!(
+            encode(&event(
+                1,
+                table_event::Kind::HandStarted(HandStarted {
+                    hand: 1,
+                    button: 0,
+                    stacks: vec![100, 0],
+                })
+            )),
+            r#"{"event":{"sequence":"1","handStarted":{"hand":"1","stacks":["100","0"]}},"tableId":"t1"}"#
+        );
+        // Oneof members are written even at their defaults.
+        assert_eq!(
+            encode(&event(2, game(game_event::Kind::PhaseStarted(0)))),
+            r#"{"event":{"sequence":"2","game":{"phaseStarted":0}},"tableId":"t1"}"#
+        );
+        assert_eq!(
+            encode(&event(
+                3,
+                game(game_event::Kind::ShuffleRevealed(ShuffleRevealed {
+                    seed: 9,
+                    salt: b"salt".to_vec(),
+                }))
+            )),
+            r#"{"event":{"sequence":"3","game":{"shuffleRevealed":{"seed":"9","salt":"c2FsdA=="}}},"tableId":"t1"}"#
+        );
+        let raise = PlayerAction {
+            kind: ActionKind::Raise.into(),
+            amount: 6,
+            ..Default::default()
+        };
+        assert_eq!(
+            encode(&event(
+                4,
+                game(game_event::Kind::Acted(Acted {
+                    seat: 1,
+                    action: Some(raise),
+                }))
+            )),
+            r#"{"event":{"sequence":"4","game":{"acted":{"seat":1,"action":{"kind":"KIND_RAISE","amount":"6"}}}},"tableId":"t1"}"#
+        );
+        assert_eq!(
+            encode(&event(
+                5,
+                table_event::Kind::Presence(Presence {
+                    seat: 1,
+                    away: true,
+                })
+            )),
+            r#"{"event":{"sequence":"5","presence":{"seat":1,"away":true}},"tableId":"t1"}"#
+        );
+        assert_eq!(
+            encode(&event(
+                6,
+                table_event::Kind::Chat(Chat {
+                    name: "ann".to_string(),
+                    seat: -1,
+                    text: "nh".to_string(),
+                    emote: chat::Emote::NiceHand.into(),
+                    muted: false,
+                })
+            )),
+            r#"{"event":{"sequence":"6","chat":{"name":"ann","seat":-1,"text":"nh","emote":"EMOTE_NICE_HAND"}},"tableId":"t1"}"#
+        );
+    }
+}
@@ -0,0 +1,323 @@
+//! Limits on how fast clients may send the requests that cost a table the
+//! most, so one misbehaving client can't slow play for everyone else.
+//!
+//! Each connection, and each signed-in player across all their
+//! connections, has a budget for each kind of request that refills at a
+//! steady rate. A request over any of its budgets is turned down, and a
+//! client turned down too many times in a row is shut out of limited
+//! requests for a while.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::table::TableError;
+
+/// The kinds of request that are limited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Action,
+    Chat,
+    Table,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Action => "actions",
+            Kind::Chat => "chat messages",
+            Kind::Table => "tables",
+        }
+    }
+}
+
+/// Who a budget belongs to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Who {
+    Connection(SocketAddr),
+    Player(String),
+}
+
+/// The budgets a request from `peer`, signed in as `user`, counts against.
+pub fn who(peer: Option<SocketAddr>, user: Option<&str>) -> Vec<Who> {
+    let peer = peer.map(Who::Connection);
+    let user = user.map(|user| Who::Player(user.to_string()));
+    peer.into_iter().chain(user).collect()
+}
+
+/// `count` requests every `per`, in bursts of up to `count`; or any
+/// number if `count` is 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rate {
+    pub count: u32,
+    pub per: Duration,
+}
+
+impl Rate {
+    /// A rate written `COUNT/SECONDS`.
+    fn parse(text: &str) -> Option<Rate> {
+        let (count, seconds) = text.split_once('/')?;
+        let count = count.parse().ok()?;
+        let per = Duration::from_secs(seconds.parse().ok()?);
+        (per > Duration::ZERO).then_some(Rate { count, per })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Limits {
+    pub actions: Rate,
+    pub chat: Rate,
+    pub tables: Rate,
+    /// Requests turned down in a row before a client is shut out, or 0
+    /// to never shut anyone out, and for how long.
+    pub strikes: u32,
+    pub penalty: Duration,
+    /// Turns in a row a player may take longer than the table's timeout
+    /// before they are put on the clock, and acted for whenever they take
+    /// that long again until they act in time; or 0 to never.
+    pub slow_turns: u32,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            actions: Rate {
+                count: 10,
+                per: Duration::from_secs(1),
+            },
+            chat: Rate {
+                count: 5,
+                per: Duration::from_secs(10),
+            },
+            tables: Rate {
+                count: 5,
+                per: Duration::from_secs(60),
+            },
+            strikes: 10,
+            penalty: Duration::from_secs(60),
+            slow_turns: 3,
+        }
+    }
+}
+
+impl Limits {
+    /// Sets the rate for a kind of request from `KIND=COUNT/SECONDS`.
+    pub fn set(&mut self, limit: &str) -> Result<(), String> {
+        let bad = || format!("{:?} isn't a limit like actions=10/1", limit);
+        let (kind, rate) = limit.split_once('=').ok_or_else(bad)?;
+        let rate = Rate::parse(rate).ok_or_else(bad)?;
+        match kind {
+            "actions" => self.actions = rate,
+            "chat" => self.chat = rate,
+            "tables" => self.tables = rate,
+            _ => {
+                return Err(format!(
+                    "no limit on {:?}; there are limits on actions, chat and tables",
+                    kind
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn rate(&self, kind: Kind) -> Rate {
+        match kind {
+            Kind::Action => self.actions,
+            Kind::Chat => self.chat,
+            Kind::Table => self.tables,
+        }
+    }
+}
+
+/// What is left of a budget, as of `at`.
+struct Bucket {
+    left: f64,
+    at: Instant,
+}
+
+#[derive(Default)]
+struct Record {
+    buckets: HashMap<Kind, Bucket>,
+    /// Requests turned down in a row.
+    strikes: u32,
+    shut_out: Option<Instant>,
+}
+
+/// Every client's budgets, shared by the servers for each protocol.
+#[derive(Clone, Default)]
+pub struct Limiter {
+    limits: Limits,
+    clients: Arc<Mutex<HashMap<Who, Record>>>,
+}
+
+impl Limiter {
+    pub fn new(limits: Limits) -> Self {
+        Limiter {
+            limits,
+            ..Limiter::default()
+        }
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Counts a request of `kind` against the budgets of everyone in
+    /// `who`, or turns it down, counting nothing, if any of them is out of
+    /// budget or shut out.
+    pub fn check(&self, kind: Kind, who: &[Who], now: Instant) -> Result<(), TableError> {
+        let mut clients = self.clients.lock().expect("limiter lock");
+        let shut_out = who
+            .iter()
+            .filter_map(|w| clients.get(w)?.shut_out)
+            .filter(|&until| until > now)
+            .max();
+        if let Some(until) = shut_out {
+            return Err(TableError::Limited(format!(
+                "shut out for {}s for sending too many requests",
+                (until - now).as_secs_f64().ceil()
+            )));
+        }
+        let rate = self.limits.rate(kind);
+        if rate.count == 0 {
+            return Ok(());
+        }
+        let full = f64::from(rate.count);
+        let refill = full / rate.per.as_secs_f64();
+        let empty: Vec<bool> = who
+            .iter()
+            .map(|w| {
+                let record = clients.entry(w.clone()).or_default();
+                let bucket = record.buckets.entry(kind).or_insert(Bucket {
+                    left: full,
+                    at: now,
+                });
+                let waited = now.saturating_duration_since(bucket.at).as_secs_f64();
+                bucket.left = (bucket.left + waited * refill).min(full);
+                bucket.at = now;
+                bucket.left < 1.0
+            })
+            .collect();
+        let over = empty.contains(&true);
+        for (w, empty) in who.iter().zip(empty) {
+            let record = clients.get_mut(w).expect("counted");
+            if !over {
+                record.buckets.get_mut(&kind).expect("counted").left -= 1.0;
+                record.strikes = 0;
+            } else if empty {
+                record.strikes += 1;
+                if self.limits.strikes > 0 && record.strikes >= self.limits.strikes {
+                    record.strikes = 0;
+                    record.shut_out = Some(now + self.limits.penalty);
+                }
+            }
+        }
+        if over {
+            return Err(TableError::Limited(format!(
+                "too many {}: no more than {} every {}s",
+                kind.name(),
+                rate.count,
+                rate.per.as_secs()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Forgets the clients whose budgets have all filled back up, unless
+    /// they are shut out.
+    pub fn sweep(&self, now: Instant) {
+        let mut clients = self.clients.lock().expect("limiter lock");
+        clients.retain(|_, record| {
+            record.shut_out.is_some_and(|until| until > now)
+                || record
+                    .buckets
+                    .iter()
+                    .any(|(&kind, bucket)| bucket.at + self.limits.rate(kind).per > now)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limiter() {
+        let limiter = Limiter::new(Limits {
+            actions: Rate {
+                count: 2,
+                per: Duration::from_secs(10),
+            },
+            strikes: 3,
+            penalty: Duration::from_secs(60),
+            ..Limits::default()
+        });
+        let peer = |port| Some(SocketAddr::from(([127, 0, 0, 1], port)));
+        let one = who(peer(1), Some("ann"));
+        let two = who(peer(2), Some("ann"));
+        let three = who(peer(3), None);
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+        let check = |who: &[Who], seconds| limiter.check(Kind::Action, who, at(seconds));
+
+        // Ann's budget is shared by her connections; others have their own.
+        assert_eq!(check(&one, 0), Ok(()));
+        assert_eq!(check(&two, 0), Ok(()));
+        assert!(matches!(check(&one, 0), Err(TableError::Limited(_))));
+        assert_eq!(check(&three, 0), Ok(()));
+        // Budgets are separate for each kind, and refill over time.
+        assert_eq!(limiter.check(Kind::Table, &one, at(0)), Ok(()));
+        assert_eq!(check(&two, 5), Ok(()));
+        assert!(check(&two, 5).is_err());
+
+        // Turned down three times in a row, Ann is shut out until the
+        // penalty is over, however much her budget has refilled.
+        assert!(check(&one, 5).is_err());
+        assert!(check(&two, 5).is_err());
+        assert_eq!(
+            check(&one, 30),
+            Err(TableError::Limited(
+                "shut out for 35s for sending too many requests".to_string()
+            ))
+        );
+        assert!(limiter.check(Kind::Table, &two, at(30)).is_err());
+        assert_eq!(check(&three, 30), Ok(()));
+        assert_eq!(check(&one, 65), Ok(()));
+
+        // Only Ann and her first connection have spent budget lately.
+        limiter.sweep(at(70));
+        assert_eq!(limiter.clients.lock().unwrap().len(), 2);
+        limiter.sweep(at(100));
+        assert!(limiter.clients.lock().unwrap().is_empty());
+
+        let unlimited = Limiter::new(Limits {
+            tables: Rate {
+                count: 0,
+                per: Duration::from_secs(1),
+            },
+            ..Limits::default()
+        });
+        for _ in 0..100 {
+            assert_eq!(unlimited.check(Kind::Table, &one, start), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_set() {
+        let mut limits = Limits::default();
+        limits.set("tables=2/30").unwrap();
+        assert_eq!(
+            limits.tables,
+            Rate {
+                count: 2,
+                per: Duration::from_secs(30)
+            }
+        );
+        assert!(limits.set("tables=2").is_err());
+        assert!(limits.set("actions=2/0").is_err());
+        limits.set("chat=1/5").unwrap();
+        assert_eq!(limits.chat.count, 1);
+        assert!(limits.set("hands=2/1").is_err());
+    }
+}
@@ -0,0 +1,322 @@
+//! `rustserver`: hosts tables of snapfold games for players to play over
+//! gRPC, or from a browser over WebSockets.
+
+mod auth;
+mod chat;
+mod connection;
+mod history;
+mod json;
+mod limits;
+mod service;
+mod table;
+mod tls;
+mod websocket;
+mod wire;
+
+use std::net::{SocketAddr, TcpListener};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use auth::Auth;
+use chat::Words;
+use history::History;
+use limits::{Limiter, Limits};
+use table::Tables;
+use tls::Tls;
+
+const USAGE: &str = "\
+usage: rustserver [--listen ADDR] [--websocket ADDR] [--auth-key FILE]
+                  [--history FILE] [--limit KIND=COUNT/SECONDS]...
+                  [--strikes N] [--penalty SECONDS] [--slow-turns N]
+                  [--chat-filter FILE] [--heartbeat SECONDS]
+                  [--tls-cert FILE --tls-key FILE [--tls-client-ca FILE]]
+       rustserver --auth-key FILE --issue NAME [--hours N]
+
+Serves the GameService in rustserver/service.proto: clients list and open
+tables for a game, take seats or wait for them, stream what happens at the
+table, act in turn and leave.
+
+With --websocket, browsers can do the same over a WebSocket, sending a
+ClientMessage for each request and getting back ServerMessages: protobuf
+JSON in text frames, or binary protobuf if they ask for the
+snapfold.proto subprotocol. Both kinds of client share the same tables.
+
+With --tls-cert and --tls-key, both listeners serve TLS only, with the
+certificate chain and private key in those PEM files. With --tls-client-ca
+too, clients must present a certificate signed by one of the authorities
+in that file.
+
+With --auth-key, players must sign in with a token signed with the key in
+FILE, a secret of at least 16 bytes, and only they can act for the seats
+they take. --issue prints a token for NAME and exits.
+
+Every hand played to the end can be listed with ListHands. With --history,
+hands are also kept in FILE, and those already there are served too;
+`snapfold replay --hand N FILE` plays back the Nth.
+
+Players at a table can chat, and mute what others say. With --chat-filter,
+words listed in FILE, one to a line, are starred out of what they say,
+and messages with any word listed as !WORD aren't sent.
+
+Each connection, and each signed-in player, may only act, chat and open
+tables so fast: --limit sets how many of each kind of request (actions,
+chat or tables) may be sent every so many seconds, or any number if COUNT
+is 0. A client turned down --strikes times in a row is shut out of all of
+them for --penalty seconds. A player who takes longer than the table's
+timeout over --slow-turns turns in a row is put on the clock: acted for,
+as if they had lost their connection, whenever they take that long again.
+
+Connections are pinged every --heartbeat seconds, and dropped once their
+pings go unanswered for a few heartbeats. Players have as much longer to
+act as their round trip to the server takes, up to 5 seconds.
+
+Options:
+  --listen ADDR     address to listen on for gRPC (default 127.0.0.1:50051)
+  --websocket ADDR  address to listen on for WebSocket clients
+  --tls-cert FILE   serve TLS with the certificate chain in FILE
+  --tls-key FILE    and the private key in FILE
+  --tls-client-ca FILE
+                    require client certificates from the authorities in FILE
+  --auth-key FILE   require tokens signed with the key in FILE
+  --issue NAME      print a token for NAME, signed with the key
+  --hours N         how long an issued token lasts (default 24)
+  --history FILE    keep played hands in FILE
+  --limit K=N/S     allow N requests of kind K every S seconds
+                    (default actions=10/1, chat=5/10 and tables=5/60)
+  --strikes N       requests turned down before a shut-out (default 10)
+  --penalty SECONDS how long a shut-out lasts (default 60)
+  --slow-turns N    slow turns in a row before the clock (default 3)
+  --chat-filter FILE
+                    star out the words in FILE from chat
+  --heartbeat SECONDS
+                    how often to ping connections (default 10)";
+
+/// Where to listen, and how players sign in.
+#[derive(Debug, PartialEq, Eq)]
+struct Options {
+    listen: SocketAddr,
+    websocket: Option<SocketAddr>,
+    auth_key: Option<String>,
+    issue: Option<String>,
+    hours: u64,
+    history: Option<String>,
+    limits: Limits,
+    chat_filter: Option<String>,
+    heartbeat: Duration,
+    tls: Option<tls::Files>,
+}
+
+fn address(addr: &str) -> Result<SocketAddr, String> {
+    addr.parse()
+        .map_err(|_| format!("{:?} isn't an address like 127.0.0.1:50051", addr))
+}
+
+/// The options from the command line, or `None` for help.
+fn parse(argv: &[String]) -> Result<Option<Options>, String> {
+    let mut options = Options {
+        listen: address("127.0.0.1:50051")?,
+        websocket: None,
+        auth_key: None,
+        issue: None,
+        hours: 24,
+        history: None,
+        limits: Limits::default(),
+        chat_filter: None,
+        heartbeat: Duration::from_secs(10),
+        tls: None,
+    };
+    let (mut cert, mut key, mut client_ca) = (None, None, None);
+    let mut args = argv.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--help" | "-h" => return Ok(None),
+            "--listen" | "--websocket" => {
+                let addr = args
+                    .next()
+                    .ok_or_else(|| format!("{} needs an address", arg))?;
+                if arg == "--listen" {
+                    options.listen = address(addr)?;
+                } else {
+                    options.websocket = Some(address(addr)?);
+                }
+            }
+            "--auth-key" | "--issue" | "--hours" | "--history" | "--chat-filter" | "--tls-cert"
+            | "--tls-key" | "--tls-client-ca" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("{} needs a value", arg))?
+                    .clone();
+                match arg.as_str() {
+                    "--auth-key" => options.auth_key = Some(value),
+                    "--issue" => options.issue = Some(value),
+                    "--history" => options.history = Some(value),
+                    "--chat-filter" => options.chat_filter = Some(value),
+                    "--tls-cert" => cert = Some(value),
+                    "--tls-key" => key = Some(value),
+                    "--tls-client-ca" => client_ca = Some(value),
+                    _ => {
+                        options.hours = value
+                            .parse()
+                            .map_err(|_| format!("{:?} isn't a number of hours", value))?
+                    }
+                }
+            }
+            "--limit" | "--strikes" | "--penalty" | "--slow-turns" | "--heartbeat" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("{} needs a value", arg))?;
+                let count = || {
+                    value
+                        .parse::<u32>()
+                        .map_err(|_| format!("{:?} isn't a count", value))
+                };
+                match arg.as_str() {
+                    "--limit" => options.limits.set(value)?,
+                    "--strikes" => options.limits.strikes = count()?,
+                    "--penalty" => options.limits.penalty = Duration::from_secs(count()?.into()),
+                    "--heartbeat" => match count()? {
+                        0 => return Err("--heartbeat needs at least a second".to_string()),
+                        seconds => options.heartbeat = Duration::from_secs(seconds.into()),
+                    },
+                    _ => options.limits.slow_turns = count()?,
+                }
+            }
+            _ => return Err(format!("unexpected argument {:?}", arg)),
+        }
+    }
+    options.tls = match (cert, key, client_ca) {
+        (Some(cert), Some(key), client_ca) => Some(tls::Files {
+            cert,
+            key,
+            client_ca,
+        }),
+        (None, None, None) => None,
+        _ => return Err("TLS needs both --tls-cert and --tls-key".to_string()),
+    };
+    if options.issue.is_some() && options.auth_key.is_none() {
+        return Err("--issue needs --auth-key to sign with".to_string());
+    }
+    Ok(Some(options))
+}
+
+fn main() -> ExitCode {
+    let argv: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse(&argv) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let auth = match &options.auth_key {
+        Some(path) => match auth::Key::load(path) {
+            Ok(key) => Some(key),
+            Err(message) => {
+                eprintln!("error: {}", message);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+    if let (Some(name), Some(key)) = (&options.issue, &auth) {
+        let expires = auth::now() + options.hours * 3600;
+        return match key.issue(name, expires) {
+            Ok(token) => {
+                println!("{}", token);
+                ExitCode::SUCCESS
+            }
+            Err(message) => {
+                eprintln!("error: {}", message);
+                ExitCode::FAILURE
+            }
+        };
+    }
+    let auth = auth.map(Auth::with_key).unwrap_or_default();
+    let tls = match options.tls.as_ref().map(Tls::load).transpose() {
+        Ok(tls) => tls,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("error: can't start the runtime: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let history = match &options.history {
+        Some(path) => match History::open(Path::new(path)) {
+            Ok(history) => history,
+            Err(message) => {
+                eprintln!("error: {}", message);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => History::default(),
+    };
+    let limiter = Limiter::new(options.limits.clone());
+    let mut tables = Tables::new(history, limiter.limits());
+    if let Some(path) = &options.chat_filter {
+        match Words::load(path) {
+            Ok(words) => tables = tables.with_chat_filter(Arc::new(words)),
+            Err(message) => {
+                eprintln!("error: {}", message);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let tables = Arc::new(tables);
+    {
+        // Reserved seats that go unclaimed open up again, players who
+        // lose their connection are acted for once their time runs out,
+        // and budgets that have filled back up are forgotten.
+        let tables = Arc::clone(&tables);
+        let limiter = limiter.clone();
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(1));
+            tables.tick();
+            limiter.sweep(Instant::now());
+        });
+    }
+    if let Some(addr) = options.websocket {
+        let listener = match TcpListener::bind(addr) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("error: can't listen on {}: {}", addr, e);
+                return ExitCode::FAILURE;
+            }
+        };
+        eprintln!("serving WebSocket clients on {}", addr);
+        let tables = Arc::clone(&tables);
+        let auth = auth.clone();
+        let limiter = limiter.clone();
+        let heartbeat = options.heartbeat;
+        let tls = tls.clone();
+        thread::spawn(move || connection::serve(listener, tables, auth, limiter, heartbeat, tls));
+    }
+    eprintln!("serving on {}", options.listen);
+    let serving = service::serve(
+        options.listen,
+        tables,
+        auth,
+        limiter,
+        options.heartbeat,
+        tls,
+    );
+    match runtime.block_on(serving) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! The `GameService` of `service.proto`, over the tables in [`Tables`].
+//! If players must sign in, each request carries its token in an
+//! `authorization: Bearer TOKEN` header. HTTP/2 keepalive pings find
+//! connections that have died; the latency of a player's actions comes
+//! from the round trips their client reports with `Ping`.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use service_proto3::snapfold::server::game_service_server::{GameService, GameServiceServer};
+use service_proto3::snapfold::server::{
+    CreateTableRequest, CreateTableResponse, JoinTableRequest, JoinTableResponse,
+    LeaveTableRequest, LeaveTableResponse, ListHandsRequest, ListHandsResponse, ListTablesRequest,
+    ListTablesResponse, MuteRequest, MuteResponse, PingRequest, PingResponse, ReserveSeatRequest,
+    ReserveSeatResponse, SendChatRequest, SendChatResponse, StreamEventsRequest,
+    SubmitActionRequest, SubmitActionResponse, TableEvent,
+};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::auth::Auth;
+use crate::limits::{self, Kind, Limiter};
+use crate::table::{self, Sink, TableError, Tables};
+use crate::tls::Tls;
+
+pub struct Service {
+    tables: Arc<Tables>,
+    auth: Auth,
+    limiter: Limiter,
+}
+
+/// Serves the `GameService` for `tables` on `addr`, over `tls` if there is
+/// any, until the server fails. Each connection is pinged every
+/// `heartbeat`, and dropped if a ping goes unanswered for two more.
+pub async fn serve(
+    addr: SocketAddr,
+    tables: Arc<Tables>,
+    auth: Auth,
+    limiter: Limiter,
+    heartbeat: Duration,
+    tls: Option<Tls>,
+) -> Result<(), tonic::transport::Error> {
+    let service = Service {
+        tables,
+        auth,
+        limiter,
+    };
+    let mut server = tonic::transport::Server::builder()
+        .http2_keepalive_interval(Some(heartbeat))
+        .http2_keepalive_timeout(Some(heartbeat * 2));
+    if let Some(tls) = tls {
+        server = server.tls_config(tls.grpc())?;
+    }
+    server
+        .add_service(GameServiceServer::new(service))
+        .serve(addr)
+        .await
+}
+
+impl Service {
+    /// Who sent `request`, if players sign in.
+    fn user<T>(&self, request: &Request<T>) -> Result<Option<String>, Status> {
+        let token = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        self.auth.identify(token).map_err(status)
+    }
+
+    /// Counts `request`, from `user`, against the budgets for `kind`.
+    fn limit<T>(&self, kind: Kind, request: &Request<T>, user: Option<&str>) -> Result<(), Status> {
+        let who = limits::who(request.remote_addr(), user);
+        self.limiter
+            .check(kind, &who, Instant::now())
+            .map_err(status)
+    }
+}
+
+impl Sink for mpsc::UnboundedSender<Result<TableEvent, Status>> {
+    fn send(&mut self, event: TableEvent) -> bool {
+        mpsc::UnboundedSender::send(self, Ok(event)).is_ok()
+    }
+
+    fn closed(&self) -> bool {
+        self.is_closed()
+    }
+}
+
+fn status(error: TableError) -> Status {
+    let message = error.to_string();
+    match error {
+        TableError::BadRequest(_) | TableError::Invalid(_) | TableError::Settings(_) => {
+            Status::invalid_argument(message)
+        }
+        TableError::NoSuchTable(_) | TableError::UnknownReservation => Status::not_found(message),
+        TableError::Full | TableError::Limited(_) => Status::resource_exhausted(message),
+        TableError::UnknownPlayer | TableError::NotYours | TableError::Moderated(_) => {
+            Status::permission_denied(message)
+        }
+        TableError::Unauthenticated(_) => Status::unauthenticated(message),
+        TableError::NoHand | TableError::Engine(_) => Status::failed_precondition(message),
+    }
+}
+
+#[tonic::async_trait]
+impl GameService for Service {
+    async fn create_table(
+        &self,
+        request: Request<CreateTableRequest>,
+    ) -> Result<Response<CreateTableResponse>, Status> {
+        let user = self.user(&request)?;
+        self.limit(Kind::Table, &request, user.as_deref())?;
+        let created = self.tables.create(request.into_inner()).map_err(status)?;
+        Ok(Response::new(created))
+    }
+
+    async fn list_tables(
+        &self,
+        request: Request<ListTablesRequest>,
+    ) -> Result<Response<ListTablesResponse>, Status> {
+        self.user(&request)?;
+        Ok(Response::new(self.tables.list(request.get_ref())))
+    }
+
+    async fn reserve_seat(
+        &self,
+        request: Request<ReserveSeatRequest>,
+    ) -> Result<Response<ReserveSeatResponse>, Status> {
+        self.user(&request)?;
+        let reserved = self.tables.reserve(request.get_ref()).map_err(status)?;
+        Ok(Response::new(reserved))
+    }
+
+    async fn join_table(
+        &self,
+        request: Request<JoinTableRequest>,
+    ) -> Result<Response<JoinTableResponse>, Status> {
+        let user = self.user(&request)?;
+        let joined = self
+            .tables
+            .join(request.get_ref(), user.as_deref())
+            .map_err(status)?;
+        Ok(Response::new(joined))
+    }
+
+    type StreamEventsStream = UnboundedReceiverStream<Result<TableEvent, Status>>;
+
+    async fn stream_events(
+        &self,
+        request: Request<StreamEventsRequest>,
+    ) -> Result<Response<Self::StreamEventsStream>, Status> {
+        let user = self.user(&request)?;
+        let request = request.into_inner();
+        let table = self.tables.get(&request.table_id).map_err(status)?;
+        let delay = table::delay(&request).map_err(status)?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let player = Some(request.player_id.as_str()).filter(|id| !id.is_empty());
+        let mut table = table::lock(&table);
+        if let Some(delay) = delay {
+            table.spectate(delay, Box::new(tx));
+            return Ok(Response::new(UnboundedReceiverStream::new(rx)));
+        }
+        if let Some(player) = player {
+            table.authorize(player, user.as_deref()).map_err(status)?;
+        }
+        let after = Some(request.after).filter(|&after| after > 0);
+        table.watch(player, after, Box::new(tx)).map_err(status)?;
+        Ok(Response::new(UnboundedReceiverStream::new(rx)))
+    }
+
+    async fn submit_action(
+        &self,
+        request: Request<SubmitActionRequest>,
+    ) -> Result<Response<SubmitActionResponse>, Status> {
+        let user = self.user(&request)?;
+        self.limit(Kind::Action, &request, user.as_deref())?;
+        self.tables
+            .submit(request.get_ref(), user.as_deref())
+            .map_err(status)?;
+        Ok(Response::new(SubmitActionResponse {}))
+    }
+
+    async fn leave_table(
+        &self,
+        request: Request<LeaveTableRequest>,
+    ) -> Result<Response<LeaveTableResponse>, Status> {
+        let user = self.user(&request)?;
+        self.tables
+            .leave(request.get_ref(), user.as_deref())
+            .map_err(status)?;
+        Ok(Response::new(LeaveTableResponse {}))
+    }
+
+    async fn list_hands(
+        &self,
+        request: Request<ListHandsRequest>,
+    ) -> Result<Response<ListHandsResponse>, Status> {
+        let user = self.user(&request)?;
+        let hands = self
+            .tables
+            .hands(request.get_ref(), user.as_deref())
+            .map_err(status)?;
+        Ok(Response::new(hands))
+    }
+
+    async fn send_chat(
+        &self,
+        request: Request<SendChatRequest>,
+    ) -> Result<Response<SendChatResponse>, Status> {
+        let user = self.user(&request)?;
+        self.limit(Kind::Chat, &request, user.as_deref())?;
+        self.tables
+            .chat(request.get_ref(), user.as_deref())
+            .map_err(status)?;
+        Ok(Response::new(SendChatResponse {}))
+    }
+
+    async fn mute(&self, request: Request<MuteRequest>) -> Result<Response<MuteResponse>, Status> {
+        let user = self.user(&request)?;
+        self.tables
+            .mute(request.get_ref(), user.as_deref())
+            .map_err(status)?;
+        Ok(Response::new(MuteResponse {}))
+    }
+
+    async fn ping(&self, request: Request<PingRequest>) -> Result<Response<PingResponse>, Status> {
+        let user = self.user(&request)?;
+        self.tables
+            .ping(request.get_ref(), user.as_deref())
+            .map_err(status)?;
+        Ok(Response::new(PingResponse {}))
+    }
+}
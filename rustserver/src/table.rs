@@ -0,0 +1,1938 @@
+//! Tables: a game, the players seated at it, the hand being played, and
+//! the streams watching it.
+//!
+//! A table is driven by calls from the service, each made with the table
+//! locked: players join, wait for seats, act and leave, and the table
+//! deals hands and sends what happens to every watcher as it happens. A
+//! clock ticks it too, to let reservations go and to act for players who
+//! have lost their connection.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use rustlib::compact::CompactState;
+use rustlib::engine::{
+    Action, Audience, Chips, DealtCard, EngineConfig, EngineError, Event, GameEngine, HandStatus,
+    LegalAction,
+};
+use rustlib::entropy::{EntropySource, OsEntropy};
+use rustlib::phase::declare::Side;
+use rustlib::shuffle::Shuffler;
+use rustlib::validation::{self, ValidationError};
+use rustlib::{BoardCards, GameStructure, HoleCards};
+use service_proto3::snapfold::server::chat::Emote;
+use service_proto3::snapfold::server::create_table_request::Timeout;
+use service_proto3::snapfold::server::{
+    table_event, CreateTableRequest, CreateTableResponse, JoinTableRequest, JoinTableResponse,
+    LeaveTableRequest, ListHandsRequest, ListHandsResponse, ListTablesRequest, ListTablesResponse,
+    MuteRequest, PingRequest, ReserveSeatRequest, ReserveSeatResponse, SeatSnapshot,
+    SendChatRequest, StreamEventsRequest, SubmitActionRequest, TableEvent, TableSnapshot,
+    TableSummary, TurnToAct,
+};
+
+use crate::auth;
+use crate::chat::{self, ChatFilter, Said, Verdict};
+use crate::history::{self, History};
+use crate::limits::Limits;
+use crate::wire;
+
+/// The most seats a table may have.
+pub const MAX_SEATS: usize = 10;
+
+/// How long a reserved seat is held.
+pub const RESERVATION: Duration = Duration::from_secs(60);
+
+/// The longest a spectator's events may be held back.
+pub const MAX_DELAY: Duration = Duration::from_secs(3600);
+
+/// The most a player's round trip to the server adds to their time to act.
+pub const MAX_LATENCY: Duration = Duration::from_secs(5);
+
+/// How a table is set up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Settings {
+    pub seats: usize,
+    /// Chips each player sits down with.
+    pub stack: Chips,
+    /// Blinds, smallest first.
+    pub blinds: Vec<Chips>,
+    pub ante: Chips,
+    /// Seated players with chips it takes to deal a hand.
+    pub start: usize,
+    /// How long a player who has lost their connection has to act.
+    pub timeout: Duration,
+    pub on_timeout: Timeout,
+}
+
+/// Why a request to a table was turned down.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TableError {
+    /// A request is missing something or has a value out of range.
+    BadRequest(String),
+    NoSuchTable(String),
+    /// The game doesn't pass validation.
+    Invalid(Vec<ValidationError>),
+    /// The settings can't make a table.
+    Settings(String),
+    Full,
+    /// No player at the table has the id.
+    UnknownPlayer,
+    /// No seat at the table is held by the reservation, if it ever was.
+    UnknownReservation,
+    /// The request doesn't say who it is from, or not in a way the
+    /// server can check.
+    Unauthenticated(String),
+    /// The player id belongs to someone other than who sent the request.
+    NotYours,
+    /// No hand is being played.
+    NoHand,
+    Engine(EngineError),
+    /// The client has sent too many requests of a kind, as
+    /// [`Limits`] says.
+    Limited(String),
+    /// The server's chat filter turned a message down, for the reason
+    /// given.
+    Moderated(String),
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableError::BadRequest(message) => f.write_str(message),
+            TableError::NoSuchTable(id) => write!(f, "no table {:?}", id),
+            TableError::Invalid(errors) => {
+                let errors: Vec<String> = errors.iter().map(ValidationError::to_string).collect();
+                write!(f, "the game isn't playable: {}", errors.join("; "))
+            }
+            TableError::Settings(message) => f.write_str(message),
+            TableError::Full => f.write_str("every seat is taken"),
+            TableError::UnknownPlayer => f.write_str("no player at the table has that id"),
+            TableError::UnknownReservation => {
+                f.write_str("no seat is reserved with that id; it may have expired")
+            }
+            TableError::Unauthenticated(message) => f.write_str(message),
+            TableError::NotYours => f.write_str("that player id belongs to someone else"),
+            TableError::NoHand => f.write_str("no hand is being played"),
+            TableError::Engine(e) => e.fmt(f),
+            TableError::Limited(message) => f.write_str(message),
+            TableError::Moderated(reason) => write!(f, "the message wasn't sent: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for TableError {}
+
+impl From<EngineError> for TableError {
+    fn from(e: EngineError) -> Self {
+        TableError::Engine(e)
+    }
+}
+
+/// Something that happened at a table, before it is filtered for a
+/// watcher by [`wire::table_event`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Update {
+    SeatTaken {
+        seat: usize,
+        name: String,
+        stack: Chips,
+    },
+    HandStarted {
+        hand: u64,
+        button: usize,
+        stacks: Vec<Chips>,
+    },
+    Game(Event),
+    /// It is `seat`'s turn, and they have `time` to act.
+    Turn {
+        seat: usize,
+        legal: Vec<LegalAction>,
+        time: Duration,
+    },
+    HandEnded {
+        stacks: Vec<Chips>,
+    },
+    /// A seat's cards changed other than by a deal.
+    Hole {
+        seat: usize,
+        cards: Vec<DealtCard>,
+    },
+    SeatLeft {
+        seat: usize,
+    },
+    Presence {
+        seat: usize,
+        away: bool,
+    },
+    /// A player said something, from `seat` or while waiting for one.
+    Chat {
+        name: String,
+        seat: Option<usize>,
+        text: String,
+        emote: Emote,
+    },
+}
+
+/// Where a watcher's events go.
+pub trait Sink: Send {
+    /// Takes the watcher's next event, and returns false once nobody is
+    /// listening any more.
+    fn send(&mut self, event: TableEvent) -> bool;
+
+    /// Whether nobody is listening any more, found without sending.
+    fn closed(&self) -> bool;
+}
+
+struct Watcher {
+    /// The id of the player watching, who is shown the cards of their own
+    /// seat once they have one.
+    player: Option<String>,
+    sink: Box<dyn Sink>,
+    delay: Option<Delay>,
+}
+
+/// The events held back from a delayed spectator.
+struct Delay {
+    by: Duration,
+    /// Set from the first hand the spectator sees the start of.
+    started: bool,
+    held: VecDeque<(Instant, TableEvent)>,
+}
+
+impl Delay {
+    fn hold(&mut self, now: Instant, event: TableEvent) {
+        if matches!(event.kind, Some(table_event::Kind::HandStarted(_))) {
+            self.started = true;
+        }
+        if self.started {
+            self.held.push_back((now + self.by, event));
+        }
+    }
+
+    /// Sends `sink` the events that are due by `now`. False once nobody
+    /// is listening.
+    fn release(&mut self, now: Instant, sink: &mut dyn Sink) -> bool {
+        while self.held.front().is_some_and(|(due, _)| *due <= now) {
+            let (_, event) = self.held.pop_front().expect("an event is due");
+            if !sink.send(event) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+struct Player {
+    id: String,
+    name: String,
+    /// Who the player signed in as, if the server signs players in. Only
+    /// they may act, watch or leave as the player.
+    user: Option<String>,
+    /// Set when the player leaves during a hand they were dealt into:
+    /// they fold as their turn comes, and their seat opens as it ends.
+    leaving: bool,
+    /// Whether the player has streamed the table's events, and since when
+    /// they have had no stream if they have lost it.
+    streamed: bool,
+    away: Option<Instant>,
+    /// Turns in a row the player took longer than the timeout over.
+    slow: u32,
+    /// The names of the players whose chat they don't want to see.
+    muted: HashSet<String>,
+    /// How long the player's last round trip to the server took.
+    latency: Duration,
+}
+
+impl Player {
+    /// A player called `name`, or by who they signed in as if `name` is
+    /// empty.
+    fn new(name: &str, user: Option<&str>) -> Self {
+        let name = match (name, user) {
+            ("", Some(user)) => user,
+            _ => name,
+        };
+        Player {
+            id: player_id(),
+            name: name.to_string(),
+            user: user.map(str::to_string),
+            leaving: false,
+            streamed: false,
+            away: None,
+            slow: 0,
+            muted: HashSet::new(),
+            latency: Duration::ZERO,
+        }
+    }
+}
+
+/// A seat held open for a player who is yet to join.
+struct Reservation {
+    id: String,
+    expires: Instant,
+}
+
+pub struct Table {
+    game: GameStructure,
+    settings: Settings,
+    /// Each seat's player, if it is taken.
+    players: Vec<Option<Player>>,
+    /// Each seat's reservation, if it is held.
+    reserved: Vec<Option<Reservation>>,
+    /// Players waiting for a seat, first come first served.
+    waiting: VecDeque<Player>,
+    /// Each seat's chips between hands.
+    stacks: Vec<Chips>,
+    engine: Option<GameEngine>,
+    /// Whether each seat was dealt into the hand being played.
+    dealt: Vec<bool>,
+    /// How many of the engine's events have been sent.
+    sent: usize,
+    /// Each seat's cards as the events sent so far have them.
+    holes: Vec<Vec<DealtCard>>,
+    hands: u64,
+    /// When the hand being played started, in seconds since the epoch.
+    started: u64,
+    button: usize,
+    /// Where hands played to the end are kept, and the table's id there.
+    history: Option<(Arc<History>, String)>,
+    /// Slow turns in a row that put a player on the clock, or 0 for never.
+    slow_turns: u32,
+    /// When the seat to act was told it was their turn.
+    turn_started: Instant,
+    /// Everything since the hand being played started, or since the table
+    /// opened if none has, for watchers that join late.
+    recent: Vec<(u64, Update)>,
+    sequence: u64,
+    watchers: Vec<Watcher>,
+}
+
+impl Table {
+    /// An empty table for `game`, if the game is playable.
+    pub fn new(game: GameStructure, settings: Settings) -> Result<Self, TableError> {
+        if !(2..=MAX_SEATS).contains(&settings.seats) {
+            return Err(TableError::Settings(format!(
+                "a table has from 2 to {} seats",
+                MAX_SEATS
+            )));
+        }
+        if settings.stack == 0 {
+            return Err(TableError::Settings(
+                "players need chips to sit down with".to_string(),
+            ));
+        }
+        if !(2..=settings.seats).contains(&settings.start) {
+            return Err(TableError::Settings(format!(
+                "a hand takes from 2 to {} players to start",
+                settings.seats
+            )));
+        }
+        validation::validate(&game).map_err(TableError::Invalid)?;
+        let max = validation::max_seats(&game);
+        if settings.seats > max {
+            return Err(TableError::Settings(format!(
+                "the deck can deal a hand of {} to at most {} players",
+                game.name, max
+            )));
+        }
+        Ok(Table {
+            game,
+            players: (0..settings.seats).map(|_| None).collect(),
+            reserved: (0..settings.seats).map(|_| None).collect(),
+            waiting: VecDeque::new(),
+            stacks: vec![0; settings.seats],
+            settings,
+            engine: None,
+            dealt: Vec::new(),
+            sent: 0,
+            holes: Vec::new(),
+            hands: 0,
+            started: 0,
+            button: 0,
+            history: None,
+            slow_turns: 0,
+            turn_started: Instant::now(),
+            recent: Vec::new(),
+            sequence: 0,
+            watchers: Vec::new(),
+        })
+    }
+
+    /// Seats a player called `name`, signed in as `user` if the server
+    /// signs players in, in the first open seat, and deals if that makes
+    /// enough players. Returns the seat and the player's id.
+    pub fn join(&mut self, name: &str, user: Option<&str>) -> Result<(usize, String), TableError> {
+        self.expire(Instant::now())?;
+        let seat = self.open_seat().ok_or(TableError::Full)?;
+        self.sit(seat, Player::new(name, user))
+    }
+
+    /// Seats a player as [`Table::join`] does, but in the seat held by
+    /// `reservation`.
+    pub fn claim(
+        &mut self,
+        reservation: &str,
+        name: &str,
+        user: Option<&str>,
+    ) -> Result<(usize, String), TableError> {
+        self.expire(Instant::now())?;
+        let seat = self
+            .reserved
+            .iter()
+            .position(|r| r.as_ref().is_some_and(|r| r.id == reservation))
+            .ok_or(TableError::UnknownReservation)?;
+        self.reserved[seat] = None;
+        self.sit(seat, Player::new(name, user))
+    }
+
+    /// Puts a player at the back of the waitlist, as [`Table::join`] would
+    /// seat them. Returns their place on it, counting from 1, and their id.
+    pub fn wait(&mut self, name: &str, user: Option<&str>) -> (usize, String) {
+        let player = Player::new(name, user);
+        let id = player.id.clone();
+        self.waiting.push_back(player);
+        (self.waiting.len(), id)
+    }
+
+    /// Holds the first open seat for [`RESERVATION`]. Returns the seat and
+    /// the reservation's id.
+    pub fn reserve(&mut self) -> Result<(usize, String), TableError> {
+        let now = Instant::now();
+        self.expire(now)?;
+        let seat = self.open_seat().ok_or(TableError::Full)?;
+        let id = player_id();
+        self.reserved[seat] = Some(Reservation {
+            id: id.clone(),
+            expires: now + RESERVATION,
+        });
+        Ok((seat, id))
+    }
+
+    /// Takes the player with id `player` off the waitlist or out of their
+    /// seat. A player dealt into the hand being played folds, and keeps
+    /// the seat until the hand ends.
+    pub fn leave(&mut self, player: &str) -> Result<(), TableError> {
+        if let Some(place) = self.waiting.iter().position(|p| p.id == player) {
+            self.waiting.remove(place);
+            return Ok(());
+        }
+        let seat = self.seat_of(player)?;
+        if self.engine.is_none() || !self.dealt[seat] {
+            self.vacate(seat);
+            return self.fill();
+        }
+        self.players[seat].as_mut().expect("seated").leaving = true;
+        let to_act = self.engine.as_ref().and_then(GameEngine::to_act);
+        if to_act == Some(seat) && self.act_for(seat, Timeout::Fold) {
+            return self.advance();
+        }
+        Ok(())
+    }
+
+    /// Lets go of the reservations that have run out by `now`, and gives
+    /// their seats to the waitlist.
+    pub fn expire(&mut self, now: Instant) -> Result<(), TableError> {
+        let mut expired = false;
+        for reservation in &mut self.reserved {
+            if reservation.as_ref().is_some_and(|r| r.expires <= now) {
+                *reservation = None;
+                expired = true;
+            }
+        }
+        if expired {
+            self.fill()?;
+        }
+        Ok(())
+    }
+
+    /// Sends `sink` what has happened since the hand being played started,
+    /// then everything that happens from now on. `player` is the id of the
+    /// player watching, if any, who may be seated or waiting.
+    ///
+    /// A stream that resumes `after` the event with that sequence is only
+    /// sent what came after it, then a snapshot of the table.
+    pub fn watch(
+        &mut self,
+        player: Option<&str>,
+        after: Option<u64>,
+        mut sink: Box<dyn Sink>,
+    ) -> Result<(), TableError> {
+        if let Some(id) = player {
+            if seat_in(&self.players, id).is_none() && !self.waiting.iter().any(|p| p.id == id) {
+                return Err(TableError::UnknownPlayer);
+            }
+        }
+        let seat = player.and_then(|id| seat_in(&self.players, id));
+        let audience = seat.map_or(Audience::Spectator, Audience::Seat);
+        let muted = player.and_then(|id| find(&self.players, &self.waiting, id));
+        let muted = muted.map(|p| p.muted.clone()).unwrap_or_default();
+        if let Some(seat) = seat {
+            let player = self.players[seat].as_mut().expect("seated");
+            player.streamed = true;
+            if player.away.take().is_some() {
+                self.emit(Update::Presence { seat, away: false });
+            }
+        }
+        for (sequence, update) in &self.recent {
+            if after.is_some_and(|after| *sequence <= after) {
+                continue;
+            }
+            let mut event = wire::table_event(*sequence, update, audience);
+            if says(update).is_some_and(|name| muted.contains(name)) {
+                wire::mute(&mut event);
+            }
+            if !sink.send(event) {
+                return Ok(());
+            }
+        }
+        if after.is_some() {
+            let snapshot = TableEvent {
+                sequence: self.sequence,
+                kind: Some(table_event::Kind::Snapshot(self.snapshot(audience))),
+            };
+            if !sink.send(snapshot) {
+                return Ok(());
+            }
+        }
+        let player = player.map(str::to_string);
+        self.watchers.push(Watcher {
+            player,
+            sink,
+            delay: None,
+        });
+        Ok(())
+    }
+
+    /// Sends `sink` what a spectator may see, each event `delay` after it
+    /// happens, starting with the next hand.
+    pub fn spectate(&mut self, delay: Duration, sink: Box<dyn Sink>) {
+        self.watchers.push(Watcher {
+            player: None,
+            sink,
+            delay: Some(Delay {
+                by: delay,
+                started: false,
+                held: VecDeque::new(),
+            }),
+        });
+    }
+
+    /// Lets reservations go as [`Table::expire`] does, sends delayed
+    /// spectators what is due, marks players away whose streams have all
+    /// ended, and acts for a player who has been away for the timeout on
+    /// their turn, or has had it for that long while on the clock.
+    pub fn tick(&mut self, now: Instant) -> Result<(), TableError> {
+        self.expire(now)?;
+        self.watchers.retain_mut(|w| {
+            !w.sink.closed()
+                && w.delay
+                    .as_mut()
+                    .is_none_or(|delay| delay.release(now, w.sink.as_mut()))
+        });
+        let gone: Vec<usize> = (0..self.players.len())
+            .filter(|&seat| {
+                self.players[seat].as_ref().is_some_and(|p| {
+                    p.streamed
+                        && p.away.is_none()
+                        && !self
+                            .watchers
+                            .iter()
+                            .any(|w| w.player.as_ref() == Some(&p.id))
+                })
+            })
+            .collect();
+        for seat in gone {
+            self.players[seat].as_mut().expect("seated").away = Some(now);
+            self.emit(Update::Presence { seat, away: true });
+        }
+        let Some(seat) = self.engine.as_ref().and_then(GameEngine::to_act) else {
+            return Ok(());
+        };
+        let Some(player) = &self.players[seat] else {
+            return Ok(());
+        };
+        let since = match player.away {
+            Some(away) => away.max(self.turn_started),
+            None if self.on_clock(player) => self.turn_started,
+            None => return Ok(()),
+        };
+        let waited = now.saturating_duration_since(since);
+        if waited >= self.time_to_act(seat) && self.act_for(seat, self.settings.on_timeout) {
+            return self.advance();
+        }
+        Ok(())
+    }
+
+    /// Fails unless a player seated or waiting at the table has id `id`
+    /// and, if they signed in, `user` is who they signed in as.
+    pub fn authorize(&self, id: &str, user: Option<&str>) -> Result<(), TableError> {
+        let player = self
+            .players
+            .iter()
+            .flatten()
+            .chain(&self.waiting)
+            .find(|p| p.id == id)
+            .ok_or(TableError::UnknownPlayer)?;
+        match &player.user {
+            Some(owner) if Some(owner.as_str()) != user => Err(TableError::NotYours),
+            _ => Ok(()),
+        }
+    }
+
+    /// What the lobby shows of the table.
+    pub fn summary(&self, table_id: &str) -> TableSummary {
+        let count = |n: usize| n as i32;
+        TableSummary {
+            table_id: table_id.to_string(),
+            game_id: self.game.id.clone(),
+            game_name: self.game.name.clone(),
+            seats: count(self.settings.seats),
+            seated: count(self.players.iter().flatten().count()),
+            reserved: count(self.reserved.iter().flatten().count()),
+            waiting: count(self.waiting.len()),
+            stack: self.settings.stack as i64,
+            blinds: self.settings.blinds.iter().map(|&b| b as i64).collect(),
+            ante: self.settings.ante as i64,
+            start: count(self.settings.start),
+            hands: self.hands,
+        }
+    }
+
+    /// Plays `action` for the player with id `player`, and deals the next
+    /// hand if it ends this one.
+    pub fn act(&mut self, player: &str, action: Action) -> Result<(), TableError> {
+        let seat = self.seat_of(player)?;
+        let engine = self.engine.as_mut().ok_or(TableError::NoHand)?;
+        engine.apply_action(seat, action)?;
+        let slow = self.turn_started.elapsed() > self.time_to_act(seat);
+        let player = self.players[seat].as_mut().expect("seated");
+        player.slow = if slow { player.slow + 1 } else { 0 };
+        self.advance()
+    }
+
+    /// Sends what the player with id `player` says to everyone watching,
+    /// as `text` that has been checked and filtered already.
+    pub fn chat(&mut self, player: &str, text: String, emote: Emote) -> Result<(), TableError> {
+        let name = find(&self.players, &self.waiting, player)
+            .ok_or(TableError::UnknownPlayer)?
+            .name
+            .clone();
+        let seat = seat_in(&self.players, player);
+        self.emit(Update::Chat {
+            name,
+            seat,
+            text,
+            emote,
+        });
+        Ok(())
+    }
+
+    /// Stops showing the player with id `player` what the player called
+    /// `name` says, or starts again if `muted` is false.
+    pub fn mute(&mut self, player: &str, name: &str, muted: bool) -> Result<(), TableError> {
+        if name.is_empty() {
+            return Err(TableError::BadRequest("no name to mute".to_string()));
+        }
+        let player = self.player_mut(player)?;
+        if muted {
+            player.muted.insert(name.to_string());
+        } else {
+            player.muted.remove(name);
+        }
+        Ok(())
+    }
+
+    /// Takes `round_trip` as how long the player with id `player` takes to
+    /// hear from the server and answer it, and allows for it on their
+    /// turns from the next one on.
+    pub fn latency(&mut self, player: &str, round_trip: Duration) -> Result<(), TableError> {
+        self.player_mut(player)?.latency = round_trip;
+        Ok(())
+    }
+
+    /// The player seated or waiting with id `id`.
+    fn player_mut(&mut self, id: &str) -> Result<&mut Player, TableError> {
+        self.players
+            .iter_mut()
+            .flatten()
+            .chain(&mut self.waiting)
+            .find(|p| p.id == id)
+            .ok_or(TableError::UnknownPlayer)
+    }
+
+    /// How long `seat` has to act once it is their turn: the table's
+    /// timeout, and their round trip to the server up to [`MAX_LATENCY`].
+    fn time_to_act(&self, seat: usize) -> Duration {
+        let latency = self.players[seat]
+            .as_ref()
+            .map_or(Duration::ZERO, |p| p.latency);
+        self.settings.timeout + latency.min(MAX_LATENCY)
+    }
+
+    /// Whether `player` has taken too long over enough turns in a row to
+    /// be acted for when they take that long again, as if they were away.
+    fn on_clock(&self, player: &Player) -> bool {
+        self.slow_turns > 0 && player.slow >= self.slow_turns
+    }
+
+    /// The seat of the player with id `id`.
+    pub fn seat_of(&self, id: &str) -> Result<usize, TableError> {
+        seat_in(&self.players, id).ok_or(TableError::UnknownPlayer)
+    }
+
+    /// The first seat that is neither taken nor held.
+    fn open_seat(&self) -> Option<usize> {
+        (0..self.settings.seats).find(|&s| self.players[s].is_none() && self.reserved[s].is_none())
+    }
+
+    /// Seats `player` in `seat`, and deals if that makes enough players.
+    /// Returns the seat and the player's id.
+    fn sit(&mut self, seat: usize, player: Player) -> Result<(usize, String), TableError> {
+        let id = player.id.clone();
+        self.take_seat(seat, player);
+        self.deal()?;
+        Ok((seat, id))
+    }
+
+    fn take_seat(&mut self, seat: usize, player: Player) {
+        let name = player.name.clone();
+        self.players[seat] = Some(player);
+        self.stacks[seat] = self.settings.stack;
+        self.emit(Update::SeatTaken {
+            seat,
+            name,
+            stack: self.settings.stack,
+        });
+    }
+
+    fn vacate(&mut self, seat: usize) {
+        self.players[seat] = None;
+        self.stacks[seat] = 0;
+        self.emit(Update::SeatLeft { seat });
+    }
+
+    /// Gives open seats to the players waiting for them, then deals if
+    /// that makes enough players.
+    fn fill(&mut self) -> Result<(), TableError> {
+        while !self.waiting.is_empty() {
+            let Some(seat) = self.open_seat() else {
+                break;
+            };
+            let player = self.waiting.pop_front().expect("a player waiting");
+            self.take_seat(seat, player);
+        }
+        self.deal()
+    }
+
+    /// Deals a hand if none is being played and enough players have chips.
+    fn deal(&mut self) -> Result<(), TableError> {
+        let ready = self.stacks.iter().filter(|&&s| s > 0).count();
+        if self.engine.is_some() || ready < self.settings.start {
+            return Ok(());
+        }
+        let seats = self.stacks.len();
+        if self.hands > 0 {
+            self.button = (1..=seats)
+                .map(|i| (self.button + i) % seats)
+                .find(|&s| self.stacks[s] > 0)
+                .unwrap_or(self.button);
+        } else if self.stacks[self.button] == 0 {
+            self.button = self.stacks.iter().position(|&s| s > 0).unwrap_or(0);
+        }
+        let config = EngineConfig {
+            stacks: self.stacks.clone(),
+            blinds: self.settings.blinds.clone(),
+            ante: self.settings.ante,
+            button: self.button,
+            shuffler: Shuffler::committed(),
+            ..EngineConfig::new(seats)
+        };
+        let engine = GameEngine::with_config(self.game.clone(), config)?;
+        self.hands += 1;
+        self.started = auth::now();
+        self.recent.clear();
+        self.emit(Update::HandStarted {
+            hand: self.hands,
+            button: self.button,
+            stacks: self.stacks.clone(),
+        });
+        self.engine = Some(engine);
+        self.dealt = self.stacks.iter().map(|&s| s > 0).collect();
+        self.sent = 0;
+        self.holes = vec![Vec::new(); seats];
+        self.advance()
+    }
+
+    /// Sends the engine's new events and any cards they don't account
+    /// for, then whose turn it is or, if the hand is over, how it left the
+    /// stacks, and deals the next one. Players who have left are acted for
+    /// rather than waited on.
+    fn advance(&mut self) -> Result<(), TableError> {
+        let Some(engine) = &self.engine else {
+            return Ok(());
+        };
+        let events = engine.events()[self.sent..].to_vec();
+        self.sent += events.len();
+        for event in events {
+            if let Event::HoleCardsDealt { seat, cards } = &event {
+                self.holes[*seat].extend(cards);
+            }
+            self.emit(Update::Game(event));
+        }
+        let engine = self.engine.as_ref().expect("hand in progress");
+        let changed: Vec<(usize, Vec<DealtCard>)> = engine
+            .seats()
+            .iter()
+            .enumerate()
+            .filter(|(seat, s)| s.hole != self.holes[*seat])
+            .map(|(seat, s)| (seat, s.hole.clone()))
+            .collect();
+        for (seat, cards) in changed {
+            self.holes[seat] = cards.clone();
+            self.emit(Update::Hole { seat, cards });
+        }
+        let engine = self.engine.as_ref().expect("hand in progress");
+        if engine.status() == HandStatus::InProgress {
+            if let Some(seat) = engine.to_act() {
+                let leaving = self.players[seat].as_ref().is_some_and(|p| p.leaving);
+                if leaving && self.act_for(seat, Timeout::Fold) {
+                    return self.advance();
+                }
+                let engine = self.engine.as_ref().expect("hand in progress");
+                let legal = engine.legal_actions(seat);
+                let time = self.time_to_act(seat);
+                self.turn_started = Instant::now();
+                self.emit(Update::Turn { seat, legal, time });
+            }
+            return Ok(());
+        }
+        // Players who sat down during the hand keep the chips they came with.
+        for (seat, s) in engine.seats().iter().enumerate() {
+            if self.dealt[seat] {
+                self.stacks[seat] = s.stack;
+            }
+        }
+        self.record(engine);
+        self.engine = None;
+        self.emit(Update::HandEnded {
+            stacks: self.stacks.clone(),
+        });
+        for seat in 0..self.players.len() {
+            if self.players[seat].as_ref().is_some_and(|p| p.leaving) {
+                self.vacate(seat);
+            }
+        }
+        self.fill()
+    }
+
+    /// Keeps the hand `engine` has played to the end in the history.
+    fn record(&self, engine: &GameEngine) {
+        let Some((history, table_id)) = &self.history else {
+            return;
+        };
+        let warn = |e: &dyn fmt::Display| {
+            eprintln!(
+                "warning: can't keep hand {} at table {}: {}",
+                self.hands, table_id, e
+            );
+        };
+        let state = match CompactState::capture(engine) {
+            Ok(state) => state,
+            Err(e) => return warn(&e),
+        };
+        let players = (0..self.players.len())
+            .map(|seat| {
+                let player = self.players[seat].as_ref().filter(|_| self.dealt[seat])?;
+                Some(history::Seated {
+                    name: player.name.clone(),
+                    user: player.user.clone(),
+                })
+            })
+            .collect();
+        let hand = history::Hand {
+            table_id: table_id.clone(),
+            hand: self.hands,
+            started: self.started,
+            ended: auth::now(),
+            players,
+            game: self.game.clone(),
+            state,
+        };
+        if let Err(e) = history.record(hand) {
+            warn(&e);
+        }
+    }
+
+    /// Plays for the player in `seat`, who isn't there, as `policy` says.
+    /// False if the engine won't have it.
+    fn act_for(&mut self, seat: usize, policy: Timeout) -> bool {
+        let Some(engine) = self.engine.as_mut() else {
+            return false;
+        };
+        let legal = engine.legal_actions(seat);
+        let action = stand_in(&legal, &engine.seats()[seat].hole, policy);
+        engine.apply_action(seat, action).is_ok()
+    }
+
+    /// The table as `audience` may see it, with the hand in progress
+    /// taken from the engine's view for them.
+    fn snapshot(&self, audience: Audience) -> TableSnapshot {
+        let engine = self.engine.as_ref();
+        let view = engine.map(|e| e.view_for(audience));
+        let seats = (0..self.settings.seats)
+            .map(|seat| {
+                let player = self.players[seat].as_ref();
+                let dealt = view
+                    .as_ref()
+                    .filter(|_| self.dealt[seat])
+                    .map(|v| &v.seats[seat]);
+                SeatSnapshot {
+                    name: player.map(|p| p.name.clone()).unwrap_or_default(),
+                    stack: dealt.map_or(self.stacks[seat] as i64, |s| s.stack),
+                    dealt: dealt.is_some(),
+                    folded: dealt.is_some_and(|s| s.folded),
+                    cards: dealt.map(|s| HoleCards {
+                        seat: seat as i32,
+                        cards: s.cards.clone(),
+                        face_up: s.face_up.clone(),
+                        hidden: s.hidden,
+                    }),
+                    away: player.is_some_and(|p| p.away.is_some()),
+                    reserved: self.reserved[seat].is_some(),
+                }
+            })
+            .collect();
+        let boards = view.as_ref().map_or(Vec::new(), |v| {
+            v.boards
+                .iter()
+                .enumerate()
+                .map(|(board, b)| BoardCards {
+                    board: board as i32,
+                    cards: b.cards.clone(),
+                })
+                .collect()
+        });
+        let turn = engine.and_then(|e| {
+            e.to_act().map(|seat| TurnToAct {
+                seat: seat as i32,
+                legal: e
+                    .legal_actions(seat)
+                    .iter()
+                    .map(wire::legal_action)
+                    .collect(),
+                time_ms: wire::millis(
+                    self.time_to_act(seat)
+                        .saturating_sub(self.turn_started.elapsed()),
+                ),
+            })
+        });
+        TableSnapshot {
+            hands: self.hands,
+            playing: engine.is_some(),
+            button: self.button as i32,
+            seats,
+            boards,
+            pot: engine.map_or(0, GameEngine::pot) as i64,
+            turn,
+        }
+    }
+
+    /// Sends `update` to every watcher, or holds it back for those who are
+    /// delayed, dropping those that have gone. Chat is blanked for
+    /// watchers who have muted the speaker, rather than left out, so that
+    /// their events still come in sequence.
+    fn emit(&mut self, update: Update) {
+        self.sequence += 1;
+        let sequence = self.sequence;
+        let players = &self.players;
+        let waiting = &self.waiting;
+        let speaker = says(&update);
+        let now = Instant::now();
+        self.watchers.retain_mut(|w| {
+            let seat = w.player.as_deref().and_then(|id| seat_in(players, id));
+            let audience = seat.map_or(Audience::Spectator, Audience::Seat);
+            let mut event = wire::table_event(sequence, &update, audience);
+            let watcher = w
+                .player
+                .as_deref()
+                .and_then(|id| find(players, waiting, id));
+            if speaker.is_some_and(|name| watcher.is_some_and(|p| p.muted.contains(name))) {
+                wire::mute(&mut event);
+            }
+            match &mut w.delay {
+                Some(delay) => {
+                    delay.hold(now, event);
+                    !w.sink.closed()
+                }
+                None => w.sink.send(event),
+            }
+        });
+        self.recent.push((sequence, update));
+    }
+}
+
+/// How long the spectator streaming for `request` wants each event held
+/// back, if at all.
+pub fn delay(request: &StreamEventsRequest) -> Result<Option<Duration>, TableError> {
+    let bad = |message: &str| Err(TableError::BadRequest(message.to_string()));
+    let delay = match u64::try_from(request.delay_seconds) {
+        Ok(0) => return Ok(None),
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => return bad("negative delay"),
+    };
+    if delay > MAX_DELAY {
+        return bad("delay longer than an hour");
+    }
+    if !request.player_id.is_empty() {
+        return bad("only spectators may be delayed");
+    }
+    if request.after > 0 {
+        return bad("a delayed stream can't be resumed");
+    }
+    Ok(Some(delay))
+}
+
+fn seat_in(players: &[Option<Player>], id: &str) -> Option<usize> {
+    players
+        .iter()
+        .position(|p| p.as_ref().is_some_and(|p| p.id == id))
+}
+
+/// The player seated or waiting with id `id`.
+fn find<'a>(
+    players: &'a [Option<Player>],
+    waiting: &'a VecDeque<Player>,
+    id: &str,
+) -> Option<&'a Player> {
+    players.iter().flatten().chain(waiting).find(|p| p.id == id)
+}
+
+/// Who said `update`, if it is chat.
+fn says(update: &Update) -> Option<&str> {
+    match update {
+        Update::Chat { name, .. } => Some(name),
+        _ => None,
+    }
+}
+
+/// The action of a player who isn't there, given their options `legal`
+/// and cards `hole`: a check if `policy` allows it and then a fold, if
+/// they may, and otherwise as little as the rules allow.
+fn stand_in(legal: &[LegalAction], hole: &[DealtCard], policy: Timeout) -> Action {
+    if policy == Timeout::CheckOrFold && legal.contains(&LegalAction::Check) {
+        return Action::Check;
+    }
+    if legal.contains(&LegalAction::Fold) {
+        return Action::Fold;
+    }
+    match legal.first() {
+        Some(LegalAction::Discard { .. }) => Action::Discard(Vec::new()),
+        Some(LegalAction::Pass { count }) => {
+            Action::Pass(hole.iter().take(*count).map(|c| c.card).collect())
+        }
+        Some(LegalAction::Reveal { count }) => Action::Reveal(
+            hole.iter()
+                .filter(|c| !c.face_up)
+                .take(*count)
+                .map(|c| c.card)
+                .collect(),
+        ),
+        Some(LegalAction::Declare) => Action::Declare(Side::High),
+        _ => Action::Fold,
+    }
+}
+
+/// A new player's id: 128 random bits in hex.
+fn player_id() -> String {
+    let mut rng = OsEntropy::new().expect("system random number generator unavailable");
+    format!("{:016x}{:016x}", rng.next_u64(), rng.next_u64())
+}
+
+/// Every open table, by id, and the hands played at them.
+#[derive(Default)]
+pub struct Tables {
+    tables: Mutex<HashMap<String, Arc<Mutex<Table>>>>,
+    opened: AtomicU64,
+    history: Arc<History>,
+    /// As [`Limits::slow_turns`] says.
+    slow_turns: u32,
+    /// What judges chat before it is sent, if anything does.
+    filter: Option<Arc<dyn ChatFilter>>,
+}
+
+impl Tables {
+    /// No tables yet, keeping their hands in `history` and putting slow
+    /// players on the clock as `limits` says.
+    pub fn new(history: History, limits: &Limits) -> Self {
+        Tables {
+            history: Arc::new(history),
+            slow_turns: limits.slow_turns,
+            ..Tables::default()
+        }
+    }
+
+    /// The same, with every message said at the tables judged by `filter`.
+    pub fn with_chat_filter(self, filter: Arc<dyn ChatFilter>) -> Self {
+        Tables {
+            filter: Some(filter),
+            ..self
+        }
+    }
+
+    /// Adds `table`, returning its id.
+    pub fn open(&self, mut table: Table) -> String {
+        let id = format!("t{}", self.opened.fetch_add(1, Ordering::Relaxed) + 1);
+        table.history = Some((Arc::clone(&self.history), id.clone()));
+        table.slow_turns = self.slow_turns;
+        self.tables
+            .lock()
+            .expect("tables lock")
+            .insert(id.clone(), Arc::new(Mutex::new(table)));
+        id
+    }
+
+    pub fn get(&self, id: &str) -> Result<Arc<Mutex<Table>>, TableError> {
+        self.tables
+            .lock()
+            .expect("tables lock")
+            .get(id)
+            .cloned()
+            .ok_or_else(|| TableError::NoSuchTable(id.to_string()))
+    }
+
+    /// Every table with its id, in the order they were opened.
+    fn all(&self) -> Vec<(String, Arc<Mutex<Table>>)> {
+        let mut tables: Vec<(String, Arc<Mutex<Table>>)> = self
+            .tables
+            .lock()
+            .expect("tables lock")
+            .iter()
+            .map(|(id, table)| (id.clone(), Arc::clone(table)))
+            .collect();
+        // Ids count up, so shorter ones are older.
+        tables.sort_by(|(a, _), (b, _)| (a.len(), a).cmp(&(b.len(), b)));
+        tables
+    }
+
+    /// Opens the table a `CreateTable` request asks for.
+    pub fn create(&self, request: CreateTableRequest) -> Result<CreateTableResponse, TableError> {
+        let on_timeout = request.on_timeout();
+        let game = request
+            .game
+            .ok_or_else(|| TableError::BadRequest("no game".to_string()))?;
+        let mut blinds = request
+            .blinds
+            .iter()
+            .map(|&b| chips(b, "blind"))
+            .collect::<Result<Vec<_>, _>>()?;
+        if blinds.is_empty() {
+            blinds = vec![1, 2];
+        }
+        let settings = Settings {
+            seats: usize::try_from(request.seats).unwrap_or(0),
+            stack: chips(request.stack, "stack")?,
+            blinds,
+            ante: chips(request.ante, "ante")?,
+            start: match request.start {
+                0 => 2,
+                start => usize::try_from(start).unwrap_or(0),
+            },
+            timeout: match request.timeout_seconds {
+                0 => Duration::from_secs(30),
+                seconds => Duration::from_secs(
+                    u64::try_from(seconds)
+                        .map_err(|_| TableError::BadRequest("negative timeout".to_string()))?,
+                ),
+            },
+            on_timeout,
+        };
+        Ok(CreateTableResponse {
+            table_id: self.open(Table::new(game, settings)?),
+        })
+    }
+
+    /// The tables a `ListTables` request asks for, in the order they were
+    /// opened.
+    pub fn list(&self, request: &ListTablesRequest) -> ListTablesResponse {
+        let tables = self
+            .all()
+            .iter()
+            .map(|(id, table)| lock(table).summary(id))
+            .filter(|t| request.game_id.is_empty() || t.game_id == request.game_id)
+            .filter(|t| !request.open || t.seated + t.reserved < t.seats)
+            .collect();
+        ListTablesResponse { tables }
+    }
+
+    pub fn reserve(&self, request: &ReserveSeatRequest) -> Result<ReserveSeatResponse, TableError> {
+        let table = self.get(&request.table_id)?;
+        let (seat, reservation) = lock(&table).reserve()?;
+        Ok(ReserveSeatResponse {
+            seat: seat as i32,
+            reservation,
+            seconds: RESERVATION.as_secs() as i32,
+        })
+    }
+
+    /// Seats the player a `JoinTable` request from `user` is for, or puts
+    /// them on the waitlist if it says to wait and there is no seat.
+    pub fn join(
+        &self,
+        request: &JoinTableRequest,
+        user: Option<&str>,
+    ) -> Result<JoinTableResponse, TableError> {
+        let table = self.get(&request.table_id)?;
+        let mut table = lock(&table);
+        let joined = match request.reservation.as_str() {
+            "" => table.join(&request.name, user),
+            reservation => table.claim(reservation, &request.name, user),
+        };
+        match joined {
+            Ok((seat, player_id)) => Ok(JoinTableResponse {
+                seat: seat as i32,
+                player_id,
+                waiting: 0,
+            }),
+            Err(TableError::Full) if request.wait => {
+                let (place, player_id) = table.wait(&request.name, user);
+                Ok(JoinTableResponse {
+                    seat: -1,
+                    player_id,
+                    waiting: place as i32,
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn leave(&self, request: &LeaveTableRequest, user: Option<&str>) -> Result<(), TableError> {
+        let table = self.get(&request.table_id)?;
+        let mut table = lock(&table);
+        table.authorize(&request.player_id, user)?;
+        table.leave(&request.player_id)
+    }
+
+    /// The hands played to the end that `request` asks for, as `user`
+    /// may see them.
+    pub fn hands(
+        &self,
+        request: &ListHandsRequest,
+        user: Option<&str>,
+    ) -> Result<ListHandsResponse, TableError> {
+        self.history.list(request, user)
+    }
+
+    /// Ticks every table's clock, as [`Table::tick`] says.
+    pub fn tick(&self) {
+        let now = Instant::now();
+        for (id, table) in self.all() {
+            if let Err(e) = lock(&table).tick(now) {
+                eprintln!("warning: table {}: {}", id, e);
+            }
+        }
+    }
+
+    pub fn submit(
+        &self,
+        request: &SubmitActionRequest,
+        user: Option<&str>,
+    ) -> Result<(), TableError> {
+        let table = self.get(&request.table_id)?;
+        let message = request
+            .action
+            .as_ref()
+            .ok_or_else(|| TableError::BadRequest("no action".to_string()))?;
+        let action =
+            Action::try_from(message).map_err(|e| TableError::BadRequest(e.to_string()))?;
+        let mut table = lock(&table);
+        table.authorize(&request.player_id, user)?;
+        table.act(&request.player_id, action)
+    }
+
+    /// Sends what a `SendChat` request from `user` says, once it passes
+    /// the chat filter.
+    pub fn chat(&self, request: &SendChatRequest, user: Option<&str>) -> Result<(), TableError> {
+        let table = self.get(&request.table_id)?;
+        let mut table = lock(&table);
+        table.authorize(&request.player_id, user)?;
+        let emote = request.emote();
+        let mut text = chat::check(&request.text, emote)?;
+        if let Some(filter) = self.filter.as_ref().filter(|_| !text.is_empty()) {
+            let speaker = find(&table.players, &table.waiting, &request.player_id);
+            let said = Said {
+                table_id: &request.table_id,
+                name: &speaker.expect("authorized").name,
+                user,
+                text: &text,
+            };
+            match filter.judge(&said) {
+                Verdict::Allow => {}
+                Verdict::Replace(replaced) => text = replaced,
+                Verdict::Reject(reason) => return Err(TableError::Moderated(reason)),
+            }
+        }
+        table.chat(&request.player_id, text, emote)
+    }
+
+    pub fn mute(&self, request: &MuteRequest, user: Option<&str>) -> Result<(), TableError> {
+        let table = self.get(&request.table_id)?;
+        let mut table = lock(&table);
+        table.authorize(&request.player_id, user)?;
+        table.mute(&request.player_id, &request.name, request.muted)
+    }
+
+    /// Takes the round trip a `Ping` request from `user` reports, if it
+    /// has one, as [`Table::latency`] does.
+    pub fn ping(&self, request: &PingRequest, user: Option<&str>) -> Result<(), TableError> {
+        let table = self.get(&request.table_id)?;
+        let mut table = lock(&table);
+        table.authorize(&request.player_id, user)?;
+        match request.round_trip_ms {
+            0 => Ok(()),
+            ms => table.latency(&request.player_id, Duration::from_millis(ms.into())),
+        }
+    }
+
+    /// Takes `round_trip` as the latency of the player with id `player`
+    /// at table `table_id`, measured by the server itself.
+    pub fn latency(&self, table_id: &str, player: &str, round_trip: Duration) {
+        if let Ok(table) = self.get(table_id) {
+            let _ = lock(&table).latency(player, round_trip);
+        }
+    }
+}
+
+pub fn lock(table: &Mutex<Table>) -> MutexGuard<'_, Table> {
+    table.lock().expect("table lock")
+}
+
+fn chips(amount: i64, field: &str) -> Result<Chips, TableError> {
+    Chips::try_from(amount).map_err(|_| TableError::BadRequest(format!("negative {}", field)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustlib::game_event;
+    use rustlib::presets;
+    use service_proto3::snapfold::server::{table_event, Chat, Presence, SeatTaken};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::mpsc;
+
+    fn settings() -> Settings {
+        Settings {
+            seats: 3,
+            stack: 100,
+            blinds: vec![1, 2],
+            ante: 0,
+            start: 2,
+            timeout: Duration::from_secs(30),
+            on_timeout: Timeout::CheckOrFold,
+        }
+    }
+
+    /// A sink that is closed once `closed` is set.
+    struct Tap {
+        events: mpsc::Sender<TableEvent>,
+        closed: Arc<AtomicBool>,
+    }
+
+    impl Sink for Tap {
+        fn send(&mut self, event: TableEvent) -> bool {
+            self.events.send(event).is_ok()
+        }
+
+        fn closed(&self) -> bool {
+            self.closed.load(Ordering::Relaxed)
+        }
+    }
+
+    fn sink() -> (Box<dyn Sink>, mpsc::Receiver<TableEvent>) {
+        let (events, rx) = mpsc::channel();
+        let closed = Arc::default();
+        (Box::new(Tap { events, closed }), rx)
+    }
+
+    #[test]
+    fn test_table() {
+        let mut table = Table::new(presets::holdem(), settings()).unwrap();
+        let (watching, watched) = sink();
+        table.watch(None, None, watching).unwrap();
+        let (alice, alice_id) = table.join("alice", None).unwrap();
+        assert_eq!(alice, 0);
+        assert!(table.engine.is_none());
+        let (bob, bob_id) = table.join("bob", None).unwrap();
+        assert_eq!(bob, 1);
+        assert!(table.engine.is_some());
+
+        // Alice joins her stream late, and is caught up on the hand.
+        let (mine, seen) = sink();
+        table.watch(Some(&alice_id), None, mine).unwrap();
+        let kinds = |rx: &mpsc::Receiver<TableEvent>| -> Vec<table_event::Kind> {
+            rx.try_iter().filter_map(|e| e.kind).collect()
+        };
+        let hole = |kinds: &[table_event::Kind], seat: i32| {
+            kinds
+                .iter()
+                .find_map(|k| match k {
+                    table_event::Kind::Game(g) => match &g.kind {
+                        Some(game_event::Kind::HoleCardsDealt(d)) if d.seat == seat => {
+                            Some(d.clone())
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .unwrap()
+        };
+        let public = kinds(&watched);
+        assert!(matches!(
+            public[..2],
+            [
+                table_event::Kind::SeatTaken(_),
+                table_event::Kind::SeatTaken(_)
+            ]
+        ));
+        assert_eq!(hole(&public, 0).cards, []);
+        assert_eq!(hole(&public, 0).hidden, 2);
+        let own = kinds(&seen);
+        assert!(matches!(own[0], table_event::Kind::HandStarted(_)));
+        assert_eq!(hole(&own, 0).cards.len(), 2);
+        assert_eq!(hole(&own, 1).cards, []);
+        let Some(table_event::Kind::Turn(turn)) = own.last() else {
+            panic!("{:?}", own.last());
+        };
+
+        // Whoever is first to act folds, which ends the hand and deals
+        // the next.
+        let (first, other) = match turn.seat {
+            0 => (&alice_id, &bob_id),
+            _ => (&bob_id, &alice_id),
+        };
+        assert_eq!(
+            table.act(other, Action::Fold),
+            Err(TableError::Engine(EngineError::NotYourTurn {
+                seat: 1 - turn.seat as usize,
+                to_act: turn.seat as usize,
+            }))
+        );
+        table.act(first, Action::Fold).unwrap();
+        let after = kinds(&seen);
+        let ended = after
+            .iter()
+            .position(|k| matches!(k, table_event::Kind::HandEnded(_)))
+            .unwrap();
+        assert!(matches!(
+            after[ended + 1],
+            table_event::Kind::HandStarted(ref h) if h.hand == 2
+        ));
+        assert_eq!(table.stacks.iter().sum::<Chips>(), 200);
+
+        assert_eq!(
+            table.act("nobody", Action::Fold),
+            Err(TableError::UnknownPlayer)
+        );
+        // A stream that has gone is dropped.
+        drop(watched);
+        table.join("carol", None).unwrap();
+        assert_eq!(table.watchers.len(), 1);
+        assert_eq!(table.join("dave", None), Err(TableError::Full));
+    }
+
+    #[test]
+    fn test_tables() {
+        let tables = Tables::default();
+        let bad = Settings {
+            seats: 11,
+            ..settings()
+        };
+        assert!(matches!(
+            Table::new(presets::holdem(), bad),
+            Err(TableError::Settings(_))
+        ));
+        // Eight stud hands would take 56 cards.
+        let eight = Settings {
+            seats: 8,
+            ..settings()
+        };
+        assert!(matches!(
+            Table::new(presets::seven_card_stud(), eight),
+            Err(TableError::Settings(_))
+        ));
+        let id = tables.open(Table::new(presets::holdem(), settings()).unwrap());
+        assert_eq!(id, "t1");
+        assert!(tables.get("t1").is_ok());
+        assert!(matches!(tables.get("t2"), Err(TableError::NoSuchTable(_))));
+
+        let heads_up = Settings {
+            seats: 2,
+            ..settings()
+        };
+        tables.open(Table::new(presets::omaha(), heads_up).unwrap());
+        for id in ["t1", "t2", "t2"] {
+            let request = JoinTableRequest {
+                table_id: id.to_string(),
+                name: "alice".to_string(),
+                ..Default::default()
+            };
+            tables.join(&request, None).unwrap();
+        }
+        let listed = |game_id: &str, open| -> Vec<String> {
+            let request = ListTablesRequest {
+                game_id: game_id.to_string(),
+                open,
+            };
+            let list = tables.list(&request);
+            list.tables.into_iter().map(|t| t.table_id).collect()
+        };
+        assert_eq!(listed("", false), ["t1", "t2"]);
+        assert_eq!(listed(&presets::omaha().id, false), ["t2"]);
+        assert_eq!(listed("", true), ["t1"]);
+        let summary = &tables.list(&ListTablesRequest::default()).tables[1];
+        assert_eq!((summary.seats, summary.seated, summary.hands), (2, 2, 1));
+        assert_eq!(summary.blinds, [1, 2]);
+    }
+
+    #[test]
+    fn test_lobby() {
+        let heads_up = Settings {
+            seats: 2,
+            ..settings()
+        };
+        let mut table = Table::new(presets::holdem(), heads_up).unwrap();
+
+        // A reserved seat isn't given to anyone else, and a full table has
+        // a waitlist.
+        let (reserved, reservation) = table.reserve().unwrap();
+        assert_eq!(reserved, 0);
+        let (alice, alice_id) = table.join("alice", None).unwrap();
+        assert_eq!(alice, 1);
+        assert_eq!(table.join("bob", None), Err(TableError::Full));
+        assert!(table.engine.is_none());
+        let (place, carol_id) = table.wait("carol", None);
+        assert_eq!(place, 1);
+        let (erin_place, erin_id) = table.wait("erin", None);
+        assert_eq!(erin_place, 2);
+        let summary = table.summary("t1");
+        assert_eq!(
+            (summary.seated, summary.reserved, summary.waiting),
+            (1, 1, 2)
+        );
+        let (watching, seen) = sink();
+        table.watch(Some(&carol_id), None, watching).unwrap();
+
+        // Once the reservation runs out, carol gets the seat, which deals.
+        assert_eq!(
+            table.claim("nonsense", "dave", None),
+            Err(TableError::UnknownReservation)
+        );
+        table.expire(Instant::now() + RESERVATION).unwrap();
+        assert_eq!(
+            table.claim(&reservation, "dave", None),
+            Err(TableError::UnknownReservation)
+        );
+        assert_eq!(table.seat_of(&carol_id), Ok(0));
+        assert_eq!(table.hands, 1);
+        let taken: Vec<SeatTaken> = seen
+            .try_iter()
+            .filter_map(|e| match e.kind {
+                Some(table_event::Kind::SeatTaken(taken)) => Some(taken),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            taken.iter().map(|t| (t.seat, t.yours)).collect::<Vec<_>>(),
+            [(1, false), (0, true)]
+        );
+
+        // Alice leaves in the middle of the hand. She folds if she is to
+        // act, and gives up her seat to erin when the hand ends.
+        table.leave(&alice_id).unwrap();
+        assert_eq!(table.seat_of(&alice_id), Ok(1));
+        if table.hands == 1 {
+            table.act(&carol_id, Action::Fold).unwrap();
+        }
+        assert_eq!(table.seat_of(&alice_id), Err(TableError::UnknownPlayer));
+        assert_eq!(table.seat_of(&erin_id), Ok(1));
+        assert_eq!(table.stacks[1], 100);
+        assert_eq!(table.hands, 2);
+        assert!(seen
+            .try_iter()
+            .any(|e| e.kind == Some(table_event::Kind::SeatLeft(1))));
+        assert_eq!(table.leave("nobody"), Err(TableError::UnknownPlayer));
+    }
+
+    #[test]
+    fn test_slow_turns() {
+        let heads_up = Settings {
+            seats: 2,
+            ..settings()
+        };
+        let mut table = Table::new(presets::holdem(), heads_up).unwrap();
+        table.slow_turns = 2;
+        let (_, alice_id) = table.join("alice", None).unwrap();
+        let (_, bob_id) = table.join("bob", None).unwrap();
+        let ids = [alice_id, bob_id];
+        let timeout = table.settings.timeout;
+        let to_act = |table: &Table| table.engine.as_ref().unwrap().to_act().unwrap();
+        // The player to act checks or calls, after taking too long if `slow`.
+        let turn = |table: &mut Table, slow: bool| {
+            let seat = to_act(table);
+            if slow {
+                table.turn_started -= timeout + Duration::from_secs(1);
+            }
+            let legal = table.engine.as_ref().unwrap().legal_actions(seat);
+            let action = if legal.contains(&LegalAction::Check) {
+                Action::Check
+            } else {
+                Action::Call
+            };
+            table.act(&ids[seat], action).unwrap();
+        };
+
+        // Both players take too long over two turns each, to the flop and
+        // past it, and are put on the clock.
+        for _ in 0..4 {
+            turn(&mut table, true);
+        }
+        let first = to_act(&table);
+        let started = table.turn_started;
+        table
+            .tick(started + timeout - Duration::from_secs(1))
+            .unwrap();
+        assert_eq!(to_act(&table), first);
+        table.tick(started + timeout).unwrap();
+        assert_ne!(to_act(&table), first);
+
+        // Acting in time takes a player off the clock.
+        let second = to_act(&table);
+        turn(&mut table, false);
+        assert_eq!(to_act(&table), first);
+        table.tick(table.turn_started + timeout).unwrap();
+        assert_eq!(to_act(&table), second);
+        table.tick(table.turn_started + timeout * 2).unwrap();
+        assert_eq!(to_act(&table), second);
+    }
+
+    #[test]
+    fn test_latency() {
+        let heads_up = Settings {
+            seats: 2,
+            ..settings()
+        };
+        let mut table = Table::new(presets::holdem(), heads_up).unwrap();
+        let (_, alice_id) = table.join("alice", None).unwrap();
+        let (_, bob_id) = table.join("bob", None).unwrap();
+        let ids = [alice_id, bob_id];
+        let timeout = table.settings.timeout;
+        let to_act = |table: &Table| table.engine.as_ref().unwrap().to_act().unwrap();
+        let (tap, rx) = sink();
+        table.watch(None, None, tap).unwrap();
+        let last_turn = |rx: &mpsc::Receiver<TableEvent>| {
+            rx.try_iter()
+                .filter_map(|e| match e.kind {
+                    Some(table_event::Kind::Turn(turn)) => Some((turn.seat as usize, turn.time_ms)),
+                    _ => None,
+                })
+                .last()
+                .unwrap()
+        };
+        let first = to_act(&table);
+        assert_eq!(last_turn(&rx), (first, 30_000));
+
+        // A player's round trip is allowed for from their next turn, up to
+        // the most there may be.
+        let lag = Duration::from_millis(800);
+        for id in &ids {
+            table.latency(id, lag).unwrap();
+        }
+        assert_eq!(table.latency("nobody", lag), Err(TableError::UnknownPlayer));
+        table.act(&ids[first], Action::Call).unwrap();
+        let second = to_act(&table);
+        assert_eq!(last_turn(&rx), (second, 30_800));
+        let time = table.snapshot(Audience::Seat(second)).turn.unwrap().time_ms;
+        assert!((30_000..=30_800).contains(&time), "{}", time);
+        for id in &ids {
+            table.latency(id, Duration::from_secs(60)).unwrap();
+        }
+        table.act(&ids[second], Action::Check).unwrap();
+        assert_eq!(last_turn(&rx), (to_act(&table), 35_000));
+
+        // An away player is acted for once their time, allowance and
+        // all, has run out.
+        let seat = to_act(&table);
+        let started = table.turn_started;
+        table.players[seat].as_mut().unwrap().away = Some(started);
+        table.tick(started + timeout).unwrap();
+        assert_eq!(to_act(&table), seat);
+        table.tick(started + timeout + MAX_LATENCY).unwrap();
+        assert_ne!(to_act(&table), seat);
+    }
+
+    #[test]
+    fn test_resume() {
+        let heads_up = Settings {
+            seats: 2,
+            ..settings()
+        };
+        let mut table = Table::new(presets::holdem(), heads_up).unwrap();
+        let (_, alice_id) = table.join("alice", None).unwrap();
+        let (_, bob_id) = table.join("bob", None).unwrap();
+        let seat = table.engine.as_ref().unwrap().to_act().unwrap();
+        let id = [&alice_id, &bob_id][seat];
+        let (events, seen) = mpsc::channel();
+        let closed = Arc::new(AtomicBool::new(false));
+        let tap = Tap {
+            events,
+            closed: Arc::clone(&closed),
+        };
+        table.watch(Some(id), None, Box::new(tap)).unwrap();
+        let last = seen.try_iter().last().unwrap().sequence;
+
+        // The player to act loses their stream. Their seat is held until
+        // the timeout, when they fold to the big blind.
+        closed.store(true, Ordering::Relaxed);
+        let now = Instant::now();
+        table.tick(now).unwrap();
+        assert!(table.players[seat].as_ref().unwrap().away.is_some());
+        table.tick(now + Duration::from_secs(29)).unwrap();
+        assert_eq!(table.hands, 1);
+        table.tick(now + Duration::from_secs(30)).unwrap();
+        assert_eq!(table.hands, 2);
+
+        // On coming back they are sent what they missed of the new hand,
+        // then the table as it is now.
+        let (events, seen) = sink();
+        table.watch(Some(id), Some(last), events).unwrap();
+        let resumed: Vec<TableEvent> = seen.try_iter().collect();
+        assert!(resumed.iter().all(|e| e.sequence > last));
+        assert!(resumed.iter().any(|e| e.kind
+            == Some(table_event::Kind::Presence(Presence {
+                seat: seat as i32,
+                away: false,
+            }))));
+        let (snapshot, replayed) = resumed.split_last().unwrap();
+        assert_eq!(snapshot.sequence, replayed.last().unwrap().sequence);
+        let Some(table_event::Kind::Snapshot(snapshot)) = &snapshot.kind else {
+            panic!("no snapshot: {:?}", snapshot);
+        };
+        assert_eq!(snapshot.hands, 2);
+        assert!(snapshot.playing);
+        let mine = &snapshot.seats[seat];
+        assert!(mine.dealt && !mine.away);
+        assert_eq!(mine.cards.as_ref().unwrap().cards.len(), 2);
+        assert_eq!(snapshot.seats[1 - seat].cards.as_ref().unwrap().hidden, 2);
+        assert_eq!(snapshot.pot, 3);
+        assert!(snapshot.turn.is_some());
+    }
+
+    #[test]
+    fn test_spectate() {
+        let mut table = Table::new(presets::holdem(), settings()).unwrap();
+        let (live, seen) = sink();
+        table.watch(None, None, live).unwrap();
+        let (delayed, held) = sink();
+        table.spectate(Duration::from_secs(10), delayed);
+        table.join("alice", None).unwrap();
+        let now = Instant::now();
+        table.join("bob", None).unwrap();
+
+        // Spectators never see a face-down card, and the delayed one sees
+        // the hand only once the delay has passed.
+        let hidden = |events: &[TableEvent]| {
+            events.iter().all(|e| match &e.kind {
+                Some(table_event::Kind::Game(g)) => match &g.kind {
+                    Some(game_event::Kind::HoleCardsDealt(d)) => d.cards.is_empty(),
+                    _ => true,
+                },
+                _ => true,
+            })
+        };
+        let live: Vec<TableEvent> = seen.try_iter().collect();
+        assert!(hidden(&live));
+        table.tick(now).unwrap();
+        assert_eq!(held.try_iter().count(), 0);
+        table.tick(now + Duration::from_secs(11)).unwrap();
+        let delayed: Vec<TableEvent> = held.try_iter().collect();
+        assert!(hidden(&delayed));
+        // It starts with the first hand, after the seats were taken.
+        assert!(matches!(
+            delayed[0].kind,
+            Some(table_event::Kind::HandStarted(_))
+        ));
+        assert_eq!(delayed, live[live.len() - delayed.len()..]);
+
+        let request = |player: &str, after, delay_seconds| StreamEventsRequest {
+            table_id: "t1".to_string(),
+            player_id: player.to_string(),
+            after,
+            delay_seconds,
+        };
+        assert_eq!(delay(&request("", 0, 0)), Ok(None));
+        assert_eq!(
+            delay(&request("", 0, 30)),
+            Ok(Some(Duration::from_secs(30)))
+        );
+        assert!(delay(&request("abc", 0, 30)).is_err());
+        assert!(delay(&request("", 4, 30)).is_err());
+        assert!(delay(&request("", 0, -1)).is_err());
+        assert!(delay(&request("", 0, 3601)).is_err());
+    }
+
+    #[test]
+    fn test_authorize() {
+        let tables = Tables::default();
+        let id = tables.open(Table::new(presets::holdem(), settings()).unwrap());
+        let join = |user| {
+            let request = JoinTableRequest {
+                table_id: id.clone(),
+                ..Default::default()
+            };
+            tables.join(&request, user).unwrap().player_id
+        };
+        let alice = join(Some("alice"));
+        let anyone = join(None);
+        let table = tables.get(&id).unwrap();
+        let table = lock(&table);
+        assert_eq!(table.players[0].as_ref().unwrap().name, "alice");
+        assert_eq!(table.authorize(&alice, Some("alice")), Ok(()));
+        assert_eq!(
+            table.authorize(&alice, Some("bob")),
+            Err(TableError::NotYours)
+        );
+        assert_eq!(table.authorize(&alice, None), Err(TableError::NotYours));
+        // A player who didn't sign in is known only by their id.
+        assert_eq!(table.authorize(&anyone, Some("bob")), Ok(()));
+        drop(table);
+        let leave = LeaveTableRequest {
+            table_id: id.clone(),
+            player_id: alice,
+        };
+        assert_eq!(tables.leave(&leave, Some("bob")), Err(TableError::NotYours));
+    }
+
+    fn moderate(said: &Said) -> Verdict {
+        if said.text.contains("spam") {
+            Verdict::Reject("no spam".to_string())
+        } else if said.name == "bob" {
+            Verdict::Replace(said.text.to_uppercase())
+        } else {
+            Verdict::Allow
+        }
+    }
+
+    #[test]
+    fn test_chat() {
+        let tables = Tables::default().with_chat_filter(Arc::new(moderate));
+        let settings = Settings {
+            start: 3,
+            ..settings()
+        };
+        let id = tables.open(Table::new(presets::holdem(), settings).unwrap());
+        let join = |name: &str| {
+            let request = JoinTableRequest {
+                table_id: id.clone(),
+                name: name.to_string(),
+                ..Default::default()
+            };
+            tables.join(&request, None).unwrap().player_id
+        };
+        let (ann, bob) = (join("ann"), join("bob"));
+        let say = |player: &str, text: &str, emote: Emote| {
+            let request = SendChatRequest {
+                table_id: id.clone(),
+                player_id: player.to_string(),
+                text: text.to_string(),
+                emote: emote.into(),
+            };
+            tables.chat(&request, None)
+        };
+        let mute = |name: &str, muted| {
+            let request = MuteRequest {
+                table_id: id.clone(),
+                player_id: ann.clone(),
+                name: name.to_string(),
+                muted,
+            };
+            tables.mute(&request, None)
+        };
+        let table = tables.get(&id).unwrap();
+        let (ann_sink, ann_rx) = sink();
+        let (bob_sink, bob_rx) = sink();
+        lock(&table).watch(Some(&ann), None, ann_sink).unwrap();
+        lock(&table).watch(Some(&bob), None, bob_sink).unwrap();
+
+        mute("bob", true).unwrap();
+        say(&bob, "hi all", Emote::Unspecified).unwrap();
+        say(&ann, "", Emote::GoodLuck).unwrap();
+        assert_eq!(
+            say(&ann, "buy my spam", Emote::Unspecified),
+            Err(TableError::Moderated("no spam".to_string()))
+        );
+        assert!(say(&ann, "  ", Emote::Unspecified).is_err());
+        assert_eq!(
+            say("nobody", "hi", Emote::Unspecified),
+            Err(TableError::UnknownPlayer)
+        );
+        mute("bob", false).unwrap();
+        say(&bob, "ty", Emote::Thanks).unwrap();
+        assert!(mute("", true).is_err());
+
+        let chat = |name: &str, text: &str, emote: Emote, muted| Chat {
+            name: name.to_string(),
+            seat: i32::from(name == "bob"),
+            text: text.to_string(),
+            emote: emote.into(),
+            muted,
+        };
+        let chats = |rx: &mpsc::Receiver<TableEvent>| {
+            let events: Vec<TableEvent> = rx.try_iter().collect();
+            // Nothing is left out, so the events still come in sequence.
+            for pair in events.windows(2) {
+                assert_eq!(pair[0].sequence + 1, pair[1].sequence);
+            }
+            events
+                .into_iter()
+                .filter_map(|e| match e.kind {
+                    Some(table_event::Kind::Chat(chat)) => Some(chat),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            chats(&ann_rx),
+            [
+                chat("bob", "", Emote::Unspecified, true),
+                chat("ann", "", Emote::GoodLuck, false),
+                chat("bob", "TY", Emote::Thanks, false),
+            ]
+        );
+        assert_eq!(
+            chats(&bob_rx),
+            [
+                chat("bob", "HI ALL", Emote::Unspecified, false),
+                chat("ann", "", Emote::GoodLuck, false),
+                chat("bob", "TY", Emote::Thanks, false),
+            ]
+        );
+
+        // A player still waiting for a seat can chat too, and a watcher
+        // who comes late sees what was said before.
+        let request = JoinTableRequest {
+            table_id: id.clone(),
+            name: "cy".to_string(),
+            wait: true,
+            ..Default::default()
+        };
+        lock(&table).players[2] = Some(Player::new("dee", None));
+        let cy = tables.join(&request, None).unwrap().player_id;
+        say(&cy, "next", Emote::Unspecified).unwrap();
+        let (late, late_rx) = sink();
+        lock(&table).watch(None, None, late).unwrap();
+        let late = chats(&late_rx);
+        assert_eq!(late.len(), 4);
+        assert_eq!((late[3].seat, late[3].text.as_str()), (-1, "next"));
+    }
+}
@@ -0,0 +1,454 @@
+//! TLS for both kinds of client, from the server's certificate and key in
+//! PEM files and, if clients must prove who they are, the certificates of
+//! the authorities their own certificates must be signed by.
+//!
+//! gRPC is served with tonic's TLS. WebSocket connections use rustls
+//! directly: a connection's reading and writing threads share its TLS
+//! session behind a lock, but each uses the socket itself, so a reader
+//! waiting to hear from the client never holds up what is sent to it.
+//! What the reader receives is kept with the session until the plaintext
+//! decrypted before it has been read, since rustls takes no more records
+//! while its plaintext buffer is full.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use rustls::pki_types::CertificateDer;
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig, ServerConnection};
+use tonic::transport::{Certificate, Identity, ServerTlsConfig};
+
+/// Where the server's TLS files are.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Files {
+    /// The server's certificate chain and its private key.
+    pub cert: String,
+    pub key: String,
+    /// The authorities that clients' certificates must be signed by, if
+    /// clients must have them.
+    pub client_ca: Option<String>,
+}
+
+/// TLS as the servers for each protocol set it up.
+#[derive(Clone)]
+pub struct Tls {
+    grpc: ServerTlsConfig,
+    websocket: Arc<ServerConfig>,
+}
+
+impl Tls {
+    /// Reads the certificates and key in `files`.
+    pub fn load(files: &Files) -> Result<Self, String> {
+        let read = |path: &str| fs::read(path).map_err(|e| format!("can't read {}: {}", path, e));
+        let cert = read(&files.cert)?;
+        let key = read(&files.key)?;
+        let client_ca = match &files.client_ca {
+            Some(path) => Some((path, read(path)?)),
+            None => None,
+        };
+
+        let chain = certs(&cert, &files.cert)?;
+        let private_key = rustls_pemfile::private_key(&mut key.as_slice())
+            .map_err(|e| format!("{}: {}", files.key, e))?
+            .ok_or_else(|| format!("{}: no private key", files.key))?;
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| e.to_string())?;
+        let builder = match &client_ca {
+            Some((path, pem)) => {
+                let mut roots = RootCertStore::empty();
+                for cert in certs(pem, path)? {
+                    roots.add(cert).map_err(|e| format!("{}: {}", path, e))?;
+                }
+                let verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                        .build()
+                        .map_err(|e| format!("{}: {}", path, e))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let websocket = builder
+            .with_single_cert(chain, private_key)
+            .map_err(|e| format!("{}: {}", files.cert, e))?;
+
+        let mut grpc = ServerTlsConfig::new().identity(Identity::from_pem(&cert, &key));
+        if let Some((_, pem)) = client_ca {
+            grpc = grpc.client_ca_root(Certificate::from_pem(pem));
+        }
+        Ok(Tls {
+            grpc,
+            websocket: Arc::new(websocket),
+        })
+    }
+
+    pub fn grpc(&self) -> ServerTlsConfig {
+        self.grpc.clone()
+    }
+
+    /// `socket`, for a WebSocket client, with its traffic encrypted.
+    pub fn wrap(&self, socket: TcpStream) -> io::Result<Stream> {
+        let session =
+            ServerConnection::new(Arc::clone(&self.websocket)).map_err(io::Error::other)?;
+        Ok(Stream::Tls {
+            socket,
+            session: Arc::new(Mutex::new(Session {
+                conn: session,
+                received: Vec::new(),
+            })),
+        })
+    }
+}
+
+/// The certificates in `pem`, read from `path`.
+fn certs(pem: &[u8], path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = rustls_pemfile::certs(&mut &pem[..])
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("{}: {}", path, e))?;
+    if certs.is_empty() {
+        return Err(format!("{}: no certificates", path));
+    }
+    Ok(certs)
+}
+
+/// A WebSocket client's socket, with or without TLS. A clone is another
+/// handle to the same connection, for another thread.
+pub enum Stream {
+    Plain(TcpStream),
+    Tls {
+        socket: TcpStream,
+        session: Arc<Mutex<Session>>,
+    },
+}
+
+/// A connection's TLS session, and what has come from the client that the
+/// session hasn't taken yet.
+pub struct Session {
+    conn: ServerConnection,
+    received: Vec<u8>,
+}
+
+impl Stream {
+    pub fn try_clone(&self) -> io::Result<Stream> {
+        Ok(match self {
+            Stream::Plain(socket) => Stream::Plain(socket.try_clone()?),
+            Stream::Tls { socket, session } => Stream::Tls {
+                socket: socket.try_clone()?,
+                session: Arc::clone(session),
+            },
+        })
+    }
+
+    fn socket(&self) -> &TcpStream {
+        match self {
+            Stream::Plain(socket) | Stream::Tls { socket, .. } => socket,
+        }
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.socket().peer_addr()
+    }
+
+    /// Shuts the socket down, without a TLS close_notify: by then the
+    /// WebSocket close handshake has ended the connection, or the client
+    /// has gone and would never read one.
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.socket().shutdown(how)
+    }
+}
+
+fn lock(session: &Mutex<Session>) -> MutexGuard<'_, Session> {
+    session.lock().expect("tls session lock")
+}
+
+/// Writes whatever the session has to send: records of what has been
+/// written to it, and its own handshake messages and alerts.
+fn send(session: &mut ServerConnection, socket: &mut TcpStream) -> io::Result<()> {
+    while session.wants_write() {
+        session.write_tls(socket)?;
+    }
+    Ok(())
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let (socket, shared) = match self {
+            Stream::Plain(socket) => return socket.read(buf),
+            Stream::Tls { socket, session } => (socket, session),
+        };
+        loop {
+            let mut session = lock(shared);
+            let Session { conn, received } = &mut *session;
+            match conn.reader().read(buf) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                read => return read,
+            }
+            // The plaintext is all read, so the session can take more.
+            if !received.is_empty() {
+                let taken = conn.read_tls(&mut received.as_slice())?;
+                received.drain(..taken);
+                let processed = conn.process_new_packets();
+                send(conn, socket)?;
+                processed.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                continue;
+            }
+            // Nothing is left to decrypt, so wait for more from the
+            // client, without the lock.
+            drop(session);
+            let mut raw = [0; 16 * 1024];
+            let n = socket.read(&mut raw)?;
+            if n == 0 {
+                return Ok(0);
+            }
+            lock(shared).received.extend_from_slice(&raw[..n]);
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(socket) => socket.write(buf),
+            Stream::Tls { socket, session } => {
+                let conn = &mut lock(session).conn;
+                let n = conn.writer().write(buf)?;
+                send(conn, socket)?;
+                Ok(n)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Plain(socket) => socket.flush(),
+            Stream::Tls { .. } => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    use rustls::pki_types::ServerName;
+    use rustls::{ClientConfig, ClientConnection, StreamOwned};
+
+    use crate::auth::Auth;
+    use crate::limits::Limiter;
+    use crate::table::Tables;
+    use crate::{connection, service, websocket};
+
+    // Made with openssl: a CA, a certificate it signed for localhost and
+    // 127.0.0.1, and one it signed for a client, alice.
+    const CA: &str = include_str!("../testdata/tls/ca.pem");
+    const SERVER_CERT: &str = include_str!("../testdata/tls/server.pem");
+    const SERVER_KEY: &str = include_str!("../testdata/tls/server.key");
+    const CLIENT_CERT: &str = include_str!("../testdata/tls/client.pem");
+    const CLIENT_KEY: &str = include_str!("../testdata/tls/client.key");
+
+    /// TLS for a server that requires clients to have a certificate from
+    /// the test CA, loaded from files in a directory named for `test`.
+    fn server_tls(test: &str) -> Tls {
+        let dir = std::env::temp_dir().join(format!("snapfold-{}-{}", test, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str, pem: &str| {
+            let path = dir.join(name);
+            fs::write(&path, pem).unwrap();
+            path.to_str().unwrap().to_string()
+        };
+        let files = Files {
+            cert: path("server.pem", SERVER_CERT),
+            key: path("server.key", SERVER_KEY),
+            client_ca: Some(path("ca.pem", CA)),
+        };
+        let tls = Tls::load(&files).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        tls
+    }
+
+    /// A TLS connection to `addr` that trusts the test CA, showing alice's
+    /// certificate if `identity`.
+    fn client(addr: SocketAddr, identity: bool) -> StreamOwned<ClientConnection, TcpStream> {
+        let mut roots = RootCertStore::empty();
+        for cert in certs(CA.as_bytes(), "ca.pem").unwrap() {
+            roots.add(cert).unwrap();
+        }
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots);
+        let config = if identity {
+            let chain = certs(CLIENT_CERT.as_bytes(), "client.pem").unwrap();
+            let key = rustls_pemfile::private_key(&mut CLIENT_KEY.as_bytes())
+                .unwrap()
+                .unwrap();
+            builder.with_client_auth_cert(chain, key).unwrap()
+        } else {
+            builder.with_no_client_auth()
+        };
+        let name = ServerName::try_from("localhost").unwrap();
+        let session = ClientConnection::new(Arc::new(config), name).unwrap();
+        StreamOwned::new(session, TcpStream::connect(addr).unwrap())
+    }
+
+    /// Asks for a WebSocket on `stream`, returning the status line of the
+    /// answer once its headers are read.
+    fn upgrade(stream: &mut (impl Read + Write)) -> io::Result<String> {
+        stream.write_all(
+            b"GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+              Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+              Sec-WebSocket-Version: 13\r\nSec-WebSocket-Protocol: snapfold.json\r\n\r\n",
+        )?;
+        // Nothing more comes until the client sends something, so the
+        // reader can't take any of it.
+        let mut reader = BufReader::new(stream);
+        let mut status = String::new();
+        reader.read_line(&mut status)?;
+        let mut line = status.clone();
+        while !line.is_empty() && line != "\r\n" {
+            line.clear();
+            reader.read_line(&mut line)?;
+        }
+        Ok(status)
+    }
+
+    /// The first byte of a server's frame and its payload.
+    fn read_frame(stream: &mut impl Read) -> (u8, Vec<u8>) {
+        let mut head = [0; 2];
+        stream.read_exact(&mut head).unwrap();
+        let len = match head[1] {
+            126 => {
+                let mut len = [0; 2];
+                stream.read_exact(&mut len).unwrap();
+                u16::from_be_bytes(len) as usize
+            }
+            len => len as usize,
+        };
+        let mut payload = vec![0; len];
+        stream.read_exact(&mut payload).unwrap();
+        (head[0], payload)
+    }
+
+    /// A server on a free port taking WebSocket connections over `tls`.
+    fn websocket_server(tls: Tls) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            connection::serve(
+                listener,
+                Arc::new(Tables::default()),
+                Auth::default(),
+                Limiter::default(),
+                Duration::from_secs(3600),
+                Some(tls),
+            )
+        });
+        addr
+    }
+
+    #[test]
+    fn test_websocket() {
+        let addr = websocket_server(server_tls("websocket"));
+        let mut stream = client(addr, true);
+        let status = upgrade(&mut stream).unwrap();
+        assert!(status.starts_with("HTTP/1.1 101 "), "{}", status);
+
+        stream
+            .write_all(&websocket::client_frames(0x9, b"ping", 125))
+            .unwrap();
+        assert_eq!(read_frame(&mut stream), (0x8a, b"ping".to_vec()));
+
+        // Several records' worth of request, more than the session holds
+        // as plaintext at once.
+        let request = format!(
+            r#"{{"id": 1, "listTables": {{"gameId": "{}"}}}}"#,
+            "x".repeat(100_000)
+        );
+        stream
+            .write_all(&websocket::client_frames(0x1, request.as_bytes(), 60_000))
+            .unwrap();
+        let (head, reply) = read_frame(&mut stream);
+        assert_eq!(head, 0x81);
+        let reply = String::from_utf8(reply).unwrap();
+        assert!(
+            reply.starts_with(r#"{"reply":{"id":"1","tables":"#),
+            "{}",
+            reply
+        );
+    }
+
+    #[test]
+    fn test_websocket_without_certificate() {
+        let addr = websocket_server(server_tls("anonymous"));
+        assert!(upgrade(&mut client(addr, false)).is_err());
+    }
+
+    #[test]
+    fn test_grpc() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let addr = SocketAddr::from(([127, 0, 0, 1], port));
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.spawn(service::serve(
+            addr,
+            Arc::new(Tables::default()),
+            Auth::default(),
+            Limiter::default(),
+            Duration::from_secs(3600),
+            Some(server_tls("grpc")),
+        ));
+        for _ in 0..500 {
+            if TcpStream::connect(addr).is_ok() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let tls = snapfold_client::Tls {
+            ca: CA.as_bytes().to_vec(),
+            identity: Some((
+                CLIENT_CERT.as_bytes().to_vec(),
+                CLIENT_KEY.as_bytes().to_vec(),
+            )),
+            domain: Some("localhost".to_string()),
+        };
+        let tables = runtime.block_on(async {
+            let url = format!("https://{}", addr);
+            let mut client = snapfold_client::Client::connect_tls(url, &tls).await?;
+            client.list_tables("").await
+        });
+        assert_eq!(tables.unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = std::env::temp_dir().join(format!("snapfold-tls-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        fs::write(path("empty.pem"), "nothing here\n").unwrap();
+        let files = |cert: &str, key: &str| Files {
+            cert: path(cert),
+            key: path(key),
+            client_ca: None,
+        };
+        let error = |files: &Files| Tls::load(files).err().unwrap();
+
+        assert!(error(&files("missing.pem", "empty.pem")).starts_with("can't read "));
+        assert_eq!(
+            error(&files("empty.pem", "empty.pem")),
+            format!("{}: no certificates", path("empty.pem"))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,464 @@
+//! The server side of the WebSocket protocol (RFC 6455): the opening
+//! handshake, and reading and writing messages.
+//!
+//! Only what a server needs is here. Client frames must be masked and
+//! server frames never are, messages may arrive in fragments but are
+//! always sent whole, and extensions such as compression are never
+//! agreed to.
+
+use std::io::{self, BufRead, Read, Write};
+
+use rustlib::fingerprint::base64;
+
+/// The largest message a client may send, once its fragments are joined.
+pub const MAX_MESSAGE: usize = 1 << 20;
+
+/// The most a handshake request may take up, headers and all.
+const MAX_REQUEST: usize = 16 << 10;
+
+/// Appended to the client's key to make the accept value.
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// How messages are framed, as the client and server agreed during the
+/// handshake.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    /// `snapfold.json`: protobuf JSON in text frames. The default.
+    Json,
+    /// `snapfold.proto`: the protobuf binary form in binary frames.
+    Proto,
+}
+
+impl Protocol {
+    fn name(self) -> &'static str {
+        match self {
+            Protocol::Json => "snapfold.json",
+            Protocol::Proto => "snapfold.proto",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [Protocol::Json, Protocol::Proto]
+            .into_iter()
+            .find(|p| p.name() == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    /// The status code and reason, if the closer gave one.
+    Close(Option<(u16, String)>),
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Reads a client's opening handshake from `reader` and answers it on
+/// `writer`. A request that isn't a WebSocket upgrade gets a 400 and an
+/// error.
+pub fn accept<R: BufRead, W: Write>(reader: &mut R, writer: &mut W) -> io::Result<Protocol> {
+    match read_handshake(reader) {
+        Ok((key, protocol)) => {
+            let mut response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\n\
+                 Upgrade: websocket\r\n\
+                 Connection: Upgrade\r\n\
+                 Sec-WebSocket-Accept: {}\r\n",
+                accept_key(&key)
+            );
+            if let Some(protocol) = protocol {
+                response.push_str(&format!("Sec-WebSocket-Protocol: {}\r\n", protocol.name()));
+            }
+            response.push_str("\r\n");
+            writer.write_all(response.as_bytes())?;
+            writer.flush()?;
+            Ok(protocol.unwrap_or(Protocol::Json))
+        }
+        Err(e) => {
+            let body = format!("{}\n", e);
+            // The request was bad already; failing to say so changes nothing.
+            let _ = write!(
+                writer,
+                "HTTP/1.1 400 Bad Request\r\n\
+                 Content-Type: text/plain\r\n\
+                 Content-Length: {}\r\n\
+                 Connection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = writer.flush();
+            Err(e)
+        }
+    }
+}
+
+/// The key from an upgrade request, and the first subprotocol offered
+/// that this server speaks, if any were offered.
+fn read_handshake<R: BufRead>(reader: &mut R) -> io::Result<(String, Option<Protocol>)> {
+    let mut read = 0;
+    let mut line = String::new();
+    let mut next_line = |line: &mut String| -> io::Result<()> {
+        line.clear();
+        let n = reader.take((MAX_REQUEST - read) as u64).read_line(line)?;
+        read += n;
+        if n == 0 || !line.ends_with('\n') {
+            return Err(invalid("the request ended early or is too long"));
+        }
+        Ok(())
+    };
+
+    next_line(&mut line)?;
+    let mut words = line.split_whitespace();
+    if words.next() != Some("GET") {
+        return Err(invalid("a WebSocket handshake is a GET request"));
+    }
+    let (mut upgrade, mut key, mut version) = (false, None, None);
+    let mut offered: Vec<String> = Vec::new();
+    loop {
+        next_line(&mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(invalid(format!("malformed header {:?}", header)));
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "upgrade" => upgrade = value.eq_ignore_ascii_case("websocket"),
+            "sec-websocket-key" => key = Some(value.to_string()),
+            "sec-websocket-version" => version = Some(value.to_string()),
+            "sec-websocket-protocol" => {
+                offered.extend(value.split(',').map(|p| p.trim().to_string()))
+            }
+            _ => {}
+        }
+    }
+    if !upgrade {
+        return Err(invalid("expected Upgrade: websocket"));
+    }
+    if version.as_deref() != Some("13") {
+        return Err(invalid("only WebSocket version 13 is supported"));
+    }
+    let key = key.ok_or_else(|| invalid("no Sec-WebSocket-Key"))?;
+    let protocol = offered.iter().find_map(|p| Protocol::from_name(p));
+    if !offered.is_empty() && protocol.is_none() {
+        return Err(invalid(format!(
+            "unsupported subprotocols {}; expected snapfold.json or snapfold.proto",
+            offered.join(", ")
+        )));
+    }
+    Ok((key, protocol))
+}
+
+/// The Sec-WebSocket-Accept value that answers `key`.
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key, GUID).as_bytes()))
+}
+
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xa;
+
+/// Reads a client's messages, joining fragments back together.
+pub struct Reader<R> {
+    inner: R,
+    /// The opcode and payload so far of a fragmented message.
+    partial: Option<(u8, Vec<u8>)>,
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(inner: R) -> Self {
+        Reader {
+            inner,
+            partial: None,
+        }
+    }
+
+    /// The next message. Control frames may come between the fragments of
+    /// another message, and are returned as they arrive.
+    pub fn read(&mut self) -> io::Result<Message> {
+        loop {
+            let (fin, opcode, payload) = self.frame()?;
+            let opcode = match opcode {
+                0 => match &mut self.partial {
+                    Some((_, data)) if data.len() + payload.len() <= MAX_MESSAGE => {
+                        data.extend_from_slice(&payload);
+                        if !fin {
+                            continue;
+                        }
+                        let (opcode, data) = self.partial.take().expect("partial message");
+                        return message(opcode, data);
+                    }
+                    Some(_) => return Err(invalid("message too long")),
+                    None => return Err(invalid("continuation without a message")),
+                },
+                TEXT | BINARY if self.partial.is_some() => {
+                    return Err(invalid("new message before the last one ended"))
+                }
+                TEXT | BINARY if !fin => {
+                    self.partial = Some((opcode, payload));
+                    continue;
+                }
+                opcode => opcode,
+            };
+            if opcode >= CLOSE && (!fin || payload.len() > 125) {
+                return Err(invalid(
+                    "control frames are at most 125 bytes, unfragmented",
+                ));
+            }
+            return message(opcode, payload);
+        }
+    }
+
+    /// The FIN bit, opcode and unmasked payload of the next frame.
+    fn frame(&mut self) -> io::Result<(bool, u8, Vec<u8>)> {
+        let mut head = [0; 2];
+        self.inner.read_exact(&mut head)?;
+        if head[0] & 0x70 != 0 {
+            return Err(invalid("reserved bits set without an extension"));
+        }
+        if head[1] & 0x80 == 0 {
+            return Err(invalid("client frames must be masked"));
+        }
+        let len = match head[1] & 0x7f {
+            126 => {
+                let mut len = [0; 2];
+                self.inner.read_exact(&mut len)?;
+                u16::from_be_bytes(len) as u64
+            }
+            127 => {
+                let mut len = [0; 8];
+                self.inner.read_exact(&mut len)?;
+                u64::from_be_bytes(len)
+            }
+            len => len as u64,
+        };
+        if len > MAX_MESSAGE as u64 {
+            return Err(invalid("message too long"));
+        }
+        let mut mask = [0; 4];
+        self.inner.read_exact(&mut mask)?;
+        let mut payload = vec![0; len as usize];
+        self.inner.read_exact(&mut payload)?;
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+        Ok((head[0] & 0x80 != 0, head[0] & 0x0f, payload))
+    }
+}
+
+fn message(opcode: u8, payload: Vec<u8>) -> io::Result<Message> {
+    match opcode {
+        TEXT => String::from_utf8(payload)
+            .map(Message::Text)
+            .map_err(|_| invalid("text message isn't UTF-8")),
+        BINARY => Ok(Message::Binary(payload)),
+        PING => Ok(Message::Ping(payload)),
+        PONG => Ok(Message::Pong(payload)),
+        CLOSE => match payload.len() {
+            0 => Ok(Message::Close(None)),
+            1 => Err(invalid("close frame with half a status code")),
+            _ => {
+                let code = u16::from_be_bytes([payload[0], payload[1]]);
+                let reason = String::from_utf8_lossy(&payload[2..]).into_owned();
+                Ok(Message::Close(Some((code, reason))))
+            }
+        },
+        opcode => Err(invalid(format!("unknown opcode {:#x}", opcode))),
+    }
+}
+
+/// Writes `message` to a client as a single unmasked frame.
+pub fn write<W: Write>(writer: &mut W, message: &Message) -> io::Result<()> {
+    let close;
+    let (opcode, payload): (u8, &[u8]) = match message {
+        Message::Text(text) => (TEXT, text.as_bytes()),
+        Message::Binary(data) => (BINARY, data),
+        Message::Ping(data) => (PING, data),
+        Message::Pong(data) => (PONG, data),
+        Message::Close(None) => (CLOSE, &[]),
+        Message::Close(Some((code, reason))) => {
+            close = [&code.to_be_bytes()[..], reason.as_bytes()].concat();
+            (CLOSE, &close)
+        }
+    };
+    let mut head = vec![0x80 | opcode];
+    match payload.len() {
+        len @ 0..=125 => head.push(len as u8),
+        len @ 126..=0xffff => {
+            head.push(126);
+            head.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            head.push(127);
+            head.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    writer.write_all(&head)?;
+    writer.write_all(payload)?;
+    writer.flush()
+}
+
+/// SHA-1, as specified in FIPS 180-4. It is broken for signatures, but the
+/// handshake only uses it to show that the server read the request.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+    let mut out = [0; 20];
+    for (i, word) in h.iter().enumerate() {
+        out[4 * i..4 * i + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+/// A message as a client sends it: masked, split into frames of at most
+/// `size` bytes.
+#[cfg(test)]
+pub fn client_frames(opcode: u8, payload: &[u8], size: usize) -> Vec<u8> {
+    let mask = [0x37, 0xfa, 0x21, 0x3d];
+    let chunks: Vec<&[u8]> = if payload.is_empty() {
+        vec![&[]]
+    } else {
+        payload.chunks(size).collect()
+    };
+    let mut out = Vec::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let fin = if i + 1 == chunks.len() { 0x80 } else { 0 };
+        out.push(fin | if i == 0 { opcode } else { 0 });
+        match chunk.len() {
+            len @ 0..=125 => out.push(0x80 | len as u8),
+            len => {
+                out.push(0x80 | 126);
+                out.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+        }
+        out.extend_from_slice(&mask);
+        out.extend(chunk.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handshake() {
+        // The example in RFC 6455, section 1.3.
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+
+        let request = "GET /ws HTTP/1.1\r\n\
+                       Host: localhost\r\n\
+                       Upgrade: websocket\r\n\
+                       Connection: Upgrade\r\n\
+                       Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                       Sec-WebSocket-Protocol: chat, snapfold.proto\r\n\
+                       Sec-WebSocket-Version: 13\r\n\r\n";
+        let mut response = Vec::new();
+        let protocol = accept(&mut request.as_bytes(), &mut response).unwrap();
+        assert_eq!(protocol, Protocol::Proto);
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 101 "));
+        assert!(response.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+        assert!(response.contains("Sec-WebSocket-Protocol: snapfold.proto\r\n"));
+
+        let request = request.replace("chat, snapfold.proto", "chat");
+        let mut response = Vec::new();
+        assert!(accept(&mut request.as_bytes(), &mut response).is_err());
+        assert!(response.starts_with(b"HTTP/1.1 400 "));
+
+        let request = "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        assert!(accept(&mut request.as_bytes(), &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_frames() {
+        let text = "x".repeat(300);
+        let mut input = client_frames(TEXT, text.as_bytes(), 100);
+        // A ping between the fragments of the next message.
+        let binary = client_frames(BINARY, &[1, 2, 3, 4], 2);
+        input.extend_from_slice(&binary[..8]);
+        input.extend(client_frames(PING, b"hi", 125));
+        input.extend_from_slice(&binary[8..]);
+        input.extend(client_frames(CLOSE, &[0x03, 0xe8, b'o', b'k'], 125));
+
+        let mut reader = Reader::new(input.as_slice());
+        assert_eq!(reader.read().unwrap(), Message::Text(text));
+        assert_eq!(reader.read().unwrap(), Message::Ping(b"hi".to_vec()));
+        assert_eq!(reader.read().unwrap(), Message::Binary(vec![1, 2, 3, 4]));
+        assert_eq!(
+            reader.read().unwrap(),
+            Message::Close(Some((1000, "ok".to_string())))
+        );
+        assert!(reader.read().is_err());
+
+        // Unmasked frames are refused.
+        let mut unmasked = client_frames(TEXT, b"hi", 125);
+        unmasked[1] &= 0x7f;
+        assert!(Reader::new(unmasked.as_slice()).read().is_err());
+
+        let mut written = Vec::new();
+        write(&mut written, &Message::Text("hello".to_string())).unwrap();
+        assert_eq!(written, b"\x81\x05hello");
+        let mut written = Vec::new();
+        write(&mut written, &Message::Binary(vec![7; 200])).unwrap();
+        assert_eq!(written[..4], [0x82, 126, 0, 200]);
+        assert_eq!(written.len(), 204);
+        let mut written = Vec::new();
+        write(
+            &mut written,
+            &Message::Close(Some((1013, "slow".to_string()))),
+        )
+        .unwrap();
+        assert_eq!(written, b"\x88\x06\x03\xf5slow");
+    }
+}
@@ -0,0 +1,120 @@
+//! Table updates as `service.proto` messages, with what a watcher may not
+//! see taken out.
+
+use std::time::Duration;
+
+use rustlib::engine::{Audience, Chips, LegalAction};
+use rustlib::events::hole_cards;
+use rustlib::player_action::Kind as ActionKind;
+use service_proto3::snapfold::server::{
+    self as proto, table_event, HandEnded, HandStarted, SeatTaken, TableEvent, TurnToAct,
+};
+
+use crate::table::Update;
+
+fn chips(amount: Chips) -> i64 {
+    amount as i64
+}
+
+fn seat(seat: usize) -> i32 {
+    seat as i32
+}
+
+pub fn millis(time: Duration) -> u32 {
+    u32::try_from(time.as_millis()).unwrap_or(u32::MAX)
+}
+
+/// `update` as `audience` sees it.
+pub fn table_event(sequence: u64, update: &Update, audience: Audience) -> TableEvent {
+    use table_event::Kind;
+    let kind = match update {
+        Update::SeatTaken {
+            seat: s,
+            name,
+            stack,
+        } => Kind::SeatTaken(SeatTaken {
+            seat: seat(*s),
+            name: name.clone(),
+            stack: chips(*stack),
+            yours: audience.sees(*s),
+        }),
+        Update::HandStarted {
+            hand,
+            button,
+            stacks,
+        } => Kind::HandStarted(HandStarted {
+            hand: *hand,
+            button: seat(*button),
+            stacks: stacks.iter().map(|&s| chips(s)).collect(),
+        }),
+        Update::Game(event) => Kind::Game(event.message_for(audience)),
+        Update::Turn {
+            seat: s,
+            legal,
+            time,
+        } => Kind::Turn(TurnToAct {
+            seat: seat(*s),
+            legal: legal.iter().map(legal_action).collect(),
+            time_ms: millis(*time),
+        }),
+        Update::HandEnded { stacks } => Kind::HandEnded(HandEnded {
+            stacks: stacks.iter().map(|&s| chips(s)).collect(),
+        }),
+        Update::Hole { seat: s, cards } => Kind::HoleCards(hole_cards(*s, cards, audience)),
+        Update::SeatLeft { seat: s } => Kind::SeatLeft(seat(*s)),
+        Update::Presence { seat: s, away } => Kind::Presence(proto::Presence {
+            seat: seat(*s),
+            away: *away,
+        }),
+        Update::Chat {
+            name,
+            seat: s,
+            text,
+            emote,
+        } => Kind::Chat(proto::Chat {
+            name: name.clone(),
+            seat: s.map_or(-1, seat),
+            text: text.clone(),
+            emote: (*emote).into(),
+            muted: false,
+        }),
+    };
+    TableEvent {
+        sequence,
+        kind: Some(kind),
+    }
+}
+
+/// Blanks `event`, if it is chat, for a watcher who has muted whoever
+/// said it.
+pub fn mute(event: &mut TableEvent) {
+    if let Some(table_event::Kind::Chat(chat)) = &mut event.kind {
+        chat.text.clear();
+        chat.emote = 0;
+        chat.muted = true;
+    }
+}
+
+pub fn legal_action(legal: &LegalAction) -> proto::LegalAction {
+    let (kind, min, max, count) = match *legal {
+        LegalAction::Fold => (ActionKind::Fold, 0, 0, 0),
+        LegalAction::Check => (ActionKind::Check, 0, 0, 0),
+        LegalAction::Call(amount) => (ActionKind::Call, amount, amount, 0),
+        LegalAction::Bet { min, max } => (ActionKind::Bet, min, max, 0),
+        LegalAction::Raise { min, max } => (ActionKind::Raise, min, max, 0),
+        LegalAction::AllIn(amount) => (ActionKind::AllIn, amount, amount, 0),
+        LegalAction::Discard { max } => (ActionKind::Discard, 0, 0, max),
+        LegalAction::Pass { count } => (ActionKind::Pass, 0, 0, count),
+        LegalAction::Reveal { count } => (ActionKind::Reveal, 0, 0, count),
+        LegalAction::Declare => (ActionKind::Declare, 0, 0, 0),
+        LegalAction::Buy { price, replace } => {
+            (ActionKind::Buy, price, price, usize::from(replace))
+        }
+    };
+    proto::LegalAction {
+        kind: kind.into(),
+        min: chips(min),
+        max: chips(max),
+        count: count as i32,
+    }
+}